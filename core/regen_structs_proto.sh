@@ -1,13 +1,15 @@
 #!/bin/sh
 
-# This script regenerates the `src/structs_proto.rs` and `src/keys_proto.rs` files from
-# `structs.proto` and `keys.proto`.
+# This script regenerates the `src/keys_proto.rs`, `src/envelope_proto.rs` and
+# `src/peer_record_proto.rs` files from `keys.proto`, `envelope.proto` and `peer_record.proto`.
 
 sudo docker run --rm -v `pwd`:/usr/code:z -w /usr/code rust /bin/bash -c " \
     apt-get update; \
     apt-get install -y protobuf-compiler; \
     cargo install --version 2.3.0 protobuf-codegen; \
-    protoc --rust_out . keys.proto"
+    protoc --rust_out . keys.proto envelope.proto peer_record.proto"
 
-sudo chown $USER:$USER keys.rs
+sudo chown $USER:$USER keys.rs envelope.rs peer_record.rs
 mv -f keys.rs ./src/keys_proto.rs
+mv -f envelope.rs ./src/envelope_proto.rs
+mv -f peer_record.rs ./src/peer_record_proto.rs
@@ -0,0 +1,42 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Source of the current time.
+//!
+//! Code that computes deadlines or measures durations should call [`now`] rather than
+//! `Instant::now()`, so that the time can be controlled by the execution context. For example,
+//! a simulation can run the nodes on a virtual clock.
+
+use wasm_timer::Instant;
+
+/// Returns the current instant, according to the clock of the execution context.
+///
+/// The clock can be overridden with `tokio_timer::clock::with_default`. Otherwise, this is the
+/// same as `Instant::now()`.
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+pub fn now() -> Instant {
+    wasm_timer::clock::now()
+}
+
+/// Returns the current instant.
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+pub fn now() -> Instant {
+    Instant::now()
+}
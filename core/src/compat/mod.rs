@@ -0,0 +1,327 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Compatibility layer between futures 0.1 and futures 0.3.
+//!
+//! The main traits of libp2p ([`Transport`](crate::Transport),
+//! [`StreamMuxer`](crate::StreamMuxer), [`InboundUpgrade`](crate::InboundUpgrade) and
+//! [`OutboundUpgrade`](crate::OutboundUpgrade)) are built on `std::future::Future`. During the
+//! transition, implementations written against futures 0.1 keep working:
+//!
+//! - This module provides the futures 0.1 flavour of these traits: [`Transport01`],
+//!   [`StreamMuxer01`], [`InboundUpgrade01`] and [`OutboundUpgrade01`]. Every type that
+//!   implements [`Transport01`] or [`StreamMuxer01`] automatically implements its futures 0.3
+//!   counterpart. Since the upgrade traits are generic over the connection, upgrades go through
+//!   [`Compat01As03`] instead.
+//! - [`Compat01As03`] wraps a futures 0.1 future, stream, `AsyncRead`/`AsyncWrite` object or
+//!   upgrade and implements the futures 0.3 flavour of its traits.
+//! - [`Compat03As01`] wraps a futures 0.3 future, stream, `AsyncRead`/`AsyncWrite` object,
+//!   transport, stream muxer or upgrade and implements the futures 0.1 flavour of its traits.
+//!   Futures, streams and `AsyncRead`/`AsyncWrite` objects have to be `Unpin`, which can be
+//!   achieved by wrapping them with `Box::pin`.
+//!
+//! Only the poll methods have been migrated. The connections and substreams that flow through
+//! these traits are still `AsyncRead`/`AsyncWrite` objects of tokio-io, which is notably required
+//! by [`Transport::with_upgrade`](crate::Transport::with_upgrade), and protocol negotiation is
+//! still performed by `multistream-select` on top of futures 0.1. The negotiated connection or
+//! substream, a `Negotiated<C>`, additionally implements the `AsyncRead`/`AsyncWrite` traits of
+//! futures 0.3 if `C` does, and otherwise can be wrapped in a [`Compat01As03`].
+//!
+//! > **Note**: Futures 0.1 objects can only be polled from within a futures 0.1 task, and
+//! >           futures 0.3 objects from within a `Context`. The wrappers take care of entering
+//! >           the appropriate one, and so do [`with_task`] and [`with_context`] for code that
+//! >           needs to call a poll method directly.
+
+mod muxing;
+mod transport;
+mod upgrade;
+
+pub use self::muxing::StreamMuxer01;
+pub use self::transport::{Compat01As03Listener, Compat03As01Listener, Transport01};
+pub use self::upgrade::{InboundUpgrade01, OutboundUpgrade01};
+
+use futures::{Async, Future as Future01, Stream as Stream01, executor::{self, Notify}, task::{self as task01, Task}};
+use futures03::{
+    future::{Future as Future03, TryFuture},
+    io::{AsyncRead as AsyncRead03, AsyncWrite as AsyncWrite03},
+    stream::{Stream as Stream03, TryStream},
+    task::{self as task03, ArcWake, Context, Poll, Waker},
+};
+use std::{io, pin::Pin, sync::Arc};
+use tokio_io::{AsyncRead as AsyncRead01, AsyncWrite as AsyncWrite01};
+
+/// Wraps an object of the futures 0.1 flavour and implements the futures 0.3 flavour of its
+/// traits.
+#[derive(Debug, Clone)]
+#[must_use = "futures do nothing unless polled"]
+pub struct Compat01As03<T> {
+    inner: T,
+}
+
+// Futures 0.1 objects never rely on not being moved.
+impl<T> Unpin for Compat01As03<T> {}
+
+impl<T> Compat01As03<T> {
+    /// Wraps an object of the futures 0.1 flavour.
+    pub fn new(inner: T) -> Self {
+        Compat01As03 { inner }
+    }
+
+    /// Returns a reference to the wrapped object.
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the wrapped object.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Destroys the wrapper and returns the wrapped object.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+/// Wraps an object of the futures 0.3 flavour and implements the futures 0.1 flavour of its
+/// traits.
+#[derive(Debug, Clone)]
+#[must_use = "futures do nothing unless polled"]
+pub struct Compat03As01<T> {
+    inner: T,
+}
+
+impl<T> Compat03As01<T> {
+    /// Wraps an object of the futures 0.3 flavour.
+    pub fn new(inner: T) -> Self {
+        Compat03As01 { inner }
+    }
+
+    /// Returns a reference to the wrapped object.
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the wrapped object.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Destroys the wrapper and returns the wrapped object.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+/// Calls `f` from within a futures 0.1 task that wakes up the given `Context` when notified.
+///
+/// This makes it possible to call the poll methods of futures 0.1 objects from a futures 0.3
+/// poll method.
+pub fn with_task<R>(cx: &mut Context<'_>, f: impl FnOnce() -> R) -> R {
+    let notify = Arc::new(WakerNotify(cx.waker().clone()));
+    executor::spawn(()).poll_fn_notify(&notify, 0, |_| f())
+}
+
+/// Calls `f` with a `Context` that notifies the current futures 0.1 task when woken up.
+///
+/// This makes it possible to call the poll methods of futures 0.3 objects from a futures 0.1
+/// poll method.
+///
+/// # Panics
+///
+/// Panics if called outside of a futures 0.1 task.
+pub fn with_context<R>(f: impl FnOnce(&mut Context<'_>) -> R) -> R {
+    let waker = task03::waker(Arc::new(TaskWaker(task01::current())));
+    f(&mut Context::from_waker(&waker))
+}
+
+/// Notifies a futures 0.3 `Waker` on behalf of a futures 0.1 task.
+struct WakerNotify(Waker);
+
+impl Notify for WakerNotify {
+    fn notify(&self, _: usize) {
+        self.0.wake_by_ref()
+    }
+}
+
+/// Wakes up a futures 0.1 task on behalf of a futures 0.3 `Waker`.
+struct TaskWaker(Task);
+
+impl ArcWake for TaskWaker {
+    fn wake_by_ref(arc_self: &Arc<Self>) {
+        arc_self.0.notify()
+    }
+}
+
+/// Turns the result of a futures 0.1 poll method into its futures 0.3 equivalent.
+fn poll_01_to_03<T, E>(poll: Result<Async<T>, E>) -> Poll<Result<T, E>> {
+    match poll {
+        Ok(Async::Ready(value)) => Poll::Ready(Ok(value)),
+        Ok(Async::NotReady) => Poll::Pending,
+        Err(err) => Poll::Ready(Err(err)),
+    }
+}
+
+/// Turns the result of a futures 0.3 poll method into its futures 0.1 equivalent.
+fn poll_03_to_01<T, E>(poll: Poll<Result<T, E>>) -> Result<Async<T>, E> {
+    match poll {
+        Poll::Ready(Ok(value)) => Ok(Async::Ready(value)),
+        Poll::Ready(Err(err)) => Err(err),
+        Poll::Pending => Ok(Async::NotReady),
+    }
+}
+
+impl<F> Future03 for Compat01As03<F>
+where
+    F: Future01,
+{
+    type Output = Result<F::Item, F::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let inner = &mut self.get_mut().inner;
+        poll_01_to_03(with_task(cx, || inner.poll()))
+    }
+}
+
+impl<S> Stream03 for Compat01As03<S>
+where
+    S: Stream01,
+{
+    type Item = Result<S::Item, S::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let inner = &mut self.get_mut().inner;
+        match with_task(cx, || inner.poll()) {
+            Ok(Async::Ready(Some(item))) => Poll::Ready(Some(Ok(item))),
+            Ok(Async::Ready(None)) => Poll::Ready(None),
+            Ok(Async::NotReady) => Poll::Pending,
+            Err(err) => Poll::Ready(Some(Err(err))),
+        }
+    }
+}
+
+impl<T> AsyncRead03 for Compat01As03<T>
+where
+    T: AsyncRead01,
+{
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8])
+        -> Poll<Result<usize, io::Error>>
+    {
+        let inner = &mut self.get_mut().inner;
+        poll_01_to_03(with_task(cx, || inner.poll_read(buf)))
+    }
+}
+
+impl<T> AsyncWrite03 for Compat01As03<T>
+where
+    T: AsyncWrite01,
+{
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8])
+        -> Poll<Result<usize, io::Error>>
+    {
+        let inner = &mut self.get_mut().inner;
+        poll_01_to_03(with_task(cx, || inner.poll_write(buf)))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        let inner = &mut self.get_mut().inner;
+        poll_01_to_03(with_task(cx, || inner.poll_flush()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        let inner = &mut self.get_mut().inner;
+        poll_01_to_03(with_task(cx, || inner.shutdown()))
+    }
+}
+
+impl<F> Future01 for Compat03As01<F>
+where
+    F: TryFuture + Unpin,
+{
+    type Item = F::Ok;
+    type Error = F::Error;
+
+    fn poll(&mut self) -> Result<Async<Self::Item>, Self::Error> {
+        poll_03_to_01(with_context(|cx| Pin::new(&mut self.inner).try_poll(cx)))
+    }
+}
+
+impl<S> Stream01 for Compat03As01<S>
+where
+    S: TryStream + Unpin,
+{
+    type Item = S::Ok;
+    type Error = S::Error;
+
+    fn poll(&mut self) -> Result<Async<Option<Self::Item>>, Self::Error> {
+        match with_context(|cx| Pin::new(&mut self.inner).try_poll_next(cx)) {
+            Poll::Ready(Some(Ok(item))) => Ok(Async::Ready(Some(item))),
+            Poll::Ready(Some(Err(err))) => Err(err),
+            Poll::Ready(None) => Ok(Async::Ready(None)),
+            Poll::Pending => Ok(Async::NotReady),
+        }
+    }
+}
+
+impl<T> io::Read for Compat03As01<T>
+where
+    T: AsyncRead03 + Unpin,
+{
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, io::Error> {
+        match with_context(|cx| Pin::new(&mut self.inner).poll_read(cx, buf)) {
+            Poll::Ready(result) => result,
+            Poll::Pending => Err(io::ErrorKind::WouldBlock.into()),
+        }
+    }
+}
+
+impl<T> AsyncRead01 for Compat03As01<T>
+where
+    T: AsyncRead03 + Unpin,
+{
+}
+
+impl<T> io::Write for Compat03As01<T>
+where
+    T: AsyncWrite03 + Unpin,
+{
+    fn write(&mut self, buf: &[u8]) -> Result<usize, io::Error> {
+        match with_context(|cx| Pin::new(&mut self.inner).poll_write(cx, buf)) {
+            Poll::Ready(result) => result,
+            Poll::Pending => Err(io::ErrorKind::WouldBlock.into()),
+        }
+    }
+
+    fn flush(&mut self) -> Result<(), io::Error> {
+        match with_context(|cx| Pin::new(&mut self.inner).poll_flush(cx)) {
+            Poll::Ready(result) => result,
+            Poll::Pending => Err(io::ErrorKind::WouldBlock.into()),
+        }
+    }
+}
+
+impl<T> AsyncWrite01 for Compat03As01<T>
+where
+    T: AsyncWrite03 + Unpin,
+{
+    fn shutdown(&mut self) -> Result<Async<()>, io::Error> {
+        poll_03_to_01(with_context(|cx| Pin::new(&mut self.inner).poll_close(cx)))
+    }
+}
@@ -0,0 +1,255 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use crate::muxing::StreamMuxer;
+use futures::Poll as Poll01;
+use futures03::task::{Context, Poll};
+use std::io;
+use super::{Compat03As01, poll_01_to_03, poll_03_to_01, with_context, with_task};
+
+/// Stream muxer built on futures 0.1.
+///
+/// This is the interface of [`StreamMuxer`] before its migration to `std::future::Future`. The
+/// methods have the same semantics, except that the task to notify is the current futures 0.1
+/// task. Every `StreamMuxer01` is also a [`StreamMuxer`].
+pub trait StreamMuxer01 {
+    /// Type of the object that represents the raw substream where data can be read and written.
+    type Substream;
+
+    /// Future that will be resolved when the outgoing substream is open.
+    type OutboundSubstream;
+
+    /// Error type of the muxer
+    type Error: Into<io::Error>;
+
+    /// Polls for an inbound substream.
+    fn poll_inbound(&self) -> Poll01<Self::Substream, Self::Error>;
+
+    /// Opens a new outgoing substream.
+    fn open_outbound(&self) -> Self::OutboundSubstream;
+
+    /// Polls the outbound substream.
+    fn poll_outbound(&self, s: &mut Self::OutboundSubstream) -> Poll01<Self::Substream, Self::Error>;
+
+    /// Destroys an outbound substream future.
+    fn destroy_outbound(&self, s: Self::OutboundSubstream);
+
+    /// Reads data from a substream. The behaviour is the same as `tokio_io::AsyncRead::poll_read`.
+    fn read_substream(&self, s: &mut Self::Substream, buf: &mut [u8]) -> Poll01<usize, Self::Error>;
+
+    /// Mimics the `prepare_uninitialized_buffer` method of the `AsyncRead` trait.
+    ///
+    /// This function isn't actually unsafe to call but unsafe to implement. The implementer must
+    /// ensure that either the whole buf has been zeroed or that `read_substream` overwrites the
+    /// buffer without reading it and returns correct value.
+    unsafe fn prepare_uninitialized_buffer(&self, buf: &mut [u8]) -> bool {
+        for b in buf.iter_mut() { *b = 0; }
+        true
+    }
+
+    /// Write data to a substream. The behaviour is the same as `tokio_io::AsyncWrite::poll_write`.
+    fn write_substream(&self, s: &mut Self::Substream, buf: &[u8]) -> Poll01<usize, Self::Error>;
+
+    /// Flushes a substream. The behaviour is the same as `tokio_io::AsyncWrite::poll_flush`.
+    fn flush_substream(&self, s: &mut Self::Substream) -> Poll01<(), Self::Error>;
+
+    /// Attempts to shut down the writing side of a substream, without flushing it.
+    fn shutdown_substream(&self, s: &mut Self::Substream) -> Poll01<(), Self::Error>;
+
+    /// Destroys a substream.
+    fn destroy_substream(&self, s: Self::Substream);
+
+    /// Returns `true` if the remote has shown any sign of activity after the muxer has been open.
+    fn is_remote_acknowledged(&self) -> bool;
+
+    /// Closes this muxer.
+    fn close(&self) -> Poll01<(), Self::Error>;
+
+    /// Flush this muxer.
+    fn flush_all(&self) -> Poll01<(), Self::Error>;
+}
+
+impl<M> StreamMuxer for M
+where
+    M: StreamMuxer01,
+{
+    type Substream = M::Substream;
+    type OutboundSubstream = M::OutboundSubstream;
+    type Error = M::Error;
+
+    fn poll_inbound(&self, cx: &mut Context<'_>) -> Poll<Result<Self::Substream, Self::Error>> {
+        poll_01_to_03(with_task(cx, || StreamMuxer01::poll_inbound(self)))
+    }
+
+    fn open_outbound(&self) -> Self::OutboundSubstream {
+        StreamMuxer01::open_outbound(self)
+    }
+
+    fn poll_outbound(&self, cx: &mut Context<'_>, s: &mut Self::OutboundSubstream)
+        -> Poll<Result<Self::Substream, Self::Error>>
+    {
+        poll_01_to_03(with_task(cx, || StreamMuxer01::poll_outbound(self, s)))
+    }
+
+    fn destroy_outbound(&self, s: Self::OutboundSubstream) {
+        StreamMuxer01::destroy_outbound(self, s)
+    }
+
+    fn read_substream(&self, cx: &mut Context<'_>, s: &mut Self::Substream, buf: &mut [u8])
+        -> Poll<Result<usize, Self::Error>>
+    {
+        poll_01_to_03(with_task(cx, || StreamMuxer01::read_substream(self, s, buf)))
+    }
+
+    fn write_substream(&self, cx: &mut Context<'_>, s: &mut Self::Substream, buf: &[u8])
+        -> Poll<Result<usize, Self::Error>>
+    {
+        poll_01_to_03(with_task(cx, || StreamMuxer01::write_substream(self, s, buf)))
+    }
+
+    fn flush_substream(&self, cx: &mut Context<'_>, s: &mut Self::Substream)
+        -> Poll<Result<(), Self::Error>>
+    {
+        poll_01_to_03(with_task(cx, || StreamMuxer01::flush_substream(self, s)))
+    }
+
+    fn shutdown_substream(&self, cx: &mut Context<'_>, s: &mut Self::Substream)
+        -> Poll<Result<(), Self::Error>>
+    {
+        poll_01_to_03(with_task(cx, || StreamMuxer01::shutdown_substream(self, s)))
+    }
+
+    fn destroy_substream(&self, s: Self::Substream) {
+        StreamMuxer01::destroy_substream(self, s)
+    }
+
+    fn is_remote_acknowledged(&self) -> bool {
+        StreamMuxer01::is_remote_acknowledged(self)
+    }
+
+    fn close(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        poll_01_to_03(with_task(cx, || StreamMuxer01::close(self)))
+    }
+
+    fn flush_all(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        poll_01_to_03(with_task(cx, || StreamMuxer01::flush_all(self)))
+    }
+}
+
+impl<M> StreamMuxer01 for Compat03As01<M>
+where
+    M: StreamMuxer,
+{
+    type Substream = M::Substream;
+    type OutboundSubstream = M::OutboundSubstream;
+    type Error = M::Error;
+
+    fn poll_inbound(&self) -> Poll01<Self::Substream, Self::Error> {
+        poll_03_to_01(with_context(|cx| self.inner.poll_inbound(cx)))
+    }
+
+    fn open_outbound(&self) -> Self::OutboundSubstream {
+        self.inner.open_outbound()
+    }
+
+    fn poll_outbound(&self, s: &mut Self::OutboundSubstream) -> Poll01<Self::Substream, Self::Error> {
+        poll_03_to_01(with_context(|cx| self.inner.poll_outbound(cx, s)))
+    }
+
+    fn destroy_outbound(&self, s: Self::OutboundSubstream) {
+        self.inner.destroy_outbound(s)
+    }
+
+    fn read_substream(&self, s: &mut Self::Substream, buf: &mut [u8]) -> Poll01<usize, Self::Error> {
+        poll_03_to_01(with_context(|cx| self.inner.read_substream(cx, s, buf)))
+    }
+
+    fn write_substream(&self, s: &mut Self::Substream, buf: &[u8]) -> Poll01<usize, Self::Error> {
+        poll_03_to_01(with_context(|cx| self.inner.write_substream(cx, s, buf)))
+    }
+
+    fn flush_substream(&self, s: &mut Self::Substream) -> Poll01<(), Self::Error> {
+        poll_03_to_01(with_context(|cx| self.inner.flush_substream(cx, s)))
+    }
+
+    fn shutdown_substream(&self, s: &mut Self::Substream) -> Poll01<(), Self::Error> {
+        poll_03_to_01(with_context(|cx| self.inner.shutdown_substream(cx, s)))
+    }
+
+    fn destroy_substream(&self, s: Self::Substream) {
+        self.inner.destroy_substream(s)
+    }
+
+    fn is_remote_acknowledged(&self) -> bool {
+        self.inner.is_remote_acknowledged()
+    }
+
+    fn close(&self) -> Poll01<(), Self::Error> {
+        poll_03_to_01(with_context(|cx| self.inner.close(cx)))
+    }
+
+    fn flush_all(&self) -> Poll01<(), Self::Error> {
+        poll_03_to_01(with_context(|cx| self.inner.flush_all(cx)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compat::Compat03As01;
+    use crate::muxing::{SingletonMuxer, inbound_from_ref_and_wrap, outbound_from_ref_and_wrap};
+    use crate::transport::{ListenerEvent, MemoryTransport, Transport};
+    use futures03::{future, io::{AsyncReadExt, AsyncWriteExt}, stream::TryStreamExt};
+    use std::sync::Arc;
+
+    #[test]
+    fn muxer_round_trip() {
+        // The futures 0.1 muxer goes through both conversions before being used from futures 0.3.
+        let transport = MemoryTransport.map(|socket, endpoint| {
+            let muxer = SingletonMuxer::new(socket, endpoint.to_endpoint());
+            Arc::new(Compat03As01::new(muxer))
+        });
+
+        let mut listener = transport.listen_on("/memory/0".parse().unwrap()).unwrap();
+
+        let test = async move {
+            let addr = match listener.try_next().await.unwrap() {
+                Some(ListenerEvent::NewAddress(addr)) => addr,
+                _ => panic!("expected a new address"),
+            };
+
+            let listen = async move {
+                let (upgrade, _) = listener.try_next().await.unwrap().unwrap().into_upgrade().unwrap();
+                let mut substream = inbound_from_ref_and_wrap(upgrade.await.unwrap()).await.unwrap();
+                let mut buf = [0; 5];
+                substream.read_exact(&mut buf).await.unwrap();
+                assert_eq!(&buf, b"hello");
+            };
+            let dial = async move {
+                let muxer = transport.dial(addr).unwrap().await.unwrap();
+                let mut substream = outbound_from_ref_and_wrap(muxer).await.unwrap();
+                substream.write_all(b"hello").await.unwrap();
+                substream.flush().await.unwrap();
+            };
+            future::join(listen, dial).await;
+        };
+
+        futures03::executor::block_on(test);
+    }
+}
@@ -0,0 +1,240 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use crate::transport::{ListenerEvent, Transport, TransportError};
+use futures::{Async, Future as Future01, Stream as Stream01};
+use futures03::{stream::Stream as Stream03, task::{Context, Poll}};
+use multiaddr::Multiaddr;
+use std::{error, pin::Pin};
+use super::{Compat01As03, Compat03As01, with_context, with_task};
+
+/// Transport built on futures 0.1.
+///
+/// This is the interface of [`Transport`] before its migration to `std::future::Future`. Every
+/// `Transport01` is also a [`Transport`], which makes it possible to use it with the rest of
+/// libp2p, and to compose it with the transport combinators. See the documentation of
+/// [`Transport`] for the meaning of each item.
+pub trait Transport01 {
+    /// The result of a connection setup process, including protocol upgrades.
+    type Output;
+
+    /// An error that occurred during connection setup.
+    type Error: error::Error;
+
+    /// A stream of [`Output`](Transport01::Output)s for inbound connections.
+    type Listener: Stream01<Item = ListenerEvent<Self::ListenerUpgrade>, Error = Self::Error>;
+
+    /// A pending [`Output`](Transport01::Output) for an inbound connection.
+    type ListenerUpgrade: Future01<Item = Self::Output, Error = Self::Error>;
+
+    /// A pending [`Output`](Transport01::Output) for an outbound connection.
+    type Dial: Future01<Item = Self::Output, Error = Self::Error>;
+
+    /// Listens on the given [`Multiaddr`], producing a stream of pending, inbound connections.
+    fn listen_on(self, addr: Multiaddr) -> Result<Self::Listener, TransportError<Self::Error>>
+    where
+        Self: Sized;
+
+    /// Dials the given [`Multiaddr`], returning a future for a pending outbound connection.
+    fn dial(self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>>
+    where
+        Self: Sized;
+
+    /// Dials the given [`Multiaddr`] in the role of the listener.
+    ///
+    /// The default implementation is the same as [`dial`](Transport01::dial).
+    fn dial_as_listener(self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>>
+    where
+        Self: Sized
+    {
+        self.dial(addr)
+    }
+}
+
+impl<T> Transport for T
+where
+    T: Transport01,
+{
+    type Output = T::Output;
+    type Error = T::Error;
+    type Listener = Compat01As03Listener<T::Listener>;
+    type ListenerUpgrade = Compat01As03<T::ListenerUpgrade>;
+    type Dial = Compat01As03<T::Dial>;
+
+    fn listen_on(self, addr: Multiaddr) -> Result<Self::Listener, TransportError<Self::Error>> {
+        let listener = Transport01::listen_on(self, addr)?;
+        Ok(Compat01As03Listener { inner: listener })
+    }
+
+    fn dial(self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
+        let dial = Transport01::dial(self, addr)?;
+        Ok(Compat01As03::new(dial))
+    }
+
+    fn dial_as_listener(self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
+        let dial = Transport01::dial_as_listener(self, addr)?;
+        Ok(Compat01As03::new(dial))
+    }
+}
+
+impl<T> Transport01 for Compat03As01<T>
+where
+    T: Transport,
+{
+    type Output = T::Output;
+    type Error = T::Error;
+    type Listener = Compat03As01Listener<T::Listener>;
+    type ListenerUpgrade = Compat03As01<Pin<Box<T::ListenerUpgrade>>>;
+    type Dial = Compat03As01<Pin<Box<T::Dial>>>;
+
+    fn listen_on(self, addr: Multiaddr) -> Result<Self::Listener, TransportError<Self::Error>> {
+        let listener = self.inner.listen_on(addr)?;
+        Ok(Compat03As01Listener { inner: Box::pin(listener) })
+    }
+
+    fn dial(self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
+        let dial = self.inner.dial(addr)?;
+        Ok(Compat03As01::new(Box::pin(dial)))
+    }
+
+    fn dial_as_listener(self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
+        let dial = self.inner.dial_as_listener(addr)?;
+        Ok(Compat03As01::new(Box::pin(dial)))
+    }
+}
+
+/// Listener of a [`Transport01`], as seen through [`Transport`].
+#[derive(Debug)]
+#[must_use = "streams do nothing unless polled"]
+pub struct Compat01As03Listener<TListener> {
+    inner: TListener,
+}
+
+impl<TListener> Unpin for Compat01As03Listener<TListener> {}
+
+impl<TListener> Compat01As03Listener<TListener> {
+    /// Wraps a listener of a [`Transport01`].
+    pub fn new(inner: TListener) -> Self {
+        Compat01As03Listener { inner }
+    }
+}
+
+impl<TListener, TUpgrade, TError> Stream03 for Compat01As03Listener<TListener>
+where
+    TListener: Stream01<Item = ListenerEvent<TUpgrade>, Error = TError>,
+{
+    type Item = Result<ListenerEvent<Compat01As03<TUpgrade>>, TError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let inner = &mut self.get_mut().inner;
+        match with_task(cx, || inner.poll()) {
+            Ok(Async::Ready(Some(event))) => Poll::Ready(Some(Ok(event.map(Compat01As03::new)))),
+            Ok(Async::Ready(None)) => Poll::Ready(None),
+            Ok(Async::NotReady) => Poll::Pending,
+            Err(err) => Poll::Ready(Some(Err(err))),
+        }
+    }
+}
+
+/// Listener of a [`Transport`] wrapped in a [`Compat03As01`].
+#[derive(Debug)]
+#[must_use = "streams do nothing unless polled"]
+pub struct Compat03As01Listener<TListener> {
+    inner: Pin<Box<TListener>>,
+}
+
+impl<TListener, TUpgrade, TError> Stream01 for Compat03As01Listener<TListener>
+where
+    TListener: Stream03<Item = Result<ListenerEvent<TUpgrade>, TError>>,
+{
+    type Item = ListenerEvent<Compat03As01<Pin<Box<TUpgrade>>>>;
+    type Error = TError;
+
+    fn poll(&mut self) -> Result<Async<Option<Self::Item>>, Self::Error> {
+        match with_context(|cx| self.inner.as_mut().poll_next(cx)) {
+            Poll::Ready(Some(Ok(event))) => {
+                Ok(Async::Ready(Some(event.map(|upgrade| Compat03As01::new(Box::pin(upgrade))))))
+            }
+            Poll::Ready(Some(Err(err))) => Err(err),
+            Poll::Ready(None) => Ok(Async::Ready(None)),
+            Poll::Pending => Ok(Async::NotReady),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compat::{Compat01As03, Compat03As01, Transport01};
+    use crate::transport::{ListenerEvent, MemoryTransport, Transport};
+    use futures::prelude::*;
+    use futures03::{io::{AsyncReadExt, AsyncWriteExt}, stream::TryStreamExt};
+
+    #[test]
+    fn memory_transport_round_trip() {
+        // The memory transport is used through the futures 0.1 interface.
+        let transport = Compat03As01::new(MemoryTransport);
+        let mut listener = Transport01::listen_on(transport.clone(), "/memory/0".parse().unwrap()).unwrap();
+        let addr = match listener.by_ref().wait().next() {
+            Some(Ok(ListenerEvent::NewAddress(addr))) => addr,
+            _ => panic!("expected a new address"),
+        };
+
+        let listen = listener
+            .filter_map(ListenerEvent::into_upgrade)
+            .into_future()
+            .map_err(|(err, _)| err)
+            .and_then(|(upgrade, _)| upgrade.unwrap().0)
+            .then(|socket| tokio_io::io::read_exact(socket.unwrap(), [0; 5]))
+            .map(|(_, buf)| assert_eq!(&buf, b"hello"));
+
+        let dial = Transport01::dial(transport, addr).unwrap()
+            .then(|socket| tokio_io::io::write_all(socket.unwrap(), b"hello"))
+            .map(|_| ());
+
+        listen.join(dial).wait().unwrap();
+    }
+
+    #[test]
+    fn async_await_on_memory_transport() {
+        // The futures 0.1 transport goes back to the `Transport` interface, so that it goes
+        // through both conversions.
+        let transport = Compat03As01::new(MemoryTransport);
+        let mut listener = Transport::listen_on(transport.clone(), "/memory/0".parse().unwrap()).unwrap();
+
+        let test = async move {
+            let addr = match listener.try_next().await.unwrap() {
+                Some(ListenerEvent::NewAddress(addr)) => addr,
+                _ => panic!("expected a new address"),
+            };
+
+            let mut outbound = Compat01As03::new(Transport::dial(transport, addr).unwrap().await.unwrap());
+            let (upgrade, _) = listener.try_next().await.unwrap().unwrap().into_upgrade().unwrap();
+            let mut inbound = Compat01As03::new(upgrade.await.unwrap());
+
+            outbound.write_all(b"hello").await.unwrap();
+            let mut buf = [0; 5];
+            inbound.read_exact(&mut buf).await.unwrap();
+            assert_eq!(&buf, b"hello");
+            Ok::<_, ()>(())
+        };
+
+        Compat03As01::new(Box::pin(test)).wait().unwrap();
+    }
+}
@@ -0,0 +1,247 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use crate::upgrade::{InboundUpgrade, Negotiated, OutboundUpgrade, UpgradeInfo};
+use futures::Future as Future01;
+use std::pin::Pin;
+use super::{Compat01As03, Compat03As01};
+
+/// Upgrade on an inbound connection or substream, built on futures 0.1.
+///
+/// This is the interface of [`InboundUpgrade`] before its migration to `std::future::Future`.
+/// Wrapping an `InboundUpgrade01` in a [`Compat01As03`] turns it into an [`InboundUpgrade`].
+pub trait InboundUpgrade01<C>: UpgradeInfo {
+    /// Output after the upgrade has been successfully negotiated and the handshake performed.
+    type Output;
+    /// Possible error during the handshake.
+    type Error;
+    /// Future that performs the handshake with the remote.
+    type Future: Future01<Item = Self::Output, Error = Self::Error>;
+
+    /// After we have determined that the remote supports one of the protocols we support, this
+    /// method is called to start the handshake.
+    ///
+    /// The `info` is the identifier of the protocol, as produced by `protocol_info`.
+    fn upgrade_inbound(self, socket: Negotiated<C>, info: Self::Info) -> Self::Future;
+}
+
+impl<U> UpgradeInfo for Compat01As03<U>
+where
+    U: UpgradeInfo,
+{
+    type Info = U::Info;
+    type InfoIter = U::InfoIter;
+
+    fn protocol_info(&self) -> Self::InfoIter {
+        self.inner.protocol_info()
+    }
+}
+
+impl<C, U> InboundUpgrade<C> for Compat01As03<U>
+where
+    U: InboundUpgrade01<C>,
+{
+    type Output = U::Output;
+    type Error = U::Error;
+    type Future = Compat01As03<U::Future>;
+
+    fn upgrade_inbound(self, socket: Negotiated<C>, info: Self::Info) -> Self::Future {
+        Compat01As03::new(self.inner.upgrade_inbound(socket, info))
+    }
+}
+
+/// Upgrade on an outbound connection or substream, built on futures 0.1.
+///
+/// This is the interface of [`OutboundUpgrade`] before its migration to `std::future::Future`.
+/// Wrapping an `OutboundUpgrade01` in a [`Compat01As03`] turns it into an [`OutboundUpgrade`].
+pub trait OutboundUpgrade01<C>: UpgradeInfo {
+    /// Output after the upgrade has been successfully negotiated and the handshake performed.
+    type Output;
+    /// Possible error during the handshake.
+    type Error;
+    /// Future that performs the handshake with the remote.
+    type Future: Future01<Item = Self::Output, Error = Self::Error>;
+
+    /// After we have determined that the remote supports one of the protocols we support, this
+    /// method is called to start the handshake.
+    ///
+    /// The `info` is the identifier of the protocol, as produced by `protocol_info`.
+    fn upgrade_outbound(self, socket: Negotiated<C>, info: Self::Info) -> Self::Future;
+}
+
+impl<C, U> OutboundUpgrade<C> for Compat01As03<U>
+where
+    U: OutboundUpgrade01<C>,
+{
+    type Output = U::Output;
+    type Error = U::Error;
+    type Future = Compat01As03<U::Future>;
+
+    fn upgrade_outbound(self, socket: Negotiated<C>, info: Self::Info) -> Self::Future {
+        Compat01As03::new(self.inner.upgrade_outbound(socket, info))
+    }
+}
+
+impl<U> UpgradeInfo for Compat03As01<U>
+where
+    U: UpgradeInfo,
+{
+    type Info = U::Info;
+    type InfoIter = U::InfoIter;
+
+    fn protocol_info(&self) -> Self::InfoIter {
+        self.inner.protocol_info()
+    }
+}
+
+impl<C, U> InboundUpgrade01<C> for Compat03As01<U>
+where
+    U: InboundUpgrade<C>,
+{
+    type Output = U::Output;
+    type Error = U::Error;
+    type Future = Compat03As01<Pin<Box<U::Future>>>;
+
+    fn upgrade_inbound(self, socket: Negotiated<C>, info: Self::Info) -> Self::Future {
+        Compat03As01::new(Box::pin(self.inner.upgrade_inbound(socket, info)))
+    }
+}
+
+impl<C, U> OutboundUpgrade01<C> for Compat03As01<U>
+where
+    U: OutboundUpgrade<C>,
+{
+    type Output = U::Output;
+    type Error = U::Error;
+    type Future = Compat03As01<Pin<Box<U::Future>>>;
+
+    fn upgrade_outbound(self, socket: Negotiated<C>, info: Self::Info) -> Self::Future {
+        Compat03As01::new(Box::pin(self.inner.upgrade_outbound(socket, info)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compat::{Compat01As03, Compat03As01};
+    use crate::transport::{ListenerEvent, MemoryTransport, Transport, memory::Channel};
+    use crate::upgrade::{InboundUpgrade, Negotiated, OutboundUpgrade, UpgradeInfo};
+    use futures03::{
+        future::{self, BoxFuture, FutureExt},
+        io::{AsyncReadExt, AsyncWriteExt},
+        stream::TryStreamExt,
+    };
+    use bytes::Bytes;
+    use std::{io, iter};
+    use tokio_io::{AsyncRead, AsyncWrite};
+
+    /// Upgrade written with async/await, in which the dialer sends `hello` and the listener
+    /// answers with `world`.
+    #[derive(Debug, Clone)]
+    struct HelloWorld;
+
+    impl UpgradeInfo for HelloWorld {
+        type Info = &'static [u8];
+        type InfoIter = iter::Once<Self::Info>;
+
+        fn protocol_info(&self) -> Self::InfoIter {
+            iter::once(b"/hello-world/1.0.0")
+        }
+    }
+
+    impl<C> InboundUpgrade<C> for HelloWorld
+    where
+        C: AsyncRead + AsyncWrite + Send + 'static,
+    {
+        type Output = Vec<u8>;
+        type Error = io::Error;
+        type Future = BoxFuture<'static, Result<Self::Output, Self::Error>>;
+
+        fn upgrade_inbound(self, socket: Negotiated<C>, _: Self::Info) -> Self::Future {
+            let mut socket = Compat01As03::new(socket);
+            async move {
+                let mut received = vec![0; 5];
+                socket.read_exact(&mut received).await?;
+                socket.write_all(b"world").await?;
+                socket.close().await?;
+                Ok(received)
+            }.boxed()
+        }
+    }
+
+    impl<C> OutboundUpgrade<C> for HelloWorld
+    where
+        C: AsyncRead + AsyncWrite + Send + 'static,
+    {
+        type Output = Vec<u8>;
+        type Error = io::Error;
+        type Future = BoxFuture<'static, Result<Self::Output, Self::Error>>;
+
+        fn upgrade_outbound(self, socket: Negotiated<C>, _: Self::Info) -> Self::Future {
+            let mut socket = Compat01As03::new(socket);
+            async move {
+                socket.write_all(b"hello").await?;
+                socket.flush().await?;
+                let mut received = Vec::new();
+                socket.read_to_end(&mut received).await?;
+                Ok(received)
+            }.boxed()
+        }
+    }
+
+    #[test]
+    fn async_upgrade_on_transport() {
+        hello_world(HelloWorld)
+    }
+
+    #[test]
+    fn upgrade_round_trip() {
+        // The upgrade goes through the futures 0.1 interface before going back to futures 0.3.
+        hello_world(Compat01As03::new(Compat03As01::new(HelloWorld)))
+    }
+
+    fn hello_world<U>(upgrade: U)
+    where
+        U: InboundUpgrade<Channel<Bytes>, Output = Vec<u8>, Error = io::Error>,
+        U: OutboundUpgrade<Channel<Bytes>, Output = Vec<u8>, Error = io::Error>,
+        U: Clone,
+    {
+        let transport = MemoryTransport.with_upgrade(upgrade);
+
+        let mut listener = transport.clone().listen_on("/memory/0".parse().unwrap()).unwrap();
+
+        let test = async move {
+            let addr = match listener.try_next().await.unwrap() {
+                Some(ListenerEvent::NewAddress(addr)) => addr,
+                _ => panic!("expected a new address"),
+            };
+
+            let listen = async move {
+                let (upgrade, _) = listener.try_next().await.unwrap().unwrap().into_upgrade().unwrap();
+                assert_eq!(upgrade.await.unwrap(), b"hello");
+            };
+            let dial = async move {
+                assert_eq!(transport.dial(addr).unwrap().await.unwrap(), b"world");
+            };
+            future::join(listen, dial).await;
+        };
+
+        futures03::executor::block_on(test);
+    }
+}
@@ -20,7 +20,13 @@
 
 use crate::{muxing::StreamMuxer, ProtocolName, transport::ListenerEvent};
 use futures::prelude::*;
-use std::{fmt, io::{Error as IoError, Read, Write}};
+use futures03::{
+    io::{AsyncRead as AsyncRead03, AsyncWrite as AsyncWrite03},
+    stream::TryStream,
+    task::{Context, Poll as Poll03},
+};
+use pin_project::pin_project;
+use std::{fmt, io::{Error as IoError, Read, Write}, pin::Pin};
 use tokio_io::{AsyncRead, AsyncWrite};
 
 #[derive(Debug, Copy, Clone)]
@@ -129,6 +135,50 @@ where
     }
 }
 
+impl<A, B> AsyncRead03 for EitherOutput<A, B>
+where
+    A: AsyncRead03 + Unpin,
+    B: AsyncRead03 + Unpin,
+{
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8])
+        -> Poll03<Result<usize, IoError>>
+    {
+        match self.get_mut() {
+            EitherOutput::First(a) => Pin::new(a).poll_read(cx, buf),
+            EitherOutput::Second(b) => Pin::new(b).poll_read(cx, buf),
+        }
+    }
+}
+
+impl<A, B> AsyncWrite03 for EitherOutput<A, B>
+where
+    A: AsyncWrite03 + Unpin,
+    B: AsyncWrite03 + Unpin,
+{
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8])
+        -> Poll03<Result<usize, IoError>>
+    {
+        match self.get_mut() {
+            EitherOutput::First(a) => Pin::new(a).poll_write(cx, buf),
+            EitherOutput::Second(b) => Pin::new(b).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll03<Result<(), IoError>> {
+        match self.get_mut() {
+            EitherOutput::First(a) => Pin::new(a).poll_flush(cx),
+            EitherOutput::Second(b) => Pin::new(b).poll_flush(cx),
+        }
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll03<Result<(), IoError>> {
+        match self.get_mut() {
+            EitherOutput::First(a) => Pin::new(a).poll_close(cx),
+            EitherOutput::Second(b) => Pin::new(b).poll_close(cx),
+        }
+    }
+}
+
 impl<A, B, I> Stream for EitherOutput<A, B>
 where
     A: Stream<Item = I>,
@@ -184,10 +234,12 @@ where
     type OutboundSubstream = EitherOutbound<A, B>;
     type Error = IoError;
 
-    fn poll_inbound(&self) -> Poll<Self::Substream, Self::Error> {
+    fn poll_inbound(&self, cx: &mut Context<'_>) -> Poll03<Result<Self::Substream, Self::Error>> {
         match self {
-            EitherOutput::First(inner) => inner.poll_inbound().map(|p| p.map(EitherOutput::First)).map_err(|e| e.into()),
-            EitherOutput::Second(inner) => inner.poll_inbound().map(|p| p.map(EitherOutput::Second)).map_err(|e| e.into()),
+            EitherOutput::First(inner) => inner.poll_inbound(cx)
+                .map(|p| p.map(EitherOutput::First).map_err(|e| e.into())),
+            EitherOutput::Second(inner) => inner.poll_inbound(cx)
+                .map(|p| p.map(EitherOutput::Second).map_err(|e| e.into())),
         }
     }
 
@@ -198,13 +250,15 @@ where
         }
     }
 
-    fn poll_outbound(&self, substream: &mut Self::OutboundSubstream) -> Poll<Self::Substream, Self::Error> {
+    fn poll_outbound(&self, cx: &mut Context<'_>, substream: &mut Self::OutboundSubstream)
+        -> Poll03<Result<Self::Substream, Self::Error>>
+    {
         match (self, substream) {
             (EitherOutput::First(ref inner), EitherOutbound::A(ref mut substream)) => {
-                inner.poll_outbound(substream).map(|p| p.map(EitherOutput::First)).map_err(|e| e.into())
+                inner.poll_outbound(cx, substream).map(|p| p.map(EitherOutput::First).map_err(|e| e.into()))
             },
             (EitherOutput::Second(ref inner), EitherOutbound::B(ref mut substream)) => {
-                inner.poll_outbound(substream).map(|p| p.map(EitherOutput::Second)).map_err(|e| e.into())
+                inner.poll_outbound(cx, substream).map(|p| p.map(EitherOutput::Second).map_err(|e| e.into()))
             },
             _ => panic!("Wrong API usage")
         }
@@ -227,56 +281,57 @@ where
         }
     }
 
-    unsafe fn prepare_uninitialized_buffer(&self, buf: &mut [u8]) -> bool {
-        match self {
-            EitherOutput::First(ref inner) => inner.prepare_uninitialized_buffer(buf),
-            EitherOutput::Second(ref inner) => inner.prepare_uninitialized_buffer(buf),
-        }
-    }
-
-    fn read_substream(&self, sub: &mut Self::Substream, buf: &mut [u8]) -> Poll<usize, Self::Error> {
+    fn read_substream(&self, cx: &mut Context<'_>, sub: &mut Self::Substream, buf: &mut [u8])
+        -> Poll03<Result<usize, Self::Error>>
+    {
         match (self, sub) {
             (EitherOutput::First(ref inner), EitherOutput::First(ref mut sub)) => {
-                inner.read_substream(sub, buf).map_err(|e| e.into())
+                inner.read_substream(cx, sub, buf).map_err(|e| e.into())
             },
             (EitherOutput::Second(ref inner), EitherOutput::Second(ref mut sub)) => {
-                inner.read_substream(sub, buf).map_err(|e| e.into())
+                inner.read_substream(cx, sub, buf).map_err(|e| e.into())
             },
             _ => panic!("Wrong API usage")
         }
     }
 
-    fn write_substream(&self, sub: &mut Self::Substream, buf: &[u8]) -> Poll<usize, Self::Error> {
+    fn write_substream(&self, cx: &mut Context<'_>, sub: &mut Self::Substream, buf: &[u8])
+        -> Poll03<Result<usize, Self::Error>>
+    {
         match (self, sub) {
             (EitherOutput::First(ref inner), EitherOutput::First(ref mut sub)) => {
-                inner.write_substream(sub, buf).map_err(|e| e.into())
+                inner.write_substream(cx, sub, buf).map_err(|e| e.into())
             },
             (EitherOutput::Second(ref inner), EitherOutput::Second(ref mut sub)) => {
-                inner.write_substream(sub, buf).map_err(|e| e.into())
+                inner.write_substream(cx, sub, buf).map_err(|e| e.into())
             },
             _ => panic!("Wrong API usage")
         }
     }
 
-    fn flush_substream(&self, sub: &mut Self::Substream) -> Poll<(), Self::Error> {
+    fn flush_substream(&self, cx: &mut Context<'_>, sub: &mut Self::Substream)
+        -> Poll03<Result<(), Self::Error>>
+    {
         match (self, sub) {
             (EitherOutput::First(ref inner), EitherOutput::First(ref mut sub)) => {
-                inner.flush_substream(sub).map_err(|e| e.into())
+                inner.flush_substream(cx, sub).map_err(|e| e.into())
             },
             (EitherOutput::Second(ref inner), EitherOutput::Second(ref mut sub)) => {
-                inner.flush_substream(sub).map_err(|e| e.into())
+                inner.flush_substream(cx, sub).map_err(|e| e.into())
             },
             _ => panic!("Wrong API usage")
         }
     }
 
-    fn shutdown_substream(&self, sub: &mut Self::Substream) -> Poll<(), Self::Error> {
+    fn shutdown_substream(&self, cx: &mut Context<'_>, sub: &mut Self::Substream)
+        -> Poll03<Result<(), Self::Error>>
+    {
         match (self, sub) {
             (EitherOutput::First(ref inner), EitherOutput::First(ref mut sub)) => {
-                inner.shutdown_substream(sub).map_err(|e| e.into())
+                inner.shutdown_substream(cx, sub).map_err(|e| e.into())
             },
             (EitherOutput::Second(ref inner), EitherOutput::Second(ref mut sub)) => {
-                inner.shutdown_substream(sub).map_err(|e| e.into())
+                inner.shutdown_substream(cx, sub).map_err(|e| e.into())
             },
             _ => panic!("Wrong API usage")
         }
@@ -306,17 +361,17 @@ where
         }
     }
 
-    fn close(&self) -> Poll<(), Self::Error> {
+    fn close(&self, cx: &mut Context<'_>) -> Poll03<Result<(), Self::Error>> {
         match self {
-            EitherOutput::First(inner) => inner.close().map_err(|e| e.into()),
-            EitherOutput::Second(inner) => inner.close().map_err(|e| e.into()),
+            EitherOutput::First(inner) => inner.close(cx).map_err(|e| e.into()),
+            EitherOutput::Second(inner) => inner.close(cx).map_err(|e| e.into()),
         }
     }
 
-    fn flush_all(&self) -> Poll<(), Self::Error> {
+    fn flush_all(&self, cx: &mut Context<'_>) -> Poll03<Result<(), Self::Error>> {
         match self {
-            EitherOutput::First(inner) => inner.flush_all().map_err(|e| e.into()),
-            EitherOutput::Second(inner) => inner.flush_all().map_err(|e| e.into()),
+            EitherOutput::First(inner) => inner.flush_all(cx).map_err(|e| e.into()),
+            EitherOutput::Second(inner) => inner.flush_all(cx).map_err(|e| e.into()),
         }
     }
 }
@@ -329,78 +384,76 @@ pub enum EitherOutbound<A: StreamMuxer, B: StreamMuxer> {
 }
 
 /// Implements `Stream` and dispatches all method calls to either `First` or `Second`.
+#[pin_project(project = EitherListenStreamProj)]
 #[derive(Debug, Copy, Clone)]
 #[must_use = "futures do nothing unless polled"]
 pub enum EitherListenStream<A, B> {
-    First(A),
-    Second(B),
+    First(#[pin] A),
+    Second(#[pin] B),
 }
 
-impl<AStream, BStream, AInner, BInner> Stream for EitherListenStream<AStream, BStream>
+impl<AStream, BStream, AInner, BInner> futures03::Stream for EitherListenStream<AStream, BStream>
 where
-    AStream: Stream<Item = ListenerEvent<AInner>>,
-    BStream: Stream<Item = ListenerEvent<BInner>>,
+    AStream: TryStream<Ok = ListenerEvent<AInner>>,
+    BStream: TryStream<Ok = ListenerEvent<BInner>>,
 {
-    type Item = ListenerEvent<EitherFuture<AInner, BInner>>;
-    type Error = EitherError<AStream::Error, BStream::Error>;
-
-    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
-        match self {
-            EitherListenStream::First(a) => a.poll()
-                .map(|i| (i.map(|v| (v.map(|e| e.map(EitherFuture::First))))))
-                .map_err(EitherError::A),
-            EitherListenStream::Second(a) => a.poll()
-                .map(|i| (i.map(|v| (v.map(|e| e.map(EitherFuture::Second))))))
-                .map_err(EitherError::B),
+    type Item = Result<ListenerEvent<EitherFuture<AInner, BInner>>, EitherError<AStream::Error, BStream::Error>>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll03<Option<Self::Item>> {
+        match self.project() {
+            EitherListenStreamProj::First(a) => a.try_poll_next(cx)
+                .map(|i| i.map(|v| v.map(|e| e.map(EitherFuture::First)).map_err(EitherError::A))),
+            EitherListenStreamProj::Second(a) => a.try_poll_next(cx)
+                .map(|i| i.map(|v| v.map(|e| e.map(EitherFuture::Second)).map_err(EitherError::B))),
         }
     }
 }
 
 /// Implements `Future` and dispatches all method calls to either `First` or `Second`.
+#[pin_project(project = EitherFutureProj)]
 #[derive(Debug, Copy, Clone)]
 #[must_use = "futures do nothing unless polled"]
 pub enum EitherFuture<A, B> {
-    First(A),
-    Second(B),
+    First(#[pin] A),
+    Second(#[pin] B),
 }
 
-impl<AFuture, BFuture, AInner, BInner> Future for EitherFuture<AFuture, BFuture>
+impl<AFuture, BFuture, AInner, BInner, AError, BError> std::future::Future for EitherFuture<AFuture, BFuture>
 where
-    AFuture: Future<Item = AInner>,
-    BFuture: Future<Item = BInner>,
+    AFuture: std::future::Future<Output = Result<AInner, AError>>,
+    BFuture: std::future::Future<Output = Result<BInner, BError>>,
 {
-    type Item = EitherOutput<AInner, BInner>;
-    type Error = EitherError<AFuture::Error, BFuture::Error>;
-
-    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
-        match self {
-            EitherFuture::First(a) => a.poll().map(|v| v.map(EitherOutput::First)).map_err(EitherError::A),
-            EitherFuture::Second(a) => a.poll().map(|v| v.map(EitherOutput::Second)).map_err(EitherError::B),
+    type Output = Result<EitherOutput<AInner, BInner>, EitherError<AError, BError>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll03<Self::Output> {
+        match self.project() {
+            EitherFutureProj::First(a) => a.poll(cx)
+                .map(|v| v.map(EitherOutput::First).map_err(EitherError::A)),
+            EitherFutureProj::Second(a) => a.poll(cx)
+                .map(|v| v.map(EitherOutput::Second).map_err(EitherError::B)),
         }
     }
 }
 
+#[pin_project(project = EitherFuture2Proj)]
 #[derive(Debug, Copy, Clone)]
 #[must_use = "futures do nothing unless polled"]
-pub enum EitherFuture2<A, B> { A(A), B(B) }
+pub enum EitherFuture2<A, B> { A(#[pin] A), B(#[pin] B) }
 
-impl<AFut, BFut, AItem, BItem, AError, BError> Future for EitherFuture2<AFut, BFut>
+impl<AFut, BFut, AItem, BItem, AError, BError> std::future::Future for EitherFuture2<AFut, BFut>
 where
-    AFut: Future<Item = AItem, Error = AError>,
-    BFut: Future<Item = BItem, Error = BError>
+    AFut: std::future::Future<Output = Result<AItem, AError>>,
+    BFut: std::future::Future<Output = Result<BItem, BError>>,
 {
-    type Item = EitherOutput<AItem, BItem>;
-    type Error = EitherError<AError, BError>;
+    type Output = Result<EitherOutput<AItem, BItem>, EitherError<AError, BError>>;
 
-    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
-        match self {
-            EitherFuture2::A(a) => a.poll()
-                .map(|v| v.map(EitherOutput::First))
-                .map_err(EitherError::A),
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll03<Self::Output> {
+        match self.project() {
+            EitherFuture2Proj::A(a) => a.poll(cx)
+                .map(|v| v.map(EitherOutput::First).map_err(EitherError::A)),
 
-            EitherFuture2::B(b) => b.poll()
-                .map(|v| v.map(EitherOutput::Second))
-                .map_err(EitherError::B)
+            EitherFuture2Proj::B(b) => b.poll(cx)
+                .map(|v| v.map(EitherOutput::Second).map_err(EitherError::B))
         }
     }
 }
@@ -0,0 +1,132 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Executors that run the background tasks of the connections.
+//!
+//! Each connection of a [`Network`](crate::nodes::Network) is processed by a dedicated task,
+//! which is handed to an [`Executor`]. By default, [`TokioExecutor`] is used, but any runtime or
+//! thread pool can be plugged in by implementing the [`Executor`] trait, which is automatically
+//! implemented on closures.
+
+use crate::compat;
+use futures::{Async, Future as Future01, future::Executor as _};
+use futures03::{future::BoxFuture, task::Poll};
+
+/// Runs futures in the background.
+pub trait Executor {
+    /// Runs the given future to completion in the background.
+    ///
+    /// If the executor can't run the future, it must return it back. The future is then polled
+    /// by the caller, on the task that uses the executor.
+    fn exec(&self, future: BoxFuture<'static, ()>) -> Result<(), BoxFuture<'static, ()>>;
+}
+
+impl<F> Executor for F
+where
+    F: Fn(BoxFuture<'static, ()>),
+{
+    fn exec(&self, future: BoxFuture<'static, ()>) -> Result<(), BoxFuture<'static, ()>> {
+        self(future);
+        Ok(())
+    }
+}
+
+/// Runs the futures on the default tokio executor, if any.
+///
+/// Outside of a tokio runtime, the futures are returned back to the caller. In the case of a
+/// `Network`, this means that the connections are processed on the task that polls it.
+///
+/// This is the executor used by default.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct TokioExecutor;
+
+impl Executor for TokioExecutor {
+    fn exec(&self, future: BoxFuture<'static, ()>) -> Result<(), BoxFuture<'static, ()>> {
+        tokio_executor::DefaultExecutor::current()
+            .execute(Task(future))
+            .map_err(|err| err.into_future().0)
+    }
+}
+
+/// Returns all the futures back to the caller.
+///
+/// In the case of a `Network`, this means that the connections are always processed on the task
+/// that polls it, which makes the processing deterministic.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct InlineExecutor;
+
+impl Executor for InlineExecutor {
+    fn exec(&self, future: BoxFuture<'static, ()>) -> Result<(), BoxFuture<'static, ()>> {
+        Err(future)
+    }
+}
+
+/// Future given to a futures 0.1 executor, from which the original future can be recovered if
+/// the executor refuses it.
+struct Task(BoxFuture<'static, ()>);
+
+impl Future01 for Task {
+    type Item = ();
+    type Error = ();
+
+    fn poll(&mut self) -> Result<Async<()>, ()> {
+        match compat::with_context(|cx| self.0.as_mut().poll(cx)) {
+            Poll::Ready(()) => Ok(Async::Ready(())),
+            Poll::Pending => Ok(Async::NotReady),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Executor, InlineExecutor, TokioExecutor};
+    use futures::{future, sync::oneshot};
+    use futures03::{executor::block_on, future::ready};
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn tokio_executor_falls_back_outside_of_runtime() {
+        assert!(TokioExecutor.exec(Box::pin(ready(()))).is_err());
+
+        let mut rt = tokio::runtime::Runtime::new().unwrap();
+        let (tx, rx) = oneshot::channel();
+        rt.block_on(future::lazy(move || {
+            let task = async move { tx.send(()).unwrap() };
+            assert!(TokioExecutor.exec(Box::pin(task)).is_ok());
+            rx
+        })).unwrap();
+    }
+
+    #[test]
+    fn inline_executor_returns_futures() {
+        assert!(InlineExecutor.exec(Box::pin(ready(()))).is_err());
+    }
+
+    #[test]
+    fn closures_are_executors() {
+        let spawned = Arc::new(Mutex::new(Vec::new()));
+        let executor = {
+            let spawned = spawned.clone();
+            move |future| spawned.lock().unwrap().push(future)
+        };
+        assert!(executor.exec(Box::pin(ready(()))).is_ok());
+        block_on(spawned.lock().unwrap().pop().unwrap());
+    }
+}
@@ -39,24 +39,34 @@
 pub use multiaddr;
 pub use multistream_select::Negotiated;
 
+mod envelope_proto;
 mod keys_proto;
 mod peer_id;
+mod peer_record_proto;
 mod translation;
 
 #[cfg(test)]
 mod tests;
 
+pub mod clock;
+pub mod compat;
 pub mod either;
+pub mod executor;
 pub mod identity;
 pub mod muxing;
 pub mod nodes;
+pub mod peer_record;
+pub mod signed_envelope;
 pub mod transport;
 pub mod upgrade;
 
 pub use multiaddr::Multiaddr;
+pub use executor::Executor;
 pub use muxing::StreamMuxer;
 pub use peer_id::PeerId;
+pub use peer_record::PeerRecord;
 pub use identity::PublicKey;
+pub use signed_envelope::SignedEnvelope;
 pub use transport::Transport;
 pub use translation::address_translation;
 pub use upgrade::{InboundUpgrade, OutboundUpgrade, UpgradeInfo, UpgradeError, ProtocolName};
@@ -107,6 +117,11 @@ pub enum ConnectedPoint {
     Dialer {
         /// Multiaddress that was successfully dialed.
         address: Multiaddr,
+        /// The role we take in the upgrades of the connection.
+        ///
+        /// This is `Endpoint::Listener` if we dialed as the listener, see
+        /// [`Transport::dial_as_listener`](transport::Transport::dial_as_listener).
+        role_override: Endpoint,
     },
     /// We received the node.
     Listener {
@@ -153,5 +168,15 @@ impl ConnectedPoint {
             ConnectedPoint::Listener { .. } => true
         }
     }
+
+    /// Returns the role we take in the upgrades of the connection.
+    ///
+    /// This is the same as `to_endpoint`, unless we dialed as the listener.
+    pub fn role(&self) -> Endpoint {
+        match self {
+            ConnectedPoint::Dialer { role_override, .. } => *role_override,
+            ConnectedPoint::Listener { .. } => Endpoint::Listener
+        }
+    }
 }
 
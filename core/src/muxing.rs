@@ -51,13 +51,23 @@
 //! The upgrade process will take ownership of the connection, which makes it possible for the
 //! implementation of `StreamMuxer` to control everything that happens on the wire.
 
+use crate::compat::{with_context, with_task};
+use crate::nodes::node::SubstreamPermit;
 use fnv::FnvHashMap;
-use futures::{future, prelude::*, try_ready};
+use futures::Async;
+use futures03::{
+    future::{self, TryFutureExt},
+    io::{AsyncRead as AsyncRead03, AsyncWrite as AsyncWrite03},
+    ready,
+    task::{Context, Poll},
+};
 use parking_lot::Mutex;
+use std::future::Future;
 use std::io::{self, Read, Write};
 use std::ops::Deref;
-use std::fmt;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::pin::Pin;
+use std::{cmp, fmt};
+use std::sync::{Arc, atomic::{AtomicUsize, Ordering}};
 use tokio_io::{AsyncRead, AsyncWrite};
 
 pub use self::singleton::SingletonMuxer;
@@ -76,6 +86,9 @@ mod singleton;
 /// - A list of outbound substreams being opened. The `open_outbound`, `poll_outbound` and
 ///   `destroy_outbound` methods allow controlling these entries.
 ///
+/// Muxers written against futures 0.1 implement
+/// [`StreamMuxer01`](crate::compat::StreamMuxer01) instead, and automatically implement this
+/// trait as well.
 pub trait StreamMuxer {
     /// Type of the object that represents the raw substream where data can be read and written.
     type Substream;
@@ -90,12 +103,12 @@ pub trait StreamMuxer {
     ///
     /// This function behaves the same as a `Stream`.
     ///
-    /// If `NotReady` is returned, then the current task will be notified once the muxer
-    /// is ready to be polled, similar to the API of `Stream::poll()`.
-    /// Only the latest task that was used to call this method may be notified.
+    /// If `Pending` is returned, then the waker of `cx` will be woken up once the muxer
+    /// is ready to be polled, similar to the API of `Stream::poll_next()`.
+    /// Only the latest waker that was passed to this method may be woken up.
     ///
     /// An error can be generated if the connection has been closed.
-    fn poll_inbound(&self) -> Poll<Self::Substream, Self::Error>;
+    fn poll_inbound(&self, cx: &mut Context<'_>) -> Poll<Result<Self::Substream, Self::Error>>;
 
     /// Opens a new outgoing substream, and produces the equivalent to a future that will be
     /// resolved when it becomes available.
@@ -106,75 +119,65 @@ pub trait StreamMuxer {
 
     /// Polls the outbound substream.
     ///
-    /// If `NotReady` is returned, then the current task will be notified once the substream
+    /// If `Pending` is returned, then the waker of `cx` will be woken up once the substream
     /// is ready to be polled, similar to the API of `Future::poll()`.
-    /// However, for each individual outbound substream, only the latest task that was used to
-    /// call this method may be notified.
+    /// However, for each individual outbound substream, only the latest waker that was passed
+    /// to this method may be woken up.
     ///
     /// May panic or produce an undefined result if an earlier polling of the same substream
-    /// returned `Ready` or `Err`.
-    fn poll_outbound(&self, s: &mut Self::OutboundSubstream) -> Poll<Self::Substream, Self::Error>;
+    /// returned `Ready`.
+    fn poll_outbound(&self, cx: &mut Context<'_>, s: &mut Self::OutboundSubstream)
+        -> Poll<Result<Self::Substream, Self::Error>>;
 
     /// Destroys an outbound substream future. Use this after the outbound substream has finished,
     /// or if you want to interrupt it.
     fn destroy_outbound(&self, s: Self::OutboundSubstream);
 
-    /// Reads data from a substream. The behaviour is the same as `tokio_io::AsyncRead::poll_read`.
+    /// Reads data from a substream. The behaviour is the same as `futures::AsyncRead::poll_read`.
     ///
-    /// If `NotReady` is returned, then the current task will be notified once the substream
-    /// is ready to be read. However, for each individual substream, only the latest task that
-    /// was used to call this method may be notified.
+    /// If `Pending` is returned, then the waker of `cx` will be woken up once the substream
+    /// is ready to be read. However, for each individual substream, only the latest waker that
+    /// was passed to this method may be woken up.
     ///
-    /// If `Async::Ready(0)` is returned, the substream has been closed by the remote and should
+    /// If `Ready(Ok(0))` is returned, the substream has been closed by the remote and should
     /// no longer be read afterwards.
     ///
     /// An error can be generated if the connection has been closed, or if a protocol misbehaviour
     /// happened.
-    fn read_substream(&self, s: &mut Self::Substream, buf: &mut [u8]) -> Poll<usize, Self::Error>;
+    fn read_substream(&self, cx: &mut Context<'_>, s: &mut Self::Substream, buf: &mut [u8])
+        -> Poll<Result<usize, Self::Error>>;
 
-    /// Mimics the `prepare_uninitialized_buffer` method of the `AsyncRead` trait.
+    /// Write data to a substream. The behaviour is the same as `futures::AsyncWrite::poll_write`.
     ///
-    /// This function isn't actually unsafe to call but unsafe to implement. The implementer must
-    /// ensure that either the whole buf has been zeroed or that `read_substream` overwrites the
-    /// buffer without reading it and returns correct value.
-    ///
-    /// If this function returns true, then the memory has been zeroed out. This allows
-    /// implementations of `AsyncRead` which are composed of multiple subimplementations to
-    /// efficiently implement `prepare_uninitialized_buffer`.
-    unsafe fn prepare_uninitialized_buffer(&self, buf: &mut [u8]) -> bool {
-        for b in buf.iter_mut() { *b = 0; }
-        true
-    }
-
-    /// Write data to a substream. The behaviour is the same as `tokio_io::AsyncWrite::poll_write`.
-    ///
-    /// If `NotReady` is returned, then the current task will be notified once the substream
-    /// is ready to be read. For each individual substream, only the latest task that was used to
-    /// call this method may be notified.
+    /// If `Pending` is returned, then the waker of `cx` will be woken up once the substream
+    /// is ready to be written. For each individual substream, only the latest waker that was
+    /// passed to this method may be woken up.
     ///
     /// Calling `write_substream` does not guarantee that data will arrive to the remote. To
     /// ensure that, you should call `flush_substream`.
     ///
     /// It is incorrect to call this method on a substream if you called `shutdown_substream` on
     /// this substream earlier.
-    fn write_substream(&self, s: &mut Self::Substream, buf: &[u8]) -> Poll<usize, Self::Error>;
+    fn write_substream(&self, cx: &mut Context<'_>, s: &mut Self::Substream, buf: &[u8])
+        -> Poll<Result<usize, Self::Error>>;
 
-    /// Flushes a substream. The behaviour is the same as `tokio_io::AsyncWrite::poll_flush`.
+    /// Flushes a substream. The behaviour is the same as `futures::AsyncWrite::poll_flush`.
     ///
     /// After this method has been called, data written earlier on the substream is guaranteed to
     /// be received by the remote.
     ///
-    /// If `NotReady` is returned, then the current task will be notified once the substream
-    /// is ready to be read. For each individual substream, only the latest task that was used to
-    /// call this method may be notified.
+    /// If `Pending` is returned, then the waker of `cx` will be woken up once the substream
+    /// is ready to be flushed. For each individual substream, only the latest waker that was
+    /// passed to this method may be woken up.
     ///
     /// > **Note**: This method may be implemented as a call to `flush_all`.
-    fn flush_substream(&self, s: &mut Self::Substream) -> Poll<(), Self::Error>;
+    fn flush_substream(&self, cx: &mut Context<'_>, s: &mut Self::Substream)
+        -> Poll<Result<(), Self::Error>>;
 
     /// Attempts to shut down the writing side of a substream. The behaviour is similar to
-    /// `tokio_io::AsyncWrite::shutdown`.
+    /// `futures::AsyncWrite::poll_close`.
     ///
-    /// Contrary to `AsyncWrite::shutdown`, shutting down a substream does not imply
+    /// Contrary to `AsyncWrite::poll_close`, shutting down a substream does not imply
     /// `flush_substream`. If you want to make sure that the remote is immediately informed about
     /// the shutdown, use `flush_substream` or `flush_all`.
     ///
@@ -182,7 +185,8 @@ pub trait StreamMuxer {
     ///
     /// An error can be generated if the connection has been closed, or if a protocol misbehaviour
     /// happened.
-    fn shutdown_substream(&self, s: &mut Self::Substream) -> Poll<(), Self::Error>;
+    fn shutdown_substream(&self, cx: &mut Context<'_>, s: &mut Self::Substream)
+        -> Poll<Result<(), Self::Error>>;
 
     /// Destroys a substream.
     fn destroy_substream(&self, s: Self::Substream);
@@ -197,7 +201,7 @@ pub trait StreamMuxer {
 
     /// Closes this `StreamMuxer`.
     ///
-    /// After this has returned `Ok(Async::Ready(()))`, the muxer has become useless. All
+    /// After this has returned `Ready(Ok(()))`, the muxer has become useless. All
     /// subsequent reads must return either `EOF` or an error. All subsequent writes, shutdowns,
     /// or polls must generate an error or be ignored.
     ///
@@ -207,14 +211,14 @@ pub trait StreamMuxer {
     /// >           that the remote is properly informed of the shutdown. However, apart from
     /// >           properly informing the remote, there is no difference between this and
     /// >           immediately dropping the muxer.
-    fn close(&self) -> Poll<(), Self::Error>;
+    fn close(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>>;
 
     /// Flush this `StreamMuxer`.
     ///
     /// This drains any write buffers of substreams and delivers any pending shutdown notifications
     /// due to `shutdown_substream` or `close`. One may thus shutdown groups of substreams
     /// followed by a final `flush_all` instead of having to do `flush_substream` for each.
-    fn flush_all(&self) -> Poll<(), Self::Error>;
+    fn flush_all(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>>;
 }
 
 /// Polls for an inbound from the muxer but wraps the output in an object that
@@ -222,14 +226,14 @@ pub trait StreamMuxer {
 #[inline]
 pub fn inbound_from_ref_and_wrap<P>(
     muxer: P,
-) -> impl Future<Item = SubstreamRef<P>, Error = <P::Target as StreamMuxer>::Error>
+) -> impl Future<Output = Result<SubstreamRef<P>, <P::Target as StreamMuxer>::Error>>
 where
     P: Deref + Clone,
     P::Target: StreamMuxer,
 {
     let muxer2 = muxer.clone();
-    future::poll_fn(move || muxer.poll_inbound())
-        .map(|substream| substream_from_ref(muxer2, substream))
+    future::poll_fn(move |cx| muxer.poll_inbound(cx))
+        .map_ok(|substream| substream_from_ref(muxer2, substream))
 }
 
 /// Same as `outbound_from_ref`, but wraps the output in an object that
@@ -258,18 +262,11 @@ where
     P: Deref + Clone,
     P::Target: StreamMuxer,
 {
-    type Item = SubstreamRef<P>;
-    type Error = <P::Target as StreamMuxer>::Error;
-
-    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
-        match self.inner.poll() {
-            Ok(Async::Ready(substream)) => {
-                let out = substream_from_ref(self.inner.muxer.clone(), substream);
-                Ok(Async::Ready(out))
-            }
-            Ok(Async::NotReady) => Ok(Async::NotReady),
-            Err(err) => Err(err),
-        }
+    type Output = Result<SubstreamRef<P>, <P::Target as StreamMuxer>::Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let substream = ready!(Pin::new(&mut self.inner).poll(cx))?;
+        Poll::Ready(Ok(substream_from_ref(self.inner.muxer.clone(), substream)))
     }
 }
 
@@ -297,18 +294,26 @@ where
     outbound: Option<<P::Target as StreamMuxer>::OutboundSubstream>,
 }
 
+// The outbound substream is only ever accessed through the muxer, which doesn't require it to
+// stay at the same place in memory.
+impl<P> Unpin for OutboundSubstreamRefFuture<P>
+where
+    P: Deref,
+    P::Target: StreamMuxer,
+{
+}
+
 impl<P> Future for OutboundSubstreamRefFuture<P>
 where
     P: Deref,
     P::Target: StreamMuxer,
 {
-    type Item = <P::Target as StreamMuxer>::Substream;
-    type Error = <P::Target as StreamMuxer>::Error;
+    type Output = Result<<P::Target as StreamMuxer>::Substream, <P::Target as StreamMuxer>::Error>;
 
     #[inline]
-    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
-        self.muxer
-            .poll_outbound(self.outbound.as_mut().expect("outbound was empty"))
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        this.muxer.poll_outbound(cx, this.outbound.as_mut().expect("outbound was empty"))
     }
 }
 
@@ -339,9 +344,51 @@ where
         muxer,
         substream: Some(substream),
         shutdown_state: ShutdownState::Shutdown,
+        meter: None,
+        permit: None,
     }
 }
 
+/// Receives notifications about the traffic on a metered substream, and can throttle it.
+///
+/// A meter is attached to a substream before the protocol negotiation takes place. The bytes
+/// exchanged during the negotiation are therefore reported as well, before `on_negotiated` is
+/// called.
+pub trait SubstreamMeter: Send + Sync {
+    /// Called once the protocol of the substream has been negotiated.
+    fn on_negotiated(&self, _protocol: &[u8]) {}
+
+    /// Called before reading into a buffer of `len` bytes, with `len` greater than zero.
+    ///
+    /// Returns the maximum number of bytes that may be read, which must be greater than zero, or
+    /// `NotReady` if the substream must not be read for now, in which case the current task is
+    /// notified once it can be.
+    fn poll_read_quota(&self, len: usize) -> Async<usize> {
+        Async::Ready(len)
+    }
+
+    /// Called before writing a buffer of `len` bytes, with `len` greater than zero.
+    ///
+    /// Returns the maximum number of bytes that may be written, which must be greater than zero,
+    /// or `NotReady` if the substream must not be written for now, in which case the current task
+    /// is notified once it can be.
+    fn poll_write_quota(&self, len: usize) -> Async<usize> {
+        Async::Ready(len)
+    }
+
+    /// Called after `bytes` have been read from the substream.
+    fn on_read(&self, bytes: usize);
+
+    /// Called after `bytes` have been written to the substream.
+    fn on_write(&self, bytes: usize);
+}
+
+/// Substream that can report its traffic to a `SubstreamMeter`.
+pub trait MeteredSubstream {
+    /// Attaches a meter to the substream, replacing the previous one, if any.
+    fn set_meter(&mut self, meter: Arc<dyn SubstreamMeter>);
+}
+
 /// Stream returned by `substream_from_ref`.
 pub struct SubstreamRef<P>
 where
@@ -351,6 +398,8 @@ where
     muxer: P,
     substream: Option<<P::Target as StreamMuxer>::Substream>,
     shutdown_state: ShutdownState,
+    meter: Option<Arc<dyn SubstreamMeter>>,
+    permit: Option<SubstreamPermit>,
 }
 
 enum ShutdownState {
@@ -370,96 +419,180 @@ where
     }
 }
 
-
-impl<P> Read for SubstreamRef<P>
+impl<P> MeteredSubstream for SubstreamRef<P>
 where
     P: Deref,
     P::Target: StreamMuxer,
 {
-    #[inline]
-    fn read(&mut self, buf: &mut [u8]) -> Result<usize, io::Error> {
-        let s = self.substream.as_mut().expect("substream was empty");
-        match self.muxer.read_substream(s, buf).map_err(|e| e.into())? {
-            Async::Ready(n) => Ok(n),
-            Async::NotReady => Err(io::ErrorKind::WouldBlock.into())
-        }
+    fn set_meter(&mut self, meter: Arc<dyn SubstreamMeter>) {
+        self.meter = Some(meter);
     }
 }
 
-impl<P> AsyncRead for SubstreamRef<P>
+// The substream is only ever accessed through the muxer, which doesn't require it to stay at the
+// same place in memory.
+impl<P> Unpin for SubstreamRef<P>
 where
     P: Deref,
     P::Target: StreamMuxer,
 {
-    unsafe fn prepare_uninitialized_buffer(&self, buf: &mut [u8]) -> bool {
-        self.muxer.prepare_uninitialized_buffer(buf)
-    }
-
-    fn poll_read(&mut self, buf: &mut [u8]) -> Poll<usize, io::Error> {
-        let s = self.substream.as_mut().expect("substream was empty");
-        self.muxer.read_substream(s, buf).map_err(|e| e.into())
-    }
 }
 
-impl<P> Write for SubstreamRef<P>
+impl<P> SubstreamRef<P>
 where
     P: Deref,
     P::Target: StreamMuxer,
 {
-    #[inline]
-    fn write(&mut self, buf: &[u8]) -> Result<usize, io::Error> {
+    /// Attaches the permit under which this substream is counted by a substream limit.
+    pub(crate) fn set_permit(&mut self, permit: Option<SubstreamPermit>) {
+        self.permit = permit;
+    }
+
+    /// Reads from the substream, within the quota granted by the meter, if any.
+    fn read_metered(&mut self, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<Result<usize, io::Error>> {
+        let quota = match &self.meter {
+            Some(meter) if !buf.is_empty() => with_task(cx, || meter.poll_read_quota(buf.len())),
+            _ => Async::Ready(buf.len())
+        };
+        let len = match quota {
+            Async::Ready(quota) => cmp::min(cmp::max(quota, 1), buf.len()),
+            Async::NotReady => return Poll::Pending
+        };
         let s = self.substream.as_mut().expect("substream was empty");
-        match self.muxer.write_substream(s, buf).map_err(|e| e.into())? {
-            Async::Ready(n) => Ok(n),
-            Async::NotReady => Err(io::ErrorKind::WouldBlock.into())
+        let n = ready!(self.muxer.read_substream(cx, s, &mut buf[..len]).map_err(|e| e.into()))?;
+        if let Some(meter) = &self.meter {
+            meter.on_read(n)
         }
+        Poll::Ready(Ok(n))
     }
 
-    #[inline]
-    fn flush(&mut self) -> Result<(), io::Error> {
+    /// Writes to the substream, within the quota granted by the meter, if any.
+    fn write_metered(&mut self, cx: &mut Context<'_>, buf: &[u8]) -> Poll<Result<usize, io::Error>> {
+        let quota = match &self.meter {
+            Some(meter) if !buf.is_empty() => with_task(cx, || meter.poll_write_quota(buf.len())),
+            _ => Async::Ready(buf.len())
+        };
+        let len = match quota {
+            Async::Ready(quota) => cmp::min(cmp::max(quota, 1), buf.len()),
+            Async::NotReady => return Poll::Pending
+        };
         let s = self.substream.as_mut().expect("substream was empty");
-        match self.muxer.flush_substream(s).map_err(|e| e.into())? {
-            Async::Ready(()) => Ok(()),
-            Async::NotReady => Err(io::ErrorKind::WouldBlock.into())
+        let n = ready!(self.muxer.write_substream(cx, s, &buf[..len]).map_err(|e| e.into()))?;
+        if let Some(meter) = &self.meter {
+            meter.on_write(n)
         }
+        Poll::Ready(Ok(n))
     }
-}
 
-impl<P> AsyncWrite for SubstreamRef<P>
-where
-    P: Deref,
-    P::Target: StreamMuxer,
-{
-    #[inline]
-    fn poll_write(&mut self, buf: &[u8]) -> Poll<usize, io::Error> {
+    /// Flushes the substream.
+    fn flush_substream(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
         let s = self.substream.as_mut().expect("substream was empty");
-        self.muxer.write_substream(s, buf).map_err(|e| e.into())
+        self.muxer.flush_substream(cx, s).map_err(|e| e.into())
     }
 
-    #[inline]
-    fn shutdown(&mut self) -> Poll<(), io::Error> {
+    /// Shuts down the writing side of the substream, then flushes it.
+    fn shutdown_substream(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
         let s = self.substream.as_mut().expect("substream was empty");
         loop {
             match self.shutdown_state {
                 ShutdownState::Shutdown => {
-                    try_ready!(self.muxer.shutdown_substream(s).map_err(|e| e.into()));
+                    ready!(self.muxer.shutdown_substream(cx, s).map_err(|e| e.into()))?;
                     self.shutdown_state = ShutdownState::Flush;
                 }
                 ShutdownState::Flush => {
-                    try_ready!(self.muxer.flush_substream(s).map_err(|e| e.into()));
+                    ready!(self.muxer.flush_substream(cx, s).map_err(|e| e.into()))?;
                     self.shutdown_state = ShutdownState::Done;
                 }
                 ShutdownState::Done => {
-                    return Ok(Async::Ready(()));
+                    return Poll::Ready(Ok(()));
                 }
             }
         }
     }
+}
+
+/// Turns the result of a poll method into the result of the equivalent blocking I/O method.
+fn poll_to_io<T>(poll: Poll<Result<T, io::Error>>) -> Result<T, io::Error> {
+    match poll {
+        Poll::Ready(result) => result,
+        Poll::Pending => Err(io::ErrorKind::WouldBlock.into())
+    }
+}
 
+impl<P> AsyncRead03 for SubstreamRef<P>
+where
+    P: Deref,
+    P::Target: StreamMuxer,
+{
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<Result<usize, io::Error>> {
+        self.get_mut().read_metered(cx, buf)
+    }
+}
+
+impl<P> AsyncWrite03 for SubstreamRef<P>
+where
+    P: Deref,
+    P::Target: StreamMuxer,
+{
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<Result<usize, io::Error>> {
+        self.get_mut().write_metered(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        self.get_mut().flush_substream(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        self.get_mut().shutdown_substream(cx)
+    }
+}
+
+impl<P> Read for SubstreamRef<P>
+where
+    P: Deref,
+    P::Target: StreamMuxer,
+{
     #[inline]
-    fn poll_flush(&mut self) -> Poll<(), io::Error> {
-        let s = self.substream.as_mut().expect("substream was empty");
-        self.muxer.flush_substream(s).map_err(|e| e.into())
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, io::Error> {
+        poll_to_io(with_context(|cx| self.read_metered(cx, buf)))
+    }
+}
+
+impl<P> AsyncRead for SubstreamRef<P>
+where
+    P: Deref,
+    P::Target: StreamMuxer,
+{
+}
+
+impl<P> Write for SubstreamRef<P>
+where
+    P: Deref,
+    P::Target: StreamMuxer,
+{
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> Result<usize, io::Error> {
+        poll_to_io(with_context(|cx| self.write_metered(cx, buf)))
+    }
+
+    #[inline]
+    fn flush(&mut self) -> Result<(), io::Error> {
+        poll_to_io(with_context(|cx| self.flush_substream(cx)))
+    }
+}
+
+impl<P> AsyncWrite for SubstreamRef<P>
+where
+    P: Deref,
+    P::Target: StreamMuxer,
+{
+    #[inline]
+    fn shutdown(&mut self) -> futures::Poll<(), io::Error> {
+        match with_context(|cx| self.shutdown_substream(cx)) {
+            Poll::Ready(Ok(())) => Ok(Async::Ready(())),
+            Poll::Ready(Err(err)) => Err(err),
+            Poll::Pending => Ok(Async::NotReady)
+        }
     }
 }
 
@@ -507,8 +640,8 @@ impl StreamMuxer for StreamMuxerBox {
     type Error = io::Error;
 
     #[inline]
-    fn poll_inbound(&self) -> Poll<Self::Substream, Self::Error> {
-        self.inner.poll_inbound()
+    fn poll_inbound(&self, cx: &mut Context<'_>) -> Poll<Result<Self::Substream, Self::Error>> {
+        self.inner.poll_inbound(cx)
     }
 
     #[inline]
@@ -517,8 +650,10 @@ impl StreamMuxer for StreamMuxerBox {
     }
 
     #[inline]
-    fn poll_outbound(&self, s: &mut Self::OutboundSubstream) -> Poll<Self::Substream, Self::Error> {
-        self.inner.poll_outbound(s)
+    fn poll_outbound(&self, cx: &mut Context<'_>, s: &mut Self::OutboundSubstream)
+        -> Poll<Result<Self::Substream, Self::Error>>
+    {
+        self.inner.poll_outbound(cx, s)
     }
 
     #[inline]
@@ -526,28 +661,32 @@ impl StreamMuxer for StreamMuxerBox {
         self.inner.destroy_outbound(substream)
     }
 
-    unsafe fn prepare_uninitialized_buffer(&self, buf: &mut [u8]) -> bool {
-        self.inner.prepare_uninitialized_buffer(buf)
-    }
-
     #[inline]
-    fn read_substream(&self, s: &mut Self::Substream, buf: &mut [u8]) -> Poll<usize, Self::Error> {
-        self.inner.read_substream(s, buf)
+    fn read_substream(&self, cx: &mut Context<'_>, s: &mut Self::Substream, buf: &mut [u8])
+        -> Poll<Result<usize, Self::Error>>
+    {
+        self.inner.read_substream(cx, s, buf)
     }
 
     #[inline]
-    fn write_substream(&self, s: &mut Self::Substream, buf: &[u8]) -> Poll<usize, Self::Error> {
-        self.inner.write_substream(s, buf)
+    fn write_substream(&self, cx: &mut Context<'_>, s: &mut Self::Substream, buf: &[u8])
+        -> Poll<Result<usize, Self::Error>>
+    {
+        self.inner.write_substream(cx, s, buf)
     }
 
     #[inline]
-    fn flush_substream(&self, s: &mut Self::Substream) -> Poll<(), Self::Error> {
-        self.inner.flush_substream(s)
+    fn flush_substream(&self, cx: &mut Context<'_>, s: &mut Self::Substream)
+        -> Poll<Result<(), Self::Error>>
+    {
+        self.inner.flush_substream(cx, s)
     }
 
     #[inline]
-    fn shutdown_substream(&self, s: &mut Self::Substream) -> Poll<(), Self::Error> {
-        self.inner.shutdown_substream(s)
+    fn shutdown_substream(&self, cx: &mut Context<'_>, s: &mut Self::Substream)
+        -> Poll<Result<(), Self::Error>>
+    {
+        self.inner.shutdown_substream(cx, s)
     }
 
     #[inline]
@@ -556,8 +695,8 @@ impl StreamMuxer for StreamMuxerBox {
     }
 
     #[inline]
-    fn close(&self) -> Poll<(), Self::Error> {
-        self.inner.close()
+    fn close(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.close(cx)
     }
 
     #[inline]
@@ -566,8 +705,8 @@ impl StreamMuxer for StreamMuxerBox {
     }
 
     #[inline]
-    fn flush_all(&self) -> Poll<(), Self::Error> {
-        self.inner.flush_all()
+    fn flush_all(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.flush_all(cx)
     }
 }
 
@@ -588,11 +727,11 @@ where
     type Error = io::Error;
 
     #[inline]
-    fn poll_inbound(&self) -> Poll<Self::Substream, Self::Error> {
-        let substream = try_ready!(self.inner.poll_inbound().map_err(|e| e.into()));
+    fn poll_inbound(&self, cx: &mut Context<'_>) -> Poll<Result<Self::Substream, Self::Error>> {
+        let substream = ready!(self.inner.poll_inbound(cx).map_err(|e| e.into()))?;
         let id = self.next_substream.fetch_add(1, Ordering::Relaxed);
         self.substreams.lock().insert(id, substream);
-        Ok(Async::Ready(id))
+        Poll::Ready(Ok(id))
     }
 
     #[inline]
@@ -606,13 +745,14 @@ where
     #[inline]
     fn poll_outbound(
         &self,
+        cx: &mut Context<'_>,
         substream: &mut Self::OutboundSubstream,
-    ) -> Poll<Self::Substream, Self::Error> {
+    ) -> Poll<Result<Self::Substream, Self::Error>> {
         let mut list = self.outbound.lock();
-        let substream = try_ready!(self.inner.poll_outbound(list.get_mut(substream).unwrap()).map_err(|e| e.into()));
+        let substream = ready!(self.inner.poll_outbound(cx, list.get_mut(substream).unwrap()).map_err(|e| e.into()))?;
         let id = self.next_substream.fetch_add(1, Ordering::Relaxed);
         self.substreams.lock().insert(id, substream);
-        Ok(Async::Ready(id))
+        Poll::Ready(Ok(id))
     }
 
     #[inline]
@@ -621,32 +761,36 @@ where
         self.inner.destroy_outbound(list.remove(&substream).unwrap())
     }
 
-    unsafe fn prepare_uninitialized_buffer(&self, buf: &mut [u8]) -> bool {
-        self.inner.prepare_uninitialized_buffer(buf)
-    }
-
     #[inline]
-    fn read_substream(&self, s: &mut Self::Substream, buf: &mut [u8]) -> Poll<usize, Self::Error> {
+    fn read_substream(&self, cx: &mut Context<'_>, s: &mut Self::Substream, buf: &mut [u8])
+        -> Poll<Result<usize, Self::Error>>
+    {
         let mut list = self.substreams.lock();
-        self.inner.read_substream(list.get_mut(s).unwrap(), buf).map_err(|e| e.into())
+        self.inner.read_substream(cx, list.get_mut(s).unwrap(), buf).map_err(|e| e.into())
     }
 
     #[inline]
-    fn write_substream(&self, s: &mut Self::Substream, buf: &[u8]) -> Poll<usize, Self::Error> {
+    fn write_substream(&self, cx: &mut Context<'_>, s: &mut Self::Substream, buf: &[u8])
+        -> Poll<Result<usize, Self::Error>>
+    {
         let mut list = self.substreams.lock();
-        self.inner.write_substream(list.get_mut(s).unwrap(), buf).map_err(|e| e.into())
+        self.inner.write_substream(cx, list.get_mut(s).unwrap(), buf).map_err(|e| e.into())
     }
 
     #[inline]
-    fn flush_substream(&self, s: &mut Self::Substream) -> Poll<(), Self::Error> {
+    fn flush_substream(&self, cx: &mut Context<'_>, s: &mut Self::Substream)
+        -> Poll<Result<(), Self::Error>>
+    {
         let mut list = self.substreams.lock();
-        self.inner.flush_substream(list.get_mut(s).unwrap()).map_err(|e| e.into())
+        self.inner.flush_substream(cx, list.get_mut(s).unwrap()).map_err(|e| e.into())
     }
 
     #[inline]
-    fn shutdown_substream(&self, s: &mut Self::Substream) -> Poll<(), Self::Error> {
+    fn shutdown_substream(&self, cx: &mut Context<'_>, s: &mut Self::Substream)
+        -> Poll<Result<(), Self::Error>>
+    {
         let mut list = self.substreams.lock();
-        self.inner.shutdown_substream(list.get_mut(s).unwrap()).map_err(|e| e.into())
+        self.inner.shutdown_substream(cx, list.get_mut(s).unwrap()).map_err(|e| e.into())
     }
 
     #[inline]
@@ -656,8 +800,8 @@ where
     }
 
     #[inline]
-    fn close(&self) -> Poll<(), Self::Error> {
-        self.inner.close().map_err(|e| e.into())
+    fn close(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.close(cx).map_err(|e| e.into())
     }
 
     #[inline]
@@ -666,7 +810,7 @@ where
     }
 
     #[inline]
-    fn flush_all(&self) -> Poll<(), Self::Error> {
-        self.inner.flush_all().map_err(|e| e.into())
+    fn flush_all(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.flush_all(cx).map_err(|e| e.into())
     }
 }
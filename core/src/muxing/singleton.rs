@@ -18,7 +18,7 @@
 // FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // DEALINGS IN THE SOFTWARE.
 
-use crate::{Endpoint, muxing::StreamMuxer};
+use crate::{Endpoint, compat::StreamMuxer01};
 use futures::prelude::*;
 use parking_lot::Mutex;
 use std::{io, sync::atomic::{AtomicBool, Ordering}};
@@ -60,7 +60,7 @@ pub struct Substream {}
 /// Outbound substream attempt of the `SingletonMuxer`.
 pub struct OutboundSubstream {}
 
-impl<TSocket> StreamMuxer for SingletonMuxer<TSocket>
+impl<TSocket> StreamMuxer01 for SingletonMuxer<TSocket>
 where
     TSocket: AsyncRead + AsyncWrite,
 {
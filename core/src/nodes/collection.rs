@@ -20,15 +20,17 @@
 
 use crate::{
     PeerId,
+    executor::Executor,
     muxing::StreamMuxer,
     nodes::{
-        node::Substream,
+        node::{Substream, SubstreamLimit},
         handled_node::{HandledNodeError, IntoNodeHandler, NodeHandler},
         tasks::{self, ClosedTask, TaskEntry, TaskId}
     }
 };
 use fnv::FnvHashMap;
 use futures::prelude::*;
+use smallvec::SmallVec;
 use std::{error, fmt, hash::Hash, mem};
 
 pub use crate::nodes::tasks::StartTakeOver;
@@ -43,9 +45,10 @@ pub struct CollectionStream<TInEvent, TOutEvent, THandler, TReachErr, THandlerEr
     /// must be present in `nodes`.
     inner: tasks::Manager<TInEvent, TOutEvent, THandler, TReachErr, THandlerErr, TaskState<TConnInfo, TUserData>, TConnInfo>,
 
-    /// List of nodes, with the task id that handles this node. The corresponding entry in `tasks`
+    /// List of nodes, with the ids of the tasks that handle the connections to this node, in the
+    /// order in which the connections have been established. The corresponding entries in `tasks`
     /// must always be in the `Connected` state.
-    nodes: FnvHashMap<TPeerId, TaskId>,
+    nodes: FnvHashMap<TPeerId, SmallVec<[TaskId; 2]>>,
 }
 
 impl<TInEvent, TOutEvent, THandler, TReachErr, THandlerErr, TUserData, TConnInfo, TPeerId> fmt::Debug for
@@ -77,12 +80,16 @@ pub enum CollectionEvent<'a, TInEvent, TOutEvent, THandler, TReachErr, THandlerE
     ///
     /// Can only happen after a node has been successfully reached.
     NodeClosed {
+        /// Identifier of the connection.
+        id: ConnectionId,
         /// Information about the connection.
         conn_info: TConnInfo,
         /// The error that happened.
         error: HandledNodeError<THandlerErr>,
         /// User data that was passed when accepting.
         user_data: TUserData,
+        /// Number of connections to the same node that are still established.
+        num_established: usize,
     },
 
     /// An error happened on the future that was trying to reach a node.
@@ -102,6 +109,12 @@ pub enum CollectionEvent<'a, TInEvent, TOutEvent, THandler, TReachErr, THandlerE
         /// The produced event.
         event: TOutEvent,
     },
+
+    /// A node has refused inbound substreams because of the substream limit.
+    SubstreamLimitReached {
+        /// The node that refused the substreams.
+        peer: PeerMut<'a, TInEvent, TUserData, TConnInfo, TPeerId>,
+    },
 }
 
 impl<'a, TInEvent, TOutEvent, THandler, TReachErr, THandlerErr, TUserData, TConnInfo, TPeerId> fmt::Debug for
@@ -119,11 +132,13 @@ where TOutEvent: fmt::Debug,
                 .field(inner)
                 .finish()
             },
-            CollectionEvent::NodeClosed { ref conn_info, ref error, ref user_data } => {
+            CollectionEvent::NodeClosed { ref id, ref conn_info, ref error, ref user_data, ref num_established } => {
                 f.debug_struct("CollectionEvent::NodeClosed")
+                .field("id", id)
                 .field("conn_info", conn_info)
                 .field("user_data", user_data)
                 .field("error", error)
+                .field("num_established", num_established)
                 .finish()
             },
             CollectionEvent::ReachError { ref id, ref error, .. } => {
@@ -134,10 +149,17 @@ where TOutEvent: fmt::Debug,
             },
             CollectionEvent::NodeEvent { ref peer, ref event } => {
                 f.debug_struct("CollectionEvent::NodeEvent")
+                .field("id", &peer.connection_id())
                 .field("conn_info", peer.info())
                 .field("event", event)
                 .finish()
             },
+            CollectionEvent::SubstreamLimitReached { ref peer } => {
+                f.debug_struct("CollectionEvent::SubstreamLimitReached")
+                .field("id", &peer.connection_id())
+                .field("conn_info", peer.info())
+                .finish()
+            },
         }
     }
 }
@@ -175,6 +197,12 @@ impl<'a, TInEvent, TOutEvent, THandler, TReachErr, THandlerErr, TUserData, TConn
     pub fn reach_attempt_id(&self) -> ReachAttemptId {
         ReachAttemptId(self.id)
     }
+
+    /// Returns the identifier that the connection will have if it is accepted.
+    #[inline]
+    pub fn connection_id(&self) -> ConnectionId {
+        ConnectionId(self.id)
+    }
 }
 
 impl<'a, TInEvent, TOutEvent, THandler, TReachErr, THandlerErr, TUserData, TConnInfo, TPeerId>
@@ -183,15 +211,16 @@ where
     TConnInfo: ConnectionInfo<PeerId = TPeerId>,
     TPeerId: Eq + Hash,
 {
-    /// Returns `true` if accepting this reached node would replace an existing connection to that
-    /// node.
+    /// Returns the number of connections to the node that are already established.
     #[inline]
-    pub fn would_replace(&self) -> bool {
-        self.parent.nodes.contains_key(self.connection_info().peer_id())
+    pub fn num_established(&self) -> usize {
+        self.parent.nodes.get(self.connection_info().peer_id()).map_or(0, |ids| ids.len())
     }
 
     /// Accepts the new node.
-    pub fn accept(mut self, user_data: TUserData) -> (CollectionNodeAccept<TConnInfo, TUserData>, TConnInfo)
+    ///
+    /// The connection is added to the ones that may already exist with the same node.
+    pub fn accept(mut self, user_data: TUserData) -> (CollectionNodeAccept, TConnInfo)
     where
         // TODO: these two clones shouldn't be necessary if we return references
         TConnInfo: Clone,
@@ -201,34 +230,21 @@ where
             .expect("conn_info is always Some when the object is alive; QED");
 
         // Set the state of the task to `Connected`.
-        let former_task_id = self.parent.nodes.insert(self_conn_info.peer_id().clone(), self.id);
+        let ids = self.parent.nodes.entry(self_conn_info.peer_id().clone()).or_default();
+        let outcome = if ids.is_empty() {
+            CollectionNodeAccept::NewEntry
+        } else {
+            CollectionNodeAccept::AdditionalConnection
+        };
+        ids.push(self.id);
         *self.parent.inner.task(self.id)
             .expect("A CollectionReachEvent is only ever created from a valid attempt; QED")
             .user_data_mut() = TaskState::Connected(self_conn_info.clone(), user_data);
 
-        // It is possible that we already have a task connected to the same peer. In this
-        // case, we need to emit a `NodeReplaced` event.
-        let tasks = &mut self.parent.inner;
-        let ret_value = if let Some(former_task) = former_task_id.and_then(|i| tasks.task(i)) {
-            debug_assert!(match *former_task.user_data() {
-                TaskState::Connected(ref p, _) if p.peer_id() == self_conn_info.peer_id() => true,
-                _ => false
-            });
-            let (old_info, user_data) = match former_task.close().into_user_data() {
-                TaskState::Connected(old_info, user_data) => (old_info, user_data),
-                _ => panic!("The former task was picked from `nodes`; all the nodes in `nodes` \
-                             are always in the connected state")
-            };
-            (CollectionNodeAccept::ReplacedExisting(old_info, user_data), self_conn_info)
-
-        } else {
-            (CollectionNodeAccept::NewEntry, self_conn_info)
-        };
-
         // Don't run the destructor.
         mem::forget(self);
 
-        ret_value
+        (outcome, self_conn_info)
     }
 
     /// Denies the node.
@@ -272,18 +288,45 @@ impl<'a, TInEvent, TOutEvent, THandler, TReachErr, THandlerErr, TUserData, TConn
 
 /// Outcome of accepting a node.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
-pub enum CollectionNodeAccept<TConnInfo, TUserData> {
-    /// We replaced an existing node. Returns the information about the old connection and the
-    /// user data that was assigned to this node.
-    ReplacedExisting(TConnInfo, TUserData),
-    /// We didn't replace anything existing.
+pub enum CollectionNodeAccept {
+    /// This is the first connection to this node.
     NewEntry,
+    /// We already had at least one connection to this node. The new connection is added next
+    /// to the existing ones.
+    AdditionalConnection,
 }
 
 /// Identifier for a future that attempts to reach a node.
 #[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct ReachAttemptId(TaskId);
 
+/// Identifier of a connection to a node.
+///
+/// A connection keeps the identifier of the task that reached the node, which means that a
+/// `ReachAttemptId` and the `ConnectionId` of the resulting connection can be compared with
+/// `ReachAttemptId::connection_id`.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ConnectionId(TaskId);
+
+impl ConnectionId {
+    /// Creates a `ConnectionId` from a raw number.
+    ///
+    /// This is primarily useful in tests, in order to inject events that appear to come from a
+    /// specific connection. The identifiers of actual connections are only ever produced by the
+    /// `CollectionStream`.
+    pub fn new(id: usize) -> Self {
+        ConnectionId(TaskId(id))
+    }
+}
+
+impl ReachAttemptId {
+    /// Returns the identifier that the connection will have if the reach attempt succeeds.
+    #[inline]
+    pub fn connection_id(&self) -> ConnectionId {
+        ConnectionId(self.0)
+    }
+}
+
 /// Information about a connection.
 pub trait ConnectionInfo {
     /// Identity of the node we are connected to.
@@ -316,6 +359,20 @@ where
         }
     }
 
+    /// Creates a new empty collection whose tasks are spawned with the given executor.
+    #[inline]
+    pub fn with_executor(executor: Box<dyn Executor + Send>) -> Self {
+        CollectionStream {
+            inner: tasks::Manager::with_executor(executor),
+            nodes: Default::default(),
+        }
+    }
+
+    /// Sets the limit on the substreams that are open over all the nodes added afterwards.
+    pub(crate) fn set_substream_limit(&mut self, limit: Option<SubstreamLimit>) {
+        self.inner.set_substream_limit(limit);
+    }
+
     /// Adds to the collection a future that tries to reach a remote.
     ///
     /// This method spawns a task dedicated to resolving this future and processing the node's
@@ -373,9 +430,10 @@ where
 
     /// Adds an existing connection to a node to the collection.
     ///
-    /// Returns whether we have replaced an existing connection, or not.
+    /// Returns whether this is the first connection to this node, and the identifier of the new
+    /// connection.
     pub fn add_connection<TMuxer>(&mut self, conn_info: TConnInfo, user_data: TUserData, muxer: TMuxer, handler: THandler::Handler)
-        -> CollectionNodeAccept<TConnInfo, TUserData>
+        -> (CollectionNodeAccept, ConnectionId)
     where
         THandler: IntoNodeHandler<TConnInfo> + Send + 'static,
         THandler::Handler: NodeHandler<Substream = Substream<TMuxer>, InEvent = TInEvent, OutEvent = TOutEvent, Error = THandlerErr> + Send + 'static,
@@ -400,32 +458,52 @@ where
             handler
         );
 
-        CollectionReachEvent {
+        let outcome = CollectionReachEvent {
             conn_info: Some(conn_info),
             id: task_id,
             parent: self,
-        }.accept(user_data).0
+        }.accept(user_data).0;
+
+        (outcome, ConnectionId(task_id))
     }
 
-    /// Grants access to an object that allows controlling a peer of the collection.
+    /// Grants access to an object that allows controlling the oldest connection to a peer of the
+    /// collection.
     ///
     /// Returns `None` if we don't have a connection to this peer.
     #[inline]
     pub fn peer_mut(&mut self, id: &TPeerId) -> Option<PeerMut<'_, TInEvent, TUserData, TConnInfo, TPeerId>> {
-        let task = match self.nodes.get(id) {
+        let task = match self.nodes.get(id).and_then(|ids| ids.first()) {
             Some(&task) => task,
             None => return None,
         };
 
-        match self.inner.task(task) {
-            Some(inner) => Some(PeerMut {
-                inner,
-                nodes: &mut self.nodes,
-            }),
+        self.connection_mut(ConnectionId(task))
+    }
+
+    /// Grants access to an object that allows controlling a connection of the collection.
+    ///
+    /// Returns `None` if the connection doesn't exist or is not established yet.
+    pub fn connection_mut(&mut self, id: ConnectionId) -> Option<PeerMut<'_, TInEvent, TUserData, TConnInfo, TPeerId>> {
+        match self.inner.task(id.0) {
+            Some(inner) => {
+                if let TaskState::Pending = inner.user_data() {
+                    return None;
+                }
+                Some(PeerMut {
+                    inner,
+                    nodes: &mut self.nodes,
+                })
+            }
             None => None,
         }
     }
 
+    /// Returns the established connections to the given peer, from the oldest to the newest.
+    pub fn peer_connections(&self, id: &TPeerId) -> impl Iterator<Item = ConnectionId> + '_ {
+        self.nodes.get(id).into_iter().flat_map(|ids| ids.iter().map(|id| ConnectionId(*id)))
+    }
+
     /// Returns true if we are connected to the given peer.
     ///
     /// This will return true only after a `NodeReached` event has been produced by `poll()`.
@@ -480,12 +558,13 @@ where
                     },
                     (TaskState::Connected(conn_info, user_data), tasks::Error::Node(err), _handler) => {
                         debug_assert!(_handler.is_none());
-                        let _node_task_id = self.nodes.remove(conn_info.peer_id());
-                        debug_assert_eq!(_node_task_id, Some(id));
+                        let num_established = remove_connection(&mut self.nodes, conn_info.peer_id(), id);
                         Async::Ready(CollectionEvent::NodeClosed {
+                            id: ConnectionId(id),
                             conn_info,
                             error: err,
                             user_data,
+                            num_established,
                         })
                     },
                     (TaskState::Connected(_, _), tasks::Error::Reach(_), _) => {
@@ -504,28 +583,56 @@ where
                 }))
             },
             tasks::Event::NodeEvent { task, event } => {
-                let conn_info = match task.user_data() {
-                    TaskState::Connected(conn_info, _) => conn_info.clone(),
-                    _ => panic!("we can only receive NodeEvent events from a task after we \
-                                 received a corresponding NodeReached event from that same task; \
-                                 when we receive a NodeReached event, we ensure that the entry in \
-                                 self.tasks is switched to the Connected state; QED"),
-                };
+                let id = task.id();
                 drop(task);
                 Async::Ready(CollectionEvent::NodeEvent {
                     // TODO: normally we'd build a `PeerMut` manually here, but the borrow checker
                     //       doesn't like it
-                    peer: self.peer_mut(&conn_info.peer_id())
+                    peer: self.connection_mut(ConnectionId(id))
                         .expect("we can only receive NodeEvent events from a task after we \
-                                 received a corresponding NodeReached event from that same task;\
-                                 when that happens, peer_mut will always return Some; QED"),
+                                 received a corresponding NodeReached event from that same task; \
+                                 when we receive a NodeReached event, we ensure that the entry in \
+                                 self.tasks is switched to the Connected state; QED"),
                     event,
                 })
             }
+            tasks::Event::SubstreamLimitReached { task } => {
+                let id = task.id();
+                drop(task);
+                Async::Ready(CollectionEvent::SubstreamLimitReached {
+                    peer: self.connection_mut(ConnectionId(id))
+                        .expect("a task can only refuse substreams once its node has been \
+                                 reached, and the entry in self.tasks is then switched to the \
+                                 Connected state; QED"),
+                })
+            }
         }
     }
 }
 
+/// Removes a connection from the list of connections to a node. Returns the number of
+/// connections to this node that remain.
+fn remove_connection<TPeerId>(nodes: &mut FnvHashMap<TPeerId, SmallVec<[TaskId; 2]>>, peer_id: &TPeerId, id: TaskId)
+    -> usize
+where
+    TPeerId: Eq + Hash,
+{
+    debug_assert!(nodes.get(peer_id).map_or(false, |ids| ids.contains(&id)));
+    let remaining = match nodes.get_mut(peer_id) {
+        Some(ids) => {
+            ids.retain(|i| *i != id);
+            ids.len()
+        }
+        None => return 0,
+    };
+
+    if remaining == 0 {
+        nodes.remove(peer_id);
+    }
+
+    remaining
+}
+
 /// Reach attempt interrupt errors.
 #[derive(Debug)]
 pub enum InterruptError {
@@ -569,13 +676,18 @@ where
     }
 }
 
-/// Access to a peer in the collection.
+/// Access to a connection to a peer in the collection.
 pub struct PeerMut<'a, TInEvent, TUserData, TConnInfo = PeerId, TPeerId = PeerId> {
     inner: TaskEntry<'a, TInEvent, TaskState<TConnInfo, TUserData>>,
-    nodes: &'a mut FnvHashMap<TPeerId, TaskId>,
+    nodes: &'a mut FnvHashMap<TPeerId, SmallVec<[TaskId; 2]>>,
 }
 
 impl<'a, TInEvent, TUserData, TConnInfo, TPeerId> PeerMut<'a, TInEvent, TUserData, TConnInfo, TPeerId> {
+    /// Returns the identifier of the connection.
+    pub fn connection_id(&self) -> ConnectionId {
+        ConnectionId(self.inner.id())
+    }
+
     /// Returns the information of the connection with the peer.
     // TODO: we would love to return a `&'a TConnInfo`, but this isn't possible because we have
     //       a mutable borrow.
@@ -626,14 +738,14 @@ where
         self.inner.complete_send_event()
     }
 
-    /// Closes the connections to this node. Returns the user data.
+    /// Closes this connection to the node. Returns the user data.
     ///
-    /// No further event will be generated for this node.
+    /// No further event will be generated for this connection. The other connections to the same
+    /// node, if any, are not affected.
     pub fn close(self) -> TUserData {
         let task_id = self.inner.id();
         if let TaskState::Connected(conn_info, user_data) = self.inner.close().into_user_data() {
-            let old_task_id = self.nodes.remove(conn_info.peer_id());
-            debug_assert_eq!(old_task_id, Some(task_id));
+            remove_connection(self.nodes, conn_info.peer_id(), task_id);
             user_data
         } else {
             panic!("a PeerMut can only be created if an entry is present in nodes; an entry in \
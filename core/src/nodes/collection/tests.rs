@@ -123,6 +123,32 @@ fn accepting_a_node_yields_new_entry() {
     rt.block_on(fut).expect("running the future works");
 }
 
+#[test]
+fn multiple_connections_to_the_same_peer_are_kept() {
+    let mut cs = TestCollectionStream::new();
+    let peer_id = PeerId::random();
+
+    let (first, first_id) = cs.add_connection(peer_id.clone(), (), DummyMuxer::new(), Handler::default());
+    assert_eq!(first, CollectionNodeAccept::NewEntry);
+    let (second, second_id) = cs.add_connection(peer_id.clone(), (), DummyMuxer::new(), Handler::default());
+    assert_eq!(second, CollectionNodeAccept::AdditionalConnection);
+    assert_ne!(first_id, second_id);
+
+    assert_eq!(cs.peer_connections(&peer_id).collect::<Vec<_>>(), vec![first_id, second_id]);
+    assert_eq!(cs.peer_mut(&peer_id).unwrap().connection_id(), first_id);
+    assert_eq!(cs.connections().count(), 1);
+
+    // Closing one connection leaves the other one untouched.
+    cs.connection_mut(first_id).expect("connection exists").close();
+    assert!(cs.has_connection(&peer_id));
+    assert!(cs.connection_mut(first_id).is_none());
+    assert_eq!(cs.peer_connections(&peer_id).collect::<Vec<_>>(), vec![second_id]);
+
+    cs.connection_mut(second_id).expect("connection exists").close();
+    assert!(!cs.has_connection(&peer_id));
+    assert!(cs.peer_mut(&peer_id).is_none());
+}
+
 #[test]
 fn events_in_a_node_reaches_the_collection_stream() {
     let cs = Arc::new(Mutex::new(TestCollectionStream::new()));
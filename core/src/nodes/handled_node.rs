@@ -19,7 +19,7 @@
 // DEALINGS IN THE SOFTWARE.
 
 use crate::{PeerId, muxing::StreamMuxer};
-use crate::nodes::node::{NodeEvent, NodeStream, Substream, Close};
+use crate::nodes::node::{NodeEvent, NodeStream, Substream, SubstreamLimit, Close};
 use futures::prelude::*;
 use std::{error, fmt, io};
 
@@ -185,6 +185,17 @@ where
         }
     }
 
+    /// Sets the limit on open substreams. See `NodeStream::set_substream_limit`.
+    pub(crate) fn set_substream_limit(&mut self, limit: Option<SubstreamLimit>) {
+        self.node.set_substream_limit(limit);
+    }
+
+    /// Returns `true` if an inbound substream has been refused because of the substream limit
+    /// since the last call to this method.
+    pub(crate) fn take_substream_limit_reached(&mut self) -> bool {
+        self.node.take_substream_limit_reached()
+    }
+
     /// Returns a reference to the `NodeHandler`
     pub fn handler(&self) -> &THandler {
         &self.handler
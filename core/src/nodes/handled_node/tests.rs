@@ -24,6 +24,7 @@ use super::*;
 use assert_matches::assert_matches;
 use crate::tests::dummy_muxer::{DummyMuxer, DummyConnectionState};
 use crate::tests::dummy_handler::{Handler, HandlerState, InEvent, OutEvent, TestHandledNode};
+use tokio_mock_task::MockTask;
 
 struct TestBuilder {
     muxer: DummyMuxer,
@@ -89,82 +90,100 @@ fn can_inject_event() {
 
 #[test]
 fn poll_with_unready_node_stream_and_handler_emits_custom_event() {
-    let expected_event = NodeHandlerEvent::Custom(OutEvent::Custom("pineapple"));
-    let mut handled = TestBuilder::new()
-        // make NodeStream return NotReady
-        .with_muxer_inbound_state(DummyConnectionState::Pending)
-        // make Handler return return Ready(Some(…))
-        .with_handler_state(HandlerState::Ready(expected_event))
-        .handled_node();
-
-    assert_matches!(handled.poll(), Ok(Async::Ready(event)) => {
-        assert_matches!(event, OutEvent::Custom("pineapple"))
+    let mut task = MockTask::new();
+    task.enter(|| {
+        let expected_event = NodeHandlerEvent::Custom(OutEvent::Custom("pineapple"));
+        let mut handled = TestBuilder::new()
+            // make NodeStream return NotReady
+            .with_muxer_inbound_state(DummyConnectionState::Pending)
+            // make Handler return return Ready(Some(…))
+            .with_handler_state(HandlerState::Ready(expected_event))
+            .handled_node();
+
+        assert_matches!(handled.poll(), Ok(Async::Ready(event)) => {
+            assert_matches!(event, OutEvent::Custom("pineapple"))
+        });
     });
 }
 
 #[test]
 fn handler_emits_outbound_closed_when_opening_new_substream_on_closed_node() {
-    let open_event = NodeHandlerEvent::OutboundSubstreamRequest(456);
-    let mut handled = TestBuilder::new()
-        .with_muxer_inbound_state(DummyConnectionState::Pending)
-        .with_muxer_outbound_state(DummyConnectionState::Pending)
-        .with_handler_state(HandlerState::Ready(open_event))
-        .handled_node();
-
-    set_next_handler_outbound_state(
-        &mut handled,
-        HandlerState::Ready(NodeHandlerEvent::Custom(OutEvent::Custom("pear")))
-    );
-    handled.poll().expect("poll works");
+    let mut task = MockTask::new();
+    task.enter(|| {
+        let open_event = NodeHandlerEvent::OutboundSubstreamRequest(456);
+        let mut handled = TestBuilder::new()
+            .with_muxer_inbound_state(DummyConnectionState::Pending)
+            .with_muxer_outbound_state(DummyConnectionState::Pending)
+            .with_handler_state(HandlerState::Ready(open_event))
+            .handled_node();
+
+        set_next_handler_outbound_state(
+            &mut handled,
+            HandlerState::Ready(NodeHandlerEvent::Custom(OutEvent::Custom("pear")))
+        );
+        handled.poll().expect("poll works");
+    });
 }
 
 #[test]
 fn poll_yields_inbound_closed_event() {
-    let mut h = TestBuilder::new()
-        .with_muxer_inbound_state(DummyConnectionState::Pending)
-        .with_handler_state(HandlerState::Err) // stop the loop
-        .handled_node();
-
-    assert_eq!(h.handler().events, vec![]);
-    let _ = h.poll();
+    let mut task = MockTask::new();
+    task.enter(|| {
+        let mut h = TestBuilder::new()
+            .with_muxer_inbound_state(DummyConnectionState::Pending)
+            .with_handler_state(HandlerState::Err) // stop the loop
+            .handled_node();
+
+        assert_eq!(h.handler().events, vec![]);
+        let _ = h.poll();
+    });
 }
 
 #[test]
 fn poll_yields_outbound_closed_event() {
-    let mut h = TestBuilder::new()
-        .with_muxer_inbound_state(DummyConnectionState::Pending)
-        .with_open_substream(32)
-        .with_muxer_outbound_state(DummyConnectionState::Pending)
-        .with_handler_state(HandlerState::Err) // stop the loop
-        .handled_node();
-
-    assert_eq!(h.handler().events, vec![]);
-    let _ = h.poll();
+    let mut task = MockTask::new();
+    task.enter(|| {
+        let mut h = TestBuilder::new()
+            .with_muxer_inbound_state(DummyConnectionState::Pending)
+            .with_open_substream(32)
+            .with_muxer_outbound_state(DummyConnectionState::Pending)
+            .with_handler_state(HandlerState::Err) // stop the loop
+            .handled_node();
+
+        assert_eq!(h.handler().events, vec![]);
+        let _ = h.poll();
+    });
 }
 
 #[test]
 fn poll_yields_outbound_substream() {
-    let mut h = TestBuilder::new()
-        .with_muxer_inbound_state(DummyConnectionState::Pending)
-        .with_muxer_outbound_state(DummyConnectionState::Opened)
-        .with_open_substream(1)
-        .with_handler_state(HandlerState::Err) // stop the loop
-        .handled_node();
-
-    assert_eq!(h.handler().events, vec![]);
-    let _ = h.poll();
-    assert_eq!(h.handler().events, vec![InEvent::Substream(Some(1))]);
+    let mut task = MockTask::new();
+    task.enter(|| {
+        let mut h = TestBuilder::new()
+            .with_muxer_inbound_state(DummyConnectionState::Pending)
+            .with_muxer_outbound_state(DummyConnectionState::Opened)
+            .with_open_substream(1)
+            .with_handler_state(HandlerState::Err) // stop the loop
+            .handled_node();
+
+        assert_eq!(h.handler().events, vec![]);
+        let _ = h.poll();
+        assert_eq!(h.handler().events, vec![InEvent::Substream(Some(1))]);
+    });
 }
 
 #[test]
 fn poll_yields_inbound_substream() {
-    let mut h = TestBuilder::new()
-        .with_muxer_inbound_state(DummyConnectionState::Opened)
-        .with_muxer_outbound_state(DummyConnectionState::Pending)
-        .with_handler_state(HandlerState::Err) // stop the loop
-        .handled_node();
-
-    assert_eq!(h.handler().events, vec![]);
-    let _ = h.poll();
-    assert_eq!(h.handler().events, vec![InEvent::Substream(None)]);
+    let mut task = MockTask::new();
+    task.enter(|| {
+        let mut h = TestBuilder::new()
+            .with_muxer_inbound_state(DummyConnectionState::Opened)
+            .with_muxer_outbound_state(DummyConnectionState::Pending)
+            .with_handler_state(HandlerState::Err) // stop the loop
+            .handled_node();
+
+        assert_eq!(h.handler().events, vec![]);
+        let _ = h.poll();
+        assert_eq!(h.handler().events, vec![InEvent::Substream(None)]);
+    });
 }
@@ -20,10 +20,11 @@
 
 //! Manage listening on multiple multiaddresses at once.
 
-use crate::{Multiaddr, Transport, transport::{TransportError, ListenerEvent}};
+use crate::{Multiaddr, Transport, compat::with_context, transport::{TransportError, ListenerEvent}};
 use futures::prelude::*;
+use futures03::{Stream as _, task::Poll as Poll03};
 use smallvec::SmallVec;
-use std::{collections::VecDeque, fmt};
+use std::{collections::VecDeque, fmt, pin::Pin};
 use void::Void;
 
 /// Implementation of `futures::Stream` that allows listening on multiaddresses.
@@ -93,7 +94,7 @@ where
     TTrans: Transport,
 {
     /// The object that actually listens.
-    listener: TTrans::Listener,
+    listener: Pin<Box<TTrans::Listener>>,
     /// Addresses it is listening on.
     addresses: SmallVec<[Multiaddr; 4]>
 }
@@ -125,9 +126,9 @@ where
     /// A listener has closed, either gracefully or with an error.
     Closed {
         /// The listener that closed.
-        listener: TTrans::Listener,
+        listener: Pin<Box<TTrans::Listener>>,
         /// The error that happened. `Ok` if gracefully closed.
-        result: Result<(), <TTrans::Listener as Stream>::Error>,
+        result: Result<(), TTrans::Error>,
     },
 }
 
@@ -161,7 +162,7 @@ where
     where
         TTrans: Clone,
     {
-        let listener = self.transport.clone().listen_on(addr)?;
+        let listener = Box::pin(self.transport.clone().listen_on(addr)?);
         self.listeners.push_back(Listener { listener, addresses: SmallVec::new() });
         Ok(())
     }
@@ -181,13 +182,13 @@ where
         // We remove each element from `listeners` one by one and add them back.
         let mut remaining = self.listeners.len();
         while let Some(mut listener) = self.listeners.pop_back() {
-            match listener.listener.poll() {
-                Ok(Async::NotReady) => {
+            match with_context(|cx| listener.listener.as_mut().poll_next(cx)) {
+                Poll03::Pending => {
                     self.listeners.push_front(listener);
                     remaining -= 1;
                     if remaining == 0 { break }
                 }
-                Ok(Async::Ready(Some(ListenerEvent::Upgrade { upgrade, listen_addr, remote_addr }))) => {
+                Poll03::Ready(Some(Ok(ListenerEvent::Upgrade { upgrade, listen_addr, remote_addr }))) => {
                     debug_assert!(listener.addresses.contains(&listen_addr),
                         "Transport reported listen address {} not in the list: {:?}",
                         listen_addr, listener.addresses);
@@ -198,7 +199,7 @@ where
                         send_back_addr: remote_addr
                     })
                 }
-                Ok(Async::Ready(Some(ListenerEvent::NewAddress(a)))) => {
+                Poll03::Ready(Some(Ok(ListenerEvent::NewAddress(a)))) => {
                     debug_assert!(!listener.addresses.contains(&a),
                         "Transport has reported address {} multiple times", a);
                     if !listener.addresses.contains(&a) {
@@ -207,18 +208,18 @@ where
                     self.listeners.push_front(listener);
                     return Async::Ready(ListenersEvent::NewAddress { listen_addr: a })
                 }
-                Ok(Async::Ready(Some(ListenerEvent::AddressExpired(a)))) => {
+                Poll03::Ready(Some(Ok(ListenerEvent::AddressExpired(a)))) => {
                     listener.addresses.retain(|x| x != &a);
                     self.listeners.push_front(listener);
                     return Async::Ready(ListenersEvent::AddressExpired { listen_addr: a })
                 }
-                Ok(Async::Ready(None)) => {
+                Poll03::Ready(None) => {
                     return Async::Ready(ListenersEvent::Closed {
                         listener: listener.listener,
                         result: Ok(()),
                     })
                 }
-                Err(err) => {
+                Poll03::Ready(Some(Err(err))) => {
                     return Async::Ready(ListenersEvent::Closed {
                         listener: listener.listener,
                         result: Err(err),
@@ -260,7 +261,7 @@ where
 impl<TTrans> fmt::Debug for ListenersEvent<TTrans>
 where
     TTrans: Transport,
-    <TTrans::Listener as Stream>::Error: fmt::Debug,
+    TTrans::Error: fmt::Debug,
 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
         match self {
@@ -287,9 +288,11 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::compat::{Compat01As03Listener, Compat03As01, Transport01};
     use crate::transport::{self, ListenerEvent};
     use assert_matches::assert_matches;
     use tokio::runtime::current_thread::Runtime;
+    use tokio_mock_task::MockTask;
     use std::{io, iter::FromIterator};
     use futures::{future::{self}, stream};
     use crate::tests::dummy_transport::{DummyTransport, ListenerState};
@@ -297,7 +300,7 @@ mod tests {
     use crate::PeerId;
 
     fn set_listener_state(ls: &mut ListenersStream<DummyTransport>, idx: usize, state: ListenerState) {
-        ls.listeners[idx].listener = match state {
+        let listener: <DummyTransport as Transport01>::Listener = match state {
             ListenerState::Error =>
                 Box::new(stream::poll_fn(|| Err(io::Error::new(io::ErrorKind::Other, "oh noes")))),
             ListenerState::Ok(state) => match state {
@@ -310,6 +313,7 @@ mod tests {
             ListenerState::Events(events) =>
                 Box::new(stream::iter_ok(events.into_iter().map(|e| e.map(future::ok))))
         };
+        ls.listeners[idx].listener = Box::pin(Compat01As03Listener::new(listener));
     }
 
     #[test]
@@ -338,12 +342,12 @@ mod tests {
                     Some(ListenersEvent::Incoming { listen_addr, upgrade, send_back_addr, .. }) => {
                         assert_eq!(listen_addr, address);
                         assert_eq!(send_back_addr, address);
-                        upgrade.map(|_| ()).map_err(|_| panic!())
+                        Compat03As01::new(upgrade).map(|_| ()).map_err(|_| panic!())
                     },
                     _ => panic!()
                 }
             })
-            .select(dial.map(|_| ()).map_err(|_| panic!()))
+            .select(Compat03As01::new(dial).map(|_| ()).map_err(|_| panic!()))
             .map_err(|(err, _)| err);
 
         let mut runtime = Runtime::new().unwrap();
@@ -389,13 +393,16 @@ mod tests {
 
     #[test]
     fn listener_stream_poll_with_listeners_that_arent_ready_is_not_ready() {
-        let t = DummyTransport::new();
-        let addr = tcp4([127, 0, 0, 1], 1234);
-        let mut ls = ListenersStream::new(t);
-        ls.listen_on(addr).expect("listen_on failed");
-        set_listener_state(&mut ls, 0, ListenerState::Ok(Async::NotReady));
-        assert_matches!(ls.poll(), Async::NotReady);
-        assert_eq!(ls.listeners.len(), 1); // listener is still there
+        let mut task = MockTask::new();
+        task.enter(|| {
+            let t = DummyTransport::new();
+            let addr = tcp4([127, 0, 0, 1], 1234);
+            let mut ls = ListenersStream::new(t);
+            ls.listen_on(addr).expect("listen_on failed");
+            set_listener_state(&mut ls, 0, ListenerState::Ok(Async::NotReady));
+            assert_matches!(ls.poll(), Async::NotReady);
+            assert_eq!(ls.listeners.len(), 1); // listener is still there
+        });
     }
 
     #[test]
@@ -439,7 +446,7 @@ mod tests {
 
         assert_matches!(ls.by_ref().wait().next(), Some(Ok(listeners_event)) => {
             assert_matches!(listeners_event, ListenersEvent::Incoming { upgrade, .. } => {
-                assert_matches!(upgrade.wait(), Ok(output) => {
+                assert_matches!(futures03::executor::block_on(upgrade), Ok(output) => {
                     assert_eq!(output, expected_output)
                 });
             })
@@ -447,7 +454,7 @@ mod tests {
 
         assert_matches!(ls.by_ref().wait().next(), Some(Ok(listeners_event)) => {
             assert_matches!(listeners_event, ListenersEvent::Incoming { upgrade, .. } => {
-                assert_matches!(upgrade.wait(), Ok(output) => {
+                assert_matches!(futures03::executor::block_on(upgrade), Ok(output) => {
                     assert_eq!(output, expected_output)
                 });
             })
@@ -457,7 +464,7 @@ mod tests {
 
         assert_matches!(ls.by_ref().wait().next(), Some(Ok(listeners_event)) => {
             assert_matches!(listeners_event, ListenersEvent::Incoming { upgrade, .. } => {
-                assert_matches!(upgrade.wait(), Ok(output) => {
+                assert_matches!(futures03::executor::block_on(upgrade), Ok(output) => {
                     assert_eq!(output, expected_output)
                 });
             })
@@ -20,28 +20,30 @@
 
 use crate::muxing::StreamMuxer;
 use crate::{
-    ConnectedPoint, Multiaddr, PeerId, address_translation,
+    ConnectedPoint, Endpoint, Multiaddr, PeerId, address_translation,
+    compat::Compat03As01,
+    executor::{Executor, TokioExecutor},
     nodes::{
         collection::{
             CollectionEvent,
             CollectionNodeAccept,
             CollectionReachEvent,
             CollectionStream,
+            ConnectionId,
             ConnectionInfo,
-            ReachAttemptId,
-            InterruptedReachAttempt
+            ReachAttemptId
         },
         handled_node::{
             HandledNodeError,
             NodeHandler
         },
         handled_node::IntoNodeHandler,
-        node::Substream
+        node::{Substream, SubstreamLimit}
     },
     nodes::listeners::{ListenersEvent, ListenersStream},
     transport::{Transport, TransportError}
 };
-use fnv::FnvHashMap;
+use fnv::{FnvHashMap, FnvHashSet};
 use futures::{prelude::*, future};
 use std::{
     collections::hash_map::{Entry, OccupiedEntry},
@@ -49,10 +51,13 @@ use std::{
     fmt,
     hash::Hash,
     num::NonZeroUsize,
+    pin::Pin,
 };
 
 pub use crate::nodes::collection::StartTakeOver;
+pub use self::limits::{ConnectionLimit, ConnectionLimitKind, ConnectionLimits};
 
+mod limits;
 mod tests;
 
 /// Implementation of `Stream` that handles the nodes.
@@ -70,18 +75,8 @@ where
     /// This needs to be a separate struct in order to handle multiple mutable borrows issues.
     reach_attempts: ReachAttempts<TPeerId>,
 
-    /// Max numer of incoming connections.
-    incoming_limit: Option<u32>,
-
-    /// Unfinished take over message to be delivered.
-    ///
-    /// If the pair's second element is `AsyncSink::NotReady`, the take over
-    /// message has yet to be sent using `PeerMut::start_take_over`.
-    ///
-    /// If the pair's second element is `AsyncSink::Ready`, the take over
-    /// message has been sent and needs to be flushed using
-    /// `PeerMut::complete_take_over`.
-    take_over_to_complete: Option<(TPeerId, AsyncSink<InterruptedReachAttempt<TInEvent, (TConnInfo, ConnectedPoint), ()>>)>
+    /// Limits on the number of pending and established connections.
+    limits: ConnectionLimits,
 }
 
 impl<TTrans, TInEvent, TOutEvent, THandler, THandlerErr, TConnInfo, TPeerId> fmt::Debug for
@@ -96,8 +91,7 @@ where
             .field("listeners", &self.listeners)
             .field("active_nodes", &self.active_nodes)
             .field("reach_attempts", &self.reach_attempts)
-            .field("incoming_limit", &self.incoming_limit)
-            .field("take_over_to_complete", &self.take_over_to_complete)
+            .field("limits", &self.limits)
             .finish()
     }
 }
@@ -125,9 +119,14 @@ struct ReachAttempts<TPeerId> {
     /// the peer ID.
     other_reach_attempts: Vec<(ReachAttemptId, ConnectedPoint)>,
 
-    /// For each peer ID we're connected to, contains the endpoint we're connected to.
+    /// For each established connection, contains the endpoint we're connected to.
     /// Always in sync with `active_nodes`.
-    connected_points: FnvHashMap<TPeerId, ConnectedPoint>,
+    connected_points: FnvHashMap<ConnectionId, ConnectedPoint>,
+
+    /// Outgoing reach attempts that have been refused because of the connection limits. They are
+    /// present in `out_reach_attempts` or `other_reach_attempts` until the error is reported, but
+    /// don't count as pending connections.
+    denied_attempts: FnvHashSet<ReachAttemptId>,
 }
 
 impl<TPeerId> fmt::Debug for ReachAttempts<TPeerId>
@@ -140,6 +139,7 @@ where
             .field("out_reach_attempts", &self.out_reach_attempts)
             .field("other_reach_attempts", &self.other_reach_attempts)
             .field("connected_points", &self.connected_points)
+            .field("denied_attempts", &self.denied_attempts)
             .finish()
     }
 }
@@ -163,9 +163,9 @@ where
     /// One of the listeners gracefully closed.
     ListenerClosed {
         /// The listener which closed.
-        listener: TTrans::Listener,
+        listener: Pin<Box<TTrans::Listener>>,
         /// The error that happened. `Ok` if gracefully closed.
-        result: Result<(), <TTrans::Listener as Stream>::Error>,
+        result: Result<(), TTrans::Error>,
     },
 
     /// One of the listeners is now listening on an additional address.
@@ -197,37 +197,33 @@ where
     },
 
     /// A new connection to a peer has been opened.
+    ///
+    /// Other connections to the same peer may already be open, in which case they are kept
+    /// alongside this one.
     Connected {
         /// Information about the connection, including the peer ID.
         conn_info: TConnInfo,
+        /// Identifier of the new connection.
+        connection_id: ConnectionId,
         /// If `Listener`, then we received the connection. If `Dial`, then it's a connection that
         /// we opened.
         endpoint: ConnectedPoint,
-    },
-
-    /// A connection to a peer has been replaced with a new one.
-    Replaced {
-        /// Information about the new connection. The `TPeerId` is the same as the one as the one
-        /// in `old_info`.
-        new_info: TConnInfo,
-        /// Information about the old connection. The `TPeerId` is the same as the one as the one
-        /// in `new_info`.
-        old_info: TConnInfo,
-        /// Endpoint we were connected to.
-        closed_endpoint: ConnectedPoint,
-        /// If `Listener`, then we received the connection. If `Dial`, then it's a connection that
-        /// we opened.
-        endpoint: ConnectedPoint,
+        /// Number of connections to this peer that are now established, including this one.
+        num_established: NonZeroUsize,
     },
 
     /// The handler of a node has produced an error.
     NodeClosed {
         /// Information about the connection that has been closed.
         conn_info: TConnInfo,
+        /// Identifier of the connection that has been closed.
+        connection_id: ConnectionId,
         /// Endpoint we were connected to.
         endpoint: ConnectedPoint,
         /// The error that happened.
         error: HandledNodeError<THandlerErr>,
+        /// Number of other connections to this peer that are still established.
+        num_established: usize,
     },
 
     /// Failed to reach a peer that we were trying to dial.
@@ -261,9 +257,26 @@ where
     NodeEvent {
         /// Connection that produced the event.
         conn_info: TConnInfo,
+        /// Identifier of the connection that produced the event.
+        connection_id: ConnectionId,
         /// Event that was produced by the node.
         event: TOutEvent,
     },
+
+    /// A connection has been refused or closed because one of the `ConnectionLimits` was
+    /// reached, or inbound substreams of a connection have been refused.
+    ///
+    /// If the connection was an outgoing attempt to a known peer, the remaining addresses of
+    /// this attempt are not tried. For the `Substreams` limit, the connection is still open and
+    /// `endpoint` is its endpoint.
+    ConnectionLimitReached {
+        /// The limit that was reached.
+        limit: ConnectionLimit,
+        /// The peer of the connection, if known.
+        peer_id: Option<TPeerId>,
+        /// Endpoint of the refused connection.
+        endpoint: ConnectedPoint,
+    },
 }
 
 impl<'a, TTrans, TInEvent, TOutEvent, THandler, THandlerErr, TConnInfo, TPeerId> fmt::Debug for
@@ -310,25 +323,21 @@ where
                     .field("error", error)
                     .finish()
             }
-            NetworkEvent::Connected { ref conn_info, ref endpoint } => {
+            NetworkEvent::Connected { ref conn_info, ref connection_id, ref endpoint, ref num_established } => {
                 f.debug_struct("Connected")
                     .field("conn_info", conn_info)
+                    .field("connection_id", connection_id)
                     .field("endpoint", endpoint)
+                    .field("num_established", num_established)
                     .finish()
             }
-            NetworkEvent::Replaced { ref new_info, ref old_info, ref closed_endpoint, ref endpoint } => {
-                f.debug_struct("Replaced")
-                    .field("new_info", new_info)
-                    .field("old_info", old_info)
-                    .field("closed_endpoint", closed_endpoint)
-                    .field("endpoint", endpoint)
-                    .finish()
-            }
-            NetworkEvent::NodeClosed { ref conn_info, ref endpoint, ref error } => {
+            NetworkEvent::NodeClosed { ref conn_info, ref connection_id, ref endpoint, ref error, ref num_established } => {
                 f.debug_struct("NodeClosed")
                     .field("conn_info", conn_info)
+                    .field("connection_id", connection_id)
                     .field("endpoint", endpoint)
                     .field("error", error)
+                    .field("num_established", num_established)
                     .finish()
             }
             NetworkEvent::DialError { ref new_state, ref peer_id, ref multiaddr, ref error } => {
@@ -345,12 +354,20 @@ where
                     .field("error", error)
                     .finish()
             }
-            NetworkEvent::NodeEvent { ref conn_info, ref event } => {
+            NetworkEvent::NodeEvent { ref conn_info, ref connection_id, ref event } => {
                 f.debug_struct("NodeEvent")
                     .field("conn_info", conn_info)
+                    .field("connection_id", connection_id)
                     .field("event", event)
                     .finish()
             }
+            NetworkEvent::ConnectionLimitReached { ref limit, ref peer_id, ref endpoint } => {
+                f.debug_struct("ConnectionLimitReached")
+                    .field("limit", limit)
+                    .field("peer_id", peer_id)
+                    .field("endpoint", endpoint)
+                    .finish()
+            }
         }
     }
 }
@@ -368,6 +385,8 @@ enum InternalReachErr<TTransErr, TConnInfo> {
     },
     /// The negotiated `PeerId` is the same as the one of the local node.
     FoundLocalPeerId,
+    /// The attempt was refused because of the connection limits.
+    ConnectionLimit(ConnectionLimit),
 }
 
 impl<TTransErr, TConnInfo> fmt::Display for InternalReachErr<TTransErr, TConnInfo>
@@ -384,6 +403,7 @@ where
             InternalReachErr::FoundLocalPeerId => {
                 write!(f, "Remote has the same PeerId as us")
             }
+            InternalReachErr::ConnectionLimit(limit) => write!(f, "{}", limit),
         }
     }
 }
@@ -398,6 +418,7 @@ where
             InternalReachErr::Transport(err) => Some(err),
             InternalReachErr::PeerIdMismatch { .. } => None,
             InternalReachErr::FoundLocalPeerId => None,
+            InternalReachErr::ConnectionLimit(limit) => Some(limit),
         }
     }
 }
@@ -497,9 +518,6 @@ pub enum IncomingError<TTransErr> {
     /// Error in the transport layer.
     // TODO: just TTransError should be enough?
     Transport(TransportError<TTransErr>),
-    /// Denied the incoming connection because we're already connected to this peer as a dialer
-    /// and we have a higher priority than the remote.
-    DeniedLowerPriority,
     /// The negotiated `PeerId` is the same as the local node.
     FoundLocalPeerId,
 }
@@ -510,9 +528,6 @@ where TTransErr: fmt::Display
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             IncomingError::Transport(err) => write!(f, "{}", err),
-            IncomingError::DeniedLowerPriority => {
-                write!(f, "Denied because of lower priority")
-            },
             IncomingError::FoundLocalPeerId => {
                 write!(f, "Incoming connection has same PeerId as us")
             },
@@ -526,7 +541,6 @@ where TTransErr: error::Error + 'static
     fn source(&self) -> Option<&(dyn error::Error + 'static)> {
         match self {
             IncomingError::Transport(err) => Some(err),
-            IncomingError::DeniedLowerPriority => None,
             IncomingError::FoundLocalPeerId => None,
         }
     }
@@ -580,7 +594,7 @@ where
         let connected_point = self.to_connected_point();
         let handler = builder(self.info());
         let local_peer_id = self.local_peer_id;
-        let upgrade = self.upgrade
+        let upgrade = Compat03As01::new(Box::pin(self.upgrade))
             .map_err(|err| InternalReachErr::Transport(TransportError::Other(err)))
             .and_then({
                 let connected_point = connected_point.clone();
@@ -661,36 +675,42 @@ where
 {
     /// Creates a new node events stream.
     pub fn new(transport: TTrans, local_peer_id: TPeerId) -> Self {
-        // TODO: with_capacity?
-        Network {
-            listeners: ListenersStream::new(transport),
-            active_nodes: CollectionStream::new(),
-            reach_attempts: ReachAttempts {
-                local_peer_id,
-                out_reach_attempts: Default::default(),
-                other_reach_attempts: Vec::new(),
-                connected_points: Default::default(),
-            },
-            incoming_limit: None,
-            take_over_to_complete: None
-        }
+        Self::new_with_limits(transport, local_peer_id, ConnectionLimits::default())
     }
 
     /// Creates a new node event stream with incoming connections limit.
     pub fn new_with_incoming_limit(transport: TTrans,
         local_peer_id: TPeerId, incoming_limit: Option<u32>) -> Self
     {
+        let limits = ConnectionLimits::default().with_max_pending_incoming(incoming_limit);
+        Self::new_with_limits(transport, local_peer_id, limits)
+    }
+
+    /// Creates a new node event stream that enforces the given connection limits.
+    pub fn new_with_limits(transport: TTrans, local_peer_id: TPeerId, limits: ConnectionLimits) -> Self {
+        Self::new_with_executor(transport, local_peer_id, limits, Box::new(TokioExecutor))
+    }
+
+    /// Creates a new node event stream that enforces the given connection limits and runs the
+    /// connection tasks with the given executor.
+    pub fn new_with_executor(transport: TTrans, local_peer_id: TPeerId, limits: ConnectionLimits,
+        executor: Box<dyn Executor + Send>) -> Self
+    {
+        let mut active_nodes = CollectionStream::with_executor(executor);
+        active_nodes.set_substream_limit(limits.max_substreams().map(|max| SubstreamLimit::new(max as usize)));
+
+        // TODO: with_capacity?
         Network {
-            incoming_limit,
             listeners: ListenersStream::new(transport),
-            active_nodes: CollectionStream::new(),
+            active_nodes,
             reach_attempts: ReachAttempts {
                 local_peer_id,
                 out_reach_attempts: Default::default(),
                 other_reach_attempts: Vec::new(),
                 connected_points: Default::default(),
+                denied_attempts: Default::default(),
             },
-            take_over_to_complete: None
+            limits,
         }
     }
 
@@ -711,7 +731,12 @@ where
 
     /// Returns limit on incoming connections.
     pub fn incoming_limit(&self) -> Option<u32> {
-        self.incoming_limit
+        self.limits.max_pending_incoming()
+    }
+
+    /// Returns the connection limits enforced by this network.
+    pub fn limits(&self) -> &ConnectionLimits {
+        &self.limits
     }
 
     /// Call this function in order to know which address remotes should dial in order to access
@@ -740,6 +765,9 @@ where
     /// Dials a multiaddress without knowing the peer ID we're going to obtain.
     ///
     /// The second parameter is the handler to use if we manage to reach a node.
+    ///
+    /// If the limit on pending outgoing connections is reached, the address is not dialed and a
+    /// `ConnectionLimitReached` event is produced instead.
     pub fn dial(&mut self, addr: Multiaddr, handler: THandler) -> Result<(), TransportError<TTrans::Error>>
     where
         TTrans: Transport<Output = (TConnInfo, TMuxer)>,
@@ -753,9 +781,57 @@ where
         TConnInfo: Send + 'static,
         TPeerId: Send + 'static,
     {
+        self.dial_with_role(addr, Endpoint::Dialer, handler)
+    }
+
+    /// Same as `dial`, but we take the role of the listener in the upgrades of the connection.
+    ///
+    /// See [`Transport::dial_as_listener`].
+    pub fn dial_as_listener(&mut self, addr: Multiaddr, handler: THandler) -> Result<(), TransportError<TTrans::Error>>
+    where
+        TTrans: Transport<Output = (TConnInfo, TMuxer)>,
+        TTrans::Error: Send + 'static,
+        TTrans::Dial: Send + 'static,
+        TMuxer: StreamMuxer + Send + Sync + 'static,
+        TMuxer::OutboundSubstream: Send,
+        TMuxer::Substream: Send,
+        TInEvent: Send + 'static,
+        TOutEvent: Send + 'static,
+        TConnInfo: Send + 'static,
+        TPeerId: Send + 'static,
+    {
+        self.dial_with_role(addr, Endpoint::Listener, handler)
+    }
+
+    fn dial_with_role(&mut self, addr: Multiaddr, role_override: Endpoint, handler: THandler)
+        -> Result<(), TransportError<TTrans::Error>>
+    where
+        TTrans: Transport<Output = (TConnInfo, TMuxer)>,
+        TTrans::Error: Send + 'static,
+        TTrans::Dial: Send + 'static,
+        TMuxer: StreamMuxer + Send + Sync + 'static,
+        TMuxer::OutboundSubstream: Send,
+        TMuxer::Substream: Send,
+        TInEvent: Send + 'static,
+        TOutEvent: Send + 'static,
+        TConnInfo: Send + 'static,
+        TPeerId: Send + 'static,
+    {
+        let connected_point = ConnectedPoint::Dialer { address: addr.clone(), role_override };
+        if let Err(limit) = self.limits.check(ConnectionLimitKind::PendingOutgoing, self.num_pending_outgoing()) {
+            let reach_id = self.active_nodes.add_reach_attempt(future::err(InternalReachErr::ConnectionLimit(limit)), handler);
+            self.reach_attempts.denied_attempts.insert(reach_id);
+            self.reach_attempts.other_reach_attempts.push((reach_id, connected_point));
+            return Ok(());
+        }
+
         let local_peer_id = self.reach_attempts.local_peer_id.clone();
-        let connected_point = ConnectedPoint::Dialer { address: addr.clone() };
-        let future = self.transport().clone().dial(addr)?
+        let transport = self.transport().clone();
+        let future = match role_override {
+            Endpoint::Dialer => transport.dial(addr)?,
+            Endpoint::Listener => transport.dial_as_listener(addr)?,
+        };
+        let future = Compat03As01::new(Box::pin(future))
             .map_err(|err| InternalReachErr::Transport(TransportError::Other(err)))
             .and_then({
                 let connected_point = connected_point.clone();
@@ -786,6 +862,17 @@ where
             .count()
     }
 
+    /// Returns the number of outgoing connections that are being dialed or negotiated, ignoring
+    /// the attempts that were refused because of the connection limits.
+    fn num_pending_outgoing(&self) -> usize {
+        let unknown = self.reach_attempts.other_reach_attempts
+            .iter()
+            .filter(|(_, endpoint)| !endpoint.is_listener())
+            .count();
+        (unknown + self.reach_attempts.out_reach_attempts.len())
+            .saturating_sub(self.reach_attempts.denied_attempts.len())
+    }
+
     /// Returns the list of incoming connections that are currently in the process of being
     /// negotiated. We don't know the `PeerId` of these nodes yet.
     pub fn incoming_negotiated(&self) -> impl Iterator<Item = IncomingInfo<'_>> {
@@ -847,7 +934,7 @@ where
             .iter()
             .filter_map(|&(_, ref endpoint)| {
                 match endpoint {
-                    ConnectedPoint::Dialer { address } => Some(address),
+                    ConnectedPoint::Dialer { address, .. } => Some(address),
                     ConnectedPoint::Listener { .. } => None,
                 }
             })
@@ -868,12 +955,10 @@ where
                 peer_id,
                 connected_points: &mut self.reach_attempts.connected_points,
                 out_reach_attempts: &mut self.reach_attempts.out_reach_attempts,
+                denied_attempts: &mut self.reach_attempts.denied_attempts,
             });
         }
 
-        // The state of `connected_points` always follows `self.active_nodes`.
-        debug_assert!(!self.reach_attempts.connected_points.contains_key(&peer_id));
-
         if self.reach_attempts.out_reach_attempts.get_mut(&peer_id).is_some() {
             return Peer::PendingConnect(PeerPendingConnect {
                 attempt: match self.reach_attempts.out_reach_attempts.entry(peer_id.clone()) {
@@ -881,6 +966,7 @@ where
                     Entry::Vacant(_) => panic!("we checked for Some just above"),
                 },
                 active_nodes: &mut self.active_nodes,
+                denied_attempts: &mut self.reach_attempts.denied_attempts,
             });
         }
 
@@ -908,11 +994,16 @@ where
         TConnInfo: Send + 'static,
         TPeerId: Send + 'static,
     {
-        let reach_id = match self.transport().clone().dial(first.clone()) {
+        let dial = match self.limits.check(ConnectionLimitKind::PendingOutgoing, self.num_pending_outgoing()) {
+            Ok(()) => self.transport().clone().dial(first.clone()).map_err(InternalReachErr::Transport),
+            Err(limit) => Err(InternalReachErr::ConnectionLimit(limit)),
+        };
+
+        let reach_id = match dial {
             Ok(fut) => {
                 let expected_peer_id = peer_id.clone();
-                let connected_point = ConnectedPoint::Dialer { address: first.clone() };
-                let fut = fut
+                let connected_point = ConnectedPoint::Dialer { address: first.clone(), role_override: Endpoint::Dialer };
+                let fut = Compat03As01::new(Box::pin(fut))
                     .map_err(|err| InternalReachErr::Transport(TransportError::Other(err)))
                     .and_then(move |(actual_conn_info, muxer)| {
                         if *actual_conn_info.peer_id() == expected_peer_id {
@@ -923,10 +1014,13 @@ where
                     });
                 self.active_nodes.add_reach_attempt(fut, handler)
             },
-            Err(err) => {
-                let fut = future::err(InternalReachErr::Transport(err));
-                self.active_nodes.add_reach_attempt(fut, handler)
+            Err(InternalReachErr::ConnectionLimit(limit)) => {
+                let fut = future::err(InternalReachErr::ConnectionLimit(limit));
+                let reach_id = self.active_nodes.add_reach_attempt(fut, handler);
+                self.reach_attempts.denied_attempts.insert(reach_id);
+                reach_id
             },
+            Err(err) => self.active_nodes.add_reach_attempt(future::err(err), handler),
         };
 
         let former = self.reach_attempts.out_reach_attempts.insert(
@@ -958,64 +1052,48 @@ where
         <THandler::Handler as NodeHandler>::OutboundOpenInfo: Send + 'static, // TODO: shouldn't be necessary
         THandlerErr: error::Error + Send + 'static,
         TConnInfo: Clone,
-        TPeerId: AsRef<[u8]> + Send + 'static,
+        TPeerId: Send + 'static,
     {
-        // Start by polling the listeners for events, but only if the number
-        // of incoming connections does not exceed the limit.
-        match self.incoming_limit {
-            Some(x) if self.incoming_negotiated().count() >= (x as usize)
-                => (),
-            _ => {
-                match self.listeners.poll() {
-                    Async::NotReady => (),
-                    Async::Ready(ListenersEvent::Incoming { upgrade, listen_addr, send_back_addr }) => {
-                        let event = IncomingConnectionEvent {
-                            upgrade,
-                            local_peer_id: self.reach_attempts.local_peer_id.clone(),
-                            listen_addr,
-                            send_back_addr,
-                            active_nodes: &mut self.active_nodes,
-                            other_reach_attempts: &mut self.reach_attempts.other_reach_attempts,
-                        };
-                        return Async::Ready(NetworkEvent::IncomingConnection(event));
-                    }
-                    Async::Ready(ListenersEvent::NewAddress { listen_addr }) => {
-                        return Async::Ready(NetworkEvent::NewListenerAddress { listen_addr })
-                    }
-                    Async::Ready(ListenersEvent::AddressExpired { listen_addr }) => {
-                        return Async::Ready(NetworkEvent::ExpiredListenerAddress { listen_addr })
-                    }
-                    Async::Ready(ListenersEvent::Closed { listener, result }) => {
-                        return Async::Ready(NetworkEvent::ListenerClosed { listener, result })
-                    }
-                }
-            }
-        }
+        // Start by polling the listeners for events, but only if the number of incoming
+        // connections being negotiated is below the limit. Otherwise the pending connections
+        // stay in the listeners' backlog until there is room for them.
+        let pending_incoming = self.incoming_negotiated().count();
+        let listeners_event = if self.limits.check(ConnectionLimitKind::PendingIncoming, pending_incoming).is_ok() {
+            self.listeners.poll()
+        } else {
+            Async::NotReady
+        };
 
-        // Attempt to deliver any pending take over messages.
-        if let Some((id, interrupted)) = self.take_over_to_complete.take() {
-            if let Some(mut peer) = self.active_nodes.peer_mut(&id) {
-                if let AsyncSink::NotReady(i) = interrupted {
-                    if let StartTakeOver::NotReady(i) = peer.start_take_over(i) {
-                        self.take_over_to_complete = Some((id, AsyncSink::NotReady(i)))
-                    } else if let Ok(Async::NotReady) = peer.complete_take_over() {
-                        self.take_over_to_complete = Some((id, AsyncSink::Ready))
-                    }
-                } else if let Ok(Async::NotReady) = peer.complete_take_over() {
-                    self.take_over_to_complete = Some((id, AsyncSink::Ready))
-                }
+        match listeners_event {
+            Async::NotReady => (),
+            Async::Ready(ListenersEvent::Incoming { upgrade, listen_addr, send_back_addr }) => {
+                let event = IncomingConnectionEvent {
+                    upgrade,
+                    local_peer_id: self.reach_attempts.local_peer_id.clone(),
+                    listen_addr,
+                    send_back_addr,
+                    active_nodes: &mut self.active_nodes,
+                    other_reach_attempts: &mut self.reach_attempts.other_reach_attempts,
+                };
+                return Async::Ready(NetworkEvent::IncomingConnection(event));
+            }
+            Async::Ready(ListenersEvent::NewAddress { listen_addr }) => {
+                return Async::Ready(NetworkEvent::NewListenerAddress { listen_addr })
+            }
+            Async::Ready(ListenersEvent::AddressExpired { listen_addr }) => {
+                return Async::Ready(NetworkEvent::ExpiredListenerAddress { listen_addr })
+            }
+            Async::Ready(ListenersEvent::Closed { listener, result }) => {
+                return Async::Ready(NetworkEvent::ListenerClosed { listener, result })
             }
-        }
-        if self.take_over_to_complete.is_some() {
-            return Async::NotReady
         }
 
         // Poll the existing nodes.
-        let (action, out_event);
+        let (action, mut out_event);
         match self.active_nodes.poll() {
             Async::NotReady => return Async::NotReady,
             Async::Ready(CollectionEvent::NodeReached(reach_event)) => {
-                let (a, e) = handle_node_reached(&mut self.reach_attempts, reach_event);
+                let (a, e) = handle_node_reached(&mut self.reach_attempts, &self.limits, reach_event);
                 action = a;
                 out_event = e;
             }
@@ -1025,11 +1103,13 @@ where
                 out_event = e;
             }
             Async::Ready(CollectionEvent::NodeClosed {
+                id,
                 conn_info,
                 error,
+                num_established,
                 ..
             }) => {
-                let endpoint = self.reach_attempts.connected_points.remove(conn_info.peer_id())
+                let endpoint = self.reach_attempts.connected_points.remove(&id)
                     .expect("We insert into connected_points whenever a connection is \
                              opened and remove only when a connection is closed; the \
                              underlying API is guaranteed to always deliver a connection \
@@ -1038,13 +1118,29 @@ where
                 action = Default::default();
                 out_event = NetworkEvent::NodeClosed {
                     conn_info: conn_info.0,
+                    connection_id: id,
                     endpoint,
                     error,
+                    num_established,
                 };
             }
             Async::Ready(CollectionEvent::NodeEvent { peer, event }) => {
                 action = Default::default();
-                out_event = NetworkEvent::NodeEvent { conn_info: peer.info().0.clone(), event };
+                out_event = NetworkEvent::NodeEvent {
+                    conn_info: peer.info().0.clone(),
+                    connection_id: peer.connection_id(),
+                    event,
+                };
+            }
+            Async::Ready(CollectionEvent::SubstreamLimitReached { peer }) => {
+                let limit = self.limits.max_substreams()
+                    .expect("Substreams are only refused if max_substreams is set; QED");
+                action = Default::default();
+                out_event = NetworkEvent::ConnectionLimitReached {
+                    limit: ConnectionLimit { kind: ConnectionLimitKind::Substreams, limit },
+                    peer_id: Some(peer.info().0.peer_id().clone()),
+                    endpoint: peer.info().1.clone(),
+                };
             }
         }
 
@@ -1052,24 +1148,9 @@ where
             self.start_dial_out(peer_id, handler, first, rest);
         }
 
-        if let Some((peer_id, interrupt)) = action.take_over {
-            // TODO: improve proof or remove; this is too complicated right now
-            let interrupted = self.active_nodes
-                .interrupt(interrupt)
-                .expect("take_over is guaranteed to be gathered from `out_reach_attempts`;
-                         we insert in out_reach_attempts only when we call \
-                         active_nodes.add_reach_attempt, and we remove only when we call \
-                         interrupt or when a reach attempt succeeds or errors; therefore the \
-                         out_reach_attempts should always be in sync with the actual \
-                         attempts; QED");
-            let mut peer = self.active_nodes.peer_mut(&peer_id).unwrap();
-            if let StartTakeOver::NotReady(i) = peer.start_take_over(interrupted) {
-                self.take_over_to_complete = Some((peer_id, AsyncSink::NotReady(i)));
-                return Async::NotReady
-            }
-            if let Ok(Async::NotReady) = peer.complete_take_over() {
-                self.take_over_to_complete = Some((peer_id, AsyncSink::Ready));
-                return Async::NotReady
+        if let NetworkEvent::DialError { ref mut new_state, ref peer_id, .. } = out_event {
+            if self.active_nodes.has_connection(peer_id) {
+                *new_state = PeerState::Connected;
             }
         }
 
@@ -1082,16 +1163,12 @@ where
 #[must_use]
 struct ActionItem<THandler, TPeerId> {
     start_dial_out: Option<(TPeerId, THandler, Multiaddr, Vec<Multiaddr>)>,
-    /// The `ReachAttemptId` should be interrupted, and the task for the given `PeerId` should take
-    /// over it.
-    take_over: Option<(TPeerId, ReachAttemptId)>,
 }
 
 impl<THandler, TPeerId> Default for ActionItem<THandler, TPeerId> {
     fn default() -> Self {
         ActionItem {
             start_dial_out: None,
-            take_over: None,
         }
     }
 }
@@ -1104,6 +1181,7 @@ impl<THandler, TPeerId> Default for ActionItem<THandler, TPeerId> {
 /// >           panics will likely happen.
 fn handle_node_reached<'a, TTrans, TMuxer, TInEvent, TOutEvent, THandler, THandlerErr, TConnInfo, TPeerId>(
     reach_attempts: &mut ReachAttempts<TPeerId>,
+    limits: &ConnectionLimits,
     event: CollectionReachEvent<'_, TInEvent, TOutEvent, THandler, InternalReachErr<TTrans::Error, TConnInfo>, THandlerErr, (), (TConnInfo, ConnectedPoint), TPeerId>,
 ) -> (ActionItem<THandler, TPeerId>, NetworkEvent<'a, TTrans, TInEvent, TOutEvent, THandler, THandlerErr, TConnInfo, TPeerId>)
 where
@@ -1114,7 +1192,7 @@ where
     TInEvent: Send + 'static,
     TOutEvent: Send + 'static,
     TConnInfo: ConnectionInfo<PeerId = TPeerId> + Clone + Send + 'static,
-    TPeerId: Eq + Hash + AsRef<[u8]> + Clone,
+    TPeerId: Eq + Hash + Clone,
 {
     // We first start looking in the incoming attempts. While this makes the code less optimal,
     // it also makes the logic easier.
@@ -1124,66 +1202,16 @@ where
         .position(|i| i.0 == event.reach_attempt_id())
     {
         let (_, opened_endpoint) = reach_attempts.other_reach_attempts.swap_remove(in_pos);
-        let has_dial_prio = has_dial_prio(&reach_attempts.local_peer_id, event.peer_id());
-
-        // If we already have an active connection to this peer, a priority system comes into play.
-        // If we have a lower peer ID than the incoming one, we drop an incoming connection.
-        if event.would_replace() && has_dial_prio {
-            if let Some(ConnectedPoint::Dialer { .. }) = reach_attempts.connected_points.get(event.peer_id()) {
-                if let ConnectedPoint::Listener { listen_addr, send_back_addr } = opened_endpoint {
-                    return (Default::default(), NetworkEvent::IncomingConnectionError {
-                        listen_addr,
-                        send_back_addr,
-                        error: IncomingError::DeniedLowerPriority,
-                    });
-                }
-            }
-        }
 
-        // Set the endpoint for this peer.
-        let closed_endpoint = reach_attempts.connected_points.insert(event.peer_id().clone(), opened_endpoint.clone());
-
-        // If we have dial priority, we keep the current outgoing attempt because it may already
-        // have succeeded without us knowing. It is possible that the remote has already closed
-        // its ougoing attempt because it sees our outgoing attempt as a success.
-        // However we cancel any further multiaddress to attempt in any situation.
-        let action = if has_dial_prio {
-            if let Some(attempt) = reach_attempts.out_reach_attempts.get_mut(&event.peer_id()) {
-                debug_assert_ne!(attempt.id, event.reach_attempt_id());
-                attempt.next_attempts.clear();
-            }
-            ActionItem::default()
-        } else {
-            if let Some(attempt) = reach_attempts.out_reach_attempts.remove(&event.peer_id()) {
-                debug_assert_ne!(attempt.id, event.reach_attempt_id());
-                ActionItem {
-                    take_over: Some((event.peer_id().clone(), attempt.id)),
-                    .. Default::default()
-                }
-            } else {
-                ActionItem::default()
-            }
-        };
-
-        let (outcome, conn_info) = event.accept(());
-        if let CollectionNodeAccept::ReplacedExisting(old_info, ()) = outcome {
-            let closed_endpoint = closed_endpoint
-                .expect("We insert into connected_points whenever a connection is opened and \
-                         remove only when a connection is closed; the underlying API is \
-                         guaranteed to always deliver a connection closed message after it has \
-                         been opened, and no two closed messages; QED");
-            return (action, NetworkEvent::Replaced {
-                new_info: conn_info.0,
-                old_info: old_info.0,
-                endpoint: opened_endpoint,
-                closed_endpoint,
-            });
-        } else {
-            return (action, NetworkEvent::Connected {
-                conn_info: conn_info.0,
-                endpoint: opened_endpoint
-            });
+        // If we are also dialing this peer, we keep the current outgoing attempt because it may
+        // already have succeeded without us knowing, in which case it becomes an additional
+        // connection. However we cancel any further multiaddress to attempt.
+        if let Some(attempt) = reach_attempts.out_reach_attempts.get_mut(&event.peer_id()) {
+            debug_assert_ne!(attempt.id, event.reach_attempt_id());
+            attempt.next_attempts.clear();
         }
+
+        return (Default::default(), accept_node(reach_attempts, limits, event, opened_endpoint));
     }
 
     // Otherwise, try for outgoing attempts.
@@ -1202,31 +1230,10 @@ where
 
         let opened_endpoint = ConnectedPoint::Dialer {
             address: attempt.cur_attempted,
+            role_override: Endpoint::Dialer,
         };
 
-        let closed_endpoint = reach_attempts.connected_points
-            .insert(event.peer_id().clone(), opened_endpoint.clone());
-
-        let (outcome, conn_info) = event.accept(());
-        if let CollectionNodeAccept::ReplacedExisting(old_info, ()) = outcome {
-            let closed_endpoint = closed_endpoint
-                .expect("We insert into connected_points whenever a connection is opened and \
-                        remove only when a connection is closed; the underlying API is guaranteed \
-                        to always deliver a connection closed message after it has been opened, \
-                        and no two closed messages; QED");
-            return (Default::default(), NetworkEvent::Replaced {
-                new_info: conn_info.0,
-                old_info: old_info.0,
-                endpoint: opened_endpoint,
-                closed_endpoint,
-            });
-
-        } else {
-            return (Default::default(), NetworkEvent::Connected {
-                conn_info: conn_info.0,
-                endpoint: opened_endpoint
-            });
-        }
+        return (Default::default(), accept_node(reach_attempts, limits, event, opened_endpoint));
     }
 
     // We didn't find any entry in neither the outgoing connections not ingoing connections.
@@ -1238,15 +1245,53 @@ where
             find back this ID in either of these two sets");
 }
 
-/// Returns true if `local` has dialing priority over `other`.
+/// Accepts a reached node as a new connection and builds the corresponding `Connected` event.
 ///
-/// This means that if `local` and `other` both dial each other, the connection from `local` should
-/// be kept and the one from `other` will be dropped.
-fn has_dial_prio<TPeerId>(local: &TPeerId, other: &TPeerId) -> bool
+/// If accepting the node would exceed one of the limits on established connections, the node is
+/// denied instead and a `ConnectionLimitReached` event is built.
+fn accept_node<'a, TTrans, TInEvent, TOutEvent, THandler, THandlerErr, TConnInfo, TPeerId>(
+    reach_attempts: &mut ReachAttempts<TPeerId>,
+    limits: &ConnectionLimits,
+    event: CollectionReachEvent<'_, TInEvent, TOutEvent, THandler, InternalReachErr<TTrans::Error, TConnInfo>, THandlerErr, (), (TConnInfo, ConnectedPoint), TPeerId>,
+    opened_endpoint: ConnectedPoint,
+) -> NetworkEvent<'a, TTrans, TInEvent, TOutEvent, THandler, THandlerErr, TConnInfo, TPeerId>
 where
-    TPeerId: AsRef<[u8]>,
+    TTrans: Transport,
+    TConnInfo: ConnectionInfo<PeerId = TPeerId> + Clone,
+    TPeerId: Eq + Hash + Clone,
 {
-    local.as_ref() < other.as_ref()
+    let num_incoming = reach_attempts.connected_points.values().filter(|e| e.is_listener()).count();
+    let num_outgoing = reach_attempts.connected_points.len() - num_incoming;
+    let checked = if opened_endpoint.is_listener() {
+        limits.check(ConnectionLimitKind::EstablishedIncoming, num_incoming)
+    } else {
+        limits.check(ConnectionLimitKind::EstablishedOutgoing, num_outgoing)
+    }
+    .and_then(|()| limits.check(ConnectionLimitKind::EstablishedTotal, reach_attempts.connected_points.len()))
+    .and_then(|()| limits.check(ConnectionLimitKind::EstablishedPerPeer, event.num_established()));
+
+    if let Err(limit) = checked {
+        let (conn_info, _) = event.deny();
+        return NetworkEvent::ConnectionLimitReached {
+            limit,
+            peer_id: Some(conn_info.peer_id().clone()),
+            endpoint: opened_endpoint,
+        };
+    }
+
+    let connection_id = event.connection_id();
+    let num_established = NonZeroUsize::new(event.num_established() + 1)
+        .expect("n + 1 is always non-zero; QED");
+    let _former = reach_attempts.connected_points.insert(connection_id, opened_endpoint.clone());
+    debug_assert!(_former.is_none());
+
+    let (_, conn_info) = event.accept(());
+    NetworkEvent::Connected {
+        conn_info: conn_info.0,
+        connection_id,
+        endpoint: opened_endpoint,
+        num_established,
+    }
 }
 
 /// Handles a reach error event from the collection.
@@ -1266,6 +1311,41 @@ where
     TConnInfo: ConnectionInfo<PeerId = TPeerId> + Send + 'static,
     TPeerId: Eq + Hash + Clone,
 {
+    // Attempts refused because of the connection limits are reported as such, without trying
+    // any other address.
+    if let InternalReachErr::ConnectionLimit(limit) = error {
+        let _was_denied = reach_attempts.denied_attempts.remove(&reach_id);
+        debug_assert!(_was_denied);
+
+        let out_reach_peer_id = reach_attempts
+            .out_reach_attempts
+            .iter()
+            .find(|(_, a)| a.id == reach_id)
+            .map(|(p, _)| p.clone());
+        if let Some(peer_id) = out_reach_peer_id {
+            let attempt = reach_attempts.out_reach_attempts.remove(&peer_id)
+                .expect("out_reach_peer_id is a key that is grabbed from out_reach_attempts");
+            return (Default::default(), NetworkEvent::ConnectionLimitReached {
+                limit,
+                peer_id: Some(peer_id),
+                endpoint: ConnectedPoint::Dialer { address: attempt.cur_attempted, role_override: Endpoint::Dialer },
+            });
+        }
+
+        let in_pos = reach_attempts
+            .other_reach_attempts
+            .iter()
+            .position(|i| i.0 == reach_id)
+            .expect("Refused attempts are always inserted in either out_reach_attempts or \
+                     other_reach_attempts; QED");
+        let (_, endpoint) = reach_attempts.other_reach_attempts.swap_remove(in_pos);
+        return (Default::default(), NetworkEvent::ConnectionLimitReached {
+            limit,
+            peer_id: None,
+            endpoint,
+        });
+    }
+
     // Search for the attempt in `out_reach_attempts`.
     // TODO: could be more optimal than iterating over everything
     let out_reach_peer_id = reach_attempts
@@ -1280,9 +1360,9 @@ where
         let num_remain = attempt.next_attempts.len();
         let failed_addr = attempt.cur_attempted.clone();
 
-        let new_state = if reach_attempts.connected_points.contains_key(&peer_id) {
-            PeerState::Connected
-        } else if num_remain == 0 {
+        // Note that the peer might be connected through another connection, in which case the
+        // state is adjusted by the caller.
+        let new_state = if num_remain == 0 {
             PeerState::NotConnected
         } else {
             PeerState::Dialing {
@@ -1311,6 +1391,9 @@ where
                 unreachable!("We only generate FoundLocalPeerId within dial() or accept(); neither \
                               of these methods add an entry to out_reach_attempts; QED")
             },
+            InternalReachErr::ConnectionLimit(_) => {
+                unreachable!("Refused attempts are handled at the beginning of this function; QED")
+            },
         };
 
         return (action, NetworkEvent::DialError {
@@ -1329,7 +1412,7 @@ where
     {
         let (_, endpoint) = reach_attempts.other_reach_attempts.swap_remove(in_pos);
         match endpoint {
-            ConnectedPoint::Dialer { address } => {
+            ConnectedPoint::Dialer { address, .. } => {
                 let error = match error {
                     InternalReachErr::Transport(err) => UnknownPeerDialErr::Transport(err),
                     InternalReachErr::FoundLocalPeerId => UnknownPeerDialErr::FoundLocalPeerId,
@@ -1337,6 +1420,10 @@ where
                         unreachable!("We only generate PeerIdMismatch within start_dial_out(),
                                       which doesn't add any entry in other_reach_attempts; QED")
                     },
+                    InternalReachErr::ConnectionLimit(_) => {
+                        unreachable!("Refused attempts are handled at the beginning of this \
+                                      function; QED")
+                    },
                 };
                 return (Default::default(), NetworkEvent::UnknownPeerDialError {
                     multiaddr: address,
@@ -1352,6 +1439,10 @@ where
                         unreachable!("We only generate PeerIdMismatch within start_dial_out(),
                                       which doesn't add any entry in other_reach_attempts; QED")
                     },
+                    InternalReachErr::ConnectionLimit(_) => {
+                        unreachable!("Refused attempts are handled at the beginning of this \
+                                      function; QED")
+                    },
                 };
                 return (Default::default(), NetworkEvent::IncomingConnectionError {
                     listen_addr,
@@ -1402,10 +1493,13 @@ where
 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
         match *self {
-            Peer::Connected( PeerConnected { ref peer_id, ref connected_points, .. }) => {
+            Peer::Connected(PeerConnected { ref peer_id, ref active_nodes, ref connected_points, .. }) => {
+                let endpoints = active_nodes.peer_connections(peer_id)
+                    .filter_map(|id| connected_points.get(&id))
+                    .collect::<Vec<_>>();
                 f.debug_struct("Connected")
                     .field("peer_id", peer_id)
-                    .field("connected_points", connected_points)
+                    .field("connected_points", &endpoints)
                     .finish()
             }
             Peer::PendingConnect( PeerPendingConnect { ref attempt, .. } ) => {
@@ -1557,9 +1651,11 @@ where TTrans: Transport,
     /// Reference to the `active_nodes` of the parent.
     active_nodes: &'a mut CollectionStream<TInEvent, TOutEvent, THandler, InternalReachErr<TTrans::Error, TConnInfo>, THandlerErr, (), (TConnInfo, ConnectedPoint), TPeerId>,
     /// Reference to the `connected_points` field of the parent.
-    connected_points: &'a mut FnvHashMap<TPeerId, ConnectedPoint>,
+    connected_points: &'a mut FnvHashMap<ConnectionId, ConnectedPoint>,
     /// Reference to the `out_reach_attempts` field of the parent.
     out_reach_attempts: &'a mut FnvHashMap<TPeerId, OutReachAttempt>,
+    /// Reference to the `denied_attempts` field of the parent.
+    denied_attempts: &'a mut FnvHashSet<ReachAttemptId>,
     peer_id: TPeerId,
 }
 
@@ -1569,25 +1665,55 @@ where
     TConnInfo: ConnectionInfo<PeerId = TPeerId>,
     TPeerId: Eq + Hash,
 {
-    /// Closes the connection to this node.
+    /// Closes all the connections to this node.
     ///
     /// No `NodeClosed` message will be generated for this node.
     // TODO: consider returning a `PeerNotConnected`; however this makes all the borrows things
     // much more annoying to deal with
     pub fn close(self) {
         if let Some(reach_attempt) = self.out_reach_attempts.remove(&self.peer_id) {
+            self.denied_attempts.remove(&reach_attempt.id);
             self.active_nodes
                 .interrupt(reach_attempt.id)
                 .expect("Elements in out_reach_attempts are in sync with active_nodes; QED");
         }
 
-        self.connected_points.remove(&self.peer_id);
-        self.active_nodes.peer_mut(&self.peer_id)
-            .expect("A PeerConnected is always created with a PeerId in active_nodes; QED")
+        let connections = self.active_nodes.peer_connections(&self.peer_id).collect::<Vec<_>>();
+        for id in connections {
+            self.connected_points.remove(&id);
+            self.active_nodes.connection_mut(id)
+                .expect("peer_connections only returns established connections; QED")
+                .close();
+        }
+    }
+
+    /// Closes one of the connections to this node. The other connections are kept alive.
+    ///
+    /// No `NodeClosed` message will be generated for this connection. Does nothing if the
+    /// connection doesn't belong to this node.
+    pub fn close_connection(self, id: ConnectionId) {
+        if !self.active_nodes.peer_connections(&self.peer_id).any(|c| c == id) {
+            return;
+        }
+
+        self.connected_points.remove(&id);
+        self.active_nodes.connection_mut(id)
+            .expect("peer_connections only returns established connections; QED")
             .close();
     }
 
-    /// Returns the connection info for this node.
+    /// Returns the identifiers of the connections to this node, from the oldest to the newest.
+    pub fn connections(&self) -> impl Iterator<Item = ConnectionId> + '_ {
+        self.active_nodes.peer_connections(&self.peer_id)
+    }
+
+    /// Returns the number of connections to this node.
+    pub fn num_connections(&self) -> NonZeroUsize {
+        NonZeroUsize::new(self.connections().count())
+            .expect("A PeerConnected is always created with a PeerId in active_nodes; QED")
+    }
+
+    /// Returns the connection info of the oldest connection to this node.
     // TODO: we would love to return a `&'a TConnInfo`, but this isn't possible because of lifetime
     //       issues; see the corresponding method in collection.rs module
     // TODO: should take a `&self`, but the API in collection.rs requires &mut
@@ -1600,16 +1726,28 @@ where
             .info().0.clone()
     }
 
-    /// Returns the endpoint we're connected to.
+    /// Returns the endpoint of the oldest connection to this node.
     pub fn endpoint(&self) -> &ConnectedPoint {
-        self.connected_points.get(&self.peer_id)
+        let id = self.connections().next()
+            .expect("A PeerConnected is always created with a PeerId in active_nodes; QED");
+        self.connected_points.get(&id)
             .expect("We insert into connected_points whenever a connection is opened and remove \
                      only when a connection is closed; the underlying API is guaranteed to always \
                      deliver a connection closed message after it has been opened, and no two \
                      closed messages; QED")
     }
 
-    /// Start sending an event to the node.
+    /// Returns the endpoint of the given connection to this node, or `None` if the connection
+    /// doesn't belong to this node.
+    pub fn connection_endpoint(&self, id: ConnectionId) -> Option<&ConnectedPoint> {
+        if self.connections().any(|c| c == id) {
+            self.connected_points.get(&id)
+        } else {
+            None
+        }
+    }
+
+    /// Start sending an event to the node, using any of the connections.
     pub fn start_send_event(&mut self, event: TInEvent) -> StartSend<TInEvent, ()> {
         self.active_nodes.peer_mut(&self.peer_id)
             .expect("A PeerConnected is always created with a PeerId in active_nodes; QED")
@@ -1622,6 +1760,24 @@ where
             .expect("A PeerConnected is always created with a PeerId in active_nodes; QED")
             .complete_send_event()
     }
+
+    /// Start sending an event to a specific connection of the node.
+    ///
+    /// Returns an error if the connection doesn't belong to this node.
+    pub fn start_send_event_to(&mut self, id: ConnectionId, event: TInEvent) -> StartSend<TInEvent, ()> {
+        match self.active_nodes.connection_mut(id) {
+            Some(ref mut conn) if *conn.id() == self.peer_id => conn.start_send_event(event),
+            _ => Err(()),
+        }
+    }
+
+    /// Complete sending an event message, initiated by `start_send_event_to`.
+    pub fn complete_send_event_to(&mut self, id: ConnectionId) -> Poll<(), ()> {
+        match self.active_nodes.connection_mut(id) {
+            Some(ref mut conn) if *conn.id() == self.peer_id => conn.complete_send_event(),
+            _ => Err(()),
+        }
+    }
 }
 
 /// Access to a peer we are attempting to connect to.
@@ -1632,6 +1788,8 @@ where
 {
     attempt: OccupiedEntry<'a, TPeerId, OutReachAttempt>,
     active_nodes: &'a mut CollectionStream<TInEvent, TOutEvent, THandler, InternalReachErr<TTrans::Error, TConnInfo>, THandlerErr, (), (TConnInfo, ConnectedPoint), TPeerId>,
+    /// Reference to the `denied_attempts` field of the parent.
+    denied_attempts: &'a mut FnvHashSet<ReachAttemptId>,
 }
 
 impl<'a, TTrans, TInEvent, TOutEvent, THandler, THandlerErr, TConnInfo, TPeerId>
@@ -1646,6 +1804,7 @@ where
     // borrows
     pub fn interrupt(self) {
         let attempt = self.attempt.remove();
+        self.denied_attempts.remove(&attempt.id);
         if self.active_nodes.interrupt(attempt.id).is_err() {
             // TODO: improve proof or remove; this is too complicated right now
             panic!("We retreived this attempt.id from out_reach_attempts. We insert in \
@@ -1780,17 +1939,20 @@ where
             panic!("Mismatch between conn_info PeerId and request PeerId");
         }
 
-        match self.nodes.active_nodes.add_connection((conn_info, connected_point), (), muxer, handler) {
+        let (outcome, id) = self.nodes.active_nodes.add_connection((conn_info, connected_point.clone()), (), muxer, handler);
+        match outcome {
             CollectionNodeAccept::NewEntry => {},
-            CollectionNodeAccept::ReplacedExisting { .. } =>
+            CollectionNodeAccept::AdditionalConnection =>
                 unreachable!("We can only build a PeerNotConnected if we don't have this peer in \
                               the collection yet"),
         }
+        self.nodes.reach_attempts.connected_points.insert(id, connected_point);
 
         PeerConnected {
             active_nodes: &mut self.nodes.active_nodes,
             connected_points: &mut self.nodes.reach_attempts.connected_points,
             out_reach_attempts: &mut self.nodes.reach_attempts.out_reach_attempts,
+            denied_attempts: &mut self.nodes.reach_attempts.denied_attempts,
             peer_id: self.peer_id,
         }
     }
@@ -1811,6 +1973,7 @@ where
                 },
             },
             active_nodes: &mut self.nodes.active_nodes,
+            denied_attempts: &mut self.nodes.reach_attempts.denied_attempts,
         }
     }
 }
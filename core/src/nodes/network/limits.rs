@@ -0,0 +1,211 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use std::{error, fmt};
+
+/// Limits on the number of connections and substreams that a `Network` keeps open or pending.
+///
+/// Every limit is optional and disabled by default. When a limit is reached, the connection or
+/// substream is refused and the `Network` produces a `NetworkEvent::ConnectionLimitReached`.
+///
+/// The limit on pending incoming connections is the exception: instead of refusing them, the
+/// `Network` stops accepting incoming connections from its listeners until there is room for
+/// them again, and no event is produced.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConnectionLimits {
+    max_pending_incoming: Option<u32>,
+    max_pending_outgoing: Option<u32>,
+    max_established_incoming: Option<u32>,
+    max_established_outgoing: Option<u32>,
+    max_established_total: Option<u32>,
+    max_established_per_peer: Option<u32>,
+    max_substreams: Option<u32>,
+}
+
+impl ConnectionLimits {
+    /// Limits the number of incoming connections that are being negotiated.
+    ///
+    /// Incoming connections beyond this limit are left to wait in the listeners.
+    pub fn with_max_pending_incoming(mut self, limit: Option<u32>) -> Self {
+        self.max_pending_incoming = limit;
+        self
+    }
+
+    /// Limits the number of outgoing connections that are being dialed or negotiated.
+    pub fn with_max_pending_outgoing(mut self, limit: Option<u32>) -> Self {
+        self.max_pending_outgoing = limit;
+        self
+    }
+
+    /// Limits the number of established connections that we received.
+    pub fn with_max_established_incoming(mut self, limit: Option<u32>) -> Self {
+        self.max_established_incoming = limit;
+        self
+    }
+
+    /// Limits the number of established connections that we opened.
+    pub fn with_max_established_outgoing(mut self, limit: Option<u32>) -> Self {
+        self.max_established_outgoing = limit;
+        self
+    }
+
+    /// Limits the total number of established connections, regardless of their direction.
+    pub fn with_max_established_total(mut self, limit: Option<u32>) -> Self {
+        self.max_established_total = limit;
+        self
+    }
+
+    /// Limits the number of established connections to a single peer.
+    pub fn with_max_established_per_peer(mut self, limit: Option<u32>) -> Self {
+        self.max_established_per_peer = limit;
+        self
+    }
+
+    /// Limits the total number of substreams that are open over all the established connections.
+    ///
+    /// Inbound substreams beyond this limit are refused. Outbound substreams are counted but
+    /// never refused, as opening them is under the control of the local node.
+    pub fn with_max_substreams(mut self, limit: Option<u32>) -> Self {
+        self.max_substreams = limit;
+        self
+    }
+
+    /// Returns the limit on pending incoming connections.
+    pub fn max_pending_incoming(&self) -> Option<u32> {
+        self.max_pending_incoming
+    }
+
+    /// Returns the limit on pending outgoing connections.
+    pub fn max_pending_outgoing(&self) -> Option<u32> {
+        self.max_pending_outgoing
+    }
+
+    /// Returns the limit on established incoming connections.
+    pub fn max_established_incoming(&self) -> Option<u32> {
+        self.max_established_incoming
+    }
+
+    /// Returns the limit on established outgoing connections.
+    pub fn max_established_outgoing(&self) -> Option<u32> {
+        self.max_established_outgoing
+    }
+
+    /// Returns the limit on the total number of established connections.
+    pub fn max_established_total(&self) -> Option<u32> {
+        self.max_established_total
+    }
+
+    /// Returns the limit on established connections to a single peer.
+    pub fn max_established_per_peer(&self) -> Option<u32> {
+        self.max_established_per_peer
+    }
+
+    /// Returns the limit on the total number of open substreams.
+    pub fn max_substreams(&self) -> Option<u32> {
+        self.max_substreams
+    }
+
+    /// Returns an error if `current` connections of the given kind leave no room for another one.
+    pub(crate) fn check(&self, kind: ConnectionLimitKind, current: usize) -> Result<(), ConnectionLimit> {
+        let limit = match kind {
+            ConnectionLimitKind::PendingIncoming => self.max_pending_incoming,
+            ConnectionLimitKind::PendingOutgoing => self.max_pending_outgoing,
+            ConnectionLimitKind::EstablishedIncoming => self.max_established_incoming,
+            ConnectionLimitKind::EstablishedOutgoing => self.max_established_outgoing,
+            ConnectionLimitKind::EstablishedTotal => self.max_established_total,
+            ConnectionLimitKind::EstablishedPerPeer => self.max_established_per_peer,
+            ConnectionLimitKind::Substreams => self.max_substreams,
+        };
+
+        match limit {
+            Some(limit) if current >= limit as usize => Err(ConnectionLimit { kind, limit }),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The kind of limit that has been reached.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ConnectionLimitKind {
+    /// Too many incoming connections are being negotiated.
+    ///
+    /// Never reported by the `Network`, which stops accepting incoming connections instead.
+    PendingIncoming,
+    /// Too many outgoing connections are being dialed or negotiated.
+    PendingOutgoing,
+    /// Too many established incoming connections.
+    EstablishedIncoming,
+    /// Too many established outgoing connections.
+    EstablishedOutgoing,
+    /// Too many established connections in total.
+    EstablishedTotal,
+    /// Too many established connections to the same peer.
+    EstablishedPerPeer,
+    /// Too many open substreams in total.
+    Substreams,
+}
+
+impl fmt::Display for ConnectionLimitKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConnectionLimitKind::PendingIncoming => f.write_str("pending incoming connections"),
+            ConnectionLimitKind::PendingOutgoing => f.write_str("pending outgoing connections"),
+            ConnectionLimitKind::EstablishedIncoming => f.write_str("established incoming connections"),
+            ConnectionLimitKind::EstablishedOutgoing => f.write_str("established outgoing connections"),
+            ConnectionLimitKind::EstablishedTotal => f.write_str("established connections"),
+            ConnectionLimitKind::EstablishedPerPeer => f.write_str("established connections per peer"),
+            ConnectionLimitKind::Substreams => f.write_str("open substreams"),
+        }
+    }
+}
+
+/// A connection or substream has been refused because a limit of the `ConnectionLimits` was
+/// reached.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ConnectionLimit {
+    /// The limit that was reached.
+    pub kind: ConnectionLimitKind,
+    /// The configured value of this limit.
+    pub limit: u32,
+}
+
+impl fmt::Display for ConnectionLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Connection limit reached: at most {} {}", self.limit, self.kind)
+    }
+}
+
+impl error::Error for ConnectionLimit {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_refuses_at_limit() {
+        let limits = ConnectionLimits::default().with_max_established_per_peer(Some(2));
+        assert!(limits.check(ConnectionLimitKind::EstablishedPerPeer, 1).is_ok());
+        assert_eq!(
+            limits.check(ConnectionLimitKind::EstablishedPerPeer, 2),
+            Err(ConnectionLimit { kind: ConnectionLimitKind::EstablishedPerPeer, limit: 2 })
+        );
+        assert!(limits.check(ConnectionLimitKind::EstablishedTotal, 100).is_ok());
+    }
+}
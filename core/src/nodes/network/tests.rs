@@ -162,7 +162,7 @@ fn broadcasted_events_reach_active_nodes() {
             let mut network = network_fut.lock();
             match network.poll() {
                 Async::Ready(event) => {
-                    assert_matches!(event, NetworkEvent::NodeEvent { conn_info: _, event: inner_event } => {
+                    assert_matches!(event, NetworkEvent::NodeEvent { conn_info: _, event: inner_event, .. } => {
                         // The event we sent reached the node and triggered sending the out event we told it to return
                         assert_matches!(inner_event, OutEvent::Custom("from handler 1"));
                     });
@@ -372,20 +372,48 @@ fn yields_node_error_when_there_is_an_error_after_successful_connect() {
     let expected_peer_id = peer_id.clone();
     rt.block_on(future::poll_fn(move || -> Poll<_, ()> {
         let mut network = network_fut.lock();
-        assert_matches!(network.poll(), Async::Ready(NetworkEvent::NodeClosed { conn_info, .. }) => {
+        assert_matches!(network.poll(), Async::Ready(NetworkEvent::NodeClosed { conn_info, num_established, .. }) => {
             assert_eq!(conn_info, expected_peer_id);
+            assert_eq!(num_established, 0);
         });
         Ok(Async::Ready(()))
     })).expect("tokio works");
 }
 
 #[test]
-fn local_prio_equivalence_relation() {
-    for _ in 0..1000 {
-        let a = PeerId::random();
-        let b = PeerId::random();
-        assert_ne!(has_dial_prio(&a, &b), has_dial_prio(&b, &a));
+fn second_connection_to_peer_is_kept() {
+    // Both connections to a peer are kept, instead of a dial priority deciding which one
+    // replaces the other.
+    let mut transport = DummyTransport::new();
+    let peer_id = PeerId::random();
+    transport.set_next_peer_id(&peer_id);
+    let mut network = Network::<_, _, _, Handler, _>::new(transport, PeerId::random());
+    for _ in 0 .. 2 {
+        let addr = "/ip4/127.0.0.1/tcp/1234".parse::<Multiaddr>().expect("bad multiaddr");
+        network.dial(addr, Handler::default()).expect("dialing works");
     }
+
+    let network = Arc::new(Mutex::new(network));
+    let mut rt = Runtime::new().unwrap();
+    let mut established = Vec::new();
+    while established.len() < 2 {
+        let network_fut = network.clone();
+        let event = rt.block_on(future::poll_fn(move || -> Poll<_, ()> {
+            match network_fut.lock().poll() {
+                Async::Ready(NetworkEvent::Connected { conn_info, connection_id, num_established, .. }) =>
+                    Ok(Async::Ready(Some((conn_info, connection_id, num_established.get())))),
+                _ => Ok(Async::Ready(None))
+            }
+        })).expect("tokio works");
+        established.extend(event);
+    }
+
+    assert!(established.iter().all(|(conn_info, _, _)| *conn_info == peer_id));
+    assert_ne!(established[0].1, established[1].1);
+    assert_eq!(established.iter().map(|(_, _, n)| *n).collect::<Vec<_>>(), vec![1, 2]);
+    let mut network = network.lock();
+    let peer = network.peer(peer_id).into_connected().expect("the peer is connected");
+    assert_eq!(peer.connections().count(), 2);
 }
 
 #[test]
@@ -443,3 +471,95 @@ fn limit_incoming_connections() {
         assert!(network.incoming_negotiated().count() <= (limit as usize));
     }
 }
+
+#[test]
+fn limit_pending_outgoing_connections() {
+    let limits = ConnectionLimits::default().with_max_pending_outgoing(Some(1));
+    let mut network = Network::<_, _, _, Handler, _>::new_with_limits(DummyTransport::new(), PeerId::random(), limits);
+    let addr1 = "/ip4/127.0.0.1/tcp/1234".parse::<Multiaddr>().expect("bad multiaddr");
+    let addr2 = "/ip4/127.0.0.1/tcp/1235".parse::<Multiaddr>().expect("bad multiaddr");
+    assert!(network.dial(addr1, Handler::default()).is_ok());
+    assert!(network.dial(addr2.clone(), Handler::default()).is_ok());
+
+    let network = Arc::new(Mutex::new(network));
+    let mut rt = Runtime::new().unwrap();
+    let mut refused = None;
+    while refused.is_none() {
+        let network_fut = network.clone();
+        refused = rt.block_on(future::poll_fn(move || -> Poll<_, ()> {
+            match network_fut.lock().poll() {
+                Async::Ready(NetworkEvent::ConnectionLimitReached { limit, peer_id, endpoint }) => {
+                    Ok(Async::Ready(Some((limit, peer_id, endpoint))))
+                }
+                _ => Ok(Async::Ready(None))
+            }
+        })).expect("tokio works");
+    }
+
+    let (limit, peer_id, endpoint) = refused.unwrap();
+    assert_eq!(limit, ConnectionLimit { kind: ConnectionLimitKind::PendingOutgoing, limit: 1 });
+    assert_eq!(peer_id, None);
+    assert_matches!(endpoint, ConnectedPoint::Dialer { address, .. } => assert_eq!(address, addr2));
+    assert!(network.lock().reach_attempts.denied_attempts.is_empty());
+}
+
+#[test]
+fn interrupting_a_refused_dial_forgets_it() {
+    let limits = ConnectionLimits::default().with_max_pending_outgoing(Some(0));
+    let mut network = Network::<_, _, _, Handler, _>::new_with_limits(DummyTransport::new(), PeerId::random(), limits);
+    let addr = "/ip4/127.0.0.1/tcp/1234".parse::<Multiaddr>().expect("bad multiaddr");
+    let peer_id = PeerId::random();
+    network.peer(peer_id.clone())
+        .into_not_connected()
+        .expect("we are not connected to a random peer")
+        .connect(addr.clone(), Handler::default());
+    network.peer(peer_id)
+        .into_pending_connect()
+        .expect("the refused attempt is pending until polled")
+        .interrupt();
+
+    // The interrupted attempt must not be counted as refused anymore.
+    assert_eq!(network.num_pending_outgoing(), 0);
+    assert!(network.dial(addr, Handler::default()).is_ok());
+}
+
+#[test]
+fn limit_established_connections_per_peer() {
+    let peer_id = PeerId::random();
+    let mut transport = DummyTransport::new();
+    transport.set_next_peer_id(&peer_id);
+    let limits = ConnectionLimits::default().with_max_established_per_peer(Some(1));
+    let mut network = Network::<_, _, _, Handler, _>::new_with_limits(transport, PeerId::random(), limits);
+    let addr = "/ip4/127.0.0.1/tcp/1234".parse::<Multiaddr>().expect("bad multiaddr");
+    assert!(network.dial(addr.clone(), Handler::default()).is_ok());
+    assert!(network.dial(addr, Handler::default()).is_ok());
+
+    let network = Arc::new(Mutex::new(network));
+    let mut rt = Runtime::new().unwrap();
+    let (mut connected, mut refused) = (0, None);
+    while connected == 0 || refused.is_none() {
+        let network_fut = network.clone();
+        let event = rt.block_on(future::poll_fn(move || -> Poll<_, ()> {
+            match network_fut.lock().poll() {
+                Async::Ready(NetworkEvent::Connected { .. }) => Ok(Async::Ready(Some(None))),
+                Async::Ready(NetworkEvent::ConnectionLimitReached { limit, peer_id, .. }) => {
+                    Ok(Async::Ready(Some(Some((limit, peer_id)))))
+                }
+                _ => Ok(Async::Ready(None))
+            }
+        })).expect("tokio works");
+        match event {
+            Some(None) => connected += 1,
+            Some(Some(r)) => refused = Some(r),
+            None => {}
+        }
+    }
+
+    assert_eq!(connected, 1);
+    let (limit, refused_peer) = refused.unwrap();
+    assert_eq!(limit.kind, ConnectionLimitKind::EstablishedPerPeer);
+    assert_eq!(refused_peer, Some(peer_id.clone()));
+    let mut network = network.lock();
+    let peer = network.peer(peer_id).into_connected().expect("the first connection is kept");
+    assert_eq!(peer.num_connections().get(), 1);
+}
@@ -18,12 +18,13 @@
 // FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // DEALINGS IN THE SOFTWARE.
 
-use futures::prelude::*;
-use crate::muxing;
+use futures::{prelude::*, task};
+use futures03::task::Poll as Poll03;
+use crate::{compat::with_context, muxing};
 use smallvec::SmallVec;
 use std::fmt;
 use std::io::Error as IoError;
-use std::sync::Arc;
+use std::sync::{Arc, atomic::{AtomicUsize, Ordering}};
 
 // Implementation notes
 // =================
@@ -56,6 +57,56 @@ where
     muxer: Arc<TMuxer>,
     /// List of substreams we are currently opening.
     outbound_substreams: SmallVec<[(TUserData, TMuxer::OutboundSubstream); 8]>,
+    /// Limit on the substreams that are open, shared with other nodes.
+    substream_limit: Option<SubstreamLimit>,
+    /// True if an inbound substream has been refused because of `substream_limit` since the
+    /// last call to `take_substream_limit_reached`.
+    substream_limit_reached: bool,
+}
+
+/// Limit on the number of substreams that are open at the same time, shared by all the nodes
+/// that have a clone of it.
+#[derive(Debug, Clone)]
+pub(crate) struct SubstreamLimit {
+    /// Number of substreams currently open.
+    open: Arc<AtomicUsize>,
+    /// Maximum number of substreams.
+    max: usize,
+}
+
+impl SubstreamLimit {
+    /// Creates a limit of `max` open substreams.
+    pub(crate) fn new(max: usize) -> Self {
+        SubstreamLimit { open: Arc::new(AtomicUsize::new(0)), max }
+    }
+
+    /// Counts a new substream, unless the limit is already reached.
+    fn try_acquire(&self) -> Option<SubstreamPermit> {
+        if self.open.fetch_add(1, Ordering::SeqCst) >= self.max {
+            self.open.fetch_sub(1, Ordering::SeqCst);
+            return None;
+        }
+        Some(SubstreamPermit { open: self.open.clone() })
+    }
+
+    /// Counts a new substream, even if the limit is reached.
+    fn acquire(&self) -> SubstreamPermit {
+        self.open.fetch_add(1, Ordering::SeqCst);
+        SubstreamPermit { open: self.open.clone() }
+    }
+}
+
+/// A substream counted by a `SubstreamLimit`. The substream stops being counted when the permit
+/// is dropped.
+#[derive(Debug)]
+pub(crate) struct SubstreamPermit {
+    open: Arc<AtomicUsize>,
+}
+
+impl Drop for SubstreamPermit {
+    fn drop(&mut self) {
+        self.open.fetch_sub(1, Ordering::SeqCst);
+    }
 }
 
 /// Future that signals the remote that we have closed the connection.
@@ -103,9 +154,23 @@ where
         NodeStream {
             muxer: Arc::new(muxer),
             outbound_substreams: SmallVec::new(),
+            substream_limit: None,
+            substream_limit_reached: false,
         }
     }
 
+    /// Sets the limit on open substreams. Inbound substreams beyond the limit are refused, while
+    /// the outbound substreams are counted but never refused.
+    pub(crate) fn set_substream_limit(&mut self, limit: Option<SubstreamLimit>) {
+        self.substream_limit = limit;
+    }
+
+    /// Returns `true` if an inbound substream has been refused because of the substream limit
+    /// since the last call to this method.
+    pub(crate) fn take_substream_limit_reached(&mut self) -> bool {
+        std::mem::replace(&mut self.substream_limit_reached, false)
+    }
+
     /// Starts the process of opening a new outbound substream.
     ///
     /// After calling this method, polling the stream should eventually produce either an
@@ -145,33 +210,50 @@ where
     /// Provides an API similar to `Future`.
     pub fn poll(&mut self) -> Poll<NodeEvent<TMuxer, TUserData>, IoError> {
         // Polling inbound substream.
-        match self.muxer.poll_inbound().map_err(|e| e.into())? {
-            Async::Ready(substream) => {
-                let substream = muxing::substream_from_ref(self.muxer.clone(), substream);
+        match with_context(|cx| self.muxer.poll_inbound(cx)) {
+            Poll03::Ready(Err(err)) => return Err(err.into()),
+            Poll03::Ready(Ok(substream)) => {
+                let permit = match self.substream_limit {
+                    Some(ref limit) => match limit.try_acquire() {
+                        Some(permit) => Some(permit),
+                        None => {
+                            // The substream is refused. We yield instead of looping, as the
+                            // remote may open substreams faster than we can refuse them.
+                            self.muxer.destroy_substream(substream);
+                            self.substream_limit_reached = true;
+                            task::current().notify();
+                            return Ok(Async::NotReady);
+                        }
+                    },
+                    None => None,
+                };
+                let mut substream = muxing::substream_from_ref(self.muxer.clone(), substream);
+                substream.set_permit(permit);
                 return Ok(Async::Ready(NodeEvent::InboundSubstream {
                     substream,
                 }));
             }
-            Async::NotReady => {}
+            Poll03::Pending => {}
         }
 
         // Polling outbound substreams.
         // We remove each element from `outbound_substreams` one by one and add them back.
         for n in (0..self.outbound_substreams.len()).rev() {
             let (user_data, mut outbound) = self.outbound_substreams.swap_remove(n);
-            match self.muxer.poll_outbound(&mut outbound) {
-                Ok(Async::Ready(substream)) => {
-                    let substream = muxing::substream_from_ref(self.muxer.clone(), substream);
+            match with_context(|cx| self.muxer.poll_outbound(cx, &mut outbound)) {
+                Poll03::Ready(Ok(substream)) => {
+                    let mut substream = muxing::substream_from_ref(self.muxer.clone(), substream);
+                    substream.set_permit(self.substream_limit.as_ref().map(SubstreamLimit::acquire));
                     self.muxer.destroy_outbound(outbound);
                     return Ok(Async::Ready(NodeEvent::OutboundSubstream {
                         user_data,
                         substream,
                     }));
                 }
-                Ok(Async::NotReady) => {
+                Poll03::Pending => {
                     self.outbound_substreams.push((user_data, outbound));
                 }
-                Err(err) => {
+                Poll03::Ready(Err(err)) => {
                     self.muxer.destroy_outbound(outbound);
                     return Err(err.into());
                 }
@@ -216,7 +298,11 @@ where
     type Error = IoError;
 
     fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
-        self.muxer.close().map_err(|e| e.into())
+        match with_context(|cx| self.muxer.close(cx)) {
+            Poll03::Ready(Ok(())) => Ok(Async::Ready(())),
+            Poll03::Ready(Err(err)) => Err(err.into()),
+            Poll03::Pending => Ok(Async::NotReady),
+        }
     }
 }
 
@@ -255,7 +341,7 @@ where
 
 #[cfg(test)]
 mod node_stream {
-    use super::{NodeEvent, NodeStream};
+    use super::{NodeEvent, NodeStream, SubstreamLimit};
     use crate::tests::dummy_muxer::{DummyMuxer, DummyConnectionState};
     use assert_matches::assert_matches;
     use futures::prelude::*;
@@ -296,26 +382,81 @@ mod node_stream {
 
     #[test]
     fn poll_keeps_outbound_substreams_when_the_outgoing_connection_is_not_ready() {
-        let mut muxer = DummyMuxer::new();
-        // ensure muxer.poll_inbound() returns Async::NotReady
-        muxer.set_inbound_connection_state(DummyConnectionState::Pending);
-        // ensure muxer.poll_outbound() returns Async::NotReady
-        muxer.set_outbound_connection_state(DummyConnectionState::Pending);
-        let mut ns = NodeStream::<_, Vec<u8>>::new(muxer);
-        ns.open_substream(vec![1]);
-        ns.poll().unwrap(); // poll past inbound
-        ns.poll().unwrap(); // poll outbound
-        assert!(format!("{:?}", ns).contains("outbound_substreams: 1"));
+        let mut task = MockTask::new();
+        task.enter(|| {
+            let mut muxer = DummyMuxer::new();
+            // ensure muxer.poll_inbound() returns Async::NotReady
+            muxer.set_inbound_connection_state(DummyConnectionState::Pending);
+            // ensure muxer.poll_outbound() returns Async::NotReady
+            muxer.set_outbound_connection_state(DummyConnectionState::Pending);
+            let mut ns = NodeStream::<_, Vec<u8>>::new(muxer);
+            ns.open_substream(vec![1]);
+            ns.poll().unwrap(); // poll past inbound
+            ns.poll().unwrap(); // poll outbound
+            assert!(format!("{:?}", ns).contains("outbound_substreams: 1"));
+        });
     }
 
     #[test]
     fn poll_returns_incoming_substream() {
-        let mut muxer = DummyMuxer::new();
-        // ensure muxer.poll_inbound() returns Async::Ready(subs)
-        muxer.set_inbound_connection_state(DummyConnectionState::Opened);
-        let mut ns = NodeStream::<_, Vec<u8>>::new(muxer);
-        assert_matches!(ns.poll(), Ok(Async::Ready(node_event)) => {
-            assert_matches!(node_event, NodeEvent::InboundSubstream{ substream: _ });
+        let mut task = MockTask::new();
+        task.enter(|| {
+            let mut muxer = DummyMuxer::new();
+            // ensure muxer.poll_inbound() returns Async::Ready(subs)
+            muxer.set_inbound_connection_state(DummyConnectionState::Opened);
+            let mut ns = NodeStream::<_, Vec<u8>>::new(muxer);
+            assert_matches!(ns.poll(), Ok(Async::Ready(node_event)) => {
+                assert_matches!(node_event, NodeEvent::InboundSubstream{ substream: _ });
+            });
+        });
+    }
+
+    #[test]
+    fn inbound_substreams_beyond_the_limit_are_refused() {
+        let mut task = MockTask::new();
+        task.enter(|| {
+            let mut muxer = DummyMuxer::new();
+            muxer.set_inbound_connection_state(DummyConnectionState::Opened);
+            let mut ns = NodeStream::<_, Vec<u8>>::new(muxer);
+            ns.set_substream_limit(Some(SubstreamLimit::new(1)));
+
+            let substream = match ns.poll() {
+                Ok(Async::Ready(NodeEvent::InboundSubstream { substream })) => substream,
+                _ => panic!("expected an inbound substream"),
+            };
+            assert!(!ns.take_substream_limit_reached());
+            assert_matches!(ns.poll(), Ok(Async::NotReady));
+            assert!(ns.take_substream_limit_reached());
+            assert!(!ns.take_substream_limit_reached());
+
+            // Closing the open substream makes room for a new one.
+            drop(substream);
+            assert_matches!(ns.poll(), Ok(Async::Ready(NodeEvent::InboundSubstream { .. })));
+            assert!(!ns.take_substream_limit_reached());
+        });
+    }
+
+    #[test]
+    fn outbound_substreams_count_towards_the_limit() {
+        let mut task = MockTask::new();
+        task.enter(|| {
+            let mut muxer = DummyMuxer::new();
+            muxer.set_outbound_connection_state(DummyConnectionState::Opened);
+            let limit = SubstreamLimit::new(1);
+            let mut ns = NodeStream::<_, Vec<u8>>::new(muxer.clone());
+            ns.set_substream_limit(Some(limit.clone()));
+            ns.open_substream(vec![1]);
+            let _outbound = match ns.poll() {
+                Ok(Async::Ready(NodeEvent::OutboundSubstream { substream, .. })) => substream,
+                _ => panic!("expected an outbound substream"),
+            };
+
+            // The limit is shared with a second node, which now refuses inbound substreams.
+            muxer.set_inbound_connection_state(DummyConnectionState::Opened);
+            let mut other = NodeStream::<_, Vec<u8>>::new(muxer);
+            other.set_substream_limit(Some(limit));
+            assert_matches!(other.poll(), Ok(Async::NotReady));
+            assert!(other.take_substream_limit_reached());
         });
     }
 }
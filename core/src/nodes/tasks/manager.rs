@@ -20,14 +20,17 @@
 
 use crate::{
     PeerId,
+    compat::{self, Compat01As03},
+    executor::{Executor, TokioExecutor},
     muxing::StreamMuxer,
     nodes::{
         handled_node::{HandledNode, IntoNodeHandler, NodeHandler},
-        node::Substream
+        node::{Substream, SubstreamLimit}
     }
 };
 use fnv::FnvHashMap;
-use futures::{prelude::*, future::Executor, sync::mpsc};
+use futures::{prelude::*, sync::mpsc};
+use futures03::{FutureExt, future::BoxFuture};
 use smallvec::SmallVec;
 use std::{collections::hash_map::{Entry, OccupiedEntry}, error, fmt};
 use super::{TaskId, task::{Task, FromTaskMessage, ToTaskMessage}, Error};
@@ -67,15 +70,21 @@ pub struct Manager<I, O, H, E, HE, T, C = PeerId> {
     /// List of node tasks to spawn.
     to_spawn: SmallVec<[Box<dyn Future<Item = (), Error = ()> + Send>; 8]>,
 
-    /// If no tokio executor is available, we move tasks to this list, and futures are polled on
+    /// Executor that runs the node tasks.
+    executor: Box<dyn Executor + Send>,
+
+    /// If the executor can't run a task, we move it to this list, and futures are polled on
     /// the current thread instead.
-    local_spawns: Vec<Box<dyn Future<Item = (), Error = ()> + Send>>,
+    local_spawns: Vec<BoxFuture<'static, ()>>,
 
     /// Sender to emit events to the outside. Meant to be cloned and sent to tasks.
     events_tx: mpsc::Sender<(FromTaskMessage<O, H, E, HE, C>, TaskId)>,
 
     /// Receiver side for the events.
-    events_rx: mpsc::Receiver<(FromTaskMessage<O, H, E, HE, C>, TaskId)>
+    events_rx: mpsc::Receiver<(FromTaskMessage<O, H, E, HE, C>, TaskId)>,
+
+    /// Limit on the open substreams, shared by all the tasks.
+    substream_limit: Option<SubstreamLimit>
 }
 
 impl<I, O, H, E, HE, T, C> fmt::Debug for Manager<I, O, H, E, HE, T, C>
@@ -133,23 +142,42 @@ pub enum Event<'a, I, O, H, E, HE, T, C = PeerId> {
         task: TaskEntry<'a, I, T>,
         /// The produced event.
         event: O
+    },
+
+    /// A task has refused inbound substreams because of the substream limit.
+    SubstreamLimitReached {
+        /// The task that refused the substreams.
+        task: TaskEntry<'a, I, T>
     }
 }
 
 impl<I, O, H, E, HE, T, C> Manager<I, O, H, E, HE, T, C> {
-    /// Creates a new task manager.
+    /// Creates a new task manager that spawns its tasks with the [`TokioExecutor`].
     pub fn new() -> Self {
+        Self::with_executor(Box::new(TokioExecutor))
+    }
+
+    /// Creates a new task manager that spawns its tasks with the given executor.
+    pub fn with_executor(executor: Box<dyn Executor + Send>) -> Self {
         let (tx, rx) = mpsc::channel(1);
         Self {
             tasks: FnvHashMap::default(),
             next_task_id: TaskId(0),
+            executor,
             to_spawn: SmallVec::new(),
             local_spawns: Vec::new(),
             events_tx: tx,
-            events_rx: rx
+            events_rx: rx,
+            substream_limit: None
         }
     }
 
+    /// Sets the limit on the substreams that are open over all the nodes of the tasks that are
+    /// added afterwards.
+    pub(crate) fn set_substream_limit(&mut self, limit: Option<SubstreamLimit>) {
+        self.substream_limit = limit;
+    }
+
     /// Adds to the manager a future that tries to reach a node.
     ///
     /// This method spawns a task dedicated to resolving this future and
@@ -174,7 +202,9 @@ impl<I, O, H, E, HE, T, C> Manager<I, O, H, E, HE, T, C> {
         let (tx, rx) = mpsc::channel(4);
         self.tasks.insert(task_id, TaskInfo { sender: tx, user_data, pending: None });
 
-        let task = Box::new(Task::new(task_id, self.events_tx.clone(), rx, future, handler));
+        let task = Task::new(task_id, self.events_tx.clone(), rx, future, handler)
+            .with_substream_limit(self.substream_limit.clone());
+        let task = Box::new(task);
         self.to_spawn.push(task);
         task_id
     }
@@ -204,8 +234,10 @@ impl<I, O, H, E, HE, T, C> Manager<I, O, H, E, HE, T, C> {
         let (tx, rx) = mpsc::channel(4);
         self.tasks.insert(task_id, TaskInfo { sender: tx, user_data, pending: None });
 
+        let mut node = HandledNode::new(muxer, handler);
+        node.set_substream_limit(self.substream_limit.clone());
         let task: Task<futures::future::Empty<_, _>, _, _, _, _, _, _> =
-            Task::node(task_id, self.events_tx.clone(), rx, HandledNode::new(muxer, handler));
+            Task::node(task_id, self.events_tx.clone(), rx, node);
 
         self.to_spawn.push(Box::new(task));
         task_id
@@ -287,24 +319,23 @@ impl<I, O, H, E, HE, T, C> Manager<I, O, H, E, HE, T, C> {
     /// Provides an API similar to `Stream`, except that it cannot produce an error.
     pub fn poll(&mut self) -> Async<Event<I, O, H, E, HE, T, C>> {
         for to_spawn in self.to_spawn.drain() {
-            // We try to use the default executor, but fall back to polling the task manually if
-            // no executor is available. This makes it possible to use the core in environments
-            // outside of tokio.
-            let executor = tokio_executor::DefaultExecutor::current();
-            if let Err(err) = executor.execute(to_spawn) {
-                self.local_spawns.push(err.into_future())
+            // We try to use the executor, but fall back to polling the task manually if it
+            // refuses the task. This makes it possible to use the core in environments outside
+            // of tokio, or to process all the nodes on the current task.
+            //
+            // It would normally be desirable to either report or log when a background task
+            // errors. However the default tokio executor doesn't do anything in case of error,
+            // and therefore we mimic this behaviour by also not doing anything.
+            let to_spawn = Compat01As03::new(to_spawn).map(|_| ()).boxed();
+            if let Err(task) = self.executor.exec(to_spawn) {
+                self.local_spawns.push(task)
             }
         }
 
         for n in (0 .. self.local_spawns.len()).rev() {
             let mut task = self.local_spawns.swap_remove(n);
-            match task.poll() {
-                Ok(Async::Ready(())) => {}
-                Ok(Async::NotReady) => self.local_spawns.push(task),
-                // It would normally be desirable to either report or log when a background task
-                // errors. However the default tokio executor doesn't do anything in case of error,
-                // and therefore we mimic this behaviour by also not doing anything.
-                Err(()) => {}
+            if compat::with_context(|cx| task.as_mut().poll(cx)).is_pending() {
+                self.local_spawns.push(task)
             }
         }
 
@@ -341,6 +372,13 @@ impl<I, O, H, E, HE, T, C> Manager<I, O, H, E, HE, T, C> {
                     },
                     conn_info
                 },
+            FromTaskMessage::SubstreamLimitReached =>
+                Event::SubstreamLimitReached {
+                    task: match self.tasks.entry(task_id) {
+                        Entry::Occupied(inner) => TaskEntry { inner },
+                        Entry::Vacant(_) => panic!("poll_inner only returns valid TaskIds; QED")
+                    }
+                },
             FromTaskMessage::TaskClosed(result, handler) => {
                 let entry = self.tasks.remove(&task_id)
                     .expect("poll_inner only returns valid TaskIds; QED");
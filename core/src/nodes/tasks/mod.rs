@@ -41,5 +41,5 @@ pub use manager::{ClosedTask, TaskEntry, Manager, Event, StartTakeOver};
 
 /// Task identifier.
 #[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
-pub struct TaskId(usize);
+pub struct TaskId(pub(crate) usize);
 
@@ -22,7 +22,7 @@ use crate::{
     muxing::StreamMuxer,
     nodes::{
         handled_node::{HandledNode, IntoNodeHandler, NodeHandler},
-        node::{Close, Substream}
+        node::{Close, Substream, SubstreamLimit}
     }
 };
 use futures::{prelude::*, stream, sync::mpsc};
@@ -47,7 +47,9 @@ pub enum FromTaskMessage<T, H, E, HE, C> {
     /// The task closed.
     TaskClosed(Error<E, HE>, Option<H>),
     /// An event from the node.
-    NodeEvent(T)
+    NodeEvent(T),
+    /// Inbound substreams have been refused because of the substream limit.
+    SubstreamLimitReached
 }
 
 /// Implementation of [`Future`] that handles a single node.
@@ -70,7 +72,10 @@ where
     state: State<F, M, H, I, O, E, C>,
 
     /// Channels to keep alive for as long as we don't have an acknowledgment from the remote.
-    taken_over: SmallVec<[mpsc::Sender<ToTaskMessage<I>>; 1]>
+    taken_over: SmallVec<[mpsc::Sender<ToTaskMessage<I>>; 1]>,
+
+    /// Limit on the open substreams to apply to the node once it is reached.
+    substream_limit: Option<SubstreamLimit>
 }
 
 impl<F, M, H, I, O, E, C> Task<F, M, H, I, O, E, C>
@@ -92,7 +97,8 @@ where
             sender: s,
             receiver: r.fuse(),
             state: State::Future { future: f, handler: h, events_buffer: Vec::new() },
-            taken_over: SmallVec::new()
+            taken_over: SmallVec::new(),
+            substream_limit: None
         }
     }
 
@@ -108,9 +114,16 @@ where
             sender: s,
             receiver: r.fuse(),
             state: State::Node(n),
-            taken_over: SmallVec::new()
+            taken_over: SmallVec::new(),
+            substream_limit: None
         }
     }
+
+    /// Sets the limit on the open substreams of the node, once it is reached.
+    pub(crate) fn with_substream_limit(mut self, limit: Option<SubstreamLimit>) -> Self {
+        self.substream_limit = limit;
+        self
+    }
 }
 
 /// State of the future.
@@ -191,6 +204,7 @@ where
                     match future.poll() {
                         Ok(Async::Ready((conn_info, muxer))) => {
                             let mut node = HandledNode::new(muxer, handler.into_handler(&conn_info));
+                            node.set_substream_limit(self.substream_limit.take());
                             for event in events_buffer {
                                 node.inject_event(event)
                             }
@@ -233,6 +247,13 @@ where
                         }
                         match node.poll() {
                             Ok(Async::NotReady) => {
+                                if node.take_substream_limit_reached() {
+                                    self.state = State::SendEvent {
+                                        node: Some(node),
+                                        event: FromTaskMessage::SubstreamLimitReached
+                                    };
+                                    continue 'poll
+                                }
                                 self.state = State::Node(node);
                                 return Ok(Async::NotReady)
                             }
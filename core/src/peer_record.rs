@@ -0,0 +1,193 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Signed records of the addresses of a peer.
+//!
+//! Addresses received from other peers can't be trusted: a malicious peer can claim that any
+//! peer is reachable at any address. A [`PeerRecord`] holds the addresses of a peer signed by
+//! that peer, so that it can be forwarded by third parties without them being able to alter it.
+
+use crate::{Multiaddr, PeerId, peer_record_proto};
+use crate::identity::{Keypair, error::{DecodingError, SigningError}};
+use crate::signed_envelope::{ReadPayloadError, SignedEnvelope};
+use protobuf::Message;
+use std::{convert::TryFrom, error, fmt, time::{SystemTime, UNIX_EPOCH}};
+
+/// Domain-separation string of the signature of peer records.
+const DOMAIN_SEPARATION: &str = "libp2p-peer-record";
+
+/// Payload type of peer records in a `SignedEnvelope`.
+const PAYLOAD_TYPE: &[u8] = &[0x03, 0x01];
+
+/// The addresses of a peer, signed by that peer.
+///
+/// The records of a same peer are ordered by their sequence number: a record supersedes the
+/// records with a lower sequence number.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeerRecord {
+    peer_id: PeerId,
+    seq: u64,
+    addresses: Vec<Multiaddr>,
+    envelope: SignedEnvelope,
+}
+
+impl PeerRecord {
+    /// Builds a record of the given addresses, signed with the given key.
+    ///
+    /// The sequence number is the current time in milliseconds since the Unix epoch, so that
+    /// the latest record of a peer supersedes the previous ones.
+    pub fn new(key: &Keypair, addresses: Vec<Multiaddr>) -> Result<PeerRecord, SigningError> {
+        let seq = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() * 1000 + u64::from(d.subsec_millis()))
+            .unwrap_or(0);
+        PeerRecord::with_seq(key, seq, addresses)
+    }
+
+    /// Same as `new`, but with an explicit sequence number.
+    pub fn with_seq(key: &Keypair, seq: u64, addresses: Vec<Multiaddr>) -> Result<PeerRecord, SigningError> {
+        let peer_id = key.public().into_peer_id();
+
+        let mut msg = peer_record_proto::PeerRecord::new();
+        msg.set_peer_id(peer_id.as_bytes().to_vec());
+        msg.set_seq(seq);
+        for address in &addresses {
+            let mut info = peer_record_proto::PeerRecord_AddressInfo::new();
+            info.set_multiaddr(address.to_vec());
+            msg.mut_addresses().push(info);
+        }
+        let payload = msg.write_to_bytes().expect("writing protobuf to a Vec never fails; QED");
+
+        let envelope = SignedEnvelope::new(key, DOMAIN_SEPARATION, PAYLOAD_TYPE.to_vec(), payload)?;
+        Ok(PeerRecord { peer_id, seq, addresses, envelope })
+    }
+
+    /// Extracts a record from a signed envelope, checking the signature and that the record
+    /// has been signed by the peer it describes.
+    pub fn from_signed_envelope(envelope: SignedEnvelope) -> Result<PeerRecord, FromEnvelopeError> {
+        let payload = envelope.payload(DOMAIN_SEPARATION, PAYLOAD_TYPE)
+            .map_err(FromEnvelopeError::BadPayload)?;
+        let msg: peer_record_proto::PeerRecord = protobuf::parse_from_bytes(payload)
+            .map_err(|e| FromEnvelopeError::InvalidRecord(DecodingError::new("PeerRecord").source(e)))?;
+
+        let peer_id = PeerId::from_bytes(msg.get_peer_id().to_vec())
+            .map_err(|_| FromEnvelopeError::InvalidRecord(DecodingError::new("Invalid peer id")))?;
+        if peer_id != envelope.signing_key().clone().into_peer_id() {
+            return Err(FromEnvelopeError::MismatchedSignature);
+        }
+        let addresses = msg.get_addresses()
+            .iter()
+            .map(|info| Multiaddr::try_from(info.get_multiaddr().to_vec()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| FromEnvelopeError::InvalidRecord(DecodingError::new("Invalid address").source(e)))?;
+
+        Ok(PeerRecord { peer_id, seq: msg.get_seq(), addresses, envelope })
+    }
+
+    /// Returns the envelope holding the signed record, in order to transmit it.
+    pub fn to_signed_envelope(&self) -> SignedEnvelope {
+        self.envelope.clone()
+    }
+
+    /// Same as `to_signed_envelope`, but consumes the record.
+    pub fn into_signed_envelope(self) -> SignedEnvelope {
+        self.envelope
+    }
+
+    /// Returns the peer the record describes, which is also the signer of the record.
+    pub fn peer_id(&self) -> &PeerId {
+        &self.peer_id
+    }
+
+    /// Returns the sequence number of the record.
+    pub fn seq(&self) -> u64 {
+        self.seq
+    }
+
+    /// Returns the addresses of the peer.
+    pub fn addresses(&self) -> &[Multiaddr] {
+        &self.addresses
+    }
+}
+
+/// Error when extracting a `PeerRecord` from a `SignedEnvelope`.
+#[derive(Debug)]
+pub enum FromEnvelopeError {
+    /// The envelope doesn't hold a validly signed peer record.
+    BadPayload(ReadPayloadError),
+    /// The payload of the envelope isn't a valid peer record.
+    InvalidRecord(DecodingError),
+    /// The record describes another peer than the signer.
+    MismatchedSignature,
+}
+
+impl fmt::Display for FromEnvelopeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FromEnvelopeError::BadPayload(err) => write!(f, "Bad payload: {}", err),
+            FromEnvelopeError::InvalidRecord(err) => write!(f, "Invalid peer record: {}", err),
+            FromEnvelopeError::MismatchedSignature =>
+                write!(f, "The peer record has been signed by another peer"),
+        }
+    }
+}
+
+impl error::Error for FromEnvelopeError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            FromEnvelopeError::BadPayload(err) => Some(err),
+            FromEnvelopeError::InvalidRecord(err) => Some(err),
+            FromEnvelopeError::MismatchedSignature => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_roundtrip() {
+        let key = Keypair::generate_ed25519();
+        let addresses = vec!["/ip4/1.2.3.4/tcp/1234".parse().unwrap()];
+        let record = PeerRecord::with_seq(&key, 7, addresses.clone()).unwrap();
+
+        let bytes = record.to_signed_envelope().into_protobuf_encoding();
+        let envelope = SignedEnvelope::from_protobuf_encoding(&bytes).unwrap();
+        let decoded = PeerRecord::from_signed_envelope(envelope).unwrap();
+        assert_eq!(decoded, record);
+        assert_eq!(decoded.peer_id(), &key.public().into_peer_id());
+        assert_eq!(decoded.seq(), 7);
+        assert_eq!(decoded.addresses(), &addresses[..]);
+    }
+
+    #[test]
+    fn record_of_another_peer_is_refused() {
+        let key = Keypair::generate_ed25519();
+        let mut msg = peer_record_proto::PeerRecord::new();
+        msg.set_peer_id(PeerId::random().into_bytes());
+        let payload = msg.write_to_bytes().unwrap();
+        let envelope = SignedEnvelope::new(&key, DOMAIN_SEPARATION, PAYLOAD_TYPE.to_vec(), payload).unwrap();
+        match PeerRecord::from_signed_envelope(envelope) {
+            Err(FromEnvelopeError::MismatchedSignature) => {}
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+}
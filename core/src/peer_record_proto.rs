@@ -0,0 +1,481 @@
+// This file is generated by rust-protobuf 2.3.0. Do not edit
+// @generated
+
+// https://github.com/Manishearth/rust-clippy/issues/702
+#![allow(unknown_lints)]
+#![allow(clippy::all)]
+
+#![cfg_attr(rustfmt, rustfmt_skip)]
+
+#![allow(box_pointers)]
+#![allow(dead_code)]
+#![allow(missing_docs)]
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+#![allow(non_upper_case_globals)]
+#![allow(trivial_casts)]
+#![allow(unsafe_code)]
+#![allow(unused_imports)]
+#![allow(unused_results)]
+
+use protobuf::Message as Message_imported_for_functions;
+use protobuf::ProtobufEnum as ProtobufEnum_imported_for_functions;
+
+#[derive(PartialEq,Clone,Default)]
+pub struct PeerRecord {
+    // message fields
+    peer_id: ::protobuf::SingularField<::std::vec::Vec<u8>>,
+    seq: ::std::option::Option<u64>,
+    addresses: ::protobuf::RepeatedField<PeerRecord_AddressInfo>,
+    // special fields
+    pub unknown_fields: ::protobuf::UnknownFields,
+    pub cached_size: ::protobuf::CachedSize,
+}
+
+impl PeerRecord {
+    pub fn new() -> PeerRecord {
+        ::std::default::Default::default()
+    }
+
+    // optional bytes peer_id = 1;
+
+    pub fn clear_peer_id(&mut self) {
+        self.peer_id.clear();
+    }
+
+    pub fn has_peer_id(&self) -> bool {
+        self.peer_id.is_some()
+    }
+
+    // Param is passed by value, moved
+    pub fn set_peer_id(&mut self, v: ::std::vec::Vec<u8>) {
+        self.peer_id = ::protobuf::SingularField::some(v);
+    }
+
+    // Mutable pointer to the field.
+    // If field is not initialized, it is initialized with default value first.
+    pub fn mut_peer_id(&mut self) -> &mut ::std::vec::Vec<u8> {
+        if self.peer_id.is_none() {
+            self.peer_id.set_default();
+        }
+        self.peer_id.as_mut().unwrap()
+    }
+
+    // Take field
+    pub fn take_peer_id(&mut self) -> ::std::vec::Vec<u8> {
+        self.peer_id.take().unwrap_or_else(|| ::std::vec::Vec::new())
+    }
+
+    pub fn get_peer_id(&self) -> &[u8] {
+        match self.peer_id.as_ref() {
+            Some(v) => &v,
+            None => &[],
+        }
+    }
+
+    // optional uint64 seq = 2;
+
+    pub fn clear_seq(&mut self) {
+        self.seq = ::std::option::Option::None;
+    }
+
+    pub fn has_seq(&self) -> bool {
+        self.seq.is_some()
+    }
+
+    // Param is passed by value, moved
+    pub fn set_seq(&mut self, v: u64) {
+        self.seq = ::std::option::Option::Some(v);
+    }
+
+    pub fn get_seq(&self) -> u64 {
+        self.seq.unwrap_or(0)
+    }
+
+    // repeated .peer_record_proto.PeerRecord.AddressInfo addresses = 3;
+
+    pub fn clear_addresses(&mut self) {
+        self.addresses.clear();
+    }
+
+    // Param is passed by value, moved
+    pub fn set_addresses(&mut self, v: ::protobuf::RepeatedField<PeerRecord_AddressInfo>) {
+        self.addresses = v;
+    }
+
+    // Mutable pointer to the field.
+    pub fn mut_addresses(&mut self) -> &mut ::protobuf::RepeatedField<PeerRecord_AddressInfo> {
+        &mut self.addresses
+    }
+
+    // Take field
+    pub fn take_addresses(&mut self) -> ::protobuf::RepeatedField<PeerRecord_AddressInfo> {
+        ::std::mem::replace(&mut self.addresses, ::protobuf::RepeatedField::new())
+    }
+
+    pub fn get_addresses(&self) -> &[PeerRecord_AddressInfo] {
+        &self.addresses
+    }
+}
+
+impl ::protobuf::Message for PeerRecord {
+    fn is_initialized(&self) -> bool {
+        for v in &self.addresses {
+            if !v.is_initialized() {
+                return false;
+            }
+        };
+        true
+    }
+
+    fn merge_from(&mut self, is: &mut ::protobuf::CodedInputStream<'_>) -> ::protobuf::ProtobufResult<()> {
+        while !is.eof()? {
+            let (field_number, wire_type) = is.read_tag_unpack()?;
+            match field_number {
+                1 => {
+                    ::protobuf::rt::read_singular_bytes_into(wire_type, is, &mut self.peer_id)?;
+                },
+                2 => {
+                    if wire_type != ::protobuf::wire_format::WireTypeVarint {
+                        return ::std::result::Result::Err(::protobuf::rt::unexpected_wire_type(wire_type));
+                    }
+                    let tmp = is.read_uint64()?;
+                    self.seq = ::std::option::Option::Some(tmp);
+                },
+                3 => {
+                    ::protobuf::rt::read_repeated_message_into(wire_type, is, &mut self.addresses)?;
+                },
+                _ => {
+                    ::protobuf::rt::read_unknown_or_skip_group(field_number, wire_type, is, self.mut_unknown_fields())?;
+                },
+            };
+        }
+        ::std::result::Result::Ok(())
+    }
+
+    // Compute sizes of nested messages
+    #[allow(unused_variables)]
+    fn compute_size(&self) -> u32 {
+        let mut my_size = 0;
+        if let Some(ref v) = self.peer_id.as_ref() {
+            my_size += ::protobuf::rt::bytes_size(1, &v);
+        }
+        if let Some(v) = self.seq {
+            my_size += ::protobuf::rt::value_size(2, v, ::protobuf::wire_format::WireTypeVarint);
+        }
+        for value in &self.addresses {
+            let len = value.compute_size();
+            my_size += 1 + ::protobuf::rt::compute_raw_varint32_size(len) + len;
+        };
+        my_size += ::protobuf::rt::unknown_fields_size(self.get_unknown_fields());
+        self.cached_size.set(my_size);
+        my_size
+    }
+
+    fn write_to_with_cached_sizes(&self, os: &mut ::protobuf::CodedOutputStream<'_>) -> ::protobuf::ProtobufResult<()> {
+        if let Some(ref v) = self.peer_id.as_ref() {
+            os.write_bytes(1, &v)?;
+        }
+        if let Some(v) = self.seq {
+            os.write_uint64(2, v)?;
+        }
+        for v in &self.addresses {
+            os.write_tag(3, ::protobuf::wire_format::WireTypeLengthDelimited)?;
+            os.write_raw_varint32(v.get_cached_size())?;
+            v.write_to_with_cached_sizes(os)?;
+        };
+        os.write_unknown_fields(self.get_unknown_fields())?;
+        ::std::result::Result::Ok(())
+    }
+
+    fn get_cached_size(&self) -> u32 {
+        self.cached_size.get()
+    }
+
+    fn get_unknown_fields(&self) -> &::protobuf::UnknownFields {
+        &self.unknown_fields
+    }
+
+    fn mut_unknown_fields(&mut self) -> &mut ::protobuf::UnknownFields {
+        &mut self.unknown_fields
+    }
+
+    fn as_any(&self) -> &dyn (::std::any::Any) {
+        self as &dyn (::std::any::Any)
+    }
+    fn as_any_mut(&mut self) -> &mut dyn (::std::any::Any) {
+        self as &mut dyn (::std::any::Any)
+    }
+    fn into_any(self: Box<Self>) -> ::std::boxed::Box<dyn (::std::any::Any)> {
+        self
+    }
+
+    fn descriptor(&self) -> &'static ::protobuf::reflect::MessageDescriptor {
+        Self::descriptor_static()
+    }
+
+    fn new() -> PeerRecord {
+        PeerRecord::new()
+    }
+
+    fn descriptor_static() -> &'static ::protobuf::reflect::MessageDescriptor {
+        static mut descriptor: ::protobuf::lazy::Lazy<::protobuf::reflect::MessageDescriptor> = ::protobuf::lazy::Lazy {
+            lock: ::protobuf::lazy::ONCE_INIT,
+            ptr: 0 as *const ::protobuf::reflect::MessageDescriptor,
+        };
+        unsafe {
+            descriptor.get(|| {
+                let mut fields = ::std::vec::Vec::new();
+                fields.push(::protobuf::reflect::accessor::make_singular_field_accessor::<_, ::protobuf::types::ProtobufTypeBytes>(
+                    "peer_id",
+                    |m: &PeerRecord| { &m.peer_id },
+                    |m: &mut PeerRecord| { &mut m.peer_id },
+                ));
+                fields.push(::protobuf::reflect::accessor::make_option_accessor::<_, ::protobuf::types::ProtobufTypeUint64>(
+                    "seq",
+                    |m: &PeerRecord| { &m.seq },
+                    |m: &mut PeerRecord| { &mut m.seq },
+                ));
+                fields.push(::protobuf::reflect::accessor::make_repeated_field_accessor::<_, ::protobuf::types::ProtobufTypeMessage<PeerRecord_AddressInfo>>(
+                    "addresses",
+                    |m: &PeerRecord| { &m.addresses },
+                    |m: &mut PeerRecord| { &mut m.addresses },
+                ));
+                ::protobuf::reflect::MessageDescriptor::new::<PeerRecord>(
+                    "PeerRecord",
+                    fields,
+                    file_descriptor_proto()
+                )
+            })
+        }
+    }
+
+    fn default_instance() -> &'static PeerRecord {
+        static mut instance: ::protobuf::lazy::Lazy<PeerRecord> = ::protobuf::lazy::Lazy {
+            lock: ::protobuf::lazy::ONCE_INIT,
+            ptr: 0 as *const PeerRecord,
+        };
+        unsafe {
+            instance.get(PeerRecord::new)
+        }
+    }
+}
+
+impl ::protobuf::Clear for PeerRecord {
+    fn clear(&mut self) {
+        self.clear_peer_id();
+        self.clear_seq();
+        self.clear_addresses();
+        self.unknown_fields.clear();
+    }
+}
+
+impl ::std::fmt::Debug for PeerRecord {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+        ::protobuf::text_format::fmt(self, f)
+    }
+}
+
+impl ::protobuf::reflect::ProtobufValue for PeerRecord {
+    fn as_ref(&self) -> ::protobuf::reflect::ProtobufValueRef<'_> {
+        ::protobuf::reflect::ProtobufValueRef::Message(self)
+    }
+}
+
+#[derive(PartialEq,Clone,Default)]
+pub struct PeerRecord_AddressInfo {
+    // message fields
+    multiaddr: ::protobuf::SingularField<::std::vec::Vec<u8>>,
+    // special fields
+    pub unknown_fields: ::protobuf::UnknownFields,
+    pub cached_size: ::protobuf::CachedSize,
+}
+
+impl PeerRecord_AddressInfo {
+    pub fn new() -> PeerRecord_AddressInfo {
+        ::std::default::Default::default()
+    }
+
+    // optional bytes multiaddr = 1;
+
+    pub fn clear_multiaddr(&mut self) {
+        self.multiaddr.clear();
+    }
+
+    pub fn has_multiaddr(&self) -> bool {
+        self.multiaddr.is_some()
+    }
+
+    // Param is passed by value, moved
+    pub fn set_multiaddr(&mut self, v: ::std::vec::Vec<u8>) {
+        self.multiaddr = ::protobuf::SingularField::some(v);
+    }
+
+    // Mutable pointer to the field.
+    // If field is not initialized, it is initialized with default value first.
+    pub fn mut_multiaddr(&mut self) -> &mut ::std::vec::Vec<u8> {
+        if self.multiaddr.is_none() {
+            self.multiaddr.set_default();
+        }
+        self.multiaddr.as_mut().unwrap()
+    }
+
+    // Take field
+    pub fn take_multiaddr(&mut self) -> ::std::vec::Vec<u8> {
+        self.multiaddr.take().unwrap_or_else(|| ::std::vec::Vec::new())
+    }
+
+    pub fn get_multiaddr(&self) -> &[u8] {
+        match self.multiaddr.as_ref() {
+            Some(v) => &v,
+            None => &[],
+        }
+    }
+}
+
+impl ::protobuf::Message for PeerRecord_AddressInfo {
+    fn is_initialized(&self) -> bool {
+        true
+    }
+
+    fn merge_from(&mut self, is: &mut ::protobuf::CodedInputStream<'_>) -> ::protobuf::ProtobufResult<()> {
+        while !is.eof()? {
+            let (field_number, wire_type) = is.read_tag_unpack()?;
+            match field_number {
+                1 => {
+                    ::protobuf::rt::read_singular_bytes_into(wire_type, is, &mut self.multiaddr)?;
+                },
+                _ => {
+                    ::protobuf::rt::read_unknown_or_skip_group(field_number, wire_type, is, self.mut_unknown_fields())?;
+                },
+            };
+        }
+        ::std::result::Result::Ok(())
+    }
+
+    // Compute sizes of nested messages
+    #[allow(unused_variables)]
+    fn compute_size(&self) -> u32 {
+        let mut my_size = 0;
+        if let Some(ref v) = self.multiaddr.as_ref() {
+            my_size += ::protobuf::rt::bytes_size(1, &v);
+        }
+        my_size += ::protobuf::rt::unknown_fields_size(self.get_unknown_fields());
+        self.cached_size.set(my_size);
+        my_size
+    }
+
+    fn write_to_with_cached_sizes(&self, os: &mut ::protobuf::CodedOutputStream<'_>) -> ::protobuf::ProtobufResult<()> {
+        if let Some(ref v) = self.multiaddr.as_ref() {
+            os.write_bytes(1, &v)?;
+        }
+        os.write_unknown_fields(self.get_unknown_fields())?;
+        ::std::result::Result::Ok(())
+    }
+
+    fn get_cached_size(&self) -> u32 {
+        self.cached_size.get()
+    }
+
+    fn get_unknown_fields(&self) -> &::protobuf::UnknownFields {
+        &self.unknown_fields
+    }
+
+    fn mut_unknown_fields(&mut self) -> &mut ::protobuf::UnknownFields {
+        &mut self.unknown_fields
+    }
+
+    fn as_any(&self) -> &dyn (::std::any::Any) {
+        self as &dyn (::std::any::Any)
+    }
+    fn as_any_mut(&mut self) -> &mut dyn (::std::any::Any) {
+        self as &mut dyn (::std::any::Any)
+    }
+    fn into_any(self: Box<Self>) -> ::std::boxed::Box<dyn (::std::any::Any)> {
+        self
+    }
+
+    fn descriptor(&self) -> &'static ::protobuf::reflect::MessageDescriptor {
+        Self::descriptor_static()
+    }
+
+    fn new() -> PeerRecord_AddressInfo {
+        PeerRecord_AddressInfo::new()
+    }
+
+    fn descriptor_static() -> &'static ::protobuf::reflect::MessageDescriptor {
+        static mut descriptor: ::protobuf::lazy::Lazy<::protobuf::reflect::MessageDescriptor> = ::protobuf::lazy::Lazy {
+            lock: ::protobuf::lazy::ONCE_INIT,
+            ptr: 0 as *const ::protobuf::reflect::MessageDescriptor,
+        };
+        unsafe {
+            descriptor.get(|| {
+                let mut fields = ::std::vec::Vec::new();
+                fields.push(::protobuf::reflect::accessor::make_singular_field_accessor::<_, ::protobuf::types::ProtobufTypeBytes>(
+                    "multiaddr",
+                    |m: &PeerRecord_AddressInfo| { &m.multiaddr },
+                    |m: &mut PeerRecord_AddressInfo| { &mut m.multiaddr },
+                ));
+                ::protobuf::reflect::MessageDescriptor::new::<PeerRecord_AddressInfo>(
+                    "PeerRecord_AddressInfo",
+                    fields,
+                    file_descriptor_proto()
+                )
+            })
+        }
+    }
+
+    fn default_instance() -> &'static PeerRecord_AddressInfo {
+        static mut instance: ::protobuf::lazy::Lazy<PeerRecord_AddressInfo> = ::protobuf::lazy::Lazy {
+            lock: ::protobuf::lazy::ONCE_INIT,
+            ptr: 0 as *const PeerRecord_AddressInfo,
+        };
+        unsafe {
+            instance.get(PeerRecord_AddressInfo::new)
+        }
+    }
+}
+
+impl ::protobuf::Clear for PeerRecord_AddressInfo {
+    fn clear(&mut self) {
+        self.clear_multiaddr();
+        self.unknown_fields.clear();
+    }
+}
+
+impl ::std::fmt::Debug for PeerRecord_AddressInfo {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+        ::protobuf::text_format::fmt(self, f)
+    }
+}
+
+impl ::protobuf::reflect::ProtobufValue for PeerRecord_AddressInfo {
+    fn as_ref(&self) -> ::protobuf::reflect::ProtobufValueRef<'_> {
+        ::protobuf::reflect::ProtobufValueRef::Message(self)
+    }
+}
+
+static file_descriptor_proto_data: &'static [u8] = b"\
+    \n\x11peer_record.proto\x12\x11peer_record_proto\"\xad\x01\n\nPeerRecord\
+    \x12\x17\n\x07peer_id\x18\x01\x20\x01(\x0cR\x06peerId\x12\x10\n\x03seq\
+    \x18\x02\x20\x01(\x04R\x03seq\x12G\n\taddresses\x18\x03\x20\x03(\x0b2).p\
+    eer_record_proto.PeerRecord.AddressInfoR\taddresses\x1a+\n\x0bAddressInf\
+    o\x12\x1c\n\tmultiaddr\x18\x01\x20\x01(\x0cR\tmultiaddr\
+";
+
+static mut file_descriptor_proto_lazy: ::protobuf::lazy::Lazy<::protobuf::descriptor::FileDescriptorProto> = ::protobuf::lazy::Lazy {
+    lock: ::protobuf::lazy::ONCE_INIT,
+    ptr: 0 as *const ::protobuf::descriptor::FileDescriptorProto,
+};
+
+fn parse_descriptor_proto() -> ::protobuf::descriptor::FileDescriptorProto {
+    ::protobuf::parse_from_bytes(file_descriptor_proto_data).unwrap()
+}
+
+pub fn file_descriptor_proto() -> &'static ::protobuf::descriptor::FileDescriptorProto {
+    unsafe {
+        file_descriptor_proto_lazy.get(|| {
+            parse_descriptor_proto()
+        })
+    }
+}
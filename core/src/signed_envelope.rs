@@ -0,0 +1,173 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Payloads signed with the identity key of a peer.
+//!
+//! A [`SignedEnvelope`] wraps an arbitrary payload together with the public key of the signer
+//! and the signature. The signature covers a domain-separation string and a payload type, so
+//! that a signature produced for one purpose can't be reused for another one.
+
+use crate::envelope_proto;
+use crate::identity::{Keypair, PublicKey, error::{DecodingError, SigningError}};
+use protobuf::Message;
+use std::{error, fmt};
+
+/// A payload signed with the identity key of a peer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignedEnvelope {
+    key: PublicKey,
+    payload_type: Vec<u8>,
+    payload: Vec<u8>,
+    signature: Vec<u8>,
+}
+
+impl SignedEnvelope {
+    /// Signs a payload of the given type with the given key, within the given domain.
+    pub fn new(key: &Keypair, domain_separation: &str, payload_type: Vec<u8>, payload: Vec<u8>)
+        -> Result<SignedEnvelope, SigningError>
+    {
+        let signature = key.sign(&signature_payload(domain_separation, &payload_type, &payload))?;
+        Ok(SignedEnvelope { key: key.public(), payload_type, payload, signature })
+    }
+
+    /// Returns true if the signature of the envelope is valid for the given domain.
+    pub fn verify(&self, domain_separation: &str) -> bool {
+        let msg = signature_payload(domain_separation, &self.payload_type, &self.payload);
+        self.key.verify(&msg, &self.signature)
+    }
+
+    /// Returns the payload, after checking its type and the signature for the given domain.
+    pub fn payload(&self, domain_separation: &str, expected_payload_type: &[u8])
+        -> Result<&[u8], ReadPayloadError>
+    {
+        if self.payload_type != expected_payload_type {
+            return Err(ReadPayloadError::UnexpectedPayloadType {
+                expected: expected_payload_type.to_vec(),
+                got: self.payload_type.clone(),
+            });
+        }
+        if !self.verify(domain_separation) {
+            return Err(ReadPayloadError::InvalidSignature);
+        }
+        Ok(&self.payload)
+    }
+
+    /// Returns the public key of the signer.
+    ///
+    /// Note that this doesn't imply that the signature is valid.
+    pub fn signing_key(&self) -> &PublicKey {
+        &self.key
+    }
+
+    /// Returns the type of the payload.
+    pub fn payload_type(&self) -> &[u8] {
+        &self.payload_type
+    }
+
+    /// Encodes the envelope as a protobuf `Envelope` message.
+    pub fn into_protobuf_encoding(self) -> Vec<u8> {
+        let mut msg = envelope_proto::Envelope::new();
+        msg.set_public_key(self.key.into_protobuf_encoding());
+        msg.set_payload_type(self.payload_type);
+        msg.set_payload(self.payload);
+        msg.set_signature(self.signature);
+        msg.write_to_bytes().expect("writing protobuf to a Vec never fails; QED")
+    }
+
+    /// Decodes a protobuf `Envelope` message.
+    ///
+    /// The signature isn't checked; use `payload` or `verify` to do so.
+    pub fn from_protobuf_encoding(bytes: &[u8]) -> Result<SignedEnvelope, DecodingError> {
+        let mut msg: envelope_proto::Envelope = protobuf::parse_from_bytes(bytes)
+            .map_err(|e| DecodingError::new("Envelope").source(e))?;
+        let key = PublicKey::from_protobuf_encoding(msg.get_public_key())?;
+        Ok(SignedEnvelope {
+            key,
+            payload_type: msg.take_payload_type(),
+            payload: msg.take_payload(),
+            signature: msg.take_signature(),
+        })
+    }
+}
+
+/// Builds the message signed by an envelope, made of the length-prefixed domain, payload type
+/// and payload.
+fn signature_payload(domain_separation: &str, payload_type: &[u8], payload: &[u8]) -> Vec<u8> {
+    let mut msg = Vec::with_capacity(domain_separation.len() + payload_type.len() + payload.len() + 30);
+    for part in &[domain_separation.as_bytes(), payload_type, payload] {
+        let mut len = unsigned_varint::encode::usize_buffer();
+        msg.extend_from_slice(unsigned_varint::encode::usize(part.len(), &mut len));
+        msg.extend_from_slice(part);
+    }
+    msg
+}
+
+/// Error when reading the payload of a `SignedEnvelope`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReadPayloadError {
+    /// The signature is invalid for the requested domain.
+    InvalidSignature,
+    /// The payload isn't of the requested type.
+    UnexpectedPayloadType {
+        /// The requested type.
+        expected: Vec<u8>,
+        /// The type of the payload.
+        got: Vec<u8>,
+    },
+}
+
+impl fmt::Display for ReadPayloadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReadPayloadError::InvalidSignature => write!(f, "Invalid signature"),
+            ReadPayloadError::UnexpectedPayloadType { expected, got } =>
+                write!(f, "Unexpected payload type, expected {:?} but got {:?}", expected, got),
+        }
+    }
+}
+
+impl error::Error for ReadPayloadError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn envelope_roundtrip() {
+        let key = Keypair::generate_ed25519();
+        let envelope = SignedEnvelope::new(&key, "test-domain", b"type".to_vec(), b"payload".to_vec()).unwrap();
+        let decoded = SignedEnvelope::from_protobuf_encoding(&envelope.clone().into_protobuf_encoding()).unwrap();
+        assert_eq!(decoded, envelope);
+        assert_eq!(decoded.signing_key(), &key.public());
+        assert_eq!(decoded.payload("test-domain", b"type"), Ok(&b"payload"[..]));
+    }
+
+    #[test]
+    fn domain_and_type_are_checked() {
+        let key = Keypair::generate_ed25519();
+        let envelope = SignedEnvelope::new(&key, "test-domain", b"type".to_vec(), b"payload".to_vec()).unwrap();
+        assert_eq!(envelope.payload("other-domain", b"type"), Err(ReadPayloadError::InvalidSignature));
+        assert!(envelope.payload("test-domain", b"other").is_err());
+
+        let mut tampered = envelope.clone();
+        tampered.payload = b"forged".to_vec();
+        assert!(!tampered.verify("test-domain"));
+    }
+}
@@ -23,7 +23,7 @@
 //! desired way when testing other components.
 
 use futures::prelude::*;
-use crate::muxing::StreamMuxer;
+use crate::compat::StreamMuxer01;
 use std::io::Error as IoError;
 
 /// Substream type
@@ -76,7 +76,7 @@ impl DummyMuxer {
     }
 }
 
-impl StreamMuxer for DummyMuxer {
+impl StreamMuxer01 for DummyMuxer {
     type Substream = DummySubstream;
     type OutboundSubstream = DummyOutboundSubstream;
     type Error = IoError;
@@ -28,7 +28,7 @@ use futures::{
     stream,
 };
 use std::io;
-use crate::{Multiaddr, PeerId, Transport, transport::{ListenerEvent, TransportError}};
+use crate::{Multiaddr, PeerId, compat::Transport01, transport::{ListenerEvent, TransportError}};
 use crate::tests::dummy_muxer::DummyMuxer;
 
 #[derive(Debug, PartialEq, Clone)]
@@ -67,7 +67,7 @@ impl DummyTransport {
         self.dial_should_fail = true;
     }
 }
-impl Transport for DummyTransport {
+impl Transport01 for DummyTransport {
     type Output = (PeerId, DummyMuxer);
     type Error = io::Error;
     type Listener = Box<dyn Stream<Item=ListenerEvent<Self::ListenerUpgrade>, Error=io::Error> + Send>;
@@ -20,12 +20,14 @@
 
 use crate::{
     ConnectedPoint,
+    Endpoint,
     either::EitherError,
     transport::{Transport, TransportError, ListenerEvent}
 };
-use futures::{future::Either, prelude::*, try_ready};
+use futures03::{future::{TryFuture, TryFutureExt, IntoFuture}, ready, stream::TryStream, task::{Context, Poll}};
 use multiaddr::Multiaddr;
-use std::error;
+use pin_project::pin_project;
+use std::{error, future::Future, pin::Pin};
 
 /// See the `Transport::and_then` method.
 #[derive(Debug, Clone)]
@@ -41,14 +43,14 @@ impl<T, C, F, O> Transport for AndThen<T, C>
 where
     T: Transport,
     C: FnOnce(T::Output, ConnectedPoint) -> F + Clone,
-    F: IntoFuture<Item = O>,
+    F: TryFuture<Ok = O>,
     F::Error: error::Error,
 {
     type Output = O;
     type Error = EitherError<T::Error, F::Error>;
     type Listener = AndThenStream<T::Listener, C>;
-    type ListenerUpgrade = AndThenFuture<T::ListenerUpgrade, C, F::Future>;
-    type Dial = AndThenFuture<T::Dial, C, F::Future>;
+    type ListenerUpgrade = AndThenFuture<T::ListenerUpgrade, C, F>;
+    type Dial = AndThenFuture<T::Dial, C, F>;
 
     fn listen_on(self, addr: Multiaddr) -> Result<Self::Listener, TransportError<Self::Error>> {
         let listener = self.transport.listen_on(addr).map_err(|err| err.map(EitherError::A))?;
@@ -62,9 +64,20 @@ where
 
     fn dial(self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
         let dialed_fut = self.transport.dial(addr.clone()).map_err(|err| err.map(EitherError::A))?;
+        let point = ConnectedPoint::Dialer { address: addr, role_override: Endpoint::Dialer };
         let future = AndThenFuture {
-            inner: Either::A(dialed_fut),
-            args: Some((self.fun, ConnectedPoint::Dialer { address: addr }))
+            inner: AndThenState::First(dialed_fut),
+            args: Some((self.fun, point))
+        };
+        Ok(future)
+    }
+
+    fn dial_as_listener(self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
+        let dialed_fut = self.transport.dial_as_listener(addr.clone()).map_err(|err| err.map(EitherError::A))?;
+        let point = ConnectedPoint::Dialer { address: addr, role_override: Endpoint::Listener };
+        let future = AndThenFuture {
+            inner: AndThenState::First(dialed_fut),
+            args: Some((self.fun, point))
         };
         Ok(future)
     }
@@ -73,25 +86,27 @@ where
 /// Custom `Stream` to avoid boxing.
 ///
 /// Applies a function to every stream item.
+#[pin_project]
 #[derive(Debug, Clone)]
 pub struct AndThenStream<TListener, TMap> {
+    #[pin]
     stream: TListener,
     fun: TMap
 }
 
-impl<TListener, TMap, TTransOut, TMapOut, TListUpgr, TTransErr> Stream for AndThenStream<TListener, TMap>
+impl<TListener, TMap, TTransOut, TMapOut, TListUpgr, TTransErr> futures03::Stream for AndThenStream<TListener, TMap>
 where
-    TListener: Stream<Item = ListenerEvent<TListUpgr>, Error = TTransErr>,
-    TListUpgr: Future<Item = TTransOut, Error = TTransErr>,
+    TListener: TryStream<Ok = ListenerEvent<TListUpgr>, Error = TTransErr>,
+    TListUpgr: Future<Output = Result<TTransOut, TTransErr>>,
     TMap: FnOnce(TTransOut, ConnectedPoint) -> TMapOut + Clone,
-    TMapOut: IntoFuture
+    TMapOut: TryFuture
 {
-    type Item = ListenerEvent<AndThenFuture<TListUpgr, TMap, TMapOut::Future>>;
-    type Error = EitherError<TTransErr, TMapOut::Error>;
+    type Item = Result<ListenerEvent<AndThenFuture<TListUpgr, TMap, TMapOut>>, EitherError<TTransErr, TMapOut::Error>>;
 
-    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
-        match self.stream.poll().map_err(EitherError::A)? {
-            Async::Ready(Some(event)) => {
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        match ready!(this.stream.try_poll_next(cx)) {
+            Some(Ok(event)) => {
                 let event = match event {
                     ListenerEvent::Upgrade { upgrade, listen_addr, remote_addr } => {
                         let point = ConnectedPoint::Listener {
@@ -100,8 +115,8 @@ where
                         };
                         ListenerEvent::Upgrade {
                             upgrade: AndThenFuture {
-                                inner: Either::A(upgrade),
-                                args: Some((self.fun.clone(), point))
+                                inner: AndThenState::First(upgrade),
+                                args: Some((this.fun.clone(), point))
                             },
                             listen_addr,
                             remote_addr
@@ -110,10 +125,10 @@ where
                     ListenerEvent::NewAddress(a) => ListenerEvent::NewAddress(a),
                     ListenerEvent::AddressExpired(a) => ListenerEvent::AddressExpired(a)
                 };
-                Ok(Async::Ready(Some(event)))
+                Poll::Ready(Some(Ok(event)))
             }
-            Async::Ready(None) => Ok(Async::Ready(None)),
-            Async::NotReady => Ok(Async::NotReady)
+            Some(Err(err)) => Poll::Ready(Some(Err(EitherError::A(err)))),
+            None => Poll::Ready(None)
         }
     }
 }
@@ -121,34 +136,45 @@ where
 /// Custom `Future` to avoid boxing.
 ///
 /// Applies a function to the result of the inner future.
+#[pin_project]
 #[derive(Debug)]
 pub struct AndThenFuture<TFut, TMap, TMapOut> {
-    inner: Either<TFut, TMapOut>,
+    #[pin]
+    inner: AndThenState<TFut, IntoFuture<TMapOut>>,
     args: Option<(TMap, ConnectedPoint)>
 }
 
-impl<TFut, TMap, TMapOut> Future for AndThenFuture<TFut, TMap, TMapOut::Future>
+/// State of an `AndThenFuture`.
+#[pin_project(project = AndThenStateProj)]
+#[derive(Debug)]
+enum AndThenState<A, B> {
+    First(#[pin] A),
+    Second(#[pin] B),
+}
+
+impl<TFut, TMap, TMapOut, TItem, TErr> Future for AndThenFuture<TFut, TMap, TMapOut>
 where
-    TFut: Future,
-    TMap: FnOnce(TFut::Item, ConnectedPoint) -> TMapOut,
-    TMapOut: IntoFuture
+    TFut: Future<Output = Result<TItem, TErr>>,
+    TMap: FnOnce(TItem, ConnectedPoint) -> TMapOut,
+    TMapOut: TryFuture
 {
-    type Item = <TMapOut::Future as Future>::Item;
-    type Error = EitherError<TFut::Error, TMapOut::Error>;
+    type Output = Result<TMapOut::Ok, EitherError<TErr, TMapOut::Error>>;
 
-    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut this = self.project();
         loop {
-            let future = match self.inner {
-                Either::A(ref mut future) => {
-                    let item = try_ready!(future.poll().map_err(EitherError::A));
-                    let (f, a) = self.args.take().expect("AndThenFuture has already finished.");
+            let future = match this.inner.as_mut().project() {
+                AndThenStateProj::First(future) => {
+                    let item = ready!(future.poll(cx)).map_err(EitherError::A)?;
+                    let (f, a) = this.args.take().expect("AndThenFuture has already finished.");
                     f(item, a).into_future()
                 }
-                Either::B(ref mut future) => return future.poll().map_err(EitherError::B)
+                AndThenStateProj::Second(future) => {
+                    return future.poll(cx).map_err(EitherError::B)
+                }
             };
 
-            self.inner = Either::B(future);
+            this.inner.set(AndThenState::Second(future));
         }
     }
 }
-
@@ -19,7 +19,7 @@
 // DEALINGS IN THE SOFTWARE.
 
 use crate::transport::{ListenerEvent, Transport, TransportError};
-use futures::prelude::*;
+use futures03::{future::BoxFuture, stream::{BoxStream, TryStreamExt}};
 use multiaddr::Multiaddr;
 use std::{error, fmt, sync::Arc};
 
@@ -37,13 +37,14 @@ where
     }
 }
 
-pub type Dial<O, E> = Box<dyn Future<Item = O, Error = E> + Send>;
-pub type Listener<O, E> = Box<dyn Stream<Item = ListenerEvent<ListenerUpgrade<O, E>>, Error = E> + Send>;
-pub type ListenerUpgrade<O, E> = Box<dyn Future<Item = O, Error = E> + Send>;
+pub type Dial<O, E> = BoxFuture<'static, Result<O, E>>;
+pub type Listener<O, E> = BoxStream<'static, Result<ListenerEvent<ListenerUpgrade<O, E>>, E>>;
+pub type ListenerUpgrade<O, E> = BoxFuture<'static, Result<O, E>>;
 
 trait Abstract<O, E> {
     fn listen_on(&self, addr: Multiaddr) -> Result<Listener<O, E>, TransportError<E>>;
     fn dial(&self, addr: Multiaddr) -> Result<Dial<O, E>, TransportError<E>>;
+    fn dial_as_listener(&self, addr: Multiaddr) -> Result<Dial<O, E>, TransportError<E>>;
 }
 
 impl<T, O, E> Abstract<O, E> for T
@@ -56,15 +57,20 @@ where
 {
     fn listen_on(&self, addr: Multiaddr) -> Result<Listener<O, E>, TransportError<E>> {
         let listener = Transport::listen_on(self.clone(), addr)?;
-        let fut = listener.map(|event| event.map(|upgrade| {
-            Box::new(upgrade) as ListenerUpgrade<O, E>
+        let fut = listener.map_ok(|event| event.map(|upgrade| {
+            Box::pin(upgrade) as ListenerUpgrade<O, E>
         }));
-        Ok(Box::new(fut) as Box<_>)
+        Ok(Box::pin(fut))
     }
 
     fn dial(&self, addr: Multiaddr) -> Result<Dial<O, E>, TransportError<E>> {
         let fut = Transport::dial(self.clone(), addr)?;
-        Ok(Box::new(fut) as Box<_>)
+        Ok(Box::pin(fut))
+    }
+
+    fn dial_as_listener(&self, addr: Multiaddr) -> Result<Dial<O, E>, TransportError<E>> {
+        let fut = Transport::dial_as_listener(self.clone(), addr)?;
+        Ok(Box::pin(fut))
     }
 }
 
@@ -103,4 +109,8 @@ where E: error::Error,
     fn dial(self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
         self.inner.dial(addr)
     }
+
+    fn dial_as_listener(self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
+        self.inner.dial_as_listener(addr)
+    }
 }
@@ -74,4 +74,20 @@ where
 
         Err(TransportError::MultiaddrNotSupported(addr))
     }
+
+    fn dial_as_listener(self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
+        let addr = match self.0.dial_as_listener(addr) {
+            Ok(connec) => return Ok(EitherFuture::First(connec)),
+            Err(TransportError::MultiaddrNotSupported(addr)) => addr,
+            Err(TransportError::Other(err)) => return Err(TransportError::Other(EitherError::A(err))),
+        };
+
+        let addr = match self.1.dial_as_listener(addr) {
+            Ok(connec) => return Ok(EitherFuture::Second(connec)),
+            Err(TransportError::MultiaddrNotSupported(addr)) => addr,
+            Err(TransportError::Other(err)) => return Err(TransportError::Other(EitherError::B(err))),
+        };
+
+        Err(TransportError::MultiaddrNotSupported(addr))
+    }
 }
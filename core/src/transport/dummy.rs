@@ -55,9 +55,9 @@ impl<TOut> Clone for DummyTransport<TOut> {
 impl<TOut> Transport for DummyTransport<TOut> {
     type Output = TOut;
     type Error = io::Error;
-    type Listener = futures::stream::Empty<ListenerEvent<Self::ListenerUpgrade>, io::Error>;
-    type ListenerUpgrade = futures::future::Empty<Self::Output, io::Error>;
-    type Dial = futures::future::Empty<Self::Output, io::Error>;
+    type Listener = futures03::stream::Pending<Result<ListenerEvent<Self::ListenerUpgrade>, io::Error>>;
+    type ListenerUpgrade = futures03::future::Pending<Result<Self::Output, io::Error>>;
+    type Dial = futures03::future::Pending<Result<Self::Output, io::Error>>;
 
     fn listen_on(self, addr: Multiaddr) -> Result<Self::Listener, TransportError<Self::Error>> {
         Err(TransportError::MultiaddrNotSupported(addr))
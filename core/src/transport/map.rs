@@ -20,10 +20,13 @@
 
 use crate::{
     ConnectedPoint,
+    Endpoint,
     transport::{Transport, TransportError, ListenerEvent}
 };
-use futures::{prelude::*, try_ready};
+use futures03::{ready, stream::TryStream, task::{Context, Poll}};
 use multiaddr::Multiaddr;
+use pin_project::pin_project;
+use std::{future::Future, pin::Pin};
 
 /// See `Transport::map`.
 #[derive(Debug, Copy, Clone)]
@@ -53,7 +56,13 @@ where
 
     fn dial(self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
         let future = self.transport.dial(addr.clone())?;
-        let p = ConnectedPoint::Dialer { address: addr };
+        let p = ConnectedPoint::Dialer { address: addr, role_override: Endpoint::Dialer };
+        Ok(MapFuture { inner: future, args: Some((self.fun, p)) })
+    }
+
+    fn dial_as_listener(self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
+        let future = self.transport.dial_as_listener(addr.clone())?;
+        let p = ConnectedPoint::Dialer { address: addr, role_override: Endpoint::Listener };
         Ok(MapFuture { inner: future, args: Some((self.fun, p)) })
     }
 }
@@ -61,21 +70,22 @@ where
 /// Custom `Stream` implementation to avoid boxing.
 ///
 /// Maps a function over every stream item.
+#[pin_project]
 #[derive(Clone, Debug)]
-pub struct MapStream<T, F> { stream: T, fun: F }
+pub struct MapStream<T, F> { #[pin] stream: T, fun: F }
 
-impl<T, F, A, B, X> Stream for MapStream<T, F>
+impl<T, F, A, B, X, E> futures03::Stream for MapStream<T, F>
 where
-    T: Stream<Item = ListenerEvent<X>>,
-    X: Future<Item = A>,
+    T: TryStream<Ok = ListenerEvent<X>, Error = E>,
+    X: Future<Output = Result<A, E>>,
     F: FnOnce(A, ConnectedPoint) -> B + Clone
 {
-    type Item = ListenerEvent<MapFuture<X, F>>;
-    type Error = T::Error;
+    type Item = Result<ListenerEvent<MapFuture<X, F>>, E>;
 
-    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
-        match self.stream.poll()? {
-            Async::Ready(Some(event)) => {
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        match ready!(this.stream.try_poll_next(cx)) {
+            Some(Ok(event)) => {
                 let event = match event {
                     ListenerEvent::Upgrade { upgrade, listen_addr, remote_addr } => {
                         let point = ConnectedPoint::Listener {
@@ -85,7 +95,7 @@ where
                         ListenerEvent::Upgrade {
                             upgrade: MapFuture {
                                 inner: upgrade,
-                                args: Some((self.fun.clone(), point))
+                                args: Some((this.fun.clone(), point))
                             },
                             listen_addr,
                             remote_addr
@@ -94,10 +104,10 @@ where
                     ListenerEvent::NewAddress(a) => ListenerEvent::NewAddress(a),
                     ListenerEvent::AddressExpired(a) => ListenerEvent::AddressExpired(a)
                 };
-                Ok(Async::Ready(Some(event)))
+                Poll::Ready(Some(Ok(event)))
             }
-            Async::Ready(None) => Ok(Async::Ready(None)),
-            Async::NotReady => Ok(Async::NotReady)
+            Some(Err(err)) => Poll::Ready(Some(Err(err))),
+            None => Poll::Ready(None)
         }
     }
 }
@@ -105,24 +115,25 @@ where
 /// Custom `Future` to avoid boxing.
 ///
 /// Applies a function to the inner future's result.
+#[pin_project]
 #[derive(Clone, Debug)]
 pub struct MapFuture<T, F> {
+    #[pin]
     inner: T,
     args: Option<(F, ConnectedPoint)>
 }
 
-impl<T, A, F, B> Future for MapFuture<T, F>
+impl<T, A, F, B, E> Future for MapFuture<T, F>
 where
-    T: Future<Item = A>,
+    T: Future<Output = Result<A, E>>,
     F: FnOnce(A, ConnectedPoint) -> B
 {
-    type Item = B;
-    type Error = T::Error;
+    type Output = Result<B, E>;
 
-    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
-        let item = try_ready!(self.inner.poll());
-        let (f, a) = self.args.take().expect("MapFuture has already finished.");
-        Ok(Async::Ready(f(item, a)))
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let item = ready!(this.inner.poll(cx))?;
+        let (f, a) = this.args.take().expect("MapFuture has already finished.");
+        Poll::Ready(Ok(f(item, a)))
     }
 }
-
@@ -19,9 +19,10 @@
 // DEALINGS IN THE SOFTWARE.
 
 use crate::transport::{Transport, TransportError, ListenerEvent};
-use futures::prelude::*;
+use futures03::{ready, task::{Context, Poll}};
 use multiaddr::Multiaddr;
-use std::error;
+use pin_project::pin_project;
+use std::{error, future::Future, pin::Pin};
 
 /// See `Transport::map_err`.
 #[derive(Debug, Copy, Clone)]
@@ -64,43 +65,55 @@ where
             Err(err) => Err(err.map(map)),
         }
     }
+
+    fn dial_as_listener(self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
+        let map = self.map;
+        match self.transport.dial_as_listener(addr) {
+            Ok(future) => Ok(MapErrDial { inner: future, map: Some(map) }),
+            Err(err) => Err(err.map(map)),
+        }
+    }
 }
 
 /// Listening stream for `MapErr`.
+#[pin_project]
 pub struct MapErrListener<T: Transport, F> {
+    #[pin]
     inner: T::Listener,
     map: F,
 }
 
-impl<T, F, TErr> Stream for MapErrListener<T, F>
+impl<T, F, TErr> futures03::Stream for MapErrListener<T, F>
 where
     T: Transport,
     F: FnOnce(T::Error) -> TErr + Clone,
     TErr: error::Error,
 {
-    type Item = ListenerEvent<MapErrListenerUpgrade<T, F>>;
-    type Error = TErr;
+    type Item = Result<ListenerEvent<MapErrListenerUpgrade<T, F>>, TErr>;
 
-    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
-        match self.inner.poll() {
-            Ok(Async::Ready(Some(event))) => {
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        match ready!(this.inner.poll_next(cx)) {
+            Some(Ok(event)) => {
+                let map = &*this.map;
                 let event = event.map(move |value| {
                     MapErrListenerUpgrade {
                         inner: value,
-                        map: Some(self.map.clone())
+                        map: Some(map.clone())
                     }
                 });
-                Ok(Async::Ready(Some(event)))
+                Poll::Ready(Some(Ok(event)))
             }
-            Ok(Async::Ready(None)) => Ok(Async::Ready(None)),
-            Ok(Async::NotReady) => Ok(Async::NotReady),
-            Err(err) => Err((self.map.clone())(err)),
+            None => Poll::Ready(None),
+            Some(Err(err)) => Poll::Ready(Some(Err((this.map.clone())(err)))),
         }
     }
 }
 
 /// Listening upgrade future for `MapErr`.
+#[pin_project]
 pub struct MapErrListenerUpgrade<T: Transport, F> {
+    #[pin]
     inner: T::ListenerUpgrade,
     map: Option<F>,
 }
@@ -109,23 +122,24 @@ impl<T, F, TErr> Future for MapErrListenerUpgrade<T, F>
 where T: Transport,
     F: FnOnce(T::Error) -> TErr,
 {
-    type Item = T::Output;
-    type Error = TErr;
+    type Output = Result<T::Output, TErr>;
 
-    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
-        match self.inner.poll() {
-            Ok(Async::Ready(value)) => Ok(Async::Ready(value)),
-            Ok(Async::NotReady) => Ok(Async::NotReady),
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        match ready!(this.inner.poll(cx)) {
+            Ok(value) => Poll::Ready(Ok(value)),
             Err(err) => {
-                let map = self.map.take().expect("poll() called again after error");
-                Err(map(err))
+                let map = this.map.take().expect("poll() called again after error");
+                Poll::Ready(Err(map(err)))
             }
         }
     }
 }
 
 /// Dialing future for `MapErr`.
+#[pin_project]
 pub struct MapErrDial<T: Transport, F> {
+    #[pin]
     inner: T::Dial,
     map: Option<F>,
 }
@@ -135,18 +149,15 @@ where
     T: Transport,
     F: FnOnce(T::Error) -> TErr,
 {
-    type Item = T::Output;
-    type Error = TErr;
+    type Output = Result<T::Output, TErr>;
 
-    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
-        match self.inner.poll() {
-            Ok(Async::Ready(value)) => {
-                Ok(Async::Ready(value))
-            },
-            Ok(Async::NotReady) => Ok(Async::NotReady),
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        match ready!(this.inner.poll(cx)) {
+            Ok(value) => Poll::Ready(Ok(value)),
             Err(err) => {
-                let map = self.map.take().expect("poll() called again after error");
-                Err(map(err))
+                let map = this.map.take().expect("poll() called again after error");
+                Poll::Ready(Err(map(err)))
             }
         }
     }
@@ -18,15 +18,16 @@
 // FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // DEALINGS IN THE SOFTWARE.
 
-use crate::{Transport, transport::{TransportError, ListenerEvent}};
+use crate::{Transport, compat::with_task, transport::{TransportError, ListenerEvent}};
 use bytes::{Bytes, IntoBuf};
 use fnv::FnvHashMap;
-use futures::{future::{self, FutureResult}, prelude::*, sync::mpsc, try_ready};
+use futures::{prelude::*, sync::mpsc};
+use futures03::{future::{self, Ready}, task::{Context, Poll as Poll03}};
 use lazy_static::lazy_static;
 use multiaddr::{Protocol, Multiaddr};
 use parking_lot::Mutex;
 use rw_stream_sink::RwStreamSink;
-use std::{collections::hash_map::Entry, error, fmt, io, num::NonZeroU64};
+use std::{collections::hash_map::Entry, error, fmt, io, num::NonZeroU64, pin::Pin};
 
 lazy_static! {
     static ref HUB: Mutex<FnvHashMap<NonZeroU64, mpsc::Sender<Channel<Bytes>>>> =
@@ -44,27 +45,29 @@ pub struct DialFuture {
     channel_to_return: Option<Channel<Bytes>>,
 }
 
-impl Future for DialFuture {
-    type Item = Channel<Bytes>;
-    type Error = MemoryTransportError;
-
-    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
-        if let Some(c) = self.channel_to_send.take() {
-            match self.sender.start_send(c) {
-                Err(_) => return Err(MemoryTransportError::Unreachable),
-                Ok(AsyncSink::NotReady(t)) => {
-                    self.channel_to_send = Some(t);
-                    return Ok(Async::NotReady)
-                },
-                _ => (),
+impl std::future::Future for DialFuture {
+    type Output = Result<Channel<Bytes>, MemoryTransportError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll03<Self::Output> {
+        let this = self.get_mut();
+        with_task(cx, || {
+            if let Some(c) = this.channel_to_send.take() {
+                match this.sender.start_send(c) {
+                    Err(_) => return Poll03::Ready(Err(MemoryTransportError::Unreachable)),
+                    Ok(AsyncSink::NotReady(t)) => {
+                        this.channel_to_send = Some(t);
+                        return Poll03::Pending
+                    },
+                    _ => (),
+                }
             }
-        }
-        match self.sender.close() {
-            Err(_) => Err(MemoryTransportError::Unreachable),
-            Ok(Async::NotReady) => Ok(Async::NotReady),
-            Ok(Async::Ready(_)) => Ok(Async::Ready(self.channel_to_return.take()
-                .expect("Future should not be polled again once complete"))),
-        }
+            match this.sender.close() {
+                Err(_) => Poll03::Ready(Err(MemoryTransportError::Unreachable)),
+                Ok(Async::NotReady) => Poll03::Pending,
+                Ok(Async::Ready(_)) => Poll03::Ready(Ok(this.channel_to_return.take()
+                    .expect("Future should not be polled again once complete"))),
+            }
+        })
     }
 }
 
@@ -72,7 +75,7 @@ impl Transport for MemoryTransport {
     type Output = Channel<Bytes>;
     type Error = MemoryTransportError;
     type Listener = Listener;
-    type ListenerUpgrade = FutureResult<Self::Output, Self::Error>;
+    type ListenerUpgrade = Ready<Result<Self::Output, Self::Error>>;
     type Dial = DialFuture;
 
     fn listen_on(self, addr: Multiaddr) -> Result<Self::Listener, TransportError<Self::Error>> {
@@ -175,27 +178,28 @@ pub struct Listener {
     tell_listen_addr: bool
 }
 
-impl Stream for Listener {
-    type Item = ListenerEvent<FutureResult<Channel<Bytes>, MemoryTransportError>>;
-    type Error = MemoryTransportError;
+impl futures03::Stream for Listener {
+    type Item = Result<ListenerEvent<Ready<Result<Channel<Bytes>, MemoryTransportError>>>, MemoryTransportError>;
 
-    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
-        if self.tell_listen_addr {
-            self.tell_listen_addr = false;
-            return Ok(Async::Ready(Some(ListenerEvent::NewAddress(self.addr.clone()))))
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll03<Option<Self::Item>> {
+        let this = self.get_mut();
+        if this.tell_listen_addr {
+            this.tell_listen_addr = false;
+            return Poll03::Ready(Some(Ok(ListenerEvent::NewAddress(this.addr.clone()))))
         }
-        let channel = try_ready!(Ok(self.receiver.poll()
-            .expect("Life listeners always have a sender.")));
-        let channel = match channel {
-            Some(c) => c,
-            None => return Ok(Async::Ready(None))
+        let channel = match with_task(cx, || this.receiver.poll())
+            .expect("Life listeners always have a sender.")
+        {
+            Async::Ready(Some(c)) => c,
+            Async::Ready(None) => return Poll03::Ready(None),
+            Async::NotReady => return Poll03::Pending
         };
         let event = ListenerEvent::Upgrade {
             upgrade: future::ok(channel),
-            listen_addr: self.addr.clone(),
-            remote_addr: Protocol::Memory(self.port.get()).into()
+            listen_addr: this.addr.clone(),
+            remote_addr: Protocol::Memory(this.port.get()).into()
         };
-        Ok(Async::Ready(Some(event)))
+        Poll03::Ready(Some(Ok(event)))
     }
 }
 
@@ -26,8 +26,9 @@
 //! modifying a transport through composition with other transports or protocol upgrades.
 
 use crate::{InboundUpgrade, OutboundUpgrade, ConnectedPoint};
-use futures::prelude::*;
+use futures03::{future::TryFuture, stream::Stream};
 use multiaddr::Multiaddr;
+use std::future::Future;
 use std::{error, fmt};
 use std::time::Duration;
 use tokio_io::{AsyncRead, AsyncWrite};
@@ -75,6 +76,10 @@ pub use self::upgrade::Upgrade;
 /// whose connection setup incorporates all earlier upgrades followed by the new upgrade,
 /// i.e. the order of the upgrades is significant.
 ///
+/// The listeners and the futures of a transport are built on `std::future::Future` and futures
+/// 0.3. Transports written against futures 0.1 implement
+/// [`Transport01`](crate::compat::Transport01) instead, and are then a `Transport` as well.
+///
 /// > **Note**: The methods of this trait use `self` and not `&self` or `&mut self`. In other
 /// >           words, listening or dialing consumes the transport object. This has been designed
 /// >           so that you would implement this trait on `&Foo` or `&mut Foo` instead of directly
@@ -96,7 +101,7 @@ pub trait Transport {
     /// transport stack. The item must be a [`ListenerUpgrade`](Transport::ListenerUpgrade) future
     /// that resolves to an [`Output`](Transport::Output) value once all protocol upgrades
     /// have been applied.
-    type Listener: Stream<Item = ListenerEvent<Self::ListenerUpgrade>, Error = Self::Error>;
+    type Listener: Stream<Item = Result<ListenerEvent<Self::ListenerUpgrade>, Self::Error>>;
 
     /// A pending [`Output`](Transport::Output) for an inbound connection,
     /// obtained from the [`Listener`](Transport::Listener) stream.
@@ -107,11 +112,11 @@ pub trait Transport {
     /// connection, hence further connection setup proceeds asynchronously.
     /// Once a `ListenerUpgrade` future resolves it yields the [`Output`](Transport::Output)
     /// of the connection setup process.
-    type ListenerUpgrade: Future<Item = Self::Output, Error = Self::Error>;
+    type ListenerUpgrade: Future<Output = Result<Self::Output, Self::Error>>;
 
     /// A pending [`Output`](Transport::Output) for an outbound connection,
     /// obtained from [dialing](Transport::dial).
-    type Dial: Future<Item = Self::Output, Error = Self::Error>;
+    type Dial: Future<Output = Result<Self::Output, Self::Error>>;
 
     /// Listens on the given [`Multiaddr`], producing a stream of pending, inbound connections
     /// and addresses this transport is listening on (cf. [`ListenerEvent`]).
@@ -127,6 +132,24 @@ pub trait Transport {
     where
         Self: Sized;
 
+    /// Dials the given [`Multiaddr`], taking the role of the listener in the upgrades of the
+    /// connection.
+    ///
+    /// This is used for hole punching, where both peers dial each other at the same time. A TCP
+    /// simultaneous open then results in a single connection that both peers consider outgoing,
+    /// and one of them has to act as the listener for the upgrades to be negotiated.
+    ///
+    /// The default implementation calls [`dial`](Transport::dial), which is correct for the
+    /// transports that don't upgrade their connections. The transports that do, or that wrap
+    /// another transport, must override it, and return
+    /// [`TransportError::MultiaddrNotSupported`] if they can't take the role of the listener.
+    fn dial_as_listener(self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>>
+    where
+        Self: Sized
+    {
+        self.dial(addr)
+    }
+
     /// Turns this `Transport` into an abstract boxed transport.
     fn boxed(self) -> boxed::Boxed<Self::Output, Self::Error>
     where Self: Sized + Clone + Send + Sync + 'static,
@@ -194,7 +217,7 @@ pub trait Transport {
     where
         Self: Sized,
         C: FnOnce(Self::Output, ConnectedPoint) -> F + Clone,
-        F: IntoFuture<Item = O>
+        F: TryFuture<Ok = O>
     {
         and_then::AndThen::new(self, upgrade)
     }
@@ -24,12 +24,13 @@
 //! underlying `Transport`.
 // TODO: add example
 
-use crate::{Multiaddr, Transport, transport::{TransportError, ListenerEvent}};
-use futures::{try_ready, Async, Future, Poll, Stream};
+use crate::{Multiaddr, Transport, clock, compat::with_task, transport::{TransportError, ListenerEvent}};
+use futures::{Async, Future as Future01};
+use futures03::{ready, stream::TryStream, task::{Context, Poll}};
 use log::debug;
-use std::{error, fmt, time::Duration};
-use wasm_timer::Timeout;
-use wasm_timer::timeout::Error as TimeoutError;
+use pin_project::pin_project;
+use std::{error, fmt, future::Future, pin::Pin, time::Duration};
+use wasm_timer::Delay;
 
 /// A `TransportTimeout` is a `Transport` that wraps another `Transport` and adds
 /// timeouts to all inbound and outbound connection attempts.
@@ -80,8 +81,8 @@ where
     type Output = InnerTrans::Output;
     type Error = TransportTimeoutError<InnerTrans::Error>;
     type Listener = TimeoutListener<InnerTrans::Listener>;
-    type ListenerUpgrade = TokioTimerMapErr<Timeout<InnerTrans::ListenerUpgrade>>;
-    type Dial = TokioTimerMapErr<Timeout<InnerTrans::Dial>>;
+    type ListenerUpgrade = Timeout<InnerTrans::ListenerUpgrade>;
+    type Dial = Timeout<InnerTrans::Dial>;
 
     fn listen_on(self, addr: Multiaddr) -> Result<Self::Listener, TransportError<Self::Error>> {
         let listener = self.inner.listen_on(addr)
@@ -98,68 +99,85 @@ where
     fn dial(self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
         let dial = self.inner.dial(addr)
             .map_err(|err| err.map(TransportTimeoutError::Other))?;
-        Ok(TokioTimerMapErr {
-            inner: Timeout::new(dial, self.outgoing_timeout),
-        })
+        Ok(Timeout::new(dial, self.outgoing_timeout))
+    }
+
+    fn dial_as_listener(self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
+        let dial = self.inner.dial_as_listener(addr)
+            .map_err(|err| err.map(TransportTimeoutError::Other))?;
+        Ok(Timeout::new(dial, self.outgoing_timeout))
     }
 }
 
 // TODO: can be removed and replaced with an `impl Stream` once impl Trait is fully stable
 //       in Rust (https://github.com/rust-lang/rust/issues/34511)
+#[pin_project]
 pub struct TimeoutListener<InnerStream> {
+    #[pin]
     inner: InnerStream,
     timeout: Duration,
 }
 
-impl<InnerStream, O> Stream for TimeoutListener<InnerStream>
+impl<InnerStream, O> futures03::Stream for TimeoutListener<InnerStream>
 where
-    InnerStream: Stream<Item = ListenerEvent<O>>
+    InnerStream: TryStream<Ok = ListenerEvent<O>>
 {
-    type Item = ListenerEvent<TokioTimerMapErr<Timeout<O>>>;
-    type Error = TransportTimeoutError<InnerStream::Error>;
-
-    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
-        let poll_out = try_ready!(self.inner.poll().map_err(TransportTimeoutError::Other));
-        if let Some(event) = poll_out {
-            let event = event.map(move |inner_fut| {
-                TokioTimerMapErr { inner: Timeout::new(inner_fut, self.timeout) }
-            });
-            Ok(Async::Ready(Some(event)))
-        } else {
-            Ok(Async::Ready(None))
-        }
+    type Item = Result<ListenerEvent<Timeout<O>>, TransportTimeoutError<InnerStream::Error>>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        let poll_out = match ready!(this.inner.try_poll_next(cx)) {
+            Some(Ok(event)) => event,
+            Some(Err(err)) => return Poll::Ready(Some(Err(TransportTimeoutError::Other(err)))),
+            None => return Poll::Ready(None)
+        };
+        let timeout = *this.timeout;
+        let event = poll_out.map(move |inner_fut| Timeout::new(inner_fut, timeout));
+        Poll::Ready(Some(Ok(event)))
     }
 }
 
-/// Wraps around a `Future`. Turns the error type from `TimeoutError<Err>` to
-/// `TransportTimeoutError<Err>`.
+/// Wraps around a `Future` and produces a `TransportTimeoutError::Timeout` if it doesn't
+/// complete within the given duration.
 // TODO: can be replaced with `impl Future` once `impl Trait` are fully stable in Rust
 //       (https://github.com/rust-lang/rust/issues/34511)
+#[pin_project]
 #[must_use = "futures do nothing unless polled"]
-pub struct TokioTimerMapErr<InnerFut> {
+pub struct Timeout<InnerFut> {
+    #[pin]
     inner: InnerFut,
+    timer: Delay,
+}
+
+impl<InnerFut> Timeout<InnerFut> {
+    fn new(inner: InnerFut, timeout: Duration) -> Self {
+        Timeout { inner, timer: Delay::new(clock::now() + timeout) }
+    }
 }
 
-impl<InnerFut, TErr> Future for TokioTimerMapErr<InnerFut>
+impl<InnerFut, TOut, TErr> Future for Timeout<InnerFut>
 where
-    InnerFut: Future<Error = TimeoutError<TErr>>,
+    InnerFut: Future<Output = Result<TOut, TErr>>,
 {
-    type Item = InnerFut::Item;
-    type Error = TransportTimeoutError<TErr>;
-
-    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
-        self.inner.poll().map_err(|err: TimeoutError<TErr>| {
-            if err.is_inner() {
-                TransportTimeoutError::Other(err.into_inner().expect("ensured by is_inner()"))
-            } else if err.is_elapsed() {
+    type Output = Result<TOut, TransportTimeoutError<TErr>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        if let Poll::Ready(result) = this.inner.poll(cx) {
+            return Poll::Ready(result.map_err(TransportTimeoutError::Other))
+        }
+        let timer = this.timer;
+        match with_task(cx, || timer.poll()) {
+            Ok(Async::NotReady) => Poll::Pending,
+            Ok(Async::Ready(())) => {
                 debug!("timeout elapsed for connection");
-                TransportTimeoutError::Timeout
-            } else {
-                assert!(err.is_timer());
-                debug!("tokio timer error in timeout wrapper");
-                TransportTimeoutError::TimerError
+                Poll::Ready(Err(TransportTimeoutError::Timeout))
+            }
+            Err(err) => {
+                debug!("tokio timer error in timeout wrapper: {:?}", err);
+                Poll::Ready(Err(TransportTimeoutError::TimerError))
             }
-        })
+        }
     }
 }
 
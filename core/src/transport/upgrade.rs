@@ -19,6 +19,7 @@
 // DEALINGS IN THE SOFTWARE.
 
 use crate::{
+    Endpoint,
     transport::{Transport, TransportError, ListenerEvent},
     upgrade::{
         OutboundUpgrade,
@@ -30,9 +31,10 @@ use crate::{
         InboundUpgradeApply
     }
 };
-use futures::{future::Either, prelude::*, try_ready};
+use futures03::{future::Either, ready, stream::TryStream, task::{Context, Poll}};
 use multiaddr::Multiaddr;
-use std::{error, fmt};
+use pin_project::pin_project;
+use std::{error, fmt, future::Future, pin::Pin};
 use tokio_io::{AsyncRead, AsyncWrite};
 
 #[derive(Debug, Copy, Clone)]
@@ -56,15 +58,26 @@ where
     type Output = O;
     type Error = TransportUpgradeError<D::Error, TUpgrErr>;
     type Listener = ListenerStream<D::Listener, U>;
-    type ListenerUpgrade = ListenerUpgradeFuture<D::ListenerUpgrade, U>;
-    type Dial = DialUpgradeFuture<D::Dial, U>;
+    type ListenerUpgrade = ListenerUpgradeFuture<D::ListenerUpgrade, U, D::Output>;
+    type Dial = DialUpgradeFuture<D::Dial, U, D::Output>;
 
     fn dial(self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
         let outbound = self.inner.dial(addr.clone())
             .map_err(|err| err.map(TransportUpgradeError::Transport))?;
         Ok(DialUpgradeFuture {
             future: outbound,
-            upgrade: Either::A(Some(self.upgrade))
+            role: Endpoint::Dialer,
+            upgrade: Either::Left(Some(self.upgrade))
+        })
+    }
+
+    fn dial_as_listener(self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
+        let outbound = self.inner.dial_as_listener(addr)
+            .map_err(|err| err.map(TransportUpgradeError::Transport))?;
+        Ok(DialUpgradeFuture {
+            future: outbound,
+            role: Endpoint::Listener,
+            upgrade: Either::Left(Some(self.upgrade))
         })
     }
 
@@ -110,104 +123,119 @@ where
     }
 }
 
-pub struct DialUpgradeFuture<T, U>
+#[pin_project]
+pub struct DialUpgradeFuture<T, U, C>
 where
-    T: Future,
-    T::Item: AsyncRead + AsyncWrite,
-    U: OutboundUpgrade<T::Item>
+    C: AsyncRead + AsyncWrite,
+    U: InboundUpgrade<C> + OutboundUpgrade<C>
 {
+    #[pin]
     future: T,
-    upgrade: Either<Option<U>, OutboundUpgradeApply<T::Item, U>>
+    role: Endpoint,
+    upgrade: Either<Option<U>, UpgradeApply<C, U>>
 }
 
-impl<T, U> Future for DialUpgradeFuture<T, U>
+/// Upgrade of a connection, in the role of the listener or of the dialer.
+type UpgradeApply<C, U> = Either<InboundUpgradeApply<C, U>, OutboundUpgradeApply<C, U>>;
+
+impl<T, U, C, O, E, TErr> Future for DialUpgradeFuture<T, U, C>
 where
-    T: Future,
-    T::Item: AsyncRead + AsyncWrite,
-    U: OutboundUpgrade<T::Item>,
-    U::Error: std::error::Error + Send + Sync + 'static
+    T: Future<Output = Result<C, TErr>>,
+    C: AsyncRead + AsyncWrite,
+    U: InboundUpgrade<C, Output = O, Error = E>,
+    U: OutboundUpgrade<C, Output = O, Error = E>,
+    E: std::error::Error + Send + Sync + 'static
 {
-    type Item = U::Output;
-    type Error = TransportUpgradeError<T::Error, U::Error>;
+    type Output = Result<O, TransportUpgradeError<TErr, E>>;
 
-    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut this = self.project();
         loop {
-            let next = match self.upgrade {
-                Either::A(ref mut up) => {
-                    let x = try_ready!(self.future.poll().map_err(TransportUpgradeError::Transport));
-                    let u = up.take().expect("DialUpgradeFuture is constructed with Either::A(Some).");
-                    Either::B(apply_outbound(x, u))
+            let next = match this.upgrade {
+                Either::Left(ref mut up) => {
+                    let x = ready!(this.future.as_mut().poll(cx)).map_err(TransportUpgradeError::Transport)?;
+                    let u = up.take().expect("DialUpgradeFuture is constructed with Either::Left(Some).");
+                    match this.role {
+                        Endpoint::Dialer => Either::Right(Either::Right(apply_outbound(x, u))),
+                        Endpoint::Listener => Either::Right(Either::Left(apply_inbound(x, u)))
+                    }
                 }
-                Either::B(ref mut up) => return up.poll().map_err(TransportUpgradeError::Upgrade)
+                Either::Right(ref mut up) =>
+                    return Pin::new(up).poll(cx).map_err(TransportUpgradeError::Upgrade)
             };
-            self.upgrade = next
+            *this.upgrade = next
         }
     }
 }
 
+#[pin_project]
 pub struct ListenerStream<T, U> {
+    #[pin]
     stream: T,
     upgrade: U
 }
 
-impl<T, U, F> Stream for ListenerStream<T, U>
+impl<T, U, F, C, TErr> futures03::Stream for ListenerStream<T, U>
 where
-    T: Stream<Item = ListenerEvent<F>>,
-    F: Future,
-    F::Item: AsyncRead + AsyncWrite,
-    U: InboundUpgrade<F::Item> + Clone
+    T: TryStream<Ok = ListenerEvent<F>>,
+    F: Future<Output = Result<C, TErr>>,
+    C: AsyncRead + AsyncWrite,
+    U: InboundUpgrade<C> + Clone
 {
-    type Item = ListenerEvent<ListenerUpgradeFuture<F, U>>;
-    type Error = TransportUpgradeError<T::Error, U::Error>;
+    type Item = Result<ListenerEvent<ListenerUpgradeFuture<F, U, C>>, TransportUpgradeError<T::Error, U::Error>>;
 
-    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
-        match try_ready!(self.stream.poll().map_err(TransportUpgradeError::Transport)) {
-            Some(event) => {
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        match ready!(this.stream.try_poll_next(cx)) {
+            Some(Ok(event)) => {
+                let upgrade = this.upgrade;
                 let event = event.map(move |x| {
                     ListenerUpgradeFuture {
                         future: x,
-                        upgrade: Either::A(Some(self.upgrade.clone()))
+                        upgrade: Either::Left(Some(upgrade.clone()))
                     }
                 });
-                Ok(Async::Ready(Some(event)))
+                Poll::Ready(Some(Ok(event)))
             }
-            None => Ok(Async::Ready(None))
+            Some(Err(err)) => Poll::Ready(Some(Err(TransportUpgradeError::Transport(err)))),
+            None => Poll::Ready(None)
         }
     }
 }
 
-pub struct ListenerUpgradeFuture<T, U>
+#[pin_project]
+pub struct ListenerUpgradeFuture<T, U, C>
 where
-    T: Future,
-    T::Item: AsyncRead + AsyncWrite,
-    U: InboundUpgrade<T::Item>
+    C: AsyncRead + AsyncWrite,
+    U: InboundUpgrade<C>
 {
+    #[pin]
     future: T,
-    upgrade: Either<Option<U>, InboundUpgradeApply<T::Item, U>>
+    upgrade: Either<Option<U>, InboundUpgradeApply<C, U>>
 }
 
-impl<T, U> Future for ListenerUpgradeFuture<T, U>
+impl<T, U, C, TErr> Future for ListenerUpgradeFuture<T, U, C>
 where
-    T: Future,
-    T::Item: AsyncRead + AsyncWrite,
-    U: InboundUpgrade<T::Item>,
+    T: Future<Output = Result<C, TErr>>,
+    C: AsyncRead + AsyncWrite,
+    U: InboundUpgrade<C>,
     U::Error: std::error::Error + Send + Sync + 'static
 {
-    type Item = U::Output;
-    type Error = TransportUpgradeError<T::Error, U::Error>;
+    type Output = Result<U::Output, TransportUpgradeError<TErr, U::Error>>;
 
-    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut this = self.project();
         loop {
-            let next = match self.upgrade {
-                Either::A(ref mut up) => {
-                    let x = try_ready!(self.future.poll().map_err(TransportUpgradeError::Transport));
-                    let u = up.take().expect("ListenerUpgradeFuture is constructed with Either::A(Some).");
-                    Either::B(apply_inbound(x, u))
+            let next = match this.upgrade {
+                Either::Left(ref mut up) => {
+                    let x = ready!(this.future.as_mut().poll(cx)).map_err(TransportUpgradeError::Transport)?;
+                    let u = up.take().expect("ListenerUpgradeFuture is constructed with Either::Left(Some).");
+                    Either::Right(apply_inbound(x, u))
                 }
-                Either::B(ref mut up) => return up.poll().map_err(TransportUpgradeError::Upgrade)
+                Either::Right(ref mut up) =>
+                    return Pin::new(up).poll(cx).map_err(TransportUpgradeError::Upgrade)
             };
-            self.upgrade = next
+            *this.upgrade = next
         }
     }
 }
-
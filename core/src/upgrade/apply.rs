@@ -19,11 +19,14 @@
 // DEALINGS IN THE SOFTWARE.
 
 use crate::ConnectedPoint;
+use crate::compat::with_task;
+use crate::muxing::SubstreamMeter;
 use crate::upgrade::{UpgradeInfo, InboundUpgrade, OutboundUpgrade, UpgradeError, ProtocolName};
-use futures::{future::Either, prelude::*};
+use futures::{Async, Future as Future01};
+use futures03::{future::Either, task::{Context, Poll}};
 use log::debug;
 use multistream_select::{self, DialerSelectFuture, ListenerSelectFuture};
-use std::mem;
+use std::{future::Future, mem, pin::Pin, sync::Arc};
 use tokio_io::{AsyncRead, AsyncWrite};
 
 /// Applies an upgrade to the inbound and outbound direction of a connection or substream.
@@ -33,10 +36,10 @@ where
     C: AsyncRead + AsyncWrite,
     U: InboundUpgrade<C> + OutboundUpgrade<C>,
 {
-    if cp.is_listener() {
-        Either::A(apply_inbound(conn, up))
+    if cp.role().is_listener() {
+        Either::Left(apply_inbound(conn, up))
     } else {
-        Either::B(apply_outbound(conn, up))
+        Either::Right(apply_outbound(conn, up))
     }
 }
 
@@ -49,7 +52,9 @@ where
     let iter = UpgradeInfoIterWrap(up);
     let future = multistream_select::listener_select_proto(conn, iter);
     InboundUpgradeApply {
-        inner: InboundUpgradeApplyState::Init { future }
+        inner: InboundUpgradeApplyState::Init { future },
+        protocol: None,
+        meter: None
     }
 }
 
@@ -62,7 +67,9 @@ where
     let iter = up.protocol_info().into_iter().map(NameWrap as fn(_) -> NameWrap<_>);
     let future = multistream_select::dialer_select_proto(conn, iter);
     OutboundUpgradeApply {
-        inner: OutboundUpgradeApplyState::Init { future, upgrade: up }
+        inner: OutboundUpgradeApplyState::Init { future, upgrade: up },
+        protocol: None,
+        meter: None
     }
 }
 
@@ -72,7 +79,34 @@ where
     C: AsyncRead + AsyncWrite,
     U: InboundUpgrade<C>
 {
-    inner: InboundUpgradeApplyState<C, U>
+    inner: InboundUpgradeApplyState<C, U>,
+    protocol: Option<U::Info>,
+    meter: Option<Arc<dyn SubstreamMeter>>
+}
+
+impl<C, U> InboundUpgradeApply<C, U>
+where
+    C: AsyncRead + AsyncWrite,
+    U: InboundUpgrade<C>
+{
+    /// Returns the name of the negotiated protocol, once the negotiation has succeeded.
+    pub fn negotiated_protocol(&self) -> Option<&[u8]> {
+        self.protocol.as_ref().map(|p| p.protocol_name())
+    }
+
+    /// Informs the given meter of the negotiated protocol, before the upgrade is applied.
+    pub fn with_meter(mut self, meter: Arc<dyn SubstreamMeter>) -> Self {
+        self.meter = Some(meter);
+        self
+    }
+}
+
+// The negotiation is a futures 0.1 future, and the upgrade is boxed.
+impl<C, U> Unpin for InboundUpgradeApply<C, U>
+where
+    C: AsyncRead + AsyncWrite,
+    U: InboundUpgrade<C>
+{
 }
 
 enum InboundUpgradeApplyState<C, U>
@@ -84,7 +118,7 @@ where
         future: ListenerSelectFuture<C, UpgradeInfoIterWrap<U>, NameWrap<U::Info>>,
     },
     Upgrade {
-        future: U::Future
+        future: Pin<Box<U::Future>>
     },
     Undefined
 }
@@ -94,37 +128,40 @@ where
     C: AsyncRead + AsyncWrite,
     U: InboundUpgrade<C>,
 {
-    type Item = U::Output;
-    type Error = UpgradeError<U::Error>;
+    type Output = Result<U::Output, UpgradeError<U::Error>>;
 
-    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         loop {
             match mem::replace(&mut self.inner, InboundUpgradeApplyState::Undefined) {
                 InboundUpgradeApplyState::Init { mut future } => {
-                    let (info, connection, upgrade) = match future.poll()? {
+                    let (info, connection, upgrade) = match with_task(cx, || future.poll())? {
                         Async::Ready(x) => x,
                         Async::NotReady => {
                             self.inner = InboundUpgradeApplyState::Init { future };
-                            return Ok(Async::NotReady)
+                            return Poll::Pending
                         }
                     };
+                    if let Some(meter) = self.meter.take() {
+                        meter.on_negotiated(info.0.protocol_name())
+                    }
+                    self.protocol = Some(info.0.clone());
                     self.inner = InboundUpgradeApplyState::Upgrade {
-                        future: upgrade.0.upgrade_inbound(connection, info.0)
+                        future: Box::pin(upgrade.0.upgrade_inbound(connection, info.0))
                     };
                 }
                 InboundUpgradeApplyState::Upgrade { mut future } => {
-                    match future.poll() {
-                        Ok(Async::NotReady) => {
+                    match future.as_mut().poll(cx) {
+                        Poll::Pending => {
                             self.inner = InboundUpgradeApplyState::Upgrade { future };
-                            return Ok(Async::NotReady)
+                            return Poll::Pending
                         }
-                        Ok(Async::Ready(x)) => {
+                        Poll::Ready(Ok(x)) => {
                             debug!("Successfully applied negotiated protocol");
-                            return Ok(Async::Ready(x))
+                            return Poll::Ready(Ok(x))
                         }
-                        Err(e) => {
+                        Poll::Ready(Err(e)) => {
                             debug!("Failed to apply negotiated protocol");
-                            return Err(UpgradeError::Apply(e))
+                            return Poll::Ready(Err(UpgradeError::Apply(e)))
                         }
                     }
                 }
@@ -141,7 +178,34 @@ where
     C: AsyncRead + AsyncWrite,
     U: OutboundUpgrade<C>
 {
-    inner: OutboundUpgradeApplyState<C, U>
+    inner: OutboundUpgradeApplyState<C, U>,
+    protocol: Option<U::Info>,
+    meter: Option<Arc<dyn SubstreamMeter>>
+}
+
+impl<C, U> OutboundUpgradeApply<C, U>
+where
+    C: AsyncRead + AsyncWrite,
+    U: OutboundUpgrade<C>
+{
+    /// Returns the name of the negotiated protocol, once the negotiation has succeeded.
+    pub fn negotiated_protocol(&self) -> Option<&[u8]> {
+        self.protocol.as_ref().map(|p| p.protocol_name())
+    }
+
+    /// Informs the given meter of the negotiated protocol, before the upgrade is applied.
+    pub fn with_meter(mut self, meter: Arc<dyn SubstreamMeter>) -> Self {
+        self.meter = Some(meter);
+        self
+    }
+}
+
+// The negotiation is a futures 0.1 future, and the upgrade is boxed.
+impl<C, U> Unpin for OutboundUpgradeApply<C, U>
+where
+    C: AsyncRead + AsyncWrite,
+    U: OutboundUpgrade<C>
+{
 }
 
 enum OutboundUpgradeApplyState<C, U>
@@ -154,7 +218,7 @@ where
         upgrade: U
     },
     Upgrade {
-        future: U::Future
+        future: Pin<Box<U::Future>>
     },
     Undefined
 }
@@ -164,37 +228,40 @@ where
     C: AsyncRead + AsyncWrite,
     U: OutboundUpgrade<C>
 {
-    type Item = U::Output;
-    type Error = UpgradeError<U::Error>;
+    type Output = Result<U::Output, UpgradeError<U::Error>>;
 
-    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         loop {
             match mem::replace(&mut self.inner, OutboundUpgradeApplyState::Undefined) {
                 OutboundUpgradeApplyState::Init { mut future, upgrade } => {
-                    let (info, connection) = match future.poll()? {
+                    let (info, connection) = match with_task(cx, || future.poll())? {
                         Async::Ready(x) => x,
                         Async::NotReady => {
                             self.inner = OutboundUpgradeApplyState::Init { future, upgrade };
-                            return Ok(Async::NotReady)
+                            return Poll::Pending
                         }
                     };
+                    if let Some(meter) = self.meter.take() {
+                        meter.on_negotiated(info.0.protocol_name())
+                    }
+                    self.protocol = Some(info.0.clone());
                     self.inner = OutboundUpgradeApplyState::Upgrade {
-                        future: upgrade.upgrade_outbound(connection, info.0)
+                        future: Box::pin(upgrade.upgrade_outbound(connection, info.0))
                     };
                 }
                 OutboundUpgradeApplyState::Upgrade { mut future } => {
-                    match future.poll() {
-                        Ok(Async::NotReady) => {
+                    match future.as_mut().poll(cx) {
+                        Poll::Pending => {
                             self.inner = OutboundUpgradeApplyState::Upgrade { future };
-                            return Ok(Async::NotReady)
+                            return Poll::Pending
                         }
-                        Ok(Async::Ready(x)) => {
+                        Poll::Ready(Ok(x)) => {
                             debug!("Successfully applied negotiated protocol");
-                            return Ok(Async::Ready(x))
+                            return Poll::Ready(Ok(x))
                         }
-                        Err(e) => {
+                        Poll::Ready(Err(e)) => {
                             debug!("Failed to apply negotiated protocol");
-                            return Err(UpgradeError::Apply(e))
+                            return Poll::Ready(Err(UpgradeError::Apply(e)))
                         }
                     }
                 }
@@ -19,7 +19,7 @@
 // DEALINGS IN THE SOFTWARE.
 
 use crate::upgrade::{InboundUpgrade, OutboundUpgrade, UpgradeInfo};
-use futures::future;
+use futures03::future;
 use multistream_select::Negotiated;
 use std::iter;
 use void::Void;
@@ -41,20 +41,20 @@ impl UpgradeInfo for DeniedUpgrade {
 impl<C> InboundUpgrade<C> for DeniedUpgrade {
     type Output = Void;
     type Error = Void;
-    type Future = future::Empty<Self::Output, Self::Error>;
+    type Future = future::Pending<Result<Self::Output, Self::Error>>;
 
     fn upgrade_inbound(self, _: Negotiated<C>, _: Self::Info) -> Self::Future {
-        future::empty()
+        future::pending()
     }
 }
 
 impl<C> OutboundUpgrade<C> for DeniedUpgrade {
     type Output = Void;
     type Error = Void;
-    type Future = future::Empty<Self::Output, Self::Error>;
+    type Future = future::Pending<Result<Self::Output, Self::Error>>;
 
     fn upgrade_outbound(self, _: Negotiated<C>, _: Self::Info) -> Self::Future {
-        future::empty()
+        future::pending()
     }
 }
 
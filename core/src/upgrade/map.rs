@@ -19,8 +19,10 @@
 // DEALINGS IN THE SOFTWARE.
 
 use crate::upgrade::{InboundUpgrade, OutboundUpgrade, UpgradeInfo};
-use futures::{prelude::*, try_ready};
+use futures03::{ready, task::{Context, Poll}};
 use multistream_select::Negotiated;
+use pin_project::pin_project;
+use std::{future::Future, pin::Pin};
 
 /// Wraps around an upgrade and applies a closure to the output.
 #[derive(Debug, Clone)]
@@ -230,48 +232,50 @@ where
     }
 }
 
+#[pin_project]
 pub struct MapFuture<TInnerFut, TMap> {
+    #[pin]
     inner: TInnerFut,
     map: Option<TMap>,
 }
 
-impl<TInnerFut, TIn, TMap, TOut> Future for MapFuture<TInnerFut, TMap>
+impl<TInnerFut, TIn, TErr, TMap, TOut> Future for MapFuture<TInnerFut, TMap>
 where
-    TInnerFut: Future<Item = TIn>,
+    TInnerFut: Future<Output = Result<TIn, TErr>>,
     TMap: FnOnce(TIn) -> TOut,
 {
-    type Item = TOut;
-    type Error = TInnerFut::Error;
+    type Output = Result<TOut, TErr>;
 
-    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
-        let item = try_ready!(self.inner.poll());
-        let map = self.map.take().expect("Future has already finished");
-        Ok(Async::Ready(map(item)))
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let item = ready!(this.inner.poll(cx))?;
+        let map = this.map.take().expect("Future has already finished");
+        Poll::Ready(Ok(map(item)))
     }
 }
 
+#[pin_project]
 pub struct MapErrFuture<T, F> {
+    #[pin]
     fut: T,
     fun: Option<F>,
 }
 
-impl<T, E, F, A> Future for MapErrFuture<T, F>
+impl<T, I, E, F, A> Future for MapErrFuture<T, F>
 where
-    T: Future<Error = E>,
+    T: Future<Output = Result<I, E>>,
     F: FnOnce(E) -> A,
 {
-    type Item = T::Item;
-    type Error = A;
+    type Output = Result<I, A>;
 
-    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
-        match self.fut.poll() {
-            Ok(Async::NotReady) => Ok(Async::NotReady),
-            Ok(Async::Ready(x)) => Ok(Async::Ready(x)),
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        match ready!(this.fut.poll(cx)) {
+            Ok(x) => Poll::Ready(Ok(x)),
             Err(e) => {
-                let f = self.fun.take().expect("Future has not resolved yet");
-                Err(f(e))
+                let f = this.fun.take().expect("Future has not resolved yet");
+                Poll::Ready(Err(f(e)))
             }
         }
     }
 }
-
@@ -56,6 +56,11 @@
 //!             `ProtocolsHandler` APIs, the upgrade is automatically handled for you and you don't
 //!             need to use these methods.
 //!
+//! The handshake futures are `std::future::Future`s. Upgrades written against futures 0.1
+//! implement the [`InboundUpgrade01`](crate::compat::InboundUpgrade01) and
+//! [`OutboundUpgrade01`](crate::compat::OutboundUpgrade01) traits instead, and can be used
+//! wherever an `InboundUpgrade` or `OutboundUpgrade` is expected.
+//!
 
 mod apply;
 mod denied;
@@ -66,7 +71,7 @@ mod optional;
 mod select;
 mod transfer;
 
-use futures::future::Future;
+use std::future::Future;
 
 pub use multistream_select::Negotiated;
 pub use self::{
@@ -143,12 +148,14 @@ pub trait InboundUpgrade<C>: UpgradeInfo {
     /// Possible error during the handshake.
     type Error;
     /// Future that performs the handshake with the remote.
-    type Future: Future<Item = Self::Output, Error = Self::Error>;
+    type Future: Future<Output = Result<Self::Output, Self::Error>>;
 
     /// After we have determined that the remote supports one of the protocols we support, this
     /// method is called to start the handshake.
     ///
-    /// The `info` is the identifier of the protocol, as produced by `protocol_info`.
+    /// The `info` is the identifier of the protocol, as produced by `protocol_info`. The
+    /// negotiated `socket` implements the `AsyncRead`/`AsyncWrite` traits of futures 0.3 if `C`
+    /// does, which is the case of the substreams of a muxer.
     fn upgrade_inbound(self, socket: Negotiated<C>, info: Self::Info) -> Self::Future;
 }
 
@@ -183,12 +190,13 @@ pub trait OutboundUpgrade<C>: UpgradeInfo {
     /// Possible error during the handshake.
     type Error;
     /// Future that performs the handshake with the remote.
-    type Future: Future<Item = Self::Output, Error = Self::Error>;
+    type Future: Future<Output = Result<Self::Output, Self::Error>>;
 
     /// After we have determined that the remote supports one of the protocols we support, this
     /// method is called to start the handshake.
     ///
-    /// The `info` is the identifier of the protocol, as produced by `protocol_info`.
+    /// The `info` is the identifier of the protocol, as produced by `protocol_info`. See
+    /// [`InboundUpgrade::upgrade_inbound`] regarding the `socket`.
     fn upgrade_outbound(self, socket: Negotiated<C>, info: Self::Info) -> Self::Future;
 }
 
@@ -215,4 +223,3 @@ pub trait OutboundUpgradeExt<C>: OutboundUpgrade<C> {
 }
 
 impl<C, U: OutboundUpgrade<C>> OutboundUpgradeExt<C> for U {}
-
@@ -19,6 +19,7 @@
 // DEALINGS IN THE SOFTWARE.
 
 use futures::{future, prelude::*};
+use futures03::task::{Context, Poll as Poll03};
 use libp2p_core::identity;
 use libp2p_core::multiaddr::multiaddr;
 use libp2p_core::nodes::network::{Network, NetworkEvent, NetworkReachError, PeerState, UnknownPeerDialErr, IncomingError};
@@ -80,8 +81,8 @@ where
 
     fn connection_keep_alive(&self) -> KeepAlive { KeepAlive::No }
 
-    fn poll(&mut self) -> Poll<ProtocolsHandlerEvent<Self::OutboundProtocol, Self::OutboundOpenInfo, Self::OutEvent>, Self::Error> {
-        Ok(Async::NotReady)
+    fn poll(&mut self, _: &mut Context<'_>) -> Poll03<Result<ProtocolsHandlerEvent<Self::OutboundProtocol, Self::OutboundOpenInfo, Self::OutEvent>, Self::Error>> {
+        Poll03::Pending
     }
 }
 
@@ -125,12 +126,16 @@ fn deny_incoming_connec() {
 
     swarm1.listen_on("/ip4/127.0.0.1/tcp/0".parse().unwrap()).unwrap();
 
-    let address =
-        if let Async::Ready(NetworkEvent::NewListenerAddress { listen_addr, .. }) = swarm1.poll() {
-            listen_addr
-        } else {
-            panic!("Was expecting the listen address to be reported")
-        };
+    let (address, mut swarm1) =
+        future::lazy(move || {
+            if let Async::Ready(NetworkEvent::NewListenerAddress { listen_addr, .. }) = swarm1.poll() {
+                Ok::<_, void::Void>((listen_addr, swarm1))
+            } else {
+                panic!("Was expecting the listen address to be reported")
+            }
+        })
+        .wait()
+        .unwrap();
 
     swarm2
         .peer(swarm1.local_peer_id().clone())
@@ -19,8 +19,9 @@
 // DEALINGS IN THE SOFTWARE.
 
 use futures::{future, prelude::*};
+use futures03::task::{Context, Poll as Poll03};
 use libp2p_core::identity;
-use libp2p_core::nodes::network::{Network, NetworkEvent, IncomingError};
+use libp2p_core::nodes::network::{Network, NetworkEvent};
 use libp2p_core::{Transport, upgrade, upgrade::OutboundUpgradeExt, upgrade::InboundUpgradeExt};
 use libp2p_swarm::{
     ProtocolsHandler,
@@ -78,8 +79,8 @@ where
 
     fn connection_keep_alive(&self) -> KeepAlive { KeepAlive::Yes }
 
-    fn poll(&mut self) -> Poll<ProtocolsHandlerEvent<Self::OutboundProtocol, Self::OutboundOpenInfo, Self::OutEvent>, Self::Error> {
-        Ok(Async::NotReady)
+    fn poll(&mut self, _: &mut Context<'_>) -> Poll03<Result<ProtocolsHandlerEvent<Self::OutboundProtocol, Self::OutboundOpenInfo, Self::OutEvent>, Self::Error>> {
+        Poll03::Pending
     }
 }
 
@@ -87,17 +88,10 @@ where
 fn raw_swarm_simultaneous_connect() {
     // Checks whether two swarms dialing each other simultaneously properly works.
 
-    // When two swarms A and B dial each other, the following can happen:
-    //
-    // - A and B both successfully open a dialing connection simultaneously, then either A or B
-    //   (but not both) closes its dialing connection and get a `Replaced` event to replace the
-    //   dialing connection with the listening one. The other one gets an `IncomingConnectionError`.
-    // - A successfully dials B; B doesn't have dialing priority and thus cancels its dialing
-    //   attempt. If A receives B's dialing attempt, it gets an `IncomingConnectionError`.
-    // - A successfully dials B; B does have dialing priority and thus continues dialing; then B
-    //   successfully dials A; A and B both get a `Replaced` event to replace the dialing
-    //   connection with the listening one.
-    //
+    // When two swarms A and B dial each other, both the connection dialed by A and the one
+    // dialed by B are kept, regardless of the order in which they are negotiated. Each swarm
+    // must therefore get one `Connected` event per dialed connection, with an increasing number
+    // of established connections.
 
     // Important note: This test is meant to detect race conditions which don't seem to happen
     //                 if we use the `MemoryTransport`. Using the TCP transport is important,
@@ -165,8 +159,14 @@ fn raw_swarm_simultaneous_connect() {
         let mut reactor = tokio::runtime::current_thread::Runtime::new().unwrap();
 
         for _ in 0 .. 10 {
-            let mut swarm1_step = 0;
-            let mut swarm2_step = 0;
+            // Whether each swarm has reached the moment when it starts dialing.
+            let mut swarm1_dial_done = false;
+            let mut swarm2_dial_done = false;
+            // Number of connections that have actually been dialed, by either swarm.
+            let mut num_dials = 0;
+            // Number of `Connected` events received by each swarm.
+            let mut swarm1_connections = 0;
+            let mut swarm2_connections = 0;
 
             let mut swarm1_dial_start = Delay::new(Instant::now() + Duration::new(0, rand::random::<u32>() % 50_000_000));
             let mut swarm2_dial_start = Delay::new(Instant::now() + Duration::new(0, rand::random::<u32>() % 50_000_000));
@@ -179,26 +179,32 @@ fn raw_swarm_simultaneous_connect() {
                     // We add a lot of randomness. In a real-life situation the swarm also has to
                     // handle other nodes, which may delay the processing.
 
-                    if swarm1_step == 0 {
+                    if !swarm1_dial_done {
                         match swarm1_dial_start.poll().unwrap() {
                             Async::Ready(_) => {
+                                // The connection dialed by the other swarm may already have been
+                                // established, in which case we don't dial.
                                 let handler = TestHandler::default().into_node_handler_builder();
-                                swarm1.peer(swarm2.local_peer_id().clone()).into_not_connected().unwrap()
-                                    .connect(swarm2_listen_addr.clone(), handler);
-                                swarm1_step = 1;
+                                if let Some(peer) = swarm1.peer(swarm2.local_peer_id().clone()).into_not_connected() {
+                                    peer.connect(swarm2_listen_addr.clone(), handler);
+                                    num_dials += 1;
+                                }
+                                swarm1_dial_done = true;
                                 swarm1_not_ready = false;
                             },
                             Async::NotReady => swarm1_not_ready = true,
                         }
                     }
 
-                    if swarm2_step == 0 {
+                    if !swarm2_dial_done {
                         match swarm2_dial_start.poll().unwrap() {
                             Async::Ready(_) => {
                                 let handler = TestHandler::default().into_node_handler_builder();
-                                swarm2.peer(swarm1.local_peer_id().clone()).into_not_connected().unwrap()
-                                    .connect(swarm1_listen_addr.clone(), handler);
-                                swarm2_step = 1;
+                                if let Some(peer) = swarm2.peer(swarm1.local_peer_id().clone()).into_not_connected() {
+                                    peer.connect(swarm1_listen_addr.clone(), handler);
+                                    num_dials += 1;
+                                }
+                                swarm2_dial_done = true;
                                 swarm2_not_ready = false;
                             },
                             Async::NotReady => swarm2_not_ready = true,
@@ -207,19 +213,10 @@ fn raw_swarm_simultaneous_connect() {
 
                     if rand::random::<f32>() < 0.1 {
                         match swarm1.poll() {
-                            Async::Ready(NetworkEvent::IncomingConnectionError { error: IncomingError::DeniedLowerPriority, .. }) => {
-                                assert_eq!(swarm1_step, 2);
-                                swarm1_step = 3;
-                            },
-                            Async::Ready(NetworkEvent::Connected { conn_info, .. }) => {
+                            Async::Ready(NetworkEvent::Connected { conn_info, num_established, .. }) => {
                                 assert_eq!(conn_info, *swarm2.local_peer_id());
-                                assert_eq!(swarm1_step, 1);
-                                swarm1_step = 2;
-                            },
-                            Async::Ready(NetworkEvent::Replaced { new_info, .. }) => {
-                                assert_eq!(new_info, *swarm2.local_peer_id());
-                                assert_eq!(swarm1_step, 2);
-                                swarm1_step = 3;
+                                swarm1_connections += 1;
+                                assert_eq!(num_established.get(), swarm1_connections);
                             },
                             Async::Ready(NetworkEvent::IncomingConnection(inc)) => {
                                 inc.accept(TestHandler::default().into_node_handler_builder());
@@ -231,19 +228,10 @@ fn raw_swarm_simultaneous_connect() {
 
                     if rand::random::<f32>() < 0.1 {
                         match swarm2.poll() {
-                            Async::Ready(NetworkEvent::IncomingConnectionError { error: IncomingError::DeniedLowerPriority, .. }) => {
-                                assert_eq!(swarm2_step, 2);
-                                swarm2_step = 3;
-                            },
-                            Async::Ready(NetworkEvent::Connected { conn_info, .. }) => {
+                            Async::Ready(NetworkEvent::Connected { conn_info, num_established, .. }) => {
                                 assert_eq!(conn_info, *swarm1.local_peer_id());
-                                assert_eq!(swarm2_step, 1);
-                                swarm2_step = 2;
-                            },
-                            Async::Ready(NetworkEvent::Replaced { new_info, .. }) => {
-                                assert_eq!(new_info, *swarm1.local_peer_id());
-                                assert_eq!(swarm2_step, 2);
-                                swarm2_step = 3;
+                                swarm2_connections += 1;
+                                assert_eq!(num_established.get(), swarm2_connections);
                             },
                             Async::Ready(NetworkEvent::IncomingConnection(inc)) => {
                                 inc.accept(TestHandler::default().into_node_handler_builder());
@@ -253,8 +241,11 @@ fn raw_swarm_simultaneous_connect() {
                         }
                     }
 
-                    // TODO: make sure that >= 5 is correct
-                    if swarm1_step + swarm2_step >= 5 {
+                    // Every connection that has been dialed is kept, and is seen by both swarms.
+                    assert!(swarm1_connections <= 2 && swarm2_connections <= 2);
+                    if swarm1_dial_done && swarm2_dial_done &&
+                        swarm1_connections == num_dials && swarm2_connections == num_dials
+                    {
                         return Ok(Async::Ready(()));
                     }
 
@@ -47,7 +47,7 @@ fn build_struct(ast: &DeriveInput, data_struct: &DataStruct) -> TokenStream {
     let name = &ast.ident;
     let (_, ty_generics, where_clause) = ast.generics.split_for_impl();
     let multiaddr = quote!{::libp2p::core::Multiaddr};
-    let trait_to_impl = quote!{::libp2p::swarm::NetworkBehaviour};
+    let trait_to_impl = &quote!{::libp2p::swarm::NetworkBehaviour};
     let net_behv_event_proc = quote!{::libp2p::swarm::NetworkBehaviourEventProcess};
     let either_ident = quote!{::libp2p::core::either::EitherOutput};
     let network_behaviour_action = quote!{::libp2p::swarm::NetworkBehaviourAction};
@@ -56,6 +56,8 @@ fn build_struct(ast: &DeriveInput, data_struct: &DataStruct) -> TokenStream {
     let into_proto_select_ident = quote!{::libp2p::swarm::IntoProtocolsHandlerSelect};
     let peer_id = quote!{::libp2p::core::PeerId};
     let connected_point = quote!{::libp2p::core::ConnectedPoint};
+    let connection_id = quote!{::libp2p::core::nodes::ConnectionId};
+    let connection_limit = quote!{::libp2p::core::nodes::network::ConnectionLimit};
 
     // Name of the type parameter that represents the substream.
     let substream_generic = {
@@ -138,8 +140,8 @@ fn build_struct(ast: &DeriveInput, data_struct: &DataStruct) -> TokenStream {
             }
 
             Some(match field.ident {
-                Some(ref i) => quote!{ out.extend(self.#i.addresses_of_peer(peer_id)); },
-                None => quote!{ out.extend(self.#field_n.addresses_of_peer(peer_id)); },
+                Some(ref i) => quote!{ out.extend(#trait_to_impl::addresses_of_peer(&mut self.#i, peer_id)); },
+                None => quote!{ out.extend(#trait_to_impl::addresses_of_peer(&mut self.#field_n, peer_id)); },
             })
         })
     };
@@ -154,13 +156,13 @@ fn build_struct(ast: &DeriveInput, data_struct: &DataStruct) -> TokenStream {
 
             Some(if field_n == num_fields - 1 {
                 match field.ident {
-                    Some(ref i) => quote!{ self.#i.inject_connected(peer_id, endpoint); },
-                    None => quote!{ self.#field_n.inject_connected(peer_id, endpoint); },
+                    Some(ref i) => quote!{ #trait_to_impl::inject_connected(&mut self.#i, peer_id, endpoint); },
+                    None => quote!{ #trait_to_impl::inject_connected(&mut self.#field_n, peer_id, endpoint); },
                 }
             } else {
                 match field.ident {
-                    Some(ref i) => quote!{ self.#i.inject_connected(peer_id.clone(), endpoint.clone()); },
-                    None => quote!{ self.#field_n.inject_connected(peer_id.clone(), endpoint.clone()); },
+                    Some(ref i) => quote!{ #trait_to_impl::inject_connected(&mut self.#i, peer_id.clone(), endpoint.clone()); },
+                    None => quote!{ #trait_to_impl::inject_connected(&mut self.#field_n, peer_id.clone(), endpoint.clone()); },
                 }
             })
         })
@@ -176,40 +178,42 @@ fn build_struct(ast: &DeriveInput, data_struct: &DataStruct) -> TokenStream {
 
             Some(if field_n == num_fields - 1 {
                 match field.ident {
-                    Some(ref i) => quote!{ self.#i.inject_disconnected(peer_id, endpoint); },
-                    None => quote!{ self.#field_n.inject_disconnected(peer_id, endpoint); },
+                    Some(ref i) => quote!{ #trait_to_impl::inject_disconnected(&mut self.#i, peer_id, endpoint); },
+                    None => quote!{ #trait_to_impl::inject_disconnected(&mut self.#field_n, peer_id, endpoint); },
                 }
             } else {
                 match field.ident {
-                    Some(ref i) => quote!{ self.#i.inject_disconnected(peer_id, endpoint.clone()); },
-                    None => quote!{ self.#field_n.inject_disconnected(peer_id, endpoint.clone()); },
+                    Some(ref i) => quote!{ #trait_to_impl::inject_disconnected(&mut self.#i, peer_id, endpoint.clone()); },
+                    None => quote!{ #trait_to_impl::inject_disconnected(&mut self.#field_n, peer_id, endpoint.clone()); },
                 }
             })
         })
     };
 
-    // Build the list of statements to put in the body of `inject_replaced()`.
-    let inject_replaced_stmts = {
-        let num_fields = data_struct.fields.iter().filter(|f| !is_ignored(f)).count();
+    // Build the list of statements to put in the body of `inject_connection_established()`.
+    let inject_connection_established_stmts = {
         data_struct.fields.iter().enumerate().filter_map(move |(field_n, field)| {
             if is_ignored(&field) {
                 return None;
             }
 
-            Some(if field_n == num_fields - 1 {
-                match field.ident {
-                    Some(ref i) => quote!{ self.#i.inject_replaced(peer_id, closed_endpoint, new_endpoint); },
-                    None => quote!{ self.#field_n.inject_replaced(peer_id, closed_endpoint, new_endpoint); },
-                }
-            } else {
-                match field.ident {
-                    Some(ref i) => quote!{
-                        self.#i.inject_replaced(peer_id.clone(), closed_endpoint.clone(), new_endpoint.clone());
-                    },
-                    None => quote!{
-                        self.#field_n.inject_replaced(peer_id.clone(), closed_endpoint.clone(), new_endpoint.clone());
-                    },
-                }
+            Some(match field.ident {
+                Some(ref i) => quote!{ #trait_to_impl::inject_connection_established(&mut self.#i, peer_id, connection, endpoint); },
+                None => quote!{ #trait_to_impl::inject_connection_established(&mut self.#field_n, peer_id, connection, endpoint); },
+            })
+        })
+    };
+
+    // Build the list of statements to put in the body of `inject_connection_closed()`.
+    let inject_connection_closed_stmts = {
+        data_struct.fields.iter().enumerate().filter_map(move |(field_n, field)| {
+            if is_ignored(&field) {
+                return None;
+            }
+
+            Some(match field.ident {
+                Some(ref i) => quote!{ #trait_to_impl::inject_connection_closed(&mut self.#i, peer_id, connection, endpoint); },
+                None => quote!{ #trait_to_impl::inject_connection_closed(&mut self.#field_n, peer_id, connection, endpoint); },
             })
         })
     };
@@ -222,8 +226,8 @@ fn build_struct(ast: &DeriveInput, data_struct: &DataStruct) -> TokenStream {
             }
 
             Some(match field.ident {
-                Some(ref i) => quote!{ self.#i.inject_addr_reach_failure(peer_id, addr, error); },
-                None => quote!{ self.#field_n.inject_addr_reach_failure(peer_id, addr, error); },
+                Some(ref i) => quote!{ #trait_to_impl::inject_addr_reach_failure(&mut self.#i, peer_id, addr, error); },
+                None => quote!{ #trait_to_impl::inject_addr_reach_failure(&mut self.#field_n, peer_id, addr, error); },
             })
         })
     };
@@ -236,8 +240,22 @@ fn build_struct(ast: &DeriveInput, data_struct: &DataStruct) -> TokenStream {
             }
 
             Some(match field.ident {
-                Some(ref i) => quote!{ self.#i.inject_dial_failure(peer_id); },
-                None => quote!{ self.#field_n.inject_dial_failure(peer_id); },
+                Some(ref i) => quote!{ #trait_to_impl::inject_dial_failure(&mut self.#i, peer_id); },
+                None => quote!{ #trait_to_impl::inject_dial_failure(&mut self.#field_n, peer_id); },
+            })
+        })
+    };
+
+    // Build the list of statements to put in the body of `inject_connection_limit_reached()`.
+    let inject_connection_limit_reached_stmts = {
+        data_struct.fields.iter().enumerate().filter_map(move |(field_n, field)| {
+            if is_ignored(&field) {
+                return None;
+            }
+
+            Some(match field.ident {
+                Some(ref i) => quote!{ #trait_to_impl::inject_connection_limit_reached(&mut self.#i, peer_id, endpoint, limit); },
+                None => quote!{ #trait_to_impl::inject_connection_limit_reached(&mut self.#field_n, peer_id, endpoint, limit); },
             })
         })
     };
@@ -250,8 +268,8 @@ fn build_struct(ast: &DeriveInput, data_struct: &DataStruct) -> TokenStream {
             }
 
             Some(match field.ident {
-                Some(ref i) => quote!{ self.#i.inject_new_listen_addr(addr); },
-                None => quote!{ self.#field_n.inject_new_listen_addr(addr); },
+                Some(ref i) => quote!{ #trait_to_impl::inject_new_listen_addr(&mut self.#i, addr); },
+                None => quote!{ #trait_to_impl::inject_new_listen_addr(&mut self.#field_n, addr); },
             })
         })
     };
@@ -264,8 +282,8 @@ fn build_struct(ast: &DeriveInput, data_struct: &DataStruct) -> TokenStream {
             }
 
             Some(match field.ident {
-                Some(ref i) => quote!{ self.#i.inject_expired_listen_addr(addr); },
-                None => quote!{ self.#field_n.inject_expired_listen_addr(addr); },
+                Some(ref i) => quote!{ #trait_to_impl::inject_expired_listen_addr(&mut self.#i, addr); },
+                None => quote!{ #trait_to_impl::inject_expired_listen_addr(&mut self.#field_n, addr); },
             })
         })
     };
@@ -278,8 +296,22 @@ fn build_struct(ast: &DeriveInput, data_struct: &DataStruct) -> TokenStream {
             }
 
             Some(match field.ident {
-                Some(ref i) => quote!{ self.#i.inject_new_external_addr(addr); },
-                None => quote!{ self.#field_n.inject_new_external_addr(addr); },
+                Some(ref i) => quote!{ #trait_to_impl::inject_new_external_addr(&mut self.#i, addr); },
+                None => quote!{ #trait_to_impl::inject_new_external_addr(&mut self.#field_n, addr); },
+            })
+        })
+    };
+
+    // Build the list of statements to put in the body of `inject_expired_external_addr()`.
+    let inject_expired_external_addr_stmts = {
+        data_struct.fields.iter().enumerate().filter_map(move |(field_n, field)| {
+            if is_ignored(&field) {
+                return None;
+            }
+
+            Some(match field.ident {
+                Some(ref i) => quote!{ #trait_to_impl::inject_expired_external_addr(&mut self.#i, addr); },
+                None => quote!{ #trait_to_impl::inject_expired_external_addr(&mut self.#field_n, addr); },
             })
         })
     };
@@ -300,8 +332,8 @@ fn build_struct(ast: &DeriveInput, data_struct: &DataStruct) -> TokenStream {
         }
 
         Some(match field.ident {
-            Some(ref i) => quote!{ #elem => self.#i.inject_node_event(peer_id, ev) },
-            None => quote!{ #elem => self.#field_n.inject_node_event(peer_id, ev) },
+            Some(ref i) => quote!{ #elem => #trait_to_impl::inject_node_event(&mut self.#i, peer_id, connection, ev) },
+            None => quote!{ #elem => #trait_to_impl::inject_node_event(&mut self.#field_n, peer_id, connection, ev) },
         })
     });
 
@@ -338,7 +370,7 @@ fn build_struct(ast: &DeriveInput, data_struct: &DataStruct) -> TokenStream {
             };
 
             let builder = quote! {
-                #field_name.new_handler()
+                #trait_to_impl::new_handler(&mut #field_name)
             };
 
             match out_handler {
@@ -352,16 +384,22 @@ fn build_struct(ast: &DeriveInput, data_struct: &DataStruct) -> TokenStream {
 
     // The method to use to poll.
     // If we find a `#[behaviour(poll_method = "poll")]` attribute on the struct, we call
-    // `self.poll()` at the end of the polling.
+    // `self.poll()` at the end of the polling. The method returns a futures 0.1 `Async`, hence it
+    // is called from within a futures 0.1 task.
     let poll_method = {
-        let mut poll_method = quote!{Async::NotReady};
+        let mut poll_method = quote!{::std::task::Poll::Pending};
         for meta_items in ast.attrs.iter().filter_map(get_meta_items) {
             for meta_item in meta_items {
                 match meta_item {
                     syn::NestedMeta::Meta(syn::Meta::NameValue(ref m)) if m.ident == "poll_method" => {
                         if let syn::Lit::Str(ref s) = m.lit {
                             let ident: Ident = syn::parse_str(&s.value()).unwrap();
-                            poll_method = quote!{#name::#ident(self)};
+                            poll_method = quote!{
+                                match ::libp2p::core::compat::with_task(cx, || #name::#ident(self)) {
+                                    ::libp2p::futures::Async::Ready(action) => ::std::task::Poll::Ready(action),
+                                    ::libp2p::futures::Async::NotReady => ::std::task::Poll::Pending,
+                                }
+                            };
                         }
                     }
                     _ => ()
@@ -391,26 +429,39 @@ fn build_struct(ast: &DeriveInput, data_struct: &DataStruct) -> TokenStream {
 
         Some(quote!{
             loop {
-                match #field_name.poll(poll_params) {
-                    Async::Ready(#network_behaviour_action::GenerateEvent(event)) => {
+                match #trait_to_impl::poll(&mut #field_name, cx, poll_params) {
+                    ::std::task::Poll::Ready(#network_behaviour_action::GenerateEvent(event)) => {
                         #net_behv_event_proc::inject_event(self, event)
                     }
-                    Async::Ready(#network_behaviour_action::DialAddress { address }) => {
-                        return Async::Ready(#network_behaviour_action::DialAddress { address });
+                    ::std::task::Poll::Ready(#network_behaviour_action::DialAddress { address }) => {
+                        return ::std::task::Poll::Ready(#network_behaviour_action::DialAddress { address });
+                    }
+                    ::std::task::Poll::Ready(#network_behaviour_action::DialAddressAsListener { address }) => {
+                        return ::std::task::Poll::Ready(#network_behaviour_action::DialAddressAsListener { address });
                     }
-                    Async::Ready(#network_behaviour_action::DialPeer { peer_id }) => {
-                        return Async::Ready(#network_behaviour_action::DialPeer { peer_id });
+                    ::std::task::Poll::Ready(#network_behaviour_action::DialPeer { peer_id }) => {
+                        return ::std::task::Poll::Ready(#network_behaviour_action::DialPeer { peer_id });
                     }
-                    Async::Ready(#network_behaviour_action::SendEvent { peer_id, event }) => {
-                        return Async::Ready(#network_behaviour_action::SendEvent {
+                    ::std::task::Poll::Ready(#network_behaviour_action::NotifyHandler { peer_id, handler, event }) => {
+                        return ::std::task::Poll::Ready(#network_behaviour_action::NotifyHandler {
                             peer_id,
+                            handler,
                             event: #wrapped_event,
                         });
                     }
-                    Async::Ready(#network_behaviour_action::ReportObservedAddr { address }) => {
-                        return Async::Ready(#network_behaviour_action::ReportObservedAddr { address });
+                    ::std::task::Poll::Ready(#network_behaviour_action::ReportObservedAddr { address }) => {
+                        return ::std::task::Poll::Ready(#network_behaviour_action::ReportObservedAddr { address });
+                    }
+                    ::std::task::Poll::Ready(#network_behaviour_action::ReportExternalAddr { address }) => {
+                        return ::std::task::Poll::Ready(#network_behaviour_action::ReportExternalAddr { address });
+                    }
+                    ::std::task::Poll::Ready(#network_behaviour_action::ReportExpiredExternalAddr { address }) => {
+                        return ::std::task::Poll::Ready(#network_behaviour_action::ReportExpiredExternalAddr { address });
+                    }
+                    ::std::task::Poll::Ready(#network_behaviour_action::ReportMisbehaviour { peer_id, misbehaviour }) => {
+                        return ::std::task::Poll::Ready(#network_behaviour_action::ReportMisbehaviour { peer_id, misbehaviour });
                     }
-                    Async::NotReady => break,
+                    ::std::task::Poll::Pending => break,
                 }
             }
         })
@@ -443,8 +494,12 @@ fn build_struct(ast: &DeriveInput, data_struct: &DataStruct) -> TokenStream {
                 #(#inject_disconnected_stmts);*
             }
 
-            fn inject_replaced(&mut self, peer_id: #peer_id, closed_endpoint: #connected_point, new_endpoint: #connected_point) {
-                #(#inject_replaced_stmts);*
+            fn inject_connection_established(&mut self, peer_id: &#peer_id, connection: &#connection_id, endpoint: &#connected_point) {
+                #(#inject_connection_established_stmts);*
+            }
+
+            fn inject_connection_closed(&mut self, peer_id: &#peer_id, connection: &#connection_id, endpoint: &#connected_point) {
+                #(#inject_connection_closed_stmts);*
             }
 
             fn inject_addr_reach_failure(&mut self, peer_id: Option<&#peer_id>, addr: &#multiaddr, error: &dyn std::error::Error) {
@@ -455,6 +510,10 @@ fn build_struct(ast: &DeriveInput, data_struct: &DataStruct) -> TokenStream {
                 #(#inject_dial_failure_stmts);*
             }
 
+            fn inject_connection_limit_reached(&mut self, peer_id: Option<&#peer_id>, endpoint: &#connected_point, limit: &#connection_limit) {
+                #(#inject_connection_limit_reached_stmts);*
+            }
+
             fn inject_new_listen_addr(&mut self, addr: &#multiaddr) {
                 #(#inject_new_listen_addr_stmts);*
             }
@@ -467,9 +526,14 @@ fn build_struct(ast: &DeriveInput, data_struct: &DataStruct) -> TokenStream {
                 #(#inject_new_external_addr_stmts);*
             }
 
+            fn inject_expired_external_addr(&mut self, addr: &#multiaddr) {
+                #(#inject_expired_external_addr_stmts);*
+            }
+
             fn inject_node_event(
                 &mut self,
                 peer_id: #peer_id,
+                connection: #connection_id,
                 event: <<Self::ProtocolsHandler as #into_protocols_handler>::Handler as #protocols_handler>::OutEvent
             ) {
                 match event {
@@ -477,10 +541,9 @@ fn build_struct(ast: &DeriveInput, data_struct: &DataStruct) -> TokenStream {
                 }
             }
 
-            fn poll(&mut self, poll_params: &mut impl #poll_parameters) -> ::libp2p::futures::Async<#network_behaviour_action<<<Self::ProtocolsHandler as #into_protocols_handler>::Handler as #protocols_handler>::InEvent, Self::OutEvent>> {
-                use libp2p::futures::prelude::*;
+            fn poll(&mut self, cx: &mut ::std::task::Context<'_>, poll_params: &mut impl #poll_parameters) -> ::std::task::Poll<#network_behaviour_action<<<Self::ProtocolsHandler as #into_protocols_handler>::Handler as #protocols_handler>::InEvent, Self::OutEvent>> {
                 #(#poll_stmts)*
-                let f: ::libp2p::futures::Async<#network_behaviour_action<<<Self::ProtocolsHandler as #into_protocols_handler>::Handler as #protocols_handler>::InEvent, Self::OutEvent>> = #poll_method;
+                let f: ::std::task::Poll<#network_behaviour_action<<<Self::ProtocolsHandler as #into_protocols_handler>::Handler as #protocols_handler>::InEvent, Self::OutEvent>> = #poll_method;
                 f
             }
         }
@@ -20,12 +20,13 @@
 
 use crate::service::{MdnsService, MdnsPacket};
 use futures::prelude::*;
-use libp2p_core::{address_translation, ConnectedPoint, Multiaddr, PeerId, multiaddr::Protocol};
+use libp2p_core::{address_translation, clock, ConnectedPoint, Multiaddr, PeerId, multiaddr::Protocol, nodes::ConnectionId};
 use libp2p_swarm::{
-    NetworkBehaviour,
+    AddressSource,
     NetworkBehaviourAction,
     PollParameters,
     ProtocolsHandler,
+    compat::NetworkBehaviour01,
     protocols_handler::DummyProtocolsHandler
 };
 use log::warn;
@@ -143,7 +144,7 @@ impl fmt::Debug for ExpiredAddrsIter {
     }
 }
 
-impl<TSubstream> NetworkBehaviour for Mdns<TSubstream>
+impl<TSubstream> NetworkBehaviour01 for Mdns<TSubstream>
 where
     TSubstream: AsyncRead + AsyncWrite,
 {
@@ -155,7 +156,7 @@ where
     }
 
     fn addresses_of_peer(&mut self, peer_id: &PeerId) -> Vec<Multiaddr> {
-        let now = Instant::now();
+        let now = clock::now();
         self.discovered_nodes
             .iter()
             .filter(move |(p, _, expires)| p == peer_id && *expires > now)
@@ -170,6 +171,7 @@ where
     fn inject_node_event(
         &mut self,
         _: PeerId,
+        _: ConnectionId,
         _ev: <Self::ProtocolsHandler as ProtocolsHandler>::OutEvent,
     ) {
         void::unreachable(_ev)
@@ -188,7 +190,7 @@ where
         if let Some(ref mut closest_expiration) = self.closest_expiration {
             match closest_expiration.poll() {
                 Ok(Async::Ready(())) => {
-                    let now = Instant::now();
+                    let now = clock::now();
                     let mut expired = SmallVec::<[(PeerId, Multiaddr); 4]>::new();
                     while let Some(pos) = self.discovered_nodes.iter().position(|(_, _, exp)| *exp < now) {
                         let (peer_id, addr, _) = self.discovered_nodes.remove(pos);
@@ -238,7 +240,7 @@ where
                             continue;
                         }
 
-                        let new_expiration = Instant::now() + peer.ttl();
+                        let new_expiration = clock::now() + peer.ttl();
 
                         let mut addrs = Vec::new();
                         for addr in peer.addresses() {
@@ -249,6 +251,8 @@ where
                         }
 
                         for addr in addrs {
+                            params.peer_store_mut()
+                                .add_address(peer.id(), addr.clone(), AddressSource::Mdns, Some(peer.ttl()));
                             if let Some((_, _, cur_expires)) = self.discovered_nodes.iter_mut()
                                 .find(|(p, a, _)| p == peer.id() && *a == addr)
                             {
@@ -0,0 +1,166 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Encoding of the metrics of a [`Registry`] in the OpenMetrics text format.
+//!
+//! The format is described in the
+//! [OpenMetrics specification](https://github.com/OpenObservability/OpenMetrics/blob/master/specification/OpenMetrics.md),
+//! and is understood by Prometheus.
+
+use crate::registry::Registry;
+use std::fmt::Write;
+
+/// The type of a metric, as announced in the `# TYPE` line of its family.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MetricType {
+    Counter,
+    Gauge,
+    Histogram,
+}
+
+impl MetricType {
+    fn as_str(&self) -> &'static str {
+        match self {
+            MetricType::Counter => "counter",
+            MetricType::Gauge => "gauge",
+            MetricType::Histogram => "histogram",
+        }
+    }
+}
+
+/// A metric that can be encoded in the OpenMetrics text format.
+pub trait EncodeMetric: Send + Sync {
+    /// Returns the type of the metric.
+    fn metric_type(&self) -> MetricType;
+
+    /// Writes the samples of the metric to `out`, one per line.
+    ///
+    /// `name` is the name of the metric family and `labels` the labels that apply to all the
+    /// samples of the metric.
+    fn encode(&self, name: &str, labels: &[(&str, &str)], out: &mut String);
+}
+
+/// Encodes all the metrics of the registry in the OpenMetrics text format.
+pub fn encode(registry: &Registry) -> String {
+    let mut out = String::new();
+    for (name, help, metric) in registry.iter() {
+        let _ = writeln!(out, "# HELP {} {}", name, escape_help(help));
+        let _ = writeln!(out, "# TYPE {} {}", name, metric.metric_type().as_str());
+        metric.encode(name, &[], &mut out);
+    }
+    out.push_str("# EOF\n");
+    out
+}
+
+/// Writes a single sample, i.e. `name{labels} value`, to `out`.
+pub(crate) fn encode_sample(
+    out: &mut String,
+    name: &str,
+    suffix: &str,
+    labels: &[(&str, &str)],
+    extra_label: Option<(&str, &str)>,
+    value: impl std::fmt::Display
+) {
+    out.push_str(name);
+    out.push_str(suffix);
+    let mut labels = labels.iter().cloned().chain(extra_label).peekable();
+    if labels.peek().is_some() {
+        out.push('{');
+        for (i, (name, value)) in labels.enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            let _ = write!(out, "{}=\"{}\"", name, escape_label_value(value));
+        }
+        out.push('}');
+    }
+    let _ = writeln!(out, " {}", value);
+}
+
+fn escape_help(help: &str) -> String {
+    help.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+fn escape_label_value(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metric::{Counter, Family, Gauge, Histogram};
+
+    #[test]
+    fn encode_counter_and_gauge() {
+        let mut registry = Registry::new();
+        let counter = Counter::default();
+        let gauge = Gauge::default();
+        registry.register("requests", "Number of requests.", counter.clone());
+        registry.register("peers", "Number of \"peers\".\nConnected ones.", gauge.clone());
+        counter.inc_by(3);
+        gauge.set(-2);
+
+        assert_eq!(encode(&registry),
+            "# HELP requests Number of requests.\n\
+             # TYPE requests counter\n\
+             requests_total 3\n\
+             # HELP peers Number of \"peers\".\\nConnected ones.\n\
+             # TYPE peers gauge\n\
+             peers -2\n\
+             # EOF\n");
+    }
+
+    #[test]
+    fn encode_family() {
+        let mut registry = Registry::new();
+        let family = Family::<Counter>::new(&["protocol", "direction"]);
+        registry.register("substreams", "Substreams.", family.clone());
+        family.get(&["/b", "in"]).inc();
+        family.get(&["/a\"quoted\"", "out"]).inc_by(2);
+        family.get(&["/b", "in"]).inc();
+
+        assert_eq!(encode(&registry),
+            "# HELP substreams Substreams.\n\
+             # TYPE substreams counter\n\
+             substreams_total{protocol=\"/a\\\"quoted\\\"\",direction=\"out\"} 2\n\
+             substreams_total{protocol=\"/b\",direction=\"in\"} 2\n\
+             # EOF\n");
+    }
+
+    #[test]
+    fn encode_histogram() {
+        let mut registry = Registry::new();
+        let histogram = Histogram::new(vec![0.5, 1.0]);
+        registry.register("rtt_seconds", "Round-trip times.", histogram.clone());
+        histogram.observe(0.25);
+        histogram.observe(0.75);
+        histogram.observe(2.0);
+
+        assert_eq!(encode(&registry),
+            "# HELP rtt_seconds Round-trip times.\n\
+             # TYPE rtt_seconds histogram\n\
+             rtt_seconds_bucket{le=\"0.5\"} 1\n\
+             rtt_seconds_bucket{le=\"1\"} 2\n\
+             rtt_seconds_bucket{le=\"+Inf\"} 3\n\
+             rtt_seconds_sum 3\n\
+             rtt_seconds_count 3\n\
+             # EOF\n");
+    }
+}
@@ -0,0 +1,50 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use crate::{Recorder, Registry, metric::{Counter, Family}};
+use libp2p_floodsub::FloodsubEvent;
+
+/// Metrics of the floodsub protocol.
+pub(crate) struct Metrics {
+    messages: Family<Counter>,
+}
+
+impl Metrics {
+    pub(crate) fn new(registry: &mut Registry) -> Self {
+        let messages = Family::new(&["direction"]);
+        registry.register("libp2p_floodsub_messages",
+            "Number of floodsub messages received (inbound) and published (outbound)",
+            messages.clone());
+
+        Metrics { messages }
+    }
+
+    pub(crate) fn record_published(&self) {
+        self.messages.get(&["outbound"]).inc();
+    }
+}
+
+impl Recorder<FloodsubEvent> for Metrics {
+    fn record(&self, event: &FloodsubEvent) {
+        if let FloodsubEvent::Message(_) = event {
+            self.messages.get(&["inbound"]).inc();
+        }
+    }
+}
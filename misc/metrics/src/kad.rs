@@ -0,0 +1,54 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use crate::{Recorder, Registry, metric::{Counter, Family, Histogram, exponential_buckets}};
+use libp2p_kad::QueryStats;
+
+/// Metrics of the Kademlia queries.
+pub(crate) struct Metrics {
+    queries: Family<Counter>,
+    query_duration: Family<Histogram>,
+}
+
+impl Metrics {
+    pub(crate) fn new(registry: &mut Registry) -> Self {
+        let queries = Family::new(&["kind", "outcome"]);
+        registry.register("libp2p_kad_queries",
+            "Number of finished Kademlia queries, by kind and outcome",
+            queries.clone());
+
+        let query_duration = Family::new_with_constructor(&["kind"],
+            || Histogram::new(exponential_buckets(0.1, 2.0, 10)));
+        registry.register("libp2p_kad_query_duration_seconds",
+            "Duration of the Kademlia queries, by kind",
+            query_duration.clone());
+
+        Metrics { queries, query_duration }
+    }
+}
+
+impl Recorder<QueryStats> for Metrics {
+    fn record(&self, stats: &QueryStats) {
+        let kind = stats.kind.as_str();
+        let outcome = if stats.success { "success" } else { "failure" };
+        self.queries.get(&[kind, outcome]).inc();
+        self.query_duration.get(&[kind]).observe(stats.duration.as_secs_f64());
+    }
+}
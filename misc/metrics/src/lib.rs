@@ -0,0 +1,145 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Metrics for libp2p, exposed in the OpenMetrics text format understood by Prometheus.
+//!
+//! The [`Metrics`] collect:
+//!
+//! - the number of connections established and closed, and of open connections;
+//! - the number of failed dials and incoming connections, by kind of error;
+//! - the number of substreams and of bytes exchanged on them, by negotiated protocol;
+//! - the duration and outcome of the Kademlia queries;
+//! - the round-trip times of pings and the number of failed pings;
+//! - the number of floodsub messages received and published.
+//!
+//! The metrics of the swarm itself are collected by passing the [`Metrics`] to
+//! `SwarmBuilder::observer`. The metrics of the protocols are collected from the events the
+//! network behaviours already produce, by passing these events to [`Recorder::record`].
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! let mut registry = Registry::new();
+//! let metrics = Arc::new(Metrics::new(&mut registry));
+//! let mut swarm = SwarmBuilder::new(transport, behaviour, local_peer_id)
+//!     .observer(metrics.clone())
+//!     .build();
+//!
+//! // In `NetworkBehaviourEventProcess<PingEvent>::inject_event`:
+//! metrics.record(&event);
+//!
+//! // When scraped:
+//! let body = libp2p_metrics::encode(&registry);
+//! ```
+
+mod encoding;
+mod floodsub;
+mod kad;
+mod ping;
+mod registry;
+mod swarm;
+
+pub mod metric;
+
+pub use self::encoding::{EncodeMetric, MetricType, encode};
+pub use self::registry::Registry;
+
+use libp2p_core::{ConnectedPoint, Endpoint, Multiaddr, PeerId, muxing::SubstreamMeter};
+use libp2p_swarm::{ConnectionErrorKind, SwarmObserver};
+use std::{fmt, sync::Arc};
+
+/// Records the metrics related to an event.
+pub trait Recorder<TEvent> {
+    /// Records the metrics related to the given event.
+    fn record(&self, event: &TEvent);
+}
+
+/// The metrics of the swarm and of the protocols shipped with libp2p.
+pub struct Metrics {
+    swarm: swarm::Metrics,
+    kad: kad::Metrics,
+    ping: ping::Metrics,
+    floodsub: floodsub::Metrics,
+}
+
+impl Metrics {
+    /// Creates the metrics and registers them in the given registry.
+    pub fn new(registry: &mut Registry) -> Self {
+        Metrics {
+            swarm: swarm::Metrics::new(registry),
+            kad: kad::Metrics::new(registry),
+            ping: ping::Metrics::new(registry),
+            floodsub: floodsub::Metrics::new(registry),
+        }
+    }
+
+    /// Records that a message has been published with `Floodsub::publish` or one of its
+    /// variants.
+    pub fn record_floodsub_published(&self) {
+        self.floodsub.record_published()
+    }
+}
+
+impl fmt::Debug for Metrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Metrics").finish()
+    }
+}
+
+impl SwarmObserver for Metrics {
+    fn connection_established(&self, peer_id: &PeerId, endpoint: &ConnectedPoint) {
+        self.swarm.connection_established(peer_id, endpoint)
+    }
+
+    fn connection_closed(&self, peer_id: &PeerId, endpoint: &ConnectedPoint) {
+        self.swarm.connection_closed(peer_id, endpoint)
+    }
+
+    fn dial_failure(&self, peer_id: Option<&PeerId>, address: &Multiaddr, kind: ConnectionErrorKind) {
+        self.swarm.dial_failure(peer_id, address, kind)
+    }
+
+    fn incoming_connection_error(&self, send_back_addr: &Multiaddr, kind: ConnectionErrorKind) {
+        self.swarm.incoming_connection_error(send_back_addr, kind)
+    }
+
+    fn substream_meter(&self, peer_id: &PeerId, endpoint: Endpoint) -> Option<Arc<dyn SubstreamMeter>> {
+        self.swarm.substream_meter(peer_id, endpoint)
+    }
+}
+
+/// Records the duration and outcome of a query taken with `Kademlia::take_query_stats`.
+impl Recorder<libp2p_kad::QueryStats> for Metrics {
+    fn record(&self, stats: &libp2p_kad::QueryStats) {
+        self.kad.record(stats)
+    }
+}
+
+impl Recorder<libp2p_ping::PingEvent> for Metrics {
+    fn record(&self, event: &libp2p_ping::PingEvent) {
+        self.ping.record(event)
+    }
+}
+
+impl Recorder<libp2p_floodsub::FloodsubEvent> for Metrics {
+    fn record(&self, event: &libp2p_floodsub::FloodsubEvent) {
+        self.floodsub.record(event)
+    }
+}
@@ -0,0 +1,264 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! The types of metrics.
+//!
+//! All the metrics are cheap to clone handles to a shared value, so that the same metric can be
+//! registered in a [`Registry`](crate::Registry) and updated from anywhere.
+
+use crate::encoding::{EncodeMetric, MetricType, encode_sample};
+use parking_lot::{Mutex, RwLock};
+use std::{collections::BTreeMap, fmt, sync::Arc};
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+
+/// A monotonically increasing value.
+#[derive(Debug, Clone, Default)]
+pub struct Counter {
+    value: Arc<AtomicU64>,
+}
+
+impl Counter {
+    /// Increments the counter by one.
+    pub fn inc(&self) {
+        self.inc_by(1)
+    }
+
+    /// Increments the counter by the given amount.
+    pub fn inc_by(&self, n: u64) {
+        self.value.fetch_add(n, Ordering::Relaxed);
+    }
+
+    /// Returns the current value of the counter.
+    pub fn get(&self) -> u64 {
+        self.value.load(Ordering::Relaxed)
+    }
+}
+
+impl EncodeMetric for Counter {
+    fn metric_type(&self) -> MetricType {
+        MetricType::Counter
+    }
+
+    fn encode(&self, name: &str, labels: &[(&str, &str)], out: &mut String) {
+        encode_sample(out, name, "_total", labels, None, self.get())
+    }
+}
+
+/// A value that can go up and down.
+#[derive(Debug, Clone, Default)]
+pub struct Gauge {
+    value: Arc<AtomicI64>,
+}
+
+impl Gauge {
+    /// Increments the gauge by one.
+    pub fn inc(&self) {
+        self.value.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Decrements the gauge by one.
+    pub fn dec(&self) {
+        self.value.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    /// Sets the value of the gauge.
+    pub fn set(&self, value: i64) {
+        self.value.store(value, Ordering::Relaxed)
+    }
+
+    /// Returns the current value of the gauge.
+    pub fn get(&self) -> i64 {
+        self.value.load(Ordering::Relaxed)
+    }
+}
+
+impl EncodeMetric for Gauge {
+    fn metric_type(&self) -> MetricType {
+        MetricType::Gauge
+    }
+
+    fn encode(&self, name: &str, labels: &[(&str, &str)], out: &mut String) {
+        encode_sample(out, name, "", labels, None, self.get())
+    }
+}
+
+/// Distribution of observed values into buckets.
+#[derive(Debug, Clone)]
+pub struct Histogram {
+    inner: Arc<Mutex<HistogramInner>>,
+}
+
+#[derive(Debug)]
+struct HistogramInner {
+    /// The upper bounds of the buckets, in increasing order.
+    bounds: Vec<f64>,
+    /// The number of observations of each bucket, not cumulated. The last element
+    /// is the `+Inf` bucket.
+    counts: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    /// Creates a histogram with buckets of the given upper bounds.
+    ///
+    /// A bucket for all the values greater than the largest bound is implied.
+    pub fn new(bounds: impl IntoIterator<Item = f64>) -> Self {
+        let mut bounds = bounds.into_iter().filter(|b| !b.is_nan()).collect::<Vec<_>>();
+        bounds.sort_by(|a, b| a.partial_cmp(b).expect("NaN bounds are filtered out; QED"));
+        bounds.dedup();
+        let counts = vec![0; bounds.len() + 1];
+        Histogram {
+            inner: Arc::new(Mutex::new(HistogramInner { bounds, counts, sum: 0.0, count: 0 }))
+        }
+    }
+
+    /// Records a value.
+    pub fn observe(&self, value: f64) {
+        let mut inner = self.inner.lock();
+        let bucket = inner.bounds.iter().position(|b| value <= *b).unwrap_or(inner.bounds.len());
+        inner.counts[bucket] += 1;
+        inner.sum += value;
+        inner.count += 1;
+    }
+
+    /// Returns the number of recorded values.
+    pub fn count(&self) -> u64 {
+        self.inner.lock().count
+    }
+
+    /// Returns the sum of the recorded values.
+    pub fn sum(&self) -> f64 {
+        self.inner.lock().sum
+    }
+}
+
+impl EncodeMetric for Histogram {
+    fn metric_type(&self) -> MetricType {
+        MetricType::Histogram
+    }
+
+    fn encode(&self, name: &str, labels: &[(&str, &str)], out: &mut String) {
+        let inner = self.inner.lock();
+        let mut cumulated = 0;
+        for (bound, count) in inner.bounds.iter().zip(inner.counts.iter()) {
+            cumulated += count;
+            let bound = bound.to_string();
+            encode_sample(out, name, "_bucket", labels, Some(("le", &bound)), cumulated);
+        }
+        encode_sample(out, name, "_bucket", labels, Some(("le", "+Inf")), inner.count);
+        encode_sample(out, name, "_sum", labels, None, inner.sum);
+        encode_sample(out, name, "_count", labels, None, inner.count);
+    }
+}
+
+/// Returns `count` bucket bounds, starting at `start` and each one `factor` times the previous
+/// one.
+pub fn exponential_buckets(start: f64, factor: f64, count: usize) -> impl Iterator<Item = f64> {
+    (0 .. count).scan(start, move |bound, _| {
+        let current = *bound;
+        *bound *= factor;
+        Some(current)
+    })
+}
+
+/// A set of metrics of the same kind, distinguished by the values of their labels.
+///
+/// The metric for a given set of label values is created the first time it is requested.
+pub struct Family<M> {
+    label_names: Arc<[&'static str]>,
+    metrics: Arc<RwLock<BTreeMap<Vec<String>, M>>>,
+    constructor: Arc<dyn Fn() -> M + Send + Sync>,
+}
+
+impl<M: Default + 'static> Family<M> {
+    /// Creates a family of metrics with the given label names.
+    pub fn new(label_names: &[&'static str]) -> Self {
+        Family::new_with_constructor(label_names, M::default)
+    }
+}
+
+impl<M> Family<M> {
+    /// Creates a family of metrics with the given label names, using `constructor` to create the
+    /// metric of each new set of label values.
+    pub fn new_with_constructor<F>(label_names: &[&'static str], constructor: F) -> Self
+    where
+        F: Fn() -> M + Send + Sync + 'static
+    {
+        Family {
+            label_names: label_names.into(),
+            metrics: Arc::new(RwLock::new(BTreeMap::new())),
+            constructor: Arc::new(constructor),
+        }
+    }
+}
+
+impl<M: Clone> Family<M> {
+    /// Returns the metric with the given label values, creating it if necessary.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of values is not the number of label names of the family.
+    pub fn get(&self, label_values: &[&str]) -> M {
+        assert_eq!(label_values.len(), self.label_names.len(), "Wrong number of label values");
+        let key = label_values.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+        if let Some(metric) = self.metrics.read().get(&key) {
+            return metric.clone()
+        }
+        self.metrics.write().entry(key).or_insert_with(|| (self.constructor)()).clone()
+    }
+}
+
+impl<M> Clone for Family<M> {
+    fn clone(&self) -> Self {
+        Family {
+            label_names: self.label_names.clone(),
+            metrics: self.metrics.clone(),
+            constructor: self.constructor.clone(),
+        }
+    }
+}
+
+impl<M: fmt::Debug> fmt::Debug for Family<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Family")
+            .field("label_names", &self.label_names)
+            .field("metrics", &*self.metrics.read())
+            .finish()
+    }
+}
+
+impl<M> EncodeMetric for Family<M>
+where
+    M: EncodeMetric + Send + Sync
+{
+    fn metric_type(&self) -> MetricType {
+        (self.constructor)().metric_type()
+    }
+
+    fn encode(&self, name: &str, labels: &[(&str, &str)], out: &mut String) {
+        for (values, metric) in self.metrics.read().iter() {
+            let labels = labels.iter().cloned()
+                .chain(self.label_names.iter().cloned().zip(values.iter().map(|v| v.as_str())))
+                .collect::<Vec<_>>();
+            metric.encode(name, &labels, out);
+        }
+    }
+}
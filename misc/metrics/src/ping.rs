@@ -0,0 +1,55 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use crate::{Recorder, Registry, metric::{Counter, Family, Histogram, exponential_buckets}};
+use libp2p_ping::{PingEvent, PingFailure, PingSuccess};
+
+/// Metrics of the ping protocol.
+pub(crate) struct Metrics {
+    rtt: Histogram,
+    failures: Family<Counter>,
+}
+
+impl Metrics {
+    pub(crate) fn new(registry: &mut Registry) -> Self {
+        let rtt = Histogram::new(exponential_buckets(0.001, 2.0, 12));
+        registry.register("libp2p_ping_rtt_seconds",
+            "Round-trip time of the pings sent to remotes",
+            rtt.clone());
+
+        let failures = Family::new(&["reason"]);
+        registry.register("libp2p_ping_failures",
+            "Number of failed pings, by reason",
+            failures.clone());
+
+        Metrics { rtt, failures }
+    }
+}
+
+impl Recorder<PingEvent> for Metrics {
+    fn record(&self, event: &PingEvent) {
+        match &event.result {
+            Ok(PingSuccess::Ping { rtt }) => self.rtt.observe(rtt.as_secs_f64()),
+            Ok(PingSuccess::Pong) => {}
+            Err(PingFailure::Timeout) => self.failures.get(&["timeout"]).inc(),
+            Err(PingFailure::Other { .. }) => self.failures.get(&["other"]).inc(),
+        }
+    }
+}
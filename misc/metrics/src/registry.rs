@@ -0,0 +1,63 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use crate::encoding::EncodeMetric;
+use std::fmt;
+
+/// Collection of metrics, each registered under a name along with a help text.
+///
+/// The metrics are shared handles: a metric keeps being updated after having been registered,
+/// and its current value is read whenever the registry is encoded with
+/// [`encode`](crate::encode).
+#[derive(Default)]
+pub struct Registry {
+    metrics: Vec<(String, String, Box<dyn EncodeMetric>)>,
+}
+
+impl Registry {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Registry::default()
+    }
+
+    /// Registers a metric.
+    ///
+    /// The name must be unique within the registry and, for a counter, must not end with
+    /// `_total`, which is appended when encoding.
+    pub fn register<M>(&mut self, name: impl Into<String>, help: impl Into<String>, metric: M)
+    where
+        M: EncodeMetric + 'static
+    {
+        let name = name.into();
+        debug_assert!(self.metrics.iter().all(|(n, _, _)| *n != name), "Duplicate metric {}", name);
+        self.metrics.push((name, help.into(), Box::new(metric)));
+    }
+
+    /// Iterates over the registered metrics, in the order in which they have been registered.
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (&str, &str, &dyn EncodeMetric)> {
+        self.metrics.iter().map(|(name, help, metric)| (name.as_str(), help.as_str(), &**metric))
+    }
+}
+
+impl fmt::Debug for Registry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.metrics.iter().map(|(name, _, _)| name)).finish()
+    }
+}
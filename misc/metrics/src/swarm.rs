@@ -0,0 +1,163 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use crate::{Registry, metric::{Counter, Family, Gauge}};
+use libp2p_core::{ConnectedPoint, Endpoint, Multiaddr, PeerId, muxing::SubstreamMeter};
+use libp2p_swarm::{ConnectionErrorKind, MeterState, SwarmObserver};
+use parking_lot::Mutex;
+use std::sync::Arc;
+
+/// Metrics of the `Swarm`, updated through its `SwarmObserver` hooks.
+pub(crate) struct Metrics {
+    connections_established: Family<Counter>,
+    connections_closed: Family<Counter>,
+    connections: Family<Gauge>,
+    dial_failures: Family<Counter>,
+    incoming_connection_errors: Family<Counter>,
+    substreams: Family<Counter>,
+    substream_bytes: Family<Counter>,
+}
+
+impl Metrics {
+    pub(crate) fn new(registry: &mut Registry) -> Self {
+        let connections_established = Family::new(&["role"]);
+        registry.register("libp2p_swarm_connections_established",
+            "Number of connections established, by role of the local node",
+            connections_established.clone());
+
+        let connections_closed = Family::new(&["role"]);
+        registry.register("libp2p_swarm_connections_closed",
+            "Number of connections closed, by role of the local node",
+            connections_closed.clone());
+
+        let connections = Family::new(&["role"]);
+        registry.register("libp2p_swarm_connections",
+            "Number of open connections, by role of the local node",
+            connections.clone());
+
+        let dial_failures = Family::new(&["kind"]);
+        registry.register("libp2p_swarm_dial_failures",
+            "Number of failed attempts to reach an address, by kind of error",
+            dial_failures.clone());
+
+        let incoming_connection_errors = Family::new(&["kind"]);
+        registry.register("libp2p_swarm_incoming_connection_errors",
+            "Number of incoming connections that could not be established, by kind of error",
+            incoming_connection_errors.clone());
+
+        let substreams = Family::new(&["protocol", "direction"]);
+        registry.register("libp2p_swarm_substreams",
+            "Number of substreams whose protocol has been negotiated, by protocol and direction",
+            substreams.clone());
+
+        let substream_bytes = Family::new(&["protocol", "direction"]);
+        registry.register("libp2p_swarm_substream_bytes",
+            "Number of bytes received (inbound) and sent (outbound) on substreams, by protocol",
+            substream_bytes.clone());
+
+        Metrics {
+            connections_established,
+            connections_closed,
+            connections,
+            dial_failures,
+            incoming_connection_errors,
+            substreams,
+            substream_bytes,
+        }
+    }
+}
+
+impl SwarmObserver for Metrics {
+    fn connection_established(&self, _: &PeerId, endpoint: &ConnectedPoint) {
+        let role = role(endpoint);
+        self.connections_established.get(&[role]).inc();
+        self.connections.get(&[role]).inc();
+    }
+
+    fn connection_closed(&self, _: &PeerId, endpoint: &ConnectedPoint) {
+        let role = role(endpoint);
+        self.connections_closed.get(&[role]).inc();
+        self.connections.get(&[role]).dec();
+    }
+
+    fn dial_failure(&self, _: Option<&PeerId>, _: &Multiaddr, kind: ConnectionErrorKind) {
+        self.dial_failures.get(&[kind.as_str()]).inc();
+    }
+
+    fn incoming_connection_error(&self, _: &Multiaddr, kind: ConnectionErrorKind) {
+        self.incoming_connection_errors.get(&[kind.as_str()]).inc();
+    }
+
+    fn substream_meter(&self, _: &PeerId, endpoint: Endpoint) -> Option<Arc<dyn SubstreamMeter>> {
+        Some(Arc::new(ProtocolMeter {
+            direction: match endpoint {
+                Endpoint::Dialer => "outbound",
+                Endpoint::Listener => "inbound",
+            },
+            substreams: self.substreams.clone(),
+            substream_bytes: self.substream_bytes.clone(),
+            state: Mutex::new(MeterState::new()),
+        }))
+    }
+}
+
+fn role(endpoint: &ConnectedPoint) -> &'static str {
+    match endpoint {
+        ConnectedPoint::Dialer { .. } => "dialer",
+        ConnectedPoint::Listener { .. } => "listener",
+    }
+}
+
+/// Meter of a single substream.
+///
+/// The traffic is attributed to the protocol of the substream once it has been negotiated.
+/// The traffic of a substream whose negotiation fails is not accounted for.
+struct ProtocolMeter {
+    /// Whether the substream has been opened by the local node or by the remote.
+    direction: &'static str,
+    substreams: Family<Counter>,
+    substream_bytes: Family<Counter>,
+    /// The counters of the bytes received and sent once the protocol is negotiated.
+    state: Mutex<MeterState<(Counter, Counter)>>,
+}
+
+impl SubstreamMeter for ProtocolMeter {
+    fn on_negotiated(&self, protocol: &[u8]) {
+        let protocol = String::from_utf8_lossy(protocol);
+        self.substreams.get(&[&protocol, self.direction]).inc();
+        let read = self.substream_bytes.get(&[&protocol, "inbound"]);
+        let written = self.substream_bytes.get(&[&protocol, "outbound"]);
+        let (r, w) = self.state.lock().negotiated((read.clone(), written.clone()));
+        read.inc_by(r);
+        written.inc_by(w);
+    }
+
+    fn on_read(&self, bytes: usize) {
+        if let Some((read, _)) = self.state.lock().on_read(bytes) {
+            read.inc_by(bytes as u64);
+        }
+    }
+
+    fn on_write(&self, bytes: usize) {
+        if let Some((_, written)) = self.state.lock().on_write(bytes) {
+            written.inc_by(bytes as u64);
+        }
+    }
+}
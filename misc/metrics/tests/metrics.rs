@@ -0,0 +1,140 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use futures::{future, prelude::*};
+use libp2p_core::{
+    Multiaddr,
+    PeerId,
+    identity,
+    muxing::StreamMuxer,
+    upgrade::{self, OutboundUpgradeExt, InboundUpgradeExt},
+    transport::Transport
+};
+use libp2p_metrics::{Metrics, Recorder, Registry, encode};
+use libp2p_ping::{Ping, PingConfig, PingEvent, PingSuccess};
+use libp2p_secio::SecioConfig;
+use libp2p_swarm::{Swarm, SwarmBuilder};
+use libp2p_tcp::TcpConfig;
+use libp2p_yamux as yamux;
+use std::{fmt, io, sync::{Arc, mpsc::sync_channel}};
+use tokio::runtime::Runtime;
+
+#[test]
+fn swarm_and_ping_metrics() {
+    let cfg = PingConfig::new().with_keep_alive(true);
+
+    let mut registry1 = Registry::new();
+    let metrics1 = Arc::new(Metrics::new(&mut registry1));
+    let (peer1_id, trans) = mk_transport();
+    let mut swarm1 = SwarmBuilder::new(trans, Ping::new(cfg.clone()), peer1_id)
+        .observer(metrics1.clone())
+        .build();
+
+    let mut registry2 = Registry::new();
+    let metrics2 = Arc::new(Metrics::new(&mut registry2));
+    let (peer2_id, trans) = mk_transport();
+    let mut swarm2 = SwarmBuilder::new(trans, Ping::new(cfg), peer2_id)
+        .observer(metrics2.clone())
+        .build();
+
+    let (tx, rx) = sync_channel::<Multiaddr>(1);
+
+    let addr = "/ip4/127.0.0.1/tcp/0".parse().unwrap();
+    let mut listening = false;
+    Swarm::listen_on(&mut swarm1, addr).unwrap();
+    let peer1 = future::poll_fn(move || -> Result<_, ()> {
+        loop {
+            match swarm1.poll().expect("Error while polling swarm") {
+                Async::Ready(Some(event)) => metrics1.record(&event),
+                _ => {
+                    if !listening {
+                        for l in Swarm::listeners(&swarm1) {
+                            tx.send(l.clone()).unwrap();
+                            listening = true;
+                        }
+                    }
+                    return Ok(Async::NotReady)
+                }
+            }
+        }
+    });
+
+    let mut dialing = false;
+    let peer2 = future::poll_fn(move || -> Result<_, ()> {
+        loop {
+            match swarm2.poll().expect("Error while polling swarm") {
+                Async::Ready(Some(event)) => {
+                    metrics2.record(&event);
+                    if let PingEvent { result: Ok(PingSuccess::Ping { .. }), .. } = event {
+                        return Ok(Async::Ready(()))
+                    }
+                }
+                _ => {
+                    if !dialing {
+                        Swarm::dial_addr(&mut swarm2, rx.recv().unwrap()).unwrap();
+                        dialing = true;
+                    }
+                    return Ok(Async::NotReady)
+                }
+            }
+        }
+    });
+
+    let result = peer1.select(peer2).map(|_| ()).map_err(|_| panic!());
+    Runtime::new().unwrap().block_on(result).unwrap();
+
+    let encoded = encode(&registry2);
+    assert!(encoded.contains("libp2p_swarm_connections_established_total{role=\"dialer\"} 1\n"));
+    assert!(encoded.contains("libp2p_swarm_connections{role=\"dialer\"} 1\n"));
+    assert!(encoded.contains("libp2p_swarm_substreams_total{protocol=\"/ipfs/ping/1.0.0\",direction=\"outbound\"} 1\n"));
+    assert!(encoded.contains("libp2p_ping_rtt_seconds_count 1\n"));
+    assert!(encoded.ends_with("# EOF\n"));
+
+    // A ping is 32 bytes, plus the negotiation of the protocol.
+    let sent = encoded.lines()
+        .find(|l| l.starts_with("libp2p_swarm_substream_bytes_total{protocol=\"/ipfs/ping/1.0.0\",direction=\"outbound\"}"))
+        .and_then(|l| l.rsplit(' ').next())
+        .and_then(|n| n.parse::<u64>().ok())
+        .expect("bytes sent by ping are accounted for");
+    assert!(sent > 32);
+}
+
+fn mk_transport() -> (PeerId, impl Transport<
+    Output = (PeerId, impl StreamMuxer<Substream = impl Send, OutboundSubstream = impl Send, Error = impl Into<io::Error>>),
+    Listener = impl Send,
+    ListenerUpgrade = impl Send,
+    Dial = impl Send,
+    Error = impl fmt::Debug
+> + Clone) {
+    let id_keys = identity::Keypair::generate_ed25519();
+    let peer_id = id_keys.public().into_peer_id();
+    let transport = TcpConfig::new()
+        .nodelay(true)
+        .with_upgrade(SecioConfig::new(id_keys))
+        .and_then(move |out, endpoint| {
+            let peer_id = out.remote_key.into_peer_id();
+            let peer_id2 = peer_id.clone();
+            let upgrade = yamux::Config::default()
+                .map_outbound(move |muxer| (peer_id, muxer))
+                .map_inbound(move |muxer| (peer_id2, muxer));
+            upgrade::apply(out.stream, upgrade, endpoint)
+        });
+    (peer_id, transport)
+}
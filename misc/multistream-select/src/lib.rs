@@ -75,7 +75,8 @@ mod tests;
 mod protocol;
 
 use futures::prelude::*;
-use std::io;
+use futures03::{io::{AsyncRead as AsyncRead03, AsyncWrite as AsyncWrite03}, task::Context};
+use std::{io, pin::Pin};
 
 pub use self::dialer_select::{dialer_select_proto, DialerSelectFuture};
 pub use self::error::ProtocolChoiceError;
@@ -127,3 +128,33 @@ where
         self.0.shutdown()
     }
 }
+
+impl<TInner> AsyncRead03 for Negotiated<TInner>
+where
+    TInner: AsyncRead03 + Unpin
+{
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8])
+        -> futures03::task::Poll<io::Result<usize>>
+    {
+        Pin::new(&mut self.0).poll_read(cx, buf)
+    }
+}
+
+impl<TInner> AsyncWrite03 for Negotiated<TInner>
+where
+    TInner: AsyncWrite03 + Unpin
+{
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8])
+        -> futures03::task::Poll<io::Result<usize>>
+    {
+        Pin::new(&mut self.0).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> futures03::task::Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> futures03::task::Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_close(cx)
+    }
+}
@@ -0,0 +1,509 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use crate::{Mapping, PortMapError, igd::{self, Gateway}, natpmp};
+use futures::{future::{self, Either}, prelude::*};
+use libp2p_core::{ConnectedPoint, Multiaddr, PeerId, clock, multiaddr::Protocol, nodes::ConnectionId};
+use libp2p_swarm::{
+    NetworkBehaviourAction,
+    PollParameters,
+    ProtocolsHandler,
+    compat::NetworkBehaviour01,
+    protocols_handler::DummyProtocolsHandler
+};
+use log::debug;
+use std::{
+    cmp,
+    collections::VecDeque,
+    marker::PhantomData,
+    net::{Ipv4Addr, SocketAddr, SocketAddrV4},
+    time::Duration
+};
+use tokio_io::{AsyncRead, AsyncWrite};
+use void::Void;
+use wasm_timer::{Delay, Instant, Timeout};
+
+/// Delay before the first retry of a failed renewal. It doubles at each new failure, without
+/// going past the end of the lease.
+const INITIAL_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// Configuration for the port mapping behaviour.
+#[derive(Debug, Clone)]
+pub struct PortMapConfig {
+    ssdp_addr: SocketAddr,
+    discovery_timeout: Duration,
+    request_timeout: Duration,
+    nat_pmp_gateway: Option<SocketAddr>,
+    lease_duration: Duration,
+    description: String,
+}
+
+impl Default for PortMapConfig {
+    fn default() -> Self {
+        PortMapConfig {
+            ssdp_addr: SocketAddr::from(([239, 255, 255, 250], 1900)),
+            discovery_timeout: Duration::from_secs(3),
+            request_timeout: Duration::from_secs(10),
+            nat_pmp_gateway: None,
+            lease_duration: Duration::from_secs(3600),
+            description: "libp2p".to_owned(),
+        }
+    }
+}
+
+impl PortMapConfig {
+    /// Sets the address to which the SSDP search for a UPnP gateway is sent.
+    ///
+    /// The default is the SSDP multicast address, `239.255.255.250:1900`.
+    pub fn set_ssdp_addr(&mut self, addr: SocketAddr) -> &mut Self {
+        self.ssdp_addr = addr;
+        self
+    }
+
+    /// Sets how long we wait for a UPnP gateway to answer the SSDP search, before falling back
+    /// to NAT-PMP.
+    ///
+    /// The default is 3 seconds.
+    pub fn set_discovery_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.discovery_timeout = timeout;
+        self
+    }
+
+    /// Sets how long a mapping request to a UPnP gateway can take, discovery included, before
+    /// falling back to NAT-PMP.
+    ///
+    /// The default is 10 seconds.
+    pub fn set_request_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.request_timeout = timeout;
+        self
+    }
+
+    /// Sets the address of the NAT-PMP gateway.
+    ///
+    /// By default, the gateway is assumed to be the first address of the `/24` network of the
+    /// listen address, for example `192.168.1.1` when listening on `192.168.1.20`, on port 5351.
+    pub fn set_nat_pmp_gateway(&mut self, addr: SocketAddr) -> &mut Self {
+        self.nat_pmp_gateway = Some(addr);
+        self
+    }
+
+    /// Sets the lease duration requested for the mappings. Mappings are renewed when half of
+    /// their lease has elapsed, and failed renewals are retried until the lease ends.
+    ///
+    /// The default is one hour.
+    pub fn set_lease_duration(&mut self, lease: Duration) -> &mut Self {
+        self.lease_duration = lease;
+        self
+    }
+
+    /// Sets the description of the mappings, shown by UPnP gateways in their configuration
+    /// interface.
+    ///
+    /// The default is `libp2p`.
+    pub fn set_description(&mut self, description: impl Into<String>) -> &mut Self {
+        self.description = description.into();
+        self
+    }
+}
+
+/// A `NetworkBehaviour` that asks the gateway of the local network to forward a public port to
+/// each private TCP address we listen on.
+///
+/// UPnP-IGD is tried first, then NAT-PMP. The resulting public addresses are reported to the
+/// swarm as external addresses.
+pub struct PortMap<TSubstream> {
+    /// Configuration of the behaviour.
+    config: PortMapConfig,
+
+    /// UPnP gateway found by a previous search, reused for the next mappings.
+    gateway: Option<Gateway>,
+
+    /// Mappings of our listen addresses, created or in progress.
+    mappings: Vec<PortMapping>,
+
+    /// Requests to remove the mappings of addresses we no longer listen on.
+    removals: Vec<Box<dyn Future<Item = (), Error = PortMapError> + Send>>,
+
+    /// Events that need to be yielded to the outside when polling.
+    events: VecDeque<NetworkBehaviourAction<Void, PortMapEvent>>,
+
+    /// Marker to pin the generic.
+    marker: PhantomData<TSubstream>,
+}
+
+/// Future that creates or renews a mapping.
+type MappingFuture = Box<dyn Future<Item = (Mapping, Method), Error = PortMapError> + Send>;
+
+/// How a mapping has been created.
+#[derive(Debug, Clone)]
+enum Method {
+    Igd(Gateway),
+    NatPmp(SocketAddr),
+}
+
+/// Mapping of a listen address.
+struct PortMapping {
+    /// The address we listen on.
+    listen_addr: Multiaddr,
+    /// The same address, as a socket address.
+    internal: SocketAddrV4,
+    /// The mapping, if it has been created, and how.
+    mapped: Option<(Mapping, Method)>,
+    /// When the lease of the mapping ends, if it has been created.
+    expires: Option<Instant>,
+    /// Number of renewals that failed in a row.
+    failures: u32,
+    /// Request in progress, or delay until the next renewal.
+    state: MappingState,
+}
+
+enum MappingState {
+    Requesting(MappingFuture),
+    Renewing(Delay),
+}
+
+impl<TSubstream> PortMap<TSubstream> {
+    /// Builds a new `PortMap` behaviour with the given configuration.
+    pub fn new(config: PortMapConfig) -> Self {
+        PortMap {
+            config,
+            gateway: None,
+            mappings: Vec::new(),
+            removals: Vec::new(),
+            events: VecDeque::new(),
+            marker: PhantomData,
+        }
+    }
+
+    /// Returns the public addresses that are currently mapped to our listen addresses.
+    pub fn external_addresses(&self) -> impl Iterator<Item = Multiaddr> + '_ {
+        self.mappings.iter().filter_map(|mapping| {
+            mapping.mapped.as_ref().map(|(mapping, _)| socket_to_multiaddr(mapping.external))
+        })
+    }
+
+    /// Starts a request for a mapping of `internal`. Renewals use the same method as the
+    /// existing mapping.
+    fn request(&self, internal: SocketAddrV4, previous: Option<&Method>) -> MappingFuture {
+        let lease = self.config.lease_duration;
+        let nat_pmp_gateway = match previous {
+            Some(Method::NatPmp(gateway)) => *gateway,
+            _ => self.config.nat_pmp_gateway.unwrap_or_else(|| guess_gateway(*internal.ip())),
+        };
+        let nat_pmp = future::lazy(move || natpmp::map_tcp(nat_pmp_gateway, internal.port(), lease))
+            .map(move |mapping| (mapping, Method::NatPmp(nat_pmp_gateway)));
+
+        if let Some(Method::NatPmp(_)) = previous {
+            return Box::new(nat_pmp);
+        }
+
+        let gateway = match previous {
+            Some(Method::Igd(gateway)) => Some(gateway.clone()),
+            _ => self.gateway.clone(),
+        };
+        let gateway = match gateway {
+            Some(gateway) => Either::A(future::ok(gateway)),
+            None => Either::B(igd::search_gateway(self.config.ssdp_addr, self.config.discovery_timeout)),
+        };
+        let description = self.config.description.clone();
+        let igd = gateway.and_then(move |gateway| {
+            gateway.map_tcp(internal, lease, &description)
+                .map(move |mapping| (mapping, Method::Igd(gateway)))
+        });
+
+        Box::new(Timeout::new(igd, self.config.request_timeout)
+            .map_err(|err| err.into_inner().unwrap_or(PortMapError::Timeout))
+            .or_else(move |err| {
+                debug!("Port mapping with UPnP failed, trying NAT-PMP: {}", err);
+                nat_pmp
+            }))
+    }
+}
+
+impl<TSubstream> NetworkBehaviour01 for PortMap<TSubstream>
+where
+    TSubstream: AsyncRead + AsyncWrite,
+{
+    type ProtocolsHandler = DummyProtocolsHandler<TSubstream>;
+    type OutEvent = PortMapEvent;
+
+    fn new_handler(&mut self) -> Self::ProtocolsHandler {
+        DummyProtocolsHandler::default()
+    }
+
+    fn addresses_of_peer(&mut self, _: &PeerId) -> Vec<Multiaddr> {
+        Vec::new()
+    }
+
+    fn inject_connected(&mut self, _: PeerId, _: ConnectedPoint) {}
+
+    fn inject_disconnected(&mut self, _: &PeerId, _: ConnectedPoint) {}
+
+    fn inject_node_event(
+        &mut self,
+        _: PeerId,
+        _: ConnectionId,
+        _ev: <Self::ProtocolsHandler as ProtocolsHandler>::OutEvent,
+    ) {
+        void::unreachable(_ev)
+    }
+
+    fn inject_new_listen_addr(&mut self, addr: &Multiaddr) {
+        let internal = match private_tcp_addr(addr) {
+            Some(internal) => internal,
+            None => return,
+        };
+        if self.mappings.iter().any(|mapping| mapping.internal == internal) {
+            return;
+        }
+        self.mappings.push(PortMapping {
+            listen_addr: addr.clone(),
+            internal,
+            mapped: None,
+            expires: None,
+            failures: 0,
+            state: MappingState::Requesting(self.request(internal, None)),
+        });
+    }
+
+    fn inject_expired_listen_addr(&mut self, addr: &Multiaddr) {
+        let pos = match self.mappings.iter().position(|mapping| mapping.listen_addr == *addr) {
+            Some(pos) => pos,
+            None => return,
+        };
+        let mapping = self.mappings.remove(pos);
+        if let Some((mapped, _)) = &mapping.mapped {
+            self.events.push_back(NetworkBehaviourAction::ReportExpiredExternalAddr {
+                address: socket_to_multiaddr(mapped.external),
+            });
+        }
+        match mapping.mapped {
+            Some((mapped, Method::Igd(gateway))) =>
+                self.removals.push(Box::new(gateway.remove_port(mapped.external.port()))),
+            Some((_, Method::NatPmp(gateway))) =>
+                self.removals.push(Box::new(natpmp::unmap_tcp(gateway, mapping.internal.port()))),
+            None => {}
+        }
+    }
+
+    fn poll(
+        &mut self,
+        _: &mut impl PollParameters,
+    ) -> Async<
+        NetworkBehaviourAction<
+            <Self::ProtocolsHandler as ProtocolsHandler>::InEvent,
+            Self::OutEvent,
+        >,
+    > {
+        // Drive the removals. Errors are ignored, as the mappings expire anyway.
+        let mut n = 0;
+        while n < self.removals.len() {
+            match self.removals[n].poll() {
+                Ok(Async::NotReady) => {
+                    n += 1;
+                    continue;
+                }
+                Ok(Async::Ready(())) => {}
+                Err(err) => debug!("Failed to remove port mapping: {}", err),
+            }
+            drop(self.removals.swap_remove(n));
+        }
+
+        let mut n = 0;
+        while n < self.mappings.len() {
+            let result = match self.mappings[n].state {
+                MappingState::Requesting(ref mut request) => request.poll(),
+                MappingState::Renewing(ref mut delay) => {
+                    match delay.poll() {
+                        Ok(Async::NotReady) => {}
+                        Ok(Async::Ready(())) | Err(_) => {
+                            let mapping = &self.mappings[n];
+                            let previous = mapping.mapped.as_ref().map(|(_, method)| method);
+                            let request = self.request(mapping.internal, previous);
+                            self.mappings[n].state = MappingState::Requesting(request);
+                            // Poll the new request right away.
+                            continue;
+                        }
+                    }
+                    n += 1;
+                    continue;
+                }
+            };
+
+            match result {
+                Ok(Async::NotReady) => n += 1,
+                Ok(Async::Ready((mapped, method))) => {
+                    if let Method::Igd(ref gateway) = method {
+                        self.gateway = Some(gateway.clone());
+                    }
+
+                    // Permanent mappings are checked as often as the other ones, in case the
+                    // gateway forgot them, for example after a reboot.
+                    let lease = if mapped.lease == Duration::from_secs(0) {
+                        self.config.lease_duration
+                    } else {
+                        mapped.lease
+                    };
+                    let now = clock::now();
+                    let mapping = &mut self.mappings[n];
+                    mapping.state = MappingState::Renewing(Delay::new(now + lease / 2));
+                    mapping.expires = Some(now + lease);
+                    mapping.failures = 0;
+
+                    let changed = mapping.mapped.as_ref().map_or(true, |(m, _)| m.external != mapped.external);
+                    if changed {
+                        if let Some((previous, _)) = &mapping.mapped {
+                            self.events.push_back(NetworkBehaviourAction::ReportExpiredExternalAddr {
+                                address: socket_to_multiaddr(previous.external),
+                            });
+                        }
+                        let external_addr = socket_to_multiaddr(mapped.external);
+                        let protocol = match method {
+                            Method::Igd(_) => MappingProtocol::Igd,
+                            Method::NatPmp(_) => MappingProtocol::NatPmp,
+                        };
+                        self.events.push_back(NetworkBehaviourAction::ReportExternalAddr {
+                            address: external_addr.clone(),
+                        });
+                        self.events.push_back(NetworkBehaviourAction::GenerateEvent(PortMapEvent::Mapped {
+                            listen_addr: mapping.listen_addr.clone(),
+                            external_addr,
+                            protocol,
+                        }));
+                    }
+                    mapping.mapped = Some((mapped, method));
+                    n += 1;
+                }
+                Err(error) => {
+                    // A mapping whose renewal failed is kept until its lease ends, and the
+                    // renewal is retried in the meantime.
+                    let now = clock::now();
+                    let mapping = &mut self.mappings[n];
+                    if let Some(expires) = mapping.expires.filter(|expires| now < *expires) {
+                        let backoff = INITIAL_RETRY_DELAY * 2u32.pow(cmp::min(mapping.failures, 16));
+                        let delay = cmp::min(backoff, expires - now);
+                        debug!("Failed to renew the port mapping of {}, retrying in {:?}: {}",
+                            mapping.listen_addr, delay, error);
+                        mapping.failures += 1;
+                        mapping.state = MappingState::Renewing(Delay::new(now + delay));
+                        n += 1;
+                        continue;
+                    }
+
+                    let mapping = self.mappings.remove(n);
+                    let event = match mapping.mapped {
+                        Some((mapped, _)) => {
+                            let external_addr = socket_to_multiaddr(mapped.external);
+                            self.events.push_back(NetworkBehaviourAction::ReportExpiredExternalAddr {
+                                address: external_addr.clone(),
+                            });
+                            PortMapEvent::Expired {
+                                listen_addr: mapping.listen_addr,
+                                external_addr,
+                                error,
+                            }
+                        }
+                        None => PortMapEvent::MappingFailed {
+                            listen_addr: mapping.listen_addr,
+                            error,
+                        },
+                    };
+                    self.events.push_back(NetworkBehaviourAction::GenerateEvent(event));
+                }
+            }
+        }
+
+        if let Some(event) = self.events.pop_front() {
+            return Async::Ready(event);
+        }
+
+        Async::NotReady
+    }
+}
+
+/// Event that can be produced by the `PortMap` behaviour.
+#[derive(Debug)]
+pub enum PortMapEvent {
+    /// A public address is now forwarded to one of our listen addresses. Also produced when the
+    /// public address of an existing mapping changes, in which case the previous one is
+    /// withdrawn from the external addresses of the swarm.
+    Mapped {
+        /// The address we listen on.
+        listen_addr: Multiaddr,
+        /// The public address forwarded to it, which has been reported to the swarm.
+        external_addr: Multiaddr,
+        /// The protocol used to create the mapping.
+        protocol: MappingProtocol,
+    },
+
+    /// Creating the mapping of a listen address failed. The mapping is not retried until we
+    /// listen on the address again.
+    MappingFailed {
+        /// The address we listen on.
+        listen_addr: Multiaddr,
+        /// The error of the last protocol tried.
+        error: PortMapError,
+    },
+
+    /// The lease of a mapping ended before it could be renewed. The public address has been
+    /// withdrawn from the external addresses of the swarm, and the mapping is not retried until
+    /// we listen on the address again.
+    Expired {
+        /// The address we listen on.
+        listen_addr: Multiaddr,
+        /// The public address that was forwarded to it.
+        external_addr: Multiaddr,
+        /// The error of the last attempt to renew the mapping.
+        error: PortMapError,
+    },
+}
+
+/// Protocol used to create a mapping.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MappingProtocol {
+    /// UPnP Internet Gateway Device.
+    Igd,
+    /// NAT-PMP.
+    NatPmp,
+}
+
+/// Returns the socket address of `/ip4/<ip>/tcp/<port>` addresses with a private IP address,
+/// which are the only ones a gateway can forward a port to.
+fn private_tcp_addr(addr: &Multiaddr) -> Option<SocketAddrV4> {
+    let mut iter = addr.iter();
+    match (iter.next(), iter.next(), iter.next()) {
+        (Some(Protocol::Ip4(ip)), Some(Protocol::Tcp(port)), None) if ip.is_private() =>
+            Some(SocketAddrV4::new(ip, port)),
+        _ => None,
+    }
+}
+
+/// Guesses the address of the gateway of a private network, which is usually the first
+/// address of the network.
+fn guess_gateway(ip: Ipv4Addr) -> SocketAddr {
+    let [a, b, c, _] = ip.octets();
+    SocketAddr::from(([a, b, c, 1], natpmp::NAT_PMP_PORT))
+}
+
+fn socket_to_multiaddr(addr: SocketAddrV4) -> Multiaddr {
+    Multiaddr::empty()
+        .with(Protocol::Ip4(*addr.ip()))
+        .with(Protocol::Tcp(addr.port()))
+}
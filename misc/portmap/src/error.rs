@@ -0,0 +1,82 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use std::{error, fmt, io};
+
+/// Error that can happen when asking a gateway for a port mapping.
+#[derive(Debug)]
+pub enum PortMapError {
+    /// Error while communicating with the gateway.
+    Io(io::Error),
+    /// The gateway didn't answer in time.
+    Timeout,
+    /// No UPnP gateway offering the `WANIPConnection` or `WANPPPConnection` service was found.
+    NoGateway,
+    /// The gateway sent an answer we couldn't parse.
+    InvalidResponse,
+    /// The UPnP gateway refused the request.
+    Upnp {
+        /// The UPnP error code, for example 718 if the port is already mapped to another client.
+        code: u16,
+        /// Description of the error sent by the gateway.
+        description: String,
+    },
+    /// The NAT-PMP gateway refused the request with the given result code.
+    NatPmp(u16),
+}
+
+impl From<io::Error> for PortMapError {
+    #[inline]
+    fn from(err: io::Error) -> Self {
+        PortMapError::Io(err)
+    }
+}
+
+impl fmt::Display for PortMapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PortMapError::Io(err) =>
+                write!(f, "I/O error: {}", err),
+            PortMapError::Timeout =>
+                write!(f, "The gateway didn't answer in time"),
+            PortMapError::NoGateway =>
+                write!(f, "No UPnP internet gateway found"),
+            PortMapError::InvalidResponse =>
+                write!(f, "Received an invalid answer from the gateway"),
+            PortMapError::Upnp { code, description } =>
+                write!(f, "UPnP error {}: {}", code, description),
+            PortMapError::NatPmp(code) =>
+                write!(f, "NAT-PMP error {}", code),
+        }
+    }
+}
+
+impl error::Error for PortMapError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            PortMapError::Io(err) => Some(err),
+            PortMapError::Timeout => None,
+            PortMapError::NoGateway => None,
+            PortMapError::InvalidResponse => None,
+            PortMapError::Upnp { .. } => None,
+            PortMapError::NatPmp(_) => None,
+        }
+    }
+}
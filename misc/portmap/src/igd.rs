@@ -0,0 +1,375 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Client side of the Internet Gateway Device protocol of UPnP.
+//!
+//! The gateway is discovered by sending an SSDP search over UDP, to which it answers with the
+//! URL of its device description. The description lists the services of the gateway, and we
+//! then send SOAP requests over HTTP to the control URL of its WAN connection service.
+
+use crate::{Mapping, PortMapError};
+use futures::{future, prelude::*, try_ready};
+use libp2p_core::clock;
+use std::{
+    net::{Ipv4Addr, SocketAddr, SocketAddrV4},
+    str,
+    time::Duration
+};
+use tokio_io::io::{read_to_end, write_all};
+use tokio_tcp::TcpStream;
+use tokio_udp::UdpSocket;
+use url::{Host, Url};
+use wasm_timer::Delay;
+
+/// Type of the devices searched with SSDP.
+const SEARCH_TARGET: &str = "urn:schemas-upnp-org:device:InternetGatewayDevice:1";
+
+/// Prefixes of the types of the services that can create port mappings.
+const WAN_SERVICES: [&str; 2] = [
+    "urn:schemas-upnp-org:service:WANIPConnection:",
+    "urn:schemas-upnp-org:service:WANPPPConnection:",
+];
+
+/// An internet gateway device, and more precisely its WAN connection service.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Gateway {
+    /// Address of the HTTP server of the gateway.
+    addr: SocketAddr,
+    /// Path of the control URL of the service.
+    control_path: String,
+    /// Type of the service.
+    service_type: String,
+}
+
+/// Searches for a gateway by sending an SSDP search to `ssdp_addr`, which is normally the
+/// multicast address `239.255.255.250:1900`, then fetches its description.
+///
+/// Fails with `PortMapError::NoGateway` if no gateway answers within `timeout`.
+pub fn search_gateway(ssdp_addr: SocketAddr, timeout: Duration) -> impl Future<Item = Gateway, Error = PortMapError> {
+    Search {
+        ssdp_addr,
+        socket: None,
+        sent: false,
+        deadline: Delay::new(clock::now() + timeout),
+    }
+    .and_then(|location| {
+        let (addr, path) = split_url(&location)?;
+        let request = format!("GET {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n", path, addr);
+        Ok(http_request(addr, request.into_bytes()).map(move |response| (location, response)))
+    })
+    .flatten()
+    .and_then(|(location, (status, body))| {
+        if status != 200 {
+            return Err(PortMapError::InvalidResponse);
+        }
+        parse_description(&location, &body)
+    })
+}
+
+impl Gateway {
+    /// Asks the gateway to forward `external_port` of its public address to `internal`, for
+    /// the duration of `lease`, then fetches the public address.
+    pub fn map_tcp(&self, internal: SocketAddrV4, lease: Duration, description: &str)
+        -> impl Future<Item = Mapping, Error = PortMapError>
+    {
+        let gateway = self.clone();
+        self.add_port(internal, internal.port(), lease, description)
+            .and_then(move |()| gateway.external_ip())
+            .map(move |ip| Mapping {
+                external: SocketAddrV4::new(ip, internal.port()),
+                lease,
+            })
+    }
+
+    /// Returns the public address of the gateway.
+    pub fn external_ip(&self) -> impl Future<Item = Ipv4Addr, Error = PortMapError> {
+        self.soap_request("GetExternalIPAddress", &[])
+            .and_then(|body| {
+                element(&body, "NewExternalIPAddress")
+                    .and_then(|ip| ip.trim().parse().ok())
+                    .ok_or(PortMapError::InvalidResponse)
+            })
+    }
+
+    /// Asks the gateway to forward TCP `external_port` of its public address to `internal`.
+    /// A `lease` of zero requests a permanent mapping.
+    pub fn add_port(&self, internal: SocketAddrV4, external_port: u16, lease: Duration, description: &str)
+        -> impl Future<Item = (), Error = PortMapError>
+    {
+        let args = [
+            ("NewRemoteHost", String::new()),
+            ("NewExternalPort", external_port.to_string()),
+            ("NewProtocol", "TCP".to_owned()),
+            ("NewInternalPort", internal.port().to_string()),
+            ("NewInternalClient", internal.ip().to_string()),
+            ("NewEnabled", "1".to_owned()),
+            ("NewPortMappingDescription", description.to_owned()),
+            ("NewLeaseDuration", lease.as_secs().to_string()),
+        ];
+        self.soap_request("AddPortMapping", &args).map(|_| ())
+    }
+
+    /// Asks the gateway to remove the mapping of TCP `external_port`.
+    pub fn remove_port(&self, external_port: u16) -> impl Future<Item = (), Error = PortMapError> {
+        let args = [
+            ("NewRemoteHost", String::new()),
+            ("NewExternalPort", external_port.to_string()),
+            ("NewProtocol", "TCP".to_owned()),
+        ];
+        self.soap_request("DeletePortMapping", &args).map(|_| ())
+    }
+
+    /// Sends a SOAP request to the control URL of the service, and returns the body of the
+    /// answer on success.
+    fn soap_request(&self, action: &str, args: &[(&str, String)]) -> impl Future<Item = String, Error = PortMapError> {
+        let mut body = format!(
+            "<?xml version=\"1.0\"?>\r\n\
+             <s:Envelope xmlns:s=\"http://schemas.xmlsoap.org/soap/envelope/\" \
+             s:encodingStyle=\"http://schemas.xmlsoap.org/soap/encoding/\">\
+             <s:Body><u:{} xmlns:u=\"{}\">",
+            action, self.service_type
+        );
+        for (name, value) in args {
+            body.push_str(&format!("<{}>{}</{}>", name, escape(value), name));
+        }
+        body.push_str(&format!("</u:{}></s:Body></s:Envelope>\r\n", action));
+
+        let request = format!(
+            "POST {} HTTP/1.1\r\n\
+             Host: {}\r\n\
+             Content-Type: text/xml; charset=\"utf-8\"\r\n\
+             SOAPAction: \"{}#{}\"\r\n\
+             Content-Length: {}\r\n\
+             Connection: close\r\n\r\n{}",
+            self.control_path, self.addr, self.service_type, action, body.len(), body
+        );
+
+        http_request(self.addr, request.into_bytes()).and_then(|(status, body)| {
+            if status == 200 {
+                return Ok(body);
+            }
+            match element(&body, "errorCode").and_then(|code| code.trim().parse().ok()) {
+                Some(code) => Err(PortMapError::Upnp {
+                    code,
+                    description: element(&body, "errorDescription").unwrap_or_default().to_owned(),
+                }),
+                None => Err(PortMapError::InvalidResponse),
+            }
+        })
+    }
+}
+
+/// Future that sends an SSDP search and yields the location of the description of the first
+/// gateway that answers.
+struct Search {
+    ssdp_addr: SocketAddr,
+    socket: Option<UdpSocket>,
+    /// Whether the search has been sent.
+    sent: bool,
+    /// When to give up.
+    deadline: Delay,
+}
+
+impl Future for Search {
+    type Item = Url;
+    type Error = PortMapError;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        if self.socket.is_none() {
+            let local_addr = match self.ssdp_addr {
+                SocketAddr::V4(_) => SocketAddr::from(([0, 0, 0, 0], 0)),
+                SocketAddr::V6(_) => SocketAddr::from(([0; 16], 0)),
+            };
+            self.socket = Some(UdpSocket::bind(&local_addr)?);
+        }
+        let socket = self.socket.as_mut().expect("the socket has just been bound; qed");
+
+        if !self.sent {
+            let search = format!(
+                "M-SEARCH * HTTP/1.1\r\n\
+                 HOST: {}\r\n\
+                 ST: {}\r\n\
+                 MAN: \"ssdp:discover\"\r\n\
+                 MX: 2\r\n\r\n",
+                self.ssdp_addr, SEARCH_TARGET
+            );
+            try_ready!(socket.poll_send_to(search.as_bytes(), &self.ssdp_addr));
+            self.sent = true;
+        }
+
+        let mut buf = [0; 2048];
+        while let Async::Ready((len, _)) = socket.poll_recv_from(&mut buf)? {
+            // Devices that are not gateways may answer as well, and are ignored.
+            if let Some(location) = parse_search_response(&buf[..len]) {
+                return Ok(Async::Ready(location));
+            }
+        }
+
+        match self.deadline.poll() {
+            Ok(Async::NotReady) => Ok(Async::NotReady),
+            Ok(Async::Ready(())) => Err(PortMapError::NoGateway),
+            Err(err) => Err(PortMapError::Io(std::io::Error::new(std::io::ErrorKind::Other, err))),
+        }
+    }
+}
+
+/// Parses the answer to an SSDP search and returns the location of the device description.
+fn parse_search_response(response: &[u8]) -> Option<Url> {
+    let mut headers = [httparse::EMPTY_HEADER; 32];
+    let mut parsed = httparse::Response::new(&mut headers);
+    match parsed.parse(response) {
+        Ok(httparse::Status::Complete(_)) if parsed.code == Some(200) => {}
+        _ => return None,
+    }
+    let location = parsed.headers.iter().find(|h| h.name.eq_ignore_ascii_case("location"))?;
+    Url::parse(str::from_utf8(location.value).ok()?.trim()).ok()
+}
+
+/// Finds the WAN connection service in the description of a gateway.
+fn parse_description(location: &Url, description: &str) -> Result<Gateway, PortMapError> {
+    // The URLs of the description are relative to `URLBase` if it is present, or to the
+    // location of the description otherwise.
+    let base = element(description, "URLBase")
+        .and_then(|base| Url::parse(base.trim()).ok())
+        .unwrap_or_else(|| location.clone());
+
+    for service in elements(description, "service") {
+        let service_type = match element(service, "serviceType") {
+            Some(ty) => ty.trim(),
+            None => continue,
+        };
+        if !WAN_SERVICES.iter().any(|prefix| service_type.starts_with(prefix)) {
+            continue;
+        }
+        let control_url = element(service, "controlURL")
+            .and_then(|url| base.join(url.trim()).ok())
+            .ok_or(PortMapError::InvalidResponse)?;
+        let (addr, control_path) = split_url(&control_url)?;
+        return Ok(Gateway {
+            addr,
+            control_path,
+            service_type: service_type.to_owned(),
+        });
+    }
+
+    Err(PortMapError::NoGateway)
+}
+
+/// Splits an HTTP URL into the address of the server and the path. Gateways are on the local
+/// network, so we only support URLs with an IP address.
+fn split_url(url: &Url) -> Result<(SocketAddr, String), PortMapError> {
+    let port = url.port_or_known_default().ok_or(PortMapError::InvalidResponse)?;
+    let addr = match url.host() {
+        Some(Host::Ipv4(ip)) => SocketAddr::from((ip, port)),
+        Some(Host::Ipv6(ip)) => SocketAddr::from((ip, port)),
+        _ => return Err(PortMapError::InvalidResponse),
+    };
+    let path = match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => url.path().to_owned(),
+    };
+    Ok((addr, path))
+}
+
+/// Sends an HTTP request, and returns the status code and the body of the response. The
+/// request must ask the server to close the connection after the response.
+fn http_request(addr: SocketAddr, request: Vec<u8>) -> impl Future<Item = (u16, String), Error = PortMapError> {
+    future::lazy(move || TcpStream::connect(&addr))
+        .and_then(move |socket| write_all(socket, request))
+        .and_then(|(socket, _)| read_to_end(socket, Vec::new()))
+        .map_err(PortMapError::Io)
+        .and_then(|(_, response)| {
+            let mut headers = [httparse::EMPTY_HEADER; 32];
+            let mut parsed = httparse::Response::new(&mut headers);
+            match parsed.parse(&response) {
+                Ok(httparse::Status::Complete(len)) => {
+                    let status = parsed.code.ok_or(PortMapError::InvalidResponse)?;
+                    Ok((status, String::from_utf8_lossy(&response[len..]).into_owned()))
+                }
+                _ => Err(PortMapError::InvalidResponse),
+            }
+        })
+}
+
+/// Returns the content of the first element named `tag`.
+fn element<'a>(xml: &'a str, tag: &str) -> Option<&'a str> {
+    elements(xml, tag).next()
+}
+
+/// Returns the contents of the elements named `tag`. This is not a real XML parser, but is
+/// enough for the documents sent by gateways.
+fn elements<'a>(xml: &'a str, tag: &str) -> impl Iterator<Item = &'a str> {
+    let open = format!("<{}>", tag);
+    let close = format!("</{}>", tag);
+    let mut rest = xml;
+    std::iter::from_fn(move || {
+        let start = rest.find(&open)? + open.len();
+        let len = rest[start..].find(&close)?;
+        let content = &rest[start..start + len];
+        rest = &rest[start + len + close.len()..];
+        Some(content)
+    })
+}
+
+/// Escapes a string to be used as the content of an XML element.
+fn escape(value: &str) -> String {
+    value.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DESCRIPTION: &str = "<?xml version=\"1.0\"?>
+<root xmlns=\"urn:schemas-upnp-org:device-1-0\">
+<device><deviceType>urn:schemas-upnp-org:device:InternetGatewayDevice:1</deviceType>
+<serviceList><service>
+<serviceType>urn:schemas-upnp-org:service:Layer3Forwarding:1</serviceType>
+<controlURL>/ctl/L3F</controlURL>
+</service></serviceList>
+<deviceList><device><deviceList><device><serviceList><service>
+<serviceType>urn:schemas-upnp-org:service:WANIPConnection:1</serviceType>
+<controlURL>/ctl/IPConn</controlURL>
+</service></serviceList></device></deviceList></device></deviceList>
+</device></root>";
+
+    #[test]
+    fn find_wan_service() {
+        let location = Url::parse("http://192.168.1.1:5000/rootDesc.xml").unwrap();
+        let gateway = parse_description(&location, DESCRIPTION).unwrap();
+        assert_eq!(gateway, Gateway {
+            addr: "192.168.1.1:5000".parse().unwrap(),
+            control_path: "/ctl/IPConn".to_owned(),
+            service_type: "urn:schemas-upnp-org:service:WANIPConnection:1".to_owned(),
+        });
+    }
+
+    #[test]
+    fn parse_search() {
+        let response = b"HTTP/1.1 200 OK\r\n\
+            CACHE-CONTROL: max-age=120\r\n\
+            ST: urn:schemas-upnp-org:device:InternetGatewayDevice:1\r\n\
+            Location: http://192.168.1.1:5000/rootDesc.xml\r\n\r\n";
+        let location = parse_search_response(response).unwrap();
+        assert_eq!(location.as_str(), "http://192.168.1.1:5000/rootDesc.xml");
+        assert!(parse_search_response(b"NOTIFY * HTTP/1.1\r\n\r\n").is_none());
+    }
+}
@@ -0,0 +1,53 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Port mapping on home routers, with UPnP-IGD or NAT-PMP.
+//!
+//! A node behind a NAT listens on a private address that other nodes can't dial. Most home
+//! routers can however be asked to forward a port of their public address to the node, either
+//! with the Internet Gateway Device protocol of UPnP, or with NAT-PMP
+//! ([RFC 6886](https://tools.ietf.org/html/rfc6886)).
+//!
+//! # Usage
+//!
+//! This crate provides the `PortMap` struct which implements the `NetworkBehaviour` trait. When
+//! the swarm starts listening on a private TCP address, it asks the gateway to map the port,
+//! renews the mapping before its lease expires, and reports the public address to the swarm as
+//! an external address.
+
+pub use self::behaviour::{MappingProtocol, PortMap, PortMapConfig, PortMapEvent};
+pub use self::error::PortMapError;
+
+mod behaviour;
+mod error;
+
+pub mod igd;
+pub mod natpmp;
+
+use std::{net::SocketAddrV4, time::Duration};
+
+/// A port mapping created by a gateway.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mapping {
+    /// The public address and port forwarded to the local node.
+    pub external: SocketAddrV4,
+    /// How long the mapping lasts before it has to be renewed. Zero if it never expires.
+    pub lease: Duration,
+}
@@ -0,0 +1,265 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Client side of NAT-PMP, see [RFC 6886](https://tools.ietf.org/html/rfc6886).
+//!
+//! Requests are sent over UDP to port 5351 of the gateway, and retransmitted with an
+//! exponential backoff until the gateway answers.
+
+use crate::{Mapping, PortMapError};
+use futures::{prelude::*, try_ready};
+use libp2p_core::clock;
+use std::{
+    mem,
+    net::{Ipv4Addr, SocketAddr, SocketAddrV4},
+    time::Duration
+};
+use tokio_udp::UdpSocket;
+use wasm_timer::Delay;
+
+/// Port on which NAT-PMP gateways listen.
+pub const NAT_PMP_PORT: u16 = 5351;
+
+/// Opcode of the request for the public address of the gateway.
+const OP_EXTERNAL_ADDRESS: u8 = 0;
+/// Opcode of the request for a TCP port mapping.
+const OP_MAP_TCP: u8 = 2;
+
+/// Delay before the first retransmission of a request. Doubled after each retransmission.
+const INITIAL_RETRANSMIT: Duration = Duration::from_millis(250);
+/// Number of times a request is sent before giving up.
+const MAX_TRANSMISSIONS: u32 = 5;
+
+/// Asks the gateway to forward a TCP port of its public address to `internal_port` of the
+/// local node, for the duration of `lease`.
+///
+/// The gateway tries to use the same port number for the public port, but may pick another
+/// one. The mapping is renewed by calling this function again.
+pub fn map_tcp(gateway: SocketAddr, internal_port: u16, lease: Duration) -> MapTcp {
+    MapTcp {
+        gateway,
+        internal_port,
+        lease,
+        state: MapTcpState::ExternalAddress(Request::new(gateway, external_address_request())),
+    }
+}
+
+/// Asks the gateway to remove the mapping of `internal_port`.
+pub fn unmap_tcp(gateway: SocketAddr, internal_port: u16) -> impl Future<Item = (), Error = PortMapError> {
+    Request::new(gateway, map_request(internal_port, 0, Duration::from_secs(0)))
+        .and_then(|response| parse_map_response(&response).map(|_| ()))
+}
+
+/// Future returned by `map_tcp`.
+pub struct MapTcp {
+    gateway: SocketAddr,
+    internal_port: u16,
+    lease: Duration,
+    state: MapTcpState,
+}
+
+enum MapTcpState {
+    ExternalAddress(Request),
+    Map(Request, Ipv4Addr),
+    Poisoned,
+}
+
+impl Future for MapTcp {
+    type Item = Mapping;
+    type Error = PortMapError;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        loop {
+            match mem::replace(&mut self.state, MapTcpState::Poisoned) {
+                MapTcpState::ExternalAddress(mut request) => match request.poll()? {
+                    Async::Ready(response) => {
+                        let ip = parse_external_address_response(&response)?;
+                        let map = map_request(self.internal_port, self.internal_port, self.lease);
+                        self.state = MapTcpState::Map(Request::new(self.gateway, map), ip);
+                    }
+                    Async::NotReady => {
+                        self.state = MapTcpState::ExternalAddress(request);
+                        return Ok(Async::NotReady);
+                    }
+                },
+                MapTcpState::Map(mut request, ip) => match request.poll()? {
+                    Async::Ready(response) => {
+                        let (external_port, lease) = parse_map_response(&response)?;
+                        return Ok(Async::Ready(Mapping {
+                            external: SocketAddrV4::new(ip, external_port),
+                            lease,
+                        }));
+                    }
+                    Async::NotReady => {
+                        self.state = MapTcpState::Map(request, ip);
+                        return Ok(Async::NotReady);
+                    }
+                },
+                MapTcpState::Poisoned => panic!("MapTcp polled after completion"),
+            }
+        }
+    }
+}
+
+/// Future that sends a request to the gateway, retransmitting it until we get an answer, and
+/// yields the answer.
+struct Request {
+    gateway: SocketAddr,
+    socket: Option<UdpSocket>,
+    packet: Vec<u8>,
+    /// Whether the packet has been sent since the last timeout.
+    sent: bool,
+    /// Number of times the packet has been sent.
+    transmissions: u32,
+    /// When to retransmit the packet.
+    retransmit: Delay,
+}
+
+impl Request {
+    fn new(gateway: SocketAddr, packet: Vec<u8>) -> Self {
+        Request {
+            gateway,
+            socket: None,
+            packet,
+            sent: false,
+            transmissions: 0,
+            retransmit: Delay::new(clock::now()),
+        }
+    }
+}
+
+impl Future for Request {
+    type Item = Vec<u8>;
+    type Error = PortMapError;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        if self.socket.is_none() {
+            let local_addr = match self.gateway {
+                SocketAddr::V4(_) => SocketAddr::from(([0, 0, 0, 0], 0)),
+                SocketAddr::V6(_) => SocketAddr::from(([0; 16], 0)),
+            };
+            self.socket = Some(UdpSocket::bind(&local_addr)?);
+        }
+        let socket = self.socket.as_mut().expect("the socket has just been bound; qed");
+
+        loop {
+            if !self.sent {
+                try_ready!(socket.poll_send_to(&self.packet, &self.gateway));
+                self.sent = true;
+                let backoff = INITIAL_RETRANSMIT * 2u32.pow(self.transmissions);
+                self.transmissions += 1;
+                self.retransmit.reset(clock::now() + backoff);
+            }
+
+            let mut buf = [0; 16];
+            match socket.poll_recv_from(&mut buf)? {
+                Async::Ready((len, from)) => {
+                    // The answer must come from the gateway, and be the answer to our request.
+                    if from == self.gateway && len >= 4 && buf[0] == 0 && buf[1] == 128 + self.packet[1] {
+                        return Ok(Async::Ready(buf[..len].to_vec()));
+                    }
+                    continue;
+                }
+                Async::NotReady => {}
+            }
+
+            match self.retransmit.poll() {
+                Ok(Async::NotReady) => return Ok(Async::NotReady),
+                Ok(Async::Ready(())) if self.transmissions < MAX_TRANSMISSIONS => self.sent = false,
+                Ok(Async::Ready(())) => return Err(PortMapError::Timeout),
+                Err(err) => return Err(PortMapError::Io(io_error(err))),
+            }
+        }
+    }
+}
+
+fn io_error(err: wasm_timer::Error) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::Other, err)
+}
+
+fn external_address_request() -> Vec<u8> {
+    vec![0, OP_EXTERNAL_ADDRESS]
+}
+
+fn map_request(internal_port: u16, external_port: u16, lease: Duration) -> Vec<u8> {
+    let mut packet = vec![0, OP_MAP_TCP, 0, 0];
+    packet.extend_from_slice(&internal_port.to_be_bytes());
+    packet.extend_from_slice(&external_port.to_be_bytes());
+    let lease = lease.as_secs().min(u64::from(u32::max_value())) as u32;
+    packet.extend_from_slice(&lease.to_be_bytes());
+    packet
+}
+
+/// Checks the result code of a response.
+fn check_result(response: &[u8]) -> Result<(), PortMapError> {
+    match u16::from_be_bytes([response[2], response[3]]) {
+        0 => Ok(()),
+        code => Err(PortMapError::NatPmp(code)),
+    }
+}
+
+/// Parses the response to an external address request, which is made of the version, the
+/// opcode, the result code, the seconds since the start of the epoch, and the address.
+fn parse_external_address_response(response: &[u8]) -> Result<Ipv4Addr, PortMapError> {
+    check_result(response)?;
+    if response.len() < 12 {
+        return Err(PortMapError::InvalidResponse);
+    }
+    Ok(Ipv4Addr::new(response[8], response[9], response[10], response[11]))
+}
+
+/// Parses the response to a mapping request, which is made of the version, the opcode, the
+/// result code, the seconds since the start of the epoch, the internal and external ports, and
+/// the lifetime of the mapping. Returns the external port and the lifetime.
+fn parse_map_response(response: &[u8]) -> Result<(u16, Duration), PortMapError> {
+    check_result(response)?;
+    if response.len() < 16 {
+        return Err(PortMapError::InvalidResponse);
+    }
+    let external_port = u16::from_be_bytes([response[10], response[11]]);
+    let lease = u32::from_be_bytes([response[12], response[13], response[14], response[15]]);
+    Ok((external_port, Duration::from_secs(u64::from(lease))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn map_request_layout() {
+        let packet = map_request(4001, 4001, Duration::from_secs(3600));
+        assert_eq!(packet, vec![0, 2, 0, 0, 0x0f, 0xa1, 0x0f, 0xa1, 0, 0, 0x0e, 0x10]);
+    }
+
+    #[test]
+    fn parse_responses() {
+        let response = [0, 128, 0, 0, 0, 0, 0, 7, 203, 0, 113, 7];
+        assert_eq!(parse_external_address_response(&response).unwrap(), Ipv4Addr::new(203, 0, 113, 7));
+
+        let response = [0, 130, 0, 0, 0, 0, 0, 7, 0x0f, 0xa1, 0x9c, 0x41, 0, 0, 0x0e, 0x10];
+        assert_eq!(parse_map_response(&response).unwrap(), (40001, Duration::from_secs(3600)));
+
+        let refused = [0, 130, 0, 3, 0, 0, 0, 7, 0x0f, 0xa1, 0, 0, 0, 0, 0, 0];
+        match parse_map_response(&refused) {
+            Err(PortMapError::NatPmp(3)) => {}
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,359 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use futures::{future, prelude::*, stream};
+use libp2p_core::{
+    PeerId,
+    compat::Transport01,
+    identity,
+    multiaddr::Multiaddr,
+    muxing::StreamMuxerBox,
+    nodes::Substream,
+    transport::{ListenerEvent, TransportError},
+};
+use libp2p_portmap::{MappingProtocol, PortMap, PortMapConfig, PortMapEvent};
+use libp2p_swarm::Swarm;
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{SocketAddr, TcpListener, UdpSocket},
+    sync::{Arc, Mutex, atomic::{AtomicUsize, Ordering}},
+    thread,
+    time::Duration
+};
+use tokio::{runtime::current_thread::Runtime, util::FutureExt};
+
+const DESCRIPTION: &str = "<?xml version=\"1.0\"?>
+<root xmlns=\"urn:schemas-upnp-org:device-1-0\">
+<device><deviceType>urn:schemas-upnp-org:device:InternetGatewayDevice:1</deviceType>
+<deviceList><device><deviceList><device><serviceList><service>
+<serviceType>urn:schemas-upnp-org:service:WANIPConnection:1</serviceType>
+<controlURL>/ctl/IPConn</controlURL>
+</service></serviceList></device></deviceList></device></deviceList>
+</device></root>";
+
+/// Stand-in for a home router on localhost, speaking SSDP, the SOAP interface of UPnP-IGD and
+/// NAT-PMP. Its public address is `203.0.113.7`.
+struct Gateway {
+    ssdp_addr: SocketAddr,
+    nat_pmp_addr: SocketAddr,
+    /// The UPnP actions and NAT-PMP requests received, in order.
+    requests: Arc<Mutex<Vec<String>>>,
+    /// Number of NAT-PMP mapping requests to refuse before accepting them again.
+    refusals: Arc<AtomicUsize>,
+}
+
+impl Gateway {
+    /// Starts the gateway. If `upnp` is false, SSDP searches are not answered.
+    fn spawn(upnp: bool) -> Gateway {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let http = TcpListener::bind("127.0.0.1:0").unwrap();
+        let http_addr = http.local_addr().unwrap();
+        let http_requests = requests.clone();
+        thread::spawn(move || {
+            for stream in http.incoming() {
+                serve_http(stream.unwrap(), &http_requests);
+            }
+        });
+
+        let ssdp = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let ssdp_addr = ssdp.local_addr().unwrap();
+        thread::spawn(move || {
+            let mut buf = [0; 2048];
+            loop {
+                let (len, from) = ssdp.recv_from(&mut buf).unwrap();
+                let search = String::from_utf8_lossy(&buf[..len]);
+                if upnp && search.starts_with("M-SEARCH") && search.contains("ssdp:discover") {
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\n\
+                         ST: urn:schemas-upnp-org:device:InternetGatewayDevice:1\r\n\
+                         LOCATION: http://{}/rootDesc.xml\r\n\r\n",
+                        http_addr
+                    );
+                    ssdp.send_to(response.as_bytes(), from).unwrap();
+                }
+            }
+        });
+
+        let nat_pmp = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let nat_pmp_addr = nat_pmp.local_addr().unwrap();
+        let nat_pmp_requests = requests.clone();
+        let refusals = Arc::new(AtomicUsize::new(0));
+        let nat_pmp_refusals = refusals.clone();
+        thread::spawn(move || {
+            let mut buf = [0; 16];
+            loop {
+                let (len, from) = nat_pmp.recv_from(&mut buf).unwrap();
+                let epoch = [0, 0, 0, 42];
+                let response = match (&buf[..len], len) {
+                    ([0, 0], _) => [&[0, 128, 0, 0][..], &epoch, &[203, 0, 113, 7]].concat(),
+                    ([0, 2, 0, 0, ..], 12) => {
+                        let internal = u16::from_be_bytes([buf[4], buf[5]]);
+                        let lifetime = &buf[8..12];
+                        nat_pmp_requests.lock().unwrap().push(format!("MapTcp {}", internal));
+                        let refused = nat_pmp_refusals
+                            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+                            .is_ok();
+                        if refused {
+                            // Network failure.
+                            [&[0, 130, 0, 3][..], &epoch, &buf[4..6], &[0; 6]].concat()
+                        } else {
+                            // Pretend the port is taken and map another one.
+                            [&[0, 130, 0, 0][..], &epoch, &buf[4..6], &40001u16.to_be_bytes(), lifetime].concat()
+                        }
+                    }
+                    _ => continue,
+                };
+                nat_pmp.send_to(&response, from).unwrap();
+            }
+        });
+
+        Gateway { ssdp_addr, nat_pmp_addr, requests, refusals }
+    }
+
+    fn config(&self) -> PortMapConfig {
+        let mut config = PortMapConfig::default();
+        config
+            .set_ssdp_addr(self.ssdp_addr)
+            .set_discovery_timeout(Duration::from_millis(200))
+            .set_nat_pmp_gateway(self.nat_pmp_addr);
+        config
+    }
+
+    fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// Makes the gateway refuse the next `n` NAT-PMP mapping requests.
+    fn refuse(&self, n: usize) {
+        self.refusals.store(n, Ordering::SeqCst);
+    }
+}
+
+/// Answers an HTTP request: the device description, or one of the UPnP actions.
+fn serve_http(mut stream: std::net::TcpStream, requests: &Mutex<Vec<String>>) {
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).unwrap();
+    let mut content_length = 0;
+    let mut action = String::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        if line == "\r\n" {
+            break;
+        }
+        let (name, value) = line.split_at(line.find(':').unwrap());
+        let value = value[1..].trim();
+        if name.eq_ignore_ascii_case("content-length") {
+            content_length = value.parse().unwrap();
+        } else if name.eq_ignore_ascii_case("soapaction") {
+            action = value.trim_matches('"').rsplit('#').next().unwrap().to_owned();
+        }
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).unwrap();
+    let body = String::from_utf8(body).unwrap();
+
+    let response_body = if request_line.starts_with("GET /rootDesc.xml ") {
+        DESCRIPTION.to_owned()
+    } else {
+        assert!(request_line.starts_with("POST /ctl/IPConn "));
+        let args = match action.as_str() {
+            "GetExternalIPAddress" => "<NewExternalIPAddress>203.0.113.7</NewExternalIPAddress>",
+            "AddPortMapping" => {
+                assert!(body.contains("<NewProtocol>TCP</NewProtocol>"));
+                assert!(body.contains("<NewInternalClient>192.168.1.20</NewInternalClient>"));
+                ""
+            }
+            action => panic!("Unexpected action {}", action),
+        };
+        requests.lock().unwrap().push(action.clone());
+        format!(
+            "<?xml version=\"1.0\"?>\
+             <s:Envelope xmlns:s=\"http://schemas.xmlsoap.org/soap/envelope/\"><s:Body>\
+             <u:{}Response xmlns:u=\"urn:schemas-upnp-org:service:WANIPConnection:1\">{}</u:{}Response>\
+             </s:Body></s:Envelope>",
+            action, args, action
+        )
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/xml\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response_body.len(), response_body
+    ).unwrap();
+}
+
+/// Transport that pretends to listen on a private address. It never dials nor accepts
+/// connections.
+#[derive(Clone)]
+struct PrivateListenTransport;
+
+impl Transport01 for PrivateListenTransport {
+    type Output = (PeerId, StreamMuxerBox);
+    type Error = io::Error;
+    type Listener = Box<dyn Stream<Item = ListenerEvent<Self::ListenerUpgrade>, Error = io::Error> + Send>;
+    type ListenerUpgrade = future::Empty<Self::Output, io::Error>;
+    type Dial = future::Empty<Self::Output, io::Error>;
+
+    fn listen_on(self, addr: Multiaddr) -> Result<Self::Listener, TransportError<Self::Error>> {
+        let new_addr = stream::once(Ok(ListenerEvent::NewAddress(addr)));
+        Ok(Box::new(new_addr.chain(future::empty().into_stream())))
+    }
+
+    fn dial(self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
+        Err(TransportError::MultiaddrNotSupported(addr))
+    }
+}
+
+type TestSwarm = Swarm<PrivateListenTransport, PortMap<Substream<StreamMuxerBox>>>;
+
+fn build_swarm(config: PortMapConfig) -> TestSwarm {
+    let peer_id = identity::Keypair::generate_ed25519().public().into_peer_id();
+    let mut swarm = Swarm::new(PrivateListenTransport, PortMap::new(config), peer_id);
+    Swarm::listen_on(&mut swarm, "/ip4/192.168.1.20/tcp/4001".parse().unwrap()).unwrap();
+    swarm
+}
+
+/// Polls the swarm until it produces an event.
+fn next_event(rt: &mut Runtime, swarm: &mut TestSwarm) -> PortMapEvent {
+    let event = future::poll_fn(|| swarm.poll()).map(|event| event.expect("swarm stopped"));
+    rt.block_on(event.timeout(Duration::from_secs(10))).unwrap()
+}
+
+#[test]
+fn map_with_upnp_igd() {
+    let mut rt = Runtime::new().unwrap();
+    let gateway = Gateway::spawn(true);
+    let mut config = gateway.config();
+    config.set_lease_duration(Duration::from_secs(2));
+    let mut swarm = build_swarm(config);
+
+    let external_addr: Multiaddr = "/ip4/203.0.113.7/tcp/4001".parse().unwrap();
+    match next_event(&mut rt, &mut swarm) {
+        PortMapEvent::Mapped { listen_addr, external_addr: addr, protocol } => {
+            assert_eq!(listen_addr, "/ip4/192.168.1.20/tcp/4001".parse().unwrap());
+            assert_eq!(addr, external_addr);
+            assert_eq!(protocol, MappingProtocol::Igd);
+        }
+        event => panic!("Unexpected event: {:?}", event),
+    }
+    assert!(Swarm::external_addresses(&swarm).any(|addr| *addr == external_addr));
+    assert_eq!(gateway.requests(), vec!["AddPortMapping", "GetExternalIPAddress"]);
+
+    // The mapping is renewed after half of its lease, without any event as the public address
+    // doesn't change. The gateway records the requests before answering them, so they are all
+    // recorded once the swarm is woken up by the last answer.
+    let renewed = future::poll_fn(|| -> Poll<(), io::Error> {
+        if let Async::Ready(event) = swarm.poll()? {
+            panic!("Unexpected event: {:?}", event);
+        }
+        if gateway.requests().len() == 4 {
+            Ok(Async::Ready(()))
+        } else {
+            Ok(Async::NotReady)
+        }
+    });
+    rt.block_on(renewed.timeout(Duration::from_secs(10))).unwrap();
+    assert_eq!(gateway.requests()[2..], ["AddPortMapping", "GetExternalIPAddress"]);
+}
+
+#[test]
+fn fall_back_to_nat_pmp() {
+    let mut rt = Runtime::new().unwrap();
+    let gateway = Gateway::spawn(false);
+    let mut swarm = build_swarm(gateway.config());
+
+    match next_event(&mut rt, &mut swarm) {
+        PortMapEvent::Mapped { external_addr, protocol, .. } => {
+            assert_eq!(external_addr, "/ip4/203.0.113.7/tcp/40001".parse().unwrap());
+            assert_eq!(protocol, MappingProtocol::NatPmp);
+        }
+        event => panic!("Unexpected event: {:?}", event),
+    }
+    assert_eq!(gateway.requests(), vec!["MapTcp 4001"]);
+}
+
+#[test]
+fn retry_failed_renewals() {
+    let mut rt = Runtime::new().unwrap();
+    let gateway = Gateway::spawn(false);
+    let mut config = gateway.config();
+    config.set_lease_duration(Duration::from_secs(2));
+    let mut swarm = build_swarm(config);
+
+    let external_addr: Multiaddr = "/ip4/203.0.113.7/tcp/40001".parse().unwrap();
+    match next_event(&mut rt, &mut swarm) {
+        PortMapEvent::Mapped { external_addr: addr, .. } => assert_eq!(addr, external_addr),
+        event => panic!("Unexpected event: {:?}", event),
+    }
+
+    // The first renewal fails, the retry succeeds before the end of the lease. The mapping is
+    // kept all along.
+    gateway.refuse(1);
+    let renewed = future::poll_fn(|| -> Poll<(), io::Error> {
+        if let Async::Ready(event) = swarm.poll()? {
+            panic!("Unexpected event: {:?}", event);
+        }
+        if gateway.requests().len() == 3 {
+            Ok(Async::Ready(()))
+        } else {
+            Ok(Async::NotReady)
+        }
+    });
+    rt.block_on(renewed.timeout(Duration::from_secs(10))).unwrap();
+    let idle = future::poll_fn(|| -> Poll<(), io::Error> {
+        if let Async::Ready(event) = swarm.poll()? {
+            panic!("Unexpected event: {:?}", event);
+        }
+        Ok(Async::NotReady)
+    });
+    assert!(rt.block_on(idle.timeout(Duration::from_millis(500))).unwrap_err().is_elapsed());
+    assert!(Swarm::external_addresses(&swarm).any(|addr| *addr == external_addr));
+}
+
+#[test]
+fn withdraw_expired_mappings() {
+    let mut rt = Runtime::new().unwrap();
+    let gateway = Gateway::spawn(false);
+    let mut config = gateway.config();
+    config.set_lease_duration(Duration::from_secs(2));
+    let mut swarm = build_swarm(config);
+
+    let external_addr: Multiaddr = "/ip4/203.0.113.7/tcp/40001".parse().unwrap();
+    match next_event(&mut rt, &mut swarm) {
+        PortMapEvent::Mapped { external_addr: addr, .. } => assert_eq!(addr, external_addr),
+        event => panic!("Unexpected event: {:?}", event),
+    }
+    assert!(Swarm::external_addresses(&swarm).any(|addr| *addr == external_addr));
+
+    // The renewal and its retries fail until the lease ends.
+    gateway.refuse(usize::MAX);
+    match next_event(&mut rt, &mut swarm) {
+        PortMapEvent::Expired { listen_addr, external_addr: addr, .. } => {
+            assert_eq!(listen_addr, "/ip4/192.168.1.20/tcp/4001".parse().unwrap());
+            assert_eq!(addr, external_addr);
+        }
+        event => panic!("Unexpected event: {:?}", event),
+    }
+    assert!(Swarm::external_addresses(&swarm).all(|addr| *addr != external_addr));
+    assert!(gateway.requests().len() >= 3);
+}
@@ -0,0 +1,103 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Virtual clock of the simulation.
+
+use parking_lot::Mutex;
+use std::{sync::Arc, time::{Duration, Instant}};
+use tokio_executor::park::{Park, Unpark};
+use void::Void;
+
+/// Clock whose time only moves forward when the simulation advances it.
+#[derive(Debug, Clone)]
+pub(crate) struct VirtualClock {
+    /// Instant at which the simulation started.
+    start: Instant,
+    /// Virtual time elapsed since the start.
+    elapsed: Arc<Mutex<Duration>>,
+}
+
+impl VirtualClock {
+    pub(crate) fn new() -> Self {
+        VirtualClock {
+            start: Instant::now(),
+            elapsed: Arc::new(Mutex::new(Duration::from_secs(0))),
+        }
+    }
+
+    pub(crate) fn elapsed(&self) -> Duration {
+        *self.elapsed.lock()
+    }
+
+    pub(crate) fn advance_to(&self, elapsed: Duration) {
+        let mut current = self.elapsed.lock();
+        debug_assert!(*current <= elapsed);
+        *current = elapsed;
+    }
+}
+
+impl tokio_timer::clock::Now for VirtualClock {
+    fn now(&self) -> Instant {
+        self.start + self.elapsed()
+    }
+}
+
+/// Implementation of `Park` for the timer of the simulation.
+///
+/// Instead of blocking the thread, records how long the timer wants to wait for its next
+/// deadline.
+#[derive(Debug, Default)]
+pub(crate) struct RecordWait {
+    wait: Option<Duration>,
+}
+
+impl RecordWait {
+    /// Returns the wait requested by the timer since the last call, if any.
+    pub(crate) fn take(&mut self) -> Option<Duration> {
+        self.wait.take()
+    }
+}
+
+impl Park for RecordWait {
+    type Unpark = NoUnpark;
+    type Error = Void;
+
+    fn unpark(&self) -> NoUnpark {
+        NoUnpark
+    }
+
+    fn park(&mut self) -> Result<(), Void> {
+        self.wait = None;
+        Ok(())
+    }
+
+    fn park_timeout(&mut self, duration: Duration) -> Result<(), Void> {
+        self.wait = Some(duration);
+        Ok(())
+    }
+}
+
+/// Implementation of `Unpark` for `RecordWait`, which never blocks.
+#[derive(Debug)]
+pub(crate) struct NoUnpark;
+
+impl Unpark for NoUnpark {
+    fn unpark(&self) {}
+}
@@ -0,0 +1,60 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Executor that spawns tasks on the simulation.
+
+use futures03::future::BoxFuture;
+use libp2p_core::Executor;
+use parking_lot::Mutex;
+use std::{fmt, mem, sync::Arc};
+
+/// Future spawned on the simulation.
+pub(crate) type Task = BoxFuture<'static, ()>;
+
+/// [`Executor`] that runs the spawned futures as tasks of the simulation.
+///
+/// The tasks are polled by [`Simulation::run_until`](crate::Simulation::run_until) when they
+/// are woken up, in the order in which they have been spawned. Compared to an `InlineExecutor`,
+/// a `Swarm` built with this executor only processes the connections that have made progress.
+#[derive(Clone, Default)]
+pub struct SimExecutor {
+    /// Futures that have been spawned, but not picked up by the simulation yet.
+    spawned: Arc<Mutex<Vec<Task>>>,
+}
+
+impl SimExecutor {
+    /// Removes the futures that have been spawned since the last call.
+    pub(crate) fn take(&self) -> Vec<Task> {
+        mem::replace(&mut *self.spawned.lock(), Vec::new())
+    }
+}
+
+impl fmt::Debug for SimExecutor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SimExecutor").field("spawned", &self.spawned.lock().len()).finish()
+    }
+}
+
+impl Executor for SimExecutor {
+    fn exec(&self, future: Task) -> Result<(), Task> {
+        self.spawned.lock().push(future);
+        Ok(())
+    }
+}
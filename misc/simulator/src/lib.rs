@@ -0,0 +1,424 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Deterministic simulation of libp2p networks.
+//!
+//! A [`Simulation`] runs many nodes, usually `Swarm`s, on the current thread:
+//!
+//! - The nodes communicate through their [`SimTransport`], whose connections go through
+//!   simulated links. The latency, jitter and packet loss of the links are configured with
+//!   [`LinkConfig`], and the network can be split with [`Simulation::partition`].
+//! - The time is virtual. The timers of tokio-timer (`Delay`, `Interval`, `Timeout`) and
+//!   `libp2p_core::clock::now()` follow the clock of the simulation, which jumps to the next
+//!   timer deadline or packet delivery whenever all the nodes are idle. Simulating hours of
+//!   activity therefore only takes as long as processing the traffic.
+//! - The randomness of the simulation, including the packet losses and the identities returned
+//!   by [`Simulation::keypair`], comes from the seed passed to [`Simulation::new`].
+//!
+//! The nodes, and the tasks spawned through [`Simulation::executor`], are polled one after the
+//! other in a fixed order. As a result, two simulations with the same seed produce the same
+//! events, as long as the nodes themselves are deterministic. In particular, the nodes must
+//! spawn their connection tasks with a [`SimExecutor`] or process them on their own task with
+//! an `InlineExecutor`, and must read the time with `libp2p_core::clock::now()` rather than
+//! `Instant::now()`.
+//!
+//! # Example
+//!
+//! ```rust
+//! use futures03::TryFutureExt;
+//! use libp2p_core::{Transport, upgrade};
+//! use libp2p_mplex::MplexConfig;
+//! use libp2p_ping::{Ping, PingConfig, PingSuccess};
+//! use libp2p_secio::SecioConfig;
+//! use libp2p_simulator::{LinkConfig, Simulation};
+//! use libp2p_swarm::{Swarm, SwarmBuilder};
+//! use std::time::Duration;
+//!
+//! let mut sim = Simulation::new(42);
+//! sim.set_default_link(LinkConfig::default().with_latency(Duration::from_millis(50)));
+//!
+//! let mut nodes = Vec::new();
+//! for _ in 0 .. 2 {
+//!     let keypair = sim.keypair();
+//!     let executor = sim.executor();
+//!     nodes.push(sim.add_node(move |transport| {
+//!         let local_peer_id = keypair.public().into_peer_id();
+//!         let transport = transport
+//!             .with_upgrade(SecioConfig::new(keypair))
+//!             .and_then(move |out, endpoint| {
+//!                 let peer_id = out.remote_key.into_peer_id();
+//!                 upgrade::apply(out.stream, MplexConfig::new(), endpoint)
+//!                     .map_ok(move |muxer| (peer_id, muxer))
+//!             });
+//!         SwarmBuilder::new(transport, Ping::new(PingConfig::new()), local_peer_id)
+//!             .executor(Box::new(executor))
+//!             .build()
+//!     }));
+//! }
+//!
+//! sim.with_node(nodes[0], |swarm| Swarm::listen_on(swarm, "/memory/1".parse().unwrap()))
+//!     .unwrap();
+//! sim.with_node(nodes[1], |swarm| Swarm::dial_addr(swarm, "/memory/1".parse().unwrap()))
+//!     .unwrap();
+//!
+//! // The round-trip time is measured on the virtual clock.
+//! let mut rtt = None;
+//! sim.run_until(Duration::from_secs(60), |_, event| {
+//!     if let Ok(PingSuccess::Ping { rtt: r }) = event.result {
+//!         rtt = Some(r);
+//!     }
+//!     rtt.is_some()
+//! });
+//! assert_eq!(rtt, Some(Duration::from_millis(100)));
+//! ```
+
+mod clock;
+mod executor;
+mod network;
+mod transport;
+
+pub use executor::SimExecutor;
+pub use network::LinkConfig;
+pub use transport::{SimDial, SimListener, SimStream, SimTransport};
+
+use crate::{clock::{RecordWait, VirtualClock}, executor::Task, network::Network};
+use futures::{executor::{Notify, Spawn, spawn}, prelude::*};
+use futures03::{FutureExt as _, future::UnitError};
+use libp2p_core::{compat::Compat03As01, identity};
+use parking_lot::Mutex;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use std::{cmp, collections::BTreeSet, fmt, sync::Arc, time::Duration};
+use tokio_timer::{clock::Clock, timer::Timer};
+
+/// Identifier of a node of a [`Simulation`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NodeId(usize);
+
+impl NodeId {
+    /// Returns the position of the node in the order in which the nodes have been added.
+    pub fn index(&self) -> usize {
+        self.0
+    }
+}
+
+impl fmt::Display for NodeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "node #{}", self.0)
+    }
+}
+
+/// Wait passed to the timer, which is so long that it means that there is no timer to wait for.
+const NO_TIMER: Duration = Duration::from_secs(u64::max_value());
+
+/// Simulation of a network of nodes of type `T`.
+///
+/// The nodes are streams, usually `Swarm`s, whose events are reported by [`Simulation::run_until`].
+pub struct Simulation<T> {
+    /// State of the simulated network.
+    network: Arc<Mutex<Network>>,
+    /// Virtual clock, as it is seen by the nodes.
+    clock: Clock,
+    /// Virtual clock, as it is advanced by the simulation.
+    virtual_clock: VirtualClock,
+    /// Timer that drives the `Delay`s of the nodes.
+    timer: Timer<RecordWait, Clock>,
+    /// Executor on which the nodes spawn their tasks.
+    executor: SimExecutor,
+    /// Nodes and tasks that need to be polled, by position in `tasks`.
+    ready: Arc<Ready>,
+    /// The nodes and the tasks that they have spawned, in the order in which they are polled.
+    tasks: Vec<Slot<T>>,
+    /// Position of each node in `tasks`.
+    nodes: Vec<usize>,
+    /// Source of the randomness of the simulation.
+    rng: StdRng,
+}
+
+impl<T> fmt::Debug for Simulation<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Simulation")
+            .field("elapsed", &self.elapsed())
+            .field("nodes", &self.nodes.len())
+            .finish()
+    }
+}
+
+impl<T> Simulation<T> {
+    /// Creates a simulation without any node, whose randomness is derived from `seed`.
+    pub fn new(seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let network_rng = StdRng::from_rng(&mut rng).expect("StdRng never fails; QED");
+        let virtual_clock = VirtualClock::new();
+        let clock = Clock::new_with_now(virtual_clock.clone());
+        let timer = Timer::new_with_now(RecordWait::default(), clock.clone());
+        Simulation {
+            network: Arc::new(Mutex::new(Network::new(virtual_clock.clone(), network_rng))),
+            clock,
+            virtual_clock,
+            timer,
+            executor: SimExecutor::default(),
+            ready: Arc::new(Ready::default()),
+            tasks: Vec::new(),
+            nodes: Vec::new(),
+            rng,
+        }
+    }
+
+    /// Returns the virtual time elapsed since the start of the simulation.
+    pub fn elapsed(&self) -> Duration {
+        self.virtual_clock.elapsed()
+    }
+
+    /// Returns the seeded source of randomness of the simulation.
+    pub fn rng(&mut self) -> &mut StdRng {
+        &mut self.rng
+    }
+
+    /// Returns an executor that runs the futures spawned on it as tasks of the simulation.
+    ///
+    /// This is usually passed to the `SwarmBuilder` of the nodes.
+    pub fn executor(&self) -> SimExecutor {
+        self.executor.clone()
+    }
+
+    /// Generates an Ed25519 keypair from the randomness of the simulation.
+    pub fn keypair(&mut self) -> identity::Keypair {
+        let mut bytes = [0; 32];
+        self.rng.fill(&mut bytes);
+        let secret = identity::ed25519::SecretKey::from_bytes(&mut bytes)
+            .expect("any 32 bytes are a valid Ed25519 secret key; QED");
+        identity::Keypair::Ed25519(secret.into())
+    }
+
+    /// Configures the links that aren't configured with [`Simulation::set_link`].
+    pub fn set_default_link(&mut self, config: LinkConfig) {
+        self.network.lock().set_default_link(config)
+    }
+
+    /// Configures the link between two nodes, in both directions.
+    pub fn set_link(&mut self, a: NodeId, b: NodeId, config: LinkConfig) {
+        let mut network = self.network.lock();
+        network.set_link(a, b, config.clone());
+        network.set_link(b, a, config);
+    }
+
+    /// Cuts the links between the nodes of `a` and the nodes of `b`, until [`Simulation::heal`]
+    /// is called.
+    ///
+    /// The connections between the two groups are reset, and the dials between them fail with
+    /// `ConnectionRefused` once they reach the other side.
+    pub fn partition(&mut self, a: &[NodeId], b: &[NodeId]) {
+        self.network.lock().partition(a, b)
+    }
+
+    /// Restores all the links cut by [`Simulation::partition`].
+    pub fn heal(&mut self) {
+        self.network.lock().heal()
+    }
+
+    /// Returns a node of the simulation.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the node doesn't belong to the simulation.
+    pub fn node(&self, id: NodeId) -> &T {
+        match &self.tasks[self.nodes[id.0]] {
+            Slot::Node(node, _, _) => node.get_ref(),
+            _ => unreachable!("nodes are never removed from `tasks`; QED"),
+        }
+    }
+
+    /// Adds the futures spawned on the executor to the tasks to poll.
+    fn spawn_pending(&mut self) {
+        for task in self.executor.take() {
+            self.ready.wake(self.tasks.len());
+            self.tasks.push(Slot::Task(spawn(Compat03As01::new(task.unit_error()))));
+        }
+    }
+
+    /// Sets the clock and the timer of the simulation as the default ones of the current
+    /// thread, until the returned guards are dropped.
+    fn enter(&self) -> (tokio_timer::clock::DefaultGuard, tokio_timer::timer::DefaultGuard) {
+        let clock = tokio_timer::clock::set_default(&self.clock);
+        let timer = tokio_timer::timer::set_default(&self.timer.handle());
+        (clock, timer)
+    }
+}
+
+impl<T> Simulation<T>
+where
+    T: Stream,
+    T::Error: fmt::Debug,
+{
+    /// Adds a node to the simulation, built by `f` from its transport.
+    ///
+    /// The nodes are polled in the order in which they are added.
+    pub fn add_node(&mut self, f: impl FnOnce(SimTransport) -> T) -> NodeId {
+        let id = NodeId(self.nodes.len());
+        let transport = SimTransport { network: self.network.clone(), node: id };
+        let node = {
+            let _enter = self.enter();
+            f(transport)
+        };
+        self.ready.wake(self.tasks.len());
+        self.nodes.push(self.tasks.len());
+        self.tasks.push(Slot::Node(spawn(node), id, false));
+        id
+    }
+
+    /// Calls `f` with a node of the simulation, for example to start dialing or a query.
+    ///
+    /// The node is polled during the next run of the simulation.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the node doesn't belong to the simulation.
+    pub fn with_node<R>(&mut self, id: NodeId, f: impl FnOnce(&mut T) -> R) -> R {
+        let index = self.nodes[id.0];
+        let _enter = self.enter();
+        let result = match &mut self.tasks[index] {
+            Slot::Node(node, _, _) => f(node.get_mut()),
+            _ => unreachable!("nodes are never removed from `tasks`; QED"),
+        };
+        self.ready.wake(index);
+        result
+    }
+
+    /// Runs the simulation for the given virtual duration, discarding the events of the nodes.
+    pub fn run_for(&mut self, duration: Duration) {
+        self.run_until(duration, |_, _| false);
+    }
+
+    /// Runs the simulation until `f` returns `true` for an event produced by a node, or until
+    /// the given virtual duration has elapsed.
+    ///
+    /// Returns `true` if `f` has returned `true`. The simulation can be continued afterwards.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a node produces an error.
+    pub fn run_until<F>(&mut self, limit: Duration, mut f: F) -> bool
+    where
+        F: FnMut(NodeId, T::Item) -> bool,
+    {
+        let deadline = self.elapsed() + limit;
+        let _enter = self.enter();
+
+        loop {
+            // Poll the nodes and tasks that have been woken up, until they are all idle.
+            self.spawn_pending();
+            while let Some(index) = self.ready.next() {
+                match &mut self.tasks[index] {
+                    Slot::Node(node, id, finished) => while !*finished {
+                        match node.poll_stream_notify(&self.ready, index) {
+                            Ok(Async::Ready(Some(event))) => if f(*id, event) {
+                                // The node may have more events to produce.
+                                self.ready.wake(index);
+                                self.spawn_pending();
+                                return true
+                            },
+                            Ok(Async::Ready(None)) => *finished = true,
+                            Ok(Async::NotReady) => break,
+                            Err(err) => panic!("{} failed: {:?}", id, err),
+                        }
+                    },
+                    Slot::Task(task) => match task.poll_future_notify(&self.ready, index) {
+                        Ok(Async::NotReady) => {}
+                        Ok(Async::Ready(())) | Err(()) => self.tasks[index] = Slot::Finished,
+                    },
+                    Slot::Finished => {}
+                }
+                self.spawn_pending();
+            }
+
+            // Fire the timers whose deadline has been reached, and deliver the packets that
+            // have arrived.
+            let now = self.elapsed();
+            match self.timer.turn(Some(NO_TIMER)) {
+                Ok(_) => {}
+                Err(err) => void::unreachable(err),
+            }
+            let next_timer = self.timer.get_park_mut().take()
+                .filter(|wait| *wait != NO_TIMER)
+                .map(|wait| now + wait);
+            let next_packet = {
+                let mut network = self.network.lock();
+                network.deliver();
+                network.next_event()
+            };
+            if !self.ready.is_empty() {
+                continue
+            }
+
+            // Everything is idle: jump to the next instant at which something happens.
+            let next = match (next_timer, next_packet) {
+                (Some(a), Some(b)) => Some(cmp::min(a, b)),
+                (a, b) => a.or(b),
+            };
+            match next {
+                Some(next) if next <= deadline => self.virtual_clock.advance_to(cmp::max(next, now)),
+                _ => {
+                    self.virtual_clock.advance_to(deadline);
+                    return false
+                }
+            }
+        }
+    }
+}
+
+/// Node or task of a [`Simulation`].
+enum Slot<T> {
+    /// A node, which produces events, and whether it has finished.
+    Node(Spawn<T>, NodeId, bool),
+    /// A future spawned on the [`SimExecutor`].
+    Task(Spawn<Compat03As01<UnitError<Task>>>),
+    /// A task that has finished.
+    Finished,
+}
+
+/// Set of the nodes and tasks that need to be polled, which are woken up by their tasks.
+#[derive(Debug, Default)]
+struct Ready(Mutex<BTreeSet<usize>>);
+
+impl Ready {
+    fn wake(&self, index: usize) {
+        self.0.lock().insert(index);
+    }
+
+    /// Returns the node or task with the lowest index that needs to be polled.
+    fn next(&self) -> Option<usize> {
+        let mut ready = self.0.lock();
+        let index = ready.iter().next().cloned();
+        if let Some(index) = index {
+            ready.remove(&index);
+        }
+        index
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0.lock().is_empty()
+    }
+}
+
+impl Notify for Ready {
+    fn notify(&self, index: usize) {
+        self.wake(index)
+    }
+}
@@ -0,0 +1,449 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! State of the simulated network, shared by all the transports of a simulation.
+
+use crate::{NodeId, clock::VirtualClock};
+use bytes::BytesMut;
+use futures::task::Task;
+use rand::{Rng, rngs::StdRng};
+use std::{collections::{BTreeMap, HashMap, HashSet, VecDeque}, io, time::Duration};
+
+/// Characteristics of the link between two nodes.
+///
+/// The default link delivers the data instantly and never loses it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LinkConfig {
+    latency: Duration,
+    jitter: Duration,
+    loss: f64,
+}
+
+impl LinkConfig {
+    /// Sets the time it takes for data to travel over the link.
+    pub fn with_latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    /// Sets the maximum random delay added to the latency of each packet.
+    pub fn with_jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Sets the probability that a packet is lost.
+    ///
+    /// Connections are reliable, so lost packets are retransmitted, after twice the latency plus
+    /// 200 milliseconds. Since the data of a connection is delivered in order, a lost packet
+    /// also delays the packets sent after it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `loss` isn't in the `[0.0, 1.0)` range.
+    pub fn with_loss(mut self, loss: f64) -> Self {
+        assert!((0.0 .. 1.0).contains(&loss), "packet loss must be in the [0.0, 1.0) range");
+        self.loss = loss;
+        self
+    }
+}
+
+/// State of the simulated network.
+pub(crate) struct Network {
+    /// Clock of the simulation.
+    clock: VirtualClock,
+    /// Source of the randomness of the links.
+    rng: StdRng,
+    /// Configuration of the links that haven't been configured individually.
+    default_link: LinkConfig,
+    /// Configuration of individual links, by sender and receiver.
+    links: HashMap<(NodeId, NodeId), LinkConfig>,
+    /// Links that are cut by a partition, by sender and receiver.
+    blocked: HashSet<(NodeId, NodeId)>,
+    /// Active listeners, by port.
+    listeners: BTreeMap<u64, Listener>,
+    /// Dials in progress, by identifier.
+    dials: HashMap<u64, Dial>,
+    /// Open connections, by identifier.
+    connections: HashMap<u64, Connection>,
+    /// Events in flight, ordered by delivery time and then by emission order.
+    events: BTreeMap<(Duration, u64), Event>,
+    /// Identifier of the next dial, connection or event.
+    next_id: u64,
+}
+
+/// A listener of the network.
+struct Listener {
+    /// Node that owns the listener.
+    node: NodeId,
+    /// Connections that have been received but not yet reported.
+    incoming: VecDeque<u64>,
+    /// Task to notify when a connection is received.
+    task: Option<Task>,
+}
+
+/// A connection being opened.
+struct Dial {
+    /// Outcome of the dial, once known.
+    result: Option<io::Result<u64>>,
+    /// Task to notify once the outcome is known.
+    task: Option<Task>,
+}
+
+/// A connection between two nodes. Side 0 is the dialer and side 1 the listener.
+struct Connection {
+    /// Nodes on each side.
+    nodes: [NodeId; 2],
+    /// Data written by each side.
+    pipes: [Pipe; 2],
+    /// Whether the stream of each side has been dropped.
+    dropped: [bool; 2],
+    /// Whether the connection has been reset by a partition.
+    reset: bool,
+}
+
+/// Data flowing in one direction of a connection.
+#[derive(Default)]
+struct Pipe {
+    /// Data delivered to the reader, but not read yet.
+    buffer: BytesMut,
+    /// Whether the writer has closed its side, and the reader has been informed about it.
+    eof: bool,
+    /// Whether the writer has closed its side.
+    write_closed: bool,
+    /// Task of the reader, to notify when data is delivered.
+    reader: Option<Task>,
+    /// Delivery time of the last packet, which the next packets can't overtake.
+    last_delivery: Duration,
+}
+
+/// An event in flight.
+enum Event {
+    /// A dial reaches the listener.
+    Connect { dial: u64, from: NodeId, to: NodeId, port: u64 },
+    /// The acceptance of a connection reaches the dialer.
+    Accept { dial: u64, connection: u64 },
+    /// The refusal of a connection reaches the dialer.
+    Refuse { dial: u64 },
+    /// Data written by a side of a connection reaches the other side.
+    Data { connection: u64, side: usize, data: Vec<u8> },
+    /// The closing of a side of a connection reaches the other side.
+    Close { connection: u64, side: usize },
+}
+
+impl Network {
+    pub(crate) fn new(clock: VirtualClock, rng: StdRng) -> Self {
+        Network {
+            clock,
+            rng,
+            default_link: LinkConfig::default(),
+            links: HashMap::new(),
+            blocked: HashSet::new(),
+            listeners: BTreeMap::new(),
+            dials: HashMap::new(),
+            connections: HashMap::new(),
+            events: BTreeMap::new(),
+            next_id: 0,
+        }
+    }
+
+    pub(crate) fn set_default_link(&mut self, config: LinkConfig) {
+        self.default_link = config;
+    }
+
+    pub(crate) fn set_link(&mut self, from: NodeId, to: NodeId, config: LinkConfig) {
+        self.links.insert((from, to), config);
+    }
+
+    /// Cuts the links between the nodes of `a` and the nodes of `b`, and resets the connections
+    /// between them.
+    pub(crate) fn partition(&mut self, a: &[NodeId], b: &[NodeId]) {
+        for &x in a {
+            for &y in b {
+                self.blocked.insert((x, y));
+                self.blocked.insert((y, x));
+            }
+        }
+
+        for connection in self.connections.values_mut() {
+            if self.blocked.contains(&(connection.nodes[0], connection.nodes[1])) {
+                connection.reset = true;
+                for pipe in &mut connection.pipes {
+                    if let Some(task) = pipe.reader.take() {
+                        task.notify()
+                    }
+                }
+            }
+        }
+    }
+
+    pub(crate) fn heal(&mut self) {
+        self.blocked.clear();
+    }
+
+    /// Returns the delivery time of the next event in flight.
+    pub(crate) fn next_event(&self) -> Option<Duration> {
+        self.events.keys().next().map(|(at, _)| *at)
+    }
+
+    /// Delivers all the events whose delivery time has been reached.
+    pub(crate) fn deliver(&mut self) {
+        let now = self.clock.elapsed();
+        while let Some(key) = self.events.keys().next().cloned() {
+            if key.0 > now {
+                break
+            }
+            let event = self.events.remove(&key).expect("key was just found in the map; QED");
+            self.process(event);
+        }
+    }
+
+    fn process(&mut self, event: Event) {
+        match event {
+            Event::Connect { dial, from, to, port } => {
+                let listening = self.listeners.get(&port).map_or(false, |l| l.node == to);
+                if !listening || self.blocked.contains(&(from, to)) {
+                    let at = self.transit(to, from);
+                    self.schedule(at, Event::Refuse { dial });
+                    return
+                }
+                let connection = self.next_id();
+                self.connections.insert(connection, Connection {
+                    nodes: [from, to],
+                    pipes: Default::default(),
+                    dropped: [false, false],
+                    reset: false,
+                });
+                let listener = self.listeners.get_mut(&port).expect("checked above; QED");
+                listener.incoming.push_back(connection);
+                if let Some(task) = listener.task.take() {
+                    task.notify()
+                }
+                let at = self.transit(to, from);
+                self.schedule(at, Event::Accept { dial, connection });
+            }
+            Event::Accept { dial, connection } => {
+                if let Some(dial) = self.dials.get_mut(&dial) {
+                    dial.result = Some(Ok(connection));
+                    if let Some(task) = dial.task.take() {
+                        task.notify()
+                    }
+                } else {
+                    self.drop_stream(connection, 0)
+                }
+            }
+            Event::Refuse { dial } => {
+                if let Some(dial) = self.dials.get_mut(&dial) {
+                    dial.result = Some(Err(io::ErrorKind::ConnectionRefused.into()));
+                    if let Some(task) = dial.task.take() {
+                        task.notify()
+                    }
+                }
+            }
+            Event::Data { connection, side, data } => {
+                if let Some(connection) = self.connections.get_mut(&connection) {
+                    let pipe = &mut connection.pipes[side];
+                    pipe.buffer.extend_from_slice(&data);
+                    if let Some(task) = pipe.reader.take() {
+                        task.notify()
+                    }
+                }
+            }
+            Event::Close { connection, side } => {
+                if let Some(connection) = self.connections.get_mut(&connection) {
+                    let pipe = &mut connection.pipes[side];
+                    pipe.eof = true;
+                    if let Some(task) = pipe.reader.take() {
+                        task.notify()
+                    }
+                }
+            }
+        }
+    }
+
+    /// Starts listening on the given port on behalf of `node`. A port of 0 picks a free port.
+    pub(crate) fn listen(&mut self, node: NodeId, port: u64) -> io::Result<u64> {
+        let port = if port == 0 {
+            (1 ..).find(|p| !self.listeners.contains_key(p)).expect("not all ports are in use; QED")
+        } else if self.listeners.contains_key(&port) {
+            return Err(io::ErrorKind::AddrInUse.into())
+        } else {
+            port
+        };
+        self.listeners.insert(port, Listener { node, incoming: VecDeque::new(), task: None });
+        Ok(port)
+    }
+
+    /// Returns the next incoming connection of a listener.
+    pub(crate) fn poll_accept(&mut self, port: u64) -> Option<u64> {
+        let listener = self.listeners.get_mut(&port).expect("listeners are alive until dropped; QED");
+        let connection = listener.incoming.pop_front();
+        if connection.is_none() {
+            listener.task = Some(futures::task::current());
+        }
+        connection
+    }
+
+    pub(crate) fn drop_listener(&mut self, port: u64) {
+        if let Some(listener) = self.listeners.remove(&port) {
+            for connection in listener.incoming {
+                self.drop_stream(connection, 1)
+            }
+        }
+    }
+
+    /// Starts dialing the listener on the given port on behalf of `node`.
+    pub(crate) fn dial(&mut self, node: NodeId, port: u64) -> io::Result<u64> {
+        let remote = match self.listeners.get(&port) {
+            Some(listener) => listener.node,
+            None => return Err(io::ErrorKind::ConnectionRefused.into()),
+        };
+        let dial = self.next_id();
+        self.dials.insert(dial, Dial { result: None, task: None });
+        let at = self.transit(node, remote);
+        self.schedule(at, Event::Connect { dial, from: node, to: remote, port });
+        Ok(dial)
+    }
+
+    /// Returns the outcome of a dial, once known.
+    pub(crate) fn poll_dial(&mut self, dial: u64) -> Option<io::Result<u64>> {
+        let state = self.dials.get_mut(&dial).expect("dials are alive until dropped; QED");
+        let result = state.result.take();
+        if result.is_some() {
+            self.dials.remove(&dial);
+        } else {
+            state.task = Some(futures::task::current());
+        }
+        result
+    }
+
+    pub(crate) fn drop_dial(&mut self, dial: u64) {
+        if let Some(Dial { result: Some(Ok(connection)), .. }) = self.dials.remove(&dial) {
+            self.drop_stream(connection, 0)
+        }
+    }
+
+    /// Reads the data received by a side of a connection.
+    pub(crate) fn read(&mut self, connection: u64, side: usize, buf: &mut [u8]) -> io::Result<usize> {
+        let connection = self.connections.get_mut(&connection)
+            .expect("connections are alive until both streams are dropped; QED");
+        if connection.reset {
+            return Err(io::ErrorKind::ConnectionReset.into())
+        }
+        let pipe = &mut connection.pipes[1 - side];
+        if !pipe.buffer.is_empty() {
+            let len = std::cmp::min(buf.len(), pipe.buffer.len());
+            buf[.. len].copy_from_slice(&pipe.buffer.split_to(len));
+            Ok(len)
+        } else if pipe.eof {
+            Ok(0)
+        } else {
+            pipe.reader = Some(futures::task::current());
+            Err(io::ErrorKind::WouldBlock.into())
+        }
+    }
+
+    /// Sends data from a side of a connection to the other side.
+    pub(crate) fn write(&mut self, id: u64, side: usize, buf: &[u8]) -> io::Result<usize> {
+        let (reset, write_closed, from, to) = {
+            let connection = self.connections.get(&id)
+                .expect("connections are alive until both streams are dropped; QED");
+            (connection.reset, connection.pipes[side].write_closed, connection.nodes[side],
+                connection.nodes[1 - side])
+        };
+        if reset {
+            return Err(io::ErrorKind::ConnectionReset.into())
+        }
+        if write_closed {
+            return Err(io::ErrorKind::BrokenPipe.into())
+        }
+        if !buf.is_empty() {
+            let at = self.transit_ordered(id, side, from, to);
+            self.schedule(at, Event::Data { connection: id, side, data: buf.to_vec() });
+        }
+        Ok(buf.len())
+    }
+
+    /// Closes the writing side of a connection.
+    pub(crate) fn shutdown(&mut self, id: u64, side: usize) -> io::Result<()> {
+        let (reset, from, to) = {
+            let connection = self.connections.get_mut(&id)
+                .expect("connections are alive until both streams are dropped; QED");
+            if connection.pipes[side].write_closed {
+                return Ok(())
+            }
+            connection.pipes[side].write_closed = true;
+            (connection.reset, connection.nodes[side], connection.nodes[1 - side])
+        };
+        if reset {
+            return Err(io::ErrorKind::ConnectionReset.into())
+        }
+        let at = self.transit_ordered(id, side, from, to);
+        self.schedule(at, Event::Close { connection: id, side });
+        Ok(())
+    }
+
+    /// Closes a side of a connection whose stream is dropped.
+    pub(crate) fn drop_stream(&mut self, id: u64, side: usize) {
+        let _ = self.shutdown(id, side);
+        let connection = self.connections.get_mut(&id)
+            .expect("connections are alive until both streams are dropped; QED");
+        connection.dropped[side] = true;
+        if connection.dropped[1 - side] {
+            self.connections.remove(&id);
+        }
+    }
+
+    /// Returns the delivery time of a packet sent now from `from` to `to`.
+    fn transit(&mut self, from: NodeId, to: NodeId) -> Duration {
+        let link = self.links.get(&(from, to)).unwrap_or(&self.default_link);
+        let (latency, jitter, loss) = (link.latency, link.jitter, link.loss);
+        let mut delay = latency;
+        if jitter > Duration::from_secs(0) {
+            delay += jitter.mul_f64(self.rng.gen::<f64>());
+        }
+        while loss > 0.0 && self.rng.gen::<f64>() < loss {
+            delay += latency * 2 + Duration::from_millis(200);
+        }
+        self.clock.elapsed() + delay
+    }
+
+    /// Same as `transit`, but for a packet of a connection, which can't overtake the previous
+    /// packets.
+    fn transit_ordered(&mut self, id: u64, side: usize, from: NodeId, to: NodeId) -> Duration {
+        let at = self.transit(from, to);
+        let pipe = &mut self.connections.get_mut(&id)
+            .expect("connections are alive until both streams are dropped; QED")
+            .pipes[side];
+        pipe.last_delivery = std::cmp::max(pipe.last_delivery, at);
+        pipe.last_delivery
+    }
+
+    fn schedule(&mut self, at: Duration, event: Event) {
+        let id = self.next_id();
+        self.events.insert((at, id), event);
+    }
+
+    fn next_id(&mut self) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+}
@@ -0,0 +1,216 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Transport that connects the nodes of a simulation.
+
+use crate::{NodeId, network::Network};
+use futures::{future::{self, FutureResult}, prelude::*};
+use libp2p_core::{
+    compat::Transport01,
+    multiaddr::{Multiaddr, Protocol},
+    transport::{ListenerEvent, TransportError}
+};
+use parking_lot::Mutex;
+use std::{fmt, io, sync::Arc};
+use tokio_io::{AsyncRead, AsyncWrite};
+
+/// Transport of a node of a [`Simulation`](crate::Simulation).
+///
+/// Supports `/memory/N` multiaddresses, like the `MemoryTransport`. The connections go through
+/// the links of the simulation, which delay them according to the configuration of the
+/// simulation.
+#[derive(Clone)]
+pub struct SimTransport {
+    pub(crate) network: Arc<Mutex<Network>>,
+    pub(crate) node: NodeId,
+}
+
+impl fmt::Debug for SimTransport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SimTransport").field("node", &self.node).finish()
+    }
+}
+
+impl Transport01 for SimTransport {
+    type Output = SimStream;
+    type Error = io::Error;
+    type Listener = SimListener;
+    type ListenerUpgrade = FutureResult<SimStream, io::Error>;
+    type Dial = SimDial;
+
+    fn listen_on(self, addr: Multiaddr) -> Result<Self::Listener, TransportError<Self::Error>> {
+        let port = match parse_memory_addr(&addr) {
+            Some(port) => port,
+            None => return Err(TransportError::MultiaddrNotSupported(addr))
+        };
+        let port = self.network.lock().listen(self.node, port).map_err(TransportError::Other)?;
+        Ok(SimListener {
+            network: self.network,
+            port,
+            addr: Protocol::Memory(port).into(),
+            tell_listen_addr: true,
+        })
+    }
+
+    fn dial(self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
+        let port = match parse_memory_addr(&addr) {
+            Some(port) => port,
+            None => return Err(TransportError::MultiaddrNotSupported(addr))
+        };
+        let id = self.network.lock().dial(self.node, port).map_err(TransportError::Other)?;
+        Ok(SimDial { network: self.network, id })
+    }
+}
+
+/// Listener of a [`SimTransport`].
+pub struct SimListener {
+    network: Arc<Mutex<Network>>,
+    /// Port we're listening on.
+    port: u64,
+    /// The address we are listening on.
+    addr: Multiaddr,
+    /// Generate `ListenerEvent::NewAddress` to inform about our listen address.
+    tell_listen_addr: bool,
+}
+
+impl fmt::Debug for SimListener {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SimListener").field("addr", &self.addr).finish()
+    }
+}
+
+impl Stream for SimListener {
+    type Item = ListenerEvent<FutureResult<SimStream, io::Error>>;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        if self.tell_listen_addr {
+            self.tell_listen_addr = false;
+            return Ok(Async::Ready(Some(ListenerEvent::NewAddress(self.addr.clone()))))
+        }
+        let connection = match self.network.lock().poll_accept(self.port) {
+            Some(connection) => connection,
+            None => return Ok(Async::NotReady)
+        };
+        let stream = SimStream { network: self.network.clone(), connection, side: 1 };
+        Ok(Async::Ready(Some(ListenerEvent::Upgrade {
+            upgrade: future::ok(stream),
+            listen_addr: self.addr.clone(),
+            remote_addr: self.addr.clone(),
+        })))
+    }
+}
+
+impl Drop for SimListener {
+    fn drop(&mut self) {
+        self.network.lock().drop_listener(self.port)
+    }
+}
+
+/// Connection of a [`SimTransport`] currently being opened.
+pub struct SimDial {
+    network: Arc<Mutex<Network>>,
+    id: u64,
+}
+
+impl fmt::Debug for SimDial {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SimDial").field("id", &self.id).finish()
+    }
+}
+
+impl Future for SimDial {
+    type Item = SimStream;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        match self.network.lock().poll_dial(self.id) {
+            Some(Ok(connection)) =>
+                Ok(Async::Ready(SimStream { network: self.network.clone(), connection, side: 0 })),
+            Some(Err(err)) => Err(err),
+            None => Ok(Async::NotReady),
+        }
+    }
+}
+
+impl Drop for SimDial {
+    fn drop(&mut self) {
+        self.network.lock().drop_dial(self.id)
+    }
+}
+
+/// Connection established by a [`SimTransport`].
+///
+/// Implements `AsyncRead` and `AsyncWrite`. Writes never block: the data is immediately put in
+/// flight towards the remote.
+pub struct SimStream {
+    network: Arc<Mutex<Network>>,
+    connection: u64,
+    side: usize,
+}
+
+impl fmt::Debug for SimStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SimStream")
+            .field("connection", &self.connection)
+            .field("side", &self.side)
+            .finish()
+    }
+}
+
+impl io::Read for SimStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.network.lock().read(self.connection, self.side, buf)
+    }
+}
+
+impl AsyncRead for SimStream {}
+
+impl io::Write for SimStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.network.lock().write(self.connection, self.side, buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl AsyncWrite for SimStream {
+    fn shutdown(&mut self) -> Poll<(), io::Error> {
+        self.network.lock().shutdown(self.connection, self.side)?;
+        Ok(Async::Ready(()))
+    }
+}
+
+impl Drop for SimStream {
+    fn drop(&mut self) {
+        self.network.lock().drop_stream(self.connection, self.side)
+    }
+}
+
+/// If the address is `/memory/n`, returns the value of `n`.
+fn parse_memory_addr(a: &Multiaddr) -> Option<u64> {
+    let mut iter = a.iter();
+    match (iter.next(), iter.next()) {
+        (Some(Protocol::Memory(port)), None) => Some(port),
+        _ => None
+    }
+}
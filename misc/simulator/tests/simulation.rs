@@ -0,0 +1,254 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use futures03::TryFutureExt as _;
+use libp2p_core::{
+    PeerId, Transport,
+    muxing::StreamMuxerBox,
+    nodes::Substream,
+    transport::boxed::Boxed,
+    upgrade
+};
+use libp2p_mplex::MplexConfig;
+use libp2p_ping::{Ping, PingConfig, PingEvent, PingSuccess};
+use libp2p_secio::SecioConfig;
+use libp2p_simulator::{LinkConfig, NodeId, Simulation};
+use libp2p_swarm::{Swarm, SwarmBuilder};
+use std::{collections::HashSet, io, time::Duration};
+
+type TestSwarm = Swarm<
+    Boxed<(PeerId, StreamMuxerBox), io::Error>,
+    Ping<Substream<StreamMuxerBox>>
+>;
+
+/// Adds a node that pings every second to the simulation, listening on `/memory/<index + 1>`.
+fn add_node(sim: &mut Simulation<TestSwarm>) -> (NodeId, PeerId) {
+    let keypair = sim.keypair();
+    let executor = sim.executor();
+    let peer_id = keypair.public().into_peer_id();
+    let local_peer_id = peer_id.clone();
+    let node = sim.add_node(move |transport| {
+        let transport = transport
+            .with_upgrade(SecioConfig::new(keypair))
+            .and_then(move |out, endpoint| {
+                let peer_id = out.remote_key.into_peer_id();
+                upgrade::apply(out.stream, MplexConfig::new(), endpoint)
+                    .map_ok(move |muxer| (peer_id, StreamMuxerBox::new(muxer)))
+            })
+            .map_err(|err| io::Error::new(io::ErrorKind::Other, err.to_string()))
+            .boxed();
+        let config = PingConfig::new()
+            .with_interval(Duration::from_secs(1))
+            .with_keep_alive(true);
+        SwarmBuilder::new(transport, Ping::new(config), local_peer_id)
+            .executor(Box::new(executor))
+            .build()
+    });
+    let addr = format!("/memory/{}", node.index() + 1).parse().unwrap();
+    sim.with_node(node, |swarm| Swarm::listen_on(swarm, addr)).unwrap();
+    (node, peer_id)
+}
+
+fn dial(sim: &mut Simulation<TestSwarm>, from: NodeId, to: NodeId) {
+    let addr = format!("/memory/{}", to.index() + 1).parse().unwrap();
+    sim.with_node(from, |swarm| Swarm::dial_addr(swarm, addr)).unwrap();
+}
+
+/// Returns the round-trip time reported by an event, if any.
+fn rtt(event: &PingEvent) -> Option<Duration> {
+    match event.result {
+        Ok(PingSuccess::Ping { rtt }) => Some(rtt),
+        _ => None
+    }
+}
+
+/// Runs a full mesh of nodes over lossy links, and returns the first events.
+fn trace(seed: u64) -> Vec<(Duration, NodeId, PeerId, String)> {
+    let mut sim = Simulation::new(seed);
+    sim.set_default_link(LinkConfig::default()
+        .with_latency(Duration::from_millis(20))
+        .with_jitter(Duration::from_millis(30))
+        .with_loss(0.1));
+
+    let nodes = (0 .. 4).map(|_| add_node(&mut sim).0).collect::<Vec<_>>();
+    for (i, from) in nodes.iter().enumerate() {
+        for to in &nodes[.. i] {
+            dial(&mut sim, *from, *to);
+        }
+    }
+
+    let mut events = Vec::new();
+    while events.len() < 100 {
+        let mut event = None;
+        assert!(sim.run_until(Duration::from_secs(60), |node, e| {
+            event = Some((node, e));
+            true
+        }));
+        let (node, e) = event.unwrap();
+        events.push((sim.elapsed(), node, e.peer, format!("{:?}", e.result)));
+    }
+    events
+}
+
+#[test]
+fn same_seed_same_events() {
+    assert_eq!(trace(1), trace(1));
+    assert_ne!(trace(1), trace(2));
+}
+
+#[test]
+fn rtt_follows_links() {
+    let mut sim = Simulation::new(0);
+    let (a, _) = add_node(&mut sim);
+    let (b, _) = add_node(&mut sim);
+    let (c, peer_c) = add_node(&mut sim);
+    sim.set_link(a, b, LinkConfig::default().with_latency(Duration::from_millis(10)));
+    sim.set_link(a, c, LinkConfig::default().with_latency(Duration::from_millis(250)));
+    dial(&mut sim, b, a);
+    dial(&mut sim, c, a);
+
+    let mut rtts = Vec::new();
+    sim.run_until(Duration::from_secs(60), |node, event| {
+        if let Some(rtt) = rtt(&event) {
+            rtts.push((node == c || event.peer == peer_c, rtt));
+        }
+        rtts.len() == 8
+    });
+    assert_eq!(rtts.len(), 8);
+    for (with_c, rtt) in rtts {
+        if with_c {
+            assert_eq!(rtt, Duration::from_millis(500));
+        } else {
+            assert_eq!(rtt, Duration::from_millis(20));
+        }
+    }
+}
+
+#[test]
+fn loss_delays_packets() {
+    let mut sim = Simulation::new(0);
+    sim.set_default_link(LinkConfig::default()
+        .with_latency(Duration::from_millis(10))
+        .with_loss(0.3));
+    let (a, _) = add_node(&mut sim);
+    let (b, _) = add_node(&mut sim);
+    dial(&mut sim, b, a);
+
+    let mut rtts = Vec::new();
+    sim.run_until(Duration::from_secs(600), |_, event| {
+        rtts.extend(rtt(&event));
+        rtts.len() == 50
+    });
+    assert_eq!(rtts.len(), 50);
+    // A lost packet is retransmitted after 220ms.
+    assert!(rtts.iter().all(|rtt| *rtt >= Duration::from_millis(20)));
+    assert!(rtts.iter().any(|rtt| *rtt == Duration::from_millis(20)));
+    assert!(rtts.iter().any(|rtt| *rtt >= Duration::from_millis(240)));
+}
+
+#[test]
+fn virtual_time() {
+    let mut sim = Simulation::new(0);
+    sim.set_default_link(LinkConfig::default().with_latency(Duration::from_millis(100)));
+    let (a, _) = add_node(&mut sim);
+    let (b, _) = add_node(&mut sim);
+    dial(&mut sim, b, a);
+
+    // Pinging every second for an hour, which includes opening a substream and a round-trip.
+    let mut pings = 0;
+    let hour = Duration::from_secs(3600);
+    assert!(!sim.run_until(hour, |node, event| {
+        if node == b && rtt(&event).is_some() {
+            pings += 1;
+        }
+        false
+    }));
+    assert_eq!(sim.elapsed(), hour);
+    assert!(pings > 3600 / 2 && pings < 3600, "{} pings", pings);
+}
+
+#[test]
+fn partitions() {
+    let mut sim = Simulation::new(0);
+    let (a, peer_a) = add_node(&mut sim);
+    let (b, _) = add_node(&mut sim);
+    dial(&mut sim, b, a);
+    assert!(sim.run_until(Duration::from_secs(10), |_, event| rtt(&event).is_some()));
+
+    // The connection is reset, and can't be opened again until the partition heals.
+    sim.partition(&[a], &[b]);
+    sim.run_for(Duration::from_secs(1));
+    assert!(sim.with_node(b, |swarm| Swarm::connection_info(swarm, &peer_a)).is_none());
+    dial(&mut sim, b, a);
+    sim.run_for(Duration::from_secs(10));
+    assert!(sim.with_node(b, |swarm| Swarm::connection_info(swarm, &peer_a)).is_none());
+
+    sim.heal();
+    dial(&mut sim, b, a);
+    assert!(sim.run_until(Duration::from_secs(10), |_, event| rtt(&event).is_some()));
+    assert!(sim.with_node(b, |swarm| Swarm::connection_info(swarm, &peer_a)).is_some());
+}
+
+
+/// Runs a ring of `num` nodes, each dialing the next one, and returns the successful pings
+/// during the given virtual duration.
+fn ring(seed: u64, num: usize, duration: Duration) -> Vec<(Duration, NodeId, PeerId)> {
+    let mut sim = Simulation::new(seed);
+    sim.set_default_link(LinkConfig::default()
+        .with_latency(Duration::from_millis(20))
+        .with_jitter(Duration::from_millis(30))
+        .with_loss(0.01));
+
+    let nodes = (0 .. num).map(|_| add_node(&mut sim).0).collect::<Vec<_>>();
+    for (i, from) in nodes.iter().enumerate() {
+        dial(&mut sim, *from, nodes[(i + 1) % num]);
+    }
+
+    let mut pings = Vec::new();
+    while sim.elapsed() < duration {
+        let mut ping = None;
+        let found = sim.run_until(duration - sim.elapsed(), |node, e| {
+            if rtt(&e).is_some() {
+                ping = Some((node, e.peer));
+                true
+            } else {
+                false
+            }
+        });
+        if !found {
+            break
+        }
+        let (node, peer) = ping.unwrap();
+        pings.push((sim.elapsed(), node, peer));
+    }
+    pings
+}
+
+#[test]
+fn many_nodes() {
+    // A few hundred nodes run in a single thread, and the simulation stays reproducible.
+    let num = 300;
+    let pings = ring(3, num, Duration::from_secs(5));
+    assert_eq!(pings, ring(3, num, Duration::from_secs(5)));
+
+    // Every node pinged both of its neighbours.
+    let pairs = pings.iter().map(|(_, node, peer)| (*node, peer.clone())).collect::<HashSet<_>>();
+    assert_eq!(pairs.len(), 2 * num);
+}
@@ -26,7 +26,7 @@ use std::sync::{atomic::AtomicUsize, atomic::Ordering, Arc};
 use bytes::Bytes;
 use libp2p_core::{
     Endpoint,
-    StreamMuxer,
+    compat::{Compat01As03, StreamMuxer01},
     upgrade::{InboundUpgrade, OutboundUpgrade, UpgradeInfo, Negotiated}
 };
 use log::{debug, trace};
@@ -160,10 +160,10 @@ where
 {
     type Output = Multiplex<Negotiated<C>>;
     type Error = IoError;
-    type Future = future::FutureResult<Self::Output, IoError>;
+    type Future = Compat01As03<future::FutureResult<Self::Output, IoError>>;
 
     fn upgrade_inbound(self, socket: Negotiated<C>, _: Self::Info) -> Self::Future {
-        future::ok(self.upgrade(socket))
+        Compat01As03::new(future::ok(self.upgrade(socket)))
     }
 }
 
@@ -173,10 +173,10 @@ where
 {
     type Output = Multiplex<Negotiated<C>>;
     type Error = IoError;
-    type Future = future::FutureResult<Self::Output, IoError>;
+    type Future = Compat01As03<future::FutureResult<Self::Output, IoError>>;
 
     fn upgrade_outbound(self, socket: Negotiated<C>, _: Self::Info) -> Self::Future {
-        future::ok(self.upgrade(socket))
+        Compat01As03::new(future::ok(self.upgrade(socket)))
     }
 }
 
@@ -345,7 +345,7 @@ where C: AsyncRead + AsyncWrite
     }
 }
 
-impl<C> StreamMuxer for Multiplex<C>
+impl<C> StreamMuxer01 for Multiplex<C>
 where C: AsyncRead + AsyncWrite
 {
     type Substream = Substream;
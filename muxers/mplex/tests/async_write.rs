@@ -18,7 +18,12 @@
 // FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // DEALINGS IN THE SOFTWARE.
 
-use libp2p_core::{muxing, Transport, transport::ListenerEvent};
+use libp2p_core::{
+    muxing,
+    Transport,
+    compat::{Compat03As01, Transport01},
+    transport::ListenerEvent
+};
 use libp2p_tcp::TcpConfig;
 use futures::prelude::*;
 use std::sync::{Arc, mpsc};
@@ -33,10 +38,9 @@ fn async_write() {
 
     let bg_thread = thread::spawn(move || {
         let transport =
-            TcpConfig::new().with_upgrade(libp2p_mplex::MplexConfig::new());
+            Compat03As01::new(TcpConfig::new().with_upgrade(libp2p_mplex::MplexConfig::new()));
 
-        let mut listener = transport
-            .listen_on("/ip4/127.0.0.1/tcp/0".parse().unwrap())
+        let mut listener = Transport01::listen_on(transport, "/ip4/127.0.0.1/tcp/0".parse().unwrap())
             .unwrap();
 
         let addr = listener.by_ref().wait()
@@ -54,7 +58,7 @@ fn async_write() {
             .map_err(|(err, _)| panic!("{:?}", err))
             .and_then(|(client, _)| client.unwrap().0)
             .map_err(|err| panic!("{:?}", err))
-            .and_then(|client| muxing::outbound_from_ref_and_wrap(Arc::new(client)))
+            .and_then(|client| Compat03As01::new(muxing::outbound_from_ref_and_wrap(Arc::new(client))))
             .and_then(|client| {
                 tokio::io::read_to_end(client, vec![])
             })
@@ -67,13 +71,12 @@ fn async_write() {
         let _ = rt.block_on(future).unwrap();
     });
 
-    let transport = TcpConfig::new().with_upgrade(libp2p_mplex::MplexConfig::new());
+    let transport = Compat03As01::new(TcpConfig::new().with_upgrade(libp2p_mplex::MplexConfig::new()));
 
-    let future = transport
-        .dial(rx.recv().unwrap())
+    let future = Transport01::dial(transport, rx.recv().unwrap())
         .unwrap()
         .map_err(|err| panic!("{:?}", err))
-        .and_then(|client| muxing::inbound_from_ref_and_wrap(Arc::new(client)))
+        .and_then(|client| Compat03As01::new(muxing::inbound_from_ref_and_wrap(Arc::new(client))))
         .and_then(|server| tokio::io::write_all(server, b"hello world"))
         .and_then(|(server, _)| {
             tokio::io::shutdown(server)
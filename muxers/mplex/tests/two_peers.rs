@@ -18,7 +18,12 @@
 // FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // DEALINGS IN THE SOFTWARE.
 
-use libp2p_core::{muxing, Transport, transport::ListenerEvent};
+use libp2p_core::{
+    muxing,
+    Transport,
+    compat::{Compat03As01, Transport01},
+    transport::ListenerEvent
+};
 use libp2p_tcp::TcpConfig;
 use futures::prelude::*;
 use std::sync::{Arc, mpsc};
@@ -36,10 +41,9 @@ fn client_to_server_outbound() {
 
     let bg_thread = thread::spawn(move || {
         let transport =
-            TcpConfig::new().with_upgrade(libp2p_mplex::MplexConfig::new());
+            Compat03As01::new(TcpConfig::new().with_upgrade(libp2p_mplex::MplexConfig::new()));
 
-        let mut listener = transport
-            .listen_on("/ip4/127.0.0.1/tcp/0".parse().unwrap())
+        let mut listener = Transport01::listen_on(transport, "/ip4/127.0.0.1/tcp/0".parse().unwrap())
             .unwrap();
 
         let addr = listener.by_ref().wait()
@@ -57,7 +61,7 @@ fn client_to_server_outbound() {
             .map_err(|(err, _)| panic!("{:?}", err))
             .and_then(|(client, _)| client.unwrap().0)
             .map_err(|err| panic!("{:?}", err))
-            .and_then(|client| muxing::outbound_from_ref_and_wrap(Arc::new(client)))
+            .and_then(|client| Compat03As01::new(muxing::outbound_from_ref_and_wrap(Arc::new(client))))
             .map(|client| Builder::new().new_read(client))
             .and_then(|client| {
                 client
@@ -75,13 +79,12 @@ fn client_to_server_outbound() {
         let _ = rt.block_on(future).unwrap();
     });
 
-    let transport = TcpConfig::new().with_upgrade(libp2p_mplex::MplexConfig::new());
+    let transport = Compat03As01::new(TcpConfig::new().with_upgrade(libp2p_mplex::MplexConfig::new()));
 
-    let future = transport
-        .dial(rx.recv().unwrap())
+    let future = Transport01::dial(transport, rx.recv().unwrap())
         .unwrap()
         .map_err(|err| panic!("{:?}", err))
-        .and_then(|client| muxing::inbound_from_ref_and_wrap(Arc::new(client)))
+        .and_then(|client| Compat03As01::new(muxing::inbound_from_ref_and_wrap(Arc::new(client))))
         .map(|server| Builder::new().new_write(server))
         .and_then(|server| server.send("hello world".into()))
         .map(|_| ());
@@ -99,10 +102,9 @@ fn client_to_server_inbound() {
 
     let bg_thread = thread::spawn(move || {
         let transport =
-            TcpConfig::new().with_upgrade(libp2p_mplex::MplexConfig::new());
+            Compat03As01::new(TcpConfig::new().with_upgrade(libp2p_mplex::MplexConfig::new()));
 
-        let mut listener = transport
-            .listen_on("/ip4/127.0.0.1/tcp/0".parse().unwrap())
+        let mut listener = Transport01::listen_on(transport, "/ip4/127.0.0.1/tcp/0".parse().unwrap())
             .unwrap();
 
         let addr = listener.by_ref().wait()
@@ -121,7 +123,7 @@ fn client_to_server_inbound() {
             .map_err(|(err, _)| panic!("{:?}", err))
             .and_then(|(client, _)| client.unwrap().0)
             .map_err(|err| panic!("{:?}", err))
-            .and_then(|client| muxing::inbound_from_ref_and_wrap(Arc::new(client)))
+            .and_then(|client| Compat03As01::new(muxing::inbound_from_ref_and_wrap(Arc::new(client))))
             .map(|client| Builder::new().new_read(client))
             .and_then(|client| {
                 client
@@ -139,13 +141,12 @@ fn client_to_server_inbound() {
         let _ = rt.block_on(future).unwrap();
     });
 
-    let transport = TcpConfig::new().with_upgrade(libp2p_mplex::MplexConfig::new());
+    let transport = Compat03As01::new(TcpConfig::new().with_upgrade(libp2p_mplex::MplexConfig::new()));
 
-    let future = transport
-        .dial(rx.recv().unwrap())
+    let future = Transport01::dial(transport, rx.recv().unwrap())
         .unwrap()
         .map_err(|err| panic!("{:?}", err))
-        .and_then(|client| muxing::outbound_from_ref_and_wrap(Arc::new(client)))
+        .and_then(|client| Compat03As01::new(muxing::outbound_from_ref_and_wrap(Arc::new(client))))
         .map(|server| Builder::new().new_write(server))
         .and_then(|server| server.send("hello world".into()))
         .map(|_| ());
@@ -22,7 +22,10 @@
 //! [specification](https://github.com/hashicorp/yamux/blob/master/spec.md).
 
 use futures::{future::{self, FutureResult}, prelude::*};
-use libp2p_core::upgrade::{InboundUpgrade, OutboundUpgrade, UpgradeInfo, Negotiated};
+use libp2p_core::{
+    compat::{Compat01As03, StreamMuxer01},
+    upgrade::{InboundUpgrade, OutboundUpgrade, UpgradeInfo, Negotiated}
+};
 use log::debug;
 use std::{io, iter, sync::atomic};
 use std::io::{Error as IoError};
@@ -41,7 +44,7 @@ where
     }
 }
 
-impl<C> libp2p_core::StreamMuxer for Yamux<C>
+impl<C> StreamMuxer01 for Yamux<C>
 where
     C: AsyncRead + AsyncWrite + 'static
 {
@@ -150,10 +153,10 @@ where
 {
     type Output = Yamux<Negotiated<C>>;
     type Error = io::Error;
-    type Future = FutureResult<Yamux<Negotiated<C>>, io::Error>;
+    type Future = Compat01As03<FutureResult<Yamux<Negotiated<C>>, io::Error>>;
 
     fn upgrade_inbound(self, i: Negotiated<C>, _: Self::Info) -> Self::Future {
-        future::ok(Yamux::new(i, self.0, yamux::Mode::Server))
+        Compat01As03::new(future::ok(Yamux::new(i, self.0, yamux::Mode::Server)))
     }
 }
 
@@ -163,10 +166,10 @@ where
 {
     type Output = Yamux<Negotiated<C>>;
     type Error = io::Error;
-    type Future = FutureResult<Yamux<Negotiated<C>>, io::Error>;
+    type Future = Compat01As03<FutureResult<Yamux<Negotiated<C>>, io::Error>>;
 
     fn upgrade_outbound(self, i: Negotiated<C>, _: Self::Info) -> Self::Future {
-        future::ok(Yamux::new(i, self.0, yamux::Mode::Client))
+        Compat01As03::new(future::ok(Yamux::new(i, self.0, yamux::Mode::Client)))
     }
 }
 
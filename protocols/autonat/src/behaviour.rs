@@ -0,0 +1,574 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use crate::protocol::{AutoNatCodec, DialRequest, DialResponse, ResponseError, PROTOCOL_NAME};
+use fnv::{FnvHashMap, FnvHashSet};
+use futures::prelude::*;
+use libp2p_core::{
+    ConnectedPoint,
+    Multiaddr,
+    PeerId,
+    clock,
+    multiaddr::Protocol,
+    nodes::ConnectionId,
+};
+use libp2p_request_response::{
+    RequestId,
+    RequestProtocol,
+    RequestResponse,
+    RequestResponseConfig,
+    RequestResponseEvent,
+    RequestResponseMessage,
+    ResponseChannel,
+};
+use libp2p_swarm::{NetworkBehaviourAction, PollParameters, ProtocolsHandler, compat::NetworkBehaviour01};
+use log::debug;
+use std::{collections::VecDeque, error, iter, net::IpAddr, time::Duration};
+use tokio_io::{AsyncRead, AsyncWrite};
+use wasm_timer::Delay;
+
+/// Configuration for the AutoNAT behaviour.
+#[derive(Debug, Clone)]
+pub struct AutoNatConfig {
+    boot_delay: Duration,
+    retry_interval: Duration,
+    refresh_interval: Duration,
+    max_peers_to_probe: usize,
+    request_timeout: Duration,
+    dial_back_timeout: Duration,
+    max_dial_backs: usize,
+}
+
+impl Default for AutoNatConfig {
+    fn default() -> Self {
+        AutoNatConfig {
+            boot_delay: Duration::from_secs(15),
+            retry_interval: Duration::from_secs(90),
+            refresh_interval: Duration::from_secs(15 * 60),
+            max_peers_to_probe: 3,
+            request_timeout: Duration::from_secs(30),
+            dial_back_timeout: Duration::from_secs(15),
+            max_dial_backs: 16,
+        }
+    }
+}
+
+impl AutoNatConfig {
+    /// Sets the delay before the first probe.
+    ///
+    /// The default is 15 seconds.
+    pub fn set_boot_delay(&mut self, delay: Duration) -> &mut Self {
+        self.boot_delay = delay;
+        self
+    }
+
+    /// Sets the interval between two probes while the NAT status is unknown.
+    ///
+    /// The default is 90 seconds.
+    pub fn set_retry_interval(&mut self, interval: Duration) -> &mut Self {
+        self.retry_interval = interval;
+        self
+    }
+
+    /// Sets the interval between two probes once the NAT status is known.
+    ///
+    /// The default is 15 minutes.
+    pub fn set_refresh_interval(&mut self, interval: Duration) -> &mut Self {
+        self.refresh_interval = interval;
+        self
+    }
+
+    /// Sets the maximum number of connected peers asked to dial us back during a probe.
+    ///
+    /// The default is 3.
+    pub fn set_max_peers_to_probe(&mut self, max: usize) -> &mut Self {
+        self.max_peers_to_probe = max;
+        self
+    }
+
+    /// Sets the timeout of the dial requests, which includes the remote dialing us back.
+    ///
+    /// It should be larger than the dial back timeout of the remotes. The default is 30 seconds.
+    pub fn set_request_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.request_timeout = timeout;
+        self
+    }
+
+    /// Sets how long we try to dial back the addresses of a remote before answering that they
+    /// are not reachable.
+    ///
+    /// The default is 15 seconds.
+    pub fn set_dial_back_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.dial_back_timeout = timeout;
+        self
+    }
+
+    /// Sets the maximum number of remotes we dial back at the same time. Additional requests are
+    /// refused.
+    ///
+    /// The default is 16.
+    pub fn set_max_dial_backs(&mut self, max: usize) -> &mut Self {
+        self.max_dial_backs = max;
+        self
+    }
+}
+
+/// Reachability of the local node, as determined by the remotes dialing us back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NatStatus {
+    /// A remote successfully dialed us back on the given address.
+    Public(Multiaddr),
+    /// The remotes failed to dial us back on any of our addresses.
+    Private,
+    /// No remote has answered yet.
+    Unknown,
+}
+
+impl NatStatus {
+    /// Returns true if the local node is known to be reachable.
+    pub fn is_public(&self) -> bool {
+        match self {
+            NatStatus::Public(_) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Event generated by the `AutoNat` behaviour.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AutoNatEvent {
+    /// The NAT status of the local node has changed after a probe.
+    StatusChanged {
+        /// The previous status.
+        old: NatStatus,
+        /// The new status.
+        new: NatStatus,
+    },
+}
+
+/// Network behaviour that determines whether the local node is reachable, and answers the
+/// requests of remotes wanting to know whether they are.
+///
+/// Periodically, up to `max_peers_to_probe` connected peers are asked to dial back the
+/// candidate addresses of the local node, which are its listened and external addresses. The
+/// addresses that a remote successfully dialed are reported to the `Swarm` as external
+/// addresses.
+///
+/// Conversely, the dial requests of remotes are answered by dialing back the requested
+/// addresses that have the IP address the remote is connected from. Requests from remotes
+/// whose IP address isn't known, such as remotes connected through a relay, are refused.
+pub struct AutoNat<TSubstream> {
+    /// Configuration of the behaviour.
+    config: AutoNatConfig,
+
+    /// The request-response behaviour carrying the dial requests.
+    inner: RequestResponse<TSubstream, AutoNatCodec>,
+
+    /// Current NAT status of the local node.
+    status: NatStatus,
+
+    /// Connected peers, with the remote address of their first connection.
+    connected: FnvHashMap<PeerId, Multiaddr>,
+
+    /// When to start the next probe, if none is in progress.
+    next_probe: Delay,
+
+    /// The probe in progress, if any.
+    probe: Option<Probe>,
+
+    /// Remotes being dialed back.
+    dial_backs: Vec<DialBack>,
+
+    /// Events that need to be yielded to the outside when polling.
+    events: VecDeque<NetworkBehaviourAction<RequestProtocol<AutoNatCodec>, AutoNatEvent>>,
+}
+
+/// A probe of our candidate addresses by connected peers.
+struct Probe {
+    /// The addresses that the peers have been asked to dial.
+    candidates: Vec<Multiaddr>,
+    /// The requests without a response yet.
+    pending: FnvHashSet<RequestId>,
+    /// The addresses dialed back successfully.
+    confirmed: Vec<Multiaddr>,
+    /// Number of peers that failed to dial us back.
+    failures: usize,
+}
+
+/// A remote that we are dialing back.
+struct DialBack {
+    /// The remote that sent the dial request.
+    peer: PeerId,
+    /// The channel on which to answer the request.
+    channel: ResponseChannel<DialResponse>,
+    /// The address being dialed.
+    dialing: Multiaddr,
+    /// The addresses to dial if dialing the current one fails.
+    remaining: VecDeque<Multiaddr>,
+    /// When to give up.
+    deadline: Delay,
+}
+
+impl<TSubstream> AutoNat<TSubstream> {
+    /// Creates a new `AutoNat` behaviour with the given configuration.
+    pub fn new(config: AutoNatConfig) -> Self {
+        let mut inner_config = RequestResponseConfig::default();
+        inner_config.set_request_timeout(config.request_timeout);
+        AutoNat {
+            inner: RequestResponse::new(AutoNatCodec, iter::once(PROTOCOL_NAME), inner_config),
+            status: NatStatus::Unknown,
+            connected: FnvHashMap::default(),
+            next_probe: Delay::new(clock::now() + config.boot_delay),
+            probe: None,
+            dial_backs: Vec::new(),
+            events: VecDeque::new(),
+            config,
+        }
+    }
+
+    /// Returns the current NAT status of the local node.
+    pub fn nat_status(&self) -> &NatStatus {
+        &self.status
+    }
+}
+
+impl<TSubstream> AutoNat<TSubstream>
+where
+    TSubstream: AsyncRead + AsyncWrite + Send + 'static,
+{
+    /// Starts a probe, if we have candidate addresses and peers to ask.
+    fn start_probe(&mut self, params: &mut impl PollParameters) {
+        let mut candidates = Vec::new();
+        for address in params.external_addresses().chain(params.listened_addresses()) {
+            if !is_relayed(&address) && !candidates.contains(&address) {
+                candidates.push(address);
+            }
+        }
+
+        let servers = self.connected.keys()
+            .take(self.config.max_peers_to_probe)
+            .cloned()
+            .collect::<Vec<_>>();
+
+        if candidates.is_empty() || servers.is_empty() {
+            debug!("Postponing probe: {} candidate addresses, {} peers", candidates.len(), servers.len());
+            self.next_probe.reset(clock::now() + self.config.retry_interval);
+            return;
+        }
+
+        let mut pending = FnvHashSet::default();
+        for server in servers {
+            let request = DialRequest {
+                peer_id: params.local_peer_id().clone(),
+                addresses: candidates.clone(),
+            };
+            pending.insert(self.inner.send_request(&server, request));
+        }
+
+        self.probe = Some(Probe { candidates, pending, confirmed: Vec::new(), failures: 0 });
+    }
+
+    /// Records the response, or the absence of response, to a request of the current probe.
+    fn on_probe_result(&mut self, request_id: RequestId, result: Option<Result<Multiaddr, ResponseError>>) {
+        let probe = match &mut self.probe {
+            Some(probe) => probe,
+            None => return,
+        };
+        if !probe.pending.remove(&request_id) {
+            return;
+        }
+
+        match result {
+            Some(Ok(address)) => {
+                if probe.candidates.contains(&address) {
+                    if !probe.confirmed.contains(&address) {
+                        probe.confirmed.push(address);
+                    }
+                } else {
+                    debug!("Ignoring dial back on {}, which we didn't ask for", address);
+                }
+            }
+            Some(Err(ResponseError::DialError)) => probe.failures += 1,
+            Some(Err(err)) => debug!("Probe request refused: {}", err),
+            None => {}
+        }
+
+        if probe.pending.is_empty() {
+            self.finish_probe();
+        }
+    }
+
+    /// Aggregates the results of the current probe into a new NAT status.
+    fn finish_probe(&mut self) {
+        let probe = match self.probe.take() {
+            Some(probe) => probe,
+            None => return,
+        };
+
+        let new_status = if let Some(address) = probe.confirmed.first() {
+            NatStatus::Public(address.clone())
+        } else if probe.failures > 0 {
+            NatStatus::Private
+        } else {
+            self.status.clone()
+        };
+
+        for address in probe.confirmed {
+            self.events.push_back(NetworkBehaviourAction::ReportExternalAddr { address });
+        }
+
+        let interval = match new_status {
+            NatStatus::Unknown => self.config.retry_interval,
+            _ => self.config.refresh_interval,
+        };
+        self.next_probe.reset(clock::now() + interval);
+
+        if new_status != self.status {
+            let old = std::mem::replace(&mut self.status, new_status.clone());
+            self.events.push_back(NetworkBehaviourAction::GenerateEvent(
+                AutoNatEvent::StatusChanged { old, new: new_status }
+            ));
+        }
+    }
+
+    /// Handles a dial request from a remote.
+    fn on_dial_request(&mut self, peer: PeerId, request: DialRequest, channel: ResponseChannel<DialResponse>) {
+        if request.peer_id != peer {
+            self.respond(channel, Err(ResponseError::BadRequest), "Peer id mismatch");
+            return;
+        }
+        if self.dial_backs.len() >= self.config.max_dial_backs
+            || self.dial_backs.iter().any(|d| d.peer == peer)
+        {
+            self.respond(channel, Err(ResponseError::DialRefused), "Too many dial backs");
+            return;
+        }
+
+        // In order not to be used to attack third parties, we only dial the addresses whose IP
+        // address is the one the remote is connected from. If we don't know that IP address,
+        // for example because the remote is connected through a relay, we don't dial at all.
+        let observed_ip = match self.connected.get(&peer).and_then(ip_of) {
+            Some(ip) => ip,
+            None => {
+                self.respond(channel, Err(ResponseError::DialRefused), "Unknown observed IP address");
+                return;
+            }
+        };
+        let mut addresses = request.addresses.into_iter()
+            .filter(|a| !is_relayed(a))
+            .filter(|a| ip_of(a) == Some(observed_ip))
+            .collect::<VecDeque<_>>();
+
+        let dialing = match addresses.pop_front() {
+            Some(address) => address,
+            None => {
+                self.respond(channel, Err(ResponseError::DialRefused), "No dialable address");
+                return;
+            }
+        };
+
+        debug!("Dialing back {} on {}", peer, dialing);
+        self.events.push_back(NetworkBehaviourAction::DialAddress { address: dialing.clone() });
+        self.dial_backs.push(DialBack {
+            peer,
+            channel,
+            dialing,
+            remaining: addresses,
+            deadline: Delay::new(clock::now() + self.config.dial_back_timeout),
+        });
+    }
+
+    /// Handles the outcome of dialing the given address.
+    fn on_dial_back_result(&mut self, address: &Multiaddr, peer: Option<&PeerId>) {
+        let index = match self.dial_backs.iter().position(|d| d.dialing == *address) {
+            Some(index) => index,
+            None => return,
+        };
+
+        if peer == Some(&self.dial_backs[index].peer) {
+            let dial_back = self.dial_backs.remove(index);
+            self.respond(dial_back.channel, Ok(address.clone()), "");
+            return;
+        }
+
+        let next = self.dial_backs[index].remaining.pop_front();
+        match next {
+            Some(next) => {
+                self.dial_backs[index].dialing = next.clone();
+                self.events.push_back(NetworkBehaviourAction::DialAddress { address: next });
+            }
+            None => {
+                let dial_back = self.dial_backs.remove(index);
+                self.respond(dial_back.channel, Err(ResponseError::DialError), "Dial back failed");
+            }
+        }
+    }
+
+    fn respond(&mut self, channel: ResponseChannel<DialResponse>, result: Result<Multiaddr, ResponseError>, text: &str) {
+        let status_text = if text.is_empty() { None } else { Some(text.to_owned()) };
+        if self.inner.send_response(channel, DialResponse { result, status_text }).is_err() {
+            debug!("Dial request timed out before being answered");
+        }
+    }
+}
+
+impl<TSubstream> NetworkBehaviour01 for AutoNat<TSubstream>
+where
+    TSubstream: AsyncRead + AsyncWrite + Send + 'static,
+{
+    type ProtocolsHandler = <RequestResponse<TSubstream, AutoNatCodec> as NetworkBehaviour01>::ProtocolsHandler;
+    type OutEvent = AutoNatEvent;
+
+    fn new_handler(&mut self) -> Self::ProtocolsHandler {
+        self.inner.new_handler()
+    }
+
+    fn addresses_of_peer(&mut self, peer: &PeerId) -> Vec<Multiaddr> {
+        self.inner.addresses_of_peer(peer)
+    }
+
+    fn inject_connected(&mut self, peer: PeerId, endpoint: ConnectedPoint) {
+        let address = match &endpoint {
+            ConnectedPoint::Dialer { address, .. } => address.clone(),
+            ConnectedPoint::Listener { send_back_addr, .. } => send_back_addr.clone(),
+        };
+        self.connected.insert(peer.clone(), address);
+        self.inner.inject_connected(peer, endpoint);
+    }
+
+    fn inject_disconnected(&mut self, peer: &PeerId, endpoint: ConnectedPoint) {
+        self.connected.remove(peer);
+        self.inner.inject_disconnected(peer, endpoint);
+    }
+
+    fn inject_connection_established(&mut self, peer: &PeerId, connection: &ConnectionId, endpoint: &ConnectedPoint) {
+        if let ConnectedPoint::Dialer { address, .. } = endpoint {
+            self.on_dial_back_result(address, Some(peer));
+        }
+        self.inner.inject_connection_established(peer, connection, endpoint);
+    }
+
+    fn inject_connection_closed(&mut self, peer: &PeerId, connection: &ConnectionId, endpoint: &ConnectedPoint) {
+        self.inner.inject_connection_closed(peer, connection, endpoint);
+    }
+
+    fn inject_node_event(
+        &mut self,
+        peer: PeerId,
+        connection: ConnectionId,
+        event: <Self::ProtocolsHandler as ProtocolsHandler>::OutEvent
+    ) {
+        self.inner.inject_node_event(peer, connection, event);
+    }
+
+    fn inject_addr_reach_failure(&mut self, peer: Option<&PeerId>, address: &Multiaddr, error: &dyn error::Error) {
+        self.on_dial_back_result(address, None);
+        self.inner.inject_addr_reach_failure(peer, address, error);
+    }
+
+    fn inject_dial_failure(&mut self, peer: &PeerId) {
+        self.inner.inject_dial_failure(peer);
+    }
+
+    fn poll(&mut self, params: &mut impl PollParameters)
+        -> Async<NetworkBehaviourAction<<Self::ProtocolsHandler as ProtocolsHandler>::InEvent, Self::OutEvent>>
+    {
+        loop {
+            if let Some(event) = self.events.pop_front() {
+                return Async::Ready(event);
+            }
+
+            let mut expired = Vec::new();
+            for dial_back in &mut self.dial_backs {
+                match dial_back.deadline.poll() {
+                    Ok(Async::NotReady) => {}
+                    Ok(Async::Ready(())) | Err(_) => expired.push(dial_back.dialing.clone()),
+                }
+            }
+            for address in expired {
+                if let Some(index) = self.dial_backs.iter().position(|d| d.dialing == address) {
+                    let dial_back = self.dial_backs.remove(index);
+                    self.respond(dial_back.channel, Err(ResponseError::DialError), "Dial back timed out");
+                }
+            }
+
+            if self.probe.is_none() {
+                match self.next_probe.poll() {
+                    Ok(Async::NotReady) => {}
+                    Ok(Async::Ready(())) | Err(_) => self.start_probe(params),
+                }
+            }
+
+            match self.inner.poll(params) {
+                Async::Ready(NetworkBehaviourAction::GenerateEvent(event)) => match event {
+                    RequestResponseEvent::Message {
+                        peer,
+                        message: RequestResponseMessage::Request { request, channel, .. },
+                    } => self.on_dial_request(peer, request, channel),
+                    RequestResponseEvent::Message {
+                        message: RequestResponseMessage::Response { request_id, response }, ..
+                    } => self.on_probe_result(request_id, Some(response.result)),
+                    RequestResponseEvent::OutboundFailure { peer, request_id, error } => {
+                        debug!("Dial request to {} failed: {}", peer, error);
+                        self.on_probe_result(request_id, None);
+                    }
+                    RequestResponseEvent::InboundFailure { peer, error, .. } => {
+                        debug!("Dial request from {} failed: {}", peer, error);
+                    }
+                },
+                Async::Ready(NetworkBehaviourAction::DialAddress { address }) =>
+                    return Async::Ready(NetworkBehaviourAction::DialAddress { address }),
+                Async::Ready(NetworkBehaviourAction::DialAddressAsListener { address }) =>
+                    return Async::Ready(NetworkBehaviourAction::DialAddressAsListener { address }),
+                Async::Ready(NetworkBehaviourAction::DialPeer { peer_id }) =>
+                    return Async::Ready(NetworkBehaviourAction::DialPeer { peer_id }),
+                Async::Ready(NetworkBehaviourAction::NotifyHandler { peer_id, handler, event }) =>
+                    return Async::Ready(NetworkBehaviourAction::NotifyHandler { peer_id, handler, event }),
+                Async::Ready(NetworkBehaviourAction::ReportObservedAddr { address }) =>
+                    return Async::Ready(NetworkBehaviourAction::ReportObservedAddr { address }),
+                Async::Ready(NetworkBehaviourAction::ReportExternalAddr { address }) =>
+                    return Async::Ready(NetworkBehaviourAction::ReportExternalAddr { address }),
+                Async::Ready(NetworkBehaviourAction::ReportExpiredExternalAddr { address }) =>
+                    return Async::Ready(NetworkBehaviourAction::ReportExpiredExternalAddr { address }),
+                Async::Ready(NetworkBehaviourAction::ReportMisbehaviour { peer_id, misbehaviour }) =>
+                    return Async::Ready(NetworkBehaviourAction::ReportMisbehaviour { peer_id, misbehaviour }),
+                Async::NotReady => {
+                    if self.events.is_empty() {
+                        return Async::NotReady;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Returns the IP address of a multiaddress, if it starts with one.
+fn ip_of(address: &Multiaddr) -> Option<IpAddr> {
+    match address.iter().next() {
+        Some(Protocol::Ip4(ip)) => Some(ip.into()),
+        Some(Protocol::Ip6(ip)) => Some(ip.into()),
+        _ => None,
+    }
+}
+
+/// Returns true if the address goes through a relay.
+fn is_relayed(address: &Multiaddr) -> bool {
+    address.iter().any(|p| p == Protocol::P2pCircuit)
+}
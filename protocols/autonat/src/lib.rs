@@ -0,0 +1,38 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Implementation of the AutoNAT protocol, `/libp2p/autonat/1.0.0`, see also the
+//! [spec](https://github.com/libp2p/specs/tree/master/autonat).
+//!
+//! A node learns candidate external addresses from its listeners and from the addresses
+//! observed by its peers, but has no way to know whether they are actually reachable. With
+//! AutoNAT, the node asks a handful of connected peers to dial these addresses back, and
+//! aggregates their answers into a [`NatStatus`].
+//!
+//! The [`AutoNat`] network behaviour plays both roles: it periodically probes the addresses of
+//! the local node, and dials back the addresses of the remotes asking for it. Addresses that
+//! have been successfully dialed back are reported to the `Swarm` as external addresses.
+
+mod behaviour;
+mod protocol;
+mod structs_proto;
+
+pub use self::behaviour::{AutoNat, AutoNatConfig, AutoNatEvent, NatStatus};
+pub use self::protocol::{AutoNatCodec, DialRequest, DialResponse, ResponseError, PROTOCOL_NAME};
@@ -0,0 +1,266 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Messages of the AutoNAT protocol and their encoding.
+//!
+//! A client opens a substream and sends a `DIAL` message containing the addresses it wants to
+//! have checked. The server tries to dial them back and answers with a `DIAL_RESPONSE` message.
+
+use crate::structs_proto;
+use futures::prelude::*;
+use libp2p_core::{Multiaddr, PeerId, upgrade::Negotiated};
+use libp2p_request_response::{
+    CodecFuture,
+    RequestResponseCodec,
+    read_length_prefixed,
+    write_length_prefixed,
+};
+use protobuf::{self, Message};
+use std::{convert::TryFrom, fmt, io};
+use tokio_io::{AsyncRead, AsyncWrite};
+
+/// Name of the AutoNAT protocol.
+pub const PROTOCOL_NAME: &[u8] = b"/libp2p/autonat/1.0.0";
+
+/// Maximum size of a message of the protocol.
+const MAX_MESSAGE_SIZE: usize = 8192;
+
+/// Request to dial back the given addresses of a peer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DialRequest {
+    /// The peer that wants its addresses to be dialed back.
+    pub peer_id: PeerId,
+    /// The addresses to dial.
+    pub addresses: Vec<Multiaddr>,
+}
+
+/// Reason why a server didn't dial back any of the addresses of a `DialRequest`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResponseError {
+    /// None of the addresses could be dialed.
+    DialError,
+    /// The server refused to dial the addresses.
+    DialRefused,
+    /// The request was malformed.
+    BadRequest,
+    /// The server encountered an internal error.
+    InternalError,
+}
+
+impl fmt::Display for ResponseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ResponseError::DialError => write!(f, "Failed to dial back"),
+            ResponseError::DialRefused => write!(f, "Dial back refused"),
+            ResponseError::BadRequest => write!(f, "Bad request"),
+            ResponseError::InternalError => write!(f, "Internal error"),
+        }
+    }
+}
+
+/// Response to a `DialRequest`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DialResponse {
+    /// The address that has been successfully dialed back, or the reason of the failure.
+    pub result: Result<Multiaddr, ResponseError>,
+    /// Optional human-readable description of the result.
+    pub status_text: Option<String>,
+}
+
+impl DialRequest {
+    /// Encodes the request as a protobuf `DIAL` message.
+    pub fn into_bytes(self) -> Vec<u8> {
+        let mut peer = structs_proto::Message_PeerInfo::new();
+        peer.set_id(self.peer_id.into_bytes());
+        for address in self.addresses {
+            peer.mut_addrs().push(address.to_vec());
+        }
+        let mut dial = structs_proto::Message_Dial::new();
+        dial.set_peer(peer);
+        let mut msg = structs_proto::Message::new();
+        msg.set_field_type(structs_proto::Message_MessageType::DIAL);
+        msg.set_dial(dial);
+        msg.write_to_bytes().expect("writing protobuf to a Vec never fails; QED")
+    }
+
+    /// Decodes a protobuf `DIAL` message.
+    ///
+    /// Addresses that can't be parsed are skipped.
+    pub fn from_bytes(bytes: &[u8]) -> Result<DialRequest, io::Error> {
+        let msg: structs_proto::Message = protobuf::parse_from_bytes(bytes)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        if msg.get_field_type() != structs_proto::Message_MessageType::DIAL || !msg.has_dial() {
+            return Err(invalid_data("Expected a DIAL message"));
+        }
+        let peer = msg.get_dial().get_peer();
+        let peer_id = PeerId::from_bytes(peer.get_id().to_vec())
+            .map_err(|_| invalid_data("Invalid peer id"))?;
+        let addresses = peer.get_addrs()
+            .iter()
+            .filter_map(|bytes| Multiaddr::try_from(bytes.clone()).ok())
+            .collect();
+        Ok(DialRequest { peer_id, addresses })
+    }
+}
+
+impl DialResponse {
+    /// Encodes the response as a protobuf `DIAL_RESPONSE` message.
+    pub fn into_bytes(self) -> Vec<u8> {
+        let mut response = structs_proto::Message_DialResponse::new();
+        match self.result {
+            Ok(address) => {
+                response.set_status(structs_proto::Message_ResponseStatus::OK);
+                response.set_addr(address.to_vec());
+            }
+            Err(err) => response.set_status(match err {
+                ResponseError::DialError => structs_proto::Message_ResponseStatus::E_DIAL_ERROR,
+                ResponseError::DialRefused => structs_proto::Message_ResponseStatus::E_DIAL_REFUSED,
+                ResponseError::BadRequest => structs_proto::Message_ResponseStatus::E_BAD_REQUEST,
+                ResponseError::InternalError => structs_proto::Message_ResponseStatus::E_INTERNAL_ERROR,
+            }),
+        }
+        if let Some(text) = self.status_text {
+            response.set_statusText(text);
+        }
+        let mut msg = structs_proto::Message::new();
+        msg.set_field_type(structs_proto::Message_MessageType::DIAL_RESPONSE);
+        msg.set_dialResponse(response);
+        msg.write_to_bytes().expect("writing protobuf to a Vec never fails; QED")
+    }
+
+    /// Decodes a protobuf `DIAL_RESPONSE` message.
+    pub fn from_bytes(bytes: &[u8]) -> Result<DialResponse, io::Error> {
+        let msg: structs_proto::Message = protobuf::parse_from_bytes(bytes)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        if msg.get_field_type() != structs_proto::Message_MessageType::DIAL_RESPONSE
+            || !msg.has_dialResponse()
+        {
+            return Err(invalid_data("Expected a DIAL_RESPONSE message"));
+        }
+        let response = msg.get_dialResponse();
+        let result = match response.get_status() {
+            structs_proto::Message_ResponseStatus::OK => {
+                let address = Multiaddr::try_from(response.get_addr().to_vec())
+                    .map_err(|_| invalid_data("Invalid dialed address"))?;
+                Ok(address)
+            }
+            structs_proto::Message_ResponseStatus::E_DIAL_ERROR => Err(ResponseError::DialError),
+            structs_proto::Message_ResponseStatus::E_DIAL_REFUSED => Err(ResponseError::DialRefused),
+            structs_proto::Message_ResponseStatus::E_BAD_REQUEST => Err(ResponseError::BadRequest),
+            structs_proto::Message_ResponseStatus::E_INTERNAL_ERROR => Err(ResponseError::InternalError),
+        };
+        let status_text = if response.has_statusText() {
+            Some(response.get_statusText().to_owned())
+        } else {
+            None
+        };
+        Ok(DialResponse { result, status_text })
+    }
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Codec of the AutoNAT protocol, used by the request-response handler of `AutoNat`.
+#[derive(Debug, Clone, Default)]
+pub struct AutoNatCodec;
+
+impl RequestResponseCodec for AutoNatCodec {
+    type Protocol = &'static [u8];
+    type Request = DialRequest;
+    type Response = DialResponse;
+
+    fn read_request<T>(&mut self, _: &Self::Protocol, io: Negotiated<T>)
+        -> CodecFuture<(Negotiated<T>, DialRequest)>
+    where
+        T: AsyncRead + Send + 'static,
+    {
+        Box::new(read_length_prefixed(io, MAX_MESSAGE_SIZE)
+            .and_then(|(io, bytes)| Ok((io, DialRequest::from_bytes(&bytes)?))))
+    }
+
+    fn read_response<T>(&mut self, _: &Self::Protocol, io: Negotiated<T>)
+        -> CodecFuture<(Negotiated<T>, DialResponse)>
+    where
+        T: AsyncRead + Send + 'static,
+    {
+        Box::new(read_length_prefixed(io, MAX_MESSAGE_SIZE)
+            .and_then(|(io, bytes)| Ok((io, DialResponse::from_bytes(&bytes)?))))
+    }
+
+    fn write_request<T>(&mut self, _: &Self::Protocol, io: Negotiated<T>, request: DialRequest)
+        -> CodecFuture<Negotiated<T>>
+    where
+        T: AsyncWrite + Send + 'static,
+    {
+        Box::new(write_length_prefixed(io, request.into_bytes()))
+    }
+
+    fn write_response<T>(&mut self, _: &Self::Protocol, io: Negotiated<T>, response: DialResponse)
+        -> CodecFuture<Negotiated<T>>
+    where
+        T: AsyncWrite + Send + 'static,
+    {
+        Box::new(write_length_prefixed(io, response.into_bytes()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dial_request_roundtrip() {
+        let request = DialRequest {
+            peer_id: PeerId::random(),
+            addresses: vec![
+                "/ip4/1.2.3.4/tcp/1234".parse().unwrap(),
+                "/ip6/::1/udp/5678/quic".parse().unwrap(),
+            ],
+        };
+        let decoded = DialRequest::from_bytes(&request.clone().into_bytes()).unwrap();
+        assert_eq!(decoded, request);
+    }
+
+    #[test]
+    fn dial_response_roundtrip() {
+        let responses = vec![
+            DialResponse {
+                result: Ok("/ip4/1.2.3.4/tcp/1234".parse().unwrap()),
+                status_text: None,
+            },
+            DialResponse {
+                result: Err(ResponseError::DialRefused),
+                status_text: Some("too many dial backs".to_owned()),
+            },
+        ];
+        for response in responses {
+            let decoded = DialResponse::from_bytes(&response.clone().into_bytes()).unwrap();
+            assert_eq!(decoded, response);
+        }
+    }
+
+    #[test]
+    fn wrong_message_type_is_refused() {
+        let response = DialResponse { result: Err(ResponseError::DialError), status_text: None };
+        assert!(DialRequest::from_bytes(&response.into_bytes()).is_err());
+    }
+}
@@ -0,0 +1,188 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Probes the reachability of a node through a peer dialing it back over TCP on the loopback
+//! interface.
+
+use futures::{future, prelude::*};
+
+use futures03::TryFutureExt as _;
+use libp2p_autonat::{AutoNat, AutoNatConfig, AutoNatEvent, NatStatus};
+use libp2p_core::{
+    PeerId,
+    Transport,
+    identity,
+    multiaddr::{Multiaddr, Protocol},
+    muxing::StreamMuxerBox,
+    nodes::Substream,
+    transport::{MemoryTransport, boxed::Boxed},
+    upgrade,
+};
+use libp2p_secio::SecioConfig;
+use libp2p_swarm::Swarm;
+use libp2p_tcp::TcpConfig;
+use libp2p_yamux as yamux;
+use std::{io, net::TcpListener, time::{Duration, Instant}};
+use tokio::{runtime::current_thread::Runtime, timer::Delay};
+
+type TestTransport = Boxed<(PeerId, StreamMuxerBox), io::Error>;
+type TestSwarm = Swarm<TestTransport, AutoNat<Substream<StreamMuxerBox>>>;
+
+fn build_swarm() -> TestSwarm {
+    let key = identity::Keypair::generate_ed25519();
+    let peer_id = key.public().into_peer_id();
+    let transport = TcpConfig::new()
+        .or_transport(MemoryTransport::default())
+        .with_upgrade(SecioConfig::new(key))
+        .and_then(move |out, endpoint| {
+            let peer_id = out.remote_key.into_peer_id();
+            let yamux = yamux::Config::default();
+            upgrade::apply(out.stream, yamux, endpoint)
+                .map_ok(|muxer| (peer_id, StreamMuxerBox::new(muxer)))
+        })
+        // Dialing back unreachable addresses is expected to fail.
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))
+        .boxed();
+    let mut config = AutoNatConfig::default();
+    config.set_boot_delay(Duration::from_millis(100));
+    // The first probe may happen before the client is connected to the server.
+    config.set_retry_interval(Duration::from_millis(500));
+    config.set_dial_back_timeout(Duration::from_secs(5));
+    Swarm::new(transport, AutoNat::new(config), peer_id)
+}
+
+fn loopback_addr() -> Multiaddr {
+    "/ip4/127.0.0.1/tcp/0".parse().unwrap()
+}
+
+fn random_memory_addr() -> Multiaddr {
+    Protocol::Memory(1 + rand::random::<u64>() % (u64::max_value() - 1)).into()
+}
+
+/// Connects a client to a server listening on `server_addr`, and polls both until `done`
+/// returns true for an event of the client.
+fn run<T>(mut client: TestSwarm, server_addr: Multiaddr, mut done: impl FnMut(&TestSwarm, Option<AutoNatEvent>) -> Option<T>)
+    -> (TestSwarm, T)
+{
+    let mut server = build_swarm();
+    Swarm::listen_on(&mut server, server_addr).unwrap();
+
+    let mut dialed = false;
+    let mut client = Some(client);
+    let future = future::poll_fn(move || -> Poll<(TestSwarm, T), io::Error> {
+        loop {
+            match server.poll()? {
+                // The server probes its own address through the client as well.
+                Async::Ready(Some(_)) => {}
+                Async::Ready(None) | Async::NotReady => break,
+            }
+        }
+
+        let client_ref = client.as_mut().expect("future polled after completion");
+        if !dialed {
+            match Swarm::listeners(&server).next() {
+                Some(addr) => Swarm::dial_addr(client_ref, addr.clone()).unwrap(),
+                None => return Ok(Async::NotReady),
+            }
+            dialed = true;
+        }
+
+        loop {
+            let event = match client_ref.poll()? {
+                Async::Ready(Some(event)) => Some(event),
+                Async::Ready(None) => panic!("the swarm never ends"),
+                Async::NotReady => None,
+            };
+            let not_ready = event.is_none();
+            if let Some(result) = done(client_ref, event) {
+                return Ok(Async::Ready((client.take().unwrap(), result)));
+            }
+            if not_ready {
+                return Ok(Async::NotReady)
+            }
+        }
+    });
+
+    Runtime::new().unwrap().block_on(future).unwrap()
+}
+
+/// Connects a client to a server and returns the first NAT status change of the client.
+fn probe(client: TestSwarm) -> (TestSwarm, NatStatus) {
+    run(client, loopback_addr(), |_, event| match event {
+        Some(AutoNatEvent::StatusChanged { old, new }) => {
+            assert_eq!(old, NatStatus::Unknown);
+            Some(new)
+        }
+        None => None,
+    })
+}
+
+#[test]
+fn listening_node_is_public() {
+    let mut client = build_swarm();
+    Swarm::listen_on(&mut client, loopback_addr()).unwrap();
+
+    let (client, status) = probe(client);
+
+    let client_addr = match &status {
+        NatStatus::Public(addr) => addr.clone(),
+        status => panic!("unexpected status {:?}", status),
+    };
+    assert!(Swarm::listeners(&client).any(|a| *a == client_addr));
+    assert_eq!(client.nat_status(), &status);
+    assert!(Swarm::external_addresses(&client).any(|a| *a == client_addr));
+}
+
+#[test]
+fn unreachable_node_is_private() {
+    let mut client = build_swarm();
+    // An address nobody listens on.
+    let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let client_addr = format!("/ip4/127.0.0.1/tcp/{}", port).parse().unwrap();
+    Swarm::add_external_address(&mut client, client_addr);
+
+    let (client, status) = probe(client);
+
+    assert_eq!(status, NatStatus::Private);
+    assert_eq!(client.nat_status(), &NatStatus::Private);
+}
+
+#[test]
+fn dial_back_refused_without_observed_ip() {
+    // Over a memory transport, the server doesn't know the IP address of the client, and must
+    // not dial back the addresses the client asks for.
+    let mut client = build_swarm();
+    let client_addr = random_memory_addr();
+    Swarm::listen_on(&mut client, client_addr.clone()).unwrap();
+
+    let mut delay = Delay::new(Instant::now() + Duration::from_secs(2));
+    let (client, ()) = run(client, random_memory_addr(), |_, event| {
+        if let Some(AutoNatEvent::StatusChanged { new, .. }) = event {
+            panic!("unexpected status {:?}", new);
+        }
+        match delay.poll().unwrap() {
+            Async::Ready(()) => Some(()),
+            Async::NotReady => None,
+        }
+    });
+
+    assert_eq!(client.nat_status(), &NatStatus::Unknown);
+    assert!(!Swarm::external_addresses(&client).any(|a| *a == client_addr));
+}
@@ -0,0 +1,526 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use crate::handler::{DcutrHandler, DcutrHandlerEvent, DcutrHandlerIn};
+use crate::protocol::DcutrError;
+use fnv::FnvHashMap;
+use futures::prelude::*;
+use libp2p_core::{
+    ConnectedPoint,
+    Multiaddr,
+    PeerId,
+    address_translation,
+    clock,
+    multiaddr::Protocol,
+    nodes::ConnectionId,
+    upgrade::UpgradeError
+};
+use libp2p_swarm::{
+    NetworkBehaviourAction,
+    NotifyHandler,
+    PollParameters,
+    ProtocolsHandler,
+    ProtocolsHandlerUpgrErr,
+    compat::NetworkBehaviour01
+};
+use log::debug;
+use smallvec::SmallVec;
+use std::{collections::VecDeque, error, fmt, marker::PhantomData};
+use tokio_io::{AsyncRead, AsyncWrite};
+use wasm_timer::Delay;
+
+/// Configuration for the direct connection upgrade behaviour.
+#[derive(Debug, Clone)]
+pub struct DcutrConfig {
+    max_attempts: u8,
+    close_relayed_connection: bool,
+}
+
+impl Default for DcutrConfig {
+    fn default() -> Self {
+        DcutrConfig {
+            max_attempts: 3,
+            close_relayed_connection: true,
+        }
+    }
+}
+
+impl DcutrConfig {
+    /// Sets how many times we try to establish a direct connection with a peer before giving
+    /// up, when we are the initiator of the upgrade.
+    ///
+    /// The default is 3.
+    pub fn set_max_attempts(&mut self, max_attempts: u8) -> &mut Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Sets whether the relayed connections with a peer are closed once a direct connection
+    /// with it has been established.
+    ///
+    /// The default is `true`.
+    pub fn set_close_relayed_connection(&mut self, close: bool) -> &mut Self {
+        self.close_relayed_connection = close;
+        self
+    }
+}
+
+/// Network behaviour that upgrades relayed connections to direct connections, also known as
+/// hole punching.
+///
+/// When a peer connects to us through a relay, we exchange our addresses with it over the
+/// relayed connection, then both of us dial the addresses of the other at the same time. The
+/// two dials cross each other in the NATs of the two peers, which lets them through as if they
+/// were answers to the outgoing connections. With TCP, this requires the transport to dial from
+/// the port it listens on, see `TcpConfig::port_reuse`.
+///
+/// Once a direct connection is established, the relayed connections with the peer are closed.
+pub struct Dcutr<TSubstream> {
+    /// Configuration of the behaviour.
+    config: DcutrConfig,
+
+    /// Relayed connections with each peer.
+    relayed: FnvHashMap<PeerId, SmallVec<[ConnectionId; 2]>>,
+
+    /// Number of direct connections with each peer.
+    direct: FnvHashMap<PeerId, usize>,
+
+    /// Upgrades in progress.
+    upgrades: FnvHashMap<PeerId, Upgrade>,
+
+    /// Exchanges to start or to answer, waiting for the local addresses. The boolean is true if
+    /// we are the initiator.
+    to_notify: VecDeque<(PeerId, ConnectionId, bool)>,
+
+    /// Events that need to be yielded to the outside when polling.
+    events: VecDeque<NetworkBehaviourAction<DcutrHandlerIn, DcutrEvent>>,
+
+    /// Pin the `TSubstream` generic.
+    marker: PhantomData<TSubstream>,
+}
+
+/// Upgrade of the connection with a peer.
+struct Upgrade {
+    /// The relayed connection the exchange of addresses happens on.
+    connection: ConnectionId,
+    /// Whether we initiated the upgrade.
+    initiator: bool,
+    /// Number of attempts made so far.
+    attempts: u8,
+    /// Addresses of the remote that we will dial once the delay elapses.
+    dial_at: Option<(Delay, Vec<Multiaddr>)>,
+    /// Addresses of the remote currently being dialed.
+    dialing: SmallVec<[Multiaddr; 4]>,
+}
+
+impl<TSubstream> Dcutr<TSubstream> {
+    /// Creates a `Dcutr` with the given configuration.
+    pub fn new(config: DcutrConfig) -> Self {
+        Dcutr {
+            config,
+            relayed: FnvHashMap::default(),
+            direct: FnvHashMap::default(),
+            upgrades: FnvHashMap::default(),
+            to_notify: VecDeque::new(),
+            events: VecDeque::new(),
+            marker: PhantomData,
+        }
+    }
+
+    /// Starts dialing the addresses of a peer.
+    ///
+    /// If both dials cross each other, TCP merges them into a single connection that both of us
+    /// consider outgoing. The initiator of the upgrade then takes the role of the listener, so
+    /// that the upgrades of the connection can be negotiated.
+    fn dial(&mut self, peer_id: &PeerId, addrs: Vec<Multiaddr>) {
+        let upgrade = match self.upgrades.get_mut(peer_id) {
+            Some(upgrade) => upgrade,
+            None => return,
+        };
+        for address in addrs {
+            if !upgrade.dialing.contains(&address) {
+                upgrade.dialing.push(address.clone());
+                self.events.push_back(if upgrade.initiator {
+                    NetworkBehaviourAction::DialAddressAsListener { address }
+                } else {
+                    NetworkBehaviourAction::DialAddress { address }
+                });
+            }
+        }
+    }
+
+    /// Reports that the current attempt to upgrade the connection with a peer failed. Starts a
+    /// new attempt if we are the initiator and have attempts left.
+    fn attempt_failed(&mut self, peer_id: PeerId, error: DcutrFailure) {
+        let upgrade = match self.upgrades.remove(&peer_id) {
+            Some(upgrade) => upgrade,
+            None => return,
+        };
+
+        if !upgrade.initiator {
+            debug!("Direct connection upgrade with {:?} initiated by the remote failed: {:?}", peer_id, error);
+            return;
+        }
+
+        let still_relayed = self.relayed.get(&peer_id)
+            .map_or(false, |connections| connections.contains(&upgrade.connection));
+        if upgrade.attempts < self.config.max_attempts && still_relayed {
+            debug!("Direct connection upgrade with {:?} failed, retrying: {:?}", peer_id, error);
+            self.upgrades.insert(peer_id.clone(), Upgrade {
+                attempts: upgrade.attempts + 1,
+                dial_at: None,
+                dialing: SmallVec::new(),
+                ..upgrade
+            });
+            self.to_notify.push_back((peer_id, upgrade.connection, true));
+            return;
+        }
+
+        self.events.push_back(NetworkBehaviourAction::GenerateEvent(
+            DcutrEvent::DirectConnectionUpgradeFailed { peer_id, error }
+        ));
+    }
+}
+
+impl<TSubstream> NetworkBehaviour01 for Dcutr<TSubstream>
+where
+    TSubstream: AsyncRead + AsyncWrite,
+{
+    type ProtocolsHandler = DcutrHandler<TSubstream>;
+    type OutEvent = DcutrEvent;
+
+    fn new_handler(&mut self) -> Self::ProtocolsHandler {
+        DcutrHandler::new()
+    }
+
+    fn addresses_of_peer(&mut self, _: &PeerId) -> Vec<Multiaddr> {
+        Vec::new()
+    }
+
+    fn inject_connected(&mut self, _: PeerId, _: ConnectedPoint) {}
+
+    fn inject_disconnected(&mut self, _: &PeerId, _: ConnectedPoint) {}
+
+    fn inject_connection_established(&mut self, peer_id: &PeerId, connection: &ConnectionId, endpoint: &ConnectedPoint) {
+        if is_relayed_endpoint(endpoint) {
+            self.relayed.entry(peer_id.clone()).or_default().push(*connection);
+
+            // The peer that received the relayed connection initiates the upgrade, as it is the
+            // one whose addresses the other peer couldn't dial.
+            if endpoint.is_listener() && !self.direct.contains_key(peer_id) && !self.upgrades.contains_key(peer_id) {
+                self.upgrades.insert(peer_id.clone(), Upgrade {
+                    connection: *connection,
+                    initiator: true,
+                    attempts: 1,
+                    dial_at: None,
+                    dialing: SmallVec::new(),
+                });
+                self.to_notify.push_back((peer_id.clone(), *connection, true));
+                self.events.push_back(NetworkBehaviourAction::GenerateEvent(
+                    DcutrEvent::InitiatedDirectConnectionUpgrade { peer_id: peer_id.clone() }
+                ));
+            }
+            return;
+        }
+
+        *self.direct.entry(peer_id.clone()).or_insert(0) += 1;
+        if self.upgrades.remove(peer_id).is_none() {
+            return;
+        }
+
+        let address = match endpoint {
+            ConnectedPoint::Dialer { address, .. } => address.clone(),
+            ConnectedPoint::Listener { send_back_addr, .. } => send_back_addr.clone(),
+        };
+        self.events.push_back(NetworkBehaviourAction::GenerateEvent(
+            DcutrEvent::DirectConnectionUpgradeSucceeded { peer_id: peer_id.clone(), address }
+        ));
+
+        if self.config.close_relayed_connection {
+            for connection in self.relayed.get(peer_id).into_iter().flatten() {
+                self.events.push_back(NetworkBehaviourAction::NotifyHandler {
+                    peer_id: peer_id.clone(),
+                    handler: NotifyHandler::One(*connection),
+                    event: DcutrHandlerIn::Close,
+                });
+            }
+        }
+    }
+
+    fn inject_connection_closed(&mut self, peer_id: &PeerId, connection: &ConnectionId, endpoint: &ConnectedPoint) {
+        if !is_relayed_endpoint(endpoint) {
+            let remove = match self.direct.get_mut(peer_id) {
+                Some(n) => {
+                    *n -= 1;
+                    *n == 0
+                }
+                None => false,
+            };
+            if remove {
+                self.direct.remove(peer_id);
+            }
+            return;
+        }
+
+        if let Some(connections) = self.relayed.get_mut(peer_id) {
+            connections.retain(|c| c != connection);
+            if connections.is_empty() {
+                self.relayed.remove(peer_id);
+            }
+        }
+
+        // Without the relayed connection, the exchange of addresses can't complete. Dials that
+        // are already in progress are still worth waiting for.
+        let interrupted = self.upgrades.get(peer_id).map_or(false, |upgrade| {
+            upgrade.connection == *connection && upgrade.dial_at.is_none() && upgrade.dialing.is_empty()
+        });
+        if interrupted {
+            self.attempt_failed(peer_id.clone(), DcutrFailure::ConnectionClosed);
+        }
+    }
+
+    fn inject_node_event(&mut self, peer_id: PeerId, connection: ConnectionId, event: DcutrHandlerEvent) {
+        match event {
+            DcutrHandlerEvent::InboundConnect { remote_addrs } => {
+                if self.direct.contains_key(&peer_id) {
+                    debug!("Ignoring direct connection upgrade from already directly connected {:?}", peer_id);
+                    return;
+                }
+                self.upgrades.insert(peer_id.clone(), Upgrade {
+                    connection,
+                    initiator: false,
+                    attempts: 1,
+                    dial_at: None,
+                    dialing: SmallVec::new(),
+                });
+                self.to_notify.push_back((peer_id.clone(), connection, false));
+                self.events.push_back(NetworkBehaviourAction::GenerateEvent(
+                    DcutrEvent::RemoteInitiatedDirectConnectionUpgrade { peer_id, remote_addrs }
+                ));
+            }
+            DcutrHandlerEvent::InboundSync { remote_addrs } => {
+                if remote_addrs.is_empty() {
+                    self.attempt_failed(peer_id, DcutrFailure::NoAddresses);
+                } else {
+                    self.dial(&peer_id, remote_addrs);
+                }
+            }
+            DcutrHandlerEvent::InboundFailed(err) => {
+                let err = ProtocolsHandlerUpgrErr::Upgrade(UpgradeError::Apply(err));
+                self.attempt_failed(peer_id, DcutrFailure::Exchange(err));
+            }
+            DcutrHandlerEvent::OutboundSync(connected) => {
+                if connected.remote_addrs.is_empty() {
+                    self.attempt_failed(peer_id, DcutrFailure::NoAddresses);
+                } else if let Some(upgrade) = self.upgrades.get_mut(&peer_id) {
+                    // The remote dials us as soon as it receives the `SYNC` message, which takes
+                    // about half of the round-trip time.
+                    let delay = Delay::new(clock::now() + connected.rtt / 2);
+                    upgrade.dial_at = Some((delay, connected.remote_addrs));
+                }
+            }
+            DcutrHandlerEvent::OutboundFailed(err) => self.attempt_failed(peer_id, DcutrFailure::Exchange(err)),
+        }
+    }
+
+    fn inject_addr_reach_failure(&mut self, _: Option<&PeerId>, addr: &Multiaddr, _: &dyn error::Error) {
+        let failed = self.upgrades.iter_mut().find_map(|(peer_id, upgrade)| {
+            let pos = upgrade.dialing.iter().position(|a| a == addr)?;
+            upgrade.dialing.remove(pos);
+            if upgrade.dialing.is_empty() {
+                Some(peer_id.clone())
+            } else {
+                None
+            }
+        });
+        if let Some(peer_id) = failed {
+            self.attempt_failed(peer_id, DcutrFailure::Dial);
+        }
+    }
+
+    fn poll(
+        &mut self,
+        params: &mut impl PollParameters,
+    ) -> Async<
+        NetworkBehaviourAction<
+            <Self::ProtocolsHandler as ProtocolsHandler>::InEvent,
+            Self::OutEvent,
+        >,
+    > {
+        if !self.to_notify.is_empty() {
+            let obs_addrs = local_addresses(params);
+            for (peer_id, connection, initiator) in self.to_notify.drain(..) {
+                let obs_addrs = obs_addrs.clone();
+                let event = if initiator {
+                    DcutrHandlerIn::Connect { obs_addrs }
+                } else {
+                    DcutrHandlerIn::Accept { obs_addrs }
+                };
+                self.events.push_back(NetworkBehaviourAction::NotifyHandler {
+                    peer_id,
+                    handler: NotifyHandler::One(connection),
+                    event,
+                });
+            }
+        }
+
+        let mut to_dial = Vec::new();
+        for (peer_id, upgrade) in self.upgrades.iter_mut() {
+            let ready = match upgrade.dial_at {
+                Some((ref mut delay, _)) => match delay.poll() {
+                    Ok(Async::NotReady) => false,
+                    Ok(Async::Ready(())) => true,
+                    Err(err) => {
+                        debug!("Timer error while waiting to dial {:?}: {:?}", peer_id, err);
+                        true
+                    }
+                },
+                None => false,
+            };
+            if ready {
+                if let Some((_, addrs)) = upgrade.dial_at.take() {
+                    to_dial.push((peer_id.clone(), addrs));
+                }
+            }
+        }
+        for (peer_id, addrs) in to_dial {
+            self.dial(&peer_id, addrs);
+        }
+
+        if let Some(event) = self.events.pop_front() {
+            return Async::Ready(event);
+        }
+
+        Async::NotReady
+    }
+}
+
+/// Event that can happen on the direct connection upgrade behaviour.
+#[derive(Debug)]
+pub enum DcutrEvent {
+    /// We started upgrading the relayed connection with a peer.
+    InitiatedDirectConnectionUpgrade {
+        /// The peer we are upgrading the connection with.
+        peer_id: PeerId,
+    },
+
+    /// A peer started upgrading its relayed connection with us.
+    RemoteInitiatedDirectConnectionUpgrade {
+        /// The peer upgrading the connection.
+        peer_id: PeerId,
+        /// The addresses of the peer, that we are about to dial.
+        remote_addrs: Vec<Multiaddr>,
+    },
+
+    /// A direct connection has been established with a peer we were only connected to through
+    /// a relay.
+    DirectConnectionUpgradeSucceeded {
+        /// The peer we are now directly connected to.
+        peer_id: PeerId,
+        /// The address of the peer on the direct connection.
+        address: Multiaddr,
+    },
+
+    /// We gave up establishing a direct connection with a peer.
+    ///
+    /// This is only reported by the initiator of the upgrade, after all the attempts failed.
+    DirectConnectionUpgradeFailed {
+        /// The peer we couldn't establish a direct connection with.
+        peer_id: PeerId,
+        /// The error of the last attempt.
+        error: DcutrFailure,
+    },
+}
+
+/// Reason why an attempt to upgrade a relayed connection failed.
+#[derive(Debug)]
+pub enum DcutrFailure {
+    /// The exchange of addresses over the relayed connection failed.
+    Exchange(ProtocolsHandlerUpgrErr<DcutrError>),
+    /// The relayed connection was closed before the exchange of addresses completed.
+    ConnectionClosed,
+    /// The remote didn't send any address we can dial.
+    NoAddresses,
+    /// Dialing all the addresses of the remote failed.
+    Dial,
+}
+
+impl fmt::Display for DcutrFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DcutrFailure::Exchange(err) =>
+                write!(f, "Error while exchanging addresses: {}", err),
+            DcutrFailure::ConnectionClosed =>
+                write!(f, "Relayed connection closed during the exchange of addresses"),
+            DcutrFailure::NoAddresses =>
+                write!(f, "The remote has no address to dial"),
+            DcutrFailure::Dial =>
+                write!(f, "Failed to dial all the addresses of the remote"),
+        }
+    }
+}
+
+impl error::Error for DcutrFailure {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            DcutrFailure::Exchange(err) => Some(err),
+            DcutrFailure::ConnectionClosed => None,
+            DcutrFailure::NoAddresses => None,
+            DcutrFailure::Dial => None,
+        }
+    }
+}
+
+/// Returns the addresses to send to a remote so that it can dial us.
+///
+/// The addresses observed by other peers carry the port of our outgoing connections, which with
+/// port reuse is the port we listen on. We also translate our listen addresses with the
+/// observed IP addresses, in case a listener was added after the observation, and include the
+/// listen addresses themselves, which are reachable by peers of the same network.
+fn local_addresses(params: &impl PollParameters) -> Vec<Multiaddr> {
+    let listened = params.listened_addresses()
+        .filter(|addr| !is_relayed(addr))
+        .collect::<Vec<_>>();
+    let external = params.external_addresses()
+        .filter(|addr| !is_relayed(addr))
+        .collect::<Vec<_>>();
+
+    let translated = external.iter()
+        .flat_map(|observed| listened.iter().filter_map(move |listen| address_translation(listen, observed)));
+
+    let mut addrs = Vec::new();
+    for addr in translated.chain(external.iter().cloned()).chain(listened.iter().cloned()) {
+        if !addrs.contains(&addr) {
+            addrs.push(addr);
+        }
+    }
+    addrs
+}
+
+fn is_relayed(address: &Multiaddr) -> bool {
+    address.iter().any(|p| p == Protocol::P2pCircuit)
+}
+
+fn is_relayed_endpoint(endpoint: &ConnectedPoint) -> bool {
+    match endpoint {
+        ConnectedPoint::Dialer { address, .. } => is_relayed(address),
+        ConnectedPoint::Listener { listen_addr, .. } => is_relayed(listen_addr),
+    }
+}
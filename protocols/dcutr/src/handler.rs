@@ -0,0 +1,230 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use crate::protocol::{AcceptFuture, Connect, Connected, DcutrError, DcutrListen, PendingConnect};
+use futures::prelude::*;
+use libp2p_core::{Multiaddr, clock, upgrade::Negotiated};
+use libp2p_swarm::{
+    KeepAlive,
+    ProtocolsHandlerEvent,
+    ProtocolsHandlerUpgrErr,
+    SubstreamProtocol,
+    compat::ProtocolsHandler01
+};
+use smallvec::SmallVec;
+use std::{error, fmt, time::Duration};
+use tokio_io::{AsyncRead, AsyncWrite};
+
+/// How long a connection is kept alive by the handler when no exchange is in progress.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Protocol handler of the direct connection upgrade on a connection.
+///
+/// Performs the exchanges of addresses requested by the behaviour or by the remote, and closes
+/// the connection when the behaviour reports that the peers have migrated to a direct
+/// connection.
+pub struct DcutrHandler<TSubstream> {
+    /// Queue of events to produce in `poll()`.
+    events_out: SmallVec<[DcutrHandlerEvent; 4]>,
+    /// Queue of outbound exchanges to start.
+    connects_to_open: SmallVec<[Connect; 1]>,
+    /// Number of outbound exchanges requested to the swarm and not finished yet.
+    num_outbound: usize,
+    /// Exchange initiated by the remote, if any.
+    inbound: Option<Inbound<Negotiated<TSubstream>>>,
+    /// Whether the behaviour asked us to close the connection.
+    close: bool,
+    /// Whether the connection should be kept alive.
+    keep_alive: KeepAlive,
+}
+
+/// State of an exchange initiated by the remote.
+enum Inbound<TSocket> {
+    /// Waiting for the behaviour to give us the local addresses.
+    Pending(PendingConnect<TSocket>),
+    /// Answered, waiting for the `SYNC` message.
+    Accepting(AcceptFuture<TSocket>, Vec<Multiaddr>),
+}
+
+impl<TSubstream> DcutrHandler<TSubstream> {
+    /// Builds a new `DcutrHandler`.
+    pub fn new() -> Self {
+        DcutrHandler {
+            events_out: SmallVec::new(),
+            connects_to_open: SmallVec::new(),
+            num_outbound: 0,
+            inbound: None,
+            close: false,
+            keep_alive: KeepAlive::Until(clock::now() + IDLE_TIMEOUT),
+        }
+    }
+}
+
+impl<TSubstream> Default for DcutrHandler<TSubstream> {
+    fn default() -> Self {
+        DcutrHandler::new()
+    }
+}
+
+/// Event that can be sent to a `DcutrHandler`.
+#[derive(Debug, Clone)]
+pub enum DcutrHandlerIn {
+    /// Start an exchange of addresses with the remote, as the initiator.
+    Connect {
+        /// Addresses of the local node.
+        obs_addrs: Vec<Multiaddr>,
+    },
+    /// Answer the exchange started by the remote.
+    Accept {
+        /// Addresses of the local node.
+        obs_addrs: Vec<Multiaddr>,
+    },
+    /// The peers migrated to a direct connection, close this one.
+    Close,
+}
+
+/// Event produced by a `DcutrHandler`.
+#[derive(Debug)]
+pub enum DcutrHandlerEvent {
+    /// The remote started an exchange of addresses. Must be answered with
+    /// `DcutrHandlerIn::Accept`.
+    InboundConnect {
+        /// Addresses of the remote.
+        remote_addrs: Vec<Multiaddr>,
+    },
+    /// The remote sent the `SYNC` message of an exchange it started. Its addresses should be
+    /// dialed immediately.
+    InboundSync {
+        /// Addresses of the remote.
+        remote_addrs: Vec<Multiaddr>,
+    },
+    /// The exchange started by the remote failed.
+    InboundFailed(DcutrError),
+    /// The exchange we started succeeded. The addresses of the remote should be dialed once
+    /// half of the round-trip time has elapsed.
+    OutboundSync(Connected),
+    /// The exchange we started failed.
+    OutboundFailed(ProtocolsHandlerUpgrErr<DcutrError>),
+}
+
+/// Error that closes a connection handled by a `DcutrHandler`.
+#[derive(Debug)]
+pub enum DcutrHandlerError {
+    /// The connection was relayed, and the peers migrated to a direct connection.
+    Migrated,
+}
+
+impl fmt::Display for DcutrHandlerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DcutrHandlerError::Migrated => write!(f, "Connection replaced by a direct connection"),
+        }
+    }
+}
+
+impl error::Error for DcutrHandlerError {}
+
+impl<TSubstream> ProtocolsHandler01 for DcutrHandler<TSubstream>
+where
+    TSubstream: AsyncRead + AsyncWrite,
+{
+    type InEvent = DcutrHandlerIn;
+    type OutEvent = DcutrHandlerEvent;
+    type Error = DcutrHandlerError;
+    type Substream = TSubstream;
+    type InboundProtocol = DcutrListen;
+    type OutboundProtocol = Connect;
+    type OutboundOpenInfo = ();
+
+    fn listen_protocol(&self) -> SubstreamProtocol<Self::InboundProtocol> {
+        SubstreamProtocol::new(DcutrListen::new())
+    }
+
+    fn inject_fully_negotiated_inbound(&mut self, pending: PendingConnect<Negotiated<TSubstream>>) {
+        // A new exchange supersedes the previous one, which the remote gave up on.
+        self.events_out.push(DcutrHandlerEvent::InboundConnect {
+            remote_addrs: pending.remote_addrs().to_vec(),
+        });
+        self.inbound = Some(Inbound::Pending(pending));
+    }
+
+    fn inject_fully_negotiated_outbound(&mut self, connected: Connected, _: Self::OutboundOpenInfo) {
+        self.num_outbound -= 1;
+        self.events_out.push(DcutrHandlerEvent::OutboundSync(connected));
+    }
+
+    fn inject_event(&mut self, event: DcutrHandlerIn) {
+        match event {
+            DcutrHandlerIn::Connect { obs_addrs } => self.connects_to_open.push(Connect { obs_addrs }),
+            DcutrHandlerIn::Accept { obs_addrs } => {
+                if let Some(Inbound::Pending(pending)) = self.inbound.take() {
+                    let remote_addrs = pending.remote_addrs().to_vec();
+                    self.inbound = Some(Inbound::Accepting(pending.accept(&obs_addrs), remote_addrs));
+                }
+            }
+            DcutrHandlerIn::Close => self.close = true,
+        }
+    }
+
+    fn inject_dial_upgrade_error(&mut self, _: Self::OutboundOpenInfo, err: ProtocolsHandlerUpgrErr<DcutrError>) {
+        self.num_outbound -= 1;
+        self.events_out.push(DcutrHandlerEvent::OutboundFailed(err));
+    }
+
+    fn connection_keep_alive(&self) -> KeepAlive {
+        self.keep_alive
+    }
+
+    fn poll(&mut self) -> Poll<ProtocolsHandlerEvent<Connect, (), DcutrHandlerEvent>, DcutrHandlerError> {
+        if self.close {
+            return Err(DcutrHandlerError::Migrated);
+        }
+
+        match self.inbound.take() {
+            Some(Inbound::Accepting(mut accept, remote_addrs)) => match accept.poll() {
+                Ok(Async::NotReady) => self.inbound = Some(Inbound::Accepting(accept, remote_addrs)),
+                Ok(Async::Ready(())) =>
+                    self.events_out.push(DcutrHandlerEvent::InboundSync { remote_addrs }),
+                Err(err) => self.events_out.push(DcutrHandlerEvent::InboundFailed(err)),
+            },
+            inbound => self.inbound = inbound,
+        }
+
+        if self.num_outbound > 0 || !self.connects_to_open.is_empty() || self.inbound.is_some() {
+            self.keep_alive = KeepAlive::Yes;
+        } else if self.keep_alive.is_yes() {
+            self.keep_alive = KeepAlive::Until(clock::now() + IDLE_TIMEOUT);
+        }
+
+        if !self.events_out.is_empty() {
+            return Ok(Async::Ready(ProtocolsHandlerEvent::Custom(self.events_out.remove(0))));
+        }
+
+        if !self.connects_to_open.is_empty() {
+            self.num_outbound += 1;
+            return Ok(Async::Ready(ProtocolsHandlerEvent::OutboundSubstreamRequest {
+                protocol: SubstreamProtocol::new(self.connects_to_open.remove(0)),
+                info: (),
+            }));
+        }
+
+        Ok(Async::NotReady)
+    }
+}
@@ -0,0 +1,36 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Implementation of the direct connection upgrade through relay protocol, `/libp2p/dcutr`,
+//! see also the [spec](https://github.com/libp2p/specs/tree/master/relay/DCUtR.md).
+//!
+//! Two peers behind NATs can't dial each other, but can communicate through a relay they are
+//! both connected to. The [`Dcutr`] network behaviour uses such a relayed connection to
+//! coordinate a simultaneous dial of both peers, which punches holes in their NATs, and then
+//! migrates the peers onto the resulting direct connection.
+
+pub mod protocol;
+
+mod behaviour;
+mod handler;
+mod message_proto;
+
+pub use self::behaviour::{Dcutr, DcutrConfig, DcutrEvent, DcutrFailure};
+pub use self::handler::{DcutrHandler, DcutrHandlerError, DcutrHandlerEvent, DcutrHandlerIn};
@@ -0,0 +1,301 @@
+// This file is generated by rust-protobuf 2.3.0. Do not edit
+// @generated
+
+// https://github.com/Manishearth/rust-clippy/issues/702
+#![allow(unknown_lints)]
+#![allow(clippy::all)]
+
+#![cfg_attr(rustfmt, rustfmt_skip)]
+
+#![allow(box_pointers)]
+#![allow(dead_code)]
+#![allow(missing_docs)]
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+#![allow(non_upper_case_globals)]
+#![allow(trivial_casts)]
+#![allow(unsafe_code)]
+#![allow(unused_imports)]
+#![allow(unused_results)]
+
+use protobuf::Message as Message_imported_for_functions;
+use protobuf::ProtobufEnum as ProtobufEnum_imported_for_functions;
+
+#[derive(PartialEq,Clone,Default)]
+pub struct HolePunch {
+    // message fields
+    field_type: ::std::option::Option<HolePunch_Type>,
+    ObsAddrs: ::protobuf::RepeatedField<::std::vec::Vec<u8>>,
+    // special fields
+    pub unknown_fields: ::protobuf::UnknownFields,
+    pub cached_size: ::protobuf::CachedSize,
+}
+
+impl HolePunch {
+    pub fn new() -> HolePunch {
+        ::std::default::Default::default()
+    }
+
+    // required .holepunch.pb.HolePunch.Type type = 1;
+
+    pub fn clear_field_type(&mut self) {
+        self.field_type = ::std::option::Option::None;
+    }
+
+    pub fn has_field_type(&self) -> bool {
+        self.field_type.is_some()
+    }
+
+    // Param is passed by value, moved
+    pub fn set_field_type(&mut self, v: HolePunch_Type) {
+        self.field_type = ::std::option::Option::Some(v);
+    }
+
+    pub fn get_field_type(&self) -> HolePunch_Type {
+        self.field_type.unwrap_or(HolePunch_Type::CONNECT)
+    }
+
+    // repeated bytes ObsAddrs = 2;
+
+    pub fn clear_ObsAddrs(&mut self) {
+        self.ObsAddrs.clear();
+    }
+
+    // Param is passed by value, moved
+    pub fn set_ObsAddrs(&mut self, v: ::protobuf::RepeatedField<::std::vec::Vec<u8>>) {
+        self.ObsAddrs = v;
+    }
+
+    // Mutable pointer to the field.
+    pub fn mut_ObsAddrs(&mut self) -> &mut ::protobuf::RepeatedField<::std::vec::Vec<u8>> {
+        &mut self.ObsAddrs
+    }
+
+    // Take field
+    pub fn take_ObsAddrs(&mut self) -> ::protobuf::RepeatedField<::std::vec::Vec<u8>> {
+        ::std::mem::replace(&mut self.ObsAddrs, ::protobuf::RepeatedField::new())
+    }
+
+    pub fn get_ObsAddrs(&self) -> &[::std::vec::Vec<u8>] {
+        &self.ObsAddrs
+    }
+}
+
+impl ::protobuf::Message for HolePunch {
+    fn is_initialized(&self) -> bool {
+        if self.field_type.is_none() {
+            return false;
+        }
+        true
+    }
+
+    fn merge_from(&mut self, is: &mut ::protobuf::CodedInputStream<'_>) -> ::protobuf::ProtobufResult<()> {
+        while !is.eof()? {
+            let (field_number, wire_type) = is.read_tag_unpack()?;
+            match field_number {
+                1 => {
+                    ::protobuf::rt::read_proto2_enum_with_unknown_fields_into(wire_type, is, &mut self.field_type, 1, &mut self.unknown_fields)?
+                },
+                2 => {
+                    ::protobuf::rt::read_repeated_bytes_into(wire_type, is, &mut self.ObsAddrs)?;
+                },
+                _ => {
+                    ::protobuf::rt::read_unknown_or_skip_group(field_number, wire_type, is, self.mut_unknown_fields())?;
+                },
+            };
+        }
+        ::std::result::Result::Ok(())
+    }
+
+    // Compute sizes of nested messages
+    #[allow(unused_variables)]
+    fn compute_size(&self) -> u32 {
+        let mut my_size = 0;
+        if let Some(v) = self.field_type {
+            my_size += ::protobuf::rt::enum_size(1, v);
+        }
+        for value in &self.ObsAddrs {
+            my_size += ::protobuf::rt::bytes_size(2, &value);
+        };
+        my_size += ::protobuf::rt::unknown_fields_size(self.get_unknown_fields());
+        self.cached_size.set(my_size);
+        my_size
+    }
+
+    fn write_to_with_cached_sizes(&self, os: &mut ::protobuf::CodedOutputStream<'_>) -> ::protobuf::ProtobufResult<()> {
+        if let Some(v) = self.field_type {
+            os.write_enum(1, v.value())?;
+        }
+        for v in &self.ObsAddrs {
+            os.write_bytes(2, &v)?;
+        };
+        os.write_unknown_fields(self.get_unknown_fields())?;
+        ::std::result::Result::Ok(())
+    }
+
+    fn get_cached_size(&self) -> u32 {
+        self.cached_size.get()
+    }
+
+    fn get_unknown_fields(&self) -> &::protobuf::UnknownFields {
+        &self.unknown_fields
+    }
+
+    fn mut_unknown_fields(&mut self) -> &mut ::protobuf::UnknownFields {
+        &mut self.unknown_fields
+    }
+
+    fn as_any(&self) -> &dyn (::std::any::Any) {
+        self as &dyn (::std::any::Any)
+    }
+    fn as_any_mut(&mut self) -> &mut dyn (::std::any::Any) {
+        self as &mut dyn (::std::any::Any)
+    }
+    fn into_any(self: Box<Self>) -> ::std::boxed::Box<dyn (::std::any::Any)> {
+        self
+    }
+
+    fn descriptor(&self) -> &'static ::protobuf::reflect::MessageDescriptor {
+        Self::descriptor_static()
+    }
+
+    fn new() -> HolePunch {
+        HolePunch::new()
+    }
+
+    fn descriptor_static() -> &'static ::protobuf::reflect::MessageDescriptor {
+        static mut descriptor: ::protobuf::lazy::Lazy<::protobuf::reflect::MessageDescriptor> = ::protobuf::lazy::Lazy {
+            lock: ::protobuf::lazy::ONCE_INIT,
+            ptr: 0 as *const ::protobuf::reflect::MessageDescriptor,
+        };
+        unsafe {
+            descriptor.get(|| {
+                let mut fields = ::std::vec::Vec::new();
+                fields.push(::protobuf::reflect::accessor::make_option_accessor::<_, ::protobuf::types::ProtobufTypeEnum<HolePunch_Type>>(
+                    "type",
+                    |m: &HolePunch| { &m.field_type },
+                    |m: &mut HolePunch| { &mut m.field_type },
+                ));
+                fields.push(::protobuf::reflect::accessor::make_repeated_field_accessor::<_, ::protobuf::types::ProtobufTypeBytes>(
+                    "ObsAddrs",
+                    |m: &HolePunch| { &m.ObsAddrs },
+                    |m: &mut HolePunch| { &mut m.ObsAddrs },
+                ));
+                ::protobuf::reflect::MessageDescriptor::new::<HolePunch>(
+                    "HolePunch",
+                    fields,
+                    file_descriptor_proto()
+                )
+            })
+        }
+    }
+
+    fn default_instance() -> &'static HolePunch {
+        static mut instance: ::protobuf::lazy::Lazy<HolePunch> = ::protobuf::lazy::Lazy {
+            lock: ::protobuf::lazy::ONCE_INIT,
+            ptr: 0 as *const HolePunch,
+        };
+        unsafe {
+            instance.get(HolePunch::new)
+        }
+    }
+}
+
+impl ::protobuf::Clear for HolePunch {
+    fn clear(&mut self) {
+        self.clear_field_type();
+        self.clear_ObsAddrs();
+        self.unknown_fields.clear();
+    }
+}
+
+impl ::std::fmt::Debug for HolePunch {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+        ::protobuf::text_format::fmt(self, f)
+    }
+}
+
+impl ::protobuf::reflect::ProtobufValue for HolePunch {
+    fn as_ref(&self) -> ::protobuf::reflect::ProtobufValueRef<'_> {
+        ::protobuf::reflect::ProtobufValueRef::Message(self)
+    }
+}
+
+#[derive(Clone,PartialEq,Eq,Debug,Hash)]
+pub enum HolePunch_Type {
+    CONNECT = 100,
+    SYNC = 300,
+}
+
+impl ::protobuf::ProtobufEnum for HolePunch_Type {
+    fn value(&self) -> i32 {
+        *self as i32
+    }
+
+    fn from_i32(value: i32) -> ::std::option::Option<HolePunch_Type> {
+        match value {
+            100 => ::std::option::Option::Some(HolePunch_Type::CONNECT),
+            300 => ::std::option::Option::Some(HolePunch_Type::SYNC),
+            _ => ::std::option::Option::None
+        }
+    }
+
+    fn values() -> &'static [Self] {
+        static values: &'static [HolePunch_Type] = &[
+            HolePunch_Type::CONNECT,
+            HolePunch_Type::SYNC,
+        ];
+        values
+    }
+
+    fn enum_descriptor_static() -> &'static ::protobuf::reflect::EnumDescriptor {
+        static mut descriptor: ::protobuf::lazy::Lazy<::protobuf::reflect::EnumDescriptor> = ::protobuf::lazy::Lazy {
+            lock: ::protobuf::lazy::ONCE_INIT,
+            ptr: 0 as *const ::protobuf::reflect::EnumDescriptor,
+        };
+        unsafe {
+            descriptor.get(|| {
+                ::protobuf::reflect::EnumDescriptor::new("HolePunch_Type", file_descriptor_proto())
+            })
+        }
+    }
+}
+
+impl ::std::marker::Copy for HolePunch_Type {
+}
+
+impl ::std::default::Default for HolePunch_Type {
+    fn default() -> Self {
+        HolePunch_Type::CONNECT
+    }
+}
+
+impl ::protobuf::reflect::ProtobufValue for HolePunch_Type {
+    fn as_ref(&self) -> ::protobuf::reflect::ProtobufValueRef<'_> {
+        ::protobuf::reflect::ProtobufValueRef::Enum(self.descriptor())
+    }
+}
+
+static file_descriptor_proto_data: &'static [u8] = b"\
+    \n\rmessage.proto\x12\x0cholepunch.pb\"y\n\tHolePunch\x120\n\x04type\x18\
+    \x01\x20\x02(\x0e2\x1c.holepunch.pb.HolePunch.TypeR\x04type\x12\x1a\n\
+    \x08ObsAddrs\x18\x02\x20\x03(\x0cR\x08ObsAddrs\"\x1e\n\x04Type\x12\x0b\n\
+    \x07CONNECT\x10d\x12\t\n\x04SYNC\x10\xac\x02\
+";
+
+static mut file_descriptor_proto_lazy: ::protobuf::lazy::Lazy<::protobuf::descriptor::FileDescriptorProto> = ::protobuf::lazy::Lazy {
+    lock: ::protobuf::lazy::ONCE_INIT,
+    ptr: 0 as *const ::protobuf::descriptor::FileDescriptorProto,
+};
+
+fn parse_descriptor_proto() -> ::protobuf::descriptor::FileDescriptorProto {
+    ::protobuf::parse_from_bytes(file_descriptor_proto_data).unwrap()
+}
+
+pub fn file_descriptor_proto() -> &'static ::protobuf::descriptor::FileDescriptorProto {
+    unsafe {
+        file_descriptor_proto_lazy.get(|| {
+            parse_descriptor_proto()
+        })
+    }
+}
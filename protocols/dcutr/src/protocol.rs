@@ -0,0 +1,501 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Wire protocol of the direct connection upgrade through relay, `/libp2p/dcutr`.
+//!
+//! The exchange happens on a substream of a relayed connection, and is initiated by the peer
+//! that received the relayed connection. Each message is a length-prefixed protobuf.
+//!
+//! - The initiator sends a `CONNECT` message containing its addresses, and starts a timer.
+//! - The other peer answers with a `CONNECT` message containing its own addresses. On reception,
+//!   the initiator stops its timer, which gives it the round-trip time of the relayed
+//!   connection.
+//! - The initiator sends a `SYNC` message, then waits for half the round-trip time and dials
+//!   the addresses of the other peer. The other peer dials the addresses of the initiator as
+//!   soon as it receives the `SYNC` message, so that both dials happen at about the same time.
+
+use crate::message_proto::{HolePunch, HolePunch_Type};
+use futures::{prelude::*, try_ready};
+use libp2p_core::{
+    Multiaddr,
+    clock,
+    compat::Compat01As03,
+    multiaddr::Protocol,
+    upgrade::{self, InboundUpgrade, Negotiated, OutboundUpgrade, UpgradeInfo}
+};
+use protobuf::{self, Message, ProtobufError};
+use std::{convert::TryFrom, error, fmt, io, iter, mem, time::Duration};
+use tokio_io::{AsyncRead, AsyncWrite, io::{Flush, WriteAll}};
+use unsigned_varint::encode;
+use wasm_timer::Instant;
+
+/// Name of the protocol, as negotiated with multistream-select.
+pub const PROTOCOL_NAME: &[u8] = b"/libp2p/dcutr";
+
+/// Maximum size of a message of the protocol. Messages only contain a few addresses, so this is
+/// plenty.
+const MAX_MESSAGE_SIZE: usize = 4096;
+
+/// Outbound upgrade that starts the exchange of addresses, as the initiator.
+///
+/// On success, the output contains the addresses of the remote and the measured round-trip
+/// time. The `SYNC` message has been sent, and the remote is about to dial us.
+#[derive(Debug, Clone)]
+pub struct Connect {
+    /// Addresses of the local node, to send to the remote.
+    pub obs_addrs: Vec<Multiaddr>,
+}
+
+impl UpgradeInfo for Connect {
+    type Info = &'static [u8];
+    type InfoIter = iter::Once<Self::Info>;
+
+    fn protocol_info(&self) -> Self::InfoIter {
+        iter::once(PROTOCOL_NAME)
+    }
+}
+
+impl<TSocket> OutboundUpgrade<TSocket> for Connect
+where
+    TSocket: AsyncRead + AsyncWrite,
+{
+    type Output = Connected;
+    type Error = DcutrError;
+    type Future = Compat01As03<ConnectFuture<Negotiated<TSocket>>>;
+
+    fn upgrade_outbound(self, socket: Negotiated<TSocket>, _: Self::Info) -> Self::Future {
+        let msg = connect_message(&self.obs_addrs);
+        Compat01As03::new(ConnectFuture {
+            state: ConnectState::SendConnect(SendMessage::new(socket, &msg)),
+            started: clock::now(),
+        })
+    }
+}
+
+/// Outcome of a successful `Connect` upgrade.
+#[derive(Debug, Clone)]
+pub struct Connected {
+    /// Addresses of the remote, to dial once half of `rtt` has elapsed.
+    pub remote_addrs: Vec<Multiaddr>,
+    /// Round-trip time of the connection the exchange happened on.
+    pub rtt: Duration,
+}
+
+/// Future returned by `Connect::upgrade_outbound`.
+pub struct ConnectFuture<TSocket> {
+    state: ConnectState<TSocket>,
+    /// When we started sending the `CONNECT` message.
+    started: Instant,
+}
+
+enum ConnectState<TSocket> {
+    SendConnect(SendMessage<TSocket>),
+    RecvConnect(RecvMessage<TSocket>),
+    SendSync(SendMessage<TSocket>, Connected),
+    Poisoned,
+}
+
+impl<TSocket> Future for ConnectFuture<TSocket>
+where
+    TSocket: AsyncRead + AsyncWrite,
+{
+    type Item = Connected;
+    type Error = DcutrError;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        loop {
+            match mem::replace(&mut self.state, ConnectState::Poisoned) {
+                ConnectState::SendConnect(mut send) => match send.poll()? {
+                    Async::Ready(socket) => self.state = ConnectState::RecvConnect(recv_message(socket)),
+                    Async::NotReady => {
+                        self.state = ConnectState::SendConnect(send);
+                        return Ok(Async::NotReady);
+                    }
+                },
+                ConnectState::RecvConnect(mut recv) => match recv.poll()? {
+                    Async::Ready((socket, mut msg)) => {
+                        if msg.get_field_type() != HolePunch_Type::CONNECT {
+                            return Err(DcutrError::UnexpectedMessage);
+                        }
+                        let connected = Connected {
+                            remote_addrs: addrs_from_proto(&mut msg),
+                            rtt: self.started.elapsed(),
+                        };
+                        let sync = SendMessage::new(socket, &sync_message());
+                        self.state = ConnectState::SendSync(sync, connected);
+                    }
+                    Async::NotReady => {
+                        self.state = ConnectState::RecvConnect(recv);
+                        return Ok(Async::NotReady);
+                    }
+                },
+                ConnectState::SendSync(mut send, connected) => match send.poll()? {
+                    Async::Ready(_) => return Ok(Async::Ready(connected)),
+                    Async::NotReady => {
+                        self.state = ConnectState::SendSync(send, connected);
+                        return Ok(Async::NotReady);
+                    }
+                },
+                ConnectState::Poisoned => panic!("ConnectFuture polled after completion"),
+            }
+        }
+    }
+}
+
+/// Inbound upgrade that receives the `CONNECT` message of an initiator.
+#[derive(Debug, Default, Clone)]
+pub struct DcutrListen {}
+
+impl DcutrListen {
+    /// Builds a new `DcutrListen`.
+    pub fn new() -> Self {
+        DcutrListen {}
+    }
+}
+
+impl UpgradeInfo for DcutrListen {
+    type Info = &'static [u8];
+    type InfoIter = iter::Once<Self::Info>;
+
+    fn protocol_info(&self) -> Self::InfoIter {
+        iter::once(PROTOCOL_NAME)
+    }
+}
+
+impl<TSocket> InboundUpgrade<TSocket> for DcutrListen
+where
+    TSocket: AsyncRead + AsyncWrite,
+{
+    type Output = PendingConnect<Negotiated<TSocket>>;
+    type Error = DcutrError;
+    type Future = Compat01As03<DcutrListenFuture<Negotiated<TSocket>>>;
+
+    fn upgrade_inbound(self, socket: Negotiated<TSocket>, _: Self::Info) -> Self::Future {
+        Compat01As03::new(DcutrListenFuture {
+            inner: recv_message(socket),
+        })
+    }
+}
+
+/// Future returned by `DcutrListen::upgrade_inbound`.
+pub struct DcutrListenFuture<TSocket> {
+    inner: RecvMessage<TSocket>,
+}
+
+impl<TSocket> Future for DcutrListenFuture<TSocket>
+where
+    TSocket: AsyncRead,
+{
+    type Item = PendingConnect<TSocket>;
+    type Error = DcutrError;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        let (socket, mut msg) = try_ready!(self.inner.poll());
+        if msg.get_field_type() != HolePunch_Type::CONNECT {
+            return Err(DcutrError::UnexpectedMessage);
+        }
+        let remote_addrs = addrs_from_proto(&mut msg);
+        Ok(Async::Ready(PendingConnect { socket, remote_addrs }))
+    }
+}
+
+/// A `CONNECT` message received from an initiator, which has to be answered with `accept`.
+pub struct PendingConnect<TSocket> {
+    socket: TSocket,
+    remote_addrs: Vec<Multiaddr>,
+}
+
+impl<TSocket> PendingConnect<TSocket>
+where
+    TSocket: AsyncRead + AsyncWrite,
+{
+    /// Returns the addresses of the initiator.
+    pub fn remote_addrs(&self) -> &[Multiaddr] {
+        &self.remote_addrs
+    }
+
+    /// Answers with our own addresses. The returned future resolves once the `SYNC` message of
+    /// the initiator has been received, at which point the addresses of the initiator should
+    /// be dialed.
+    pub fn accept(self, obs_addrs: &[Multiaddr]) -> AcceptFuture<TSocket> {
+        AcceptFuture {
+            state: AcceptState::SendConnect(SendMessage::new(self.socket, &connect_message(obs_addrs))),
+        }
+    }
+}
+
+/// Future returned by `PendingConnect::accept`.
+pub struct AcceptFuture<TSocket> {
+    state: AcceptState<TSocket>,
+}
+
+enum AcceptState<TSocket> {
+    SendConnect(SendMessage<TSocket>),
+    RecvSync(RecvMessage<TSocket>),
+    Poisoned,
+}
+
+impl<TSocket> Future for AcceptFuture<TSocket>
+where
+    TSocket: AsyncRead + AsyncWrite,
+{
+    type Item = ();
+    type Error = DcutrError;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        loop {
+            match mem::replace(&mut self.state, AcceptState::Poisoned) {
+                AcceptState::SendConnect(mut send) => match send.poll()? {
+                    Async::Ready(socket) => self.state = AcceptState::RecvSync(recv_message(socket)),
+                    Async::NotReady => {
+                        self.state = AcceptState::SendConnect(send);
+                        return Ok(Async::NotReady);
+                    }
+                },
+                AcceptState::RecvSync(mut recv) => match recv.poll()? {
+                    Async::Ready((_, msg)) => {
+                        if msg.get_field_type() != HolePunch_Type::SYNC {
+                            return Err(DcutrError::UnexpectedMessage);
+                        }
+                        return Ok(Async::Ready(()));
+                    }
+                    Async::NotReady => {
+                        self.state = AcceptState::RecvSync(recv);
+                        return Ok(Async::NotReady);
+                    }
+                },
+                AcceptState::Poisoned => panic!("AcceptFuture polled after completion"),
+            }
+        }
+    }
+}
+
+/// Future that writes a message on a socket, flushes it, then yields back the socket.
+struct SendMessage<TSocket> {
+    state: SendState<TSocket>,
+}
+
+enum SendState<TSocket> {
+    Write(WriteAll<TSocket, Vec<u8>>),
+    Flush(Flush<TSocket>),
+    Poisoned,
+}
+
+impl<TSocket> SendMessage<TSocket>
+where
+    TSocket: AsyncWrite,
+{
+    fn new(socket: TSocket, msg: &HolePunch) -> Self {
+        let bytes = msg
+            .write_to_bytes()
+            .expect("there is no situation in which the protobuf message can be invalid");
+        let mut len_buf = encode::usize_buffer();
+        let mut data = encode::usize(bytes.len(), &mut len_buf).to_vec();
+        data.extend_from_slice(&bytes);
+        SendMessage {
+            state: SendState::Write(tokio_io::io::write_all(socket, data)),
+        }
+    }
+}
+
+impl<TSocket> Future for SendMessage<TSocket>
+where
+    TSocket: AsyncWrite,
+{
+    type Item = TSocket;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        loop {
+            match mem::replace(&mut self.state, SendState::Poisoned) {
+                SendState::Write(mut write) => match write.poll()? {
+                    Async::Ready((socket, _)) => self.state = SendState::Flush(tokio_io::io::flush(socket)),
+                    Async::NotReady => {
+                        self.state = SendState::Write(write);
+                        return Ok(Async::NotReady);
+                    }
+                },
+                SendState::Flush(mut flush) => match flush.poll()? {
+                    Async::Ready(socket) => return Ok(Async::Ready(socket)),
+                    Async::NotReady => {
+                        self.state = SendState::Flush(flush);
+                        return Ok(Async::NotReady);
+                    }
+                },
+                SendState::Poisoned => panic!("SendMessage polled after completion"),
+            }
+        }
+    }
+}
+
+/// Future that reads a single message, then yields back the socket and the message.
+type RecvMessage<TSocket> = upgrade::ReadRespond<
+    TSocket,
+    (),
+    fn(TSocket, Vec<u8>, ()) -> Result<(TSocket, HolePunch), DcutrError>
+>;
+
+fn recv_message<TSocket>(socket: TSocket) -> RecvMessage<TSocket>
+where
+    TSocket: AsyncRead,
+{
+    upgrade::read_respond(socket, MAX_MESSAGE_SIZE, (), parse_message as fn(_, _, _) -> _)
+}
+
+fn parse_message<TSocket>(socket: TSocket, bytes: Vec<u8>, _: ()) -> Result<(TSocket, HolePunch), DcutrError> {
+    let msg = protobuf::parse_from_bytes(&bytes)?;
+    Ok((socket, msg))
+}
+
+fn connect_message(obs_addrs: &[Multiaddr]) -> HolePunch {
+    let mut msg = HolePunch::new();
+    msg.set_field_type(HolePunch_Type::CONNECT);
+    msg.set_ObsAddrs(obs_addrs.iter().map(|addr| addr.to_vec()).collect());
+    msg
+}
+
+fn sync_message() -> HolePunch {
+    let mut msg = HolePunch::new();
+    msg.set_field_type(HolePunch_Type::SYNC);
+    msg
+}
+
+/// Extracts the addresses of a `CONNECT` message. Invalid addresses are ignored, and so are
+/// relayed addresses, which are of no use to establish a direct connection.
+fn addrs_from_proto(msg: &mut HolePunch) -> Vec<Multiaddr> {
+    msg.take_ObsAddrs()
+        .into_iter()
+        .filter_map(|addr| Multiaddr::try_from(addr).ok())
+        .filter(|addr| addr.iter().all(|p| p != Protocol::P2pCircuit))
+        .collect()
+}
+
+/// Error that can happen while performing the protocol.
+#[derive(Debug)]
+pub enum DcutrError {
+    /// Error on the socket.
+    Io(io::Error),
+    /// Error when reading the message from the socket.
+    ReadError(upgrade::ReadOneError),
+    /// Error when decoding the raw buffer into a protobuf.
+    ProtobufError(ProtobufError),
+    /// The remote sent a message of the wrong type.
+    UnexpectedMessage,
+}
+
+impl From<io::Error> for DcutrError {
+    #[inline]
+    fn from(err: io::Error) -> Self {
+        DcutrError::Io(err)
+    }
+}
+
+impl From<upgrade::ReadOneError> for DcutrError {
+    #[inline]
+    fn from(err: upgrade::ReadOneError) -> Self {
+        DcutrError::ReadError(err)
+    }
+}
+
+impl From<ProtobufError> for DcutrError {
+    #[inline]
+    fn from(err: ProtobufError) -> Self {
+        DcutrError::ProtobufError(err)
+    }
+}
+
+impl fmt::Display for DcutrError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            DcutrError::Io(ref err) =>
+                write!(f, "I/O error: {}", err),
+            DcutrError::ReadError(ref err) =>
+                write!(f, "Error while reading from socket: {}", err),
+            DcutrError::ProtobufError(ref err) =>
+                write!(f, "Error while decoding protobuf: {}", err),
+            DcutrError::UnexpectedMessage =>
+                write!(f, "Received a message of an unexpected type"),
+        }
+    }
+}
+
+impl error::Error for DcutrError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            DcutrError::Io(ref err) => Some(err),
+            DcutrError::ReadError(ref err) => Some(err),
+            DcutrError::ProtobufError(ref err) => Some(err),
+            DcutrError::UnexpectedMessage => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Connect, DcutrListen};
+    use futures::{Future, Stream};
+    use libp2p_core::{
+        Multiaddr,
+        compat::{Compat03As01, Transport01},
+        multiaddr::Protocol,
+        transport::{ListenerEvent, MemoryTransport},
+        upgrade::{apply_inbound, apply_outbound}
+    };
+    use tokio::runtime::current_thread::Runtime;
+
+    #[test]
+    fn exchange_addresses() {
+        let listen_addr: Multiaddr = Protocol::Memory(rand_port()).into();
+        let initiator_addrs: Vec<Multiaddr> = vec![
+            "/ip4/80.81.82.83/tcp/500".parse().unwrap(),
+            "/ip4/1.2.3.4/tcp/1/p2p-circuit".parse().unwrap(),
+        ];
+        let responder_addrs: Vec<Multiaddr> = vec!["/ip4/10.11.12.13/tcp/600".parse().unwrap()];
+
+        let responder_addrs2 = responder_addrs.clone();
+        let listener = Transport01::listen_on(Compat03As01::new(MemoryTransport::default()), listen_addr.clone())
+            .unwrap()
+            .filter_map(ListenerEvent::into_upgrade)
+            .into_future()
+            .map_err(|(err, _)| panic!("Listener error: {:?}", err))
+            .and_then(|(upgrade, _)| upgrade.expect("Listener closed").0.map_err(|err| panic!("{:?}", err)))
+            .and_then(|socket| Compat03As01::new(apply_inbound(socket, DcutrListen::new())).map_err(|err| panic!("{:?}", err)))
+            .and_then(move |pending| {
+                // The relayed address of the initiator has been filtered out.
+                assert_eq!(pending.remote_addrs(), &["/ip4/80.81.82.83/tcp/500".parse::<Multiaddr>().unwrap()]);
+                pending.accept(&responder_addrs2).map_err(|err| panic!("{:?}", err))
+            });
+
+        let dialer = Transport01::dial(Compat03As01::new(MemoryTransport::default()), listen_addr)
+            .unwrap()
+            .map_err(|err| panic!("{:?}", err))
+            .and_then(move |socket| {
+                Compat03As01::new(apply_outbound(socket, Connect { obs_addrs: initiator_addrs })).map_err(|err| panic!("{:?}", err))
+            });
+
+        let mut rt = Runtime::new().unwrap();
+        let (_, connected) = rt.block_on(listener.join(dialer)).unwrap();
+        assert_eq!(connected.remote_addrs, responder_addrs);
+    }
+
+    fn rand_port() -> u64 {
+        1 + rand::random::<u64>() % (u64::max_value() - 1)
+    }
+}
@@ -0,0 +1,201 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use futures::{future, prelude::*};
+
+use futures03::TryFutureExt as _;
+use libp2p_core::{
+    ConnectedPoint,
+    Endpoint,
+    PeerId,
+    Transport,
+    either::EitherOutput,
+    identity,
+    multiaddr::{Multiaddr, Protocol},
+    muxing::StreamMuxerBox,
+    nodes::Substream,
+    transport::boxed::Boxed,
+    upgrade,
+};
+use libp2p_dcutr::{Dcutr, DcutrConfig, DcutrEvent};
+use libp2p_relay::{Relay, RelayConfig, RelayEvent, RelayTransport};
+use libp2p_secio::SecioConfig;
+use libp2p_swarm::{Swarm, SwarmBuilder, SwarmObserver};
+use libp2p_tcp::TcpConfig;
+use libp2p_yamux as yamux;
+use std::{io, sync::{Arc, Mutex}, time::Duration};
+use tokio::{runtime::current_thread::Runtime, util::FutureExt};
+
+type TestTransport = Boxed<(PeerId, StreamMuxerBox), io::Error>;
+type DcutrSwarm = Swarm<TestTransport, Dcutr<Substream<StreamMuxerBox>>>;
+
+/// Builds a TCP transport that dials from its listen port, upgraded with secio and yamux.
+fn build_tcp_transport(local_key: identity::Keypair) -> TestTransport {
+    TcpConfig::new()
+        .port_reuse(true)
+        .with_upgrade(SecioConfig::new(local_key))
+        .and_then(move |out, endpoint| {
+            let peer_id = out.remote_key.into_peer_id();
+            let yamux = yamux::Config::default();
+            upgrade::apply(out.stream, yamux, endpoint)
+                .map_ok(|muxer| (peer_id, StreamMuxerBox::new(muxer)))
+        })
+        .map_err(|err| io::Error::new(io::ErrorKind::Other, err.to_string()))
+        .boxed()
+}
+
+/// Records the roles taken in the upgrades of the direct connections.
+#[derive(Default)]
+struct DirectConnections(Mutex<Vec<Endpoint>>);
+
+impl DirectConnections {
+    fn roles(&self) -> Vec<Endpoint> {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+impl SwarmObserver for DirectConnections {
+    fn connection_established(&self, _: &PeerId, endpoint: &ConnectedPoint) {
+        let relayed = match endpoint {
+            ConnectedPoint::Dialer { address, .. } => address.iter().any(|p| p == Protocol::P2pCircuit),
+            ConnectedPoint::Listener { listen_addr, .. } => listen_addr.iter().any(|p| p == Protocol::P2pCircuit),
+        };
+        if !relayed {
+            self.0.lock().unwrap().push(endpoint.role());
+        }
+    }
+}
+
+/// Builds a transport that can both dial directly and through a relay.
+fn build_peer() -> (PeerId, DcutrSwarm, Arc<DirectConnections>) {
+    let key = identity::Keypair::generate_ed25519();
+    let peer_id = key.public().into_peer_id();
+    let direct = build_tcp_transport(key.clone());
+    let relayed = RelayTransport::new(peer_id.clone(), direct.clone())
+        .with_upgrade(SecioConfig::new(key))
+        .and_then(move |out, endpoint| {
+            let peer_id = out.remote_key.into_peer_id();
+            let yamux = yamux::Config::default();
+            upgrade::apply(out.stream, yamux, endpoint)
+                .map_ok(|muxer| (peer_id, StreamMuxerBox::new(muxer)))
+        })
+        .map_err(|err| io::Error::new(io::ErrorKind::Other, err.to_string()));
+    let transport = relayed
+        .or_transport(direct)
+        .map(|output, _| match output {
+            EitherOutput::First(output) => output,
+            EitherOutput::Second(output) => output,
+        })
+        .map_err(|err| io::Error::new(io::ErrorKind::Other, err.to_string()))
+        .boxed();
+
+    let direct_connections = Arc::new(DirectConnections::default());
+    let mut swarm = SwarmBuilder::new(transport, Dcutr::new(DcutrConfig::default()), peer_id.clone())
+        .observer(direct_connections.clone())
+        .build();
+    Swarm::listen_on(&mut swarm, "/ip4/127.0.0.1/tcp/0".parse().unwrap()).unwrap();
+    (peer_id, swarm, direct_connections)
+}
+
+#[test]
+fn upgrade_relayed_connection() {
+    let mut rt = Runtime::new().unwrap();
+
+    let relay_key = identity::Keypair::generate_ed25519();
+    let relay_id = relay_key.public().into_peer_id();
+    let mut relay = Swarm::new(
+        build_tcp_transport(relay_key),
+        Relay::new(relay_id.clone(), RelayConfig::default()),
+        relay_id.clone()
+    );
+    Swarm::listen_on(&mut relay, "/ip4/127.0.0.1/tcp/0".parse().unwrap()).unwrap();
+    let relay_addr = rt.block_on(future::poll_fn(|| -> Poll<Multiaddr, ()> {
+        let _ = relay.poll();
+        Ok(Swarm::listeners(&relay).next().cloned().map_or(Async::NotReady, Async::Ready))
+    })).unwrap();
+    let circuit_addr = relay_addr
+        .with(Protocol::P2p(relay_id.into()))
+        .with(Protocol::P2pCircuit);
+
+    let (a_id, mut a, a_direct) = build_peer();
+    let (b_id, mut b, b_direct) = build_peer();
+    Swarm::listen_on(&mut b, circuit_addr.clone()).unwrap();
+    let dial_addr = circuit_addr.with(Protocol::P2p(b_id.clone().into()));
+
+    let mut dialed = false;
+    let mut a_upgraded = false;
+    let mut b_upgraded = false;
+    let mut circuit_closed = false;
+    let test = future::poll_fn(move || -> Poll<(), ()> {
+        while let Async::Ready(Some(event)) = relay.poll().unwrap() {
+            if let RelayEvent::CircuitClosed { .. } = event {
+                circuit_closed = true;
+            }
+        }
+
+        // Dial B through the relay once it is connected to the relay.
+        if !dialed && Swarm::connection_info(&mut relay, &b_id).is_some() {
+            Swarm::dial_addr(&mut a, dial_addr.clone()).unwrap();
+            dialed = true;
+        }
+
+        while let Async::Ready(Some(event)) = a.poll().unwrap() {
+            match event {
+                DcutrEvent::RemoteInitiatedDirectConnectionUpgrade { peer_id, remote_addrs } => {
+                    assert_eq!(peer_id, b_id);
+                    assert!(!remote_addrs.is_empty());
+                }
+                DcutrEvent::DirectConnectionUpgradeSucceeded { peer_id, .. } => {
+                    assert_eq!(peer_id, b_id);
+                    a_upgraded = true;
+                }
+                event => panic!("Unexpected event on A: {:?}", event),
+            }
+        }
+
+        while let Async::Ready(Some(event)) = b.poll().unwrap() {
+            match event {
+                DcutrEvent::InitiatedDirectConnectionUpgrade { peer_id } => assert_eq!(peer_id, a_id),
+                DcutrEvent::DirectConnectionUpgradeSucceeded { peer_id, address } => {
+                    assert_eq!(peer_id, a_id);
+                    assert!(address.iter().all(|p| p != Protocol::P2pCircuit));
+                    b_upgraded = true;
+                }
+                event => panic!("Unexpected event on B: {:?}", event),
+            }
+        }
+
+        if a_upgraded && b_upgraded && circuit_closed {
+            Ok(Async::Ready(()))
+        } else {
+            Ok(Async::NotReady)
+        }
+    });
+
+    rt.block_on(test.timeout(Duration::from_secs(10))).unwrap();
+
+    // Both peers dial from their listen port, hence the second dial fails, or is merged with
+    // the first one by a simultaneous open. Either way, there is a single direct connection.
+    let a_roles = a_direct.roles();
+    let b_roles = b_direct.roles();
+    assert_eq!(a_roles.len(), 1);
+    assert_eq!(b_roles.len(), 1);
+    assert_ne!(a_roles[0], b_roles[0]);
+}
@@ -24,7 +24,7 @@ use flate2::Compression;
 use std::io;
 
 use futures::future::{self, FutureResult};
-use libp2p_core::{upgrade::Negotiated, InboundUpgrade, OutboundUpgrade, UpgradeInfo};
+use libp2p_core::{compat::Compat01As03, upgrade::Negotiated, InboundUpgrade, OutboundUpgrade, UpgradeInfo};
 use std::iter;
 use tokio_io::{AsyncRead, AsyncWrite};
 
@@ -49,13 +49,13 @@ where
 {
     type Output = DeflateOutput<Negotiated<C>>;
     type Error = io::Error;
-    type Future = FutureResult<Self::Output, Self::Error>;
+    type Future = Compat01As03<FutureResult<Self::Output, Self::Error>>;
 
     fn upgrade_inbound(self, r: Negotiated<C>, _: Self::Info) -> Self::Future {
-        future::ok(DeflateDecoder::new(DeflateEncoder::new(
+        Compat01As03::new(future::ok(DeflateDecoder::new(DeflateEncoder::new(
             r,
             Compression::default(),
-        )))
+        ))))
     }
 }
 
@@ -65,12 +65,12 @@ where
 {
     type Output = DeflateOutput<Negotiated<C>>;
     type Error = io::Error;
-    type Future = FutureResult<Self::Output, Self::Error>;
+    type Future = Compat01As03<FutureResult<Self::Output, Self::Error>>;
 
     fn upgrade_outbound(self, w: Negotiated<C>, _: Self::Info) -> Self::Future {
-        future::ok(DeflateDecoder::new(DeflateEncoder::new(
+        Compat01As03::new(future::ok(DeflateDecoder::new(DeflateEncoder::new(
             w,
             Compression::default(),
-        )))
+        ))))
     }
 }
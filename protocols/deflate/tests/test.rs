@@ -19,6 +19,7 @@
 // DEALINGS IN THE SOFTWARE.
 
 use futures::prelude::*;
+use libp2p_core::compat::{Compat03As01, Transport01};
 use libp2p_core::transport::{ListenerEvent, Transport};
 use libp2p_core::upgrade::Negotiated;
 use libp2p_deflate::{DeflateConfig, DeflateOutput};
@@ -32,8 +33,8 @@ fn deflate() {
     let _ = env_logger::try_init();
 
     fn prop(message: Vec<u8>) -> bool {
-        let server_transport = TcpConfig::new().with_upgrade(DeflateConfig {});
-        let client_transport = TcpConfig::new().with_upgrade(DeflateConfig {});
+        let server_transport = Compat03As01::new(TcpConfig::new().with_upgrade(DeflateConfig {}));
+        let client_transport = Compat03As01::new(TcpConfig::new().with_upgrade(DeflateConfig {}));
         run(server_transport, client_transport, message);
         true
     }
@@ -47,15 +48,14 @@ type Output = DeflateOutput<Negotiated<TcpTransStream>>;
 
 fn run<T>(server_transport: T, client_transport: T, message1: Vec<u8>)
 where
-    T: Transport<Output = Output>,
+    T: Transport01<Output = Output>,
     T::Dial: Send + 'static,
     T::Listener: Send + 'static,
     T::ListenerUpgrade: Send + 'static,
 {
     let message2 = message1.clone();
 
-    let mut server = server_transport
-        .listen_on("/ip4/127.0.0.1/tcp/0".parse().unwrap())
+    let mut server = Transport01::listen_on(server_transport, "/ip4/127.0.0.1/tcp/0".parse().unwrap())
         .unwrap();
     let server_address = server
         .by_ref()
@@ -80,8 +80,7 @@ where
             Ok(())
         });
 
-    let client = client_transport
-        .dial(server_address.clone())
+    let client = Transport01::dial(client_transport, server_address.clone())
         .unwrap()
         .map_err(|e| panic!("client error: {}", e))
         .and_then(move |server| {
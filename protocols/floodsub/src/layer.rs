@@ -22,18 +22,19 @@ use crate::protocol::{FloodsubConfig, FloodsubMessage, FloodsubRpc, FloodsubSubs
 use crate::topic::{Topic, TopicHash};
 use cuckoofilter::CuckooFilter;
 use fnv::FnvHashSet;
-use futures::prelude::*;
-use libp2p_core::{ConnectedPoint, Multiaddr, PeerId};
+use libp2p_core::{ConnectedPoint, Multiaddr, PeerId, nodes::ConnectionId};
 use libp2p_swarm::{
+    Misbehaviour,
     NetworkBehaviour,
     NetworkBehaviourAction,
+    NotifyHandler,
     PollParameters,
     ProtocolsHandler,
     OneShotHandler
 };
 use rand;
 use smallvec::SmallVec;
-use std::{collections::VecDeque, iter, marker::PhantomData};
+use std::{collections::VecDeque, iter, marker::PhantomData, task::{Context, Poll}};
 use std::collections::hash_map::{DefaultHasher, HashMap};
 use tokio_io::{AsyncRead, AsyncWrite};
 
@@ -86,8 +87,9 @@ impl<TSubstream> Floodsub<TSubstream> {
         // Send our topics to this node if we're already connected to it.
         if self.connected_peers.contains_key(&peer_id) {
             for topic in self.subscribed_topics.iter() {
-                self.events.push_back(NetworkBehaviourAction::SendEvent {
+                self.events.push_back(NetworkBehaviourAction::NotifyHandler {
                     peer_id: peer_id.clone(),
+                    handler: NotifyHandler::Any,
                     event: FloodsubRpc {
                         messages: Vec::new(),
                         subscriptions: vec![FloodsubSubscription {
@@ -121,8 +123,9 @@ impl<TSubstream> Floodsub<TSubstream> {
         }
 
         for peer in self.connected_peers.keys() {
-            self.events.push_back(NetworkBehaviourAction::SendEvent {
+            self.events.push_back(NetworkBehaviourAction::NotifyHandler {
                 peer_id: peer.clone(),
+                handler: NotifyHandler::Any,
                 event: FloodsubRpc {
                     messages: Vec::new(),
                     subscriptions: vec![FloodsubSubscription {
@@ -152,8 +155,9 @@ impl<TSubstream> Floodsub<TSubstream> {
         self.subscribed_topics.remove(pos);
 
         for peer in self.connected_peers.keys() {
-            self.events.push_back(NetworkBehaviourAction::SendEvent {
+            self.events.push_back(NetworkBehaviourAction::NotifyHandler {
                 peer_id: peer.clone(),
+                handler: NotifyHandler::Any,
                 event: FloodsubRpc {
                     messages: Vec::new(),
                     subscriptions: vec![FloodsubSubscription {
@@ -217,8 +221,9 @@ impl<TSubstream> Floodsub<TSubstream> {
                 continue;
             }
 
-            self.events.push_back(NetworkBehaviourAction::SendEvent {
+            self.events.push_back(NetworkBehaviourAction::NotifyHandler {
                 peer_id: peer_id.clone(),
+                handler: NotifyHandler::Any,
                 event: FloodsubRpc {
                     subscriptions: Vec::new(),
                     messages: vec![message.clone()],
@@ -247,8 +252,9 @@ where
         // We need to send our subscriptions to the newly-connected node.
         if self.target_peers.contains(&id) {
             for topic in self.subscribed_topics.iter() {
-                self.events.push_back(NetworkBehaviourAction::SendEvent {
+                self.events.push_back(NetworkBehaviourAction::NotifyHandler {
                     peer_id: id.clone(),
+                    handler: NotifyHandler::Any,
                     event: FloodsubRpc {
                         messages: Vec::new(),
                         subscriptions: vec![FloodsubSubscription {
@@ -277,6 +283,7 @@ where
     fn inject_node_event(
         &mut self,
         propagation_source: PeerId,
+        _: ConnectionId,
         event: InnerMessage,
     ) {
         // We ignore successful sends event.
@@ -316,6 +323,15 @@ where
         let mut rpcs_to_dispatch: Vec<(PeerId, FloodsubRpc)> = Vec::new();
 
         for message in event.messages {
+            // A message without topics can't be delivered to anyone.
+            if message.topics.is_empty() {
+                self.events.push_back(NetworkBehaviourAction::ReportMisbehaviour {
+                    peer_id: propagation_source.clone(),
+                    misbehaviour: Misbehaviour::ProtocolViolation,
+                });
+                continue;
+            }
+
             // Use `self.received` to skip the messages that we have already received in the past.
             // Note that this can false positive.
             if !self.received.test_and_add(&message) {
@@ -350,8 +366,9 @@ where
         }
 
         for (peer_id, rpc) in rpcs_to_dispatch {
-            self.events.push_back(NetworkBehaviourAction::SendEvent {
+            self.events.push_back(NetworkBehaviourAction::NotifyHandler {
                 peer_id,
+                handler: NotifyHandler::Any,
                 event: rpc,
             });
         }
@@ -359,18 +376,19 @@ where
 
     fn poll(
         &mut self,
+        _: &mut Context<'_>,
         _: &mut impl PollParameters,
-    ) -> Async<
+    ) -> Poll<
         NetworkBehaviourAction<
             <Self::ProtocolsHandler as ProtocolsHandler>::InEvent,
             Self::OutEvent,
         >,
     > {
         if let Some(event) = self.events.pop_front() {
-            return Async::Ready(event);
+            return Poll::Ready(event);
         }
 
-        Async::NotReady
+        Poll::Pending
     }
 }
 
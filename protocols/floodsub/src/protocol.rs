@@ -20,7 +20,7 @@
 
 use crate::rpc_proto;
 use crate::topic::TopicHash;
-use libp2p_core::{InboundUpgrade, OutboundUpgrade, UpgradeInfo, PeerId, upgrade};
+use libp2p_core::{InboundUpgrade, OutboundUpgrade, UpgradeInfo, PeerId, compat::Compat01As03, upgrade};
 use protobuf::{ProtobufError, Message as ProtobufMessage};
 use std::{error, fmt, io, iter};
 use tokio_io::{AsyncRead, AsyncWrite};
@@ -53,11 +53,11 @@ where
 {
     type Output = FloodsubRpc;
     type Error = FloodsubDecodeError;
-    type Future = upgrade::ReadOneThen<upgrade::Negotiated<TSocket>, (), fn(Vec<u8>, ()) -> Result<FloodsubRpc, FloodsubDecodeError>>;
+    type Future = Compat01As03<upgrade::ReadOneThen<upgrade::Negotiated<TSocket>, (), fn(Vec<u8>, ()) -> Result<FloodsubRpc, FloodsubDecodeError>>>;
 
     #[inline]
     fn upgrade_inbound(self, socket: upgrade::Negotiated<TSocket>, _: Self::Info) -> Self::Future {
-        upgrade::read_one_then(socket, 2048, (), |packet, ()| {
+        Compat01As03::new(upgrade::read_one_then(socket, 2048, (), |packet, ()| {
             let mut rpc: rpc_proto::RPC = protobuf::parse_from_bytes(&packet)?;
 
             let mut messages = Vec::with_capacity(rpc.get_publish().len());
@@ -91,7 +91,7 @@ where
                     })
                     .collect(),
             })
-        })
+        }))
     }
 }
 
@@ -168,12 +168,12 @@ where
 {
     type Output = ();
     type Error = io::Error;
-    type Future = upgrade::WriteOne<upgrade::Negotiated<TSocket>>;
+    type Future = Compat01As03<upgrade::WriteOne<upgrade::Negotiated<TSocket>>>;
 
     #[inline]
     fn upgrade_outbound(self, socket: upgrade::Negotiated<TSocket>, _: Self::Info) -> Self::Future {
         let bytes = self.into_bytes();
-        upgrade::write_one(socket, bytes)
+        Compat01As03::new(upgrade::write_one(socket, bytes))
     }
 }
 
@@ -0,0 +1,116 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use futures03::TryFutureExt as _;
+use libp2p_core::{
+    PeerId, Transport,
+    muxing::StreamMuxerBox,
+    nodes::Substream,
+    transport::boxed::Boxed,
+    upgrade
+};
+use libp2p_floodsub::{Floodsub, FloodsubEvent, TopicBuilder};
+use libp2p_mplex::MplexConfig;
+use libp2p_secio::SecioConfig;
+use libp2p_simulator::{LinkConfig, NodeId, Simulation};
+use libp2p_swarm::{Swarm, SwarmBuilder};
+use rand::Rng;
+use std::{collections::HashMap, io, time::Duration};
+
+type TestSwarm = Swarm<
+    Boxed<(PeerId, StreamMuxerBox), io::Error>,
+    Floodsub<Substream<StreamMuxerBox>>
+>;
+
+/// Adds a floodsub node to the simulation, listening on `/memory/<index + 1>`.
+fn add_node(sim: &mut Simulation<TestSwarm>) -> (NodeId, PeerId) {
+    let keypair = sim.keypair();
+    let executor = sim.executor();
+    let peer_id = keypair.public().into_peer_id();
+    let local_peer_id = peer_id.clone();
+    let node = sim.add_node(move |transport| {
+        let transport = transport
+            .with_upgrade(SecioConfig::new(keypair))
+            .and_then(move |out, endpoint| {
+                let peer_id = out.remote_key.into_peer_id();
+                upgrade::apply(out.stream, MplexConfig::new(), endpoint)
+                    .map_ok(move |muxer| (peer_id, StreamMuxerBox::new(muxer)))
+            })
+            .map_err(|err| io::Error::new(io::ErrorKind::Other, err.to_string()))
+            .boxed();
+        SwarmBuilder::new(transport, Floodsub::new(local_peer_id.clone()), local_peer_id)
+            .executor(Box::new(executor))
+            .build()
+    });
+    let addr = format!("/memory/{}", node.index() + 1).parse().unwrap();
+    sim.with_node(node, |swarm| Swarm::listen_on(swarm, addr)).unwrap();
+    (node, peer_id)
+}
+
+#[test]
+fn messages_reach_every_node_once() {
+    let mut sim = Simulation::new(3);
+    sim.set_default_link(LinkConfig::default()
+        .with_latency(Duration::from_millis(40))
+        .with_jitter(Duration::from_millis(40))
+        .with_loss(0.05));
+    let nodes = (0 .. 30).map(|_| add_node(&mut sim)).collect::<Vec<_>>();
+
+    // A ring with a few random chords, so that messages arrive over several paths.
+    let mut links = (0 .. nodes.len()).map(|i| (i, (i + 1) % nodes.len())).collect::<Vec<_>>();
+    for i in 0 .. nodes.len() / 3 {
+        let j = sim.rng().gen_range(0, nodes.len());
+        if i != j {
+            links.push((i, j));
+        }
+    }
+    for (i, j) in &links {
+        let addr = format!("/memory/{}", j + 1).parse().unwrap();
+        sim.with_node(nodes[*i].0, |swarm| Swarm::dial_addr(swarm, addr)).unwrap();
+    }
+    sim.run_for(Duration::from_secs(1));
+
+    let topic = TopicBuilder::new("simulation").build();
+    for (i, j) in &links {
+        let (peer_i, peer_j) = (nodes[*i].1.clone(), nodes[*j].1.clone());
+        sim.with_node(nodes[*i].0, |swarm| swarm.add_node_to_partial_view(peer_j));
+        sim.with_node(nodes[*j].0, |swarm| swarm.add_node_to_partial_view(peer_i));
+    }
+    for (node, _) in &nodes {
+        sim.with_node(*node, |swarm| swarm.subscribe(topic.clone()));
+    }
+    sim.run_for(Duration::from_secs(1));
+
+    let (source, source_id) = nodes[0].clone();
+    sim.with_node(source, |swarm| swarm.publish(&topic, &b"hello"[..]));
+    let mut received = HashMap::new();
+    sim.run_until(Duration::from_secs(5), |node, event| {
+        if let FloodsubEvent::Message(message) = event {
+            assert_eq!(message.source, source_id);
+            assert_eq!(message.data, b"hello");
+            *received.entry(node).or_insert(0) += 1;
+        }
+        false
+    });
+
+    assert_eq!(received.len(), nodes.len() - 1);
+    assert!(!received.contains_key(&source));
+    assert!(received.values().all(|n| *n == 1));
+}
@@ -0,0 +1,741 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Implementation of the `Gossipsub` network behaviour.
+
+mod tests;
+
+use crate::config::GossipsubConfig;
+use crate::mcache::MessageCache;
+use crate::protocol::{
+    GossipsubControlAction,
+    GossipsubMessage,
+    GossipsubRpc,
+    GossipsubSubscription,
+    GossipsubSubscriptionAction,
+    MessageId,
+    ProtocolConfig,
+};
+use cuckoofilter::CuckooFilter;
+use futures::prelude::*;
+use libp2p_core::{ConnectedPoint, Multiaddr, PeerId, clock, nodes::ConnectionId};
+use libp2p_floodsub::{Topic, TopicHash};
+use libp2p_swarm::{
+    NetworkBehaviourAction,
+    NotifyHandler,
+    PollParameters,
+    ProtocolsHandler,
+    OneShotHandler,
+    SubstreamProtocol,
+    compat::NetworkBehaviour01
+};
+use log::{debug, trace};
+use rand::{self, seq::SliceRandom};
+use smallvec::SmallVec;
+use std::{collections::VecDeque, iter, marker::PhantomData, time::Duration};
+use std::collections::hash_map::{DefaultHasher, HashMap};
+use tokio_io::{AsyncRead, AsyncWrite};
+use wasm_timer::{Instant, Interval};
+
+/// Network behaviour that implements the gossipsub protocol.
+///
+/// Instead of flooding every message to every peer subscribed to a topic, as `Floodsub` does,
+/// each node maintains for every topic it is subscribed to a bounded set of peers, the *mesh*,
+/// to which full messages are forwarded. The ids of recent messages are additionally gossiped to
+/// a few peers outside of the mesh, which can then request the messages they missed.
+pub struct Gossipsub<TSubstream> {
+    /// Configuration of the behaviour.
+    config: GossipsubConfig,
+
+    /// Events that need to be yielded to the outside when polling.
+    events: VecDeque<NetworkBehaviourAction<GossipsubRpc, GossipsubEvent>>,
+
+    /// Control messages generated during the heartbeat, sent in a single RPC per peer at the end
+    /// of the heartbeat.
+    control_pool: HashMap<PeerId, Vec<GossipsubControlAction>>,
+
+    /// Peer id of the local node. Used for the source of the messages that we publish.
+    local_peer_id: PeerId,
+
+    /// For each topic, the list of connected peers that are subscribed to it.
+    topic_peers: HashMap<TopicHash, Vec<PeerId>>,
+
+    /// List of peers the network is connected to, and the topics that they're subscribed to.
+    peer_topics: HashMap<PeerId, SmallVec<[TopicHash; 8]>>,
+
+    /// List of topics we're subscribed to.
+    subscribed_topics: SmallVec<[Topic; 16]>,
+
+    /// For each topic we're subscribed to, the peers we forward full messages to.
+    mesh: HashMap<TopicHash, Vec<PeerId>>,
+
+    /// For each topic we published to without being subscribed to it, the peers we send our
+    /// messages to.
+    fanout: HashMap<TopicHash, Vec<PeerId>>,
+
+    /// The last time we published to each topic of `fanout`.
+    fanout_last_pub: HashMap<TopicHash, Instant>,
+
+    /// Messages of the last few heartbeats, used to answer `IWANT` requests and to gossip.
+    mcache: MessageCache,
+
+    // We keep track of the ids of the messages we received so that we don't dispatch the same
+    // message twice if we receive it twice on the network.
+    received: CuckooFilter<DefaultHasher>,
+
+    /// Fires at each heartbeat.
+    heartbeat: Interval,
+
+    /// Marker to pin the generics.
+    marker: PhantomData<TSubstream>,
+}
+
+impl<TSubstream> Gossipsub<TSubstream> {
+    /// Creates a `Gossipsub` with the given configuration.
+    pub fn new(local_peer_id: PeerId, config: GossipsubConfig) -> Self {
+        let heartbeat = Interval::new(
+            clock::now() + config.heartbeat_initial_delay,
+            config.heartbeat_interval,
+        );
+
+        Gossipsub {
+            mcache: MessageCache::new(config.history_gossip, config.history_length),
+            config,
+            events: VecDeque::new(),
+            control_pool: HashMap::new(),
+            local_peer_id,
+            topic_peers: HashMap::new(),
+            peer_topics: HashMap::new(),
+            subscribed_topics: SmallVec::new(),
+            mesh: HashMap::new(),
+            fanout: HashMap::new(),
+            fanout_last_pub: HashMap::new(),
+            received: CuckooFilter::new(),
+            heartbeat,
+            marker: PhantomData,
+        }
+    }
+
+    /// Subscribes to a topic.
+    ///
+    /// Returns true if the subscription worked. Returns false if we were already subscribed.
+    pub fn subscribe(&mut self, topic: Topic) -> bool {
+        if self.subscribed_topics.iter().any(|t| t.hash() == topic.hash()) {
+            return false;
+        }
+
+        for peer in self.peer_topics.keys() {
+            self.events.push_back(NetworkBehaviourAction::NotifyHandler {
+                peer_id: peer.clone(),
+                handler: NotifyHandler::Any,
+                event: GossipsubRpc {
+                    subscriptions: vec![GossipsubSubscription {
+                        topic: topic.hash().clone(),
+                        action: GossipsubSubscriptionAction::Subscribe,
+                    }],
+                    .. GossipsubRpc::default()
+                },
+            });
+        }
+
+        self.join(topic.hash());
+        self.subscribed_topics.push(topic);
+        true
+    }
+
+    /// Unsubscribes from a topic.
+    ///
+    /// Note that this only requires a `TopicHash` and not a full `Topic`.
+    ///
+    /// Returns true if we were subscribed to this topic.
+    pub fn unsubscribe(&mut self, topic: impl AsRef<TopicHash>) -> bool {
+        let topic = topic.as_ref();
+        let pos = match self.subscribed_topics.iter().position(|t| t.hash() == topic) {
+            Some(pos) => pos,
+            None => return false
+        };
+
+        self.subscribed_topics.remove(pos);
+
+        for peer in self.peer_topics.keys() {
+            self.events.push_back(NetworkBehaviourAction::NotifyHandler {
+                peer_id: peer.clone(),
+                handler: NotifyHandler::Any,
+                event: GossipsubRpc {
+                    subscriptions: vec![GossipsubSubscription {
+                        topic: topic.clone(),
+                        action: GossipsubSubscriptionAction::Unsubscribe,
+                    }],
+                    .. GossipsubRpc::default()
+                },
+            });
+        }
+
+        self.leave(topic);
+        true
+    }
+
+    /// Publishes a message to the network.
+    ///
+    /// If we're subscribed to the topic, the message is sent to the peers of our mesh. Otherwise
+    /// it is sent to a random set of peers subscribed to the topic, the *fanout*, which is kept
+    /// for subsequent publications.
+    pub fn publish(&mut self, topic: impl Into<TopicHash>, data: impl Into<Vec<u8>>) {
+        self.publish_many(iter::once(topic), data)
+    }
+
+    /// Publishes a message with multiple topics to the network.
+    pub fn publish_many(&mut self, topic: impl IntoIterator<Item = impl Into<TopicHash>>, data: impl Into<Vec<u8>>) {
+        let message = GossipsubMessage {
+            source: self.local_peer_id.clone(),
+            data: data.into(),
+            // If the sequence numbers are predictable, then an attacker could flood the network
+            // with packets with the predetermined sequence numbers and absorb our legitimate
+            // messages. We therefore use a random number.
+            sequence_number: rand::random::<[u8; 20]>().to_vec(),
+            topics: topic.into_iter().map(|t| t.into()).collect(),
+        };
+
+        self.received.add(&message.id());
+        self.mcache.put(message.clone());
+
+        let mut recipients: Vec<PeerId> = Vec::new();
+        for topic_hash in &message.topics {
+            let peers = if let Some(mesh_peers) = self.mesh.get(topic_hash) {
+                mesh_peers
+            } else {
+                let topic_peers = &self.topic_peers;
+                let mesh_n = self.config.mesh_n;
+                let fanout_peers = self.fanout.entry(topic_hash.clone()).or_insert_with(Vec::new);
+                if fanout_peers.is_empty() {
+                    *fanout_peers = Self::get_random_peers(topic_peers, topic_hash, mesh_n, |_| true);
+                }
+                self.fanout_last_pub.insert(topic_hash.clone(), clock::now());
+                &*fanout_peers
+            };
+
+            for peer_id in peers {
+                if !recipients.contains(peer_id) {
+                    recipients.push(peer_id.clone());
+                }
+            }
+        }
+
+        for peer_id in recipients {
+            self.events.push_back(NetworkBehaviourAction::NotifyHandler {
+                peer_id,
+                handler: NotifyHandler::Any,
+                event: GossipsubRpc {
+                    messages: vec![message.clone()],
+                    .. GossipsubRpc::default()
+                },
+            });
+        }
+    }
+
+    /// Adds the topic to our mesh and sends a `GRAFT` to the peers of the new mesh.
+    ///
+    /// If we previously published to the topic, the fanout peers are reused.
+    fn join(&mut self, topic_hash: &TopicHash) {
+        if self.mesh.contains_key(topic_hash) {
+            return;
+        }
+
+        let mesh_n = self.config.mesh_n;
+        let mut peers = self.fanout.remove(topic_hash).unwrap_or_default();
+        self.fanout_last_pub.remove(topic_hash);
+        peers.truncate(mesh_n);
+        if peers.len() < mesh_n {
+            let needed = mesh_n - peers.len();
+            let new_peers = Self::get_random_peers(&self.topic_peers, topic_hash, needed, |p| !peers.contains(p));
+            peers.extend(new_peers);
+        }
+
+        debug!("Joining mesh of {:?} with {} peers", topic_hash, peers.len());
+
+        for peer_id in &peers {
+            self.send_control(peer_id.clone(), GossipsubControlAction::Graft {
+                topic_hash: topic_hash.clone(),
+            });
+        }
+
+        self.mesh.insert(topic_hash.clone(), peers);
+    }
+
+    /// Removes the topic from our mesh and sends a `PRUNE` to the peers of the mesh.
+    fn leave(&mut self, topic_hash: &TopicHash) {
+        if let Some(peers) = self.mesh.remove(topic_hash) {
+            debug!("Leaving mesh of {:?}", topic_hash);
+            for peer_id in peers {
+                self.send_control(peer_id, GossipsubControlAction::Prune {
+                    topic_hash: topic_hash.clone(),
+                });
+            }
+        }
+    }
+
+    /// Immediately sends a control message to a peer.
+    fn send_control(&mut self, peer_id: PeerId, action: GossipsubControlAction) {
+        self.events.push_back(NetworkBehaviourAction::NotifyHandler {
+            peer_id,
+            handler: NotifyHandler::Any,
+            event: GossipsubRpc {
+                control_msgs: vec![action],
+                .. GossipsubRpc::default()
+            },
+        });
+    }
+
+    /// Returns up to `n` random peers subscribed to `topic_hash` and accepted by `filter`.
+    fn get_random_peers(
+        topic_peers: &HashMap<TopicHash, Vec<PeerId>>,
+        topic_hash: &TopicHash,
+        n: usize,
+        mut filter: impl FnMut(&PeerId) -> bool
+    ) -> Vec<PeerId> {
+        let mut peers = topic_peers
+            .get(topic_hash)
+            .map(|peers| peers.iter().filter(|p| filter(p)).cloned().collect::<Vec<_>>())
+            .unwrap_or_default();
+        peers.shuffle(&mut rand::thread_rng());
+        peers.truncate(n);
+        peers
+    }
+
+    /// Updates the topics of a remote and reports the changes.
+    fn handle_subscriptions(&mut self, propagation_source: &PeerId, subscriptions: Vec<GossipsubSubscription>) {
+        let remote_peer_topics = match self.peer_topics.get_mut(propagation_source) {
+            Some(topics) => topics,
+            None => {
+                debug!("Received subscriptions from unknown peer {:?}", propagation_source);
+                return;
+            }
+        };
+
+        for subscription in subscriptions {
+            match subscription.action {
+                GossipsubSubscriptionAction::Subscribe => {
+                    if !remote_peer_topics.contains(&subscription.topic) {
+                        remote_peer_topics.push(subscription.topic.clone());
+                    }
+                    let peers = self.topic_peers.entry(subscription.topic.clone()).or_insert_with(Vec::new);
+                    if !peers.contains(propagation_source) {
+                        peers.push(propagation_source.clone());
+                    }
+                    self.events.push_back(NetworkBehaviourAction::GenerateEvent(GossipsubEvent::Subscribed {
+                        peer_id: propagation_source.clone(),
+                        topic: subscription.topic,
+                    }));
+                }
+                GossipsubSubscriptionAction::Unsubscribe => {
+                    if let Some(pos) = remote_peer_topics.iter().position(|t| t == &subscription.topic) {
+                        remote_peer_topics.remove(pos);
+                    }
+                    if let Some(peers) = self.topic_peers.get_mut(&subscription.topic) {
+                        peers.retain(|p| p != propagation_source);
+                    }
+                    if let Some(peers) = self.mesh.get_mut(&subscription.topic) {
+                        peers.retain(|p| p != propagation_source);
+                    }
+                    self.events.push_back(NetworkBehaviourAction::GenerateEvent(GossipsubEvent::Unsubscribed {
+                        peer_id: propagation_source.clone(),
+                        topic: subscription.topic,
+                    }));
+                }
+            }
+        }
+    }
+
+    /// Dispatches a message received from the network to the user and forwards it to our mesh.
+    fn handle_received_message(&mut self, message: GossipsubMessage, propagation_source: &PeerId) {
+        // Use `self.received` to skip the messages that we have already received in the past.
+        // Note that this can false positive.
+        if !self.received.test_and_add(&message.id()) {
+            trace!("Ignoring duplicate message {}", message.id());
+            return;
+        }
+
+        self.mcache.put(message.clone());
+
+        if self.subscribed_topics.iter().any(|t| message.topics.iter().any(|u| t.hash() == u)) {
+            let event = GossipsubEvent::Message(message.clone());
+            self.events.push_back(NetworkBehaviourAction::GenerateEvent(event));
+        }
+
+        // Forward the message to the peers of our mesh, except the one we received it from.
+        let mut recipients: Vec<PeerId> = Vec::new();
+        for topic_hash in &message.topics {
+            if let Some(peers) = self.mesh.get(topic_hash) {
+                for peer_id in peers {
+                    if peer_id != propagation_source && *peer_id != message.source
+                        && !recipients.contains(peer_id)
+                    {
+                        recipients.push(peer_id.clone());
+                    }
+                }
+            }
+        }
+
+        for peer_id in recipients {
+            self.events.push_back(NetworkBehaviourAction::NotifyHandler {
+                peer_id,
+                handler: NotifyHandler::Any,
+                event: GossipsubRpc {
+                    messages: vec![message.clone()],
+                    .. GossipsubRpc::default()
+                },
+            });
+        }
+    }
+
+    /// Requests the messages advertised by a peer that we haven't seen yet, for the topics we're
+    /// subscribed to.
+    fn handle_ihave(&mut self, peer_id: &PeerId, ihave_msgs: Vec<(TopicHash, Vec<MessageId>)>) {
+        let mut iwant_ids = Vec::new();
+        for (topic_hash, message_ids) in ihave_msgs {
+            if !self.mesh.contains_key(&topic_hash) {
+                continue;
+            }
+            for message_id in message_ids {
+                if !self.received.contains(&message_id) && !iwant_ids.contains(&message_id) {
+                    iwant_ids.push(message_id);
+                }
+            }
+        }
+
+        if !iwant_ids.is_empty() {
+            self.send_control(peer_id.clone(), GossipsubControlAction::IWant {
+                message_ids: iwant_ids,
+            });
+        }
+    }
+
+    /// Sends to a peer the messages it requested that are still in our cache.
+    fn handle_iwant(&mut self, peer_id: &PeerId, message_ids: Vec<MessageId>) {
+        let messages: Vec<_> = message_ids
+            .iter()
+            .filter_map(|id| self.mcache.get(id))
+            .cloned()
+            .collect();
+
+        if !messages.is_empty() {
+            self.events.push_back(NetworkBehaviourAction::NotifyHandler {
+                peer_id: peer_id.clone(),
+                handler: NotifyHandler::Any,
+                event: GossipsubRpc {
+                    messages,
+                    .. GossipsubRpc::default()
+                },
+            });
+        }
+    }
+
+    /// Adds a peer to our mesh for the given topics, or prunes it if we're not subscribed to them.
+    fn handle_graft(&mut self, peer_id: &PeerId, topics: Vec<TopicHash>) {
+        let mut to_prune = Vec::new();
+        for topic_hash in topics {
+            if let Some(peers) = self.mesh.get_mut(&topic_hash) {
+                if !peers.contains(peer_id) {
+                    peers.push(peer_id.clone());
+                }
+            } else {
+                to_prune.push(GossipsubControlAction::Prune { topic_hash });
+            }
+        }
+
+        if !to_prune.is_empty() {
+            self.events.push_back(NetworkBehaviourAction::NotifyHandler {
+                peer_id: peer_id.clone(),
+                handler: NotifyHandler::Any,
+                event: GossipsubRpc {
+                    control_msgs: to_prune,
+                    .. GossipsubRpc::default()
+                },
+            });
+        }
+    }
+
+    /// Removes a peer from our mesh for the given topics.
+    fn handle_prune(&mut self, peer_id: &PeerId, topics: Vec<TopicHash>) {
+        for topic_hash in topics {
+            if let Some(peers) = self.mesh.get_mut(&topic_hash) {
+                peers.retain(|p| p != peer_id);
+            }
+        }
+    }
+
+    /// Maintains the mesh and the fanout, and emits gossip.
+    fn heartbeat(&mut self) {
+        let mesh_n = self.config.mesh_n;
+        let mesh_n_low = self.config.mesh_n_low;
+        let mesh_n_high = self.config.mesh_n_high;
+
+        // Bring the mesh of each topic back within its bounds.
+        for (topic_hash, peers) in self.mesh.iter_mut() {
+            if peers.len() < mesh_n_low {
+                let needed = mesh_n - peers.len();
+                let new_peers = Self::get_random_peers(&self.topic_peers, topic_hash, needed, |p| !peers.contains(p));
+                for peer_id in new_peers {
+                    self.control_pool.entry(peer_id.clone()).or_insert_with(Vec::new)
+                        .push(GossipsubControlAction::Graft { topic_hash: topic_hash.clone() });
+                    peers.push(peer_id);
+                }
+            }
+
+            if peers.len() > mesh_n_high {
+                let excess = peers.len() - mesh_n;
+                peers.shuffle(&mut rand::thread_rng());
+                for peer_id in peers.drain(..excess) {
+                    self.control_pool.entry(peer_id).or_insert_with(Vec::new)
+                        .push(GossipsubControlAction::Prune { topic_hash: topic_hash.clone() });
+                }
+            }
+        }
+
+        // Forget the fanout of topics we haven't published to for a while.
+        let now = clock::now();
+        let fanout_ttl = self.config.fanout_ttl;
+        let fanout = &mut self.fanout;
+        self.fanout_last_pub.retain(|topic_hash, last_pub| {
+            if *last_pub + fanout_ttl < now {
+                fanout.remove(topic_hash);
+                false
+            } else {
+                true
+            }
+        });
+
+        // Replace the fanout peers that unsubscribed or disconnected.
+        for (topic_hash, peers) in self.fanout.iter_mut() {
+            let topic_peers = &self.topic_peers;
+            peers.retain(|p| topic_peers.get(topic_hash).map_or(false, |t| t.contains(p)));
+            if peers.len() < mesh_n {
+                let needed = mesh_n - peers.len();
+                let new_peers = Self::get_random_peers(topic_peers, topic_hash, needed, |p| !peers.contains(p));
+                peers.extend(new_peers);
+            }
+        }
+
+        // Advertise the messages of the recent windows to a few peers outside of the mesh.
+        let gossip_lazy = self.config.gossip_lazy;
+        for (topic_hash, peers) in self.mesh.iter().chain(self.fanout.iter()) {
+            let message_ids = self.mcache.get_gossip_ids(topic_hash);
+            if message_ids.is_empty() {
+                continue;
+            }
+
+            let gossip_peers = Self::get_random_peers(&self.topic_peers, topic_hash, gossip_lazy, |p| !peers.contains(p));
+            for peer_id in gossip_peers {
+                self.control_pool.entry(peer_id).or_insert_with(Vec::new)
+                    .push(GossipsubControlAction::IHave {
+                        topic_hash: topic_hash.clone(),
+                        message_ids: message_ids.clone(),
+                    });
+            }
+        }
+
+        for (peer_id, control_msgs) in self.control_pool.drain() {
+            self.events.push_back(NetworkBehaviourAction::NotifyHandler {
+                peer_id,
+                handler: NotifyHandler::Any,
+                event: GossipsubRpc {
+                    control_msgs,
+                    .. GossipsubRpc::default()
+                },
+            });
+        }
+
+        self.mcache.shift();
+    }
+}
+
+impl<TSubstream> NetworkBehaviour01 for Gossipsub<TSubstream>
+where
+    TSubstream: AsyncRead + AsyncWrite,
+{
+    type ProtocolsHandler = OneShotHandler<TSubstream, ProtocolConfig, GossipsubRpc, InnerMessage>;
+    type OutEvent = GossipsubEvent;
+
+    fn new_handler(&mut self) -> Self::ProtocolsHandler {
+        let listen_protocol = SubstreamProtocol::new(ProtocolConfig::new(self.config.max_transmit_size));
+        OneShotHandler::new(listen_protocol, Duration::from_secs(10))
+    }
+
+    fn addresses_of_peer(&mut self, _: &PeerId) -> Vec<Multiaddr> {
+        Vec::new()
+    }
+
+    fn inject_connected(&mut self, id: PeerId, _: ConnectedPoint) {
+        // We need to send our subscriptions to the newly-connected node.
+        if !self.subscribed_topics.is_empty() {
+            self.events.push_back(NetworkBehaviourAction::NotifyHandler {
+                peer_id: id.clone(),
+                handler: NotifyHandler::Any,
+                event: GossipsubRpc {
+                    subscriptions: self.subscribed_topics
+                        .iter()
+                        .map(|topic| GossipsubSubscription {
+                            topic: topic.hash().clone(),
+                            action: GossipsubSubscriptionAction::Subscribe,
+                        })
+                        .collect(),
+                    .. GossipsubRpc::default()
+                },
+            });
+        }
+
+        self.peer_topics.insert(id, SmallVec::new());
+    }
+
+    fn inject_disconnected(&mut self, id: &PeerId, _: ConnectedPoint) {
+        let was_in = self.peer_topics.remove(id);
+        debug_assert!(was_in.is_some());
+
+        if let Some(topics) = was_in {
+            for topic_hash in topics {
+                if let Some(peers) = self.topic_peers.get_mut(&topic_hash) {
+                    peers.retain(|p| p != id);
+                }
+                if let Some(peers) = self.mesh.get_mut(&topic_hash) {
+                    peers.retain(|p| p != id);
+                }
+                if let Some(peers) = self.fanout.get_mut(&topic_hash) {
+                    peers.retain(|p| p != id);
+                }
+            }
+        }
+
+        self.control_pool.remove(id);
+    }
+
+    fn inject_node_event(
+        &mut self,
+        propagation_source: PeerId,
+        _: ConnectionId,
+        event: InnerMessage,
+    ) {
+        // We ignore successful sends event.
+        let event = match event {
+            InnerMessage::Rx(event) => event,
+            InnerMessage::Sent => return,
+        };
+
+        self.handle_subscriptions(&propagation_source, event.subscriptions);
+
+        for message in event.messages {
+            self.handle_received_message(message, &propagation_source);
+        }
+
+        let mut ihave_msgs = Vec::new();
+        let mut graft_msgs = Vec::new();
+        let mut prune_msgs = Vec::new();
+        for action in event.control_msgs {
+            match action {
+                GossipsubControlAction::IHave { topic_hash, message_ids } =>
+                    ihave_msgs.push((topic_hash, message_ids)),
+                GossipsubControlAction::IWant { message_ids } =>
+                    self.handle_iwant(&propagation_source, message_ids),
+                GossipsubControlAction::Graft { topic_hash } =>
+                    graft_msgs.push(topic_hash),
+                GossipsubControlAction::Prune { topic_hash } =>
+                    prune_msgs.push(topic_hash),
+            }
+        }
+
+        if !ihave_msgs.is_empty() {
+            self.handle_ihave(&propagation_source, ihave_msgs);
+        }
+        if !graft_msgs.is_empty() {
+            self.handle_graft(&propagation_source, graft_msgs);
+        }
+        if !prune_msgs.is_empty() {
+            self.handle_prune(&propagation_source, prune_msgs);
+        }
+    }
+
+    fn poll(
+        &mut self,
+        _: &mut impl PollParameters,
+    ) -> Async<
+        NetworkBehaviourAction<
+            <Self::ProtocolsHandler as ProtocolsHandler>::InEvent,
+            Self::OutEvent,
+        >,
+    > {
+        if let Some(event) = self.events.pop_front() {
+            return Async::Ready(event);
+        }
+
+        while let Ok(Async::Ready(Some(_))) = self.heartbeat.poll() {
+            self.heartbeat();
+        }
+
+        if let Some(event) = self.events.pop_front() {
+            return Async::Ready(event);
+        }
+
+        Async::NotReady
+    }
+}
+
+/// Transmission between the `OneShotHandler` and the `Gossipsub` behaviour.
+pub enum InnerMessage {
+    /// We received an RPC from a remote.
+    Rx(GossipsubRpc),
+    /// We successfully sent an RPC request.
+    Sent,
+}
+
+impl From<GossipsubRpc> for InnerMessage {
+    #[inline]
+    fn from(rpc: GossipsubRpc) -> InnerMessage {
+        InnerMessage::Rx(rpc)
+    }
+}
+
+impl From<()> for InnerMessage {
+    #[inline]
+    fn from(_: ()) -> InnerMessage {
+        InnerMessage::Sent
+    }
+}
+
+/// Event that can happen on the gossipsub behaviour.
+#[derive(Debug)]
+pub enum GossipsubEvent {
+    /// A message has been received.
+    Message(GossipsubMessage),
+
+    /// A remote subscribed to a topic.
+    Subscribed {
+        /// Remote that has subscribed.
+        peer_id: PeerId,
+        /// The topic it has subscribed to.
+        topic: TopicHash,
+    },
+
+    /// A remote unsubscribed from a topic.
+    Unsubscribed {
+        /// Remote that has unsubscribed.
+        peer_id: PeerId,
+        /// The topic it has subscribed from.
+        topic: TopicHash,
+    },
+}
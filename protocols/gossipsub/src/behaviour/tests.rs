@@ -0,0 +1,210 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+#![cfg(test)]
+
+use super::*;
+use libp2p_floodsub::TopicBuilder;
+use std::io::Cursor;
+
+type TestGossipsub = Gossipsub<Cursor<Vec<u8>>>;
+
+fn dialer() -> ConnectedPoint {
+    ConnectedPoint::Dialer {
+        address: "/ip4/127.0.0.1/tcp/1234".parse().unwrap(),
+        role_override: libp2p_core::Endpoint::Dialer,
+    }
+}
+
+/// Builds a `Gossipsub` connected to `peer_no` peers that are all subscribed to `topic`.
+fn build_and_inject_peers(topic: &Topic, peer_no: usize) -> (TestGossipsub, Vec<PeerId>) {
+    let mut gs = Gossipsub::new(PeerId::random(), GossipsubConfig::default());
+    let mut peers = Vec::new();
+    for _ in 0..peer_no {
+        let peer = PeerId::random();
+        gs.inject_connected(peer.clone(), dialer());
+        gs.inject_node_event(peer.clone(), ConnectionId::new(0), InnerMessage::Rx(GossipsubRpc {
+            subscriptions: vec![GossipsubSubscription {
+                topic: topic.hash().clone(),
+                action: GossipsubSubscriptionAction::Subscribe,
+            }],
+            .. GossipsubRpc::default()
+        }));
+        peers.push(peer);
+    }
+    gs.events.clear();
+    (gs, peers)
+}
+
+/// Returns the RPCs sent by the behaviour, in order.
+fn sent_rpcs(gs: &mut TestGossipsub) -> Vec<(PeerId, GossipsubRpc)> {
+    gs.events.drain(..).filter_map(|event| match event {
+        NetworkBehaviourAction::NotifyHandler { peer_id, event, .. } => Some((peer_id, event)),
+        _ => None,
+    }).collect()
+}
+
+#[test]
+fn subscribe_builds_mesh() {
+    let topic = TopicBuilder::new("test").build();
+    let (mut gs, _) = build_and_inject_peers(&topic, 20);
+
+    assert!(gs.subscribe(topic.clone()));
+    assert!(!gs.subscribe(topic.clone()));
+
+    let mesh = gs.mesh.get(topic.hash()).expect("mesh should be created");
+    assert_eq!(mesh.len(), gs.config.mesh_n);
+
+    let grafted = sent_rpcs(&mut gs)
+        .into_iter()
+        .filter(|(_, rpc)| rpc.control_msgs.iter().any(|c| match c {
+            GossipsubControlAction::Graft { topic_hash } => topic_hash == topic.hash(),
+            _ => false,
+        }))
+        .count();
+    assert_eq!(grafted, gs.config.mesh_n);
+}
+
+#[test]
+fn unsubscribe_prunes_mesh() {
+    let topic = TopicBuilder::new("test").build();
+    let (mut gs, _) = build_and_inject_peers(&topic, 20);
+    gs.subscribe(topic.clone());
+    gs.events.clear();
+
+    assert!(gs.unsubscribe(topic.clone()));
+    assert!(gs.mesh.get(topic.hash()).is_none());
+
+    let pruned = sent_rpcs(&mut gs)
+        .into_iter()
+        .filter(|(_, rpc)| rpc.control_msgs.iter().any(|c| match c {
+            GossipsubControlAction::Prune { .. } => true,
+            _ => false,
+        }))
+        .count();
+    assert_eq!(pruned, gs.config.mesh_n);
+}
+
+#[test]
+fn graft_for_unsubscribed_topic_is_pruned() {
+    let topic = TopicBuilder::new("test").build();
+    let (mut gs, peers) = build_and_inject_peers(&topic, 1);
+
+    gs.inject_node_event(peers[0].clone(), ConnectionId::new(0), InnerMessage::Rx(GossipsubRpc {
+        control_msgs: vec![GossipsubControlAction::Graft { topic_hash: topic.hash().clone() }],
+        .. GossipsubRpc::default()
+    }));
+
+    let rpcs = sent_rpcs(&mut gs);
+    assert_eq!(rpcs.len(), 1);
+    assert_eq!(rpcs[0].0, peers[0]);
+    assert_eq!(rpcs[0].1.control_msgs, vec![GossipsubControlAction::Prune {
+        topic_hash: topic.hash().clone()
+    }]);
+}
+
+#[test]
+fn publish_without_subscription_uses_fanout() {
+    let topic = TopicBuilder::new("test").build();
+    let (mut gs, _) = build_and_inject_peers(&topic, 20);
+
+    gs.publish(&topic, vec![1, 2, 3]);
+
+    let fanout = gs.fanout.get(topic.hash()).expect("fanout should be created");
+    assert_eq!(fanout.len(), gs.config.mesh_n);
+    assert!(gs.fanout_last_pub.contains_key(topic.hash()));
+
+    let fanout = fanout.clone();
+    let rpcs = sent_rpcs(&mut gs);
+    assert_eq!(rpcs.len(), gs.config.mesh_n);
+    for (peer_id, rpc) in rpcs {
+        assert!(fanout.contains(&peer_id));
+        assert_eq!(rpc.messages.len(), 1);
+        assert_eq!(rpc.messages[0].data, vec![1, 2, 3]);
+    }
+}
+
+#[test]
+fn received_message_is_forwarded_to_mesh_once() {
+    let topic = TopicBuilder::new("test").build();
+    let (mut gs, _) = build_and_inject_peers(&topic, 20);
+    gs.subscribe(topic.clone());
+    gs.events.clear();
+
+    let mesh = gs.mesh.get(topic.hash()).unwrap().clone();
+    let source = mesh[0].clone();
+    let message = GossipsubMessage {
+        source: source.clone(),
+        data: vec![4, 5, 6],
+        sequence_number: vec![0; 20],
+        topics: vec![topic.hash().clone()],
+    };
+    let rpc = GossipsubRpc {
+        messages: vec![message.clone()],
+        .. GossipsubRpc::default()
+    };
+
+    gs.inject_node_event(source.clone(), ConnectionId::new(0), InnerMessage::Rx(rpc.clone()));
+
+    let mut received = 0;
+    let mut forwarded = 0;
+    for event in gs.events.drain(..) {
+        match event {
+            NetworkBehaviourAction::GenerateEvent(GossipsubEvent::Message(m)) => {
+                assert_eq!(m, message);
+                received += 1;
+            }
+            NetworkBehaviourAction::NotifyHandler { peer_id, event, .. } => {
+                assert_ne!(peer_id, source);
+                assert!(mesh.contains(&peer_id));
+                assert_eq!(event.messages, vec![message.clone()]);
+                forwarded += 1;
+            }
+            _ => {}
+        }
+    }
+    assert_eq!(received, 1);
+    assert_eq!(forwarded, mesh.len() - 1);
+
+    // The same message received a second time is ignored.
+    gs.inject_node_event(source, ConnectionId::new(0), InnerMessage::Rx(rpc));
+    assert!(gs.events.is_empty());
+}
+
+#[test]
+fn iwant_is_answered_from_cache() {
+    let topic = TopicBuilder::new("test").build();
+    let (mut gs, peers) = build_and_inject_peers(&topic, 1);
+    gs.publish(&topic, vec![7]);
+    gs.events.clear();
+
+    let id = gs.fanout.get(topic.hash())
+        .and_then(|_| gs.mcache.get_gossip_ids(topic.hash()).into_iter().next())
+        .expect("published message should be cached");
+
+    gs.inject_node_event(peers[0].clone(), ConnectionId::new(0), InnerMessage::Rx(GossipsubRpc {
+        control_msgs: vec![GossipsubControlAction::IWant { message_ids: vec![id] }],
+        .. GossipsubRpc::default()
+    }));
+
+    let rpcs = sent_rpcs(&mut gs);
+    assert_eq!(rpcs.len(), 1);
+    assert_eq!(rpcs[0].1.messages[0].data, vec![7]);
+}
@@ -0,0 +1,127 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use std::time::Duration;
+
+/// Configuration parameters that define the performance of the gossipsub network.
+#[derive(Debug, Clone)]
+pub struct GossipsubConfig {
+    /// Number of heartbeats to keep in the message cache.
+    pub(crate) history_length: usize,
+    /// Number of past heartbeats to gossip about.
+    pub(crate) history_gossip: usize,
+    /// Target number of peers for the mesh network (D in the spec).
+    pub(crate) mesh_n: usize,
+    /// Minimum number of peers in mesh network before adding more (D_lo in the spec).
+    pub(crate) mesh_n_low: usize,
+    /// Maximum number of peers in mesh network before removing some (D_high in the spec).
+    pub(crate) mesh_n_high: usize,
+    /// Number of peers outside of the mesh to emit gossip to.
+    pub(crate) gossip_lazy: usize,
+    /// Initial delay before the first heartbeat.
+    pub(crate) heartbeat_initial_delay: Duration,
+    /// Time between each heartbeat.
+    pub(crate) heartbeat_interval: Duration,
+    /// Time to live for fanout peers.
+    pub(crate) fanout_ttl: Duration,
+    /// Maximum size in bytes of an RPC that we accept from a remote.
+    pub(crate) max_transmit_size: usize,
+}
+
+impl Default for GossipsubConfig {
+    fn default() -> Self {
+        GossipsubConfig {
+            history_length: 5,
+            history_gossip: 3,
+            mesh_n: 6,
+            mesh_n_low: 4,
+            mesh_n_high: 12,
+            gossip_lazy: 6,
+            heartbeat_initial_delay: Duration::from_secs(5),
+            heartbeat_interval: Duration::from_secs(1),
+            fanout_ttl: Duration::from_secs(60),
+            max_transmit_size: 2048,
+        }
+    }
+}
+
+impl GossipsubConfig {
+    /// Sets the number of heartbeats for which a message is kept in the message cache, and the
+    /// number of those heartbeats that are advertised to peers with `IHAVE` gossip.
+    ///
+    /// The defaults are respectively 5 and 3.
+    ///
+    /// # Panic
+    ///
+    /// Panics if `history_gossip` is greater than `history_length`.
+    pub fn set_history(&mut self, history_length: usize, history_gossip: usize) -> &mut Self {
+        assert!(history_gossip <= history_length);
+        self.history_length = history_length;
+        self.history_gossip = history_gossip;
+        self
+    }
+
+    /// Sets the bounds of the mesh of each topic.
+    ///
+    /// The heartbeat grafts peers when a mesh has fewer than `mesh_n_low` peers and prunes peers
+    /// when it has more than `mesh_n_high`, bringing it back to `mesh_n` peers in both cases.
+    /// The defaults are respectively 4, 6 and 12.
+    ///
+    /// # Panic
+    ///
+    /// Panics if `mesh_n_low <= mesh_n <= mesh_n_high` doesn't hold.
+    pub fn set_mesh_n(&mut self, mesh_n_low: usize, mesh_n: usize, mesh_n_high: usize) -> &mut Self {
+        assert!(mesh_n_low <= mesh_n && mesh_n <= mesh_n_high);
+        self.mesh_n_low = mesh_n_low;
+        self.mesh_n = mesh_n;
+        self.mesh_n_high = mesh_n_high;
+        self
+    }
+
+    /// Sets the number of peers outside of the mesh to which `IHAVE` gossip is emitted at each
+    /// heartbeat. The default is 6.
+    pub fn set_gossip_lazy(&mut self, gossip_lazy: usize) -> &mut Self {
+        self.gossip_lazy = gossip_lazy;
+        self
+    }
+
+    /// Sets the delay before the first heartbeat and the interval between two heartbeats.
+    ///
+    /// The defaults are respectively 5 seconds and 1 second.
+    pub fn set_heartbeat(&mut self, initial_delay: Duration, interval: Duration) -> &mut Self {
+        self.heartbeat_initial_delay = initial_delay;
+        self.heartbeat_interval = interval;
+        self
+    }
+
+    /// Sets for how long we keep the fanout peers of a topic we published to without being
+    /// subscribed to it. The default is 60 seconds.
+    pub fn set_fanout_ttl(&mut self, fanout_ttl: Duration) -> &mut Self {
+        self.fanout_ttl = fanout_ttl;
+        self
+    }
+
+    /// Sets the maximum size in bytes of an RPC received from a remote. The default is 2048,
+    /// like floodsub.
+    pub fn set_max_transmit_size(&mut self, max_transmit_size: usize) -> &mut Self {
+        self.max_transmit_size = max_transmit_size;
+        self
+    }
+}
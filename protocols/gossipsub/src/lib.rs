@@ -0,0 +1,41 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Implements the gossipsub protocol, see also the:
+//! [spec](https://github.com/libp2p/specs/tree/master/pubsub/gossipsub).
+//!
+//! Gossipsub is a more bandwidth-efficient alternative to floodsub. Every node keeps, for each
+//! topic it is subscribed to, a bounded *mesh* of peers that receive the full messages, while
+//! the identifiers of recent messages are lazily gossiped to other peers during a periodic
+//! heartbeat.
+//!
+//! The API mirrors the one of `Floodsub`, and topics are shared with the floodsub crate.
+
+pub mod protocol;
+
+mod behaviour;
+mod config;
+mod mcache;
+mod rpc_proto;
+
+pub use self::behaviour::{Gossipsub, GossipsubEvent};
+pub use self::config::GossipsubConfig;
+pub use self::protocol::{GossipsubMessage, GossipsubRpc, MessageId};
+pub use libp2p_floodsub::{Topic, TopicBuilder, TopicHash};
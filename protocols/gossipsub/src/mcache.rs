@@ -0,0 +1,162 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Cache of the messages seen during the last few heartbeats.
+//!
+//! The cache is used both to answer `IWANT` requests and to build the `IHAVE` gossip that is
+//! emitted at each heartbeat.
+
+use crate::protocol::{GossipsubMessage, MessageId};
+use fnv::FnvHashMap;
+use libp2p_floodsub::TopicHash;
+
+/// An entry of a history window.
+#[derive(Debug, Clone)]
+struct CacheEntry {
+    mid: MessageId,
+    topics: Vec<TopicHash>,
+}
+
+/// Sliding window of the messages received during the last `history.len()` heartbeats.
+#[derive(Debug, Clone)]
+pub struct MessageCache {
+    /// All the messages currently in the cache.
+    msgs: FnvHashMap<MessageId, GossipsubMessage>,
+    /// One window per heartbeat, the most recent one first.
+    history: Vec<Vec<CacheEntry>>,
+    /// Number of windows that are advertised in gossip.
+    gossip: usize,
+}
+
+impl MessageCache {
+    /// Creates a cache that remembers messages for `history_capacity` heartbeats, of which the
+    /// last `gossip` are advertised.
+    pub fn new(gossip: usize, history_capacity: usize) -> MessageCache {
+        MessageCache {
+            msgs: FnvHashMap::default(),
+            history: vec![Vec::new(); history_capacity],
+            gossip,
+        }
+    }
+
+    /// Puts a message into the current window of the cache.
+    pub fn put(&mut self, msg: GossipsubMessage) {
+        let mid = msg.id();
+        let entry = CacheEntry {
+            mid: mid.clone(),
+            topics: msg.topics.clone(),
+        };
+
+        if self.msgs.insert(mid, msg).is_none() {
+            if let Some(window) = self.history.first_mut() {
+                window.push(entry);
+            }
+        }
+    }
+
+    /// Returns the message with the given id, if it is still in the cache.
+    pub fn get(&self, mid: &MessageId) -> Option<&GossipsubMessage> {
+        self.msgs.get(mid)
+    }
+
+    /// Returns the ids of the messages of the given topic that are part of the gossip windows.
+    pub fn get_gossip_ids(&self, topic: &TopicHash) -> Vec<MessageId> {
+        self.history
+            .iter()
+            .take(self.gossip)
+            .flat_map(|window| window.iter())
+            .filter(|entry| entry.topics.iter().any(|t| t == topic))
+            .map(|entry| entry.mid.clone())
+            .collect()
+    }
+
+    /// Shifts the windows by one, dropping the messages of the oldest one.
+    pub fn shift(&mut self) {
+        if let Some(window) = self.history.pop() {
+            for entry in window {
+                self.msgs.remove(&entry.mid);
+            }
+        }
+        self.history.insert(0, Vec::new());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libp2p_core::PeerId;
+
+    fn gen_message(seq: u8, topics: Vec<TopicHash>) -> GossipsubMessage {
+        GossipsubMessage {
+            source: PeerId::random(),
+            data: vec![seq],
+            sequence_number: vec![seq],
+            topics,
+        }
+    }
+
+    #[test]
+    fn put_and_get() {
+        let mut mc = MessageCache::new(3, 5);
+        let topic = TopicHash::from_raw("topic".to_owned());
+        let msg = gen_message(1, vec![topic.clone()]);
+
+        mc.put(msg.clone());
+
+        assert_eq!(mc.get(&msg.id()), Some(&msg));
+        assert_eq!(mc.get_gossip_ids(&topic), vec![msg.id()]);
+        assert!(mc.get_gossip_ids(&TopicHash::from_raw("other".to_owned())).is_empty());
+    }
+
+    #[test]
+    fn gossip_is_limited_to_recent_windows() {
+        let mut mc = MessageCache::new(3, 5);
+        let topic = TopicHash::from_raw("topic".to_owned());
+
+        let old = gen_message(1, vec![topic.clone()]);
+        mc.put(old.clone());
+        for _ in 0..3 {
+            mc.shift();
+        }
+        let new = gen_message(2, vec![topic.clone()]);
+        mc.put(new.clone());
+
+        // The old message is still in the cache, but no longer advertised.
+        assert!(mc.get(&old.id()).is_some());
+        assert_eq!(mc.get_gossip_ids(&topic), vec![new.id()]);
+    }
+
+    #[test]
+    fn shift_expires_messages() {
+        let mut mc = MessageCache::new(3, 5);
+        let topic = TopicHash::from_raw("topic".to_owned());
+        let msg = gen_message(1, vec![topic.clone()]);
+        mc.put(msg.clone());
+
+        for _ in 0..4 {
+            mc.shift();
+        }
+        assert!(mc.get(&msg.id()).is_some());
+
+        mc.shift();
+        assert!(mc.get(&msg.id()).is_none());
+        assert!(mc.get_gossip_ids(&topic).is_empty());
+    }
+}
@@ -24,6 +24,7 @@ use crate::protocol::{RemoteInfo, IdentifyProtocolConfig};
 use futures::{future, prelude::*, stream, AndThen, MapErr};
 use libp2p_core::{
     Multiaddr, PeerId, PublicKey, muxing, Transport,
+    compat::{Compat03As01, Transport01},
     transport::{TransportError, ListenerEvent, upgrade::TransportUpgradeError},
     upgrade::{self, OutboundUpgradeApply, UpgradeError}
 };
@@ -57,7 +58,7 @@ impl<TTrans> IdentifyTransport<TTrans> {
     }
 }
 
-impl<TTrans, TMuxer> Transport for IdentifyTransport<TTrans>
+impl<TTrans, TMuxer> Transport01 for IdentifyTransport<TTrans>
 where
     TTrans: Transport<Output = TMuxer>,
     TTrans::Error: 'static,
@@ -69,7 +70,7 @@ where
     type Listener = stream::Empty<ListenerEvent<Self::ListenerUpgrade>, Self::Error>;
     type ListenerUpgrade = future::Empty<Self::Output, Self::Error>;
     type Dial = AndThen<
-        MapErr<TTrans::Dial, fn(TTrans::Error) -> Self::Error>,
+        MapErr<<Compat03As01<TTrans> as Transport01>::Dial, fn(TTrans::Error) -> Self::Error>,
         MapErr<IdRetriever<TMuxer>, fn(UpgradeError<IoError>) -> Self::Error>,
         fn(TMuxer) -> MapErr<IdRetriever<TMuxer>, fn(UpgradeError<IoError>) -> Self::Error>
     >;
@@ -80,7 +81,15 @@ where
 
     fn dial(self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
         // We dial a first time the node.
-        let dial = self.transport.dial(addr)
+        let dial = Transport01::dial(Compat03As01::new(self.transport), addr)
+            .map_err(|err| err.map(TransportUpgradeError::Transport))?;
+        Ok(dial.map_err::<fn(_) -> _, _>(TransportUpgradeError::Transport).and_then(|muxer| {
+            IdRetriever::new(muxer, IdentifyProtocolConfig).map_err(TransportUpgradeError::Upgrade)
+        }))
+    }
+
+    fn dial_as_listener(self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
+        let dial = Transport01::dial_as_listener(Compat03As01::new(self.transport), addr)
             .map_err(|err| err.map(TransportUpgradeError::Transport))?;
         Ok(dial.map_err::<fn(_) -> _, _>(TransportUpgradeError::Transport).and_then(|muxer| {
             IdRetriever::new(muxer, IdentifyProtocolConfig).map_err(TransportUpgradeError::Upgrade)
@@ -103,9 +112,9 @@ where TMuxer: muxing::StreamMuxer + Send + Sync + 'static,
       TMuxer::Substream: Send,
 {
     /// We are in the process of opening a substream with the remote.
-    OpeningSubstream(Arc<TMuxer>, muxing::OutboundSubstreamRefWrapFuture<Arc<TMuxer>>, IdentifyProtocolConfig),
+    OpeningSubstream(Arc<TMuxer>, Compat03As01<muxing::OutboundSubstreamRefWrapFuture<Arc<TMuxer>>>, IdentifyProtocolConfig),
     /// We opened the substream and are currently negotiating the identify protocol.
-    NegotiatingIdentify(Arc<TMuxer>, OutboundUpgradeApply<muxing::SubstreamRef<Arc<TMuxer>>, IdentifyProtocolConfig>),
+    NegotiatingIdentify(Arc<TMuxer>, Compat03As01<OutboundUpgradeApply<muxing::SubstreamRef<Arc<TMuxer>>, IdentifyProtocolConfig>>),
     /// We retreived the remote's public key and are ready to yield it when polled again.
     Finishing(Arc<TMuxer>, PublicKey),
     /// Something bad happend, or the `Future` is finished, and shouldn't be polled again.
@@ -119,7 +128,7 @@ where TMuxer: muxing::StreamMuxer + Send + Sync + 'static,
     /// Creates a new `IdRetriever` ready to be polled.
     fn new(muxer: TMuxer, config: IdentifyProtocolConfig) -> Self {
         let muxer = Arc::new(muxer);
-        let opening = muxing::outbound_from_ref_and_wrap(muxer.clone());
+        let opening = Compat03As01::new(muxing::outbound_from_ref_and_wrap(muxer.clone()));
 
         IdRetriever {
             state: IdRetrieverState::OpeningSubstream(muxer, opening, config)
@@ -143,7 +152,7 @@ where TMuxer: muxing::StreamMuxer + Send + Sync + 'static,
                 IdRetrieverState::OpeningSubstream(muxer, mut opening, config) => {
                     match opening.poll() {
                         Ok(Async::Ready(substream)) => {
-                            let upgrade = upgrade::apply_outbound(substream, config);
+                            let upgrade = Compat03As01::new(upgrade::apply_outbound(substream, config));
                             self.state = IdRetrieverState::NegotiatingIdentify(muxer, upgrade)
                         },
                         Ok(Async::NotReady) => {
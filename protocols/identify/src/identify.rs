@@ -26,23 +26,32 @@ use libp2p_core::{
     ConnectedPoint,
     Multiaddr,
     PeerId,
+    PeerRecord,
     PublicKey,
     either::EitherOutput,
+    identity::Keypair,
+    nodes::ConnectionId,
     upgrade::Negotiated
 };
 use libp2p_swarm::{
-    NetworkBehaviour,
+    AddressSource,
+    Misbehaviour,
     NetworkBehaviourAction,
     PollParameters,
     ProtocolsHandler,
     ProtocolsHandlerSelect,
-    ProtocolsHandlerUpgrErr
+    ProtocolsHandlerUpgrErr,
+    compat::NetworkBehaviour01
 };
+use log::debug;
 use smallvec::SmallVec;
-use std::{collections::HashMap, collections::VecDeque, io};
+use std::{collections::HashMap, collections::VecDeque, io, time::Duration};
 use tokio_io::{AsyncRead, AsyncWrite};
 use void::Void;
 
+/// How long the listen addresses reported by a remote are kept in the peer store.
+const LISTEN_ADDRS_TTL: Duration = Duration::from_secs(60 * 60);
+
 /// Network behaviour that automatically identifies nodes periodically, returns information
 /// about them, and answers identify queries from other nodes.
 pub struct Identify<TSubstream> {
@@ -52,14 +61,21 @@ pub struct Identify<TSubstream> {
     agent_version: String,
     /// The public key of the local node. To report on the wire.
     local_public_key: PublicKey,
-    /// For each peer we're connected to, the observed address to send back to it.
-    observed_addresses: HashMap<PeerId, Multiaddr>,
+    /// The key pair of the local node, if we sign our listen addresses.
+    local_key: Option<Keypair>,
+    /// Our latest signed peer record. Only regenerated when our addresses change.
+    local_record: Option<PeerRecord>,
+    /// For each peer we're connected to, the observed address to send back to it on each of
+    /// the connections.
+    observed_addresses: HashMap<PeerId, HashMap<ConnectionId, Multiaddr>>,
     /// List of senders to answer, with the observed multiaddr.
     to_answer: SmallVec<[(PeerId, IdentifySender<Negotiated<TSubstream>>, Multiaddr); 4]>,
     /// List of futures that send back information back to remotes.
     futures: SmallVec<[(PeerId, IdentifySenderFuture<Negotiated<TSubstream>>); 4]>,
     /// Events that need to be produced outside when polling..
     events: VecDeque<NetworkBehaviourAction<EitherOutput<Void, Void>, IdentifyEvent>>,
+    /// Information received from remotes that has yet to be recorded in the peer store.
+    to_record: SmallVec<[(PeerId, IdentifyInfo); 4]>,
 }
 
 impl<TSubstream> Identify<TSubstream> {
@@ -69,15 +85,45 @@ impl<TSubstream> Identify<TSubstream> {
             protocol_version,
             agent_version,
             local_public_key,
+            local_key: None,
+            local_record: None,
             observed_addresses: HashMap::new(),
             to_answer: SmallVec::new(),
             futures: SmallVec::new(),
             events: VecDeque::new(),
+            to_record: SmallVec::new(),
+        }
+    }
+
+    /// Creates a `Identify` that also sends to remotes a peer record of our listen addresses,
+    /// signed with the given key pair.
+    pub fn with_keypair(protocol_version: String, agent_version: String, local_key: Keypair) -> Self {
+        let mut identify = Identify::new(protocol_version, agent_version, local_key.public());
+        identify.local_key = Some(local_key);
+        identify
+    }
+
+    /// Returns a signed peer record of the given addresses, reusing the previous one if the
+    /// addresses didn't change.
+    fn local_record(&mut self, addresses: &[Multiaddr]) -> Option<PeerRecord> {
+        let key = self.local_key.as_ref()?;
+        let up_to_date = self.local_record.as_ref()
+            .map(|record| record.addresses() == addresses)
+            .unwrap_or(false);
+        if !up_to_date {
+            match PeerRecord::new(key, addresses.to_vec()) {
+                Ok(record) => self.local_record = Some(record),
+                Err(err) => {
+                    debug!("Failed to sign peer record: {}", err);
+                    self.local_record = None;
+                }
+            }
         }
+        self.local_record.clone()
     }
 }
 
-impl<TSubstream> NetworkBehaviour for Identify<TSubstream>
+impl<TSubstream> NetworkBehaviour01 for Identify<TSubstream>
 where
     TSubstream: AsyncRead + AsyncWrite,
 {
@@ -92,13 +138,22 @@ where
         Vec::new()
     }
 
-    fn inject_connected(&mut self, peer_id: PeerId, endpoint: ConnectedPoint) {
+    fn inject_connected(&mut self, _: PeerId, _: ConnectedPoint) {
+    }
+
+    fn inject_connection_established(&mut self, peer_id: &PeerId, connection: &ConnectionId, endpoint: &ConnectedPoint) {
         let observed = match endpoint {
-            ConnectedPoint::Dialer { address } => address,
-            ConnectedPoint::Listener { send_back_addr, .. } => send_back_addr,
+            ConnectedPoint::Dialer { address, .. } => address.clone(),
+            ConnectedPoint::Listener { send_back_addr, .. } => send_back_addr.clone(),
         };
 
-        self.observed_addresses.insert(peer_id, observed);
+        self.observed_addresses.entry(peer_id.clone()).or_default().insert(*connection, observed);
+    }
+
+    fn inject_connection_closed(&mut self, peer_id: &PeerId, connection: &ConnectionId, _: &ConnectedPoint) {
+        if let Some(addrs) = self.observed_addresses.get_mut(peer_id) {
+            addrs.remove(connection);
+        }
     }
 
     fn inject_disconnected(&mut self, peer_id: &PeerId, _: ConnectedPoint) {
@@ -108,10 +163,12 @@ where
     fn inject_node_event(
         &mut self,
         peer_id: PeerId,
+        connection: ConnectionId,
         event: <Self::ProtocolsHandler as ProtocolsHandler>::OutEvent,
     ) {
         match event {
             EitherOutput::Second(PeriodicIdHandlerEvent::Identified(remote)) => {
+                self.to_record.push((peer_id.clone(), remote.info.clone()));
                 self.events
                     .push_back(NetworkBehaviourAction::GenerateEvent(IdentifyEvent::Identified {
                         peer_id,
@@ -125,12 +182,20 @@ where
             }
             EitherOutput::First(sender) => {
                 let observed = self.observed_addresses.get(&peer_id)
-                    .expect("We only receive events from nodes we're connected to. We insert \
-                             into the hashmap when we connect to a node and remove only when we \
-                             disconnect; QED");
+                    .and_then(|addrs| addrs.get(&connection))
+                    .expect("We only receive events from connections that are established. We \
+                             insert into the hashmap when a connection is established and remove \
+                             only when it is closed; QED");
                 self.to_answer.push((peer_id, sender, observed.clone()));
             }
             EitherOutput::Second(PeriodicIdHandlerEvent::IdentificationError(err)) => {
+                if let Some(misbehaviour) = Misbehaviour::from_upgrade_error(&err) {
+                    self.events
+                        .push_back(NetworkBehaviourAction::ReportMisbehaviour {
+                            peer_id: peer_id.clone(),
+                            misbehaviour,
+                        });
+                }
                 self.events
                     .push_back(NetworkBehaviourAction::GenerateEvent(IdentifyEvent::Error {
                         peer_id,
@@ -149,11 +214,28 @@ where
             Self::OutEvent,
         >,
     > {
+        for (peer_id, info) in self.to_record.drain() {
+            let peer_store = params.peer_store_mut();
+            // When the remote signed its addresses, only record those, as the unsigned ones
+            // might not be authentic.
+            let addrs = match info.signed_peer_record {
+                Some(record) => record.addresses().to_vec(),
+                None => info.listen_addrs,
+            };
+            for addr in addrs {
+                peer_store.add_address(&peer_id, addr, AddressSource::Identify, Some(LISTEN_ADDRS_TTL));
+            }
+            peer_store.set_public_key(&peer_id, info.public_key);
+            peer_store.set_protocols(&peer_id, info.protocols);
+            peer_store.set_agent_version(&peer_id, info.agent_version);
+        }
+
         if let Some(event) = self.events.pop_front() {
             return Async::Ready(event);
         }
 
-        for (peer_id, sender, observed) in self.to_answer.drain() {
+        let to_answer = std::mem::replace(&mut self.to_answer, SmallVec::new());
+        for (peer_id, sender, observed) in to_answer {
             // The protocol names can be bytes, but the identify protocol except UTF-8 strings.
             // There's not much we can do to solve this conflict except strip non-UTF-8 characters.
             let protocols = params
@@ -163,6 +245,7 @@ where
 
             let mut listen_addrs: Vec<_> = params.external_addresses().collect();
             listen_addrs.extend(params.listened_addresses());
+            let signed_peer_record = self.local_record(&listen_addrs);
 
             let send_back_info = IdentifyInfo {
                 public_key: self.local_public_key.clone(),
@@ -170,6 +253,7 @@ where
                 agent_version: self.agent_version.clone(),
                 listen_addrs,
                 protocols,
+                signed_peer_record,
             };
 
             let future = sender.send(send_back_info, &observed);
@@ -250,7 +334,7 @@ mod tests {
     use std::{fmt, io};
     use tokio::runtime::current_thread;
 
-    fn transport() -> (identity::PublicKey, impl Transport<
+    fn transport() -> (identity::Keypair, impl Transport<
         Output = (PeerId, impl StreamMuxer<Substream = impl Send, OutboundSubstream = impl Send, Error = impl Into<io::Error>>),
         Listener = impl Send,
         ListenerUpgrade = impl Send,
@@ -258,7 +342,7 @@ mod tests {
         Error = impl fmt::Debug
     > + Clone) {
         let id_keys = identity::Keypair::generate_ed25519();
-        let pubkey = id_keys.public();
+        let keypair = id_keys.clone();
         let transport = TcpConfig::new()
             .nodelay(true)
             .with_upgrade(SecioConfig::new(id_keys))
@@ -270,20 +354,22 @@ mod tests {
                     .map_inbound(move |muxer| (peer_id2, muxer));
                 upgrade::apply(out.stream, upgrade, endpoint)
             });
-        (pubkey, transport)
+        (keypair, transport)
     }
 
     #[test]
     fn periodic_id_works() {
         let (mut swarm1, pubkey1) = {
-            let (pubkey, transport) = transport();
-            let protocol = Identify::new("a".to_string(), "b".to_string(), pubkey.clone());
+            let (keypair, transport) = transport();
+            let pubkey = keypair.public();
+            let protocol = Identify::with_keypair("a".to_string(), "b".to_string(), keypair);
             let swarm = Swarm::new(transport, protocol, pubkey.clone().into_peer_id());
             (swarm, pubkey)
         };
 
         let (mut swarm2, pubkey2) = {
-            let (pubkey, transport) = transport();
+            let (keypair, transport) = transport();
+            let pubkey = keypair.public();
             let protocol = Identify::new("c".to_string(), "d".to_string(), pubkey.clone());
             let swarm = Swarm::new(transport, protocol, pubkey.clone().into_peer_id());
             (swarm, pubkey)
@@ -311,6 +397,11 @@ mod tests {
                             assert_eq!(info.agent_version, "d");
                             assert!(!info.protocols.is_empty());
                             assert!(info.listen_addrs.is_empty());
+                            assert!(info.signed_peer_record.is_none());
+                            let peer_store = Swarm::peer_store(&swarm1);
+                            let peer_id = pubkey2.clone().into_peer_id();
+                            assert_eq!(peer_store.agent_version(&peer_id), Some("d"));
+                            assert_eq!(peer_store.addresses(&peer_id).count(), 0);
                             return Ok(Async::Ready(()))
                         },
                         Async::Ready(Some(IdentifyEvent::SendBack { result: Ok(()), .. })) => (),
@@ -325,6 +416,14 @@ mod tests {
                             assert_eq!(info.agent_version, "b");
                             assert!(!info.protocols.is_empty());
                             assert_eq!(info.listen_addrs.len(), 1);
+                            let record = info.signed_peer_record.expect("swarm1 signs its addresses");
+                            assert_eq!(record.peer_id(), &pubkey1.clone().into_peer_id());
+                            assert_eq!(record.addresses(), &info.listen_addrs[..]);
+                            let peer_store = Swarm::peer_store(&swarm2);
+                            let peer_id = pubkey1.clone().into_peer_id();
+                            assert_eq!(peer_store.agent_version(&peer_id), Some("b"));
+                            assert_eq!(peer_store.public_key(&peer_id), Some(&pubkey1));
+                            assert_eq!(peer_store.addresses(&peer_id).collect::<Vec<_>>(), vec![&addr]);
                             return Ok(Async::Ready(()))
                         },
                         Async::Ready(Some(IdentifyEvent::SendBack { result: Ok(()), .. })) => (),
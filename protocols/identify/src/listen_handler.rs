@@ -24,9 +24,9 @@ use libp2p_core::upgrade::{DeniedUpgrade, InboundUpgrade, OutboundUpgrade, Negot
 use libp2p_swarm::{
     KeepAlive,
     SubstreamProtocol,
-    ProtocolsHandler,
     ProtocolsHandlerEvent,
-    ProtocolsHandlerUpgrErr
+    ProtocolsHandlerUpgrErr,
+    compat::ProtocolsHandler01
 };
 use smallvec::SmallVec;
 use tokio_io::{AsyncRead, AsyncWrite};
@@ -52,7 +52,7 @@ impl<TSubstream> IdentifyListenHandler<TSubstream> {
     }
 }
 
-impl<TSubstream> ProtocolsHandler for IdentifyListenHandler<TSubstream>
+impl<TSubstream> ProtocolsHandler01 for IdentifyListenHandler<TSubstream>
 where
     TSubstream: AsyncRead + AsyncWrite,
 {
@@ -20,17 +20,18 @@
 
 use crate::protocol::{RemoteInfo, IdentifyProtocolConfig};
 use futures::prelude::*;
+use libp2p_core::clock;
 use libp2p_core::upgrade::{DeniedUpgrade, OutboundUpgrade};
 use libp2p_swarm::{
     KeepAlive,
     SubstreamProtocol,
-    ProtocolsHandler,
     ProtocolsHandlerEvent,
-    ProtocolsHandlerUpgrErr
+    ProtocolsHandlerUpgrErr,
+    compat::ProtocolsHandler01
 };
 use std::{io, marker::PhantomData, time::Duration};
 use tokio_io::{AsyncRead, AsyncWrite};
-use wasm_timer::{Delay, Instant};
+use wasm_timer::Delay;
 use void::{Void, unreachable};
 
 /// Delay between the moment we connect and the first time we identify.
@@ -75,14 +76,14 @@ impl<TSubstream> PeriodicIdHandler<TSubstream> {
         PeriodicIdHandler {
             config: IdentifyProtocolConfig,
             pending_result: None,
-            next_id: Delay::new(Instant::now() + DELAY_TO_FIRST_ID),
+            next_id: Delay::new(clock::now() + DELAY_TO_FIRST_ID),
             first_id_happened: false,
             marker: PhantomData,
         }
     }
 }
 
-impl<TSubstream> ProtocolsHandler for PeriodicIdHandler<TSubstream>
+impl<TSubstream> ProtocolsHandler01 for PeriodicIdHandler<TSubstream>
 where
     TSubstream: AsyncRead + AsyncWrite,
 {
@@ -119,7 +120,7 @@ where
     fn inject_dial_upgrade_error(&mut self, _: Self::OutboundOpenInfo, err: ProtocolsHandlerUpgrErr<<Self::OutboundProtocol as OutboundUpgrade<Self::Substream>>::Error>) {
         self.pending_result = Some(PeriodicIdHandlerEvent::IdentificationError(err));
         self.first_id_happened = true;
-        self.next_id.reset(Instant::now() + TRY_AGAIN_ON_ERR);
+        self.next_id.reset(clock::now() + TRY_AGAIN_ON_ERR);
     }
 
     #[inline]
@@ -151,7 +152,7 @@ where
         match self.next_id.poll()? {
             Async::NotReady => Ok(Async::NotReady),
             Async::Ready(()) => {
-                self.next_id.reset(Instant::now() + DELAY_TO_NEXT_ID);
+                self.next_id.reset(clock::now() + DELAY_TO_NEXT_ID);
                 let ev = ProtocolsHandlerEvent::OutboundSubstreamRequest {
                     protocol: SubstreamProtocol::new(self.config.clone()),
                     info: (),
@@ -24,7 +24,10 @@ use futures::{future::{self, FutureResult}, Async, AsyncSink, Future, Poll, Sink
 use futures::try_ready;
 use libp2p_core::{
     Multiaddr,
+    PeerRecord,
     PublicKey,
+    SignedEnvelope,
+    compat::Compat01As03,
     upgrade::{InboundUpgrade, OutboundUpgrade, UpgradeInfo, Negotiated}
 };
 use log::{debug, trace};
@@ -78,6 +81,9 @@ impl<T> IdentifySender<T> where T: AsyncWrite {
         message.set_listenAddrs(listen_addrs);
         message.set_observedAddr(observed_addr.to_vec());
         message.set_protocols(RepeatedField::from_vec(info.protocols));
+        if let Some(record) = info.signed_peer_record {
+            message.set_signedPeerRecord(record.into_signed_envelope().into_protobuf_encoding());
+        }
 
         let bytes = message
             .write_to_bytes()
@@ -137,6 +143,11 @@ pub struct IdentifyInfo {
     pub listen_addrs: Vec<Multiaddr>,
     /// Protocols supported by the node, e.g. `/ipfs/ping/1.0.0`.
     pub protocols: Vec<String>,
+    /// Addresses of the node, signed with its identity key.
+    ///
+    /// When received from a remote, the record has been verified to be signed by the key in
+    /// `public_key`. Invalid records are discarded.
+    pub signed_peer_record: Option<PeerRecord>,
 }
 
 impl UpgradeInfo for IdentifyProtocolConfig {
@@ -154,13 +165,13 @@ where
 {
     type Output = IdentifySender<Negotiated<C>>;
     type Error = IoError;
-    type Future = FutureResult<Self::Output, IoError>;
+    type Future = Compat01As03<FutureResult<Self::Output, IoError>>;
 
     fn upgrade_inbound(self, socket: Negotiated<C>, _: Self::Info) -> Self::Future {
         trace!("Upgrading inbound connection");
         let socket = Framed::new(socket, codec::UviBytes::default());
         let sender = IdentifySender { inner: socket };
-        future::ok(sender)
+        Compat01As03::new(future::ok(sender))
     }
 }
 
@@ -170,13 +181,13 @@ where
 {
     type Output = RemoteInfo;
     type Error = IoError;
-    type Future = IdentifyOutboundFuture<Negotiated<C>>;
+    type Future = Compat01As03<IdentifyOutboundFuture<Negotiated<C>>>;
 
     fn upgrade_outbound(self, socket: Negotiated<C>, _: Self::Info) -> Self::Future {
-        IdentifyOutboundFuture {
+        Compat01As03::new(IdentifyOutboundFuture {
             inner: Framed::new(socket, codec::UviBytes::<BytesMut>::default()),
             shutdown: false,
-        }
+        })
     }
 }
 
@@ -251,6 +262,24 @@ fn parse_proto_msg(msg: BytesMut) -> Result<(IdentifyInfo, Multiaddr), IoError>
             let public_key = PublicKey::from_protobuf_encoding(msg.get_publicKey())
                 .map_err(|e| IoError::new(IoErrorKind::InvalidData, e))?;
 
+            // An invalid signed record doesn't invalidate the rest of the message, as the
+            // record is optional.
+            let signed_peer_record = if msg.has_signedPeerRecord() {
+                SignedEnvelope::from_protobuf_encoding(msg.get_signedPeerRecord())
+                    .map_err(|err| err.to_string())
+                    .and_then(|envelope| PeerRecord::from_signed_envelope(envelope)
+                        .map_err(|err| err.to_string()))
+                    .and_then(|record| if record.peer_id() == &public_key.clone().into_peer_id() {
+                        Ok(record)
+                    } else {
+                        Err("peer record of another peer".to_string())
+                    })
+                    .map_err(|err| debug!("Discarding invalid signed peer record: {}", err))
+                    .ok()
+            } else {
+                None
+            };
+
             let observed_addr = bytes_to_multiaddr(msg.take_observedAddr())?;
             let info = IdentifyInfo {
                 public_key,
@@ -258,6 +287,7 @@ fn parse_proto_msg(msg: BytesMut) -> Result<(IdentifyInfo, Multiaddr), IoError>
                 agent_version: msg.take_agentVersion(),
                 listen_addrs,
                 protocols: msg.take_protocols().into_vec(),
+                signed_peer_record,
             };
 
             Ok((info, observed_addr))
@@ -269,24 +299,32 @@ fn parse_proto_msg(msg: BytesMut) -> Result<(IdentifyInfo, Multiaddr), IoError>
 
 #[cfg(test)]
 mod tests {
-    use crate::protocol::{IdentifyInfo, RemoteInfo, IdentifyProtocolConfig};
+    use bytes::BytesMut;
+    use crate::protocol::{IdentifyInfo, RemoteInfo, IdentifyProtocolConfig, parse_proto_msg};
+    use crate::structs_proto;
     use tokio::runtime::current_thread::Runtime;
     use libp2p_tcp::TcpConfig;
     use futures::{Future, Stream};
     use libp2p_core::{
         identity,
-        Transport,
+        Multiaddr,
+        PeerRecord,
+        compat::{Compat03As01, Transport01},
         transport::ListenerEvent,
         upgrade::{apply_outbound, apply_inbound}
     };
+    use protobuf::Message;
     use std::{io, sync::mpsc, thread};
 
     #[test]
     fn correct_transfer() {
         // We open a server and a client, send info from the server to the client, and check that
         // they were successfully received.
-        let send_pubkey = identity::Keypair::generate_ed25519().public();
+        let send_keypair = identity::Keypair::generate_ed25519();
+        let send_pubkey = send_keypair.public();
         let recv_pubkey = send_pubkey.clone();
+        let record = PeerRecord::new(&send_keypair, vec!["/ip4/80.81.82.83/tcp/500".parse().unwrap()]).unwrap();
+        let recv_record = record.clone();
 
         let (tx, rx) = mpsc::channel();
 
@@ -313,7 +351,7 @@ mod tests {
                 .map_err(|(err, _)| err)
                 .and_then(|(client, _)| client.unwrap().0)
                 .and_then(|socket| {
-                    apply_inbound(socket, IdentifyProtocolConfig)
+                    Compat03As01::new(apply_inbound(socket, IdentifyProtocolConfig))
                         .map_err(|e| io::Error::new(io::ErrorKind::Other, e))
                 })
                 .and_then(|sender| {
@@ -327,6 +365,7 @@ mod tests {
                                 "/ip6/::1/udp/1000".parse().unwrap(),
                             ],
                             protocols: vec!["proto1".to_string(), "proto2".to_string()],
+                            signed_peer_record: Some(record),
                         },
                         &"/ip4/100.101.102.103/tcp/5000".parse().unwrap(),
                     )
@@ -340,7 +379,7 @@ mod tests {
         let future = transport.dial(rx.recv().unwrap())
             .unwrap()
             .and_then(|socket| {
-                apply_outbound(socket, IdentifyProtocolConfig)
+                Compat03As01::new(apply_outbound(socket, IdentifyProtocolConfig))
                     .map_err(|e| io::Error::new(io::ErrorKind::Other, e))
             })
             .and_then(|RemoteInfo { info, observed_addr, .. }| {
@@ -352,6 +391,7 @@ mod tests {
                     &["/ip4/80.81.82.83/tcp/500".parse().unwrap(),
                       "/ip6/::1/udp/1000".parse().unwrap()]);
                 assert_eq!(info.protocols, &["proto1".to_string(), "proto2".to_string()]);
+                assert_eq!(info.signed_peer_record, Some(recv_record));
                 Ok(())
             });
 
@@ -359,4 +399,21 @@ mod tests {
         let _ = rt.block_on(future).unwrap();
         bg_thread.join().unwrap();
     }
+
+    #[test]
+    fn peer_record_of_another_peer_is_discarded() {
+        let keypair = identity::Keypair::generate_ed25519();
+        let other = identity::Keypair::generate_ed25519();
+        let record = PeerRecord::new(&other, vec!["/ip4/80.81.82.83/tcp/500".parse().unwrap()]).unwrap();
+
+        let mut message = structs_proto::Identify::new();
+        message.set_publicKey(keypair.public().into_protobuf_encoding());
+        message.set_observedAddr("/ip4/100.101.102.103/tcp/5000".parse::<Multiaddr>().unwrap().to_vec());
+        message.set_signedPeerRecord(record.into_signed_envelope().into_protobuf_encoding());
+        let bytes = message.write_to_bytes().unwrap();
+
+        let (info, _) = parse_proto_msg(BytesMut::from(bytes)).unwrap();
+        assert_eq!(info.public_key, keypair.public());
+        assert!(info.signed_peer_record.is_none());
+    }
 }
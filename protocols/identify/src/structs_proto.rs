@@ -30,6 +30,7 @@ pub struct Identify {
     listenAddrs: ::protobuf::RepeatedField<::std::vec::Vec<u8>>,
     observedAddr: ::protobuf::SingularField<::std::vec::Vec<u8>>,
     protocols: ::protobuf::RepeatedField<::std::string::String>,
+    signedPeerRecord: ::protobuf::SingularField<::std::vec::Vec<u8>>,
     // special fields
     pub unknown_fields: ::protobuf::UnknownFields,
     pub cached_size: ::protobuf::CachedSize,
@@ -233,6 +234,42 @@ impl Identify {
     pub fn get_protocols(&self) -> &[::std::string::String] {
         &self.protocols
     }
+
+    // optional bytes signedPeerRecord = 8;
+
+    pub fn clear_signedPeerRecord(&mut self) {
+        self.signedPeerRecord.clear();
+    }
+
+    pub fn has_signedPeerRecord(&self) -> bool {
+        self.signedPeerRecord.is_some()
+    }
+
+    // Param is passed by value, moved
+    pub fn set_signedPeerRecord(&mut self, v: ::std::vec::Vec<u8>) {
+        self.signedPeerRecord = ::protobuf::SingularField::some(v);
+    }
+
+    // Mutable pointer to the field.
+    // If field is not initialized, it is initialized with default value first.
+    pub fn mut_signedPeerRecord(&mut self) -> &mut ::std::vec::Vec<u8> {
+        if self.signedPeerRecord.is_none() {
+            self.signedPeerRecord.set_default();
+        }
+        self.signedPeerRecord.as_mut().unwrap()
+    }
+
+    // Take field
+    pub fn take_signedPeerRecord(&mut self) -> ::std::vec::Vec<u8> {
+        self.signedPeerRecord.take().unwrap_or_else(|| ::std::vec::Vec::new())
+    }
+
+    pub fn get_signedPeerRecord(&self) -> &[u8] {
+        match self.signedPeerRecord.as_ref() {
+            Some(v) => &v,
+            None => &[],
+        }
+    }
 }
 
 impl ::protobuf::Message for Identify {
@@ -262,6 +299,9 @@ impl ::protobuf::Message for Identify {
                 3 => {
                     ::protobuf::rt::read_repeated_string_into(wire_type, is, &mut self.protocols)?;
                 },
+                8 => {
+                    ::protobuf::rt::read_singular_bytes_into(wire_type, is, &mut self.signedPeerRecord)?;
+                },
                 _ => {
                     ::protobuf::rt::read_unknown_or_skip_group(field_number, wire_type, is, self.mut_unknown_fields())?;
                 },
@@ -292,6 +332,9 @@ impl ::protobuf::Message for Identify {
         for value in &self.protocols {
             my_size += ::protobuf::rt::string_size(3, &value);
         };
+        if let Some(ref v) = self.signedPeerRecord.as_ref() {
+            my_size += ::protobuf::rt::bytes_size(8, &v);
+        }
         my_size += ::protobuf::rt::unknown_fields_size(self.get_unknown_fields());
         self.cached_size.set(my_size);
         my_size
@@ -316,6 +359,9 @@ impl ::protobuf::Message for Identify {
         for v in &self.protocols {
             os.write_string(3, &v)?;
         };
+        if let Some(ref v) = self.signedPeerRecord.as_ref() {
+            os.write_bytes(8, &v)?;
+        }
         os.write_unknown_fields(self.get_unknown_fields())?;
         ::std::result::Result::Ok(())
     }
@@ -388,6 +434,11 @@ impl ::protobuf::Message for Identify {
                     |m: &Identify| { &m.protocols },
                     |m: &mut Identify| { &mut m.protocols },
                 ));
+                fields.push(::protobuf::reflect::accessor::make_singular_field_accessor::<_, ::protobuf::types::ProtobufTypeBytes>(
+                    "signedPeerRecord",
+                    |m: &Identify| { &m.signedPeerRecord },
+                    |m: &mut Identify| { &mut m.signedPeerRecord },
+                ));
                 ::protobuf::reflect::MessageDescriptor::new::<Identify>(
                     "Identify",
                     fields,
@@ -416,6 +467,7 @@ impl ::protobuf::Clear for Identify {
         self.clear_listenAddrs();
         self.clear_observedAddr();
         self.clear_protocols();
+        self.clear_signedPeerRecord();
         self.unknown_fields.clear();
     }
 }
@@ -433,50 +485,13 @@ impl ::protobuf::reflect::ProtobufValue for Identify {
 }
 
 static file_descriptor_proto_data: &'static [u8] = b"\
-    \n\rstructs.proto\"\xda\x01\n\x08Identify\x12(\n\x0fprotocolVersion\x18\
+    \n\rstructs.proto\"\x86\x02\n\x08Identify\x12(\n\x0fprotocolVersion\x18\
     \x05\x20\x01(\tR\x0fprotocolVersion\x12\"\n\x0cagentVersion\x18\x06\x20\
     \x01(\tR\x0cagentVersion\x12\x1c\n\tpublicKey\x18\x01\x20\x01(\x0cR\tpub\
     licKey\x12\x20\n\x0blistenAddrs\x18\x02\x20\x03(\x0cR\x0blistenAddrs\x12\
     \"\n\x0cobservedAddr\x18\x04\x20\x01(\x0cR\x0cobservedAddr\x12\x1c\n\tpr\
-    otocols\x18\x03\x20\x03(\tR\tprotocolsJ\xc2\t\n\x06\x12\x04\0\0\x16\x01\
-    \n\n\n\x02\x04\0\x12\x04\0\0\x16\x01\n\n\n\x03\x04\0\x01\x12\x03\0\x08\
-    \x10\nX\n\x04\x04\0\x02\0\x12\x03\x02\x02&\x1a8\x20protocolVersion\x20de\
-    termines\x20compatibility\x20between\x20peers\n\"\x11\x20e.g.\x20ipfs/1.\
-    0.0\n\n\x0c\n\x05\x04\0\x02\0\x04\x12\x03\x02\x02\n\n\x0c\n\x05\x04\0\
-    \x02\0\x05\x12\x03\x02\x0b\x11\n\x0c\n\x05\x04\0\x02\0\x01\x12\x03\x02\
-    \x12!\n\x0c\n\x05\x04\0\x02\0\x03\x12\x03\x02$%\n\x9f\x01\n\x04\x04\0\
-    \x02\x01\x12\x03\x06\x02#\x1a|\x20agentVersion\x20is\x20like\x20a\x20Use\
-    rAgent\x20string\x20in\x20browsers,\x20or\x20client\x20version\x20in\x20\
-    bittorrent\n\x20includes\x20the\x20client\x20name\x20and\x20client.\n\"\
-    \x14\x20e.g.\x20go-ipfs/0.1.0\n\n\x0c\n\x05\x04\0\x02\x01\x04\x12\x03\
-    \x06\x02\n\n\x0c\n\x05\x04\0\x02\x01\x05\x12\x03\x06\x0b\x11\n\x0c\n\x05\
-    \x04\0\x02\x01\x01\x12\x03\x06\x12\x1e\n\x0c\n\x05\x04\0\x02\x01\x03\x12\
-    \x03\x06!\"\n\xe3\x01\n\x04\x04\0\x02\x02\x12\x03\x0b\x02\x1f\x1a\xd5\
-    \x01\x20publicKey\x20is\x20this\x20node's\x20public\x20key\x20(which\x20\
-    also\x20gives\x20its\x20node.ID)\n\x20-\x20may\x20not\x20need\x20to\x20b\
-    e\x20sent,\x20as\x20secure\x20channel\x20implies\x20it\x20has\x20been\
-    \x20sent.\n\x20-\x20then\x20again,\x20if\x20we\x20change\x20/\x20disable\
-    \x20secure\x20channel,\x20may\x20still\x20want\x20it.\n\n\x0c\n\x05\x04\
-    \0\x02\x02\x04\x12\x03\x0b\x02\n\n\x0c\n\x05\x04\0\x02\x02\x05\x12\x03\
-    \x0b\x0b\x10\n\x0c\n\x05\x04\0\x02\x02\x01\x12\x03\x0b\x11\x1a\n\x0c\n\
-    \x05\x04\0\x02\x02\x03\x12\x03\x0b\x1d\x1e\n]\n\x04\x04\0\x02\x03\x12\
-    \x03\x0e\x02!\x1aP\x20listenAddrs\x20are\x20the\x20multiaddrs\x20the\x20\
-    sender\x20node\x20listens\x20for\x20open\x20connections\x20on\n\n\x0c\n\
-    \x05\x04\0\x02\x03\x04\x12\x03\x0e\x02\n\n\x0c\n\x05\x04\0\x02\x03\x05\
-    \x12\x03\x0e\x0b\x10\n\x0c\n\x05\x04\0\x02\x03\x01\x12\x03\x0e\x11\x1c\n\
-    \x0c\n\x05\x04\0\x02\x03\x03\x12\x03\x0e\x1f\x20\n\x81\x02\n\x04\x04\0\
-    \x02\x04\x12\x03\x13\x02\"\x1a\xf3\x01\x20oservedAddr\x20is\x20the\x20mu\
-    ltiaddr\x20of\x20the\x20remote\x20endpoint\x20that\x20the\x20sender\x20n\
-    ode\x20perceives\n\x20this\x20is\x20useful\x20information\x20to\x20conve\
-    y\x20to\x20the\x20other\x20side,\x20as\x20it\x20helps\x20the\x20remote\
-    \x20endpoint\n\x20determine\x20whether\x20its\x20connection\x20to\x20the\
-    \x20local\x20peer\x20goes\x20through\x20NAT.\n\n\x0c\n\x05\x04\0\x02\x04\
-    \x04\x12\x03\x13\x02\n\n\x0c\n\x05\x04\0\x02\x04\x05\x12\x03\x13\x0b\x10\
-    \n\x0c\n\x05\x04\0\x02\x04\x01\x12\x03\x13\x11\x1d\n\x0c\n\x05\x04\0\x02\
-    \x04\x03\x12\x03\x13\x20!\n\x0b\n\x04\x04\0\x02\x05\x12\x03\x15\x02\x20\
-    \n\x0c\n\x05\x04\0\x02\x05\x04\x12\x03\x15\x02\n\n\x0c\n\x05\x04\0\x02\
-    \x05\x05\x12\x03\x15\x0b\x11\n\x0c\n\x05\x04\0\x02\x05\x01\x12\x03\x15\
-    \x12\x1b\n\x0c\n\x05\x04\0\x02\x05\x03\x12\x03\x15\x1e\x1f\
+    otocols\x18\x03\x20\x03(\tR\tprotocols\x12*\n\x10signedPeerRecord\x18\
+    \x08\x20\x01(\x0cR\x10signedPeerRecord\
 ";
 
 static mut file_descriptor_proto_lazy: ::protobuf::lazy::Lazy<::protobuf::descriptor::FileDescriptorProto> = ::protobuf::lazy::Lazy {
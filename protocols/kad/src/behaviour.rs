@@ -28,19 +28,18 @@ use crate::jobs::*;
 use crate::kbucket::{self, KBucketsTable, NodeStatus};
 use crate::protocol::{KadConnectionType, KadPeer};
 use crate::query::{Query, QueryId, QueryPool, QueryConfig, QueryPoolState};
-use crate::record::{store::{self, RecordStore}, Record, ProviderRecord};
-use fnv::{FnvHashMap, FnvHashSet};
+use crate::record::{store::{self, RecordStore}, Record, RecordValidator, ProviderRecord};
+use fnv::FnvHashMap;
 use futures::prelude::*;
-use libp2p_core::{ConnectedPoint, Multiaddr, PeerId};
-use libp2p_swarm::{NetworkBehaviour, NetworkBehaviourAction, PollParameters, ProtocolsHandler};
+use libp2p_core::{ConnectedPoint, Multiaddr, PeerId, PeerRecord, clock, nodes::ConnectionId};
+use libp2p_swarm::{Misbehaviour, NetworkBehaviourAction, NotifyHandler, PollParameters, ProtocolsHandler, compat::NetworkBehaviour01};
 use log::{info, debug, warn};
 use multihash::Multihash;
 use smallvec::SmallVec;
-use std::{borrow::Cow, error, iter, marker::PhantomData, time::Duration};
+use std::{borrow::Cow, error, iter, marker::PhantomData, sync::Arc, time::Duration};
 use std::collections::VecDeque;
 use std::num::NonZeroUsize;
 use tokio_io::{AsyncRead, AsyncWrite};
-use wasm_timer::Instant;
 
 /// Network behaviour that handles Kademlia.
 pub struct Kademlia<TSubstream, TStore> {
@@ -50,13 +49,16 @@ pub struct Kademlia<TSubstream, TStore> {
     /// An optional protocol name override to segregate DHTs in the network.
     protocol_name_override: Option<Cow<'static, [u8]>>,
 
+    /// Whether the local node answers requests from remote peers.
+    mode: Mode,
+
     /// The currently active (i.e. in-progress) queries.
     queries: QueryPool<QueryInner>,
 
-    /// The currently connected peers.
+    /// The currently connected peers, with their connections from the oldest to the newest.
     ///
     /// This is a superset of the connected peers currently in the routing table.
-    connected_peers: FnvHashSet<PeerId>,
+    connected_peers: FnvHashMap<PeerId, SmallVec<[ConnectionId; 2]>>,
 
     /// A list of pending request to peers that are not currently connected.
     /// These requests are sent as soon as a connection to the peer is established.
@@ -76,9 +78,19 @@ pub struct Kademlia<TSubstream, TStore> {
     /// The TTL of provider records.
     provider_record_ttl: Option<Duration>,
 
+    /// The validator for records, if any.
+    record_validator: Option<Arc<dyn RecordValidator>>,
+
+    /// The latest signed peer records of the peers in the routing table, and of the
+    /// local node, attached to the peers reported to remotes.
+    peer_records: FnvHashMap<PeerId, PeerRecord>,
+
     /// Queued events to return when the behaviour is being polled.
     queued_events: VecDeque<NetworkBehaviourAction<KademliaHandlerIn<QueryId>, KademliaEvent>>,
 
+    /// The statistics of the finished queries that have not been taken yet.
+    query_stats: VecDeque<QueryStats>,
+
     /// Marker to pin the generics.
     marker: PhantomData<TSubstream>,
 
@@ -86,6 +98,10 @@ pub struct Kademlia<TSubstream, TStore> {
     store: TStore,
 }
 
+/// The maximum number of `QueryStats` kept by a `Kademlia` behaviour until
+/// they are taken with [`Kademlia::take_query_stats`].
+pub const MAX_QUERY_STATS: usize = 256;
+
 /// The configuration for the `Kademlia` behaviour.
 ///
 /// The configuration is consumed by [`Kademlia::new`].
@@ -94,11 +110,13 @@ pub struct KademliaConfig {
     kbucket_pending_timeout: Duration,
     query_config: QueryConfig,
     protocol_name_override: Option<Cow<'static, [u8]>>,
+    mode: Mode,
     record_ttl: Option<Duration>,
     record_replication_interval: Option<Duration>,
     record_publication_interval: Option<Duration>,
     provider_record_ttl: Option<Duration>,
     provider_publication_interval: Option<Duration>,
+    record_validator: Option<Arc<dyn RecordValidator>>,
 }
 
 impl Default for KademliaConfig {
@@ -107,11 +125,13 @@ impl Default for KademliaConfig {
             kbucket_pending_timeout: Duration::from_secs(60),
             query_config: QueryConfig::default(),
             protocol_name_override: None,
+            mode: Mode::Server,
             record_ttl: Some(Duration::from_secs(36 * 60 * 60)),
             record_replication_interval: Some(Duration::from_secs(60 * 60)),
             record_publication_interval: Some(Duration::from_secs(24 * 60 * 60)),
             provider_publication_interval: Some(Duration::from_secs(12 * 60 * 60)),
             provider_record_ttl: Some(Duration::from_secs(24 * 60 * 60)),
+            record_validator: None,
         }
     }
 }
@@ -126,6 +146,15 @@ impl KademliaConfig {
         self
     }
 
+    /// Sets whether the local node operates in client or server mode.
+    ///
+    /// The default is [`Mode::Server`]. The mode can be changed
+    /// later on through [`Kademlia::set_mode`].
+    pub fn set_mode(&mut self, mode: Mode) -> &mut Self {
+        self.mode = mode;
+        self
+    }
+
     /// Sets the timeout for a single query.
     ///
     /// > **Note**: A single query usually comprises at least as many requests
@@ -137,6 +166,21 @@ impl KademliaConfig {
         self
     }
 
+    /// Sets the number of disjoint paths followed by iterative queries.
+    ///
+    /// With `Some(d)`, the lookups for the closest peers to a key, on which
+    /// queries like [`Kademlia::get_record`] and [`Kademlia::get_providers`]
+    /// are based, follow `d` paths that do not share any peers, as described
+    /// in the S/Kademlia paper: every peer is only contacted on behalf of, and
+    /// only counts towards the results of, a single path. Such a lookup
+    /// succeeds as long as one of the paths is free of peers returning bogus
+    /// results, at the cost of contacting more peers. The default is `None`,
+    /// i.e. lookups follow a single path.
+    pub fn set_disjoint_query_paths(&mut self, paths: Option<NonZeroUsize>) -> &mut Self {
+        self.query_config.disjoint_paths = paths;
+        self
+    }
+
     /// Sets the replication factor to use.
     ///
     /// The replication factor determines to how many closest peers
@@ -219,6 +263,19 @@ impl KademliaConfig {
         self.provider_publication_interval = interval;
         self
     }
+
+    /// Sets the validator for (value-)records.
+    ///
+    /// Records rejected by the validator are neither stored locally
+    /// nor returned as the result of a [`Kademlia::get_record`] query.
+    /// By default, all records are accepted.
+    pub fn set_record_validator<V>(&mut self, validator: V) -> &mut Self
+    where
+        V: RecordValidator + 'static
+    {
+        self.record_validator = Some(Arc::new(validator));
+        self
+    }
 }
 
 impl<TSubstream, TStore> Kademlia<TSubstream, TStore>
@@ -253,14 +310,18 @@ where
             store,
             kbuckets: KBucketsTable::new(local_key, config.kbucket_pending_timeout),
             protocol_name_override: config.protocol_name_override,
+            mode: config.mode,
             queued_events: VecDeque::with_capacity(config.query_config.replication_factor.get()),
             queries: QueryPool::new(config.query_config),
+            query_stats: VecDeque::new(),
             connected_peers: Default::default(),
             pending_rpcs,
             add_provider_job,
             put_record_job,
             record_ttl: config.record_ttl,
             provider_record_ttl: config.provider_record_ttl,
+            record_validator: config.record_validator,
+            peer_records: Default::default(),
             marker: PhantomData,
         }
     }
@@ -302,7 +363,7 @@ where
             kbucket::Entry::Absent(entry) => {
                 let addresses = Addresses::new(address);
                 let status =
-                    if self.connected_peers.contains(peer) {
+                    if self.connected_peers.contains_key(peer) {
                         NodeStatus::Connected
                     } else {
                         NodeStatus::Disconnected
@@ -331,6 +392,82 @@ where
         }
     }
 
+    /// Adds a signed peer record, i.e. addresses of a peer signed by that peer.
+    ///
+    /// The addresses of the record are added to the routing table as with
+    /// [`Kademlia::add_address`], and the record is sent along with the addresses
+    /// of the peer to remotes, which can then verify them. The record of the local
+    /// node is sent when advertising the local node as a provider.
+    ///
+    /// The record is ignored if a record with a higher or equal sequence number is
+    /// already known for the peer.
+    pub fn add_peer_record(&mut self, record: PeerRecord) {
+        let peer = record.peer_id().clone();
+        let addresses = record.addresses().to_vec();
+        if !self.store_peer_record(record) {
+            return
+        }
+        if &peer != self.kbuckets.local_key().preimage() {
+            for address in addresses {
+                self.add_address(&peer, address);
+            }
+        }
+    }
+
+    /// Returns the latest signed peer record known for a peer.
+    pub fn peer_record(&self, peer: &PeerId) -> Option<&PeerRecord> {
+        self.peer_records.get(peer)
+    }
+
+    /// Stores a signed peer record, unless a newer record of the same peer is known.
+    /// Returns `true` if the record has been stored.
+    fn store_peer_record(&mut self, record: PeerRecord) -> bool {
+        match self.peer_records.get(record.peer_id()) {
+            Some(known) if known.seq() >= record.seq() => false,
+            _ => {
+                self.peer_records.insert(record.peer_id().clone(), record);
+                true
+            }
+        }
+    }
+
+    /// Stores a signed peer record received from a remote, if the peer is in the routing table.
+    fn peer_record_received(&mut self, record: &PeerRecord) {
+        let key = kbucket::Key::new(record.peer_id().clone());
+        if self.kbuckets.entry(&key).value().is_some() {
+            self.store_peer_record(record.clone());
+        }
+    }
+
+    /// Returns the mode the local node currently operates in.
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    /// Switches the local node between client and server mode, e.g. as a
+    /// result of a change in its reachability.
+    ///
+    /// The change applies to existing connections as well as to new ones.
+    ///
+    /// > **Note**: The protocols reported by `PollParameters::supported_protocols`,
+    /// > e.g. to remote peers via the identify protocol, are determined once when
+    /// > the `Swarm` is created and thus reflect the mode configured initially.
+    pub fn set_mode(&mut self, mode: Mode) {
+        if self.mode == mode {
+            return
+        }
+        self.mode = mode;
+        for (peer_id, connections) in &self.connected_peers {
+            for connection in connections {
+                self.queued_events.push_back(NetworkBehaviourAction::NotifyHandler {
+                    peer_id: peer_id.clone(),
+                    handler: NotifyHandler::One(*connection),
+                    event: KademliaHandlerIn::SetAllowListening(mode == Mode::Server),
+                });
+            }
+        }
+    }
+
     /// Returns an iterator over all peer IDs of nodes currently contained in a bucket
     /// of the Kademlia routing table.
     pub fn kbuckets_entries(&mut self) -> impl Iterator<Item = &PeerId> {
@@ -355,15 +492,24 @@ where
     /// Performs a lookup for a record in the DHT.
     ///
     /// The result of this operation is delivered in [`KademliaEvent::GetRecordResult`].
+    ///
+    /// A record found in local storage counts towards the quorum like the records
+    /// of other peers, provided that it passes the validation of the configured
+    /// [`RecordValidator`], if any. Otherwise it is removed from local storage.
     pub fn get_record(&mut self, key: &Multihash, quorum: Quorum) {
         let quorum = quorum.eval(self.queries.config().replication_factor);
         let mut records = Vec::with_capacity(quorum.get());
+        let mut rejected = Vec::new();
 
-        if let Some(record) = self.store.get(key) {
-            if record.is_expired(Instant::now()) {
+        if let Some(record) = self.store.get(key).map(Cow::into_owned) {
+            if record.is_expired(clock::now()) {
                 self.store.remove(key)
+            } else if !self.is_valid(&record) {
+                debug!("Local record rejected: {:?}", record.key);
+                self.store.remove(key);
+                rejected.push(record);
             } else {
-                records.push(record.into_owned());
+                records.push(record);
                 if quorum.get() == 1 {
                     self.queued_events.push_back(NetworkBehaviourAction::GenerateEvent(
                         KademliaEvent::GetRecordResult(Ok(GetRecordOk { records }))
@@ -374,7 +520,13 @@ where
         }
 
         let target = kbucket::Key::from(key.clone());
-        let info = QueryInfo::GetRecord { key: key.clone(), records, quorum, cache_at: None };
+        let info = QueryInfo::GetRecord {
+            key: key.clone(),
+            records,
+            rejected,
+            quorum,
+            cache_at: None
+        };
         let peers = self.kbuckets.closest_keys(&target);
         let inner = QueryInner::new(info);
         self.queries.add_iter_closest(target.clone(), peers, inner);
@@ -394,9 +546,17 @@ where
     /// does not update the record's expiration in local storage, thus a given record
     /// with an explicit expiration will always expire at that instant and until then
     /// is subject to regular (re-)replication and (re-)publication.
+    ///
+    /// If a [`RecordValidator`] is configured, the record must pass its validation.
     pub fn put_record(&mut self, mut record: Record, quorum: Quorum) {
         record.publisher = Some(self.kbuckets.local_key().preimage().clone());
-        if let Err(err) = self.store.put(record.clone()) {
+        if !self.is_valid(&record) {
+            self.queued_events.push_back(NetworkBehaviourAction::GenerateEvent(
+                KademliaEvent::PutRecordResult(Err(
+                    PutRecordError::Rejected { key: record.key }
+                ))
+            ));
+        } else if let Err(err) = self.store.put(record.clone()) {
             self.queued_events.push_back(NetworkBehaviourAction::GenerateEvent(
                 KademliaEvent::PutRecordResult(Err(
                     PutRecordError::LocalStorageError {
@@ -407,7 +567,7 @@ where
             ));
         } else {
             record.expires = record.expires.or_else(||
-                self.record_ttl.map(|ttl| Instant::now() + ttl));
+                self.record_ttl.map(|ttl| clock::now() + ttl));
             let quorum = quorum.eval(self.queries.config().replication_factor);
             let target = kbucket::Key::from(record.key.clone());
             let peers = self.kbuckets.closest_keys(&target);
@@ -440,6 +600,15 @@ where
         &mut self.store
     }
 
+    /// Takes the statistics of the queries that finished since the last call,
+    /// in the order in which they finished.
+    ///
+    /// Only the statistics of the last `MAX_QUERY_STATS` queries are kept
+    /// between calls.
+    pub fn take_query_stats(&mut self) -> impl Iterator<Item = QueryStats> + '_ {
+        self.query_stats.drain(..)
+    }
+
     /// Bootstraps the local node to join the DHT.
     ///
     /// Bootstrapping is a multi-step operation that starts with a lookup of the local node's
@@ -532,11 +701,15 @@ where
         let others_iter = peers.filter(|p| p.node_id != local_id);
 
         for peer in others_iter.clone() {
+            if let Some(record) = &peer.signed_record {
+                self.peer_record_received(record);
+            }
             self.queued_events.push_back(NetworkBehaviourAction::GenerateEvent(
                 KademliaEvent::Discovered {
                     peer_id: peer.node_id.clone(),
                     addresses: peer.multiaddrs.clone(),
                     ty: peer.connection_ty,
+                    signed_record: peer.signed_record.clone(),
                 }
             ));
         }
@@ -557,11 +730,12 @@ where
         if target == self.kbuckets.local_key() {
             Vec::new()
         } else {
+            let peer_records = &self.peer_records;
             self.kbuckets
                 .closest(target)
                 .filter(|e| e.node.key.preimage() != source)
                 .take(self.queries.config().replication_factor.get())
-                .map(KadPeer::from)
+                .map(|e| with_peer_record(KadPeer::from(e), peer_records))
                 .collect()
         }
     }
@@ -569,12 +743,14 @@ where
     /// Collects all peers who are known to be providers of the value for a given `Multihash`.
     fn provider_peers(&mut self, key: &Multihash, source: &PeerId) -> Vec<KadPeer> {
         let kbuckets = &mut self.kbuckets;
+        let peer_records = &self.peer_records;
         self.store.providers(key)
             .into_iter()
             .filter_map(move |p|
                 if &p.provider != source {
                     let key = kbucket::Key::new(p.provider.clone());
-                    kbuckets.entry(&key).view().map(|e| KadPeer::from(e.to_owned()))
+                    kbuckets.entry(&key).view()
+                        .map(|e| with_peer_record(KadPeer::from(e.to_owned()), peer_records))
                 } else {
                     None
                 })
@@ -650,7 +826,7 @@ where
                                 debug!("Bucket full. Peer not added to routing table: {}", peer)
                             },
                             kbucket::InsertResult::Pending { disconnected } => {
-                                debug_assert!(!self.connected_peers.contains(disconnected.preimage()));
+                                debug_assert!(!self.connected_peers.contains_key(disconnected.preimage()));
                                 self.queued_events.push_back(NetworkBehaviourAction::DialPeer {
                                     peer_id: disconnected.into_preimage(),
                                 })
@@ -668,7 +844,10 @@ where
     }
 
     /// Handles a finished (i.e. successful) query.
-    fn query_finished(&mut self, q: Query<QueryInner>, params: &mut impl PollParameters)
+    ///
+    /// `elapsed` is the time spent on the query so far, including previous
+    /// phases of the same operation.
+    fn query_finished(&mut self, q: Query<QueryInner>, elapsed: Duration, params: &mut impl PollParameters)
         -> Option<KademliaEvent>
     {
         let result = q.into_result();
@@ -738,12 +917,15 @@ where
                 let closest_peers = result.peers.map(kbucket::Key::from);
                 let provider_id = params.local_peer_id().clone();
                 let external_addresses = params.external_addresses().collect();
-                let inner = QueryInner::new(QueryInfo::AddProvider {
+                let signed_record = self.peer_records.get(&provider_id).cloned();
+                let mut inner = QueryInner::new(QueryInfo::AddProvider {
                     key,
                     provider_id,
                     external_addresses,
+                    signed_record,
                     context,
                 });
+                inner.elapsed = elapsed;
                 self.queries.add_fixed(closest_peers, inner);
                 None
             }
@@ -763,7 +945,8 @@ where
                 }
             }
 
-            QueryInfo::GetRecord { key, records, quorum, cache_at } => {
+            QueryInfo::GetRecord { key, mut records, rejected, quorum, cache_at } => {
+                self.select_record(&key, &mut records);
                 let result = if records.len() >= quorum.get() { // [not empty]
                     if let Some(cache_key) = cache_at {
                         // Cache the record at the closest node to the key that
@@ -776,6 +959,8 @@ where
                         self.queries.add_fixed(iter::once(cache_key), inner);
                     }
                     Ok(GetRecordOk { records })
+                } else if records.is_empty() && !rejected.is_empty() {
+                    Err(GetRecordError::Rejected { key, records: rejected })
                 } else if records.is_empty() {
                     Err(GetRecordError::NotFound {
                         key,
@@ -790,7 +975,8 @@ where
             QueryInfo::PreparePutRecord { record, quorum, context } => {
                 let closest_peers = result.peers.map(kbucket::Key::from);
                 let info = QueryInfo::PutRecord { record, quorum, context, num_results: 0 };
-                let inner = QueryInner::new(info);
+                let mut inner = QueryInner::new(info);
+                inner.elapsed = elapsed;
                 self.queries.add_fixed(closest_peers, inner);
                 None
             }
@@ -821,6 +1007,25 @@ where
         }
     }
 
+    /// Records the statistics of a query that produced the given event.
+    fn record_query_stats(&mut self, event: &KademliaEvent, duration: Duration) {
+        let (kind, success) = match event {
+            KademliaEvent::BootstrapResult(r) => (QueryKind::Bootstrap, r.is_ok()),
+            KademliaEvent::GetClosestPeersResult(r) => (QueryKind::GetClosestPeers, r.is_ok()),
+            KademliaEvent::GetProvidersResult(r) => (QueryKind::GetProviders, r.is_ok()),
+            KademliaEvent::StartProvidingResult(r) |
+            KademliaEvent::RepublishProviderResult(r) => (QueryKind::AddProvider, r.is_ok()),
+            KademliaEvent::GetRecordResult(r) => (QueryKind::GetRecord, r.is_ok()),
+            KademliaEvent::PutRecordResult(r) |
+            KademliaEvent::RepublishRecordResult(r) => (QueryKind::PutRecord, r.is_ok()),
+            _ => return
+        };
+        if self.query_stats.len() >= MAX_QUERY_STATS {
+            self.query_stats.pop_front();
+        }
+        self.query_stats.push_back(QueryStats { kind, duration, success });
+    }
+
     /// Handles a query that timed out.
     fn query_timeout(&self, query: Query<QueryInner>) -> Option<KademliaEvent> {
         let result = query.into_result();
@@ -901,9 +1106,11 @@ where
                 }
             }
 
-            QueryInfo::GetRecord { key, records, quorum, .. } =>
+            QueryInfo::GetRecord { key, mut records, quorum, .. } => {
+                self.select_record(&key, &mut records);
                 Some(KademliaEvent::GetRecordResult(Err(
-                    GetRecordError::Timeout { key, records, quorum }))),
+                    GetRecordError::Timeout { key, records, quorum })))
+            }
 
             QueryInfo::GetProviders { key, providers } =>
                 Some(KademliaEvent::GetProvidersResult(Err(
@@ -915,14 +1122,40 @@ where
         }
     }
 
+    /// Checks a record against the configured validator, if any.
+    fn is_valid(&self, record: &Record) -> bool {
+        self.record_validator.as_ref().map_or(true, |v| v.validate(record))
+    }
+
+    /// Moves the record selected by the configured validator, if any,
+    /// to the front of the records found for a key.
+    fn select_record(&self, key: &Multihash, records: &mut Vec<Record>) {
+        if let Some(validator) = &self.record_validator {
+            if records.len() > 1 {
+                let best = validator.select(key, records);
+                if best > 0 && best < records.len() {
+                    let record = records.remove(best);
+                    records.insert(0, record);
+                }
+            }
+        }
+    }
+
     /// Processes a record received from a peer.
-    fn record_received(&mut self, source: PeerId, request_id: KademliaRequestId, mut record: Record) {
+    fn record_received(
+        &mut self,
+        source: PeerId,
+        connection: ConnectionId,
+        request_id: KademliaRequestId,
+        mut record: Record
+    ) {
         if record.publisher.as_ref() == Some(self.kbuckets.local_key().preimage()) {
             // If the (alleged) publisher is the local node, do nothing. The record of
             // the original publisher should never change as a result of replication
             // and the publisher is always assumed to have the "right" value.
-            self.queued_events.push_back(NetworkBehaviourAction::SendEvent {
+            self.queued_events.push_back(NetworkBehaviourAction::NotifyHandler {
                 peer_id: source,
+                handler: NotifyHandler::One(connection),
                 event: KademliaHandlerIn::PutRecordRes {
                     key: record.key,
                     value: record.value,
@@ -932,7 +1165,24 @@ where
             return
         }
 
-        let now = Instant::now();
+        if !self.is_valid(&record) {
+            info!("Record rejected: {:?}", record.key);
+            self.queued_events.push_back(NetworkBehaviourAction::ReportMisbehaviour {
+                peer_id: source.clone(),
+                misbehaviour: Misbehaviour::InvalidRecord,
+            });
+            self.queued_events.push_back(NetworkBehaviourAction::NotifyHandler {
+                peer_id: source.clone(),
+                handler: NotifyHandler::One(connection),
+                event: KademliaHandlerIn::Reset(request_id)
+            });
+            self.queued_events.push_back(NetworkBehaviourAction::GenerateEvent(
+                KademliaEvent::RecordRejected { peer: source, record }
+            ));
+            return
+        }
+
+        let now = clock::now();
 
         // Calculate the expiration exponentially inversely proportional to the
         // number of nodes between the local node and the closest node to the key
@@ -973,8 +1223,9 @@ where
         match self.store.put(record.clone()) {
             Ok(()) => {
                 debug!("Record stored: {:?}; {} bytes", record.key, record.value.len());
-                self.queued_events.push_back(NetworkBehaviourAction::SendEvent {
+                self.queued_events.push_back(NetworkBehaviourAction::NotifyHandler {
                     peer_id: source,
+                    handler: NotifyHandler::One(connection),
                     event: KademliaHandlerIn::PutRecordRes {
                         key: record.key,
                         value: record.value,
@@ -984,8 +1235,9 @@ where
             }
             Err(e) => {
                 info!("Record not stored: {:?}", e);
-                self.queued_events.push_back(NetworkBehaviourAction::SendEvent {
+                self.queued_events.push_back(NetworkBehaviourAction::NotifyHandler {
                     peer_id: source,
+                    handler: NotifyHandler::One(connection),
                     event: KademliaHandlerIn::Reset(request_id)
                 })
             }
@@ -994,18 +1246,22 @@ where
 
     /// Processes a provider record received from a peer.
     fn provider_received(&mut self, key: Multihash, provider: KadPeer) {
+        if let Some(record) = &provider.signed_record {
+            self.peer_record_received(record);
+        }
         self.queued_events.push_back(NetworkBehaviourAction::GenerateEvent(
             KademliaEvent::Discovered {
                 peer_id: provider.node_id.clone(),
                 addresses: provider.multiaddrs.clone(),
                 ty: provider.connection_ty,
+                signed_record: provider.signed_record.clone(),
             }));
 
         if &provider.node_id != self.kbuckets.local_key().preimage() {
             let record = ProviderRecord {
                 key,
                 provider: provider.node_id,
-                expires: self.provider_record_ttl.map(|ttl| Instant::now() + ttl)
+                expires: self.provider_record_ttl.map(|ttl| clock::now() + ttl)
             };
             if let Err(e) = self.store.add_provider(record) {
                 info!("Provider record not stored: {:?}", e);
@@ -1014,7 +1270,7 @@ where
     }
 }
 
-impl<TSubstream, TStore> NetworkBehaviour for Kademlia<TSubstream, TStore>
+impl<TSubstream, TStore> NetworkBehaviour01 for Kademlia<TSubstream, TStore>
 where
     TSubstream: AsyncRead + AsyncWrite,
     for<'a> TStore: RecordStore<'a>,
@@ -1023,7 +1279,10 @@ where
     type OutEvent = KademliaEvent;
 
     fn new_handler(&mut self) -> Self::ProtocolsHandler {
-        let mut handler = KademliaHandler::dial_and_listen();
+        let mut handler = match self.mode {
+            Mode::Client => KademliaHandler::dial_only(),
+            Mode::Server => KademliaHandler::dial_and_listen(),
+        };
         if let Some(name) = self.protocol_name_override.as_ref() {
             handler = handler.with_protocol_name(name.clone());
         }
@@ -1056,8 +1315,9 @@ where
     fn inject_connected(&mut self, peer: PeerId, endpoint: ConnectedPoint) {
         while let Some(pos) = self.pending_rpcs.iter().position(|(p, _)| p == &peer) {
             let (_, rpc) = self.pending_rpcs.remove(pos);
-            self.queued_events.push_back(NetworkBehaviourAction::SendEvent {
+            self.queued_events.push_back(NetworkBehaviourAction::NotifyHandler {
                 peer_id: peer.clone(),
+                handler: NotifyHandler::Any,
                 event: rpc,
             });
         }
@@ -1067,12 +1327,11 @@ where
         // since the remote address on an inbound connection is specific to
         // that connection (e.g. typically the TCP port numbers).
         let address = match endpoint {
-            ConnectedPoint::Dialer { address } => Some(address),
+            ConnectedPoint::Dialer { address, .. } => Some(address),
             ConnectedPoint::Listener { .. } => None,
         };
 
-        self.connection_updated(peer.clone(), address, NodeStatus::Connected);
-        self.connected_peers.insert(peer);
+        self.connection_updated(peer, address, NodeStatus::Connected);
     }
 
     fn inject_addr_reach_failure(
@@ -1130,30 +1389,49 @@ where
         self.connected_peers.remove(id);
     }
 
-    fn inject_replaced(&mut self, peer_id: PeerId, _old: ConnectedPoint, new_endpoint: ConnectedPoint) {
-        // We need to re-send the active queries.
-        for query in self.queries.iter() {
-            if query.is_waiting(&peer_id) {
-                self.queued_events.push_back(NetworkBehaviourAction::SendEvent {
-                    peer_id: peer_id.clone(),
-                    event: query.inner.info.to_request(query.id()),
-                });
+    fn inject_connection_established(&mut self, peer_id: &PeerId, connection: &ConnectionId, endpoint: &ConnectedPoint) {
+        self.connected_peers.entry(peer_id.clone()).or_default().push(*connection);
+
+        // An additional connection that we dialed confirms another address of the peer.
+        if let Some(addrs) = self.kbuckets.entry(&kbucket::Key::new(peer_id.clone())).value() {
+            if let ConnectedPoint::Dialer { address, .. } = endpoint {
+                addrs.insert(address.clone());
             }
         }
+    }
 
-        if let Some(addrs) = self.kbuckets.entry(&kbucket::Key::new(peer_id)).value() {
-            if let ConnectedPoint::Dialer { address } = new_endpoint {
-                addrs.insert(address);
+    fn inject_connection_closed(&mut self, peer_id: &PeerId, connection: &ConnectionId, _: &ConnectedPoint) {
+        let was_oldest = match self.connected_peers.get_mut(peer_id) {
+            Some(connections) => {
+                let was_oldest = connections.first() == Some(connection);
+                connections.retain(|c| c != connection);
+                was_oldest && !connections.is_empty()
+            }
+            None => false,
+        };
+
+        // Requests are sent to the oldest connection. If it has been closed while other
+        // connections remain, the requests it was carrying are lost and need to be re-sent.
+        if was_oldest {
+            for query in self.queries.iter() {
+                if query.is_waiting(peer_id) {
+                    self.queued_events.push_back(NetworkBehaviourAction::NotifyHandler {
+                        peer_id: peer_id.clone(),
+                        handler: NotifyHandler::Any,
+                        event: query.inner.info.to_request(query.id()),
+                    });
+                }
             }
         }
     }
 
-    fn inject_node_event(&mut self, source: PeerId, event: KademliaHandlerEvent<QueryId>) {
+    fn inject_node_event(&mut self, source: PeerId, connection: ConnectionId, event: KademliaHandlerEvent<QueryId>) {
         match event {
             KademliaHandlerEvent::FindNodeReq { key, request_id } => {
                 let closer_peers = self.find_closest(&kbucket::Key::new(key), &source);
-                self.queued_events.push_back(NetworkBehaviourAction::SendEvent {
+                self.queued_events.push_back(NetworkBehaviourAction::NotifyHandler {
                     peer_id: source,
+                    handler: NotifyHandler::One(connection),
                     event: KademliaHandlerIn::FindNodeRes {
                         closer_peers,
                         request_id,
@@ -1171,8 +1449,9 @@ where
             KademliaHandlerEvent::GetProvidersReq { key, request_id } => {
                 let provider_peers = self.provider_peers(&key, &source);
                 let closer_peers = self.find_closest(&kbucket::Key::from(key), &source);
-                self.queued_events.push_back(NetworkBehaviourAction::SendEvent {
+                self.queued_events.push_back(NetworkBehaviourAction::NotifyHandler {
                     peer_id: source,
+                    handler: NotifyHandler::One(connection),
                     event: KademliaHandlerIn::GetProvidersRes {
                         closer_peers,
                         provider_peers,
@@ -1199,7 +1478,14 @@ where
                 }
             }
 
-            KademliaHandlerEvent::QueryError { user_data, .. } => {
+            KademliaHandlerEvent::QueryError { error, user_data } => {
+                if let Some(misbehaviour) = error.misbehaviour() {
+                    self.queued_events.push_back(NetworkBehaviourAction::ReportMisbehaviour {
+                        peer_id: source.clone(),
+                        misbehaviour,
+                    });
+                }
+
                 // It is possible that we obtain a response for a query that has finished, which is
                 // why we may not find an entry in `self.queries`.
                 if let Some(query) = self.queries.get_mut(&user_data) {
@@ -1210,6 +1496,10 @@ where
             KademliaHandlerEvent::AddProvider { key, provider } => {
                 // Only accept a provider record from a legitimate peer.
                 if provider.node_id != source {
+                    self.queued_events.push_back(NetworkBehaviourAction::ReportMisbehaviour {
+                        peer_id: source,
+                        misbehaviour: Misbehaviour::InvalidRecord,
+                    });
                     return
                 }
 
@@ -1220,7 +1510,7 @@ where
                 // Lookup the record locally.
                 let record = match self.store.get(&key) {
                     Some(record) => {
-                        if record.is_expired(Instant::now()) {
+                        if record.is_expired(clock::now()) {
                             self.store.remove(&key);
                             None
                         } else {
@@ -1238,8 +1528,9 @@ where
                         Vec::new()
                     };
 
-                self.queued_events.push_back(NetworkBehaviourAction::SendEvent {
+                self.queued_events.push_back(NetworkBehaviourAction::NotifyHandler {
                     peer_id: source,
+                    handler: NotifyHandler::One(connection),
                     event: KademliaHandlerIn::GetRecordRes {
                         record,
                         closer_peers,
@@ -1255,12 +1546,23 @@ where
             } => {
                 if let Some(query) = self.queries.get_mut(&user_data) {
                     if let QueryInfo::GetRecord {
-                        key, records, quorum, cache_at
+                        key, records, rejected, quorum, cache_at
                     } = &mut query.inner.info {
                         if let Some(record) = record {
-                            records.push(record);
-                            if records.len() == quorum.get() {
-                                query.finish()
+                            let valid = self.record_validator.as_ref()
+                                .map_or(true, |v| v.validate(&record));
+                            if valid {
+                                records.push(record);
+                                if records.len() == quorum.get() {
+                                    query.finish()
+                                }
+                            } else {
+                                debug!("Record from {} rejected: {:?}", source, record.key);
+                                rejected.push(record);
+                                self.queued_events.push_back(NetworkBehaviourAction::ReportMisbehaviour {
+                                    peer_id: source.clone(),
+                                    misbehaviour: Misbehaviour::InvalidRecord,
+                                });
                             }
                         } else if quorum.get() == 1 {
                             // It is a "standard" Kademlia query, for which the
@@ -1287,7 +1589,7 @@ where
                 record,
                 request_id
             } => {
-                self.record_received(source, request_id, record);
+                self.record_received(source, connection, request_id, record);
             }
 
             KademliaHandlerEvent::PutRecordRes {
@@ -1314,7 +1616,7 @@ where
             Self::OutEvent,
         >,
     > {
-        let now = Instant::now();
+        let now = clock::now();
 
         // Calculate the available capacity for queries triggered by background jobs.
         let mut jobs_query_capacity = JOBS_MAX_QUERIES - self.queries.size();
@@ -1360,10 +1662,14 @@ where
             // Drain applied pending entries from the routing table.
             if let Some(entry) = self.kbuckets.take_applied_pending() {
                 let kbucket::Node { key, value } = entry.inserted;
+                let old_peer = entry.evicted.map(|n| n.key.into_preimage());
+                if let Some(old_peer) = &old_peer {
+                    self.peer_records.remove(old_peer);
+                }
                 let event = KademliaEvent::RoutingUpdated {
                     peer: key.into_preimage(),
                     addresses: value,
-                    old_peer: entry.evicted.map(|n| n.key.into_preimage())
+                    old_peer,
                 };
                 return Async::Ready(NetworkBehaviourAction::GenerateEvent(event))
             }
@@ -1372,12 +1678,16 @@ where
             loop {
                 match self.queries.poll(now) {
                     QueryPoolState::Finished(q) => {
-                        if let Some(event) = self.query_finished(q, parameters) {
+                        let elapsed = q.inner.elapsed + q.elapsed(now);
+                        if let Some(event) = self.query_finished(q, elapsed, parameters) {
+                            self.record_query_stats(&event, elapsed);
                             return Async::Ready(NetworkBehaviourAction::GenerateEvent(event))
                         }
                     }
                     QueryPoolState::Timeout(q) => {
+                        let elapsed = q.inner.elapsed + q.elapsed(now);
                         if let Some(event) = self.query_timeout(q) {
+                            self.record_query_stats(&event, elapsed);
                             return Async::Ready(NetworkBehaviourAction::GenerateEvent(event))
                         }
                     }
@@ -1391,9 +1701,9 @@ where
                         if let QueryInfo::AddProvider { .. } = &query.inner.info {
                             query.on_success(&peer_id, vec![])
                         }
-                        if self.connected_peers.contains(&peer_id) {
-                            self.queued_events.push_back(NetworkBehaviourAction::SendEvent {
-                                peer_id, event
+                        if self.connected_peers.contains_key(&peer_id) {
+                            self.queued_events.push_back(NetworkBehaviourAction::NotifyHandler {
+                                peer_id, event, handler: NotifyHandler::Any
                             });
                         } else if &peer_id != self.kbuckets.local_key().preimage() {
                             self.pending_rpcs.push((peer_id.clone(), event));
@@ -1439,6 +1749,20 @@ impl Quorum {
     }
 }
 
+/// The mode of operation of the local node.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Mode {
+    /// The local node only issues requests to remote peers. It neither
+    /// accepts inbound Kademlia substreams nor advertises the protocol,
+    /// and is therefore not added to the routing tables of remote peers.
+    ///
+    /// This mode is appropriate for nodes that are not publicly reachable.
+    Client,
+    /// The local node issues requests to remote peers and answers
+    /// requests from remote peers.
+    Server,
+}
+
 //////////////////////////////////////////////////////////////////////////////
 // Events
 
@@ -1480,6 +1804,10 @@ pub enum KademliaEvent {
         /// The connection status reported by the discovered peer
         /// towards the local peer.
         ty: KadConnectionType,
+        /// The signed peer record of the discovered peer, if it has been
+        /// reported and is valid. In that case, `addresses` are the ones
+        /// of the record.
+        signed_record: Option<PeerRecord>,
     },
 
     /// The routing table has been updated.
@@ -1493,6 +1821,15 @@ pub enum KademliaEvent {
         old_peer: Option<PeerId>,
     },
 
+    /// A record sent by a peer for storage has been rejected by the
+    /// configured [`RecordValidator`].
+    RecordRejected {
+        /// The peer that sent the record.
+        peer: PeerId,
+        /// The rejected record.
+        record: Record,
+    },
+
     /// A peer has connected for whom no listen address is known.
     ///
     /// If the peer is to be added to the local node's routing table, a known
@@ -1512,11 +1849,17 @@ pub struct GetRecordOk {
 }
 
 /// The error result of [`Kademlia::get_record`].
+///
+/// The records of the `QuorumFailed` and `Timeout` errors are ordered like the
+/// records of a successful result, i.e. the record selected by the configured
+/// [`RecordValidator`], if any, comes first.
 #[derive(Debug, Clone)]
 pub enum GetRecordError {
     NotFound { key: Multihash, closest_peers: Vec<PeerId> },
     QuorumFailed { key: Multihash, records: Vec<Record>, quorum: NonZeroUsize },
-    Timeout { key: Multihash, records: Vec<Record>, quorum: NonZeroUsize }
+    Timeout { key: Multihash, records: Vec<Record>, quorum: NonZeroUsize },
+    /// All records found have been rejected by the configured [`RecordValidator`].
+    Rejected { key: Multihash, records: Vec<Record> }
 }
 
 impl GetRecordError {
@@ -1526,6 +1869,7 @@ impl GetRecordError {
             GetRecordError::QuorumFailed { key, .. } => key,
             GetRecordError::Timeout { key, .. } => key,
             GetRecordError::NotFound { key, .. } => key,
+            GetRecordError::Rejected { key, .. } => key,
         }
     }
 
@@ -1536,6 +1880,7 @@ impl GetRecordError {
             GetRecordError::QuorumFailed { key, .. } => key,
             GetRecordError::Timeout { key, .. } => key,
             GetRecordError::NotFound { key, .. } => key,
+            GetRecordError::Rejected { key, .. } => key,
         }
     }
 }
@@ -1565,6 +1910,10 @@ pub enum PutRecordError {
     LocalStorageError {
         key: Multihash,
         cause: store::Error
+    },
+    /// The record has been rejected by the configured [`RecordValidator`].
+    Rejected {
+        key: Multihash
     }
 }
 
@@ -1574,7 +1923,8 @@ impl PutRecordError {
         match self {
             PutRecordError::QuorumFailed { key, .. } => key,
             PutRecordError::Timeout { key, .. } => key,
-            PutRecordError::LocalStorageError { key, .. } => key
+            PutRecordError::LocalStorageError { key, .. } => key,
+            PutRecordError::Rejected { key } => key,
         }
     }
 
@@ -1585,6 +1935,7 @@ impl PutRecordError {
             PutRecordError::QuorumFailed { key, .. } => key,
             PutRecordError::Timeout { key, .. } => key,
             PutRecordError::LocalStorageError { key, .. } => key,
+            PutRecordError::Rejected { key } => key,
         }
     }
 }
@@ -1720,6 +2071,51 @@ impl AddProviderError {
     }
 }
 
+/// Statistics about a finished query.
+///
+/// See [`Kademlia::take_query_stats`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryStats {
+    /// The kind of the query.
+    pub kind: QueryKind,
+    /// The time it took for the query to finish, including all its phases,
+    /// e.g. the lookup of the closest peers to a key before storing a record.
+    pub duration: Duration,
+    /// Whether the query succeeded.
+    pub success: bool,
+}
+
+/// The kind of a query, as reported in [`QueryStats`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum QueryKind {
+    /// See [`Kademlia::bootstrap`].
+    Bootstrap,
+    /// See [`Kademlia::get_closest_peers`].
+    GetClosestPeers,
+    /// See [`Kademlia::get_providers`].
+    GetProviders,
+    /// See [`Kademlia::start_providing`].
+    AddProvider,
+    /// See [`Kademlia::get_record`].
+    GetRecord,
+    /// See [`Kademlia::put_record`].
+    PutRecord,
+}
+
+impl QueryKind {
+    /// Returns the name of the kind of query, in snake case.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QueryKind::Bootstrap => "bootstrap",
+            QueryKind::GetClosestPeers => "get_closest_peers",
+            QueryKind::GetProviders => "get_providers",
+            QueryKind::AddProvider => "add_provider",
+            QueryKind::GetRecord => "get_record",
+            QueryKind::PutRecord => "put_record",
+        }
+    }
+}
+
 impl From<kbucket::EntryView<kbucket::Key<PeerId>, Addresses>> for KadPeer {
     fn from(e: kbucket::EntryView<kbucket::Key<PeerId>, Addresses>) -> KadPeer {
         KadPeer {
@@ -1728,11 +2124,18 @@ impl From<kbucket::EntryView<kbucket::Key<PeerId>, Addresses>> for KadPeer {
             connection_ty: match e.status {
                 NodeStatus::Connected => KadConnectionType::Connected,
                 NodeStatus::Disconnected => KadConnectionType::NotConnected
-            }
+            },
+            signed_record: None,
         }
     }
 }
 
+/// Attaches to a `KadPeer` the known signed record of the peer, if any.
+fn with_peer_record(mut peer: KadPeer, peer_records: &FnvHashMap<PeerId, PeerRecord>) -> KadPeer {
+    peer.signed_record = peer_records.get(&peer.node_id).cloned();
+    peer
+}
+
 //////////////////////////////////////////////////////////////////////////////
 // Internal query state
 
@@ -1741,13 +2144,16 @@ struct QueryInner {
     info: QueryInfo,
     /// Addresses of peers discovered during a query.
     addresses: FnvHashMap<PeerId, SmallVec<[Multiaddr; 8]>>,
+    /// The time spent in the previous phases of the operation, if any.
+    elapsed: Duration,
 }
 
 impl QueryInner {
     fn new(info: QueryInfo) -> Self {
         QueryInner {
             info,
-            addresses: Default::default()
+            addresses: Default::default(),
+            elapsed: Duration::from_secs(0),
         }
     }
 }
@@ -1798,6 +2204,7 @@ enum QueryInfo {
         key: Multihash,
         provider_id: PeerId,
         external_addresses: Vec<Multiaddr>,
+        signed_record: Option<PeerRecord>,
         context: AddProviderContext,
     },
 
@@ -1823,6 +2230,8 @@ enum QueryInfo {
         key: Multihash,
         /// The records found.
         records: Vec<Record>,
+        /// The records found that have been rejected by the validator.
+        rejected: Vec<Record>,
         /// The number of records to look for.
         quorum: NonZeroUsize,
         /// The closest peer to `key` that did not return a record.
@@ -1858,6 +2267,7 @@ impl QueryInfo {
                 key,
                 provider_id,
                 external_addresses,
+                signed_record,
                 ..
             } => KademliaHandlerIn::AddProvider {
                 key: key.clone(),
@@ -1865,6 +2275,7 @@ impl QueryInfo {
                     node_id: provider_id.clone(),
                     multiaddrs: external_addresses.clone(),
                     connection_ty: crate::protocol::KadConnectionType::Connected,
+                    signed_record: signed_record.clone(),
                 }
             },
             QueryInfo::GetRecord { key, .. } => KademliaHandlerIn::GetRecord {
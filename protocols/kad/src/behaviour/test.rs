@@ -26,6 +26,7 @@ use crate::K_VALUE;
 use crate::kbucket::Distance;
 use crate::record::store::MemoryStore;
 use futures::future;
+use futures03::TryFutureExt as _;
 use libp2p_core::{
     PeerId,
     Transport,
@@ -37,11 +38,12 @@ use libp2p_core::{
     upgrade,
 };
 use libp2p_secio::SecioConfig;
-use libp2p_swarm::Swarm;
+use libp2p_simulator::{LinkConfig, NodeId, Simulation};
+use libp2p_swarm::{ConnectionLimits, Swarm, SwarmBuilder};
 use libp2p_yamux as yamux;
 use quickcheck::*;
 use rand::{Rng, random, thread_rng};
-use std::{collections::{HashSet, HashMap}, io, num::NonZeroUsize, u64};
+use std::{collections::{HashSet, HashMap}, io, num::NonZeroUsize, time::Duration, u64};
 use tokio::runtime::current_thread;
 use multihash::Hash::SHA2256;
 
@@ -57,6 +59,14 @@ fn build_nodes(num: usize) -> (u64, Vec<TestSwarm>) {
 
 /// Builds swarms, each listening on a port. Does *not* connect the nodes together.
 fn build_nodes_with_config(num: usize, cfg: KademliaConfig) -> (u64, Vec<TestSwarm>) {
+    build_nodes_with_limits(num, cfg, ConnectionLimits::default())
+}
+
+/// Builds swarms, each listening on a port and applying the given connection limits. Does *not*
+/// connect the nodes together.
+fn build_nodes_with_limits(num: usize, cfg: KademliaConfig, limits: ConnectionLimits)
+    -> (u64, Vec<TestSwarm>)
+{
     let port_base = 1 + random::<u64>() % (u64::MAX - num as u64);
     let mut result: Vec<Swarm<_, _>> = Vec::with_capacity(num);
 
@@ -71,7 +81,7 @@ fn build_nodes_with_config(num: usize, cfg: KademliaConfig) -> (u64, Vec<TestSwa
                 let peer_id = out.remote_key.into_peer_id();
                 let yamux = yamux::Config::default();
                 upgrade::apply(out.stream, yamux, endpoint)
-                    .map(|muxer| (peer_id, StreamMuxerBox::new(muxer)))
+                    .map_ok(|muxer| (peer_id, StreamMuxerBox::new(muxer)))
             })
             .map_err(|e| panic!("Failed to create transport: {:?}", e))
             .boxed();
@@ -79,7 +89,9 @@ fn build_nodes_with_config(num: usize, cfg: KademliaConfig) -> (u64, Vec<TestSwa
         let local_id = local_public_key.clone().into_peer_id();
         let store = MemoryStore::new(local_id.clone());
         let behaviour = Kademlia::with_config(local_id.clone(), store, cfg.clone());
-        result.push(Swarm::new(transport, behaviour, local_id));
+        result.push(SwarmBuilder::new(transport, behaviour, local_id)
+            .connection_limits(limits.clone())
+            .build());
     }
 
     for (i, s) in result.iter_mut().enumerate() {
@@ -89,6 +101,37 @@ fn build_nodes_with_config(num: usize, cfg: KademliaConfig) -> (u64, Vec<TestSwa
     (port_base, result)
 }
 
+/// Adds swarms to a simulation, listening on `/memory/<index + 1>`. Does *not* connect the nodes
+/// together.
+fn build_simulated_nodes(sim: &mut Simulation<TestSwarm>, num: usize) -> Vec<(NodeId, PeerId)> {
+    (0 .. num).map(|_| {
+        let local_key = sim.keypair();
+        let executor = sim.executor();
+        let local_id = local_key.public().into_peer_id();
+        let peer_id = local_id.clone();
+        let node = sim.add_node(move |transport| {
+            let transport = transport
+                .with_upgrade(SecioConfig::new(local_key))
+                .and_then(move |out, endpoint| {
+                    let peer_id = out.remote_key.into_peer_id();
+                    let yamux = yamux::Config::default();
+                    upgrade::apply(out.stream, yamux, endpoint)
+                        .map_ok(|muxer| (peer_id, StreamMuxerBox::new(muxer)))
+                })
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))
+                .boxed();
+            let store = MemoryStore::new(local_id.clone());
+            let behaviour = Kademlia::new(local_id.clone(), store);
+            SwarmBuilder::new(transport, behaviour, local_id)
+                .executor(Box::new(executor))
+                .build()
+        });
+        let addr = Protocol::Memory(node.index() as u64 + 1).into();
+        sim.with_node(node, |swarm| Swarm::listen_on(swarm, addr)).unwrap();
+        (node, peer_id)
+    }).collect()
+}
+
 fn build_connected_nodes(total: usize, step: usize) -> (Vec<PeerId>, Vec<TestSwarm>) {
     build_connected_nodes_with_config(total, step, Default::default())
 }
@@ -208,6 +251,76 @@ fn query_iter() {
     }
 }
 
+#[test]
+fn simulated_lookups_with_partition() {
+    /// Returns the distances of the peers to the key, in ascending order.
+    fn distances(key: &kbucket::Key<PeerId>, peers: impl IntoIterator<Item = PeerId>) -> Vec<Distance> {
+        let mut distances = peers.into_iter()
+            .map(|p| kbucket::Key::new(p).distance(key))
+            .collect::<Vec<_>>();
+        distances.sort();
+        distances
+    }
+
+    fn lookup(sim: &mut Simulation<TestSwarm>, origin: NodeId, target: &PeerId) -> Vec<PeerId> {
+        sim.with_node(origin, |swarm| swarm.get_closest_peers(target.clone()));
+        let mut peers = None;
+        assert!(sim.run_until(Duration::from_secs(600), |node, event| match event {
+            KademliaEvent::GetClosestPeersResult(result) if node == origin => {
+                peers = Some(match result {
+                    Ok(ok) => ok.peers,
+                    Err(GetClosestPeersError::Timeout { peers, .. }) => peers,
+                });
+                true
+            }
+            _ => false
+        }));
+        peers.unwrap()
+    }
+
+    let mut sim = Simulation::new(7);
+    sim.set_default_link(LinkConfig::default()
+        .with_latency(Duration::from_millis(30))
+        .with_jitter(Duration::from_millis(20))
+        .with_loss(0.02));
+    let nodes = build_simulated_nodes(&mut sim, 25);
+
+    // Every node knows the next one and a random one, and joins the DHT.
+    for i in 0 .. nodes.len() {
+        let contacts = vec![(i + 1) % nodes.len(), sim.rng().gen_range(0, nodes.len())];
+        sim.with_node(nodes[i].0, |swarm| {
+            for j in contacts.into_iter().filter(|j| *j != i) {
+                swarm.add_address(&nodes[j].1, Protocol::Memory(j as u64 + 1).into());
+            }
+            swarm.bootstrap();
+        });
+    }
+    sim.run_for(Duration::from_secs(300));
+    let (origin, _) = nodes[nodes.len() - 1];
+
+    let target = sim.keypair().public().into_peer_id();
+    let key = kbucket::Key::new(target.clone());
+    let mut expected = distances(&key, nodes[.. nodes.len() - 1].iter().map(|(_, id)| id.clone()));
+    expected.truncate(K_VALUE.get());
+    assert_eq!(distances(&key, lookup(&mut sim, origin, &target)), expected);
+
+    // The nodes on the other side of a partition are not returned. The lookup still completes
+    // once the requests to these nodes have timed out, on the virtual clock.
+    let (unreachable, reachable) = nodes.split_at(nodes.len() / 2);
+    sim.partition(
+        &reachable.iter().map(|(n, _)| *n).collect::<Vec<_>>(),
+        &unreachable.iter().map(|(n, _)| *n).collect::<Vec<_>>()
+    );
+    let reachable = reachable.iter().map(|(_, id)| id.clone()).collect::<HashSet<_>>();
+    let peers = lookup(&mut sim, origin, &target);
+    assert!(!peers.is_empty());
+    assert!(peers.iter().all(|p| reachable.contains(p)));
+
+    sim.heal();
+    sim.run_for(Duration::from_secs(300));
+    assert_eq!(distances(&key, lookup(&mut sim, origin, &target)), expected);
+}
+
 #[test]
 fn unresponsive_not_returned_direct() {
     // Build one node. It contains fake addresses to non-existing nodes. We ask it to find a
@@ -244,6 +357,110 @@ fn unresponsive_not_returned_direct() {
         }))
 }
 
+#[test]
+fn signed_peer_records_are_forwarded() {
+    // Build two nodes. Node #1 knows a signed record of a non-existing node. Node #2 looks up
+    // that node through node #1 and must discover it along with its signed record.
+
+    let (port_base, mut swarms) = build_nodes(2);
+    let first_peer_id = Swarm::local_peer_id(&swarms[0]).clone();
+    swarms[1].add_address(&first_peer_id, Protocol::Memory(port_base).into());
+
+    let key = identity::Keypair::generate_ed25519();
+    let record = PeerRecord::new(&key, vec![Protocol::Udp(10u16).into()]).unwrap();
+    let target = record.peer_id().clone();
+    swarms[0].add_peer_record(record.clone());
+    assert_eq!(swarms[0].peer_record(&target), Some(&record));
+
+    // A record with a lower sequence number is ignored.
+    let older = PeerRecord::with_seq(&key, record.seq() - 1, Vec::new()).unwrap();
+    swarms[0].add_peer_record(older);
+    assert_eq!(swarms[0].peer_record(&target), Some(&record));
+
+    swarms[1].get_closest_peers(target.clone());
+
+    current_thread::run(
+        future::poll_fn(move || {
+            for swarm in &mut swarms {
+                loop {
+                    match swarm.poll().unwrap() {
+                        Async::Ready(Some(KademliaEvent::Discovered { peer_id, addresses, signed_record, .. }))
+                            if peer_id == target =>
+                        {
+                            assert_eq!(signed_record.as_ref(), Some(&record));
+                            assert_eq!(addresses, record.addresses());
+                            return Ok(Async::Ready(()));
+                        }
+                        Async::Ready(_) => (),
+                        Async::NotReady => break,
+                    }
+                }
+            }
+
+            Ok(Async::NotReady)
+        }))
+}
+
+#[test]
+fn refused_substreams_keep_the_address() {
+    // The first node dials the second one, but refuses all the substreams the second one opens
+    // in return. The connection stays usable, so the address of the second node must be kept.
+
+    let (port_a, swarms_a) = build_nodes_with_limits(
+        1, Default::default(), ConnectionLimits::default().with_max_substreams(Some(0)));
+    let (port_b, swarms_b) = build_nodes(1);
+    let mut swarms = swarms_a.into_iter().chain(swarms_b).collect::<Vec<_>>();
+    let swarm_ids: Vec<_> = swarms.iter().map(Swarm::local_peer_id).cloned().collect();
+    let addr_a: Multiaddr = Protocol::Memory(port_a).into();
+    let addr_b: Multiaddr = Protocol::Memory(port_b).into();
+
+    // A second, unused address, as the last address of a peer is never removed.
+    swarms[0].add_address(&swarm_ids[1], addr_b.clone());
+    swarms[0].add_address(&swarm_ids[1], Protocol::Memory(random::<u64>()).into());
+    swarms[1].add_address(&swarm_ids[0], addr_a);
+
+    swarms[0].get_closest_peers(PeerId::random());
+
+    let mut second_query_started = false;
+    let mut second_query_done = false;
+    current_thread::run(
+        future::poll_fn(move || {
+            for i in 0 .. swarms.len() {
+                loop {
+                    match swarms[i].poll().unwrap() {
+                        Async::Ready(Some(KademliaEvent::GetClosestPeersResult(Ok(ok)))) => {
+                            if i == 0 {
+                                assert_eq!(ok.peers, vec![swarm_ids[1].clone()]);
+                                // Now that the first node is connected, the second one queries
+                                // it over the same connection.
+                                swarms[1].get_closest_peers(PeerId::random());
+                                second_query_started = true;
+                            } else {
+                                // The first node refused the substream.
+                                assert!(second_query_started);
+                                assert!(ok.peers.is_empty());
+                                second_query_done = true;
+                            }
+                        }
+                        Async::Ready(_) => (),
+                        Async::NotReady => break,
+                    }
+                }
+            }
+
+            if second_query_done {
+                let key = kbucket::Key::new(swarm_ids[1].clone());
+                let addrs = swarms[0].kbuckets.entry(&key).value()
+                    .expect("The second node is in the routing table of the first one.")
+                    .iter().cloned().collect::<Vec<_>>();
+                assert!(addrs.contains(&addr_b));
+                return Ok(Async::Ready(()));
+            }
+
+            Ok(Async::NotReady)
+        }))
+}
+
 #[test]
 fn unresponsive_not_returned_indirect() {
     // Build two nodes. Node #2 knows about node #1. Node #1 contains fake addresses to
@@ -309,6 +526,10 @@ fn get_record_not_found() {
                                 assert_eq!(closest_peers.len(), 2);
                                 assert!(closest_peers.contains(&swarm_ids[1]));
                                 assert!(closest_peers.contains(&swarm_ids[2]));
+                                let stats = swarm.take_query_stats().collect::<Vec<_>>();
+                                assert_eq!(stats.len(), 1);
+                                assert_eq!(stats[0].kind, QueryKind::GetRecord);
+                                assert!(!stats[0].success);
                                 return Ok(Async::Ready(()));
                             } else {
                                 panic!("Unexpected error result: {:?}", e);
@@ -324,6 +545,67 @@ fn get_record_not_found() {
         }))
 }
 
+#[test]
+fn client_mode() {
+    let (port_base, mut swarms) = build_nodes(2);
+
+    let swarm_ids: Vec<_> = swarms.iter().map(Swarm::local_peer_id).cloned().collect();
+
+    swarms[0].set_mode(Mode::Client);
+    swarms[0].add_address(&swarm_ids[1], Protocol::Memory(port_base + 1).into());
+    swarms[1].add_address(&swarm_ids[0], Protocol::Memory(port_base).into());
+
+    let client_record = Record::new(multihash::encode(SHA2256, &vec![1]).unwrap(), vec![1]);
+    let server_record = Record::new(multihash::encode(SHA2256, &vec![2]).unwrap(), vec![2]);
+    swarms[0].store.put(client_record.clone()).unwrap();
+    swarms[1].store.put(server_record.clone()).unwrap();
+
+    // The client does not answer requests from the server.
+    swarms[1].get_record(&client_record.key, Quorum::One);
+    let mut phase = 0;
+
+    current_thread::run(
+        future::poll_fn(move || loop {
+            // Starting a query does not wake up the task, so all swarms are
+            // polled again whenever the test moves to the next phase.
+            let current_phase = phase;
+            for i in 0 .. swarms.len() {
+                loop {
+                    match swarms[i].poll().unwrap() {
+                        Async::Ready(Some(KademliaEvent::GetRecordResult(result))) => {
+                            match (phase, i, result) {
+                                (0, 1, Err(GetRecordError::NotFound { .. })) => {
+                                    // The client can still query the server.
+                                    swarms[0].get_record(&server_record.key, Quorum::One);
+                                }
+                                (1, 0, Ok(ok)) => {
+                                    assert_eq!(ok.records, vec![server_record.clone()]);
+                                    // After switching to server mode, the client
+                                    // answers requests from the server.
+                                    swarms[0].set_mode(Mode::Server);
+                                    swarms[1].get_record(&client_record.key, Quorum::One);
+                                }
+                                (2, 1, Ok(ok)) => {
+                                    assert_eq!(ok.records, vec![client_record.clone()]);
+                                    return Ok(Async::Ready(()));
+                                }
+                                (phase, i, r) => {
+                                    panic!("Unexpected result in phase {} at node {}: {:?}", phase, i, r)
+                                }
+                            }
+                            phase += 1;
+                        }
+                        Async::Ready(_) => (),
+                        Async::NotReady => break,
+                    }
+                }
+            }
+            if phase == current_phase {
+                return Ok(Async::NotReady)
+            }
+        }))
+}
+
 #[test]
 fn put_record() {
     fn prop(replication_factor: usize, records: Vec<Record>) {
@@ -472,6 +754,192 @@ fn get_value() {
         }))
 }
 
+/// Rejects records with empty values or values starting with `0`
+/// and prefers records with larger values.
+#[derive(Debug)]
+struct TestValidator;
+
+impl RecordValidator for TestValidator {
+    fn validate(&self, record: &Record) -> bool {
+        record.value.first().map_or(false, |b| *b != 0)
+    }
+
+    fn select(&self, _: &Multihash, records: &[Record]) -> usize {
+        records.iter().enumerate().max_by_key(|(_, r)| &r.value).map_or(0, |(i, _)| i)
+    }
+}
+
+#[test]
+fn put_record_rejected() {
+    let mut config = KademliaConfig::default();
+    config.set_record_validator(TestValidator);
+    let (_, mut swarms) = build_nodes_with_config(1, config);
+
+    let record = Record::new(multihash::encode(SHA2256, &vec![1,2,3]).unwrap(), vec![0]);
+    swarms[0].put_record(record.clone(), Quorum::One);
+    assert!(swarms[0].store.get(&record.key).is_none());
+
+    current_thread::run(
+        future::poll_fn(move || {
+            match swarms[0].poll().unwrap() {
+                Async::Ready(Some(KademliaEvent::PutRecordResult(Err(e)))) => {
+                    match e {
+                        PutRecordError::Rejected { key } => assert_eq!(key, record.key),
+                        e => panic!("Unexpected error: {:?}", e),
+                    }
+                    Ok(Async::Ready(()))
+                }
+                Async::Ready(e) => panic!("Unexpected event: {:?}", e),
+                Async::NotReady => Ok(Async::NotReady),
+            }
+        }))
+}
+
+#[test]
+fn get_value_rejected() {
+    let mut config = KademliaConfig::default();
+    config.set_record_validator(TestValidator);
+    let (port_base, mut swarms) = build_nodes_with_config(2, config);
+
+    let swarm_ids: Vec<_> = swarms.iter().map(Swarm::local_peer_id).cloned().collect();
+    swarms[0].add_address(&swarm_ids[1], Protocol::Memory(port_base + 1).into());
+
+    let record = Record::new(multihash::encode(SHA2256, &vec![1,2,3]).unwrap(), vec![0]);
+    swarms[1].store.put(record.clone()).unwrap();
+    swarms[0].get_record(&record.key, Quorum::One);
+
+    current_thread::run(
+        future::poll_fn(move || {
+            for swarm in &mut swarms {
+                loop {
+                    match swarm.poll().unwrap() {
+                        Async::Ready(Some(KademliaEvent::GetRecordResult(result))) => {
+                            match result {
+                                Err(GetRecordError::Rejected { key, records }) => {
+                                    assert_eq!(key, record.key);
+                                    assert_eq!(records, vec![record.clone()]);
+                                }
+                                r => panic!("Unexpected result: {:?}", r),
+                            }
+                            return Ok(Async::Ready(()));
+                        }
+                        Async::Ready(_) => (),
+                        Async::NotReady => break,
+                    }
+                }
+            }
+            Ok(Async::NotReady)
+        }))
+}
+
+#[test]
+fn get_value_selects_best_record() {
+    let mut config = KademliaConfig::default();
+    config.set_record_validator(TestValidator);
+    let (port_base, mut swarms) = build_nodes_with_config(3, config);
+
+    let swarm_ids: Vec<_> = swarms.iter().map(Swarm::local_peer_id).cloned().collect();
+    swarms[0].add_address(&swarm_ids[1], Protocol::Memory(port_base + 1).into());
+    swarms[0].add_address(&swarm_ids[2], Protocol::Memory(port_base + 2).into());
+
+    let key = multihash::encode(SHA2256, &vec![1,2,3]).unwrap();
+    let worse = Record::new(key.clone(), vec![1]);
+    let better = Record::new(key.clone(), vec![2]);
+    swarms[1].store.put(worse.clone()).unwrap();
+    swarms[2].store.put(better.clone()).unwrap();
+    swarms[0].get_record(&key, Quorum::N(NonZeroUsize::new(2).unwrap()));
+
+    current_thread::run(
+        future::poll_fn(move || {
+            for swarm in &mut swarms {
+                loop {
+                    match swarm.poll().unwrap() {
+                        Async::Ready(Some(KademliaEvent::GetRecordResult(Ok(ok)))) => {
+                            assert_eq!(ok.records.len(), 2);
+                            assert_eq!(ok.records.first(), Some(&better));
+                            return Ok(Async::Ready(()));
+                        }
+                        Async::Ready(_) => (),
+                        Async::NotReady => break,
+                    }
+                }
+            }
+            Ok(Async::NotReady)
+        }))
+}
+
+#[test]
+fn get_value_rejects_local_record() {
+    let mut config = KademliaConfig::default();
+    config.set_record_validator(TestValidator);
+    let (_, mut swarms) = build_nodes_with_config(1, config);
+
+    // The record bypasses validation when put into the store directly.
+    let record = Record::new(multihash::encode(SHA2256, &vec![1,2,3]).unwrap(), vec![0]);
+    swarms[0].store.put(record.clone()).unwrap();
+    swarms[0].get_record(&record.key, Quorum::One);
+    assert!(swarms[0].store.get(&record.key).is_none());
+
+    current_thread::run(
+        future::poll_fn(move || {
+            match swarms[0].poll().unwrap() {
+                Async::Ready(Some(KademliaEvent::GetRecordResult(result))) => {
+                    match result {
+                        Err(GetRecordError::Rejected { key, records }) => {
+                            assert_eq!(key, record.key);
+                            assert_eq!(records, vec![record.clone()]);
+                        }
+                        r => panic!("Unexpected result: {:?}", r),
+                    }
+                    Ok(Async::Ready(()))
+                }
+                Async::Ready(e) => panic!("Unexpected event: {:?}", e),
+                Async::NotReady => Ok(Async::NotReady),
+            }
+        }))
+}
+
+#[test]
+fn get_value_quorum_failed_selects_best_record() {
+    let mut config = KademliaConfig::default();
+    config.set_record_validator(TestValidator);
+    let (port_base, mut swarms) = build_nodes_with_config(3, config);
+
+    let swarm_ids: Vec<_> = swarms.iter().map(Swarm::local_peer_id).cloned().collect();
+    swarms[0].add_address(&swarm_ids[1], Protocol::Memory(port_base + 1).into());
+    swarms[0].add_address(&swarm_ids[2], Protocol::Memory(port_base + 2).into());
+
+    let key = multihash::encode(SHA2256, &vec![1,2,3]).unwrap();
+    let worse = Record::new(key.clone(), vec![1]);
+    let better = Record::new(key.clone(), vec![2]);
+    swarms[1].store.put(worse.clone()).unwrap();
+    swarms[2].store.put(better.clone()).unwrap();
+    swarms[0].get_record(&key, Quorum::N(NonZeroUsize::new(3).unwrap()));
+
+    current_thread::run(
+        future::poll_fn(move || {
+            for swarm in &mut swarms {
+                loop {
+                    match swarm.poll().unwrap() {
+                        Async::Ready(Some(KademliaEvent::GetRecordResult(result))) => {
+                            match result {
+                                Err(GetRecordError::QuorumFailed { records, .. }) => {
+                                    assert_eq!(records.len(), 2);
+                                    assert_eq!(records.first(), Some(&better));
+                                }
+                                r => panic!("Unexpected result: {:?}", r),
+                            }
+                            return Ok(Async::Ready(()));
+                        }
+                        Async::Ready(_) => (),
+                        Async::NotReady => break,
+                    }
+                }
+            }
+            Ok(Async::NotReady)
+        }))
+}
+
 #[test]
 fn get_value_many() {
     // TODO: Randomise
@@ -26,19 +26,20 @@ use crate::record::Record;
 use futures::prelude::*;
 use libp2p_swarm::{
     KeepAlive,
+    Misbehaviour,
     SubstreamProtocol,
-    ProtocolsHandler,
     ProtocolsHandlerEvent,
-    ProtocolsHandlerUpgrErr
+    ProtocolsHandlerUpgrErr,
+    compat::ProtocolsHandler01
 };
 use libp2p_core::{
+    clock,
     either::EitherOutput,
     upgrade::{self, InboundUpgrade, OutboundUpgrade, Negotiated}
 };
 use multihash::Multihash;
 use std::{borrow::Cow, error, fmt, io, time::Duration};
 use tokio_io::{AsyncRead, AsyncWrite};
-use wasm_timer::Instant;
 
 /// Protocol handler that handles Kademlia communications with the remote.
 ///
@@ -234,6 +235,19 @@ pub enum KademliaHandlerQueryErr {
     Io(io::Error),
 }
 
+impl KademliaHandlerQueryErr {
+    /// Returns the misbehaviour of the remote that the error denotes, if any.
+    pub(crate) fn misbehaviour(&self) -> Option<Misbehaviour> {
+        match self {
+            KademliaHandlerQueryErr::Upgrade(err) => Misbehaviour::from_upgrade_error(err),
+            KademliaHandlerQueryErr::UnexpectedMessage => Some(Misbehaviour::ProtocolViolation),
+            KademliaHandlerQueryErr::Io(err) if err.kind() == io::ErrorKind::InvalidData =>
+                Some(Misbehaviour::ProtocolViolation),
+            KademliaHandlerQueryErr::Io(_) => None,
+        }
+    }
+}
+
 impl fmt::Display for KademliaHandlerQueryErr {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -279,6 +293,12 @@ pub enum KademliaHandlerIn<TUserData> {
     /// for the query on the remote.
     Reset(KademliaRequestId),
 
+    /// Changes whether incoming Kademlia substreams are accepted,
+    /// see [`KademliaHandler::dial_only`].
+    ///
+    /// Substreams that have already been accepted are not affected.
+    SetAllowListening(bool),
+
     /// Request for the list of nodes whose IDs are the closest to `key`. The number of nodes
     /// returned is not specified, but should be around 20.
     FindNodeReq {
@@ -427,7 +447,7 @@ where
     }
 }
 
-impl<TSubstream, TUserData> ProtocolsHandler for KademliaHandler<TSubstream, TUserData>
+impl<TSubstream, TUserData> ProtocolsHandler01 for KademliaHandler<TSubstream, TUserData>
 where
     TSubstream: AsyncRead + AsyncWrite,
     TUserData: Clone,
@@ -463,14 +483,14 @@ where
         &mut self,
         protocol: <Self::InboundProtocol as InboundUpgrade<TSubstream>>::Output,
     ) {
-        // If `self.allow_listening` is false, then we produced a `DeniedUpgrade` and `protocol`
-        // is a `Void`.
+        // If `self.allow_listening` was false when the substream was opened, then we produced
+        // a `DeniedUpgrade` and `protocol` is a `Void`. A substream negotiated before listening
+        // was disallowed via `KademliaHandlerIn::SetAllowListening` is still served.
         let protocol = match protocol {
             EitherOutput::First(p) => p,
             EitherOutput::Second(p) => void::unreachable(p),
         };
 
-        debug_assert!(self.allow_listening);
         let connec_unique_id = self.next_connec_unique_id;
         self.next_connec_unique_id.0 += 1;
         self.substreams
@@ -489,6 +509,9 @@ where
                     let _ = self.substreams.remove(pos).try_close();
                 }
             }
+            KademliaHandlerIn::SetAllowListening(allow_listening) => {
+                self.allow_listening = allow_listening;
+            }
             KademliaHandlerIn::FindNodeReq { key, user_data } => {
                 let msg = KadRequestMsg::FindNode { key };
                 self.substreams.push(SubstreamState::OutPendingOpen(msg, Some(user_data.clone())));
@@ -678,7 +701,7 @@ where
         }
 
         if self.substreams.is_empty() {
-            self.keep_alive = KeepAlive::Until(Instant::now() + Duration::from_secs(10));
+            self.keep_alive = KeepAlive::Until(clock::now() + Duration::from_secs(10));
         } else {
             self.keep_alive = KeepAlive::Yes;
         }
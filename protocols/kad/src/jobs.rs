@@ -63,7 +63,7 @@
 
 use crate::record::{Record, ProviderRecord, store::RecordStore};
 
-use libp2p_core::PeerId;
+use libp2p_core::{PeerId, clock};
 use futures::prelude::*;
 use multihash::Multihash;
 use std::collections::HashSet;
@@ -99,7 +99,7 @@ impl<T> PeriodicJob<T> {
     /// for the delay to expire.
     fn asap(&mut self) {
         if let PeriodicJobState::Waiting(delay) = &mut self.state {
-            delay.reset(Instant::now() - Duration::from_secs(1))
+            delay.reset(clock::now() - Duration::from_secs(1))
         }
     }
 
@@ -144,7 +144,7 @@ impl PutRecordJob {
         publish_interval: Option<Duration>,
         record_ttl: Option<Duration>,
     ) -> Self {
-        let now = Instant::now();
+        let now = clock::now();
         let delay = Delay::new(now + replicate_interval);
         let next_publish = publish_interval.map(|i| now + i);
         Self {
@@ -177,7 +177,7 @@ impl PutRecordJob {
     /// The job is guaranteed to run on the next invocation of `poll`.
     pub fn asap(&mut self, publish: bool) {
         if publish {
-            self.next_publish = Some(Instant::now() - Duration::from_secs(1))
+            self.next_publish = Some(clock::now() - Duration::from_secs(1))
         }
         self.inner.asap()
     }
@@ -254,7 +254,7 @@ pub struct AddProviderJob {
 impl AddProviderJob {
     /// Creates a new periodic job for provider announcements.
     pub fn new(interval: Duration) -> Self {
-        let now = Instant::now();
+        let now = clock::now();
         Self {
             inner: PeriodicJob {
                 interval,
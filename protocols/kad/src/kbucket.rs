@@ -76,7 +76,9 @@ pub use entry::*;
 use arrayvec::{self, ArrayVec};
 use bucket::KBucket;
 use std::collections::VecDeque;
-use std::time::{Duration, Instant};
+use libp2p_core::clock;
+use std::time::Duration;
+use wasm_timer::Instant;
 
 /// Maximum number of k-buckets.
 const NUM_BUCKETS: usize = 256;
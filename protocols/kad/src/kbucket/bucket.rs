@@ -68,7 +68,7 @@ impl<TKey, TVal> PendingNode<TKey, TVal> {
     }
 
     pub fn is_ready(&self) -> bool {
-        Instant::now() >= self.replace
+        clock::now() >= self.replace
     }
 
     pub fn set_ready_at(&mut self, t: Instant) {
@@ -206,7 +206,7 @@ where
     /// bucket remained unchanged.
     pub fn apply_pending(&mut self) -> Option<AppliedPending<TKey, TVal>> {
         if let Some(pending) = self.pending.take() {
-            if pending.replace <= Instant::now() {
+            if pending.replace <= clock::now() {
                 if self.nodes.is_full() {
                     if self.status(Position(0)) == NodeStatus::Connected {
                         // The bucket is full with connected nodes. Drop the pending node.
@@ -329,7 +329,7 @@ where
                         self.pending = Some(PendingNode {
                             node,
                             status: NodeStatus::Connected,
-                            replace: Instant::now() + self.pending_timeout,
+                            replace: clock::now() + self.pending_timeout,
                         });
                         return InsertResult::Pending {
                             disconnected: self.nodes[0].key.clone()
@@ -36,7 +36,7 @@ mod protobuf_structs;
 mod query;
 
 pub use addresses::Addresses;
-pub use behaviour::{Kademlia, KademliaConfig, KademliaEvent, Quorum};
+pub use behaviour::{Kademlia, KademliaConfig, KademliaEvent, Mode, Quorum, QueryKind, QueryStats, MAX_QUERY_STATS};
 pub use behaviour::{
     BootstrapResult,
     BootstrapOk,
@@ -63,7 +63,7 @@ pub use behaviour::{
     GetProvidersError,
 };
 pub use protocol::KadConnectionType;
-pub use record::{store, Record, ProviderRecord};
+pub use record::{store, Record, RecordValidator, ProviderRecord};
 
 use std::num::NonZeroUsize;
 
@@ -747,6 +747,7 @@ pub struct Message_Peer {
     pub id: ::std::vec::Vec<u8>,
     pub addrs: ::protobuf::RepeatedField<::std::vec::Vec<u8>>,
     pub connection: Message_ConnectionType,
+    pub signedPeerRecord: ::std::vec::Vec<u8>,
     // special fields
     pub unknown_fields: ::protobuf::UnknownFields,
     pub cached_size: ::protobuf::CachedSize,
@@ -828,6 +829,32 @@ impl Message_Peer {
     pub fn set_connection(&mut self, v: Message_ConnectionType) {
         self.connection = v;
     }
+
+    // bytes signedPeerRecord = 888;
+
+
+    pub fn get_signedPeerRecord(&self) -> &[u8] {
+        &self.signedPeerRecord
+    }
+    pub fn clear_signedPeerRecord(&mut self) {
+        self.signedPeerRecord.clear();
+    }
+
+    // Param is passed by value, moved
+    pub fn set_signedPeerRecord(&mut self, v: ::std::vec::Vec<u8>) {
+        self.signedPeerRecord = v;
+    }
+
+    // Mutable pointer to the field.
+    // If field is not initialized, it is initialized with default value first.
+    pub fn mut_signedPeerRecord(&mut self) -> &mut ::std::vec::Vec<u8> {
+        &mut self.signedPeerRecord
+    }
+
+    // Take field
+    pub fn take_signedPeerRecord(&mut self) -> ::std::vec::Vec<u8> {
+        ::std::mem::replace(&mut self.signedPeerRecord, ::std::vec::Vec::new())
+    }
 }
 
 impl ::protobuf::Message for Message_Peer {
@@ -848,6 +875,9 @@ impl ::protobuf::Message for Message_Peer {
                 3 => {
                     ::protobuf::rt::read_proto3_enum_with_unknown_fields_into(wire_type, is, &mut self.connection, 3, &mut self.unknown_fields)?
                 },
+                888 => {
+                    ::protobuf::rt::read_singular_proto3_bytes_into(wire_type, is, &mut self.signedPeerRecord)?;
+                },
                 _ => {
                     ::protobuf::rt::read_unknown_or_skip_group(field_number, wire_type, is, self.mut_unknown_fields())?;
                 },
@@ -869,6 +899,9 @@ impl ::protobuf::Message for Message_Peer {
         if self.connection != Message_ConnectionType::NOT_CONNECTED {
             my_size += ::protobuf::rt::enum_size(3, self.connection);
         }
+        if !self.signedPeerRecord.is_empty() {
+            my_size += ::protobuf::rt::bytes_size(888, &self.signedPeerRecord);
+        }
         my_size += ::protobuf::rt::unknown_fields_size(self.get_unknown_fields());
         self.cached_size.set(my_size);
         my_size
@@ -884,6 +917,9 @@ impl ::protobuf::Message for Message_Peer {
         if self.connection != Message_ConnectionType::NOT_CONNECTED {
             os.write_enum(3, self.connection.value())?;
         }
+        if !self.signedPeerRecord.is_empty() {
+            os.write_bytes(888, &self.signedPeerRecord)?;
+        }
         os.write_unknown_fields(self.get_unknown_fields())?;
         ::std::result::Result::Ok(())
     }
@@ -941,6 +977,11 @@ impl ::protobuf::Message for Message_Peer {
                     |m: &Message_Peer| { &m.connection },
                     |m: &mut Message_Peer| { &mut m.connection },
                 ));
+                fields.push(::protobuf::reflect::accessor::make_simple_field_accessor::<_, ::protobuf::types::ProtobufTypeBytes>(
+                    "signedPeerRecord",
+                    |m: &Message_Peer| { &m.signedPeerRecord },
+                    |m: &mut Message_Peer| { &mut m.signedPeerRecord },
+                ));
                 ::protobuf::reflect::MessageDescriptor::new::<Message_Peer>(
                     "Message_Peer",
                     fields,
@@ -966,6 +1007,7 @@ impl ::protobuf::Clear for Message_Peer {
         self.id.clear();
         self.addrs.clear();
         self.connection = Message_ConnectionType::NOT_CONNECTED;
+        self.signedPeerRecord.clear();
         self.unknown_fields.clear();
     }
 }
@@ -1115,39 +1157,40 @@ static file_descriptor_proto_data: &'static [u8] = b"\
     \x01\x20\x01(\x0cR\x03key\x12\x14\n\x05value\x18\x02\x20\x01(\x0cR\x05va\
     lue\x12\"\n\x0ctimeReceived\x18\x05\x20\x01(\tR\x0ctimeReceived\x12\x1d\
     \n\tpublisher\x18\x9a\x05\x20\x01(\x0cR\tpublisher\x12\x11\n\x03ttl\x18\
-    \x89\x06\x20\x01(\rR\x03ttl\"\xc4\x04\n\x07Message\x12/\n\x04type\x18\
+    \x89\x06\x20\x01(\rR\x03ttl\"\xf2\x04\n\x07Message\x12/\n\x04type\x18\
     \x01\x20\x01(\x0e2\x1b.dht.pb.Message.MessageTypeR\x04type\x12(\n\x0fclu\
     sterLevelRaw\x18\n\x20\x01(\x05R\x0fclusterLevelRaw\x12\x10\n\x03key\x18\
     \x02\x20\x01(\x0cR\x03key\x12&\n\x06record\x18\x03\x20\x01(\x0b2\x0e.dht\
     .pb.RecordR\x06record\x126\n\x0bcloserPeers\x18\x08\x20\x03(\x0b2\x14.dh\
     t.pb.Message.PeerR\x0bcloserPeers\x12:\n\rproviderPeers\x18\t\x20\x03(\
-    \x0b2\x14.dht.pb.Message.PeerR\rproviderPeers\x1al\n\x04Peer\x12\x0e\n\
-    \x02id\x18\x01\x20\x01(\x0cR\x02id\x12\x14\n\x05addrs\x18\x02\x20\x03(\
-    \x0cR\x05addrs\x12>\n\nconnection\x18\x03\x20\x01(\x0e2\x1e.dht.pb.Messa\
-    ge.ConnectionTypeR\nconnection\"i\n\x0bMessageType\x12\r\n\tPUT_VALUE\
-    \x10\0\x12\r\n\tGET_VALUE\x10\x01\x12\x10\n\x0cADD_PROVIDER\x10\x02\x12\
-    \x11\n\rGET_PROVIDERS\x10\x03\x12\r\n\tFIND_NODE\x10\x04\x12\x08\n\x04PI\
-    NG\x10\x05\"W\n\x0eConnectionType\x12\x11\n\rNOT_CONNECTED\x10\0\x12\r\n\
-    \tCONNECTED\x10\x01\x12\x0f\n\x0bCAN_CONNECT\x10\x02\x12\x12\n\x0eCANNOT\
-    _CONNECT\x10\x03J\xbe\x18\n\x06\x12\x04\0\0X\x01\n\x08\n\x01\x0c\x12\x03\
-    \0\0\x12\n\x08\n\x01\x02\x12\x03\x01\x08\x0e\nX\n\x02\x04\0\x12\x04\x05\
-    \0\x1c\x01\x1aL\x20Record\x20represents\x20a\x20dht\x20record\x20that\
-    \x20contains\x20a\x20value\n\x20for\x20a\x20key\x20value\x20pair\n\n\n\n\
-    \x03\x04\0\x01\x12\x03\x05\x08\x0e\n2\n\x04\x04\0\x02\0\x12\x03\x07\x08\
-    \x16\x1a%\x20The\x20key\x20that\x20references\x20this\x20record\n\n\r\n\
-    \x05\x04\0\x02\0\x04\x12\x04\x07\x08\x05\x10\n\x0c\n\x05\x04\0\x02\0\x05\
-    \x12\x03\x07\x08\r\n\x0c\n\x05\x04\0\x02\0\x01\x12\x03\x07\x0e\x11\n\x0c\
-    \n\x05\x04\0\x02\0\x03\x12\x03\x07\x14\x15\n6\n\x04\x04\0\x02\x01\x12\
-    \x03\n\x08\x18\x1a)\x20The\x20actual\x20value\x20this\x20record\x20is\
-    \x20storing\n\n\r\n\x05\x04\0\x02\x01\x04\x12\x04\n\x08\x07\x16\n\x0c\n\
-    \x05\x04\0\x02\x01\x05\x12\x03\n\x08\r\n\x0c\n\x05\x04\0\x02\x01\x01\x12\
-    \x03\n\x0e\x13\n\x0c\n\x05\x04\0\x02\x01\x03\x12\x03\n\x16\x17\n\xfc\x01\
-    \n\x04\x04\0\x02\x02\x12\x03\x13\x08\x20\x1a/\x20Time\x20the\x20record\
-    \x20was\x20received,\x20set\x20by\x20receiver\n2\xbd\x01\x20Note:\x20The\
-    se\x20fields\x20were\x20removed\x20from\x20the\x20Record\x20message\n\
-    \x20hash\x20of\x20the\x20authors\x20public\x20key\noptional\x20string\
-    \x20author\x20=\x203;\n\x20A\x20PKI\x20signature\x20for\x20the\x20key+va\
-    lue+author\noptional\x20bytes\x20signature\x20=\x204;\n\n\r\n\x05\x04\0\
+    \x0b2\x14.dht.pb.Message.PeerR\rproviderPeers\x1a\x99\x01\n\x04Peer\x12\
+    \x0e\n\x02id\x18\x01\x20\x01(\x0cR\x02id\x12\x14\n\x05addrs\x18\x02\x20\
+    \x03(\x0cR\x05addrs\x12>\n\nconnection\x18\x03\x20\x01(\x0e2\x1e.dht.pb.\
+    Message.ConnectionTypeR\nconnection\x12+\n\x10signedPeerRecord\x18\xf8\
+    \x06\x20\x01(\x0cR\x10signedPeerRecord\"i\n\x0bMessageType\x12\r\n\tPUT_\
+    VALUE\x10\0\x12\r\n\tGET_VALUE\x10\x01\x12\x10\n\x0cADD_PROVIDER\x10\x02\
+    \x12\x11\n\rGET_PROVIDERS\x10\x03\x12\r\n\tFIND_NODE\x10\x04\x12\x08\n\
+    \x04PING\x10\x05\"W\n\x0eConnectionType\x12\x11\n\rNOT_CONNECTED\x10\0\
+    \x12\r\n\tCONNECTED\x10\x01\x12\x0f\n\x0bCAN_CONNECT\x10\x02\x12\x12\n\
+    \x0eCANNOT_CONNECT\x10\x03J\xbe\x18\n\x06\x12\x04\0\0X\x01\n\x08\n\x01\
+    \x0c\x12\x03\0\0\x12\n\x08\n\x01\x02\x12\x03\x01\x08\x0e\nX\n\x02\x04\0\
+    \x12\x04\x05\0\x1c\x01\x1aL\x20Record\x20represents\x20a\x20dht\x20recor\
+    d\x20that\x20contains\x20a\x20value\n\x20for\x20a\x20key\x20value\x20pai\
+    r\n\n\n\n\x03\x04\0\x01\x12\x03\x05\x08\x0e\n2\n\x04\x04\0\x02\0\x12\x03\
+    \x07\x08\x16\x1a%\x20The\x20key\x20that\x20references\x20this\x20record\
+    \n\n\r\n\x05\x04\0\x02\0\x04\x12\x04\x07\x08\x05\x10\n\x0c\n\x05\x04\0\
+    \x02\0\x05\x12\x03\x07\x08\r\n\x0c\n\x05\x04\0\x02\0\x01\x12\x03\x07\x0e\
+    \x11\n\x0c\n\x05\x04\0\x02\0\x03\x12\x03\x07\x14\x15\n6\n\x04\x04\0\x02\
+    \x01\x12\x03\n\x08\x18\x1a)\x20The\x20actual\x20value\x20this\x20record\
+    \x20is\x20storing\n\n\r\n\x05\x04\0\x02\x01\x04\x12\x04\n\x08\x07\x16\n\
+    \x0c\n\x05\x04\0\x02\x01\x05\x12\x03\n\x08\r\n\x0c\n\x05\x04\0\x02\x01\
+    \x01\x12\x03\n\x0e\x13\n\x0c\n\x05\x04\0\x02\x01\x03\x12\x03\n\x16\x17\n\
+    \xfc\x01\n\x04\x04\0\x02\x02\x12\x03\x13\x08\x20\x1a/\x20Time\x20the\x20\
+    record\x20was\x20received,\x20set\x20by\x20receiver\n2\xbd\x01\x20Note:\
+    \x20These\x20fields\x20were\x20removed\x20from\x20the\x20Record\x20messa\
+    ge\n\x20hash\x20of\x20the\x20authors\x20public\x20key\noptional\x20strin\
+    g\x20author\x20=\x203;\n\x20A\x20PKI\x20signature\x20for\x20the\x20key+v\
+    alue+author\noptional\x20bytes\x20signature\x20=\x204;\n\n\r\n\x05\x04\0\
     \x02\x02\x04\x12\x04\x13\x08\n\x18\n\x0c\n\x05\x04\0\x02\x02\x05\x12\x03\
     \x13\x08\x0e\n\x0c\n\x05\x04\0\x02\x02\x01\x12\x03\x13\x0f\x1b\n\x0c\n\
     \x05\x04\0\x02\x02\x03\x12\x03\x13\x1e\x1f\nX\n\x04\x04\0\x02\x03\x12\
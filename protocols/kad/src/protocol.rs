@@ -35,7 +35,8 @@ use codec::UviBytes;
 use crate::protobuf_structs::dht as proto;
 use crate::record::Record;
 use futures::{future::{self, FutureResult}, sink, stream, Sink, Stream};
-use libp2p_core::{Multiaddr, PeerId};
+use libp2p_core::{Multiaddr, PeerId, PeerRecord, SignedEnvelope, clock};
+use libp2p_core::compat::Compat01As03;
 use libp2p_core::upgrade::{InboundUpgrade, OutboundUpgrade, UpgradeInfo, Negotiated};
 use multihash::Multihash;
 use protobuf::{self, Message};
@@ -44,7 +45,6 @@ use std::{io, iter};
 use tokio_codec::Framed;
 use tokio_io::{AsyncRead, AsyncWrite};
 use unsigned_varint::codec;
-use wasm_timer::Instant;
 
 /// Status of our connection to a node reported by the Kademlia protocol.
 #[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
@@ -98,6 +98,11 @@ pub struct KadPeer {
     pub multiaddrs: Vec<Multiaddr>,
     /// How the sender is connected to that remote.
     pub connection_ty: KadConnectionType,
+    /// The addresses of the peer signed by the peer itself, if the sender knows them.
+    ///
+    /// When decoded from a message, the record has been verified to be signed by `node_id`,
+    /// and `multiaddrs` contains the addresses of the record.
+    pub signed_record: Option<PeerRecord>,
 }
 
 // Builds a `KadPeer` from a corresponding protobuf message.
@@ -119,10 +124,25 @@ impl TryFrom<&mut proto::Message_Peer> for KadPeer {
 
         let connection_ty = peer.get_connection().into();
 
+        // A signed record supersedes the unauthenticated addresses. An invalid record is
+        // ignored rather than invalidating the whole peer.
+        let signed_record = if peer.get_signedPeerRecord().is_empty() {
+            None
+        } else {
+            SignedEnvelope::from_protobuf_encoding(peer.get_signedPeerRecord())
+                .ok()
+                .and_then(|envelope| PeerRecord::from_signed_envelope(envelope).ok())
+                .filter(|record| record.peer_id() == &node_id)
+        };
+        if let Some(record) = &signed_record {
+            addrs = record.addresses().to_vec();
+        }
+
         Ok(KadPeer {
             node_id,
             multiaddrs: addrs,
-            connection_ty
+            connection_ty,
+            signed_record,
         })
     }
 }
@@ -135,6 +155,9 @@ impl Into<proto::Message_Peer> for KadPeer {
             out.mut_addrs().push(addr.to_vec());
         }
         out.set_connection(self.connection_ty.into());
+        if let Some(record) = self.signed_record {
+            out.set_signedPeerRecord(record.into_signed_envelope().into_protobuf_encoding());
+        }
         out
     }
 }
@@ -180,7 +203,7 @@ where
     C: AsyncRead + AsyncWrite,
 {
     type Output = KadInStreamSink<Negotiated<C>>;
-    type Future = FutureResult<Self::Output, io::Error>;
+    type Future = Compat01As03<FutureResult<Self::Output, io::Error>>;
     type Error = io::Error;
 
     #[inline]
@@ -188,7 +211,7 @@ where
         let mut codec = UviBytes::default();
         codec.set_max_len(4096);
 
-        future::ok(
+        Compat01As03::new(future::ok(
             Framed::new(incoming, codec)
                 .from_err()
                 .with::<_, fn(_) -> _, _>(|response| {
@@ -199,7 +222,7 @@ where
                     let request = protobuf::parse_from_bytes(&bytes)?;
                     proto_to_req_msg(request)
                 }),
-        )
+        ))
     }
 }
 
@@ -208,7 +231,7 @@ where
     C: AsyncRead + AsyncWrite,
 {
     type Output = KadOutStreamSink<Negotiated<C>>;
-    type Future = FutureResult<Self::Output, io::Error>;
+    type Future = Compat01As03<FutureResult<Self::Output, io::Error>>;
     type Error = io::Error;
 
     #[inline]
@@ -216,7 +239,7 @@ where
         let mut codec = UviBytes::default();
         codec.set_max_len(4096);
 
-        future::ok(
+        Compat01As03::new(future::ok(
             Framed::new(incoming, codec)
                 .from_err()
                 .with::<_, fn(_) -> _, _>(|request| {
@@ -227,7 +250,7 @@ where
                     let response = protobuf::parse_from_bytes(&bytes)?;
                     proto_to_resp_msg(response)
                 }),
-        )
+        ))
     }
 }
 
@@ -571,7 +594,7 @@ fn record_from_proto(mut record: proto::Record) -> Result<Record, io::Error> {
 
     let expires =
         if record.ttl > 0 {
-            Some(Instant::now() + Duration::from_secs(record.ttl as u64))
+            Some(clock::now() + Duration::from_secs(record.ttl as u64))
         } else {
             None
         };
@@ -587,7 +610,7 @@ fn record_to_proto(record: Record) -> proto::Record {
         pb_record.publisher = p.into_bytes();
     }
     if let Some(t) = record.expires {
-        let now = Instant::now();
+        let now = clock::now();
         if t > now {
             pb_record.ttl = (t - now).as_secs() as u32;
         } else {
@@ -697,4 +720,42 @@ mod tests {
             bg_thread.join().unwrap();
         }
     }*/
+
+    use super::*;
+    use libp2p_core::identity::Keypair;
+
+    #[test]
+    fn signed_record_supersedes_addresses() {
+        let key = Keypair::generate_ed25519();
+        let record_addr: Multiaddr = "/ip4/1.2.3.4/tcp/1234".parse().unwrap();
+        let record = PeerRecord::new(&key, vec![record_addr.clone()]).unwrap();
+        let peer = KadPeer {
+            node_id: key.public().into_peer_id(),
+            multiaddrs: vec!["/ip4/5.6.7.8/tcp/5678".parse().unwrap()],
+            connection_ty: KadConnectionType::Connected,
+            signed_record: Some(record.clone()),
+        };
+
+        let mut proto: proto::Message_Peer = peer.into();
+        let decoded = KadPeer::try_from(&mut proto).unwrap();
+        assert_eq!(decoded.multiaddrs, vec![record_addr]);
+        assert_eq!(decoded.signed_record, Some(record));
+    }
+
+    #[test]
+    fn signed_record_of_another_peer_is_ignored() {
+        let record = PeerRecord::new(&Keypair::generate_ed25519(), Vec::new()).unwrap();
+        let addr: Multiaddr = "/ip4/5.6.7.8/tcp/5678".parse().unwrap();
+        let peer = KadPeer {
+            node_id: PeerId::random(),
+            multiaddrs: vec![addr.clone()],
+            connection_ty: KadConnectionType::Connected,
+            signed_record: Some(record),
+        };
+
+        let mut proto: proto::Message_Peer = peer.into();
+        let decoded = KadPeer::try_from(&mut proto).unwrap();
+        assert_eq!(decoded.multiaddrs, vec![addr]);
+        assert!(decoded.signed_record.is_none());
+    }
 }
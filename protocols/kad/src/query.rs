@@ -22,6 +22,7 @@ mod peers;
 
 use peers::PeersIterState;
 use peers::closest::{ClosestPeersIter, ClosestPeersIterConfig};
+use peers::closest::disjoint::DisjointClosestPeersIter;
 use peers::fixed::FixedPeersIter;
 
 use crate::K_VALUE;
@@ -98,6 +99,9 @@ impl<TInner> QueryPool<TInner> {
     }
 
     /// Adds a query to the pool that iterates towards the closest peers to the target.
+    ///
+    /// If the pool is configured with `disjoint_paths`, the query follows
+    /// that many disjoint paths towards the target.
     pub fn add_iter_closest<T, I>(&mut self, target: T, peers: I, inner: TInner) -> QueryId
     where
         T: Into<KeyBytes>,
@@ -107,7 +111,11 @@ impl<TInner> QueryPool<TInner> {
             num_results: self.config.replication_factor.get(),
             .. ClosestPeersIterConfig::default()
         };
-        let peer_iter = QueryPeerIter::Closest(ClosestPeersIter::with_config(cfg, target, peers));
+        let peer_iter = match self.config.disjoint_paths {
+            Some(paths) => QueryPeerIter::ClosestDisjoint(
+                DisjointClosestPeersIter::with_config(cfg, paths, target, peers)),
+            None => QueryPeerIter::Closest(ClosestPeersIter::with_config(cfg, target, peers)),
+        };
         self.add(peer_iter, inner)
     }
 
@@ -189,13 +197,17 @@ pub struct QueryId(usize);
 pub struct QueryConfig {
     pub timeout: Duration,
     pub replication_factor: NonZeroUsize,
+    /// The number of disjoint paths followed by iterative queries,
+    /// or `None` for a single path.
+    pub disjoint_paths: Option<NonZeroUsize>,
 }
 
 impl Default for QueryConfig {
     fn default() -> Self {
         QueryConfig {
             timeout: Duration::from_secs(60),
-            replication_factor: NonZeroUsize::new(K_VALUE.get()).expect("K_VALUE > 0")
+            replication_factor: NonZeroUsize::new(K_VALUE.get()).expect("K_VALUE > 0"),
+            disjoint_paths: None,
         }
     }
 }
@@ -216,6 +228,7 @@ pub struct Query<TInner> {
 /// The peer selection strategies that can be used by queries.
 enum QueryPeerIter {
     Closest(ClosestPeersIter),
+    ClosestDisjoint(DisjointClosestPeersIter),
     Fixed(FixedPeersIter)
 }
 
@@ -225,6 +238,12 @@ impl<TInner> Query<TInner> {
         Query { id, inner, peer_iter, started: None }
     }
 
+    /// Returns the time elapsed since the query started, i.e. since it was
+    /// first polled by the `QueryPool`.
+    pub fn elapsed(&self, now: Instant) -> Duration {
+        self.started.map_or(Duration::from_secs(0), |started| now - started)
+    }
+
     /// Gets the unique ID of the query.
     pub fn id(&self) -> QueryId {
         self.id
@@ -234,6 +253,7 @@ impl<TInner> Query<TInner> {
     pub fn on_failure(&mut self, peer: &PeerId) {
         match &mut self.peer_iter {
             QueryPeerIter::Closest(iter) => iter.on_failure(peer),
+            QueryPeerIter::ClosestDisjoint(iter) => iter.on_failure(peer),
             QueryPeerIter::Fixed(iter) => iter.on_failure(peer)
         }
     }
@@ -247,6 +267,7 @@ impl<TInner> Query<TInner> {
     {
         match &mut self.peer_iter {
             QueryPeerIter::Closest(iter) => iter.on_success(peer, new_peers),
+            QueryPeerIter::ClosestDisjoint(iter) => iter.on_success(peer, new_peers),
             QueryPeerIter::Fixed(iter) => iter.on_success(peer)
         }
     }
@@ -255,6 +276,7 @@ impl<TInner> Query<TInner> {
     pub fn is_waiting(&self, peer: &PeerId) -> bool {
         match &self.peer_iter {
             QueryPeerIter::Closest(iter) => iter.is_waiting(peer),
+            QueryPeerIter::ClosestDisjoint(iter) => iter.is_waiting(peer),
             QueryPeerIter::Fixed(iter) => iter.is_waiting(peer)
         }
     }
@@ -263,6 +285,7 @@ impl<TInner> Query<TInner> {
     fn next(&mut self, now: Instant) -> PeersIterState {
         match &mut self.peer_iter {
             QueryPeerIter::Closest(iter) => iter.next(now),
+            QueryPeerIter::ClosestDisjoint(iter) => iter.next(now),
             QueryPeerIter::Fixed(iter) => iter.next()
         }
     }
@@ -274,6 +297,7 @@ impl<TInner> Query<TInner> {
     pub fn finish(&mut self) {
         match &mut self.peer_iter {
             QueryPeerIter::Closest(iter) => iter.finish(),
+            QueryPeerIter::ClosestDisjoint(iter) => iter.finish(),
             QueryPeerIter::Fixed(iter) => iter.finish()
         }
     }
@@ -281,7 +305,8 @@ impl<TInner> Query<TInner> {
     /// Consumes the query, producing the final `QueryResult`.
     pub fn into_result(self) -> QueryResult<TInner, impl Iterator<Item = PeerId>> {
         let peers = match self.peer_iter {
-            QueryPeerIter::Closest(iter) => Either::Left(iter.into_result()),
+            QueryPeerIter::Closest(iter) => Either::Left(Either::Left(iter.into_result())),
+            QueryPeerIter::ClosestDisjoint(iter) => Either::Left(Either::Right(iter.into_result())),
             QueryPeerIter::Fixed(iter) => Either::Right(iter.into_result())
         };
         QueryResult { inner: self.inner, peers }
@@ -0,0 +1,385 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use super::*;
+
+use fnv::FnvHashMap;
+use std::collections::HashSet;
+use std::num::NonZeroUsize;
+
+/// A peer iterator that runs multiple disjoint lookup paths towards
+/// the closest peers to a target, as described in S/Kademlia.
+///
+/// Every path is driven by its own [`ClosestPeersIter`], but each peer
+/// belongs to the first path that selects it: it is only contacted on behalf
+/// of that path, and only counts towards the results of that path. Other
+/// paths selecting the same peer skip it, as if it had failed. Thus, the paths
+/// do not share any peers, and peers returning bogus closer peers can at worst
+/// divert the paths they belong to, while the remaining paths continue to make
+/// progress towards the target.
+#[derive(Debug, Clone)]
+pub struct DisjointClosestPeersIter {
+    /// The configuration of every path.
+    config: ClosestPeersIterConfig,
+
+    /// The target of the lookup.
+    target: KeyBytes,
+
+    /// The paths of the lookup.
+    iters: Vec<ClosestPeersIter>,
+
+    /// The peers contacted so far, with the index of the path on whose
+    /// behalf each of them was contacted.
+    contacted_peers: FnvHashMap<PeerId, usize>,
+
+    /// The index of the path to advance first on the next call to `next`,
+    /// to contact peers on behalf of all paths in turn.
+    next_path: usize,
+}
+
+impl DisjointClosestPeersIter {
+    /// Creates a new iterator with `num_paths` paths, each with the given configuration.
+    ///
+    /// All paths start out with the same known closest peers, which are
+    /// claimed by the paths in turn as they are contacted.
+    pub fn with_config<I, T>(
+        config: ClosestPeersIterConfig,
+        num_paths: NonZeroUsize,
+        target: T,
+        known_closest_peers: I
+    ) -> Self
+    where
+        I: IntoIterator<Item = Key<PeerId>>,
+        T: Into<KeyBytes>
+    {
+        let target = target.into();
+        let known_closest_peers = known_closest_peers.into_iter()
+            .take(config.num_results)
+            .collect::<Vec<_>>();
+        let iters = (0 .. num_paths.get())
+            .map(|_| ClosestPeersIter::with_config(
+                config.clone(),
+                target.clone(),
+                known_closest_peers.iter().cloned()))
+            .collect();
+
+        DisjointClosestPeersIter {
+            config,
+            target,
+            iters,
+            contacted_peers: FnvHashMap::default(),
+            next_path: 0,
+        }
+    }
+
+    /// Callback for delivering the result of a successful request to a peer.
+    ///
+    /// The result is delivered to the path on whose behalf the peer was
+    /// contacted, which is the only path the peer belongs to.
+    ///
+    /// See [`ClosestPeersIter::on_success`].
+    pub fn on_success<I>(&mut self, peer: &PeerId, closer_peers: I)
+    where
+        I: IntoIterator<Item = PeerId>
+    {
+        if let Some(&i) = self.contacted_peers.get(peer) {
+            self.iters[i].on_success(peer, closer_peers);
+        }
+    }
+
+    /// Callback for informing the iterator about a failed request to a peer.
+    ///
+    /// See [`ClosestPeersIter::on_failure`].
+    pub fn on_failure(&mut self, peer: &PeerId) {
+        if let Some(&i) = self.contacted_peers.get(peer) {
+            self.iters[i].on_failure(peer);
+        }
+    }
+
+    /// Returns true if the iterator is waiting for a response from the given peer.
+    pub fn is_waiting(&self, peer: &PeerId) -> bool {
+        self.iters.iter().any(|iter| iter.is_waiting(peer))
+    }
+
+    /// Advances the state of the iterator, potentially getting a new peer to contact.
+    ///
+    /// The paths are advanced in turn, i.e. every call starts with the path
+    /// following the one on whose behalf the last peer was contacted.
+    pub fn next(&mut self, now: Instant) -> PeersIterState {
+        let mut state = PeersIterState::Finished;
+
+        let num_paths = self.iters.len();
+        let first = self.next_path;
+        for i in (0 .. num_paths).map(|i| (first + i) % num_paths) {
+            let iter = &mut self.iters[i];
+            loop {
+                match iter.next(now) {
+                    PeersIterState::Waiting(Some(peer)) => {
+                        let peer = peer.into_owned();
+                        if self.contacted_peers.contains_key(&peer) {
+                            // The peer belongs to another path, so this path
+                            // skips it and moves on to its next peer.
+                            iter.on_failure(&peer);
+                        } else {
+                            self.contacted_peers.insert(peer.clone(), i);
+                            self.next_path = (i + 1) % num_paths;
+                            return PeersIterState::Waiting(Some(Cow::Owned(peer)))
+                        }
+                    }
+                    PeersIterState::Waiting(None) => {
+                        state = PeersIterState::Waiting(None);
+                        break
+                    }
+                    PeersIterState::WaitingAtCapacity => {
+                        if let PeersIterState::Finished = state {
+                            state = PeersIterState::WaitingAtCapacity
+                        }
+                        break
+                    }
+                    PeersIterState::Finished => break
+                }
+            }
+        }
+
+        state
+    }
+
+    /// Immediately transitions the iterator to [`PeersIterState::Finished`].
+    pub fn finish(&mut self) {
+        for iter in self.iters.iter_mut() {
+            iter.finish()
+        }
+    }
+
+    /// Checks whether the iterator has finished, i.e. all of its paths finished.
+    pub fn finished(&self) -> bool {
+        self.iters.iter().all(ClosestPeersIter::finished)
+    }
+
+    /// Consumes the iterator, returning the closest peers.
+    ///
+    /// The results of the paths are merged by taking the closest remaining
+    /// peer of every path in turn, so that every path contributes to the
+    /// result, even if other paths found (allegedly) closer peers.
+    pub fn into_result(self) -> impl Iterator<Item = PeerId> {
+        let mut paths = self.iters.into_iter()
+            .map(|iter| iter.into_result())
+            .collect::<Vec<_>>();
+        let mut seen = HashSet::new();
+        let mut result = Vec::with_capacity(self.config.num_results);
+
+        while result.len() < self.config.num_results {
+            let mut exhausted = true;
+            for path in paths.iter_mut() {
+                if let Some(peer) = path.find(|p| !seen.contains(p)) {
+                    exhausted = false;
+                    seen.insert(peer.clone());
+                    result.push(peer);
+                    if result.len() == self.config.num_results {
+                        break
+                    }
+                }
+            }
+            if exhausted {
+                break
+            }
+        }
+
+        result.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use multihash::Multihash;
+    use std::collections::VecDeque;
+
+    /// A simulated network, in which every honest peer knows the
+    /// holder of a record, while adversarial peers only ever return
+    /// each other as being closest to the target.
+    struct Network {
+        target: Key<Multihash>,
+        holder: PeerId,
+        honest: Vec<PeerId>,
+        adversarial: Vec<PeerId>,
+    }
+
+    impl Network {
+        /// Creates a network in which the adversarial peers are closer
+        /// to the target than any honest peer.
+        fn new() -> Self {
+            let target = Key::from(Into::<Multihash>::into(PeerId::random()));
+            let mut peers = (0 .. 200).map(|_| Key::from(PeerId::random())).collect::<Vec<_>>();
+            peers.sort_by_key(|k| k.distance(&target));
+            let mut peers = peers.into_iter().map(Key::into_preimage);
+            let adversarial = peers.by_ref().take(2 * K_VALUE.get()).collect();
+            let holder = peers.next().unwrap();
+            let honest = peers.collect();
+            Network { target, holder, honest, adversarial }
+        }
+
+        /// The closer peers reported by `peer`.
+        fn closer_peers(&self, peer: &PeerId) -> Vec<PeerId> {
+            if self.adversarial.contains(peer) {
+                self.adversarial.clone()
+            } else {
+                vec![self.holder.clone()]
+            }
+        }
+    }
+
+    /// The operations of a peer iterator needed by `run`.
+    trait PeersIter {
+        fn next(&mut self, now: Instant) -> PeersIterState;
+        fn on_success(&mut self, peer: &PeerId, closer_peers: Vec<PeerId>);
+    }
+
+    impl PeersIter for ClosestPeersIter {
+        fn next(&mut self, now: Instant) -> PeersIterState {
+            ClosestPeersIter::next(self, now)
+        }
+
+        fn on_success(&mut self, peer: &PeerId, closer_peers: Vec<PeerId>) {
+            ClosestPeersIter::on_success(self, peer, closer_peers)
+        }
+    }
+
+    impl PeersIter for DisjointClosestPeersIter {
+        fn next(&mut self, now: Instant) -> PeersIterState {
+            DisjointClosestPeersIter::next(self, now)
+        }
+
+        fn on_success(&mut self, peer: &PeerId, closer_peers: Vec<PeerId>) {
+            DisjointClosestPeersIter::on_success(self, peer, closer_peers)
+        }
+    }
+
+    /// Drives a peer iterator to completion, answering requests in the order in
+    /// which they are issued with the given closer peers, and returning all
+    /// peers contacted by the iterator.
+    fn run<F>(iter: &mut impl PeersIter, closer_peers: F) -> Vec<PeerId>
+    where
+        F: Fn(&PeerId) -> Vec<PeerId>
+    {
+        let now = Instant::now();
+        let mut pending = VecDeque::new();
+        let mut contacted = Vec::new();
+        loop {
+            match iter.next(now) {
+                PeersIterState::Waiting(Some(peer)) => {
+                    let peer = peer.into_owned();
+                    assert!(!contacted.contains(&peer), "Peer contacted twice: {:?}", peer);
+                    contacted.push(peer.clone());
+                    pending.push_back(peer);
+                }
+                PeersIterState::Finished => return contacted,
+                PeersIterState::Waiting(None) | PeersIterState::WaitingAtCapacity => {
+                    let peer = pending.pop_front().expect("Waiting for a peer.");
+                    iter.on_success(&peer, closer_peers(&peer));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn single_path_is_diverted_by_adversarial_peers() {
+        let net = Network::new();
+        let known = vec![net.adversarial[0].clone(), net.honest[0].clone()];
+        let mut iter = ClosestPeersIter::with_config(
+            ClosestPeersIterConfig::default(),
+            net.target.clone(),
+            known.into_iter().map(Key::from));
+
+        let contacted = run(&mut iter, |p| net.closer_peers(p));
+        assert!(!contacted.contains(&net.holder));
+    }
+
+    #[test]
+    fn disjoint_paths_succeed_with_one_adversarial_path() {
+        let net = Network::new();
+        let known = vec![net.adversarial[0].clone(), net.honest[0].clone()];
+        let mut iter = DisjointClosestPeersIter::with_config(
+            ClosestPeersIterConfig::default(),
+            NonZeroUsize::new(2).unwrap(),
+            net.target.clone(),
+            known.into_iter().map(Key::from));
+
+        let contacted = run(&mut iter, |p| net.closer_peers(p));
+        assert!(contacted.contains(&net.holder));
+        assert!(iter.finished());
+        assert!(iter.into_result().any(|p| p == net.holder));
+    }
+
+    #[test]
+    fn paths_are_disjoint() {
+        let net = Network::new();
+        let known = net.adversarial.iter().take(3)
+            .chain(net.honest.iter().take(3))
+            .cloned()
+            .map(Key::from)
+            .collect::<Vec<_>>();
+        let mut iter = DisjointClosestPeersIter::with_config(
+            ClosestPeersIterConfig::default(),
+            NonZeroUsize::new(3).unwrap(),
+            net.target.clone(),
+            known);
+
+        // No peer is contacted more than once, see `run`.
+        let contacted = run(&mut iter, |p| net.closer_peers(p));
+        assert_eq!(contacted.len(), iter.contacted_peers.len());
+
+        // Every path contacted peers on its own behalf.
+        let paths = iter.contacted_peers.values().collect::<HashSet<_>>();
+        assert_eq!(paths.len(), 3);
+    }
+
+    #[test]
+    fn adversarial_path_does_not_claim_peers_of_other_paths() {
+        // The adversarial peers also report the honest peers and the holder,
+        // so that their path selects the peers contacted by the honest path.
+        let net = Network::new();
+        let closer_peers = |peer: &PeerId| {
+            let mut peers = net.closer_peers(peer);
+            if net.adversarial.contains(peer) {
+                peers.extend(net.honest.iter().take(K_VALUE.get()).cloned());
+                peers.push(net.holder.clone());
+            }
+            peers
+        };
+        let known = vec![net.adversarial[0].clone(), net.honest[0].clone()];
+        let mut iter = DisjointClosestPeersIter::with_config(
+            ClosestPeersIterConfig::default(),
+            NonZeroUsize::new(2).unwrap(),
+            net.target.clone(),
+            known.into_iter().map(Key::from));
+
+        run(&mut iter, closer_peers);
+
+        // The results of every path only consist of the peers contacted on
+        // its own behalf.
+        for (i, path) in iter.iters.iter().enumerate() {
+            for peer in path.clone().into_result() {
+                assert_eq!(iter.contacted_peers.get(&peer), Some(&i));
+            }
+        }
+        assert!(iter.into_result().any(|p| p == net.holder));
+    }
+}
@@ -24,6 +24,7 @@ pub mod store;
 
 use libp2p_core::PeerId;
 use multihash::Multihash;
+use std::fmt;
 use std::hash::{Hash, Hasher};
 use wasm_timer::Instant;
 
@@ -90,6 +91,28 @@ impl ProviderRecord {
     }
 }
 
+/// Validation of (value-)records, configured via
+/// `KademliaConfig::set_record_validator`.
+///
+/// The validator is consulted for records stored via `Kademlia::put_record`,
+/// records received from remote peers for storage and records returned by
+/// remote peers as the result of `Kademlia::get_record`. Records that are
+/// rejected are neither stored nor returned.
+pub trait RecordValidator: fmt::Debug + Send + Sync {
+    /// Checks whether the given record is valid.
+    fn validate(&self, record: &Record) -> bool;
+
+    /// Selects the best among several valid records for the same key,
+    /// returning its index in `records`, which is never empty.
+    ///
+    /// The selected record is returned first as the result of a
+    /// `Kademlia::get_record` query. The default implementation selects
+    /// the first record.
+    fn select(&self, _key: &Multihash, _records: &[Record]) -> usize {
+        0
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
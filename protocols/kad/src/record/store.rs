@@ -19,8 +19,12 @@
 // DEALINGS IN THE SOFTWARE.
 
 mod memory;
+#[cfg(feature = "disk-store")]
+mod disk;
 
-pub use memory::MemoryStore;
+pub use memory::{MemoryStore, MemoryStoreConfig};
+#[cfg(feature = "disk-store")]
+pub use disk::{DiskStore, DiskStoreConfig};
 
 use crate::K_VALUE;
 use super::*;
@@ -39,6 +43,8 @@ pub enum Error {
     MaxProvidedKeys,
     /// The value of a record to be stored is too large.
     ValueTooLarge,
+    /// An I/O error occurred while persisting a record.
+    Io(std::io::Error),
 }
 
 /// Trait for types implementing a record store.
@@ -0,0 +1,775 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use super::*;
+
+use libp2p_core::{PeerId, clock};
+use log::{debug, warn};
+use multihash::Multihash;
+use sha2::{Digest, Sha256};
+use std::borrow::Cow;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// File name extension of log segments.
+const SEGMENT_EXT: &str = "log";
+/// File name extension of snapshots.
+const SNAPSHOT_EXT: &str = "snapshot";
+/// File name extension of snapshots that are still being written.
+const TMP_EXT: &str = "tmp";
+
+/// Entry tags of the log format.
+const TAG_PUT_RECORD: u8 = 0;
+const TAG_REMOVE_RECORD: u8 = 1;
+const TAG_ADD_PROVIDER: u8 = 2;
+const TAG_REMOVE_PROVIDER: u8 = 3;
+
+/// Length of the checksum that follows every entry.
+const CHECKSUM_LEN: usize = 4;
+
+/// File-backed implementation of a `RecordStore`.
+///
+/// All records are kept in an in-memory index for lookups, while every
+/// modification is appended to a log in the directory of the store before
+/// it is applied to the index. Opening a store recovers its contents by
+/// replaying the latest snapshot, followed by all log segments written since.
+/// An entry that has only partially been written, e.g. because the process
+/// crashed, is detected by its checksum and ignored.
+///
+/// The expiration times of records and provider records are stored as
+/// wall-clock deadlines, so that records keep their remaining lifetime
+/// across restarts and are dropped if they expired in the meantime.
+///
+/// At every `DiskStoreConfig::compaction_interval`, the store starts a new
+/// log segment and hands the current, unexpired contents of the index to a
+/// background thread, which writes them as a new snapshot and afterwards
+/// deletes the log segments and snapshots made obsolete by it.
+pub struct DiskStore {
+    /// The directory containing the log segments and snapshots.
+    dir: PathBuf,
+    /// The in-memory index of the stored records.
+    index: MemoryStore,
+    /// The maximum size of record values, in bytes.
+    max_value_bytes: usize,
+    /// The interval at which the log is compacted.
+    compaction_interval: Duration,
+    /// Whether writes to the log are flushed to stable storage.
+    sync_writes: bool,
+    /// The sequence number of the active log segment.
+    segment: u64,
+    /// The active log segment to which entries are appended.
+    log: File,
+    /// The time at which the next compaction is started.
+    next_compaction: Instant,
+    /// The result of the compaction running in the background, if any.
+    compaction: Option<mpsc::Receiver<io::Result<()>>>,
+}
+
+/// Configuration for a `DiskStore`.
+pub struct DiskStoreConfig {
+    /// The limits w.r.t. the number and size of stored records.
+    pub limits: MemoryStoreConfig,
+    /// The interval at which the log is compacted.
+    pub compaction_interval: Duration,
+    /// Whether every write to the log is flushed to stable storage before
+    /// the operation returns.
+    ///
+    /// Without syncing, the log survives a crash of the process but not
+    /// necessarily a crash of the operating system.
+    pub sync_writes: bool,
+}
+
+impl Default for DiskStoreConfig {
+    fn default() -> Self {
+        Self {
+            limits: MemoryStoreConfig::default(),
+            compaction_interval: Duration::from_secs(10 * 60),
+            sync_writes: false,
+        }
+    }
+}
+
+impl DiskStore {
+    /// Opens the `DiskStore` in the given directory with a default configuration,
+    /// creating the directory if it does not exist.
+    pub fn open<P: AsRef<Path>>(local_id: PeerId, dir: P) -> io::Result<Self> {
+        Self::open_with_config(local_id, dir, Default::default())
+    }
+
+    /// Opens the `DiskStore` in the given directory with the given configuration,
+    /// creating the directory if it does not exist.
+    pub fn open_with_config<P: AsRef<Path>>(local_id: PeerId, dir: P, config: DiskStoreConfig)
+        -> io::Result<Self>
+    {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+
+        let max_value_bytes = config.limits.max_value_bytes;
+        let mut index = MemoryStore::with_config(local_id, config.limits);
+
+        let (snapshot, segments) = scan(&dir)?;
+        if let Some(n) = snapshot {
+            for entry in read_entries(&file_path(&dir, n, SNAPSHOT_EXT))? {
+                apply(&mut index, entry);
+            }
+        }
+        for &n in segments.iter().filter(|n| snapshot.map_or(true, |s| **n > s)) {
+            for entry in read_entries(&file_path(&dir, n, SEGMENT_EXT))? {
+                apply(&mut index, entry);
+            }
+        }
+
+        // Entries are never appended to an existing segment, since its
+        // last entry may be incomplete.
+        let segment = segments.last().cloned().into_iter().chain(snapshot).max().map_or(0, |n| n + 1);
+        let log = open_segment(&dir, segment)?;
+
+        Ok(DiskStore {
+            dir,
+            index,
+            max_value_bytes,
+            compaction_interval: config.compaction_interval,
+            sync_writes: config.sync_writes,
+            segment,
+            log,
+            next_compaction: clock::now() + config.compaction_interval,
+            compaction: None,
+        })
+    }
+
+    /// Compacts the log, waiting for the compaction to finish.
+    ///
+    /// Compaction happens periodically in the background, so calling
+    /// this method is usually not necessary.
+    pub fn compact(&mut self) -> io::Result<()> {
+        if let Some(rx) = self.compaction.take() {
+            let _ = rx.recv();
+        }
+        self.start_compaction()?;
+        match self.compaction.take() {
+            Some(rx) => rx.recv().unwrap_or(Ok(())),
+            None => Ok(())
+        }
+    }
+
+    /// Appends an entry to the active log segment.
+    fn append(&mut self, entry: &Entry) -> io::Result<()> {
+        self.maybe_compact();
+        let mut buf = Vec::new();
+        encode(entry, clock::now(), SystemTime::now(), &mut buf);
+        self.log.write_all(&buf)?;
+        if self.sync_writes {
+            self.log.sync_data()?;
+        }
+        Ok(())
+    }
+
+    /// Collects the result of a finished compaction and starts a new
+    /// one if the compaction interval elapsed.
+    fn maybe_compact(&mut self) {
+        if let Some(rx) = self.compaction.take() {
+            match rx.try_recv() {
+                Ok(Ok(())) => debug!("Compacted record store in {:?}", self.dir),
+                Ok(Err(e)) => warn!("Failed to compact record store in {:?}: {:?}", self.dir, e),
+                Err(mpsc::TryRecvError::Empty) => self.compaction = Some(rx),
+                Err(mpsc::TryRecvError::Disconnected) => {}
+            }
+        }
+
+        if self.compaction.is_none() && clock::now() >= self.next_compaction {
+            if let Err(e) = self.start_compaction() {
+                warn!("Failed to compact record store in {:?}: {:?}", self.dir, e);
+            }
+        }
+    }
+
+    /// Starts a new log segment and writes a snapshot of the
+    /// unexpired records in a background thread.
+    fn start_compaction(&mut self) -> io::Result<()> {
+        let now = clock::now();
+        self.next_compaction = now + self.compaction_interval;
+
+        let log = open_segment(&self.dir, self.segment + 1)?;
+        let snapshot = self.segment;
+        self.segment += 1;
+        self.log = log;
+
+        self.index.retain(|_, r| !r.is_expired(now));
+        self.index.retain_providers(|p| !p.is_expired(now));
+
+        let now_wall = SystemTime::now();
+        let mut buf = Vec::new();
+        for r in self.index.records() {
+            encode(&Entry::PutRecord(r.into_owned()), now, now_wall, &mut buf);
+        }
+        for p in self.index.all_providers() {
+            encode(&Entry::AddProvider(p.clone()), now, now_wall, &mut buf);
+        }
+
+        let (tx, rx) = mpsc::channel();
+        let dir = self.dir.clone();
+        thread::Builder::new()
+            .name("libp2p-kad-disk-store".into())
+            .spawn(move || {
+                let _ = tx.send(write_snapshot(&dir, snapshot, &buf));
+            })?;
+        self.compaction = Some(rx);
+
+        Ok(())
+    }
+}
+
+impl Drop for DiskStore {
+    fn drop(&mut self) {
+        // Wait for a running compaction, so that the store can safely
+        // be reopened right away.
+        if let Some(rx) = self.compaction.take() {
+            let _ = rx.recv();
+        }
+    }
+}
+
+impl<'a> RecordStore<'a> for DiskStore {
+    type RecordsIter = <MemoryStore as RecordStore<'a>>::RecordsIter;
+    type ProvidedIter = <MemoryStore as RecordStore<'a>>::ProvidedIter;
+
+    fn get(&'a self, k: &Multihash) -> Option<Cow<Record>> {
+        self.index.get(k)
+    }
+
+    fn put(&'a mut self, r: Record) -> Result<()> {
+        if r.value.len() >= self.max_value_bytes {
+            return Err(Error::ValueTooLarge)
+        }
+        let entry = Entry::PutRecord(r);
+        self.append(&entry).map_err(Error::Io)?;
+        match entry {
+            Entry::PutRecord(r) => self.index.put(r),
+            _ => unreachable!()
+        }
+    }
+
+    fn remove(&'a mut self, k: &Multihash) {
+        if let Err(e) = self.append(&Entry::RemoveRecord(k.clone())) {
+            warn!("Failed to persist removal of record {:?}: {:?}", k, e);
+        }
+        self.index.remove(k)
+    }
+
+    fn records(&'a self) -> Self::RecordsIter {
+        self.index.records()
+    }
+
+    fn add_provider(&'a mut self, record: ProviderRecord) -> Result<()> {
+        let entry = Entry::AddProvider(record);
+        self.append(&entry).map_err(Error::Io)?;
+        match entry {
+            Entry::AddProvider(record) => self.index.add_provider(record),
+            _ => unreachable!()
+        }
+    }
+
+    fn providers(&'a self, key: &Multihash) -> Vec<ProviderRecord> {
+        self.index.providers(key)
+    }
+
+    fn provided(&'a self) -> Self::ProvidedIter {
+        self.index.provided()
+    }
+
+    fn remove_provider(&'a mut self, k: &Multihash, p: &PeerId) {
+        if let Err(e) = self.append(&Entry::RemoveProvider(k.clone(), p.clone())) {
+            warn!("Failed to persist removal of provider record {:?}: {:?}", k, e);
+        }
+        self.index.remove_provider(k, p)
+    }
+}
+
+/// An entry of the log.
+#[derive(Debug)]
+enum Entry {
+    PutRecord(Record),
+    RemoveRecord(Multihash),
+    AddProvider(ProviderRecord),
+    RemoveProvider(Multihash, PeerId),
+}
+
+/// Applies an entry read from disk to the index.
+fn apply(index: &mut MemoryStore, entry: Entry) {
+    let result = match entry {
+        Entry::PutRecord(r) => index.put(r),
+        Entry::AddProvider(p) => index.add_provider(p),
+        Entry::RemoveRecord(k) => {
+            index.remove(&k);
+            Ok(())
+        }
+        Entry::RemoveProvider(k, p) => {
+            index.remove_provider(&k, &p);
+            Ok(())
+        }
+    };
+    if let Err(e) = result {
+        debug!("Skipping record that no longer fits into the store: {:?}", e);
+    }
+}
+
+/// Encodes an entry, appending it to `buf`.
+///
+/// An entry consists of the length of its body as a 32-bit big-endian integer,
+/// the body itself, and the first bytes of the SHA-256 digest of the body.
+fn encode(entry: &Entry, now: Instant, now_wall: SystemTime, buf: &mut Vec<u8>) {
+    let mut body = Vec::new();
+    match entry {
+        Entry::PutRecord(r) => {
+            body.push(TAG_PUT_RECORD);
+            put_bytes(&mut body, r.key.as_bytes());
+            put_bytes(&mut body, &r.value);
+            match &r.publisher {
+                Some(p) => { body.push(1); put_bytes(&mut body, p.as_bytes()) }
+                None => body.push(0)
+            }
+            put_deadline(&mut body, r.expires, now, now_wall);
+        }
+        Entry::RemoveRecord(k) => {
+            body.push(TAG_REMOVE_RECORD);
+            put_bytes(&mut body, k.as_bytes());
+        }
+        Entry::AddProvider(p) => {
+            body.push(TAG_ADD_PROVIDER);
+            put_bytes(&mut body, p.key.as_bytes());
+            put_bytes(&mut body, p.provider.as_bytes());
+            put_deadline(&mut body, p.expires, now, now_wall);
+        }
+        Entry::RemoveProvider(k, p) => {
+            body.push(TAG_REMOVE_PROVIDER);
+            put_bytes(&mut body, k.as_bytes());
+            put_bytes(&mut body, p.as_bytes());
+        }
+    }
+    buf.extend_from_slice(&(body.len() as u32).to_be_bytes());
+    buf.extend_from_slice(&body);
+    buf.extend_from_slice(&Sha256::digest(&body)[.. CHECKSUM_LEN]);
+}
+
+fn put_bytes(buf: &mut Vec<u8>, bytes: &[u8]) {
+    buf.extend_from_slice(&(bytes.len() as u32).to_be_bytes());
+    buf.extend_from_slice(bytes);
+}
+
+/// Encodes an expiration time as milliseconds since the Unix epoch.
+fn put_deadline(buf: &mut Vec<u8>, expires: Option<Instant>, now: Instant, now_wall: SystemTime) {
+    match expires {
+        Some(t) => {
+            let deadline = if t > now {
+                now_wall + (t - now)
+            } else {
+                now_wall - (now - t)
+            };
+            let millis = deadline.duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs() * 1000 + u64::from(d.subsec_millis()))
+                .unwrap_or(0);
+            buf.push(1);
+            buf.extend_from_slice(&millis.to_be_bytes());
+        }
+        None => buf.push(0)
+    }
+}
+
+/// Decodes the body of an entry.
+///
+/// Records and provider records whose deadline has passed
+/// are decoded as removals.
+fn decode(body: &[u8], now: Instant, now_wall: SystemTime) -> Option<Entry> {
+    let mut r = Reader(body);
+    let entry = match r.u8()? {
+        TAG_PUT_RECORD => {
+            let key = r.multihash()?;
+            let value = r.bytes()?.to_vec();
+            let publisher = match r.u8()? {
+                0 => None,
+                1 => Some(r.peer_id()?),
+                _ => return None
+            };
+            match r.deadline(now, now_wall)? {
+                Deadline::Expired => Entry::RemoveRecord(key),
+                Deadline::At(expires) => Entry::PutRecord(Record { key, value, publisher, expires })
+            }
+        }
+        TAG_REMOVE_RECORD => Entry::RemoveRecord(r.multihash()?),
+        TAG_ADD_PROVIDER => {
+            let key = r.multihash()?;
+            let provider = r.peer_id()?;
+            match r.deadline(now, now_wall)? {
+                Deadline::Expired => Entry::RemoveProvider(key, provider),
+                Deadline::At(expires) => Entry::AddProvider(ProviderRecord { key, provider, expires })
+            }
+        }
+        TAG_REMOVE_PROVIDER => Entry::RemoveProvider(r.multihash()?, r.peer_id()?),
+        _ => return None
+    };
+    if r.0.is_empty() { Some(entry) } else { None }
+}
+
+/// A decoded expiration time.
+enum Deadline {
+    Expired,
+    At(Option<Instant>),
+}
+
+/// Reader over the fields of an encoded entry.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        if self.0.len() < n {
+            return None
+        }
+        let (bytes, rest) = self.0.split_at(n);
+        self.0 = rest;
+        Some(bytes)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.take(1).map(|b| b[0])
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        let mut n = [0; 4];
+        n.copy_from_slice(self.take(4)?);
+        Some(u32::from_be_bytes(n))
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        let mut n = [0; 8];
+        n.copy_from_slice(self.take(8)?);
+        Some(u64::from_be_bytes(n))
+    }
+
+    fn bytes(&mut self) -> Option<&'a [u8]> {
+        let len = self.u32()?;
+        self.take(len as usize)
+    }
+
+    fn multihash(&mut self) -> Option<Multihash> {
+        Multihash::from_bytes(self.bytes()?.to_vec()).ok()
+    }
+
+    fn peer_id(&mut self) -> Option<PeerId> {
+        PeerId::from_bytes(self.bytes()?.to_vec()).ok()
+    }
+
+    fn deadline(&mut self, now: Instant, now_wall: SystemTime) -> Option<Deadline> {
+        match self.u8()? {
+            0 => Some(Deadline::At(None)),
+            1 => {
+                let deadline = UNIX_EPOCH + Duration::from_millis(self.u64()?);
+                match deadline.duration_since(now_wall) {
+                    Ok(d) if d > Duration::from_secs(0) => Some(Deadline::At(Some(now + d))),
+                    _ => Some(Deadline::Expired)
+                }
+            }
+            _ => None
+        }
+    }
+}
+
+/// Reads all intact entries of a log segment or snapshot.
+///
+/// Reading stops at the first incomplete or corrupted entry,
+/// which is what a crash while appending to the log leaves behind.
+fn read_entries(path: &Path) -> io::Result<Vec<Entry>> {
+    let mut data = Vec::new();
+    File::open(path)?.read_to_end(&mut data)?;
+
+    let now = clock::now();
+    let now_wall = SystemTime::now();
+    let mut entries = Vec::new();
+    let mut r = Reader(&data);
+    while !r.0.is_empty() {
+        let offset = data.len() - r.0.len();
+        let entry = r.bytes().and_then(|body| {
+            let checksum = r.take(CHECKSUM_LEN)?;
+            if &Sha256::digest(body)[.. CHECKSUM_LEN] != checksum {
+                return None
+            }
+            decode(body, now, now_wall)
+        });
+        match entry {
+            Some(e) => entries.push(e),
+            None => {
+                warn!("Ignoring incomplete or corrupted entry at offset {} of {:?}", offset, path);
+                break
+            }
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Writes a snapshot with the given sequence number and removes the log
+/// segments and snapshots that are superseded by it.
+fn write_snapshot(dir: &Path, n: u64, data: &[u8]) -> io::Result<()> {
+    let tmp = file_path(dir, n, TMP_EXT);
+    let mut file = File::create(&tmp)?;
+    file.write_all(data)?;
+    file.sync_all()?;
+    fs::rename(&tmp, file_path(dir, n, SNAPSHOT_EXT))?;
+
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let obsolete = match parse_file_name(&path) {
+            Some((m, SEGMENT_EXT)) => m <= n,
+            Some((m, SNAPSHOT_EXT)) => m < n,
+            _ => false
+        };
+        if obsolete {
+            fs::remove_file(&path)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Determines the latest snapshot and the sorted sequence numbers of the log
+/// segments in a directory, removing the remains of interrupted compactions.
+fn scan(dir: &Path) -> io::Result<(Option<u64>, Vec<u64>)> {
+    let mut snapshot = None;
+    let mut segments = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        match parse_file_name(&path) {
+            Some((n, SEGMENT_EXT)) => segments.push(n),
+            Some((n, SNAPSHOT_EXT)) => snapshot = snapshot.max(Some(n)),
+            Some((_, TMP_EXT)) => fs::remove_file(&path)?,
+            _ => {}
+        }
+    }
+    segments.sort();
+    Ok((snapshot, segments))
+}
+
+fn file_path(dir: &Path, n: u64, ext: &str) -> PathBuf {
+    dir.join(format!("{:020}.{}", n, ext))
+}
+
+fn parse_file_name(path: &Path) -> Option<(u64, &str)> {
+    let n = path.file_stem()?.to_str()?.parse().ok()?;
+    let ext = path.extension()?.to_str()?;
+    Some((n, ext))
+}
+
+fn open_segment(dir: &Path, n: u64) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(file_path(dir, n, SEGMENT_EXT))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use multihash::Hash::SHA2256;
+    use quickcheck::*;
+    use std::mem;
+
+    /// A temporary directory that is removed when dropped.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new() -> Self {
+            let dir = std::env::temp_dir()
+                .join(format!("libp2p-kad-disk-store-{}", rand::random::<u64>()));
+            TempDir(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn assert_same_record(r1: &Record, r2: &Record) {
+        assert_eq!(r1.key, r2.key);
+        assert_eq!(r1.value, r2.value);
+        assert_eq!(r1.publisher, r2.publisher);
+        assert_same_expiration(r1.expires, r2.expires);
+    }
+
+    /// Expiration times survive a reopen with millisecond precision.
+    fn assert_same_expiration(t1: Option<Instant>, t2: Option<Instant>) {
+        match (t1, t2) {
+            (None, None) => {}
+            (Some(t1), Some(t2)) => {
+                let d = if t1 > t2 { t1 - t2 } else { t2 - t1 };
+                assert!(d < Duration::from_secs(1), "{:?}", d)
+            }
+            _ => panic!("Expiration mismatch: {:?} != {:?}", t1, t2)
+        }
+    }
+
+    /// Simulates a crash while an entry is appended to the log.
+    fn crash(store: DiskStore, partial: &Entry) {
+        let path = file_path(&store.dir, store.segment, SEGMENT_EXT);
+        mem::forget(store);
+        let mut buf = Vec::new();
+        encode(partial, Instant::now(), SystemTime::now(), &mut buf);
+        buf.truncate(buf.len() - 1);
+        OpenOptions::new().append(true).open(path).unwrap().write_all(&buf).unwrap();
+    }
+
+    #[test]
+    fn reopen_restores_records() {
+        fn prop(records: Vec<Record>) {
+            let dir = TempDir::new();
+            let id = PeerId::random();
+            let mut store = DiskStore::open(id.clone(), &dir.0).unwrap();
+            for r in &records {
+                assert!(store.put(r.clone()).is_ok());
+            }
+            drop(store);
+
+            let store = DiskStore::open(id, &dir.0).unwrap();
+            let now = Instant::now();
+            for r in &records {
+                match store.get(&r.key) {
+                    Some(stored) => assert_same_record(&stored, r),
+                    None => assert!(r.is_expired(now)),
+                }
+            }
+        }
+        quickcheck(prop as fn(_))
+    }
+
+    #[test]
+    fn reopen_restores_providers() {
+        let dir = TempDir::new();
+        let id = PeerId::random();
+        let key = Multihash::random(SHA2256);
+        let mut local = ProviderRecord::new(key.clone(), id.clone());
+        local.expires = Some(Instant::now() + Duration::from_secs(60));
+        let remote = ProviderRecord::new(key.clone(), PeerId::random());
+
+        let mut store = DiskStore::open(id.clone(), &dir.0).unwrap();
+        assert!(store.add_provider(local.clone()).is_ok());
+        assert!(store.add_provider(remote.clone()).is_ok());
+        drop(store);
+
+        let store = DiskStore::open(id, &dir.0).unwrap();
+        let providers = store.providers(&key);
+        assert_eq!(providers.len(), 2);
+        let stored = providers.iter().find(|p| p.provider == local.provider).unwrap();
+        assert_same_expiration(stored.expires, local.expires);
+        assert!(providers.contains(&remote));
+        assert_eq!(store.provided().count(), 1);
+    }
+
+    #[test]
+    fn reopen_applies_removals() {
+        let dir = TempDir::new();
+        let id = PeerId::random();
+        let r = Record::new(Multihash::random(SHA2256), vec![1, 2, 3]);
+        let p = ProviderRecord::new(Multihash::random(SHA2256), PeerId::random());
+
+        let mut store = DiskStore::open(id.clone(), &dir.0).unwrap();
+        assert!(store.put(r.clone()).is_ok());
+        assert!(store.add_provider(p.clone()).is_ok());
+        store.remove(&r.key);
+        store.remove_provider(&p.key, &p.provider);
+        drop(store);
+
+        let store = DiskStore::open(id, &dir.0).unwrap();
+        assert!(store.get(&r.key).is_none());
+        assert!(store.providers(&p.key).is_empty());
+    }
+
+    #[test]
+    fn reopen_after_crash() {
+        let dir = TempDir::new();
+        let id = PeerId::random();
+        let r1 = Record::new(Multihash::random(SHA2256), vec![1]);
+        let r2 = Record::new(Multihash::random(SHA2256), vec![2]);
+        let r3 = Record::new(Multihash::random(SHA2256), vec![3]);
+
+        let mut store = DiskStore::open(id.clone(), &dir.0).unwrap();
+        assert!(store.put(r1.clone()).is_ok());
+        crash(store, &Entry::PutRecord(r2.clone()));
+
+        // The incomplete entry is ignored and the store remains usable.
+        let mut store = DiskStore::open(id.clone(), &dir.0).unwrap();
+        assert_eq!(Some(Cow::Borrowed(&r1)), store.get(&r1.key));
+        assert!(store.get(&r2.key).is_none());
+        assert!(store.put(r3.clone()).is_ok());
+        crash(store, &Entry::RemoveRecord(r1.key.clone()));
+
+        let store = DiskStore::open(id, &dir.0).unwrap();
+        assert_eq!(Some(Cow::Borrowed(&r1)), store.get(&r1.key));
+        assert_eq!(Some(Cow::Borrowed(&r3)), store.get(&r3.key));
+    }
+
+    #[test]
+    fn reopen_after_crash_during_compaction() {
+        let dir = TempDir::new();
+        let id = PeerId::random();
+        let r = Record::new(Multihash::random(SHA2256), vec![1]);
+
+        let mut store = DiskStore::open(id.clone(), &dir.0).unwrap();
+        assert!(store.put(r.clone()).is_ok());
+        let snapshot = file_path(&dir.0, store.segment, TMP_EXT);
+        drop(store);
+        fs::write(&snapshot, b"incomplete").unwrap();
+
+        let store = DiskStore::open(id, &dir.0).unwrap();
+        assert_eq!(Some(Cow::Borrowed(&r)), store.get(&r.key));
+        assert!(!snapshot.exists());
+    }
+
+    #[test]
+    fn compaction_drops_expired_records() {
+        let dir = TempDir::new();
+        let id = PeerId::random();
+        let mut live = Record::new(Multihash::random(SHA2256), vec![1]);
+        live.expires = Some(Instant::now() + Duration::from_secs(60));
+        let mut expired = Record::new(Multihash::random(SHA2256), vec![2]);
+        expired.expires = Some(Instant::now());
+        let mut provider = ProviderRecord::new(Multihash::random(SHA2256), id.clone());
+        provider.expires = Some(Instant::now());
+
+        let mut store = DiskStore::open(id.clone(), &dir.0).unwrap();
+        assert!(store.put(live.clone()).is_ok());
+        assert!(store.put(expired.clone()).is_ok());
+        assert!(store.add_provider(provider.clone()).is_ok());
+        store.compact().unwrap();
+        assert!(store.get(&expired.key).is_none());
+        assert_eq!(store.provided().count(), 0);
+        drop(store);
+
+        // Only the snapshot and the active log segment remain.
+        let (snapshot, segments) = scan(&dir.0).unwrap();
+        assert!(snapshot.is_some());
+        assert_eq!(segments.len(), 1);
+
+        let store = DiskStore::open(id, &dir.0).unwrap();
+        assert_same_record(&store.get(&live.key).unwrap(), &live);
+        assert!(store.get(&expired.key).is_none());
+        assert!(store.providers(&provider.key).is_empty());
+    }
+}
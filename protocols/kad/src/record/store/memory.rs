@@ -94,6 +94,30 @@ impl MemoryStore {
     {
         self.records.retain(f);
     }
+
+    /// Retains the provider records satisfying a predicate.
+    pub fn retain_providers<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&ProviderRecord) -> bool
+    {
+        let provided = &mut self.provided;
+        self.providers.retain(|_, providers| {
+            providers.retain(|p| {
+                if f(p) {
+                    true
+                } else {
+                    provided.remove(p);
+                    false
+                }
+            });
+            !providers.is_empty()
+        });
+    }
+
+    /// Gets an iterator over all stored provider records.
+    pub(super) fn all_providers(&self) -> impl Iterator<Item = &ProviderRecord> {
+        self.providers.values().flat_map(|ps| ps.iter())
+    }
 }
 
 impl<'a> RecordStore<'a> for MemoryStore {
@@ -18,7 +18,9 @@
 // FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // DEALINGS IN THE SOFTWARE.
 
-use futures::{future::{self, Either}, prelude::*};
+use futures::prelude::*;
+use futures03::future::{self, Either};
+use libp2p_core::compat::{Compat03As01, Transport01};
 use libp2p_core::identity;
 use libp2p_core::upgrade::{Negotiated, apply_inbound, apply_outbound};
 use libp2p_core::transport::{Transport, ListenerEvent};
@@ -48,7 +50,7 @@ fn xx() {
             .with_upgrade(NoiseConfig::xx(client_dh))
             .and_then(move |out, _| expect_identity(out, &server_id_public));
 
-        run(server_transport, client_transport, message);
+        run(Compat03As01::new(server_transport), Compat03As01::new(client_transport), message);
         true
     }
     QuickCheck::new().max_tests(30).quickcheck(prop as fn(Vec<u8>) -> bool)
@@ -74,7 +76,7 @@ fn ix() {
             .with_upgrade(NoiseConfig::ix(client_dh))
             .and_then(move |out, _| expect_identity(out, &server_id_public));
 
-        run(server_transport, client_transport, message);
+        run(Compat03As01::new(server_transport), Compat03As01::new(client_transport), message);
         true
     }
     QuickCheck::new().max_tests(30).quickcheck(prop as fn(Vec<u8>) -> bool)
@@ -95,9 +97,9 @@ fn ik_xx() {
         let server_transport = TcpConfig::new()
             .and_then(move |output, endpoint| {
                 if endpoint.is_listener() {
-                    Either::A(apply_inbound(output, NoiseConfig::ik_listener(server_dh)))
+                    Either::Left(apply_inbound(output, NoiseConfig::ik_listener(server_dh)))
                 } else {
-                    Either::B(apply_outbound(output, NoiseConfig::xx(server_dh)))
+                    Either::Right(apply_outbound(output, NoiseConfig::xx(server_dh)))
                 }
             })
             .and_then(move |out, _| expect_identity(out, &client_id_public));
@@ -107,15 +109,15 @@ fn ik_xx() {
         let client_transport = TcpConfig::new()
             .and_then(move |output, endpoint| {
                 if endpoint.is_dialer() {
-                    Either::A(apply_outbound(output,
+                    Either::Left(apply_outbound(output,
                         NoiseConfig::ik_dialer(client_dh, server_id_public, server_dh_public)))
                 } else {
-                    Either::B(apply_inbound(output, NoiseConfig::xx(client_dh)))
+                    Either::Right(apply_inbound(output, NoiseConfig::xx(client_dh)))
                 }
             })
             .and_then(move |out, _| expect_identity(out, &server_id_public2));
 
-        run(server_transport, client_transport, message);
+        run(Compat03As01::new(server_transport), Compat03As01::new(client_transport), message);
         true
     }
     QuickCheck::new().max_tests(30).quickcheck(prop as fn(Vec<u8>) -> bool)
@@ -125,19 +127,18 @@ type Output = (RemoteIdentity<X25519>, NoiseOutput<Negotiated<TcpTransStream>>);
 
 fn run<T, U>(server_transport: T, client_transport: U, message1: Vec<u8>)
 where
-    T: Transport<Output = Output>,
+    T: Transport01<Output = Output>,
     T::Dial: Send + 'static,
     T::Listener: Send + 'static,
     T::ListenerUpgrade: Send + 'static,
-    U: Transport<Output = Output>,
+    U: Transport01<Output = Output>,
     U::Dial: Send + 'static,
     U::Listener: Send + 'static,
     U::ListenerUpgrade: Send + 'static,
 {
     let message2 = message1.clone();
 
-    let mut server = server_transport
-        .listen_on("/ip4/127.0.0.1/tcp/0".parse().unwrap())
+    let mut server = Transport01::listen_on(server_transport, "/ip4/127.0.0.1/tcp/0".parse().unwrap())
         .unwrap();
 
     let server_address = server.by_ref().wait()
@@ -160,7 +161,7 @@ where
             Ok(())
         });
 
-    let client = client_transport.dial(server_address.clone()).unwrap()
+    let client = Transport01::dial(client_transport, server_address.clone()).unwrap()
         .map_err(|e| panic!("client error: {}", e))
         .and_then(move |(_, server)| {
             io::write_all(server, message2).and_then(|(client, _)| io::flush(client))
@@ -175,7 +176,7 @@ where
 }
 
 fn expect_identity(output: Output, pk: &identity::PublicKey)
-    -> impl Future<Item = Output, Error = NoiseError>
+    -> future::Ready<Result<Output, NoiseError>>
 {
     match output.0 {
         RemoteIdentity::IdentityKey(ref k) if k == pk => future::ok(output),
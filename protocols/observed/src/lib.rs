@@ -23,7 +23,7 @@
 
 use bytes::Bytes;
 use futures::{future, prelude::*};
-use libp2p_core::{Multiaddr, upgrade::{InboundUpgrade, OutboundUpgrade, UpgradeInfo, Negotiated}};
+use libp2p_core::{Multiaddr, compat::Compat01As03, upgrade::{InboundUpgrade, OutboundUpgrade, UpgradeInfo, Negotiated}};
 use std::{convert::TryFrom, io, iter};
 use tokio_codec::{FramedRead, FramedWrite};
 use tokio_io::{AsyncRead, AsyncWrite};
@@ -53,11 +53,11 @@ where
 {
     type Output = Sender<Negotiated<C>>;
     type Error = io::Error;
-    type Future = Box<dyn Future<Item=Self::Output, Error=Self::Error> + Send>;
+    type Future = Compat01As03<Box<dyn Future<Item=Self::Output, Error=Self::Error> + Send>>;
 
     fn upgrade_inbound(self, conn: Negotiated<C>, _: Self::Info) -> Self::Future {
         let io = FramedWrite::new(conn, UviBytes::default());
-        Box::new(future::ok(Sender { io }))
+        Compat01As03::new(Box::new(future::ok(Sender { io })))
     }
 }
 
@@ -67,7 +67,7 @@ where
 {
     type Output = Multiaddr;
     type Error = io::Error;
-    type Future = Box<dyn Future<Item=Self::Output, Error=Self::Error> + Send>;
+    type Future = Compat01As03<Box<dyn Future<Item=Self::Output, Error=Self::Error> + Send>>;
 
     fn upgrade_outbound(self, conn: Negotiated<C>, _: Self::Info) -> Self::Future {
         let io = FramedRead::new(conn, UviBytes::default());
@@ -82,7 +82,7 @@ where
                     Err(io::ErrorKind::InvalidData.into())
                 }
             });
-        Box::new(future)
+        Compat01As03::new(Box::new(future))
     }
 }
 
@@ -100,7 +100,7 @@ impl<C: AsyncWrite> Sender<C> {
 
 #[cfg(test)]
 mod tests {
-    use libp2p_core::{Multiaddr, upgrade::{apply_inbound, apply_outbound}};
+    use libp2p_core::{Multiaddr, compat::Compat03As01, upgrade::{apply_inbound, apply_outbound}};
     use tokio::runtime::current_thread;
     use tokio::net::{TcpListener, TcpStream};
     use super::*;
@@ -117,7 +117,7 @@ mod tests {
             .into_future()
             .map_err(|_| panic!())
             .and_then(move |(conn, _)| {
-                apply_inbound(conn.unwrap(), Observed::new())
+                Compat03As01::new(apply_inbound(conn.unwrap(), Observed::new()))
             })
             .map_err(|_| panic!())
             .and_then(move |sender| sender.send_address(observed_addr1));
@@ -125,7 +125,7 @@ mod tests {
         let client = TcpStream::connect(&server_addr)
             .map_err(|_| panic!())
             .and_then(|conn| {
-                apply_outbound(conn, Observed::new())
+                Compat03As01::new(apply_outbound(conn, Observed::new()))
             })
             .map_err(|_| panic!())
             .map(move |addr| {
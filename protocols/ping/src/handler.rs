@@ -20,17 +20,18 @@
 
 use crate::protocol;
 use futures::prelude::*;
+use libp2p_core::clock;
 use libp2p_swarm::{
     KeepAlive,
     SubstreamProtocol,
-    ProtocolsHandler,
     ProtocolsHandlerUpgrErr,
-    ProtocolsHandlerEvent
+    ProtocolsHandlerEvent,
+    compat::ProtocolsHandler01
 };
 use std::{error::Error, io, fmt, num::NonZeroU32, time::Duration};
 use std::collections::VecDeque;
 use tokio_io::{AsyncRead, AsyncWrite};
-use wasm_timer::{Delay, Instant};
+use wasm_timer::Delay;
 use void::Void;
 
 /// The configuration for outbound pings.
@@ -176,7 +177,7 @@ impl<TSubstream> PingHandler<TSubstream> {
     pub fn new(config: PingConfig) -> Self {
         PingHandler {
             config,
-            next_ping: Delay::new(Instant::now()),
+            next_ping: Delay::new(clock::now()),
             pending_results: VecDeque::with_capacity(2),
             failures: 0,
             _marker: std::marker::PhantomData
@@ -184,7 +185,7 @@ impl<TSubstream> PingHandler<TSubstream> {
     }
 }
 
-impl<TSubstream> ProtocolsHandler for PingHandler<TSubstream>
+impl<TSubstream> ProtocolsHandler01 for PingHandler<TSubstream>
 where
     TSubstream: AsyncRead + AsyncWrite,
 {
@@ -231,7 +232,7 @@ where
     fn poll(&mut self) -> Poll<ProtocolsHandlerEvent<protocol::Ping, (), PingResult>, Self::Error> {
         if let Some(result) = self.pending_results.pop_back() {
             if let Ok(PingSuccess::Ping { .. }) = result {
-                let next_ping = Instant::now() + self.config.interval;
+                let next_ping = clock::now() + self.config.interval;
                 self.failures = 0;
                 self.next_ping.reset(next_ping);
             }
@@ -248,7 +249,7 @@ where
 
         match self.next_ping.poll() {
             Ok(Async::Ready(())) => {
-                self.next_ping.reset(Instant::now() + self.config.timeout);
+                self.next_ping.reset(clock::now() + self.config.timeout);
                 let protocol = SubstreamProtocol::new(protocol::Ping)
                     .with_timeout(self.config.timeout);
                 Ok(Async::Ready(ProtocolsHandlerEvent::OutboundSubstreamRequest {
@@ -271,6 +272,7 @@ mod tests {
     use rand::Rng;
     use tokio_tcp::TcpStream;
     use tokio::runtime::current_thread::Runtime;
+    use wasm_timer::Instant;
 
     impl Arbitrary for PingConfig {
         fn arbitrary<G: Gen>(g: &mut G) -> PingConfig {
@@ -285,7 +287,7 @@ mod tests {
         ProtocolsHandlerEvent<protocol::Ping, (), PingResult>,
         PingFailure
     > {
-        Runtime::new().unwrap().block_on(future::poll_fn(|| h.poll() ))
+        Runtime::new().unwrap().block_on(future::poll_fn(|| ProtocolsHandler01::poll(h) ))
     }
 
     #[test]
@@ -32,7 +32,8 @@
 //! the connection will be closed.
 //!
 //! The `Ping` network behaviour produces [`PingEvent`]s, which may be consumed from the `Swarm`
-//! by an application, e.g. to collect statistics.
+//! by an application, e.g. to collect statistics. Pings that time out are also reported to the
+//! `Swarm` as a misbehaviour of the remote.
 //!
 //! > **Note**: The ping protocol does not keep otherwise idle connections alive,
 //! > it only adds an additional condition for terminating the connection, namely
@@ -47,11 +48,11 @@ pub mod handler;
 pub use handler::{PingConfig, PingResult, PingSuccess, PingFailure};
 use handler::PingHandler;
 
-use futures::prelude::*;
-use libp2p_core::{ConnectedPoint, Multiaddr, PeerId};
-use libp2p_swarm::{NetworkBehaviour, NetworkBehaviourAction, PollParameters};
+use libp2p_core::{ConnectedPoint, Multiaddr, PeerId, nodes::ConnectionId};
+use libp2p_swarm::{Misbehaviour, NetworkBehaviour, NetworkBehaviourAction, PollParameters};
 use std::collections::VecDeque;
 use std::marker::PhantomData;
+use std::task::{Context, Poll};
 use tokio_io::{AsyncRead, AsyncWrite};
 use void::Void;
 
@@ -62,8 +63,8 @@ use void::Void;
 pub struct Ping<TSubstream> {
     /// Configuration for outbound pings.
     config: PingConfig,
-    /// Queue of events to yield to the swarm.
-    events: VecDeque<PingEvent>,
+    /// Queue of actions to yield to the swarm.
+    events: VecDeque<NetworkBehaviourAction<Void, PingEvent>>,
     _marker: PhantomData<TSubstream>,
 }
 
@@ -112,16 +113,23 @@ where
 
     fn inject_disconnected(&mut self, _: &PeerId, _: ConnectedPoint) {}
 
-    fn inject_node_event(&mut self, peer: PeerId, result: PingResult) {
-        self.events.push_front(PingEvent { peer, result })
+    fn inject_node_event(&mut self, peer: PeerId, _: ConnectionId, result: PingResult) {
+        if let Err(PingFailure::Timeout) = result {
+            self.events.push_front(NetworkBehaviourAction::ReportMisbehaviour {
+                peer_id: peer.clone(),
+                misbehaviour: Misbehaviour::Timeout,
+            })
+        }
+        self.events.push_front(NetworkBehaviourAction::GenerateEvent(PingEvent { peer, result }))
     }
 
-    fn poll(&mut self, _: &mut impl PollParameters) -> Async<NetworkBehaviourAction<Void, PingEvent>>
+    fn poll(&mut self, _: &mut Context<'_>, _: &mut impl PollParameters)
+        -> Poll<NetworkBehaviourAction<Void, PingEvent>>
     {
         if let Some(e) = self.events.pop_back() {
-            Async::Ready(NetworkBehaviourAction::GenerateEvent(e))
+            Poll::Ready(e)
         } else {
-            Async::NotReady
+            Poll::Pending
         }
     }
 }
@@ -19,7 +19,7 @@
 // DEALINGS IN THE SOFTWARE.
 
 use futures::{prelude::*, future, try_ready};
-use libp2p_core::{InboundUpgrade, OutboundUpgrade, UpgradeInfo, upgrade::Negotiated};
+use libp2p_core::{InboundUpgrade, OutboundUpgrade, UpgradeInfo, clock, compat::Compat01As03, upgrade::Negotiated};
 use log::debug;
 use rand::{distributions, prelude::*};
 use std::{io, iter, time::Duration};
@@ -65,7 +65,7 @@ where
 {
     type Output = ();
     type Error = io::Error;
-    type Future = future::Map<
+    type Future = Compat01As03<future::Map<
         future::AndThen<
         future::AndThen<
         future::AndThen<
@@ -73,15 +73,15 @@ where
             SendPong<TSocket>, fn((Negotiated<TSocket>, [u8; 32])) -> SendPong<TSocket>>,
             Flush<TSocket>, fn((Negotiated<TSocket>, [u8; 32])) -> Flush<TSocket>>,
             Shutdown<TSocket>, fn(Negotiated<TSocket>) -> Shutdown<TSocket>>,
-    fn(Negotiated<TSocket>) -> ()>;
+    fn(Negotiated<TSocket>) -> ()>>;
 
     #[inline]
     fn upgrade_inbound(self, socket: Negotiated<TSocket>, _: Self::Info) -> Self::Future {
-        nio::read_exact(socket, [0; 32])
+        Compat01As03::new(nio::read_exact(socket, [0; 32])
             .and_then::<fn(_) -> _, _>(|(sock, buf)| nio::write_all(sock, buf))
             .and_then::<fn(_) -> _, _>(|(sock, _)| nio::flush(sock))
             .and_then::<fn(_) -> _, _>(|sock| nio::shutdown(sock))
-            .map(|_| ())
+            .map(|_| ()))
     }
 }
 
@@ -91,18 +91,18 @@ where
 {
     type Output = Duration;
     type Error = io::Error;
-    type Future = PingDialer<Negotiated<TSocket>>;
+    type Future = Compat01As03<PingDialer<Negotiated<TSocket>>>;
 
     #[inline]
     fn upgrade_outbound(self, socket: Negotiated<TSocket>, _: Self::Info) -> Self::Future {
         let payload: [u8; 32] = thread_rng().sample(distributions::Standard);
         debug!("Preparing ping payload {:?}", payload);
 
-        PingDialer {
+        Compat01As03::new(PingDialer {
             state: PingDialerState::Write {
                 inner: nio::write_all(socket, payload),
             },
-        }
+        })
     }
 }
 
@@ -149,7 +149,7 @@ where
                 },
                 PingDialerState::Flush { ref mut inner, payload } => {
                     let socket = try_ready!(inner.poll());
-                    let started = Instant::now();
+                    let started = clock::now();
                     PingDialerState::Read {
                         inner: nio::read_exact(socket, [0; 32]),
                         payload,
@@ -158,7 +158,7 @@ where
                 },
                 PingDialerState::Read { ref mut inner, payload, started } => {
                     let (socket, payload_received) = try_ready!(inner.poll());
-                    let rtt = started.elapsed();
+                    let rtt = clock::now().duration_since(started);
                     if payload_received != payload {
                         return Err(io::Error::new(
                             io::ErrorKind::InvalidData, "Ping payload mismatch"));
@@ -183,9 +183,9 @@ mod tests {
     use futures::prelude::*;
     use libp2p_core::{
         upgrade,
+        compat::{Compat03As01, Transport01},
         multiaddr::multiaddr,
         transport::{
-            Transport,
             ListenerEvent,
             memory::MemoryTransport
         }
@@ -196,10 +196,10 @@ mod tests {
     #[test]
     fn ping_pong() {
         let mem_addr = multiaddr![Memory(thread_rng().gen::<u64>())];
-        let mut listener = MemoryTransport.listen_on(mem_addr).unwrap();
+        let mut listener = Transport01::listen_on(Compat03As01::new(MemoryTransport), mem_addr).unwrap();
 
         let listener_addr =
-            if let Ok(Async::Ready(Some(ListenerEvent::NewAddress(a)))) = listener.poll() {
+            if let Some(Ok(ListenerEvent::NewAddress(a))) = listener.by_ref().wait().next() {
                 a
             } else {
                 panic!("MemoryTransport not listening on an address!");
@@ -211,13 +211,13 @@ mod tests {
             .and_then(|(listener_event, _)| {
                 let (listener_upgrade, _) = listener_event.unwrap().into_upgrade().unwrap();
                 let conn = listener_upgrade.wait().unwrap();
-                upgrade::apply_inbound(conn, Ping::default())
+                Compat03As01::new(upgrade::apply_inbound(conn, Ping::default()))
                     .map_err(|e| panic!(e))
             });
 
-        let client = MemoryTransport.dial(listener_addr).unwrap()
+        let client = Transport01::dial(Compat03As01::new(MemoryTransport), listener_addr).unwrap()
             .and_then(|c| {
-                upgrade::apply_outbound(c, Ping::default())
+                Compat03As01::new(upgrade::apply_outbound(c, Ping::default()))
                     .map_err(|e| panic!(e))
             });
 
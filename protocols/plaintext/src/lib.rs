@@ -19,7 +19,7 @@
 // DEALINGS IN THE SOFTWARE.
 
 use futures::future::{self, FutureResult};
-use libp2p_core::{InboundUpgrade, OutboundUpgrade, UpgradeInfo, upgrade::Negotiated};
+use libp2p_core::{InboundUpgrade, OutboundUpgrade, UpgradeInfo, compat::Compat01As03, upgrade::Negotiated};
 use std::iter;
 use void::Void;
 
@@ -38,20 +38,20 @@ impl UpgradeInfo for PlainTextConfig {
 impl<C> InboundUpgrade<C> for PlainTextConfig {
     type Output = Negotiated<C>;
     type Error = Void;
-    type Future = FutureResult<Negotiated<C>, Self::Error>;
+    type Future = Compat01As03<FutureResult<Negotiated<C>, Self::Error>>;
 
     fn upgrade_inbound(self, i: Negotiated<C>, _: Self::Info) -> Self::Future {
-        future::ok(i)
+        Compat01As03::new(future::ok(i))
     }
 }
 
 impl<C> OutboundUpgrade<C> for PlainTextConfig {
     type Output = Negotiated<C>;
     type Error = Void;
-    type Future = FutureResult<Negotiated<C>, Self::Error>;
+    type Future = Compat01As03<FutureResult<Negotiated<C>, Self::Error>>;
 
     fn upgrade_outbound(self, i: Negotiated<C>, _: Self::Info) -> Self::Future {
-        future::ok(i)
+        Compat01As03::new(future::ok(i))
     }
 }
 
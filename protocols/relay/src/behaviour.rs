@@ -0,0 +1,406 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use crate::copy::BidiCopy;
+use crate::handler::{RelayHandler, RelayHandlerEvent, RelayHandlerIn};
+use crate::protocol::{IncomingHop, SendMessage, Status};
+use fnv::{FnvHashMap, FnvHashSet};
+use futures::prelude::*;
+use libp2p_core::{ConnectedPoint, Multiaddr, PeerId, nodes::ConnectionId, upgrade::Negotiated};
+use libp2p_swarm::{NetworkBehaviourAction, NotifyHandler, PollParameters, ProtocolsHandler, compat::NetworkBehaviour01};
+use log::debug;
+use std::{collections::VecDeque, io, mem};
+use tokio_io::{AsyncRead, AsyncWrite};
+
+/// Configuration for the relay behaviour.
+#[derive(Debug, Clone)]
+pub struct RelayConfig {
+    max_circuits: usize,
+    max_circuits_per_peer: usize,
+}
+
+impl Default for RelayConfig {
+    fn default() -> Self {
+        RelayConfig {
+            max_circuits: 256,
+            max_circuits_per_peer: 8,
+        }
+    }
+}
+
+impl RelayConfig {
+    /// Sets the maximum number of circuits, pending or established, that we relay at the same
+    /// time.
+    ///
+    /// The default is 256.
+    pub fn set_max_circuits(&mut self, max_circuits: usize) -> &mut Self {
+        self.max_circuits = max_circuits;
+        self
+    }
+
+    /// Sets the maximum number of circuits, pending or established, that a single peer can be
+    /// part of, either as the source or as the destination.
+    ///
+    /// The default is 8.
+    pub fn set_max_circuits_per_peer(&mut self, max_circuits_per_peer: usize) -> &mut Self {
+        self.max_circuits_per_peer = max_circuits_per_peer;
+        self
+    }
+}
+
+/// Network behaviour that allows the local node to act as a relay, also called *hop*.
+///
+/// When a connected peer asks us to relay a connection to another connected peer, we open a
+/// substream with the destination and copy the data between the two substreams. Peers use the
+/// `RelayTransport` to open circuits through us and to accept them.
+pub struct Relay<TSubstream> {
+    /// Configuration of the behaviour.
+    config: RelayConfig,
+
+    /// Peer id of the local node.
+    local_peer_id: PeerId,
+
+    /// Peers we are connected to, and thus can relay connections to.
+    connected_peers: FnvHashSet<PeerId>,
+
+    /// Events that need to be yielded to the outside when polling.
+    events: VecDeque<NetworkBehaviourAction<RelayHandlerIn, RelayEvent>>,
+
+    /// Identifier for the next `STOP` request.
+    next_request_id: u64,
+
+    /// Requests of sources waiting for the destination to accept the circuit.
+    pending: FnvHashMap<u64, PendingCircuit<Negotiated<TSubstream>>>,
+
+    /// Circuits accepted by the destination.
+    circuits: Vec<Circuit<Negotiated<TSubstream>>>,
+
+    /// Requests being refused.
+    denials: Vec<SendMessage<Negotiated<TSubstream>>>,
+
+    /// Number of pending or established circuits of each peer.
+    circuits_per_peer: FnvHashMap<PeerId, usize>,
+}
+
+/// Circuit waiting for the destination to accept it.
+struct PendingCircuit<TSocket> {
+    src: PeerId,
+    dst: PeerId,
+    request: IncomingHop<TSocket>,
+}
+
+/// Circuit accepted by the destination.
+struct Circuit<TSocket> {
+    src: PeerId,
+    dst: PeerId,
+    state: CircuitState<TSocket>,
+}
+
+enum CircuitState<TSocket> {
+    /// Informing the source that the circuit is established.
+    Accepting(SendMessage<TSocket>, TSocket),
+    /// Copying data between the two substreams.
+    Active(BidiCopy<TSocket>),
+    /// Temporary state.
+    Poisoned,
+}
+
+impl<TSocket> Circuit<TSocket>
+where
+    TSocket: AsyncRead + AsyncWrite,
+{
+    fn is_active(&self) -> bool {
+        match self.state {
+            CircuitState::Active(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Drives the circuit. Resolves when both sides are closed.
+    fn poll(&mut self) -> Poll<(), io::Error> {
+        loop {
+            match mem::replace(&mut self.state, CircuitState::Poisoned) {
+                CircuitState::Accepting(mut accept, dst_stream) => match accept.poll()? {
+                    Async::Ready(src_stream) => {
+                        self.state = CircuitState::Active(BidiCopy::new(src_stream, dst_stream));
+                    }
+                    Async::NotReady => {
+                        self.state = CircuitState::Accepting(accept, dst_stream);
+                        return Ok(Async::NotReady);
+                    }
+                },
+                CircuitState::Active(mut copy) => {
+                    let result = copy.poll();
+                    self.state = CircuitState::Active(copy);
+                    return result;
+                }
+                CircuitState::Poisoned => panic!("Circuit polled after an error"),
+            }
+        }
+    }
+}
+
+impl<TSubstream> Relay<TSubstream> {
+    /// Creates a `Relay` with the given configuration.
+    pub fn new(local_peer_id: PeerId, config: RelayConfig) -> Self {
+        Relay {
+            config,
+            local_peer_id,
+            connected_peers: FnvHashSet::default(),
+            events: VecDeque::new(),
+            next_request_id: 0,
+            pending: FnvHashMap::default(),
+            circuits: Vec::new(),
+            denials: Vec::new(),
+            circuits_per_peer: FnvHashMap::default(),
+        }
+    }
+
+    /// Returns the number of circuits, pending or established, that we are relaying.
+    pub fn num_circuits(&self) -> usize {
+        self.pending.len() + self.circuits.len()
+    }
+
+    fn circuits_of(&self, peer_id: &PeerId) -> usize {
+        self.circuits_per_peer.get(peer_id).cloned().unwrap_or(0)
+    }
+
+    fn add_circuit(&mut self, src: &PeerId, dst: &PeerId) {
+        *self.circuits_per_peer.entry(src.clone()).or_insert(0) += 1;
+        *self.circuits_per_peer.entry(dst.clone()).or_insert(0) += 1;
+    }
+
+    fn remove_circuit(&mut self, src: &PeerId, dst: &PeerId) {
+        for peer_id in &[src, dst] {
+            let remove = match self.circuits_per_peer.get_mut(*peer_id) {
+                Some(n) => {
+                    *n -= 1;
+                    *n == 0
+                }
+                None => false,
+            };
+            if remove {
+                self.circuits_per_peer.remove(*peer_id);
+            }
+        }
+    }
+}
+
+impl<TSubstream> Relay<TSubstream>
+where
+    TSubstream: AsyncRead + AsyncWrite,
+{
+    /// Processes a `HOP` request received from `src`.
+    fn handle_hop_request(&mut self, src: PeerId, request: IncomingHop<Negotiated<TSubstream>>) {
+        let dst = request.dst_peer().clone();
+
+        let refusal = if dst == self.local_peer_id || dst == src {
+            Some(Status::HopCantRelayToSelf)
+        } else if !self.connected_peers.contains(&dst) {
+            Some(Status::HopNoConnToDst)
+        } else if self.num_circuits() >= self.config.max_circuits
+            || self.circuits_of(&src) >= self.config.max_circuits_per_peer
+            || self.circuits_of(&dst) >= self.config.max_circuits_per_peer
+        {
+            Some(Status::HopCantSpeakRelay)
+        } else {
+            None
+        };
+
+        if let Some(status) = refusal {
+            debug!("Refusing to relay from {:?} to {:?}: {:?}", src, dst, status);
+            self.denials.push(request.deny(status));
+            self.events.push_back(NetworkBehaviourAction::GenerateEvent(RelayEvent::CircuitDenied {
+                src,
+                dst,
+                status,
+            }));
+            return;
+        }
+
+        let request_id = self.next_request_id;
+        self.next_request_id += 1;
+
+        self.add_circuit(&src, &dst);
+        self.events.push_back(NetworkBehaviourAction::NotifyHandler {
+            peer_id: dst.clone(),
+            handler: NotifyHandler::Any,
+            event: RelayHandlerIn::OpenStop {
+                request_id,
+                src: src.clone(),
+                dst: dst.clone(),
+            },
+        });
+        self.pending.insert(request_id, PendingCircuit { src, dst, request });
+    }
+
+    /// Refuses a pending circuit whose destination couldn't be reached.
+    fn fail_pending(&mut self, pending: PendingCircuit<Negotiated<TSubstream>>) {
+        self.remove_circuit(&pending.src, &pending.dst);
+        let status = Status::HopCantOpenDstStream;
+        self.denials.push(pending.request.deny(status));
+        self.events.push_back(NetworkBehaviourAction::GenerateEvent(RelayEvent::CircuitDenied {
+            src: pending.src,
+            dst: pending.dst,
+            status,
+        }));
+    }
+}
+
+impl<TSubstream> NetworkBehaviour01 for Relay<TSubstream>
+where
+    TSubstream: AsyncRead + AsyncWrite,
+{
+    type ProtocolsHandler = RelayHandler<TSubstream>;
+    type OutEvent = RelayEvent;
+
+    fn new_handler(&mut self) -> Self::ProtocolsHandler {
+        RelayHandler::new()
+    }
+
+    fn addresses_of_peer(&mut self, _: &PeerId) -> Vec<Multiaddr> {
+        Vec::new()
+    }
+
+    fn inject_connected(&mut self, peer_id: PeerId, _: ConnectedPoint) {
+        self.connected_peers.insert(peer_id);
+    }
+
+    fn inject_disconnected(&mut self, peer_id: &PeerId, _: ConnectedPoint) {
+        self.connected_peers.remove(peer_id);
+
+        let failed = self.pending
+            .iter()
+            .filter(|(_, pending)| pending.src == *peer_id || pending.dst == *peer_id)
+            .map(|(request_id, _)| *request_id)
+            .collect::<Vec<_>>();
+        for request_id in failed {
+            if let Some(pending) = self.pending.remove(&request_id) {
+                self.fail_pending(pending);
+            }
+        }
+    }
+
+    fn inject_node_event(&mut self, peer_id: PeerId, _: ConnectionId, event: RelayHandlerEvent<TSubstream>) {
+        match event {
+            RelayHandlerEvent::HopRequest(request) => self.handle_hop_request(peer_id, request),
+            RelayHandlerEvent::StopSuccess { request_id, substream } => {
+                if let Some(pending) = self.pending.remove(&request_id) {
+                    self.circuits.push(Circuit {
+                        src: pending.src,
+                        dst: pending.dst,
+                        state: CircuitState::Accepting(pending.request.accept(), substream),
+                    });
+                }
+            }
+            RelayHandlerEvent::StopFailure { request_id } => {
+                if let Some(pending) = self.pending.remove(&request_id) {
+                    self.fail_pending(pending);
+                }
+            }
+        }
+    }
+
+    fn poll(
+        &mut self,
+        _: &mut impl PollParameters,
+    ) -> Async<
+        NetworkBehaviourAction<
+            <Self::ProtocolsHandler as ProtocolsHandler>::InEvent,
+            Self::OutEvent,
+        >,
+    > {
+        // Drive the refusals. Errors are ignored, as we drop the substreams anyway.
+        let mut n = 0;
+        while n < self.denials.len() {
+            match self.denials[n].poll() {
+                Ok(Async::NotReady) => n += 1,
+                Ok(Async::Ready(_)) | Err(_) => {
+                    self.denials.swap_remove(n);
+                }
+            }
+        }
+
+        let mut n = 0;
+        while n < self.circuits.len() {
+            let was_active = self.circuits[n].is_active();
+            let result = self.circuits[n].poll();
+            if !was_active && self.circuits[n].is_active() {
+                let circuit = &self.circuits[n];
+                self.events.push_back(NetworkBehaviourAction::GenerateEvent(RelayEvent::CircuitEstablished {
+                    src: circuit.src.clone(),
+                    dst: circuit.dst.clone(),
+                }));
+            }
+
+            match result {
+                Ok(Async::NotReady) => {
+                    n += 1;
+                    continue;
+                }
+                Ok(Async::Ready(())) => {}
+                Err(err) => debug!("Relayed circuit closed with an error: {:?}", err),
+            }
+
+            let circuit = self.circuits.swap_remove(n);
+            self.remove_circuit(&circuit.src, &circuit.dst);
+            self.events.push_back(NetworkBehaviourAction::GenerateEvent(RelayEvent::CircuitClosed {
+                src: circuit.src,
+                dst: circuit.dst,
+            }));
+        }
+
+        if let Some(event) = self.events.pop_front() {
+            return Async::Ready(event);
+        }
+
+        Async::NotReady
+    }
+}
+
+/// Event that can happen on the relay behaviour.
+#[derive(Debug, Clone)]
+pub enum RelayEvent {
+    /// We now relay the data between two peers.
+    CircuitEstablished {
+        /// The peer that requested the circuit.
+        src: PeerId,
+        /// The peer the circuit leads to.
+        dst: PeerId,
+    },
+
+    /// An established circuit has been closed.
+    CircuitClosed {
+        /// The peer that requested the circuit.
+        src: PeerId,
+        /// The peer the circuit leads to.
+        dst: PeerId,
+    },
+
+    /// We refused to relay a connection.
+    CircuitDenied {
+        /// The peer that requested the circuit.
+        src: PeerId,
+        /// The peer the circuit should have led to.
+        dst: PeerId,
+        /// The reason, as sent to the source.
+        status: Status,
+    },
+}
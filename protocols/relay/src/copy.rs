@@ -0,0 +1,139 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Bidirectional copy of data between the two substreams of a circuit.
+
+use futures::{prelude::*, try_ready};
+use std::io;
+use tokio_io::{AsyncRead, AsyncWrite};
+
+/// Size of the buffer used in each direction.
+const BUFFER_SIZE: usize = 4096;
+
+/// Future that copies the data read from each socket into the other one, until both have been
+/// closed.
+///
+/// When a socket reaches EOF, the writing side of the other socket is shut down.
+pub struct BidiCopy<TSocket> {
+    a: TSocket,
+    b: TSocket,
+    a_to_b: Direction,
+    b_to_a: Direction,
+}
+
+impl<TSocket> BidiCopy<TSocket> {
+    /// Builds a `BidiCopy` joining `a` and `b`.
+    pub fn new(a: TSocket, b: TSocket) -> Self {
+        BidiCopy {
+            a,
+            b,
+            a_to_b: Direction::new(),
+            b_to_a: Direction::new(),
+        }
+    }
+}
+
+impl<TSocket> Future for BidiCopy<TSocket>
+where
+    TSocket: AsyncRead + AsyncWrite,
+{
+    type Item = ();
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<(), io::Error> {
+        let a_to_b = self.a_to_b.poll(&mut self.a, &mut self.b)?;
+        let b_to_a = self.b_to_a.poll(&mut self.b, &mut self.a)?;
+        if a_to_b.is_ready() && b_to_a.is_ready() {
+            Ok(Async::Ready(()))
+        } else {
+            Ok(Async::NotReady)
+        }
+    }
+}
+
+/// State of the copy in one direction.
+struct Direction {
+    buf: Box<[u8]>,
+    pos: usize,
+    cap: usize,
+    read_done: bool,
+    need_flush: bool,
+    done: bool,
+}
+
+impl Direction {
+    fn new() -> Self {
+        Direction {
+            buf: vec![0; BUFFER_SIZE].into_boxed_slice(),
+            pos: 0,
+            cap: 0,
+            read_done: false,
+            need_flush: false,
+            done: false,
+        }
+    }
+
+    fn poll<R, W>(&mut self, reader: &mut R, writer: &mut W) -> Poll<(), io::Error>
+    where
+        R: AsyncRead,
+        W: AsyncWrite,
+    {
+        if self.done {
+            return Ok(Async::Ready(()));
+        }
+
+        loop {
+            if self.pos == self.cap && !self.read_done {
+                match reader.poll_read(&mut self.buf)? {
+                    Async::Ready(0) => self.read_done = true,
+                    Async::Ready(n) => {
+                        self.pos = 0;
+                        self.cap = n;
+                    }
+                    Async::NotReady => {
+                        // Nothing more to write for now; make sure that what we wrote so far
+                        // reaches the remote.
+                        if self.need_flush {
+                            try_ready!(writer.poll_flush());
+                            self.need_flush = false;
+                        }
+                        return Ok(Async::NotReady);
+                    }
+                }
+            }
+
+            while self.pos < self.cap {
+                let n = try_ready!(writer.poll_write(&self.buf[self.pos .. self.cap]));
+                if n == 0 {
+                    return Err(io::ErrorKind::WriteZero.into());
+                }
+                self.pos += n;
+                self.need_flush = true;
+            }
+
+            if self.read_done {
+                try_ready!(writer.shutdown());
+                self.done = true;
+                return Ok(Async::Ready(()));
+            }
+        }
+    }
+}
+
@@ -0,0 +1,194 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use crate::protocol::{
+    IncomingHop,
+    RelayError,
+    RelayListen,
+    RelayRemoteRequest,
+    SendMessage,
+    Status,
+    StopRequest
+};
+use futures::prelude::*;
+use libp2p_core::{PeerId, upgrade::Negotiated};
+use libp2p_swarm::{
+    KeepAlive,
+    ProtocolsHandlerEvent,
+    ProtocolsHandlerUpgrErr,
+    SubstreamProtocol,
+    compat::ProtocolsHandler01
+};
+use smallvec::SmallVec;
+use std::marker::PhantomData;
+use tokio_io::{AsyncRead, AsyncWrite};
+use void::Void;
+
+/// Protocol handler of the relay on a connection.
+///
+/// Reports the `HOP` requests of the remote to the behaviour, and opens `STOP` substreams
+/// towards the remote when the behaviour asks for it.
+pub struct RelayHandler<TSubstream> {
+    /// Queue of events to produce in `poll()`.
+    events_out: SmallVec<[RelayHandlerEvent<TSubstream>; 4]>,
+    /// Queue of `STOP` substreams to open, with the identifier of the request.
+    stops_to_open: SmallVec<[(u64, StopRequest); 4]>,
+    /// `STOP` requests received from the remote, which we are refusing.
+    denials: Vec<SendMessage<Negotiated<TSubstream>>>,
+    /// Pin the `TSubstream` generic.
+    marker: PhantomData<TSubstream>,
+}
+
+impl<TSubstream> RelayHandler<TSubstream> {
+    /// Builds a new `RelayHandler`.
+    pub fn new() -> Self {
+        RelayHandler {
+            events_out: SmallVec::new(),
+            stops_to_open: SmallVec::new(),
+            denials: Vec::new(),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<TSubstream> Default for RelayHandler<TSubstream> {
+    fn default() -> Self {
+        RelayHandler::new()
+    }
+}
+
+/// Event that can be sent to a `RelayHandler`.
+#[derive(Debug, Clone)]
+pub enum RelayHandlerIn {
+    /// Open a `STOP` substream with the remote, informing it of a circuit opened by `src`.
+    OpenStop {
+        /// Identifier of the request, reported back in the resulting event.
+        request_id: u64,
+        /// The peer at the other end of the circuit.
+        src: PeerId,
+        /// The remote of this connection.
+        dst: PeerId,
+    },
+}
+
+/// Event produced by a `RelayHandler`.
+pub enum RelayHandlerEvent<TSubstream> {
+    /// The remote asks us to relay a connection to another peer.
+    HopRequest(IncomingHop<Negotiated<TSubstream>>),
+    /// The remote accepted a `STOP` request. The substream is connected to the remote.
+    StopSuccess {
+        /// Identifier passed in `RelayHandlerIn::OpenStop`.
+        request_id: u64,
+        /// The substream, ready to transport relayed data.
+        substream: Negotiated<TSubstream>,
+    },
+    /// Opening the `STOP` substream failed, or the remote refused it.
+    StopFailure {
+        /// Identifier passed in `RelayHandlerIn::OpenStop`.
+        request_id: u64,
+    },
+}
+
+impl<TSubstream> ProtocolsHandler01 for RelayHandler<TSubstream>
+where
+    TSubstream: AsyncRead + AsyncWrite,
+{
+    type InEvent = RelayHandlerIn;
+    type OutEvent = RelayHandlerEvent<TSubstream>;
+    type Error = Void;
+    type Substream = TSubstream;
+    type InboundProtocol = RelayListen;
+    type OutboundProtocol = StopRequest;
+    type OutboundOpenInfo = u64;
+
+    fn listen_protocol(&self) -> SubstreamProtocol<Self::InboundProtocol> {
+        SubstreamProtocol::new(RelayListen::new())
+    }
+
+    fn inject_fully_negotiated_inbound(
+        &mut self,
+        request: RelayRemoteRequest<Negotiated<TSubstream>>
+    ) {
+        match request {
+            RelayRemoteRequest::Hop(request) =>
+                self.events_out.push(RelayHandlerEvent::HopRequest(request)),
+            // We are the relay of this connection, not the destination of a circuit.
+            RelayRemoteRequest::Stop(request) =>
+                self.denials.push(request.deny(Status::StopRelayRefused)),
+        }
+    }
+
+    fn inject_fully_negotiated_outbound(
+        &mut self,
+        substream: Negotiated<TSubstream>,
+        request_id: Self::OutboundOpenInfo
+    ) {
+        self.events_out.push(RelayHandlerEvent::StopSuccess { request_id, substream });
+    }
+
+    fn inject_event(&mut self, event: RelayHandlerIn) {
+        match event {
+            RelayHandlerIn::OpenStop { request_id, src, dst } =>
+                self.stops_to_open.push((request_id, StopRequest { src, dst })),
+        }
+    }
+
+    fn inject_dial_upgrade_error(
+        &mut self,
+        request_id: Self::OutboundOpenInfo,
+        _: ProtocolsHandlerUpgrErr<RelayError>
+    ) {
+        self.events_out.push(RelayHandlerEvent::StopFailure { request_id });
+    }
+
+    fn connection_keep_alive(&self) -> KeepAlive {
+        // The circuits going through this connection are driven by the behaviour, so we have no
+        // way to know whether the connection is still in use.
+        KeepAlive::Yes
+    }
+
+    fn poll(&mut self) -> Poll<ProtocolsHandlerEvent<StopRequest, u64, RelayHandlerEvent<TSubstream>>, Void> {
+        // Drive the refusals of `STOP` requests. Errors are ignored, as we drop the substreams
+        // anyway.
+        let mut n = 0;
+        while n < self.denials.len() {
+            match self.denials[n].poll() {
+                Ok(Async::NotReady) => n += 1,
+                Ok(Async::Ready(_)) | Err(_) => {
+                    self.denials.swap_remove(n);
+                }
+            }
+        }
+
+        if !self.events_out.is_empty() {
+            return Ok(Async::Ready(ProtocolsHandlerEvent::Custom(self.events_out.remove(0))));
+        }
+
+        if !self.stops_to_open.is_empty() {
+            let (request_id, request) = self.stops_to_open.remove(0);
+            return Ok(Async::Ready(ProtocolsHandlerEvent::OutboundSubstreamRequest {
+                protocol: SubstreamProtocol::new(request),
+                info: request_id,
+            }));
+        }
+
+        Ok(Async::NotReady)
+    }
+}
@@ -0,0 +1,42 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Implementation of the circuit relay protocol, `/libp2p/circuit/relay/0.1.0`, see also the
+//! [spec](https://github.com/libp2p/specs/tree/master/relay).
+//!
+//! A relay allows two peers that can't reach each other directly, for example because one of
+//! them is behind a NAT, to communicate through a third peer they are both connected to.
+//!
+//! - The [`RelayTransport`] is used by the two ends of a circuit. It dials addresses of the form
+//!   `<relay-addr>/p2p/<relay-id>/p2p-circuit/p2p/<dst-id>`, and listens on addresses of the form
+//!   `<relay-addr>/p2p/<relay-id>/p2p-circuit`.
+//! - The [`Relay`] network behaviour is used by the relay itself.
+
+pub mod protocol;
+
+mod behaviour;
+mod copy;
+mod handler;
+mod message_proto;
+mod transport;
+
+pub use self::behaviour::{Relay, RelayConfig, RelayEvent};
+pub use self::handler::{RelayHandler, RelayHandlerEvent, RelayHandlerIn};
+pub use self::transport::{RelayDial, RelayListener, RelayTransport, RelayTransportError, RelayedStream};
@@ -0,0 +1,565 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Wire protocol of the circuit relay, `/libp2p/circuit/relay/0.1.0`.
+//!
+//! Every substream starts with a single length-prefixed protobuf message. A `HOP` message is
+//! sent by the source to the relay, and a `STOP` message by the relay to the destination. The
+//! receiver answers with a `STATUS` message and, on success, the substream is from then on used
+//! to transport the raw data of the relayed connection.
+
+use crate::message_proto::{CircuitRelay, CircuitRelay_Peer, CircuitRelay_Status, CircuitRelay_Type};
+use futures::{prelude::*, try_ready};
+use libp2p_core::{
+    Multiaddr,
+    PeerId,
+    compat::Compat01As03,
+    upgrade::{self, InboundUpgrade, Negotiated, OutboundUpgrade, UpgradeInfo}
+};
+use protobuf::{self, Message, ProtobufError};
+use std::{convert::TryFrom, error, fmt, io, iter, mem};
+use tokio_io::{AsyncRead, AsyncWrite, io::{Flush, WriteAll}};
+use unsigned_varint::encode;
+
+/// Name of the protocol, as negotiated with multistream-select.
+pub const PROTOCOL_NAME: &[u8] = b"/libp2p/circuit/relay/0.1.0";
+
+/// Maximum size of a message of the protocol. Messages only contain a few peer ids and
+/// addresses, so this is plenty.
+const MAX_MESSAGE_SIZE: usize = 4096;
+
+/// Status code carried by a `STATUS` message.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Status {
+    Success,
+    HopSrcAddrTooLong,
+    HopDstAddrTooLong,
+    HopSrcMultiaddrInvalid,
+    HopDstMultiaddrInvalid,
+    HopNoConnToDst,
+    HopCantDialDst,
+    HopCantOpenDstStream,
+    HopCantSpeakRelay,
+    HopCantRelayToSelf,
+    StopSrcAddrTooLong,
+    StopDstAddrTooLong,
+    StopSrcMultiaddrInvalid,
+    StopDstMultiaddrInvalid,
+    StopRelayRefused,
+    MalformedMessage,
+}
+
+impl From<CircuitRelay_Status> for Status {
+    fn from(status: CircuitRelay_Status) -> Status {
+        match status {
+            CircuitRelay_Status::SUCCESS => Status::Success,
+            CircuitRelay_Status::HOP_SRC_ADDR_TOO_LONG => Status::HopSrcAddrTooLong,
+            CircuitRelay_Status::HOP_DST_ADDR_TOO_LONG => Status::HopDstAddrTooLong,
+            CircuitRelay_Status::HOP_SRC_MULTIADDR_INVALID => Status::HopSrcMultiaddrInvalid,
+            CircuitRelay_Status::HOP_DST_MULTIADDR_INVALID => Status::HopDstMultiaddrInvalid,
+            CircuitRelay_Status::HOP_NO_CONN_TO_DST => Status::HopNoConnToDst,
+            CircuitRelay_Status::HOP_CANT_DIAL_DST => Status::HopCantDialDst,
+            CircuitRelay_Status::HOP_CANT_OPEN_DST_STREAM => Status::HopCantOpenDstStream,
+            CircuitRelay_Status::HOP_CANT_SPEAK_RELAY => Status::HopCantSpeakRelay,
+            CircuitRelay_Status::HOP_CANT_RELAY_TO_SELF => Status::HopCantRelayToSelf,
+            CircuitRelay_Status::STOP_SRC_ADDR_TOO_LONG => Status::StopSrcAddrTooLong,
+            CircuitRelay_Status::STOP_DST_ADDR_TOO_LONG => Status::StopDstAddrTooLong,
+            CircuitRelay_Status::STOP_SRC_MULTIADDR_INVALID => Status::StopSrcMultiaddrInvalid,
+            CircuitRelay_Status::STOP_DST_MULTIADDR_INVALID => Status::StopDstMultiaddrInvalid,
+            CircuitRelay_Status::STOP_RELAY_REFUSED => Status::StopRelayRefused,
+            CircuitRelay_Status::MALFORMED_MESSAGE => Status::MalformedMessage,
+        }
+    }
+}
+
+impl From<Status> for CircuitRelay_Status {
+    fn from(status: Status) -> CircuitRelay_Status {
+        match status {
+            Status::Success => CircuitRelay_Status::SUCCESS,
+            Status::HopSrcAddrTooLong => CircuitRelay_Status::HOP_SRC_ADDR_TOO_LONG,
+            Status::HopDstAddrTooLong => CircuitRelay_Status::HOP_DST_ADDR_TOO_LONG,
+            Status::HopSrcMultiaddrInvalid => CircuitRelay_Status::HOP_SRC_MULTIADDR_INVALID,
+            Status::HopDstMultiaddrInvalid => CircuitRelay_Status::HOP_DST_MULTIADDR_INVALID,
+            Status::HopNoConnToDst => CircuitRelay_Status::HOP_NO_CONN_TO_DST,
+            Status::HopCantDialDst => CircuitRelay_Status::HOP_CANT_DIAL_DST,
+            Status::HopCantOpenDstStream => CircuitRelay_Status::HOP_CANT_OPEN_DST_STREAM,
+            Status::HopCantSpeakRelay => CircuitRelay_Status::HOP_CANT_SPEAK_RELAY,
+            Status::HopCantRelayToSelf => CircuitRelay_Status::HOP_CANT_RELAY_TO_SELF,
+            Status::StopSrcAddrTooLong => CircuitRelay_Status::STOP_SRC_ADDR_TOO_LONG,
+            Status::StopDstAddrTooLong => CircuitRelay_Status::STOP_DST_ADDR_TOO_LONG,
+            Status::StopSrcMultiaddrInvalid => CircuitRelay_Status::STOP_SRC_MULTIADDR_INVALID,
+            Status::StopDstMultiaddrInvalid => CircuitRelay_Status::STOP_DST_MULTIADDR_INVALID,
+            Status::StopRelayRefused => CircuitRelay_Status::STOP_RELAY_REFUSED,
+            Status::MalformedMessage => CircuitRelay_Status::MALFORMED_MESSAGE,
+        }
+    }
+}
+
+/// Outbound upgrade sent by the source to the relay, asking it to open a circuit towards the
+/// destination.
+///
+/// On success, the output is the substream, which is now connected to the destination.
+#[derive(Debug, Clone)]
+pub struct HopRequest {
+    /// The local peer.
+    pub src: PeerId,
+    /// The peer to connect to through the relay.
+    pub dst: PeerId,
+}
+
+impl UpgradeInfo for HopRequest {
+    type Info = &'static [u8];
+    type InfoIter = iter::Once<Self::Info>;
+
+    fn protocol_info(&self) -> Self::InfoIter {
+        iter::once(PROTOCOL_NAME)
+    }
+}
+
+impl<TSocket> OutboundUpgrade<TSocket> for HopRequest
+where
+    TSocket: AsyncRead + AsyncWrite,
+{
+    type Output = Negotiated<TSocket>;
+    type Error = RelayError;
+    type Future = Compat01As03<RequestFuture<Negotiated<TSocket>>>;
+
+    fn upgrade_outbound(self, socket: Negotiated<TSocket>, _: Self::Info) -> Self::Future {
+        let mut msg = CircuitRelay::new();
+        msg.set_field_type(CircuitRelay_Type::HOP);
+        msg.set_srcPeer(peer_to_proto(&self.src));
+        msg.set_dstPeer(peer_to_proto(&self.dst));
+        Compat01As03::new(RequestFuture::new(socket, &msg))
+    }
+}
+
+/// Outbound upgrade sent by the relay to the destination, informing it of a new incoming
+/// circuit.
+///
+/// On success, the output is the substream, which is now connected to the source.
+#[derive(Debug, Clone)]
+pub struct StopRequest {
+    /// The peer that initiated the circuit.
+    pub src: PeerId,
+    /// The peer we are opening the substream with.
+    pub dst: PeerId,
+}
+
+impl UpgradeInfo for StopRequest {
+    type Info = &'static [u8];
+    type InfoIter = iter::Once<Self::Info>;
+
+    fn protocol_info(&self) -> Self::InfoIter {
+        iter::once(PROTOCOL_NAME)
+    }
+}
+
+impl<TSocket> OutboundUpgrade<TSocket> for StopRequest
+where
+    TSocket: AsyncRead + AsyncWrite,
+{
+    type Output = Negotiated<TSocket>;
+    type Error = RelayError;
+    type Future = Compat01As03<RequestFuture<Negotiated<TSocket>>>;
+
+    fn upgrade_outbound(self, socket: Negotiated<TSocket>, _: Self::Info) -> Self::Future {
+        let mut msg = CircuitRelay::new();
+        msg.set_field_type(CircuitRelay_Type::STOP);
+        msg.set_srcPeer(peer_to_proto(&self.src));
+        msg.set_dstPeer(peer_to_proto(&self.dst));
+        Compat01As03::new(RequestFuture::new(socket, &msg))
+    }
+}
+
+/// Future that sends a request and waits for a successful `STATUS` answer.
+pub struct RequestFuture<TSocket> {
+    state: RequestState<TSocket>,
+}
+
+enum RequestState<TSocket> {
+    Send(SendMessage<TSocket>),
+    Recv(RecvMessage<TSocket>),
+    Poisoned,
+}
+
+impl<TSocket> RequestFuture<TSocket>
+where
+    TSocket: AsyncWrite,
+{
+    fn new(socket: TSocket, msg: &CircuitRelay) -> Self {
+        RequestFuture {
+            state: RequestState::Send(SendMessage::new(socket, msg)),
+        }
+    }
+}
+
+impl<TSocket> Future for RequestFuture<TSocket>
+where
+    TSocket: AsyncRead + AsyncWrite,
+{
+    type Item = TSocket;
+    type Error = RelayError;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        loop {
+            match mem::replace(&mut self.state, RequestState::Poisoned) {
+                RequestState::Send(mut send) => match send.poll()? {
+                    Async::Ready(socket) => self.state = RequestState::Recv(recv_message(socket)),
+                    Async::NotReady => {
+                        self.state = RequestState::Send(send);
+                        return Ok(Async::NotReady);
+                    }
+                },
+                RequestState::Recv(mut recv) => match recv.poll()? {
+                    Async::Ready((socket, msg)) => {
+                        if msg.get_field_type() != CircuitRelay_Type::STATUS {
+                            return Err(RelayError::UnexpectedMessage);
+                        }
+                        return match Status::from(msg.get_code()) {
+                            Status::Success => Ok(Async::Ready(socket)),
+                            status => Err(RelayError::Refused(status)),
+                        };
+                    }
+                    Async::NotReady => {
+                        self.state = RequestState::Recv(recv);
+                        return Ok(Async::NotReady);
+                    }
+                },
+                RequestState::Poisoned => panic!("RequestFuture polled after completion"),
+            }
+        }
+    }
+}
+
+/// Inbound upgrade that accepts both `HOP` and `STOP` requests.
+#[derive(Debug, Default, Clone)]
+pub struct RelayListen {}
+
+impl RelayListen {
+    /// Builds a new `RelayListen`.
+    pub fn new() -> Self {
+        RelayListen {}
+    }
+}
+
+impl UpgradeInfo for RelayListen {
+    type Info = &'static [u8];
+    type InfoIter = iter::Once<Self::Info>;
+
+    fn protocol_info(&self) -> Self::InfoIter {
+        iter::once(PROTOCOL_NAME)
+    }
+}
+
+impl<TSocket> InboundUpgrade<TSocket> for RelayListen
+where
+    TSocket: AsyncRead + AsyncWrite,
+{
+    type Output = RelayRemoteRequest<Negotiated<TSocket>>;
+    type Error = RelayError;
+    type Future = Compat01As03<RelayListenFuture<Negotiated<TSocket>>>;
+
+    fn upgrade_inbound(self, socket: Negotiated<TSocket>, _: Self::Info) -> Self::Future {
+        Compat01As03::new(RelayListenFuture {
+            inner: recv_message(socket),
+        })
+    }
+}
+
+/// Future returned by `RelayListen::upgrade_inbound`.
+pub struct RelayListenFuture<TSocket> {
+    inner: RecvMessage<TSocket>,
+}
+
+impl<TSocket> Future for RelayListenFuture<TSocket>
+where
+    TSocket: AsyncRead,
+{
+    type Item = RelayRemoteRequest<TSocket>;
+    type Error = RelayError;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        let (socket, mut msg) = try_ready!(self.inner.poll());
+        match msg.get_field_type() {
+            CircuitRelay_Type::HOP => {
+                if !msg.has_dstPeer() {
+                    return Err(RelayError::InvalidPeerId);
+                }
+                let (dst, dst_addrs) = peer_from_proto(msg.take_dstPeer())?;
+                Ok(Async::Ready(RelayRemoteRequest::Hop(IncomingHop { socket, dst, dst_addrs })))
+            }
+            CircuitRelay_Type::STOP => {
+                if !msg.has_srcPeer() {
+                    return Err(RelayError::InvalidPeerId);
+                }
+                let (src, src_addrs) = peer_from_proto(msg.take_srcPeer())?;
+                Ok(Async::Ready(RelayRemoteRequest::Stop(IncomingStop { socket, src, src_addrs })))
+            }
+            CircuitRelay_Type::STATUS | CircuitRelay_Type::CAN_HOP => Err(RelayError::UnexpectedMessage),
+        }
+    }
+}
+
+/// Request received from a remote.
+pub enum RelayRemoteRequest<TSocket> {
+    /// The remote wants us to relay a connection to another peer.
+    Hop(IncomingHop<TSocket>),
+    /// The remote, acting as a relay, informs us of a new circuit towards us.
+    Stop(IncomingStop<TSocket>),
+}
+
+/// A `HOP` request received from a remote, which has to be answered with `accept` or `deny`.
+pub struct IncomingHop<TSocket> {
+    socket: TSocket,
+    dst: PeerId,
+    dst_addrs: Vec<Multiaddr>,
+}
+
+impl<TSocket> IncomingHop<TSocket>
+where
+    TSocket: AsyncWrite,
+{
+    /// Returns the peer the remote wants to connect to.
+    pub fn dst_peer(&self) -> &PeerId {
+        &self.dst
+    }
+
+    /// Returns the addresses of the destination, as reported by the remote.
+    pub fn dst_addrs(&self) -> &[Multiaddr] {
+        &self.dst_addrs
+    }
+
+    /// Informs the remote that the circuit has been established. The returned future yields the
+    /// substream, which can then be used to transport the relayed data.
+    pub fn accept(self) -> SendMessage<TSocket> {
+        SendMessage::new(self.socket, &status_message(Status::Success))
+    }
+
+    /// Refuses the request with the given status.
+    pub fn deny(self, status: Status) -> SendMessage<TSocket> {
+        SendMessage::new(self.socket, &status_message(status))
+    }
+}
+
+/// A `STOP` request received from a relay, which has to be answered with `accept` or `deny`.
+pub struct IncomingStop<TSocket> {
+    socket: TSocket,
+    src: PeerId,
+    src_addrs: Vec<Multiaddr>,
+}
+
+impl<TSocket> IncomingStop<TSocket>
+where
+    TSocket: AsyncWrite,
+{
+    /// Returns the peer at the other end of the circuit.
+    pub fn src_peer(&self) -> &PeerId {
+        &self.src
+    }
+
+    /// Returns the addresses of the source, as reported by the relay.
+    pub fn src_addrs(&self) -> &[Multiaddr] {
+        &self.src_addrs
+    }
+
+    /// Accepts the circuit. The returned future yields the substream, which is connected to the
+    /// source.
+    pub fn accept(self) -> SendMessage<TSocket> {
+        SendMessage::new(self.socket, &status_message(Status::Success))
+    }
+
+    /// Refuses the circuit with the given status.
+    pub fn deny(self, status: Status) -> SendMessage<TSocket> {
+        SendMessage::new(self.socket, &status_message(status))
+    }
+}
+
+/// Future that writes a message on a socket, flushes it, then yields back the socket.
+pub struct SendMessage<TSocket> {
+    state: SendState<TSocket>,
+}
+
+enum SendState<TSocket> {
+    Write(WriteAll<TSocket, Vec<u8>>),
+    Flush(Flush<TSocket>),
+    Poisoned,
+}
+
+impl<TSocket> SendMessage<TSocket>
+where
+    TSocket: AsyncWrite,
+{
+    fn new(socket: TSocket, msg: &CircuitRelay) -> Self {
+        let bytes = msg
+            .write_to_bytes()
+            .expect("there is no situation in which the protobuf message can be invalid");
+        let mut len_buf = encode::usize_buffer();
+        let mut data = encode::usize(bytes.len(), &mut len_buf).to_vec();
+        data.extend_from_slice(&bytes);
+        SendMessage {
+            state: SendState::Write(tokio_io::io::write_all(socket, data)),
+        }
+    }
+}
+
+impl<TSocket> Future for SendMessage<TSocket>
+where
+    TSocket: AsyncWrite,
+{
+    type Item = TSocket;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        loop {
+            match mem::replace(&mut self.state, SendState::Poisoned) {
+                SendState::Write(mut write) => match write.poll()? {
+                    Async::Ready((socket, _)) => self.state = SendState::Flush(tokio_io::io::flush(socket)),
+                    Async::NotReady => {
+                        self.state = SendState::Write(write);
+                        return Ok(Async::NotReady);
+                    }
+                },
+                SendState::Flush(mut flush) => match flush.poll()? {
+                    Async::Ready(socket) => return Ok(Async::Ready(socket)),
+                    Async::NotReady => {
+                        self.state = SendState::Flush(flush);
+                        return Ok(Async::NotReady);
+                    }
+                },
+                SendState::Poisoned => panic!("SendMessage polled after completion"),
+            }
+        }
+    }
+}
+
+/// Future that reads a single message, then yields back the socket and the message.
+///
+/// The message is read byte by byte, so that no data of the relayed connection that directly
+/// follows it is consumed.
+type RecvMessage<TSocket> = upgrade::ReadRespond<
+    TSocket,
+    (),
+    fn(TSocket, Vec<u8>, ()) -> Result<(TSocket, CircuitRelay), RelayError>
+>;
+
+fn recv_message<TSocket>(socket: TSocket) -> RecvMessage<TSocket>
+where
+    TSocket: AsyncRead,
+{
+    upgrade::read_respond(socket, MAX_MESSAGE_SIZE, (), parse_message as fn(_, _, _) -> _)
+}
+
+fn parse_message<TSocket>(socket: TSocket, bytes: Vec<u8>, _: ()) -> Result<(TSocket, CircuitRelay), RelayError> {
+    let msg = protobuf::parse_from_bytes(&bytes)?;
+    Ok((socket, msg))
+}
+
+fn status_message(status: Status) -> CircuitRelay {
+    let mut msg = CircuitRelay::new();
+    msg.set_field_type(CircuitRelay_Type::STATUS);
+    msg.set_code(status.into());
+    msg
+}
+
+fn peer_to_proto(peer_id: &PeerId) -> CircuitRelay_Peer {
+    let mut peer = CircuitRelay_Peer::new();
+    peer.set_id(peer_id.as_bytes().to_vec());
+    peer
+}
+
+fn peer_from_proto(mut peer: CircuitRelay_Peer) -> Result<(PeerId, Vec<Multiaddr>), RelayError> {
+    let peer_id = PeerId::from_bytes(peer.take_id()).map_err(|_| RelayError::InvalidPeerId)?;
+    let addrs = peer
+        .take_addrs()
+        .into_iter()
+        .filter_map(|addr| Multiaddr::try_from(addr).ok())
+        .collect();
+    Ok((peer_id, addrs))
+}
+
+/// Error that can happen while performing the relay protocol.
+#[derive(Debug)]
+pub enum RelayError {
+    /// Error on the socket.
+    Io(io::Error),
+    /// Error when reading the message from the socket.
+    ReadError(upgrade::ReadOneError),
+    /// Error when decoding the raw buffer into a protobuf.
+    ProtobufError(ProtobufError),
+    /// Error when parsing a `PeerId` in the message.
+    InvalidPeerId,
+    /// The remote sent a message of the wrong type.
+    UnexpectedMessage,
+    /// The remote refused the request.
+    Refused(Status),
+}
+
+impl From<io::Error> for RelayError {
+    #[inline]
+    fn from(err: io::Error) -> Self {
+        RelayError::Io(err)
+    }
+}
+
+impl From<upgrade::ReadOneError> for RelayError {
+    #[inline]
+    fn from(err: upgrade::ReadOneError) -> Self {
+        RelayError::ReadError(err)
+    }
+}
+
+impl From<ProtobufError> for RelayError {
+    #[inline]
+    fn from(err: ProtobufError) -> Self {
+        RelayError::ProtobufError(err)
+    }
+}
+
+impl fmt::Display for RelayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            RelayError::Io(ref err) =>
+                write!(f, "I/O error: {}", err),
+            RelayError::ReadError(ref err) =>
+                write!(f, "Error while reading from socket: {}", err),
+            RelayError::ProtobufError(ref err) =>
+                write!(f, "Error while decoding protobuf: {}", err),
+            RelayError::InvalidPeerId =>
+                write!(f, "Error while decoding PeerId from message"),
+            RelayError::UnexpectedMessage =>
+                write!(f, "Received a message of an unexpected type"),
+            RelayError::Refused(status) =>
+                write!(f, "Request refused by the remote: {:?}", status),
+        }
+    }
+}
+
+impl error::Error for RelayError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            RelayError::Io(ref err) => Some(err),
+            RelayError::ReadError(ref err) => Some(err),
+            RelayError::ProtobufError(ref err) => Some(err),
+            RelayError::InvalidPeerId => None,
+            RelayError::UnexpectedMessage => None,
+            RelayError::Refused(_) => None,
+        }
+    }
+}
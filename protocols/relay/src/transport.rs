@@ -0,0 +1,473 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Transport that dials and listens on `/p2p-circuit` addresses.
+
+use crate::protocol::{HopRequest, RelayError, RelayListen, RelayRemoteRequest, SendMessage, Status};
+use futures::{future::{self, FutureResult}, prelude::*};
+use libp2p_core::{
+    PeerId,
+    Transport,
+    compat::{self, Compat03As01, Transport01},
+    multiaddr::{Multiaddr, Protocol},
+    muxing::{self, OutboundSubstreamRefWrapFuture, StreamMuxer, SubstreamRef},
+    transport::{ListenerEvent, TransportError},
+    upgrade::{self, InboundUpgradeApply, Negotiated, OutboundUpgradeApply, UpgradeError}
+};
+use log::debug;
+use std::{error, fmt, io, mem, sync::Arc, task};
+
+/// Stream relayed through a circuit.
+pub type RelayedStream<TMuxer> = Negotiated<SubstreamRef<Arc<TMuxer>>>;
+
+/// Transport that connects to peers through a relay.
+///
+/// The inner transport is used to reach the relay, and must yield an authenticated and
+/// multiplexed connection, in other words `(PeerId, StreamMuxer)`. Each dialing attempt and
+/// each listener opens its own connection to the relay.
+///
+/// - Dialing `<relay-addr>/p2p/<relay-id>/p2p-circuit/p2p/<dst-id>` asks the relay to open a
+///   circuit towards `<dst-id>`. The relay must be connected to the destination.
+/// - Listening on `<relay-addr>/p2p/<relay-id>/p2p-circuit` connects to the relay and accepts
+///   the circuits that the relay opens towards us.
+///
+/// The relayed streams are raw byte streams. Just like for any other transport, they are
+/// normally upgraded with an encryption and a multiplexing protocol afterwards.
+#[derive(Debug, Clone)]
+pub struct RelayTransport<TTrans> {
+    /// The local peer, advertised to the relay as the source of the circuits.
+    local_peer_id: PeerId,
+    /// Transport used to reach the relays.
+    inner: TTrans,
+}
+
+impl<TTrans> RelayTransport<TTrans> {
+    /// Creates a new `RelayTransport` on top of the given transport.
+    pub fn new(local_peer_id: PeerId, inner: TTrans) -> Self {
+        RelayTransport {
+            local_peer_id,
+            inner,
+        }
+    }
+}
+
+impl<TTrans, TMuxer> Transport01 for RelayTransport<TTrans>
+where
+    TTrans: Transport<Output = (PeerId, TMuxer)>,
+    TTrans::Error: 'static,
+    TMuxer: StreamMuxer,
+{
+    type Output = RelayedStream<TMuxer>;
+    type Error = RelayTransportError<TTrans::Error>;
+    type Listener = RelayListener<<Compat03As01<TTrans> as Transport01>::Dial, TMuxer>;
+    type ListenerUpgrade = FutureResult<Self::Output, Self::Error>;
+    type Dial = RelayDial<<Compat03As01<TTrans> as Transport01>::Dial, TMuxer>;
+
+    fn listen_on(self, addr: Multiaddr) -> Result<Self::Listener, TransportError<Self::Error>> {
+        let (relay_addr, relay) = match split_circuit_addr(&addr) {
+            Some((relay_addr, relay, rest)) => {
+                if rest.iter().next().is_some() {
+                    return Err(TransportError::MultiaddrNotSupported(addr));
+                }
+                (relay_addr, relay)
+            }
+            None => return Err(TransportError::MultiaddrNotSupported(addr)),
+        };
+
+        let dial = Transport01::dial(Compat03As01::new(self.inner), relay_addr)
+            .map_err(|err| err.map(RelayTransportError::Transport))?;
+        Ok(RelayListener {
+            listen_addr: addr,
+            relay,
+            state: ListenerState::Connecting(dial),
+            negotiating: Vec::new(),
+            tell_listen_addr: true,
+        })
+    }
+
+    fn dial(self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
+        let (relay_addr, relay, dst) = match split_circuit_addr(&addr) {
+            Some((relay_addr, relay, rest)) => {
+                let mut iter = rest.iter();
+                match (iter.next(), iter.next()) {
+                    (Some(Protocol::P2p(hash)), None) => match PeerId::from_multihash(hash) {
+                        Ok(dst) => (relay_addr, relay, dst),
+                        Err(_) => return Err(TransportError::MultiaddrNotSupported(addr)),
+                    },
+                    _ => return Err(TransportError::MultiaddrNotSupported(addr)),
+                }
+            }
+            None => return Err(TransportError::MultiaddrNotSupported(addr)),
+        };
+
+        let dial = Transport01::dial(Compat03As01::new(self.inner), relay_addr)
+            .map_err(|err| err.map(RelayTransportError::Transport))?;
+        Ok(RelayDial {
+            relay,
+            request: Some(HopRequest { src: self.local_peer_id, dst }),
+            state: DialState::Connecting(dial),
+        })
+    }
+
+    /// Circuits are always opened by the dialer through the relay, and the relayed stream can't
+    /// be the result of a simultaneous open, so dialing as the listener isn't supported.
+    fn dial_as_listener(self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
+        debug!("Refusing to dial {} as the listener through a relay", addr);
+        Err(TransportError::MultiaddrNotSupported(addr))
+    }
+}
+
+/// Splits `<relay-addr>/p2p/<relay-id>/p2p-circuit<rest>` into `<relay-addr>`, `<relay-id>`
+/// and `<rest>`.
+fn split_circuit_addr(addr: &Multiaddr) -> Option<(Multiaddr, PeerId, Multiaddr)> {
+    let mut iter = addr.iter();
+    let mut relay_addr = Multiaddr::empty();
+    loop {
+        match iter.next()? {
+            Protocol::P2pCircuit => break,
+            protocol => relay_addr.push(protocol),
+        }
+    }
+    let rest = iter.collect::<Multiaddr>();
+
+    let relay = match relay_addr.pop()? {
+        Protocol::P2p(hash) => PeerId::from_multihash(hash).ok()?,
+        _ => return None,
+    };
+
+    if relay_addr.iter().next().is_none() {
+        return None;
+    }
+
+    Some((relay_addr, relay, rest))
+}
+
+/// Future for dialing a peer through a relay.
+pub struct RelayDial<TDial, TMuxer>
+where
+    TMuxer: StreamMuxer,
+{
+    /// The relay we expect to reach.
+    relay: PeerId,
+    /// The request to send to the relay. Extracted when we start the negotiation.
+    request: Option<HopRequest>,
+    /// Current state.
+    state: DialState<TDial, TMuxer>,
+}
+
+enum DialState<TDial, TMuxer>
+where
+    TMuxer: StreamMuxer,
+{
+    /// Connecting to the relay.
+    Connecting(TDial),
+    /// Opening a substream with the relay.
+    OpeningSubstream(Compat03As01<OutboundSubstreamRefWrapFuture<Arc<TMuxer>>>),
+    /// Asking the relay to connect us to the destination.
+    Negotiating(Compat03As01<OutboundUpgradeApply<SubstreamRef<Arc<TMuxer>>, HopRequest>>),
+    /// Temporary state.
+    Poisoned,
+}
+
+impl<TDial, TMuxer, TErr> Future for RelayDial<TDial, TMuxer>
+where
+    TDial: Future<Item = (PeerId, TMuxer), Error = TErr>,
+    TMuxer: StreamMuxer,
+{
+    type Item = RelayedStream<TMuxer>;
+    type Error = RelayTransportError<TErr>;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        loop {
+            match mem::replace(&mut self.state, DialState::Poisoned) {
+                DialState::Connecting(mut dial) => match dial.poll().map_err(RelayTransportError::Transport)? {
+                    Async::Ready((peer_id, muxer)) => {
+                        if peer_id != self.relay {
+                            return Err(RelayTransportError::WrongRelay(peer_id));
+                        }
+                        let muxer = Arc::new(muxer);
+                        self.state = DialState::OpeningSubstream(Compat03As01::new(muxing::outbound_from_ref_and_wrap(muxer)));
+                    }
+                    Async::NotReady => {
+                        self.state = DialState::Connecting(dial);
+                        return Ok(Async::NotReady);
+                    }
+                },
+                DialState::OpeningSubstream(mut open) => match open.poll() {
+                    Ok(Async::Ready(substream)) => {
+                        let request = self.request.take().expect("request is only taken once");
+                        self.state = DialState::Negotiating(Compat03As01::new(upgrade::apply_outbound(substream, request)));
+                    }
+                    Ok(Async::NotReady) => {
+                        self.state = DialState::OpeningSubstream(open);
+                        return Ok(Async::NotReady);
+                    }
+                    Err(err) => return Err(RelayTransportError::Muxer(err.into())),
+                },
+                DialState::Negotiating(mut negotiation) => match negotiation.poll().map_err(RelayTransportError::Upgrade)? {
+                    Async::Ready(stream) => return Ok(Async::Ready(stream)),
+                    Async::NotReady => {
+                        self.state = DialState::Negotiating(negotiation);
+                        return Ok(Async::NotReady);
+                    }
+                },
+                DialState::Poisoned => panic!("RelayDial polled after completion"),
+            }
+        }
+    }
+}
+
+/// Listener for the circuits opened by a relay towards us.
+pub struct RelayListener<TDial, TMuxer>
+where
+    TMuxer: StreamMuxer,
+{
+    /// The address we listen on, ending with `/p2p-circuit`.
+    listen_addr: Multiaddr,
+    /// The relay we expect to reach.
+    relay: PeerId,
+    /// State of the connection to the relay.
+    state: ListenerState<TDial, TMuxer>,
+    /// Substreams opened by the relay whose request we are processing.
+    ///
+    /// These are all polled every time, rather than being put in a `FuturesUnordered`, as some
+    /// muxers only notify the task as a whole and not the individual substreams.
+    negotiating: Vec<IncomingCircuit<TMuxer>>,
+    /// If true, we still have to report `listen_addr`.
+    tell_listen_addr: bool,
+}
+
+enum ListenerState<TDial, TMuxer> {
+    /// Connecting to the relay.
+    Connecting(TDial),
+    /// Connected to the relay.
+    Connected(Arc<TMuxer>),
+    /// Temporary state.
+    Poisoned,
+}
+
+impl<TDial, TMuxer, TErr> Stream for RelayListener<TDial, TMuxer>
+where
+    TDial: Future<Item = (PeerId, TMuxer), Error = TErr>,
+    TMuxer: StreamMuxer,
+{
+    type Item = ListenerEvent<FutureResult<RelayedStream<TMuxer>, RelayTransportError<TErr>>>;
+    type Error = RelayTransportError<TErr>;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        let muxer = match mem::replace(&mut self.state, ListenerState::Poisoned) {
+            ListenerState::Connecting(mut dial) => match dial.poll().map_err(RelayTransportError::Transport)? {
+                Async::Ready((peer_id, muxer)) => {
+                    if peer_id != self.relay {
+                        return Err(RelayTransportError::WrongRelay(peer_id));
+                    }
+                    Arc::new(muxer)
+                }
+                Async::NotReady => {
+                    self.state = ListenerState::Connecting(dial);
+                    return Ok(Async::NotReady);
+                }
+            },
+            ListenerState::Connected(muxer) => muxer,
+            ListenerState::Poisoned => panic!("RelayListener polled after an error"),
+        };
+        self.state = ListenerState::Connected(muxer.clone());
+
+        if self.tell_listen_addr {
+            self.tell_listen_addr = false;
+            return Ok(Async::Ready(Some(ListenerEvent::NewAddress(self.listen_addr.clone()))));
+        }
+
+        // Accept the substreams opened by the relay.
+        loop {
+            match compat::with_context(|cx| muxer.poll_inbound(cx)) {
+                task::Poll::Ready(Ok(substream)) => {
+                    let substream = muxing::substream_from_ref(muxer.clone(), substream);
+                    self.negotiating.push(IncomingCircuit {
+                        state: IncomingState::Negotiating(Compat03As01::new(upgrade::apply_inbound(substream, RelayListen::new()))),
+                    });
+                }
+                task::Poll::Pending => break,
+                task::Poll::Ready(Err(err)) => return Err(RelayTransportError::Muxer(err.into())),
+            }
+        }
+
+        let mut n = 0;
+        while n < self.negotiating.len() {
+            match self.negotiating[n].poll() {
+                Ok(Async::Ready((src, stream))) => {
+                    self.negotiating.swap_remove(n);
+                    let remote_addr = self.listen_addr.clone().with(Protocol::P2p(src.into()));
+                    return Ok(Async::Ready(Some(ListenerEvent::Upgrade {
+                        upgrade: future::ok(stream),
+                        listen_addr: self.listen_addr.clone(),
+                        remote_addr,
+                    })));
+                }
+                Ok(Async::NotReady) => n += 1,
+                Err(err) => {
+                    debug!("Failed to accept incoming circuit: {:?}", err);
+                    self.negotiating.swap_remove(n);
+                }
+            }
+        }
+
+        Ok(Async::NotReady)
+    }
+}
+
+/// Processing of a substream opened by the relay.
+struct IncomingCircuit<TMuxer>
+where
+    TMuxer: StreamMuxer,
+{
+    state: IncomingState<TMuxer>,
+}
+
+enum IncomingState<TMuxer>
+where
+    TMuxer: StreamMuxer,
+{
+    /// Reading the request.
+    Negotiating(Compat03As01<InboundUpgradeApply<SubstreamRef<Arc<TMuxer>>, RelayListen>>),
+    /// Accepting a `STOP` request coming from the given peer.
+    Accepting(PeerId, SendMessage<RelayedStream<TMuxer>>),
+    /// Refusing a `HOP` request, as we don't act as a relay here.
+    Denying(SendMessage<RelayedStream<TMuxer>>),
+    /// Temporary state.
+    Poisoned,
+}
+
+impl<TMuxer> Future for IncomingCircuit<TMuxer>
+where
+    TMuxer: StreamMuxer,
+{
+    type Item = (PeerId, RelayedStream<TMuxer>);
+    type Error = UpgradeError<RelayError>;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        loop {
+            match mem::replace(&mut self.state, IncomingState::Poisoned) {
+                IncomingState::Negotiating(mut negotiation) => match negotiation.poll()? {
+                    Async::Ready(RelayRemoteRequest::Stop(request)) => {
+                        let src = request.src_peer().clone();
+                        self.state = IncomingState::Accepting(src, request.accept());
+                    }
+                    Async::Ready(RelayRemoteRequest::Hop(request)) => {
+                        self.state = IncomingState::Denying(request.deny(Status::HopCantSpeakRelay));
+                    }
+                    Async::NotReady => {
+                        self.state = IncomingState::Negotiating(negotiation);
+                        return Ok(Async::NotReady);
+                    }
+                },
+                IncomingState::Accepting(src, mut accept) => match accept.poll() {
+                    Ok(Async::Ready(stream)) => return Ok(Async::Ready((src, stream))),
+                    Ok(Async::NotReady) => {
+                        self.state = IncomingState::Accepting(src, accept);
+                        return Ok(Async::NotReady);
+                    }
+                    Err(err) => return Err(UpgradeError::Apply(RelayError::Io(err))),
+                },
+                IncomingState::Denying(mut deny) => match deny.poll() {
+                    Ok(Async::NotReady) => {
+                        self.state = IncomingState::Denying(deny);
+                        return Ok(Async::NotReady);
+                    }
+                    Ok(Async::Ready(_)) => return Err(UpgradeError::Apply(RelayError::UnexpectedMessage)),
+                    Err(err) => return Err(UpgradeError::Apply(RelayError::Io(err))),
+                },
+                IncomingState::Poisoned => panic!("IncomingCircuit polled after completion"),
+            }
+        }
+    }
+}
+
+/// Error that can happen on the relay transport.
+#[derive(Debug)]
+pub enum RelayTransportError<TErr> {
+    /// Error in the transport used to reach the relay.
+    Transport(TErr),
+    /// The peer reached at the address of the relay is not the expected one.
+    WrongRelay(PeerId),
+    /// Error on the multiplexed connection with the relay.
+    Muxer(io::Error),
+    /// Error while performing the relay protocol.
+    Upgrade(UpgradeError<RelayError>),
+}
+
+impl<TErr> fmt::Display for RelayTransportError<TErr>
+where TErr: fmt::Display
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RelayTransportError::Transport(err) => write!(f, "{}", err),
+            RelayTransportError::WrongRelay(peer_id) => write!(f, "Reached {:?} instead of the relay", peer_id),
+            RelayTransportError::Muxer(err) => write!(f, "Error on the connection with the relay: {}", err),
+            RelayTransportError::Upgrade(err) => write!(f, "Relay protocol error: {}", err),
+        }
+    }
+}
+
+impl<TErr> error::Error for RelayTransportError<TErr>
+where TErr: error::Error + 'static
+{
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            RelayTransportError::Transport(err) => Some(err),
+            RelayTransportError::WrongRelay(_) => None,
+            RelayTransportError::Muxer(err) => Some(err),
+            RelayTransportError::Upgrade(err) => Some(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::split_circuit_addr;
+    use libp2p_core::{PeerId, multiaddr::{Multiaddr, Protocol}};
+
+    #[test]
+    fn split_dial_addr() {
+        let relay = PeerId::random();
+        let dst = PeerId::random();
+        let addr: Multiaddr = format!("/memory/1234/p2p/{}/p2p-circuit/p2p/{}", relay.to_base58(), dst.to_base58())
+            .parse()
+            .unwrap();
+
+        let (relay_addr, relay_id, rest) = split_circuit_addr(&addr).unwrap();
+        assert_eq!(relay_addr, "/memory/1234".parse::<Multiaddr>().unwrap());
+        assert_eq!(relay_id, relay);
+        assert_eq!(rest, Multiaddr::empty().with(Protocol::P2p(dst.into())));
+    }
+
+    #[test]
+    fn reject_addr_without_relay() {
+        let dst = PeerId::random();
+        let addr: Multiaddr = format!("/p2p-circuit/p2p/{}", dst.to_base58()).parse().unwrap();
+        assert!(split_circuit_addr(&addr).is_none());
+
+        let addr: Multiaddr = format!("/p2p/{}/p2p-circuit", dst.to_base58()).parse().unwrap();
+        assert!(split_circuit_addr(&addr).is_none());
+
+        let addr: Multiaddr = "/memory/1234".parse().unwrap();
+        assert!(split_circuit_addr(&addr).is_none());
+    }
+}
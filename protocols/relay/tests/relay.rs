@@ -0,0 +1,217 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use futures::{future, prelude::*, sync::oneshot};
+
+use futures03::TryFutureExt as _;
+use libp2p_core::{
+    PeerId,
+    Transport,
+    compat::Transport01,
+    identity,
+    multiaddr::{Multiaddr, Protocol},
+    muxing::StreamMuxerBox,
+    nodes::Substream,
+    transport::{MemoryTransport, TransportError, boxed::Boxed},
+    upgrade::{self, UpgradeError},
+};
+use libp2p_relay::{
+    Relay,
+    RelayConfig,
+    RelayTransport,
+    RelayTransportError,
+    protocol::{RelayError, Status},
+};
+use libp2p_secio::SecioConfig;
+use libp2p_swarm::Swarm;
+use libp2p_yamux as yamux;
+use std::io;
+use tokio::runtime::current_thread::Runtime;
+use tokio_io::io::{flush, read_exact, read_to_end, write_all};
+
+type TestTransport = Boxed<(PeerId, StreamMuxerBox), io::Error>;
+type RelaySwarm = Swarm<TestTransport, Relay<Substream<StreamMuxerBox>>>;
+
+fn build_transport(local_key: identity::Keypair) -> TestTransport {
+    MemoryTransport::default()
+        .with_upgrade(SecioConfig::new(local_key))
+        .and_then(move |out, endpoint| {
+            let peer_id = out.remote_key.into_peer_id();
+            let yamux = yamux::Config::default();
+            upgrade::apply(out.stream, yamux, endpoint)
+                .map_ok(|muxer| (peer_id, StreamMuxerBox::new(muxer)))
+        })
+        .map_err(|e| panic!("Failed to create transport: {:?}", e))
+        .boxed()
+}
+
+/// Builds a relay listening on a random memory address. Returns the swarm and the address
+/// to use as a prefix of the circuit addresses.
+fn build_relay(config: RelayConfig) -> (RelaySwarm, Multiaddr) {
+    let key = identity::Keypair::generate_ed25519();
+    let peer_id = key.public().into_peer_id();
+    let mut swarm = Swarm::new(build_transport(key), Relay::new(peer_id.clone(), config), peer_id.clone());
+
+    let port = 1 + rand::random::<u64>() % (u64::max_value() - 1);
+    let addr: Multiaddr = Protocol::Memory(port).into();
+    Swarm::listen_on(&mut swarm, addr.clone()).unwrap();
+
+    let circuit_addr = addr
+        .with(Protocol::P2p(peer_id.into()))
+        .with(Protocol::P2pCircuit);
+    (swarm, circuit_addr)
+}
+
+/// Spawns the relay on the runtime. The returned receiver is notified once the relay is
+/// connected to `peer_id`.
+fn spawn_relay(rt: &mut Runtime, mut swarm: RelaySwarm, peer_id: PeerId) -> oneshot::Receiver<()> {
+    let (tx, rx) = oneshot::channel();
+    let mut tx = Some(tx);
+    rt.spawn(future::poll_fn(move || -> Poll<(), ()> {
+        loop {
+            match swarm.poll().expect("Error while polling the relay") {
+                Async::Ready(Some(_)) => {}
+                Async::Ready(None) | Async::NotReady => break,
+            }
+        }
+        if Swarm::connection_info(&mut swarm, &peer_id).is_some() {
+            if let Some(tx) = tx.take() {
+                let _ = tx.send(());
+            }
+        }
+        Ok(Async::NotReady)
+    }));
+    rx
+}
+
+fn build_peer() -> (PeerId, RelayTransport<TestTransport>) {
+    let key = identity::Keypair::generate_ed25519();
+    let peer_id = key.public().into_peer_id();
+    (peer_id.clone(), RelayTransport::new(peer_id, build_transport(key)))
+}
+
+#[test]
+fn relayed_connection() {
+    let mut rt = Runtime::new().unwrap();
+    let (relay, circuit_addr) = build_relay(RelayConfig::default());
+    let (dst_id, dst_transport) = build_peer();
+    let (_, src_transport) = build_peer();
+
+    let listener = Transport01::listen_on(dst_transport, circuit_addr.clone()).unwrap();
+    let dst_connected = spawn_relay(&mut rt, relay, dst_id.clone());
+
+    rt.spawn(listener
+        .filter_map(|event| event.into_upgrade())
+        .into_future()
+        .map_err(|(err, _)| panic!("Listener error: {:?}", err))
+        .and_then(|(upgrade, _)| upgrade.expect("Listener closed").0.map_err(|err| panic!("{:?}", err)))
+        .and_then(|stream| read_exact(stream, [0; 5]))
+        .and_then(|(stream, buf)| {
+            assert_eq!(&buf, b"hello");
+            write_all(stream, b"world")
+        })
+        .and_then(|(stream, _)| flush(stream))
+        .map_err(|err| panic!("Destination error: {:?}", err))
+        // Keep the circuit open until the source is done with it, as closing the connection
+        // with the relay would discard the data that it didn't forward yet.
+        .and_then(|stream| read_to_end(stream, Vec::new()).then(|_| Ok(()))));
+
+    let dial_addr = circuit_addr.with(Protocol::P2p(dst_id.into()));
+    let src = dst_connected
+        .map_err(|_| panic!("Relay stopped"))
+        .and_then(move |()| Transport01::dial(src_transport, dial_addr).unwrap().map_err(|err| panic!("{:?}", err)))
+        .and_then(|stream| write_all(stream, b"hello"))
+        .and_then(|(stream, _)| flush(stream))
+        .and_then(|stream| read_exact(stream, [0; 5]))
+        .map(|(_, buf)| buf);
+
+    let answer = rt.block_on(src).unwrap();
+    assert_eq!(&answer, b"world");
+}
+
+#[test]
+fn refuse_unknown_destination() {
+    let mut rt = Runtime::new().unwrap();
+    let (relay, circuit_addr) = build_relay(RelayConfig::default());
+    let (_, src_transport) = build_peer();
+    // Nothing ever connects to the relay with this id, so we never get notified.
+    let _ = spawn_relay(&mut rt, relay, PeerId::random());
+
+    let dial_addr = circuit_addr.with(Protocol::P2p(PeerId::random().into()));
+    match rt.block_on(Transport01::dial(src_transport, dial_addr).unwrap()) {
+        Err(RelayTransportError::Upgrade(UpgradeError::Apply(RelayError::Refused(status)))) =>
+            assert_eq!(status, Status::HopNoConnToDst),
+        Err(err) => panic!("Unexpected error: {:?}", err),
+        Ok(_) => panic!("Circuit to an unknown peer was accepted"),
+    }
+}
+
+#[test]
+fn dial_as_listener_not_supported() {
+    let (_, circuit_addr) = build_relay(RelayConfig::default());
+    let (_, src_transport) = build_peer();
+
+    let dial_addr = circuit_addr.with(Protocol::P2p(PeerId::random().into()));
+    match Transport01::dial_as_listener(src_transport, dial_addr.clone()) {
+        Err(TransportError::MultiaddrNotSupported(addr)) => assert_eq!(addr, dial_addr),
+        Err(err) => panic!("Unexpected error: {:?}", err),
+        Ok(_) => panic!("Dialing through a relay as the listener was accepted"),
+    }
+}
+
+#[test]
+fn refuse_above_circuits_per_peer() {
+    let mut rt = Runtime::new().unwrap();
+    let mut config = RelayConfig::default();
+    config.set_max_circuits_per_peer(1);
+    let (relay, circuit_addr) = build_relay(config);
+    let (dst_id, dst_transport) = build_peer();
+    let (_, src1_transport) = build_peer();
+    let (_, src2_transport) = build_peer();
+
+    // Keep accepting circuits, and keep them open.
+    let listener = Transport01::listen_on(dst_transport, circuit_addr.clone()).unwrap();
+    let mut accepted = Vec::new();
+    rt.spawn(listener
+        .filter_map(|event| event.into_upgrade())
+        .and_then(|(upgrade, _)| upgrade)
+        .for_each(move |stream| {
+            accepted.push(stream);
+            Ok(())
+        })
+        .map_err(|err| panic!("Listener error: {:?}", err)));
+    let dst_connected = spawn_relay(&mut rt, relay, dst_id.clone());
+
+    let dial_addr = circuit_addr.with(Protocol::P2p(dst_id.into()));
+    let dial_addr2 = dial_addr.clone();
+    let first = dst_connected
+        .map_err(|_| panic!("Relay stopped"))
+        .and_then(move |()| Transport01::dial(src1_transport, dial_addr).unwrap());
+    let first = rt.block_on(first).expect("First circuit should be accepted");
+
+    match rt.block_on(Transport01::dial(src2_transport, dial_addr2).unwrap()) {
+        Err(RelayTransportError::Upgrade(UpgradeError::Apply(RelayError::Refused(status)))) =>
+            assert_eq!(status, Status::HopCantSpeakRelay),
+        Err(err) => panic!("Unexpected error: {:?}", err),
+        Ok(_) => panic!("Circuit above the limit was accepted"),
+    }
+
+    drop(first);
+}
@@ -0,0 +1,581 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use crate::protocol::{
+    Cookie,
+    ErrorCode,
+    Registration,
+    RendezvousCodec,
+    Request,
+    Response,
+    MAX_NAMESPACE_LENGTH,
+    PROTOCOL_NAME,
+};
+use crate::registrations::Registrations;
+use fnv::FnvHashMap;
+use futures::prelude::*;
+use libp2p_core::{ConnectedPoint, Multiaddr, PeerId, PeerRecord, clock, identity::Keypair, nodes::ConnectionId};
+use libp2p_request_response::{
+    RequestId,
+    RequestProtocol,
+    RequestResponse,
+    RequestResponseConfig,
+    RequestResponseEvent,
+    RequestResponseMessage,
+    ResponseChannel,
+};
+use libp2p_swarm::{
+    AddressSource,
+    NetworkBehaviourAction,
+    PollParameters,
+    ProtocolsHandler,
+    compat::NetworkBehaviour01
+};
+use log::debug;
+use std::{collections::VecDeque, error, fmt, iter, time::Duration};
+use tokio_io::{AsyncRead, AsyncWrite};
+use wasm_timer::Delay;
+
+/// Configuration for the rendezvous behaviour.
+#[derive(Debug, Clone)]
+pub struct RendezvousConfig {
+    server: bool,
+    default_ttl: Duration,
+    min_ttl: Duration,
+    max_ttl: Duration,
+    max_discover_limit: u64,
+    request_timeout: Duration,
+}
+
+impl Default for RendezvousConfig {
+    fn default() -> Self {
+        RendezvousConfig {
+            server: false,
+            default_ttl: Duration::from_secs(2 * 60 * 60),
+            min_ttl: Duration::from_secs(2 * 60),
+            max_ttl: Duration::from_secs(72 * 60 * 60),
+            max_discover_limit: 1000,
+            request_timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RendezvousConfig {
+    /// Sets whether the node acts as a rendezvous server, storing the registrations of the
+    /// remotes and answering their discoveries. Otherwise, these requests are answered with
+    /// `ErrorCode::Unavailable`.
+    ///
+    /// The default is `false`.
+    pub fn set_server(&mut self, server: bool) -> &mut Self {
+        self.server = server;
+        self
+    }
+
+    /// Sets the TTL of the registrations that don't specify one.
+    ///
+    /// The default is 2 hours.
+    pub fn set_default_ttl(&mut self, ttl: Duration) -> &mut Self {
+        self.default_ttl = ttl;
+        self
+    }
+
+    /// Sets the minimum TTL of the registrations. Registrations with a smaller TTL are refused.
+    ///
+    /// The default is 2 minutes.
+    pub fn set_min_ttl(&mut self, ttl: Duration) -> &mut Self {
+        self.min_ttl = ttl;
+        self
+    }
+
+    /// Sets the maximum TTL of the registrations. Registrations with a larger TTL are refused.
+    ///
+    /// The default is 72 hours.
+    pub fn set_max_ttl(&mut self, ttl: Duration) -> &mut Self {
+        self.max_ttl = ttl;
+        self
+    }
+
+    /// Sets the maximum number of registrations returned by a discovery, whatever the limit
+    /// requested by the remote.
+    ///
+    /// The default is 1000.
+    pub fn set_max_discover_limit(&mut self, limit: u64) -> &mut Self {
+        self.max_discover_limit = limit;
+        self
+    }
+
+    /// Sets the timeout of the requests sent to rendezvous servers.
+    ///
+    /// The default is 30 seconds.
+    pub fn set_request_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.request_timeout = timeout;
+        self
+    }
+}
+
+/// Reason why a request to a rendezvous server failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RendezvousError {
+    /// The server refused the request.
+    Rejected(ErrorCode),
+    /// We have no address to register.
+    NoExternalAddresses,
+    /// Signing our addresses failed.
+    SigningFailed,
+    /// The request couldn't be sent, or no valid response was received.
+    Failed,
+}
+
+impl fmt::Display for RendezvousError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RendezvousError::Rejected(err) => write!(f, "Request rejected: {}", err),
+            RendezvousError::NoExternalAddresses => write!(f, "No address to register"),
+            RendezvousError::SigningFailed => write!(f, "Failed to sign the addresses"),
+            RendezvousError::Failed => write!(f, "Request failed"),
+        }
+    }
+}
+
+impl error::Error for RendezvousError {}
+
+/// Event generated by the `Rendezvous` behaviour.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RendezvousEvent {
+    /// We have registered with a rendezvous server.
+    Registered {
+        /// The rendezvous server.
+        rendezvous_node: PeerId,
+        /// The namespace we registered in.
+        namespace: String,
+        /// How long the registration remains valid.
+        ttl: Duration,
+    },
+    /// Registering with a rendezvous server failed.
+    RegisterFailed {
+        /// The rendezvous server.
+        rendezvous_node: PeerId,
+        /// The namespace we tried to register in.
+        namespace: String,
+        /// The reason of the failure.
+        error: RendezvousError,
+    },
+    /// A rendezvous server has answered a discovery.
+    ///
+    /// The addresses of the discovered peers are added to the peer store of the `Swarm`.
+    Discovered {
+        /// The rendezvous server.
+        rendezvous_node: PeerId,
+        /// The registrations, whose addresses are signed by the registered peers.
+        registrations: Vec<Registration>,
+        /// The cookie to pass to the next discovery in order to only get new registrations.
+        cookie: Cookie,
+    },
+    /// A discovery failed.
+    DiscoverFailed {
+        /// The rendezvous server.
+        rendezvous_node: PeerId,
+        /// The namespace of the discovery.
+        namespace: Option<String>,
+        /// The reason of the failure.
+        error: RendezvousError,
+    },
+    /// As a server, a remote has registered in a namespace.
+    PeerRegistered {
+        /// The remote.
+        peer: PeerId,
+        /// The new registration.
+        registration: Registration,
+    },
+    /// As a server, a remote has unregistered from a namespace.
+    PeerUnregistered {
+        /// The remote.
+        peer: PeerId,
+        /// The namespace the remote unregistered from.
+        namespace: String,
+    },
+    /// As a server, a registration has expired.
+    RegistrationExpired(Registration),
+}
+
+/// Network behaviour implementing both roles of the rendezvous protocol.
+///
+/// As a client, the node can register its addresses in namespaces of rendezvous servers, and
+/// discover the peers registered in these namespaces. The registered addresses are signed with
+/// the identity key of the node, and the signatures of the discovered addresses are verified.
+///
+/// If enabled with [`RendezvousConfig::set_server`], the node also stores the registrations of
+/// the remotes until they expire, and answers their discoveries.
+pub struct Rendezvous<TSubstream> {
+    /// Configuration of the behaviour.
+    config: RendezvousConfig,
+
+    /// The key used to sign our addresses.
+    keypair: Keypair,
+
+    /// The request-response behaviour carrying the messages.
+    inner: RequestResponse<TSubstream, RendezvousCodec>,
+
+    /// As a server, the registrations of the remotes.
+    registrations: Registrations,
+
+    /// As a server, fires when the next registration expires.
+    next_expiration: Option<Delay>,
+
+    /// Registrations waiting for the next call to `poll` to know our addresses. Contains the
+    /// namespace, the rendezvous server and the requested TTL.
+    pending_registrations: VecDeque<(String, PeerId, Option<Duration>)>,
+
+    /// Requests sent to rendezvous servers that didn't complete yet.
+    outbound: FnvHashMap<RequestId, (PeerId, Outbound)>,
+
+    /// Events that need to be yielded to the outside when polling.
+    events: VecDeque<NetworkBehaviourAction<RequestProtocol<RendezvousCodec>, RendezvousEvent>>,
+}
+
+/// Kind of a request sent to a rendezvous server.
+enum Outbound {
+    Register(String),
+    Unregister,
+    Discover(Option<String>),
+}
+
+impl<TSubstream> Rendezvous<TSubstream> {
+    /// Creates a new `Rendezvous` behaviour. The given key must be the identity key of the local
+    /// node, as it is used to sign the registered addresses.
+    pub fn new(keypair: Keypair, config: RendezvousConfig) -> Self {
+        let mut inner_config = RequestResponseConfig::default();
+        inner_config.set_request_timeout(config.request_timeout);
+        Rendezvous {
+            inner: RequestResponse::new(RendezvousCodec, iter::once(PROTOCOL_NAME), inner_config),
+            keypair,
+            registrations: Registrations::default(),
+            next_expiration: None,
+            pending_registrations: VecDeque::new(),
+            outbound: FnvHashMap::default(),
+            events: VecDeque::new(),
+            config,
+        }
+    }
+
+    /// Registers the local node in a namespace of the given rendezvous server.
+    ///
+    /// The registered addresses are the external addresses of the `Swarm`, or its listened
+    /// addresses if it has no external address. The server picks the TTL of the registration
+    /// if `ttl` is `None`. A `Registered` or `RegisterFailed` event is generated once the
+    /// server has answered.
+    pub fn register(&mut self, namespace: String, rendezvous_node: PeerId, ttl: Option<Duration>) {
+        self.pending_registrations.push_back((namespace, rendezvous_node, ttl));
+    }
+}
+
+impl<TSubstream> Rendezvous<TSubstream>
+where
+    TSubstream: AsyncRead + AsyncWrite + Send + 'static,
+{
+    /// Removes the registration of the local node in a namespace of the given rendezvous server.
+    pub fn unregister(&mut self, namespace: String, rendezvous_node: PeerId) {
+        self.pending_registrations.retain(|(ns, node, _)| *ns != namespace || *node != rendezvous_node);
+        let request_id = self.inner.send_request(&rendezvous_node, Request::Unregister { namespace });
+        self.outbound.insert(request_id, (rendezvous_node, Outbound::Unregister));
+    }
+
+    /// Asks a rendezvous server for the peers registered in a namespace, or in all the
+    /// namespaces if `None`.
+    ///
+    /// Passing the cookie of the previous discovery only returns the registrations made since
+    /// then. The server returns at most `limit` registrations, if specified. A `Discovered` or
+    /// `DiscoverFailed` event is generated once the server has answered.
+    pub fn discover(
+        &mut self,
+        namespace: Option<String>,
+        cookie: Option<Cookie>,
+        limit: Option<u64>,
+        rendezvous_node: PeerId
+    ) {
+        let request = Request::Discover { namespace: namespace.clone(), cookie, limit };
+        let request_id = self.inner.send_request(&rendezvous_node, request);
+        self.outbound.insert(request_id, (rendezvous_node, Outbound::Discover(namespace)));
+    }
+
+    /// Sends the pending registrations, now that we know our addresses.
+    fn send_registrations(&mut self, params: &mut impl PollParameters) {
+        if self.pending_registrations.is_empty() {
+            return;
+        }
+
+        let mut addresses = params.external_addresses().collect::<Vec<_>>();
+        if addresses.is_empty() {
+            addresses = params.listened_addresses().collect();
+        }
+
+        while let Some((namespace, rendezvous_node, ttl)) = self.pending_registrations.pop_front() {
+            let record = if addresses.is_empty() {
+                Err(RendezvousError::NoExternalAddresses)
+            } else {
+                PeerRecord::new(&self.keypair, addresses.clone())
+                    .map_err(|_| RendezvousError::SigningFailed)
+            };
+            match record {
+                Ok(record) => {
+                    let request = Request::Register { namespace: namespace.clone(), record, ttl };
+                    let request_id = self.inner.send_request(&rendezvous_node, request);
+                    self.outbound.insert(request_id, (rendezvous_node, Outbound::Register(namespace)));
+                }
+                Err(error) => {
+                    self.events.push_back(NetworkBehaviourAction::GenerateEvent(
+                        RendezvousEvent::RegisterFailed { rendezvous_node, namespace, error }
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Handles the response of a rendezvous server, or the absence of response.
+    fn on_response(&mut self, request_id: RequestId, response: Result<Response, RendezvousError>, params: &mut impl PollParameters) {
+        let (rendezvous_node, outbound) = match self.outbound.remove(&request_id) {
+            Some(outbound) => outbound,
+            None => return,
+        };
+
+        let event = match (outbound, response) {
+            (Outbound::Register(namespace), Ok(Response::Register(Ok(ttl)))) =>
+                RendezvousEvent::Registered { rendezvous_node, namespace, ttl },
+            (Outbound::Register(namespace), Ok(Response::Register(Err(err)))) =>
+                RendezvousEvent::RegisterFailed {
+                    rendezvous_node,
+                    namespace,
+                    error: RendezvousError::Rejected(err),
+                },
+            (Outbound::Register(namespace), _) =>
+                RendezvousEvent::RegisterFailed { rendezvous_node, namespace, error: RendezvousError::Failed },
+            (Outbound::Unregister, _) => return,
+            (Outbound::Discover(_), Ok(Response::Discover(Ok((registrations, cookie))))) => {
+                for registration in &registrations {
+                    let peer_id = registration.peer_id();
+                    if peer_id == *params.local_peer_id() {
+                        continue;
+                    }
+                    for address in registration.addresses() {
+                        params.peer_store_mut()
+                            .add_address(&peer_id, address.clone(), AddressSource::Other, Some(registration.ttl));
+                    }
+                }
+                RendezvousEvent::Discovered { rendezvous_node, registrations, cookie }
+            }
+            (Outbound::Discover(namespace), Ok(Response::Discover(Err(err)))) =>
+                RendezvousEvent::DiscoverFailed {
+                    rendezvous_node,
+                    namespace,
+                    error: RendezvousError::Rejected(err),
+                },
+            (Outbound::Discover(namespace), _) =>
+                RendezvousEvent::DiscoverFailed { rendezvous_node, namespace, error: RendezvousError::Failed },
+        };
+        self.events.push_back(NetworkBehaviourAction::GenerateEvent(event));
+    }
+
+    /// Handles a request from a remote.
+    fn on_request(&mut self, peer: PeerId, request: Request, channel: ResponseChannel<Response>) {
+        let response = match request {
+            Request::Unregister { namespace } => {
+                if self.config.server && self.registrations.remove(&namespace, &peer) {
+                    self.reset_expiration();
+                    self.events.push_back(NetworkBehaviourAction::GenerateEvent(
+                        RendezvousEvent::PeerUnregistered { peer, namespace }
+                    ));
+                }
+                Response::Unregister
+            }
+            Request::Register { .. } if !self.config.server =>
+                Response::Register(Err(ErrorCode::Unavailable)),
+            Request::Discover { .. } if !self.config.server =>
+                Response::Discover(Err(ErrorCode::Unavailable)),
+            Request::Register { namespace, record, ttl } =>
+                Response::Register(self.on_register(peer, namespace, record, ttl)),
+            Request::Discover { namespace, cookie, limit } => {
+                let result = if namespace.as_ref().map_or(false, |ns| !is_valid_namespace(ns)) {
+                    Err(ErrorCode::InvalidNamespace)
+                } else {
+                    let limit = limit.unwrap_or(self.config.max_discover_limit)
+                        .min(self.config.max_discover_limit);
+                    self.registrations.get(namespace.as_ref().map(|ns| ns.as_str()), cookie.as_ref(), limit as usize, clock::now())
+                };
+                Response::Discover(result)
+            }
+        };
+
+        if self.inner.send_response(channel, response).is_err() {
+            debug!("Rendezvous request timed out before being answered");
+        }
+    }
+
+    /// Handles a registration request from a remote.
+    fn on_register(&mut self, peer: PeerId, namespace: String, record: PeerRecord, ttl: Option<Duration>)
+        -> Result<Duration, ErrorCode>
+    {
+        if !is_valid_namespace(&namespace) {
+            return Err(ErrorCode::InvalidNamespace);
+        }
+        if *record.peer_id() != peer {
+            return Err(ErrorCode::NotAuthorized);
+        }
+        let ttl = ttl.unwrap_or(self.config.default_ttl);
+        if ttl < self.config.min_ttl || ttl > self.config.max_ttl {
+            return Err(ErrorCode::InvalidTtl);
+        }
+
+        let registration = Registration { namespace: namespace.clone(), record: record.clone(), ttl };
+        self.registrations.add(namespace, record, clock::now() + ttl);
+        self.reset_expiration();
+        self.events.push_back(NetworkBehaviourAction::GenerateEvent(
+            RendezvousEvent::PeerRegistered { peer, registration }
+        ));
+        Ok(ttl)
+    }
+
+    /// Sets the timer to fire when the next registration expires.
+    fn reset_expiration(&mut self) {
+        self.next_expiration = self.registrations.next_expiration().map(Delay::new);
+    }
+}
+
+/// Returns true if the namespace can be registered in.
+fn is_valid_namespace(namespace: &str) -> bool {
+    !namespace.is_empty() && namespace.len() <= MAX_NAMESPACE_LENGTH
+}
+
+impl<TSubstream> NetworkBehaviour01 for Rendezvous<TSubstream>
+where
+    TSubstream: AsyncRead + AsyncWrite + Send + 'static,
+{
+    type ProtocolsHandler = <RequestResponse<TSubstream, RendezvousCodec> as NetworkBehaviour01>::ProtocolsHandler;
+    type OutEvent = RendezvousEvent;
+
+    fn new_handler(&mut self) -> Self::ProtocolsHandler {
+        self.inner.new_handler()
+    }
+
+    fn addresses_of_peer(&mut self, peer: &PeerId) -> Vec<Multiaddr> {
+        self.inner.addresses_of_peer(peer)
+    }
+
+    fn inject_connected(&mut self, peer: PeerId, endpoint: ConnectedPoint) {
+        self.inner.inject_connected(peer, endpoint);
+    }
+
+    fn inject_disconnected(&mut self, peer: &PeerId, endpoint: ConnectedPoint) {
+        self.inner.inject_disconnected(peer, endpoint);
+    }
+
+    fn inject_connection_established(&mut self, peer: &PeerId, connection: &ConnectionId, endpoint: &ConnectedPoint) {
+        self.inner.inject_connection_established(peer, connection, endpoint);
+    }
+
+    fn inject_connection_closed(&mut self, peer: &PeerId, connection: &ConnectionId, endpoint: &ConnectedPoint) {
+        self.inner.inject_connection_closed(peer, connection, endpoint);
+    }
+
+    fn inject_node_event(
+        &mut self,
+        peer: PeerId,
+        connection: ConnectionId,
+        event: <Self::ProtocolsHandler as ProtocolsHandler>::OutEvent
+    ) {
+        self.inner.inject_node_event(peer, connection, event);
+    }
+
+    fn inject_addr_reach_failure(&mut self, peer: Option<&PeerId>, address: &Multiaddr, error: &dyn error::Error) {
+        self.inner.inject_addr_reach_failure(peer, address, error);
+    }
+
+    fn inject_dial_failure(&mut self, peer: &PeerId) {
+        self.inner.inject_dial_failure(peer);
+    }
+
+    fn poll(&mut self, params: &mut impl PollParameters)
+        -> Async<NetworkBehaviourAction<<Self::ProtocolsHandler as ProtocolsHandler>::InEvent, Self::OutEvent>>
+    {
+        self.send_registrations(params);
+
+        loop {
+            if let Some(event) = self.events.pop_front() {
+                return Async::Ready(event);
+            }
+
+            let expired = match &mut self.next_expiration {
+                Some(delay) => match delay.poll() {
+                    Ok(Async::NotReady) => false,
+                    Ok(Async::Ready(())) | Err(_) => true,
+                },
+                None => false,
+            };
+            if expired {
+                for registration in self.registrations.remove_expired(clock::now()) {
+                    self.events.push_back(NetworkBehaviourAction::GenerateEvent(
+                        RendezvousEvent::RegistrationExpired(registration)
+                    ));
+                }
+                self.reset_expiration();
+                continue;
+            }
+
+            match self.inner.poll(params) {
+                Async::Ready(NetworkBehaviourAction::GenerateEvent(event)) => match event {
+                    RequestResponseEvent::Message {
+                        peer,
+                        message: RequestResponseMessage::Request { request, channel, .. },
+                    } => self.on_request(peer, request, channel),
+                    RequestResponseEvent::Message {
+                        message: RequestResponseMessage::Response { request_id, response }, ..
+                    } => self.on_response(request_id, Ok(response), params),
+                    RequestResponseEvent::OutboundFailure { peer, request_id, error } => {
+                        debug!("Rendezvous request to {} failed: {}", peer, error);
+                        self.on_response(request_id, Err(RendezvousError::Failed), params);
+                    }
+                    RequestResponseEvent::InboundFailure { peer, error, .. } => {
+                        debug!("Rendezvous request from {} failed: {}", peer, error);
+                    }
+                },
+                Async::Ready(NetworkBehaviourAction::DialAddress { address }) =>
+                    return Async::Ready(NetworkBehaviourAction::DialAddress { address }),
+                Async::Ready(NetworkBehaviourAction::DialAddressAsListener { address }) =>
+                    return Async::Ready(NetworkBehaviourAction::DialAddressAsListener { address }),
+                Async::Ready(NetworkBehaviourAction::DialPeer { peer_id }) =>
+                    return Async::Ready(NetworkBehaviourAction::DialPeer { peer_id }),
+                Async::Ready(NetworkBehaviourAction::NotifyHandler { peer_id, handler, event }) =>
+                    return Async::Ready(NetworkBehaviourAction::NotifyHandler { peer_id, handler, event }),
+                Async::Ready(NetworkBehaviourAction::ReportObservedAddr { address }) =>
+                    return Async::Ready(NetworkBehaviourAction::ReportObservedAddr { address }),
+                Async::Ready(NetworkBehaviourAction::ReportExternalAddr { address }) =>
+                    return Async::Ready(NetworkBehaviourAction::ReportExternalAddr { address }),
+                Async::Ready(NetworkBehaviourAction::ReportExpiredExternalAddr { address }) =>
+                    return Async::Ready(NetworkBehaviourAction::ReportExpiredExternalAddr { address }),
+                Async::Ready(NetworkBehaviourAction::ReportMisbehaviour { peer_id, misbehaviour }) =>
+                    return Async::Ready(NetworkBehaviourAction::ReportMisbehaviour { peer_id, misbehaviour }),
+                Async::NotReady => {
+                    if self.events.is_empty() {
+                        return Async::NotReady;
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,49 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Implementation of the rendezvous protocol, `/rendezvous/1.0.0`, see also the
+//! [spec](https://github.com/libp2p/specs/tree/master/rendezvous).
+//!
+//! Rendezvous is a lightweight discovery mechanism for peers that can't find each other on the
+//! local network, and for which the Kademlia DHT is too heavy. Peers register their addresses
+//! in a namespace of a well-known rendezvous server, and discover the other peers registered in
+//! that namespace by asking the server.
+//!
+//! The [`Rendezvous`] network behaviour implements the client role, and optionally the server
+//! role. The registered addresses are held in a [`PeerRecord`](libp2p_core::PeerRecord), signed
+//! with the identity key of the registering peer, so that a server can't forge the addresses of
+//! the peers it hands out.
+
+mod behaviour;
+mod protocol;
+mod registrations;
+mod structs_proto;
+
+pub use self::behaviour::{Rendezvous, RendezvousConfig, RendezvousError, RendezvousEvent};
+pub use self::protocol::{
+    Cookie,
+    ErrorCode,
+    Registration,
+    RendezvousCodec,
+    Request,
+    Response,
+    MAX_NAMESPACE_LENGTH,
+    PROTOCOL_NAME,
+};
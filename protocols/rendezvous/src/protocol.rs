@@ -0,0 +1,531 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Messages of the rendezvous protocol and their encoding.
+//!
+//! A client opens a substream and sends a `REGISTER`, `UNREGISTER` or `DISCOVER` message. The
+//! server answers registrations with a `REGISTER_RESPONSE` message and discoveries with a
+//! `DISCOVER_RESPONSE` message. Unregistrations are acknowledged with an empty message, so that
+//! each request gets a response.
+
+use crate::structs_proto;
+use futures::prelude::*;
+use libp2p_core::{
+    Multiaddr,
+    PeerId,
+    PeerRecord,
+    SignedEnvelope,
+    upgrade::Negotiated
+};
+use libp2p_request_response::{
+    CodecFuture,
+    RequestResponseCodec,
+    read_length_prefixed,
+    write_length_prefixed,
+};
+use protobuf::{self, Message};
+use std::{fmt, io, time::Duration};
+use tokio_io::{AsyncRead, AsyncWrite};
+
+/// Name of the rendezvous protocol.
+pub const PROTOCOL_NAME: &[u8] = b"/rendezvous/1.0.0";
+
+/// Maximum length in bytes of a namespace.
+pub const MAX_NAMESPACE_LENGTH: usize = 255;
+
+/// Maximum size of a message of the protocol.
+const MAX_MESSAGE_SIZE: usize = 1024 * 1024;
+
+/// Position in the registrations of a server, returned by a discovery in order to fetch the
+/// following registrations with the next discovery.
+///
+/// A cookie is only valid for the namespace it was returned for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cookie {
+    id: u64,
+    namespace: Option<String>,
+}
+
+impl Cookie {
+    /// Builds a cookie pointing at the registration with the given identifier.
+    pub(crate) fn new(id: u64, namespace: Option<String>) -> Self {
+        Cookie { id, namespace }
+    }
+
+    /// Returns the identifier of the first registration that hasn't been discovered yet.
+    pub(crate) fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Returns the namespace the cookie is valid for, or `None` if it is valid for discoveries
+    /// of all the namespaces.
+    pub fn namespace(&self) -> Option<&str> {
+        self.namespace.as_ref().map(|ns| ns.as_str())
+    }
+
+    /// Encodes the cookie.
+    pub fn into_bytes(self) -> Vec<u8> {
+        let mut bytes = self.id.to_be_bytes().to_vec();
+        if let Some(namespace) = self.namespace {
+            bytes.extend_from_slice(namespace.as_bytes());
+        }
+        bytes
+    }
+
+    /// Decodes a cookie. Returns `None` if the bytes aren't a valid cookie.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Cookie> {
+        if bytes.len() < 8 {
+            return None;
+        }
+        let mut id = [0; 8];
+        id.copy_from_slice(&bytes[..8]);
+        let namespace = if bytes.len() > 8 {
+            Some(String::from_utf8(bytes[8..].to_vec()).ok()?)
+        } else {
+            None
+        };
+        Some(Cookie { id: u64::from_be_bytes(id), namespace })
+    }
+}
+
+/// A peer registered in a namespace, as returned by a discovery.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Registration {
+    /// The namespace the peer registered in.
+    pub namespace: String,
+    /// The addresses of the peer, signed by the peer.
+    pub record: PeerRecord,
+    /// How long the registration remains valid.
+    pub ttl: Duration,
+}
+
+impl Registration {
+    /// Returns the identity of the registered peer.
+    pub fn peer_id(&self) -> PeerId {
+        self.record.peer_id().clone()
+    }
+
+    /// Returns the addresses of the registered peer.
+    pub fn addresses(&self) -> &[Multiaddr] {
+        self.record.addresses()
+    }
+}
+
+/// Reason why a server refused a request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    /// The namespace is empty or too long.
+    InvalidNamespace,
+    /// The signed peer record is invalid or doesn't belong to the registering peer.
+    InvalidSignedPeerRecord,
+    /// The requested TTL is out of the bounds accepted by the server.
+    InvalidTtl,
+    /// The cookie is invalid, or doesn't match the namespace.
+    InvalidCookie,
+    /// The peer isn't allowed to perform the request.
+    NotAuthorized,
+    /// The server encountered an internal error.
+    InternalError,
+    /// The remote doesn't act as a rendezvous server.
+    Unavailable,
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ErrorCode::InvalidNamespace => write!(f, "Invalid namespace"),
+            ErrorCode::InvalidSignedPeerRecord => write!(f, "Invalid signed peer record"),
+            ErrorCode::InvalidTtl => write!(f, "Invalid TTL"),
+            ErrorCode::InvalidCookie => write!(f, "Invalid cookie"),
+            ErrorCode::NotAuthorized => write!(f, "Not authorized"),
+            ErrorCode::InternalError => write!(f, "Internal error"),
+            ErrorCode::Unavailable => write!(f, "Service unavailable"),
+        }
+    }
+}
+
+impl ErrorCode {
+    fn to_status(self) -> structs_proto::Message_ResponseStatus {
+        use structs_proto::Message_ResponseStatus::*;
+        match self {
+            ErrorCode::InvalidNamespace => E_INVALID_NAMESPACE,
+            ErrorCode::InvalidSignedPeerRecord => E_INVALID_SIGNED_PEER_RECORD,
+            ErrorCode::InvalidTtl => E_INVALID_TTL,
+            ErrorCode::InvalidCookie => E_INVALID_COOKIE,
+            ErrorCode::NotAuthorized => E_NOT_AUTHORIZED,
+            ErrorCode::InternalError => E_INTERNAL_ERROR,
+            ErrorCode::Unavailable => E_UNAVAILABLE,
+        }
+    }
+
+    /// Returns `None` for the `OK` status.
+    fn from_status(status: structs_proto::Message_ResponseStatus) -> Option<ErrorCode> {
+        use structs_proto::Message_ResponseStatus::*;
+        match status {
+            OK => None,
+            E_INVALID_NAMESPACE => Some(ErrorCode::InvalidNamespace),
+            E_INVALID_SIGNED_PEER_RECORD => Some(ErrorCode::InvalidSignedPeerRecord),
+            E_INVALID_TTL => Some(ErrorCode::InvalidTtl),
+            E_INVALID_COOKIE => Some(ErrorCode::InvalidCookie),
+            E_NOT_AUTHORIZED => Some(ErrorCode::NotAuthorized),
+            E_INTERNAL_ERROR => Some(ErrorCode::InternalError),
+            E_UNAVAILABLE => Some(ErrorCode::Unavailable),
+        }
+    }
+}
+
+/// Request sent by a client to a rendezvous server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Request {
+    /// Registers the sender in a namespace.
+    Register {
+        /// The namespace to register in.
+        namespace: String,
+        /// The addresses of the sender, signed by the sender.
+        record: PeerRecord,
+        /// How long the registration should remain valid. The server picks a default if `None`.
+        ttl: Option<Duration>,
+    },
+    /// Removes the registration of the sender in a namespace.
+    Unregister {
+        /// The namespace to unregister from.
+        namespace: String,
+    },
+    /// Asks for the peers registered in a namespace.
+    Discover {
+        /// The namespace to look into, or `None` for all the namespaces.
+        namespace: Option<String>,
+        /// The cookie returned by the previous discovery, in order to only get the new
+        /// registrations.
+        cookie: Option<Cookie>,
+        /// The maximum number of registrations to return.
+        limit: Option<u64>,
+    },
+}
+
+/// Response of a rendezvous server to a `Request`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Response {
+    /// Response to `Request::Register`, with the TTL of the registration if it succeeded.
+    Register(Result<Duration, ErrorCode>),
+    /// Response to `Request::Unregister`.
+    Unregister,
+    /// Response to `Request::Discover`, with the registrations and the cookie to pass to the
+    /// next discovery if it succeeded.
+    Discover(Result<(Vec<Registration>, Cookie), ErrorCode>),
+}
+
+impl Request {
+    /// Encodes the request as a protobuf message.
+    pub fn into_bytes(self) -> Vec<u8> {
+        let mut msg = structs_proto::Message::new();
+        match self {
+            Request::Register { namespace, record, ttl } => {
+                msg.set_field_type(structs_proto::Message_MessageType::REGISTER);
+                msg.set_register(register_to_proto(namespace, record, ttl));
+            }
+            Request::Unregister { namespace } => {
+                let mut unregister = structs_proto::Message_Unregister::new();
+                unregister.set_ns(namespace);
+                msg.set_field_type(structs_proto::Message_MessageType::UNREGISTER);
+                msg.set_unregister(unregister);
+            }
+            Request::Discover { namespace, cookie, limit } => {
+                let mut discover = structs_proto::Message_Discover::new();
+                if let Some(namespace) = namespace {
+                    discover.set_ns(namespace);
+                }
+                if let Some(cookie) = cookie {
+                    discover.set_cookie(cookie.into_bytes());
+                }
+                if let Some(limit) = limit {
+                    discover.set_limit(limit);
+                }
+                msg.set_field_type(structs_proto::Message_MessageType::DISCOVER);
+                msg.set_discover(discover);
+            }
+        }
+        msg.write_to_bytes().expect("writing protobuf to a Vec never fails; QED")
+    }
+
+    /// Decodes a protobuf message into a request.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Request, io::Error> {
+        let mut msg: structs_proto::Message = protobuf::parse_from_bytes(bytes)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        match msg.get_field_type() {
+            structs_proto::Message_MessageType::REGISTER if msg.has_register() => {
+                let (namespace, record, ttl) = register_from_proto(msg.take_register())?;
+                Ok(Request::Register { namespace, record, ttl })
+            }
+            structs_proto::Message_MessageType::UNREGISTER if msg.has_unregister() => {
+                Ok(Request::Unregister { namespace: msg.take_unregister().take_ns() })
+            }
+            structs_proto::Message_MessageType::DISCOVER if msg.has_discover() => {
+                let mut discover = msg.take_discover();
+                let namespace = if discover.has_ns() { Some(discover.take_ns()) } else { None };
+                let cookie = if discover.has_cookie() {
+                    let cookie = Cookie::from_bytes(discover.get_cookie())
+                        .ok_or_else(|| invalid_data("Invalid cookie"))?;
+                    Some(cookie)
+                } else {
+                    None
+                };
+                let limit = if discover.has_limit() { Some(discover.get_limit()) } else { None };
+                Ok(Request::Discover { namespace, cookie, limit })
+            }
+            _ => Err(invalid_data("Expected a REGISTER, UNREGISTER or DISCOVER message")),
+        }
+    }
+}
+
+impl Response {
+    /// Encodes the response as a protobuf message. The response to an unregistration is empty.
+    pub fn into_bytes(self) -> Vec<u8> {
+        let mut msg = structs_proto::Message::new();
+        match self {
+            Response::Register(result) => {
+                let mut response = structs_proto::Message_RegisterResponse::new();
+                match result {
+                    Ok(ttl) => {
+                        response.set_status(structs_proto::Message_ResponseStatus::OK);
+                        response.set_ttl(ttl.as_secs());
+                    }
+                    Err(err) => {
+                        response.set_status(err.to_status());
+                        response.set_statusText(err.to_string());
+                    }
+                }
+                msg.set_field_type(structs_proto::Message_MessageType::REGISTER_RESPONSE);
+                msg.set_registerResponse(response);
+            }
+            Response::Unregister => return Vec::new(),
+            Response::Discover(result) => {
+                let mut response = structs_proto::Message_DiscoverResponse::new();
+                match result {
+                    Ok((registrations, cookie)) => {
+                        response.set_status(structs_proto::Message_ResponseStatus::OK);
+                        for registration in registrations {
+                            response.mut_registrations().push(register_to_proto(
+                                registration.namespace,
+                                registration.record,
+                                Some(registration.ttl)
+                            ));
+                        }
+                        response.set_cookie(cookie.into_bytes());
+                    }
+                    Err(err) => {
+                        response.set_status(err.to_status());
+                        response.set_statusText(err.to_string());
+                    }
+                }
+                msg.set_field_type(structs_proto::Message_MessageType::DISCOVER_RESPONSE);
+                msg.set_discoverResponse(response);
+            }
+        }
+        msg.write_to_bytes().expect("writing protobuf to a Vec never fails; QED")
+    }
+
+    /// Decodes a protobuf message into a response. An empty message is the response to an
+    /// unregistration.
+    ///
+    /// Returns an error if one of the discovered registrations doesn't have a valid signature.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Response, io::Error> {
+        if bytes.is_empty() {
+            return Ok(Response::Unregister);
+        }
+        let mut msg: structs_proto::Message = protobuf::parse_from_bytes(bytes)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        match msg.get_field_type() {
+            structs_proto::Message_MessageType::REGISTER_RESPONSE if msg.has_registerResponse() => {
+                let response = msg.get_registerResponse();
+                let result = match ErrorCode::from_status(response.get_status()) {
+                    None => Ok(Duration::from_secs(response.get_ttl())),
+                    Some(err) => Err(err),
+                };
+                Ok(Response::Register(result))
+            }
+            structs_proto::Message_MessageType::DISCOVER_RESPONSE if msg.has_discoverResponse() => {
+                let mut response = msg.take_discoverResponse();
+                if let Some(err) = ErrorCode::from_status(response.get_status()) {
+                    return Ok(Response::Discover(Err(err)));
+                }
+                let cookie = Cookie::from_bytes(response.get_cookie())
+                    .ok_or_else(|| invalid_data("Invalid cookie"))?;
+                let registrations = response.take_registrations()
+                    .into_iter()
+                    .map(|register| {
+                        let (namespace, record, ttl) = register_from_proto(register)?;
+                        Ok(Registration { namespace, record, ttl: ttl.unwrap_or_default() })
+                    })
+                    .collect::<Result<Vec<_>, io::Error>>()?;
+                Ok(Response::Discover(Ok((registrations, cookie))))
+            }
+            _ => Err(invalid_data("Expected a REGISTER_RESPONSE or DISCOVER_RESPONSE message")),
+        }
+    }
+}
+
+fn register_to_proto(namespace: String, record: PeerRecord, ttl: Option<Duration>)
+    -> structs_proto::Message_Register
+{
+    let mut register = structs_proto::Message_Register::new();
+    register.set_ns(namespace);
+    register.set_signedPeerRecord(record.into_signed_envelope().into_protobuf_encoding());
+    if let Some(ttl) = ttl {
+        register.set_ttl(ttl.as_secs());
+    }
+    register
+}
+
+fn register_from_proto(mut register: structs_proto::Message_Register)
+    -> Result<(String, PeerRecord, Option<Duration>), io::Error>
+{
+    let envelope = SignedEnvelope::from_protobuf_encoding(register.get_signedPeerRecord())
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    let record = PeerRecord::from_signed_envelope(envelope)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    let ttl = if register.has_ttl() { Some(Duration::from_secs(register.get_ttl())) } else { None };
+    Ok((register.take_ns(), record, ttl))
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Codec of the rendezvous protocol, used by the request-response handler of `Rendezvous`.
+#[derive(Debug, Clone, Default)]
+pub struct RendezvousCodec;
+
+impl RequestResponseCodec for RendezvousCodec {
+    type Protocol = &'static [u8];
+    type Request = Request;
+    type Response = Response;
+
+    fn read_request<T>(&mut self, _: &Self::Protocol, io: Negotiated<T>)
+        -> CodecFuture<(Negotiated<T>, Request)>
+    where
+        T: AsyncRead + Send + 'static,
+    {
+        Box::new(read_length_prefixed(io, MAX_MESSAGE_SIZE)
+            .and_then(|(io, bytes)| Ok((io, Request::from_bytes(&bytes)?))))
+    }
+
+    fn read_response<T>(&mut self, _: &Self::Protocol, io: Negotiated<T>)
+        -> CodecFuture<(Negotiated<T>, Response)>
+    where
+        T: AsyncRead + Send + 'static,
+    {
+        Box::new(read_length_prefixed(io, MAX_MESSAGE_SIZE)
+            .and_then(|(io, bytes)| Ok((io, Response::from_bytes(&bytes)?))))
+    }
+
+    fn write_request<T>(&mut self, _: &Self::Protocol, io: Negotiated<T>, request: Request)
+        -> CodecFuture<Negotiated<T>>
+    where
+        T: AsyncWrite + Send + 'static,
+    {
+        Box::new(write_length_prefixed(io, request.into_bytes()))
+    }
+
+    fn write_response<T>(&mut self, _: &Self::Protocol, io: Negotiated<T>, response: Response)
+        -> CodecFuture<Negotiated<T>>
+    where
+        T: AsyncWrite + Send + 'static,
+    {
+        Box::new(write_length_prefixed(io, response.into_bytes()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use libp2p_core::identity::Keypair;
+
+    fn record(keypair: &Keypair) -> PeerRecord {
+        let addresses = vec![
+            "/ip4/1.2.3.4/tcp/1234".parse().unwrap(),
+            "/ip6/::1/udp/5678/quic".parse().unwrap(),
+        ];
+        PeerRecord::new(keypair, addresses).unwrap()
+    }
+
+    #[test]
+    fn tampered_records_are_refused() {
+        let keypair = Keypair::generate_ed25519();
+        let mut bytes = record(&keypair).into_signed_envelope().into_protobuf_encoding();
+        let pos = bytes.windows(4).position(|w| w == [1, 2, 3, 4]).unwrap();
+        bytes[pos..pos + 4].copy_from_slice(&[6, 6, 6, 6]);
+        let mut register = structs_proto::Message_Register::new();
+        register.set_ns("room".to_owned());
+        register.set_signedPeerRecord(bytes);
+        assert!(register_from_proto(register).is_err());
+    }
+
+    #[test]
+    fn records_of_other_kinds_are_refused() {
+        let keypair = Keypair::generate_ed25519();
+        let envelope = SignedEnvelope::new(&keypair, "libp2p-peer-record", b"/other".to_vec(), Vec::new()).unwrap();
+        let mut register = structs_proto::Message_Register::new();
+        register.set_ns("room".to_owned());
+        register.set_signedPeerRecord(envelope.into_protobuf_encoding());
+        assert!(register_from_proto(register).is_err());
+    }
+
+    #[test]
+    fn messages_roundtrip() {
+        let keypair = Keypair::generate_ed25519();
+        let requests = vec![
+            Request::Register {
+                namespace: "room".to_owned(),
+                record: record(&keypair),
+                ttl: Some(Duration::from_secs(3600)),
+            },
+            Request::Unregister { namespace: "room".to_owned() },
+            Request::Discover {
+                namespace: Some("room".to_owned()),
+                cookie: Some(Cookie::new(12, Some("room".to_owned()))),
+                limit: Some(10),
+            },
+            Request::Discover { namespace: None, cookie: None, limit: None },
+        ];
+        for request in requests {
+            assert_eq!(Request::from_bytes(&request.clone().into_bytes()).unwrap(), request);
+        }
+
+        let responses = vec![
+            Response::Register(Ok(Duration::from_secs(7200))),
+            Response::Register(Err(ErrorCode::InvalidTtl)),
+            Response::Unregister,
+            Response::Discover(Ok((
+                vec![Registration {
+                    namespace: "room".to_owned(),
+                    record: record(&keypair),
+                    ttl: Duration::from_secs(60),
+                }],
+                Cookie::new(3, None)
+            ))),
+            Response::Discover(Err(ErrorCode::InvalidCookie)),
+        ];
+        for response in responses {
+            assert_eq!(Response::from_bytes(&response.clone().into_bytes()).unwrap(), response);
+        }
+    }
+}
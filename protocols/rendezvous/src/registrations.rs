@@ -0,0 +1,196 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Storage of the registrations of a rendezvous server.
+
+use crate::protocol::{Cookie, ErrorCode, Registration};
+use fnv::FnvHashMap;
+use libp2p_core::{PeerId, PeerRecord};
+use std::{collections::{BTreeMap, BTreeSet}, time::Duration};
+use wasm_timer::Instant;
+
+/// The registrations of a rendezvous server, with their expiration.
+///
+/// Each registration gets an increasing identifier, which is what the cookies of the
+/// discoveries point to. Registering again in a namespace replaces the previous registration
+/// with a new one, which discoveries with a cookie therefore return again.
+#[derive(Default)]
+pub(crate) struct Registrations {
+    /// Identifier of the next registration.
+    next_id: u64,
+    /// The registrations, by identifier.
+    by_id: BTreeMap<u64, Entry>,
+    /// Identifier of the registration of each peer in each namespace.
+    by_peer: FnvHashMap<(String, PeerId), u64>,
+    /// Expiration of the registrations, in chronological order.
+    expirations: BTreeSet<(Instant, u64)>,
+}
+
+struct Entry {
+    namespace: String,
+    record: PeerRecord,
+    expires: Instant,
+}
+
+impl Entry {
+    fn to_registration(&self, now: Instant) -> Registration {
+        Registration {
+            namespace: self.namespace.clone(),
+            record: self.record.clone(),
+            ttl: if self.expires > now { self.expires - now } else { Duration::from_secs(0) },
+        }
+    }
+}
+
+impl Registrations {
+    /// Adds or replaces the registration of the signer of `record` in the given namespace.
+    pub(crate) fn add(&mut self, namespace: String, record: PeerRecord, expires: Instant) {
+        let peer_id = record.peer_id().clone();
+        self.remove(&namespace, &peer_id);
+        let id = self.next_id;
+        self.next_id += 1;
+        self.by_peer.insert((namespace.clone(), peer_id), id);
+        self.expirations.insert((expires, id));
+        self.by_id.insert(id, Entry { namespace, record, expires });
+    }
+
+    /// Removes the registration of a peer in a namespace. Returns true if it existed.
+    pub(crate) fn remove(&mut self, namespace: &str, peer_id: &PeerId) -> bool {
+        match self.by_peer.remove(&(namespace.to_owned(), peer_id.clone())) {
+            Some(id) => {
+                if let Some(entry) = self.by_id.remove(&id) {
+                    self.expirations.remove(&(entry.expires, id));
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns up to `limit` registrations in the given namespace, or in all the namespaces if
+    /// `None`, that follow the registrations already returned with `cookie`.
+    pub(crate) fn get(&self, namespace: Option<&str>, cookie: Option<&Cookie>, limit: usize, now: Instant)
+        -> Result<(Vec<Registration>, Cookie), ErrorCode>
+    {
+        let mut next = match cookie {
+            Some(cookie) if cookie.namespace() != namespace => return Err(ErrorCode::InvalidCookie),
+            Some(cookie) => cookie.id(),
+            None => 0,
+        };
+
+        let mut registrations = Vec::new();
+        for (id, entry) in self.by_id.range(next..) {
+            if registrations.len() >= limit {
+                break;
+            }
+            if namespace.map_or(false, |ns| ns != entry.namespace) || entry.expires <= now {
+                continue;
+            }
+            registrations.push(entry.to_registration(now));
+            next = id + 1;
+        }
+
+        Ok((registrations, Cookie::new(next, namespace.map(str::to_owned))))
+    }
+
+    /// Removes and returns the registrations that expired.
+    pub(crate) fn remove_expired(&mut self, now: Instant) -> Vec<Registration> {
+        let mut expired = Vec::new();
+        while let Some(&(expires, id)) = self.expirations.iter().next() {
+            if expires > now {
+                break;
+            }
+            self.expirations.remove(&(expires, id));
+            if let Some(entry) = self.by_id.remove(&id) {
+                self.by_peer.remove(&(entry.namespace.clone(), entry.record.peer_id().clone()));
+                expired.push(entry.to_registration(now));
+            }
+        }
+        expired
+    }
+
+    /// Returns when the next registration expires, if any.
+    pub(crate) fn next_expiration(&self) -> Option<Instant> {
+        self.expirations.iter().next().map(|(expires, _)| *expires)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libp2p_core::identity::Keypair;
+
+    fn record() -> PeerRecord {
+        let keypair = Keypair::generate_ed25519();
+        PeerRecord::new(&keypair, vec!["/ip4/1.2.3.4/tcp/1".parse().unwrap()]).unwrap()
+    }
+
+    #[test]
+    fn discovery_is_paginated() {
+        let now = Instant::now();
+        let expires = now + Duration::from_secs(60);
+        let mut registrations = Registrations::default();
+        let records = (0..5).map(|_| record()).collect::<Vec<_>>();
+        for record in &records {
+            registrations.add("room".to_owned(), record.clone(), expires);
+        }
+        registrations.add("other".to_owned(), record(), expires);
+
+        let (page, cookie) = registrations.get(Some("room"), None, 3, now).unwrap();
+        assert_eq!(page.iter().map(|r| &r.record).collect::<Vec<_>>(), records.iter().take(3).collect::<Vec<_>>());
+        let (page, cookie) = registrations.get(Some("room"), Some(&cookie), 3, now).unwrap();
+        assert_eq!(page.iter().map(|r| &r.record).collect::<Vec<_>>(), records.iter().skip(3).collect::<Vec<_>>());
+        let (page, cookie) = registrations.get(Some("room"), Some(&cookie), 3, now).unwrap();
+        assert!(page.is_empty());
+
+        // Registering again makes the registration appear after the cookie.
+        registrations.add("room".to_owned(), records[0].clone(), expires);
+        let (page, _) = registrations.get(Some("room"), Some(&cookie), 3, now).unwrap();
+        assert_eq!(page.len(), 1);
+        assert_eq!(registrations.get(None, None, 10, now).unwrap().0.len(), 6);
+
+        assert_eq!(registrations.get(Some("other"), Some(&cookie), 3, now), Err(ErrorCode::InvalidCookie));
+    }
+
+    #[test]
+    fn registrations_expire() {
+        let now = Instant::now();
+        let mut registrations = Registrations::default();
+        let short = record();
+        registrations.add("room".to_owned(), short.clone(), now + Duration::from_secs(10));
+        registrations.add("room".to_owned(), record(), now + Duration::from_secs(20));
+        assert_eq!(registrations.next_expiration(), Some(now + Duration::from_secs(10)));
+
+        let later = now + Duration::from_secs(15);
+        assert_eq!(registrations.get(Some("room"), None, 10, later).unwrap().0.len(), 1);
+        let expired = registrations.remove_expired(later);
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].record, short);
+        assert_eq!(registrations.next_expiration(), Some(now + Duration::from_secs(20)));
+
+        assert!(!registrations.remove("room", short.peer_id()));
+        assert!(registrations.remove("room", &record_peer(&registrations)));
+        assert_eq!(registrations.next_expiration(), None);
+    }
+
+    fn record_peer(registrations: &Registrations) -> PeerId {
+        registrations.by_peer.keys().next().unwrap().1.clone()
+    }
+}
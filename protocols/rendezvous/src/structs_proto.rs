@@ -0,0 +1,1958 @@
+// This file is generated by rust-protobuf 2.3.0. Do not edit
+// @generated
+
+// https://github.com/Manishearth/rust-clippy/issues/702
+#![allow(unknown_lints)]
+#![allow(clippy::all)]
+
+#![cfg_attr(rustfmt, rustfmt_skip)]
+
+#![allow(box_pointers)]
+#![allow(dead_code)]
+#![allow(missing_docs)]
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+#![allow(non_upper_case_globals)]
+#![allow(trivial_casts)]
+#![allow(unsafe_code)]
+#![allow(unused_imports)]
+#![allow(unused_results)]
+
+use protobuf::Message as Message_imported_for_functions;
+use protobuf::ProtobufEnum as ProtobufEnum_imported_for_functions;
+
+#[derive(PartialEq,Clone,Default)]
+pub struct Message {
+    // message fields
+    field_type: ::std::option::Option<Message_MessageType>,
+    register: ::protobuf::SingularPtrField<Message_Register>,
+    registerResponse: ::protobuf::SingularPtrField<Message_RegisterResponse>,
+    unregister: ::protobuf::SingularPtrField<Message_Unregister>,
+    discover: ::protobuf::SingularPtrField<Message_Discover>,
+    discoverResponse: ::protobuf::SingularPtrField<Message_DiscoverResponse>,
+    // special fields
+    pub unknown_fields: ::protobuf::UnknownFields,
+    pub cached_size: ::protobuf::CachedSize,
+}
+
+impl Message {
+    pub fn new() -> Message {
+        ::std::default::Default::default()
+    }
+
+    // optional .structs.Message.MessageType type = 1;
+
+    pub fn clear_field_type(&mut self) {
+        self.field_type = ::std::option::Option::None;
+    }
+
+    pub fn has_field_type(&self) -> bool {
+        self.field_type.is_some()
+    }
+
+    // Param is passed by value, moved
+    pub fn set_field_type(&mut self, v: Message_MessageType) {
+        self.field_type = ::std::option::Option::Some(v);
+    }
+
+    pub fn get_field_type(&self) -> Message_MessageType {
+        self.field_type.unwrap_or(Message_MessageType::REGISTER)
+    }
+
+    // optional .structs.Message.Register register = 2;
+
+    pub fn clear_register(&mut self) {
+        self.register.clear();
+    }
+
+    pub fn has_register(&self) -> bool {
+        self.register.is_some()
+    }
+
+    // Param is passed by value, moved
+    pub fn set_register(&mut self, v: Message_Register) {
+        self.register = ::protobuf::SingularPtrField::some(v);
+    }
+
+    // Mutable pointer to the field.
+    // If field is not initialized, it is initialized with default value first.
+    pub fn mut_register(&mut self) -> &mut Message_Register {
+        if self.register.is_none() {
+            self.register.set_default();
+        }
+        self.register.as_mut().unwrap()
+    }
+
+    // Take field
+    pub fn take_register(&mut self) -> Message_Register {
+        self.register.take().unwrap_or_else(|| Message_Register::new())
+    }
+
+    pub fn get_register(&self) -> &Message_Register {
+        self.register.as_ref().unwrap_or_else(|| Message_Register::default_instance())
+    }
+
+    // optional .structs.Message.RegisterResponse registerResponse = 3;
+
+    pub fn clear_registerResponse(&mut self) {
+        self.registerResponse.clear();
+    }
+
+    pub fn has_registerResponse(&self) -> bool {
+        self.registerResponse.is_some()
+    }
+
+    // Param is passed by value, moved
+    pub fn set_registerResponse(&mut self, v: Message_RegisterResponse) {
+        self.registerResponse = ::protobuf::SingularPtrField::some(v);
+    }
+
+    // Mutable pointer to the field.
+    // If field is not initialized, it is initialized with default value first.
+    pub fn mut_registerResponse(&mut self) -> &mut Message_RegisterResponse {
+        if self.registerResponse.is_none() {
+            self.registerResponse.set_default();
+        }
+        self.registerResponse.as_mut().unwrap()
+    }
+
+    // Take field
+    pub fn take_registerResponse(&mut self) -> Message_RegisterResponse {
+        self.registerResponse.take().unwrap_or_else(|| Message_RegisterResponse::new())
+    }
+
+    pub fn get_registerResponse(&self) -> &Message_RegisterResponse {
+        self.registerResponse.as_ref().unwrap_or_else(|| Message_RegisterResponse::default_instance())
+    }
+
+    // optional .structs.Message.Unregister unregister = 4;
+
+    pub fn clear_unregister(&mut self) {
+        self.unregister.clear();
+    }
+
+    pub fn has_unregister(&self) -> bool {
+        self.unregister.is_some()
+    }
+
+    // Param is passed by value, moved
+    pub fn set_unregister(&mut self, v: Message_Unregister) {
+        self.unregister = ::protobuf::SingularPtrField::some(v);
+    }
+
+    // Mutable pointer to the field.
+    // If field is not initialized, it is initialized with default value first.
+    pub fn mut_unregister(&mut self) -> &mut Message_Unregister {
+        if self.unregister.is_none() {
+            self.unregister.set_default();
+        }
+        self.unregister.as_mut().unwrap()
+    }
+
+    // Take field
+    pub fn take_unregister(&mut self) -> Message_Unregister {
+        self.unregister.take().unwrap_or_else(|| Message_Unregister::new())
+    }
+
+    pub fn get_unregister(&self) -> &Message_Unregister {
+        self.unregister.as_ref().unwrap_or_else(|| Message_Unregister::default_instance())
+    }
+
+    // optional .structs.Message.Discover discover = 5;
+
+    pub fn clear_discover(&mut self) {
+        self.discover.clear();
+    }
+
+    pub fn has_discover(&self) -> bool {
+        self.discover.is_some()
+    }
+
+    // Param is passed by value, moved
+    pub fn set_discover(&mut self, v: Message_Discover) {
+        self.discover = ::protobuf::SingularPtrField::some(v);
+    }
+
+    // Mutable pointer to the field.
+    // If field is not initialized, it is initialized with default value first.
+    pub fn mut_discover(&mut self) -> &mut Message_Discover {
+        if self.discover.is_none() {
+            self.discover.set_default();
+        }
+        self.discover.as_mut().unwrap()
+    }
+
+    // Take field
+    pub fn take_discover(&mut self) -> Message_Discover {
+        self.discover.take().unwrap_or_else(|| Message_Discover::new())
+    }
+
+    pub fn get_discover(&self) -> &Message_Discover {
+        self.discover.as_ref().unwrap_or_else(|| Message_Discover::default_instance())
+    }
+
+    // optional .structs.Message.DiscoverResponse discoverResponse = 6;
+
+    pub fn clear_discoverResponse(&mut self) {
+        self.discoverResponse.clear();
+    }
+
+    pub fn has_discoverResponse(&self) -> bool {
+        self.discoverResponse.is_some()
+    }
+
+    // Param is passed by value, moved
+    pub fn set_discoverResponse(&mut self, v: Message_DiscoverResponse) {
+        self.discoverResponse = ::protobuf::SingularPtrField::some(v);
+    }
+
+    // Mutable pointer to the field.
+    // If field is not initialized, it is initialized with default value first.
+    pub fn mut_discoverResponse(&mut self) -> &mut Message_DiscoverResponse {
+        if self.discoverResponse.is_none() {
+            self.discoverResponse.set_default();
+        }
+        self.discoverResponse.as_mut().unwrap()
+    }
+
+    // Take field
+    pub fn take_discoverResponse(&mut self) -> Message_DiscoverResponse {
+        self.discoverResponse.take().unwrap_or_else(|| Message_DiscoverResponse::new())
+    }
+
+    pub fn get_discoverResponse(&self) -> &Message_DiscoverResponse {
+        self.discoverResponse.as_ref().unwrap_or_else(|| Message_DiscoverResponse::default_instance())
+    }
+}
+
+impl ::protobuf::Message for Message {
+    fn is_initialized(&self) -> bool {
+        for v in &self.register {
+            if !v.is_initialized() {
+                return false;
+            }
+        };
+        for v in &self.registerResponse {
+            if !v.is_initialized() {
+                return false;
+            }
+        };
+        for v in &self.unregister {
+            if !v.is_initialized() {
+                return false;
+            }
+        };
+        for v in &self.discover {
+            if !v.is_initialized() {
+                return false;
+            }
+        };
+        for v in &self.discoverResponse {
+            if !v.is_initialized() {
+                return false;
+            }
+        };
+        true
+    }
+
+    fn merge_from(&mut self, is: &mut ::protobuf::CodedInputStream<'_>) -> ::protobuf::ProtobufResult<()> {
+        while !is.eof()? {
+            let (field_number, wire_type) = is.read_tag_unpack()?;
+            match field_number {
+                1 => {
+                    ::protobuf::rt::read_proto2_enum_with_unknown_fields_into(wire_type, is, &mut self.field_type, 1, &mut self.unknown_fields)?
+                },
+                2 => {
+                    ::protobuf::rt::read_singular_message_into(wire_type, is, &mut self.register)?;
+                },
+                3 => {
+                    ::protobuf::rt::read_singular_message_into(wire_type, is, &mut self.registerResponse)?;
+                },
+                4 => {
+                    ::protobuf::rt::read_singular_message_into(wire_type, is, &mut self.unregister)?;
+                },
+                5 => {
+                    ::protobuf::rt::read_singular_message_into(wire_type, is, &mut self.discover)?;
+                },
+                6 => {
+                    ::protobuf::rt::read_singular_message_into(wire_type, is, &mut self.discoverResponse)?;
+                },
+                _ => {
+                    ::protobuf::rt::read_unknown_or_skip_group(field_number, wire_type, is, self.mut_unknown_fields())?;
+                },
+            };
+        }
+        ::std::result::Result::Ok(())
+    }
+
+    // Compute sizes of nested messages
+    #[allow(unused_variables)]
+    fn compute_size(&self) -> u32 {
+        let mut my_size = 0;
+        if let Some(v) = self.field_type {
+            my_size += ::protobuf::rt::enum_size(1, v);
+        }
+        if let Some(ref v) = self.register.as_ref() {
+            let len = v.compute_size();
+            my_size += 1 + ::protobuf::rt::compute_raw_varint32_size(len) + len;
+        }
+        if let Some(ref v) = self.registerResponse.as_ref() {
+            let len = v.compute_size();
+            my_size += 1 + ::protobuf::rt::compute_raw_varint32_size(len) + len;
+        }
+        if let Some(ref v) = self.unregister.as_ref() {
+            let len = v.compute_size();
+            my_size += 1 + ::protobuf::rt::compute_raw_varint32_size(len) + len;
+        }
+        if let Some(ref v) = self.discover.as_ref() {
+            let len = v.compute_size();
+            my_size += 1 + ::protobuf::rt::compute_raw_varint32_size(len) + len;
+        }
+        if let Some(ref v) = self.discoverResponse.as_ref() {
+            let len = v.compute_size();
+            my_size += 1 + ::protobuf::rt::compute_raw_varint32_size(len) + len;
+        }
+        my_size += ::protobuf::rt::unknown_fields_size(self.get_unknown_fields());
+        self.cached_size.set(my_size);
+        my_size
+    }
+
+    fn write_to_with_cached_sizes(&self, os: &mut ::protobuf::CodedOutputStream<'_>) -> ::protobuf::ProtobufResult<()> {
+        if let Some(v) = self.field_type {
+            os.write_enum(1, v.value())?;
+        }
+        if let Some(ref v) = self.register.as_ref() {
+            os.write_tag(2, ::protobuf::wire_format::WireTypeLengthDelimited)?;
+            os.write_raw_varint32(v.get_cached_size())?;
+            v.write_to_with_cached_sizes(os)?;
+        }
+        if let Some(ref v) = self.registerResponse.as_ref() {
+            os.write_tag(3, ::protobuf::wire_format::WireTypeLengthDelimited)?;
+            os.write_raw_varint32(v.get_cached_size())?;
+            v.write_to_with_cached_sizes(os)?;
+        }
+        if let Some(ref v) = self.unregister.as_ref() {
+            os.write_tag(4, ::protobuf::wire_format::WireTypeLengthDelimited)?;
+            os.write_raw_varint32(v.get_cached_size())?;
+            v.write_to_with_cached_sizes(os)?;
+        }
+        if let Some(ref v) = self.discover.as_ref() {
+            os.write_tag(5, ::protobuf::wire_format::WireTypeLengthDelimited)?;
+            os.write_raw_varint32(v.get_cached_size())?;
+            v.write_to_with_cached_sizes(os)?;
+        }
+        if let Some(ref v) = self.discoverResponse.as_ref() {
+            os.write_tag(6, ::protobuf::wire_format::WireTypeLengthDelimited)?;
+            os.write_raw_varint32(v.get_cached_size())?;
+            v.write_to_with_cached_sizes(os)?;
+        }
+        os.write_unknown_fields(self.get_unknown_fields())?;
+        ::std::result::Result::Ok(())
+    }
+
+    fn get_cached_size(&self) -> u32 {
+        self.cached_size.get()
+    }
+
+    fn get_unknown_fields(&self) -> &::protobuf::UnknownFields {
+        &self.unknown_fields
+    }
+
+    fn mut_unknown_fields(&mut self) -> &mut ::protobuf::UnknownFields {
+        &mut self.unknown_fields
+    }
+
+    fn as_any(&self) -> &dyn (::std::any::Any) {
+        self as &dyn (::std::any::Any)
+    }
+    fn as_any_mut(&mut self) -> &mut dyn (::std::any::Any) {
+        self as &mut dyn (::std::any::Any)
+    }
+    fn into_any(self: Box<Self>) -> ::std::boxed::Box<dyn (::std::any::Any)> {
+        self
+    }
+
+    fn descriptor(&self) -> &'static ::protobuf::reflect::MessageDescriptor {
+        Self::descriptor_static()
+    }
+
+    fn new() -> Message {
+        Message::new()
+    }
+
+    fn descriptor_static() -> &'static ::protobuf::reflect::MessageDescriptor {
+        static mut descriptor: ::protobuf::lazy::Lazy<::protobuf::reflect::MessageDescriptor> = ::protobuf::lazy::Lazy {
+            lock: ::protobuf::lazy::ONCE_INIT,
+            ptr: 0 as *const ::protobuf::reflect::MessageDescriptor,
+        };
+        unsafe {
+            descriptor.get(|| {
+                let mut fields = ::std::vec::Vec::new();
+                fields.push(::protobuf::reflect::accessor::make_option_accessor::<_, ::protobuf::types::ProtobufTypeEnum<Message_MessageType>>(
+                    "type",
+                    |m: &Message| { &m.field_type },
+                    |m: &mut Message| { &mut m.field_type },
+                ));
+                fields.push(::protobuf::reflect::accessor::make_singular_ptr_field_accessor::<_, ::protobuf::types::ProtobufTypeMessage<Message_Register>>(
+                    "register",
+                    |m: &Message| { &m.register },
+                    |m: &mut Message| { &mut m.register },
+                ));
+                fields.push(::protobuf::reflect::accessor::make_singular_ptr_field_accessor::<_, ::protobuf::types::ProtobufTypeMessage<Message_RegisterResponse>>(
+                    "registerResponse",
+                    |m: &Message| { &m.registerResponse },
+                    |m: &mut Message| { &mut m.registerResponse },
+                ));
+                fields.push(::protobuf::reflect::accessor::make_singular_ptr_field_accessor::<_, ::protobuf::types::ProtobufTypeMessage<Message_Unregister>>(
+                    "unregister",
+                    |m: &Message| { &m.unregister },
+                    |m: &mut Message| { &mut m.unregister },
+                ));
+                fields.push(::protobuf::reflect::accessor::make_singular_ptr_field_accessor::<_, ::protobuf::types::ProtobufTypeMessage<Message_Discover>>(
+                    "discover",
+                    |m: &Message| { &m.discover },
+                    |m: &mut Message| { &mut m.discover },
+                ));
+                fields.push(::protobuf::reflect::accessor::make_singular_ptr_field_accessor::<_, ::protobuf::types::ProtobufTypeMessage<Message_DiscoverResponse>>(
+                    "discoverResponse",
+                    |m: &Message| { &m.discoverResponse },
+                    |m: &mut Message| { &mut m.discoverResponse },
+                ));
+                ::protobuf::reflect::MessageDescriptor::new::<Message>(
+                    "Message",
+                    fields,
+                    file_descriptor_proto()
+                )
+            })
+        }
+    }
+
+    fn default_instance() -> &'static Message {
+        static mut instance: ::protobuf::lazy::Lazy<Message> = ::protobuf::lazy::Lazy {
+            lock: ::protobuf::lazy::ONCE_INIT,
+            ptr: 0 as *const Message,
+        };
+        unsafe {
+            instance.get(Message::new)
+        }
+    }
+}
+
+impl ::protobuf::Clear for Message {
+    fn clear(&mut self) {
+        self.clear_field_type();
+        self.clear_register();
+        self.clear_registerResponse();
+        self.clear_unregister();
+        self.clear_discover();
+        self.clear_discoverResponse();
+        self.unknown_fields.clear();
+    }
+}
+
+impl ::std::fmt::Debug for Message {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+        ::protobuf::text_format::fmt(self, f)
+    }
+}
+
+impl ::protobuf::reflect::ProtobufValue for Message {
+    fn as_ref(&self) -> ::protobuf::reflect::ProtobufValueRef<'_> {
+        ::protobuf::reflect::ProtobufValueRef::Message(self)
+    }
+}
+
+#[derive(PartialEq,Clone,Default)]
+pub struct Message_Register {
+    // message fields
+    ns: ::protobuf::SingularField<::std::string::String>,
+    signedPeerRecord: ::protobuf::SingularField<::std::vec::Vec<u8>>,
+    ttl: ::std::option::Option<u64>,
+    // special fields
+    pub unknown_fields: ::protobuf::UnknownFields,
+    pub cached_size: ::protobuf::CachedSize,
+}
+
+impl Message_Register {
+    pub fn new() -> Message_Register {
+        ::std::default::Default::default()
+    }
+
+    // optional string ns = 1;
+
+    pub fn clear_ns(&mut self) {
+        self.ns.clear();
+    }
+
+    pub fn has_ns(&self) -> bool {
+        self.ns.is_some()
+    }
+
+    // Param is passed by value, moved
+    pub fn set_ns(&mut self, v: ::std::string::String) {
+        self.ns = ::protobuf::SingularField::some(v);
+    }
+
+    // Mutable pointer to the field.
+    // If field is not initialized, it is initialized with default value first.
+    pub fn mut_ns(&mut self) -> &mut ::std::string::String {
+        if self.ns.is_none() {
+            self.ns.set_default();
+        }
+        self.ns.as_mut().unwrap()
+    }
+
+    // Take field
+    pub fn take_ns(&mut self) -> ::std::string::String {
+        self.ns.take().unwrap_or_else(|| ::std::string::String::new())
+    }
+
+    pub fn get_ns(&self) -> &str {
+        match self.ns.as_ref() {
+            Some(v) => &v,
+            None => "",
+        }
+    }
+
+    // optional bytes signedPeerRecord = 2;
+
+    pub fn clear_signedPeerRecord(&mut self) {
+        self.signedPeerRecord.clear();
+    }
+
+    pub fn has_signedPeerRecord(&self) -> bool {
+        self.signedPeerRecord.is_some()
+    }
+
+    // Param is passed by value, moved
+    pub fn set_signedPeerRecord(&mut self, v: ::std::vec::Vec<u8>) {
+        self.signedPeerRecord = ::protobuf::SingularField::some(v);
+    }
+
+    // Mutable pointer to the field.
+    // If field is not initialized, it is initialized with default value first.
+    pub fn mut_signedPeerRecord(&mut self) -> &mut ::std::vec::Vec<u8> {
+        if self.signedPeerRecord.is_none() {
+            self.signedPeerRecord.set_default();
+        }
+        self.signedPeerRecord.as_mut().unwrap()
+    }
+
+    // Take field
+    pub fn take_signedPeerRecord(&mut self) -> ::std::vec::Vec<u8> {
+        self.signedPeerRecord.take().unwrap_or_else(|| ::std::vec::Vec::new())
+    }
+
+    pub fn get_signedPeerRecord(&self) -> &[u8] {
+        match self.signedPeerRecord.as_ref() {
+            Some(v) => &v,
+            None => &[],
+        }
+    }
+
+    // optional uint64 ttl = 3;
+
+    pub fn clear_ttl(&mut self) {
+        self.ttl = ::std::option::Option::None;
+    }
+
+    pub fn has_ttl(&self) -> bool {
+        self.ttl.is_some()
+    }
+
+    // Param is passed by value, moved
+    pub fn set_ttl(&mut self, v: u64) {
+        self.ttl = ::std::option::Option::Some(v);
+    }
+
+    pub fn get_ttl(&self) -> u64 {
+        self.ttl.unwrap_or(0)
+    }
+}
+
+impl ::protobuf::Message for Message_Register {
+    fn is_initialized(&self) -> bool {
+        true
+    }
+
+    fn merge_from(&mut self, is: &mut ::protobuf::CodedInputStream<'_>) -> ::protobuf::ProtobufResult<()> {
+        while !is.eof()? {
+            let (field_number, wire_type) = is.read_tag_unpack()?;
+            match field_number {
+                1 => {
+                    ::protobuf::rt::read_singular_string_into(wire_type, is, &mut self.ns)?;
+                },
+                2 => {
+                    ::protobuf::rt::read_singular_bytes_into(wire_type, is, &mut self.signedPeerRecord)?;
+                },
+                3 => {
+                    if wire_type != ::protobuf::wire_format::WireTypeVarint {
+                        return ::std::result::Result::Err(::protobuf::rt::unexpected_wire_type(wire_type));
+                    }
+                    let tmp = is.read_uint64()?;
+                    self.ttl = ::std::option::Option::Some(tmp);
+                },
+                _ => {
+                    ::protobuf::rt::read_unknown_or_skip_group(field_number, wire_type, is, self.mut_unknown_fields())?;
+                },
+            };
+        }
+        ::std::result::Result::Ok(())
+    }
+
+    // Compute sizes of nested messages
+    #[allow(unused_variables)]
+    fn compute_size(&self) -> u32 {
+        let mut my_size = 0;
+        if let Some(ref v) = self.ns.as_ref() {
+            my_size += ::protobuf::rt::string_size(1, &v);
+        }
+        if let Some(ref v) = self.signedPeerRecord.as_ref() {
+            my_size += ::protobuf::rt::bytes_size(2, &v);
+        }
+        if let Some(v) = self.ttl {
+            my_size += ::protobuf::rt::value_size(3, v, ::protobuf::wire_format::WireTypeVarint);
+        }
+        my_size += ::protobuf::rt::unknown_fields_size(self.get_unknown_fields());
+        self.cached_size.set(my_size);
+        my_size
+    }
+
+    fn write_to_with_cached_sizes(&self, os: &mut ::protobuf::CodedOutputStream<'_>) -> ::protobuf::ProtobufResult<()> {
+        if let Some(ref v) = self.ns.as_ref() {
+            os.write_string(1, &v)?;
+        }
+        if let Some(ref v) = self.signedPeerRecord.as_ref() {
+            os.write_bytes(2, &v)?;
+        }
+        if let Some(v) = self.ttl {
+            os.write_uint64(3, v)?;
+        }
+        os.write_unknown_fields(self.get_unknown_fields())?;
+        ::std::result::Result::Ok(())
+    }
+
+    fn get_cached_size(&self) -> u32 {
+        self.cached_size.get()
+    }
+
+    fn get_unknown_fields(&self) -> &::protobuf::UnknownFields {
+        &self.unknown_fields
+    }
+
+    fn mut_unknown_fields(&mut self) -> &mut ::protobuf::UnknownFields {
+        &mut self.unknown_fields
+    }
+
+    fn as_any(&self) -> &dyn (::std::any::Any) {
+        self as &dyn (::std::any::Any)
+    }
+    fn as_any_mut(&mut self) -> &mut dyn (::std::any::Any) {
+        self as &mut dyn (::std::any::Any)
+    }
+    fn into_any(self: Box<Self>) -> ::std::boxed::Box<dyn (::std::any::Any)> {
+        self
+    }
+
+    fn descriptor(&self) -> &'static ::protobuf::reflect::MessageDescriptor {
+        Self::descriptor_static()
+    }
+
+    fn new() -> Message_Register {
+        Message_Register::new()
+    }
+
+    fn descriptor_static() -> &'static ::protobuf::reflect::MessageDescriptor {
+        static mut descriptor: ::protobuf::lazy::Lazy<::protobuf::reflect::MessageDescriptor> = ::protobuf::lazy::Lazy {
+            lock: ::protobuf::lazy::ONCE_INIT,
+            ptr: 0 as *const ::protobuf::reflect::MessageDescriptor,
+        };
+        unsafe {
+            descriptor.get(|| {
+                let mut fields = ::std::vec::Vec::new();
+                fields.push(::protobuf::reflect::accessor::make_singular_field_accessor::<_, ::protobuf::types::ProtobufTypeString>(
+                    "ns",
+                    |m: &Message_Register| { &m.ns },
+                    |m: &mut Message_Register| { &mut m.ns },
+                ));
+                fields.push(::protobuf::reflect::accessor::make_singular_field_accessor::<_, ::protobuf::types::ProtobufTypeBytes>(
+                    "signedPeerRecord",
+                    |m: &Message_Register| { &m.signedPeerRecord },
+                    |m: &mut Message_Register| { &mut m.signedPeerRecord },
+                ));
+                fields.push(::protobuf::reflect::accessor::make_option_accessor::<_, ::protobuf::types::ProtobufTypeUint64>(
+                    "ttl",
+                    |m: &Message_Register| { &m.ttl },
+                    |m: &mut Message_Register| { &mut m.ttl },
+                ));
+                ::protobuf::reflect::MessageDescriptor::new::<Message_Register>(
+                    "Message_Register",
+                    fields,
+                    file_descriptor_proto()
+                )
+            })
+        }
+    }
+
+    fn default_instance() -> &'static Message_Register {
+        static mut instance: ::protobuf::lazy::Lazy<Message_Register> = ::protobuf::lazy::Lazy {
+            lock: ::protobuf::lazy::ONCE_INIT,
+            ptr: 0 as *const Message_Register,
+        };
+        unsafe {
+            instance.get(Message_Register::new)
+        }
+    }
+}
+
+impl ::protobuf::Clear for Message_Register {
+    fn clear(&mut self) {
+        self.clear_ns();
+        self.clear_signedPeerRecord();
+        self.clear_ttl();
+        self.unknown_fields.clear();
+    }
+}
+
+impl ::std::fmt::Debug for Message_Register {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+        ::protobuf::text_format::fmt(self, f)
+    }
+}
+
+impl ::protobuf::reflect::ProtobufValue for Message_Register {
+    fn as_ref(&self) -> ::protobuf::reflect::ProtobufValueRef<'_> {
+        ::protobuf::reflect::ProtobufValueRef::Message(self)
+    }
+}
+
+#[derive(PartialEq,Clone,Default)]
+pub struct Message_RegisterResponse {
+    // message fields
+    status: ::std::option::Option<Message_ResponseStatus>,
+    statusText: ::protobuf::SingularField<::std::string::String>,
+    ttl: ::std::option::Option<u64>,
+    // special fields
+    pub unknown_fields: ::protobuf::UnknownFields,
+    pub cached_size: ::protobuf::CachedSize,
+}
+
+impl Message_RegisterResponse {
+    pub fn new() -> Message_RegisterResponse {
+        ::std::default::Default::default()
+    }
+
+    // optional .structs.Message.ResponseStatus status = 1;
+
+    pub fn clear_status(&mut self) {
+        self.status = ::std::option::Option::None;
+    }
+
+    pub fn has_status(&self) -> bool {
+        self.status.is_some()
+    }
+
+    // Param is passed by value, moved
+    pub fn set_status(&mut self, v: Message_ResponseStatus) {
+        self.status = ::std::option::Option::Some(v);
+    }
+
+    pub fn get_status(&self) -> Message_ResponseStatus {
+        self.status.unwrap_or(Message_ResponseStatus::OK)
+    }
+
+    // optional string statusText = 2;
+
+    pub fn clear_statusText(&mut self) {
+        self.statusText.clear();
+    }
+
+    pub fn has_statusText(&self) -> bool {
+        self.statusText.is_some()
+    }
+
+    // Param is passed by value, moved
+    pub fn set_statusText(&mut self, v: ::std::string::String) {
+        self.statusText = ::protobuf::SingularField::some(v);
+    }
+
+    // Mutable pointer to the field.
+    // If field is not initialized, it is initialized with default value first.
+    pub fn mut_statusText(&mut self) -> &mut ::std::string::String {
+        if self.statusText.is_none() {
+            self.statusText.set_default();
+        }
+        self.statusText.as_mut().unwrap()
+    }
+
+    // Take field
+    pub fn take_statusText(&mut self) -> ::std::string::String {
+        self.statusText.take().unwrap_or_else(|| ::std::string::String::new())
+    }
+
+    pub fn get_statusText(&self) -> &str {
+        match self.statusText.as_ref() {
+            Some(v) => &v,
+            None => "",
+        }
+    }
+
+    // optional uint64 ttl = 3;
+
+    pub fn clear_ttl(&mut self) {
+        self.ttl = ::std::option::Option::None;
+    }
+
+    pub fn has_ttl(&self) -> bool {
+        self.ttl.is_some()
+    }
+
+    // Param is passed by value, moved
+    pub fn set_ttl(&mut self, v: u64) {
+        self.ttl = ::std::option::Option::Some(v);
+    }
+
+    pub fn get_ttl(&self) -> u64 {
+        self.ttl.unwrap_or(0)
+    }
+}
+
+impl ::protobuf::Message for Message_RegisterResponse {
+    fn is_initialized(&self) -> bool {
+        true
+    }
+
+    fn merge_from(&mut self, is: &mut ::protobuf::CodedInputStream<'_>) -> ::protobuf::ProtobufResult<()> {
+        while !is.eof()? {
+            let (field_number, wire_type) = is.read_tag_unpack()?;
+            match field_number {
+                1 => {
+                    ::protobuf::rt::read_proto2_enum_with_unknown_fields_into(wire_type, is, &mut self.status, 1, &mut self.unknown_fields)?
+                },
+                2 => {
+                    ::protobuf::rt::read_singular_string_into(wire_type, is, &mut self.statusText)?;
+                },
+                3 => {
+                    if wire_type != ::protobuf::wire_format::WireTypeVarint {
+                        return ::std::result::Result::Err(::protobuf::rt::unexpected_wire_type(wire_type));
+                    }
+                    let tmp = is.read_uint64()?;
+                    self.ttl = ::std::option::Option::Some(tmp);
+                },
+                _ => {
+                    ::protobuf::rt::read_unknown_or_skip_group(field_number, wire_type, is, self.mut_unknown_fields())?;
+                },
+            };
+        }
+        ::std::result::Result::Ok(())
+    }
+
+    // Compute sizes of nested messages
+    #[allow(unused_variables)]
+    fn compute_size(&self) -> u32 {
+        let mut my_size = 0;
+        if let Some(v) = self.status {
+            my_size += ::protobuf::rt::enum_size(1, v);
+        }
+        if let Some(ref v) = self.statusText.as_ref() {
+            my_size += ::protobuf::rt::string_size(2, &v);
+        }
+        if let Some(v) = self.ttl {
+            my_size += ::protobuf::rt::value_size(3, v, ::protobuf::wire_format::WireTypeVarint);
+        }
+        my_size += ::protobuf::rt::unknown_fields_size(self.get_unknown_fields());
+        self.cached_size.set(my_size);
+        my_size
+    }
+
+    fn write_to_with_cached_sizes(&self, os: &mut ::protobuf::CodedOutputStream<'_>) -> ::protobuf::ProtobufResult<()> {
+        if let Some(v) = self.status {
+            os.write_enum(1, v.value())?;
+        }
+        if let Some(ref v) = self.statusText.as_ref() {
+            os.write_string(2, &v)?;
+        }
+        if let Some(v) = self.ttl {
+            os.write_uint64(3, v)?;
+        }
+        os.write_unknown_fields(self.get_unknown_fields())?;
+        ::std::result::Result::Ok(())
+    }
+
+    fn get_cached_size(&self) -> u32 {
+        self.cached_size.get()
+    }
+
+    fn get_unknown_fields(&self) -> &::protobuf::UnknownFields {
+        &self.unknown_fields
+    }
+
+    fn mut_unknown_fields(&mut self) -> &mut ::protobuf::UnknownFields {
+        &mut self.unknown_fields
+    }
+
+    fn as_any(&self) -> &dyn (::std::any::Any) {
+        self as &dyn (::std::any::Any)
+    }
+    fn as_any_mut(&mut self) -> &mut dyn (::std::any::Any) {
+        self as &mut dyn (::std::any::Any)
+    }
+    fn into_any(self: Box<Self>) -> ::std::boxed::Box<dyn (::std::any::Any)> {
+        self
+    }
+
+    fn descriptor(&self) -> &'static ::protobuf::reflect::MessageDescriptor {
+        Self::descriptor_static()
+    }
+
+    fn new() -> Message_RegisterResponse {
+        Message_RegisterResponse::new()
+    }
+
+    fn descriptor_static() -> &'static ::protobuf::reflect::MessageDescriptor {
+        static mut descriptor: ::protobuf::lazy::Lazy<::protobuf::reflect::MessageDescriptor> = ::protobuf::lazy::Lazy {
+            lock: ::protobuf::lazy::ONCE_INIT,
+            ptr: 0 as *const ::protobuf::reflect::MessageDescriptor,
+        };
+        unsafe {
+            descriptor.get(|| {
+                let mut fields = ::std::vec::Vec::new();
+                fields.push(::protobuf::reflect::accessor::make_option_accessor::<_, ::protobuf::types::ProtobufTypeEnum<Message_ResponseStatus>>(
+                    "status",
+                    |m: &Message_RegisterResponse| { &m.status },
+                    |m: &mut Message_RegisterResponse| { &mut m.status },
+                ));
+                fields.push(::protobuf::reflect::accessor::make_singular_field_accessor::<_, ::protobuf::types::ProtobufTypeString>(
+                    "statusText",
+                    |m: &Message_RegisterResponse| { &m.statusText },
+                    |m: &mut Message_RegisterResponse| { &mut m.statusText },
+                ));
+                fields.push(::protobuf::reflect::accessor::make_option_accessor::<_, ::protobuf::types::ProtobufTypeUint64>(
+                    "ttl",
+                    |m: &Message_RegisterResponse| { &m.ttl },
+                    |m: &mut Message_RegisterResponse| { &mut m.ttl },
+                ));
+                ::protobuf::reflect::MessageDescriptor::new::<Message_RegisterResponse>(
+                    "Message_RegisterResponse",
+                    fields,
+                    file_descriptor_proto()
+                )
+            })
+        }
+    }
+
+    fn default_instance() -> &'static Message_RegisterResponse {
+        static mut instance: ::protobuf::lazy::Lazy<Message_RegisterResponse> = ::protobuf::lazy::Lazy {
+            lock: ::protobuf::lazy::ONCE_INIT,
+            ptr: 0 as *const Message_RegisterResponse,
+        };
+        unsafe {
+            instance.get(Message_RegisterResponse::new)
+        }
+    }
+}
+
+impl ::protobuf::Clear for Message_RegisterResponse {
+    fn clear(&mut self) {
+        self.clear_status();
+        self.clear_statusText();
+        self.clear_ttl();
+        self.unknown_fields.clear();
+    }
+}
+
+impl ::std::fmt::Debug for Message_RegisterResponse {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+        ::protobuf::text_format::fmt(self, f)
+    }
+}
+
+impl ::protobuf::reflect::ProtobufValue for Message_RegisterResponse {
+    fn as_ref(&self) -> ::protobuf::reflect::ProtobufValueRef<'_> {
+        ::protobuf::reflect::ProtobufValueRef::Message(self)
+    }
+}
+
+#[derive(PartialEq,Clone,Default)]
+pub struct Message_Unregister {
+    // message fields
+    ns: ::protobuf::SingularField<::std::string::String>,
+    id: ::protobuf::SingularField<::std::vec::Vec<u8>>,
+    // special fields
+    pub unknown_fields: ::protobuf::UnknownFields,
+    pub cached_size: ::protobuf::CachedSize,
+}
+
+impl Message_Unregister {
+    pub fn new() -> Message_Unregister {
+        ::std::default::Default::default()
+    }
+
+    // optional string ns = 1;
+
+    pub fn clear_ns(&mut self) {
+        self.ns.clear();
+    }
+
+    pub fn has_ns(&self) -> bool {
+        self.ns.is_some()
+    }
+
+    // Param is passed by value, moved
+    pub fn set_ns(&mut self, v: ::std::string::String) {
+        self.ns = ::protobuf::SingularField::some(v);
+    }
+
+    // Mutable pointer to the field.
+    // If field is not initialized, it is initialized with default value first.
+    pub fn mut_ns(&mut self) -> &mut ::std::string::String {
+        if self.ns.is_none() {
+            self.ns.set_default();
+        }
+        self.ns.as_mut().unwrap()
+    }
+
+    // Take field
+    pub fn take_ns(&mut self) -> ::std::string::String {
+        self.ns.take().unwrap_or_else(|| ::std::string::String::new())
+    }
+
+    pub fn get_ns(&self) -> &str {
+        match self.ns.as_ref() {
+            Some(v) => &v,
+            None => "",
+        }
+    }
+
+    // optional bytes id = 2;
+
+    pub fn clear_id(&mut self) {
+        self.id.clear();
+    }
+
+    pub fn has_id(&self) -> bool {
+        self.id.is_some()
+    }
+
+    // Param is passed by value, moved
+    pub fn set_id(&mut self, v: ::std::vec::Vec<u8>) {
+        self.id = ::protobuf::SingularField::some(v);
+    }
+
+    // Mutable pointer to the field.
+    // If field is not initialized, it is initialized with default value first.
+    pub fn mut_id(&mut self) -> &mut ::std::vec::Vec<u8> {
+        if self.id.is_none() {
+            self.id.set_default();
+        }
+        self.id.as_mut().unwrap()
+    }
+
+    // Take field
+    pub fn take_id(&mut self) -> ::std::vec::Vec<u8> {
+        self.id.take().unwrap_or_else(|| ::std::vec::Vec::new())
+    }
+
+    pub fn get_id(&self) -> &[u8] {
+        match self.id.as_ref() {
+            Some(v) => &v,
+            None => &[],
+        }
+    }
+}
+
+impl ::protobuf::Message for Message_Unregister {
+    fn is_initialized(&self) -> bool {
+        true
+    }
+
+    fn merge_from(&mut self, is: &mut ::protobuf::CodedInputStream<'_>) -> ::protobuf::ProtobufResult<()> {
+        while !is.eof()? {
+            let (field_number, wire_type) = is.read_tag_unpack()?;
+            match field_number {
+                1 => {
+                    ::protobuf::rt::read_singular_string_into(wire_type, is, &mut self.ns)?;
+                },
+                2 => {
+                    ::protobuf::rt::read_singular_bytes_into(wire_type, is, &mut self.id)?;
+                },
+                _ => {
+                    ::protobuf::rt::read_unknown_or_skip_group(field_number, wire_type, is, self.mut_unknown_fields())?;
+                },
+            };
+        }
+        ::std::result::Result::Ok(())
+    }
+
+    // Compute sizes of nested messages
+    #[allow(unused_variables)]
+    fn compute_size(&self) -> u32 {
+        let mut my_size = 0;
+        if let Some(ref v) = self.ns.as_ref() {
+            my_size += ::protobuf::rt::string_size(1, &v);
+        }
+        if let Some(ref v) = self.id.as_ref() {
+            my_size += ::protobuf::rt::bytes_size(2, &v);
+        }
+        my_size += ::protobuf::rt::unknown_fields_size(self.get_unknown_fields());
+        self.cached_size.set(my_size);
+        my_size
+    }
+
+    fn write_to_with_cached_sizes(&self, os: &mut ::protobuf::CodedOutputStream<'_>) -> ::protobuf::ProtobufResult<()> {
+        if let Some(ref v) = self.ns.as_ref() {
+            os.write_string(1, &v)?;
+        }
+        if let Some(ref v) = self.id.as_ref() {
+            os.write_bytes(2, &v)?;
+        }
+        os.write_unknown_fields(self.get_unknown_fields())?;
+        ::std::result::Result::Ok(())
+    }
+
+    fn get_cached_size(&self) -> u32 {
+        self.cached_size.get()
+    }
+
+    fn get_unknown_fields(&self) -> &::protobuf::UnknownFields {
+        &self.unknown_fields
+    }
+
+    fn mut_unknown_fields(&mut self) -> &mut ::protobuf::UnknownFields {
+        &mut self.unknown_fields
+    }
+
+    fn as_any(&self) -> &dyn (::std::any::Any) {
+        self as &dyn (::std::any::Any)
+    }
+    fn as_any_mut(&mut self) -> &mut dyn (::std::any::Any) {
+        self as &mut dyn (::std::any::Any)
+    }
+    fn into_any(self: Box<Self>) -> ::std::boxed::Box<dyn (::std::any::Any)> {
+        self
+    }
+
+    fn descriptor(&self) -> &'static ::protobuf::reflect::MessageDescriptor {
+        Self::descriptor_static()
+    }
+
+    fn new() -> Message_Unregister {
+        Message_Unregister::new()
+    }
+
+    fn descriptor_static() -> &'static ::protobuf::reflect::MessageDescriptor {
+        static mut descriptor: ::protobuf::lazy::Lazy<::protobuf::reflect::MessageDescriptor> = ::protobuf::lazy::Lazy {
+            lock: ::protobuf::lazy::ONCE_INIT,
+            ptr: 0 as *const ::protobuf::reflect::MessageDescriptor,
+        };
+        unsafe {
+            descriptor.get(|| {
+                let mut fields = ::std::vec::Vec::new();
+                fields.push(::protobuf::reflect::accessor::make_singular_field_accessor::<_, ::protobuf::types::ProtobufTypeString>(
+                    "ns",
+                    |m: &Message_Unregister| { &m.ns },
+                    |m: &mut Message_Unregister| { &mut m.ns },
+                ));
+                fields.push(::protobuf::reflect::accessor::make_singular_field_accessor::<_, ::protobuf::types::ProtobufTypeBytes>(
+                    "id",
+                    |m: &Message_Unregister| { &m.id },
+                    |m: &mut Message_Unregister| { &mut m.id },
+                ));
+                ::protobuf::reflect::MessageDescriptor::new::<Message_Unregister>(
+                    "Message_Unregister",
+                    fields,
+                    file_descriptor_proto()
+                )
+            })
+        }
+    }
+
+    fn default_instance() -> &'static Message_Unregister {
+        static mut instance: ::protobuf::lazy::Lazy<Message_Unregister> = ::protobuf::lazy::Lazy {
+            lock: ::protobuf::lazy::ONCE_INIT,
+            ptr: 0 as *const Message_Unregister,
+        };
+        unsafe {
+            instance.get(Message_Unregister::new)
+        }
+    }
+}
+
+impl ::protobuf::Clear for Message_Unregister {
+    fn clear(&mut self) {
+        self.clear_ns();
+        self.clear_id();
+        self.unknown_fields.clear();
+    }
+}
+
+impl ::std::fmt::Debug for Message_Unregister {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+        ::protobuf::text_format::fmt(self, f)
+    }
+}
+
+impl ::protobuf::reflect::ProtobufValue for Message_Unregister {
+    fn as_ref(&self) -> ::protobuf::reflect::ProtobufValueRef<'_> {
+        ::protobuf::reflect::ProtobufValueRef::Message(self)
+    }
+}
+
+#[derive(PartialEq,Clone,Default)]
+pub struct Message_Discover {
+    // message fields
+    ns: ::protobuf::SingularField<::std::string::String>,
+    limit: ::std::option::Option<u64>,
+    cookie: ::protobuf::SingularField<::std::vec::Vec<u8>>,
+    // special fields
+    pub unknown_fields: ::protobuf::UnknownFields,
+    pub cached_size: ::protobuf::CachedSize,
+}
+
+impl Message_Discover {
+    pub fn new() -> Message_Discover {
+        ::std::default::Default::default()
+    }
+
+    // optional string ns = 1;
+
+    pub fn clear_ns(&mut self) {
+        self.ns.clear();
+    }
+
+    pub fn has_ns(&self) -> bool {
+        self.ns.is_some()
+    }
+
+    // Param is passed by value, moved
+    pub fn set_ns(&mut self, v: ::std::string::String) {
+        self.ns = ::protobuf::SingularField::some(v);
+    }
+
+    // Mutable pointer to the field.
+    // If field is not initialized, it is initialized with default value first.
+    pub fn mut_ns(&mut self) -> &mut ::std::string::String {
+        if self.ns.is_none() {
+            self.ns.set_default();
+        }
+        self.ns.as_mut().unwrap()
+    }
+
+    // Take field
+    pub fn take_ns(&mut self) -> ::std::string::String {
+        self.ns.take().unwrap_or_else(|| ::std::string::String::new())
+    }
+
+    pub fn get_ns(&self) -> &str {
+        match self.ns.as_ref() {
+            Some(v) => &v,
+            None => "",
+        }
+    }
+
+    // optional uint64 limit = 2;
+
+    pub fn clear_limit(&mut self) {
+        self.limit = ::std::option::Option::None;
+    }
+
+    pub fn has_limit(&self) -> bool {
+        self.limit.is_some()
+    }
+
+    // Param is passed by value, moved
+    pub fn set_limit(&mut self, v: u64) {
+        self.limit = ::std::option::Option::Some(v);
+    }
+
+    pub fn get_limit(&self) -> u64 {
+        self.limit.unwrap_or(0)
+    }
+
+    // optional bytes cookie = 3;
+
+    pub fn clear_cookie(&mut self) {
+        self.cookie.clear();
+    }
+
+    pub fn has_cookie(&self) -> bool {
+        self.cookie.is_some()
+    }
+
+    // Param is passed by value, moved
+    pub fn set_cookie(&mut self, v: ::std::vec::Vec<u8>) {
+        self.cookie = ::protobuf::SingularField::some(v);
+    }
+
+    // Mutable pointer to the field.
+    // If field is not initialized, it is initialized with default value first.
+    pub fn mut_cookie(&mut self) -> &mut ::std::vec::Vec<u8> {
+        if self.cookie.is_none() {
+            self.cookie.set_default();
+        }
+        self.cookie.as_mut().unwrap()
+    }
+
+    // Take field
+    pub fn take_cookie(&mut self) -> ::std::vec::Vec<u8> {
+        self.cookie.take().unwrap_or_else(|| ::std::vec::Vec::new())
+    }
+
+    pub fn get_cookie(&self) -> &[u8] {
+        match self.cookie.as_ref() {
+            Some(v) => &v,
+            None => &[],
+        }
+    }
+}
+
+impl ::protobuf::Message for Message_Discover {
+    fn is_initialized(&self) -> bool {
+        true
+    }
+
+    fn merge_from(&mut self, is: &mut ::protobuf::CodedInputStream<'_>) -> ::protobuf::ProtobufResult<()> {
+        while !is.eof()? {
+            let (field_number, wire_type) = is.read_tag_unpack()?;
+            match field_number {
+                1 => {
+                    ::protobuf::rt::read_singular_string_into(wire_type, is, &mut self.ns)?;
+                },
+                2 => {
+                    if wire_type != ::protobuf::wire_format::WireTypeVarint {
+                        return ::std::result::Result::Err(::protobuf::rt::unexpected_wire_type(wire_type));
+                    }
+                    let tmp = is.read_uint64()?;
+                    self.limit = ::std::option::Option::Some(tmp);
+                },
+                3 => {
+                    ::protobuf::rt::read_singular_bytes_into(wire_type, is, &mut self.cookie)?;
+                },
+                _ => {
+                    ::protobuf::rt::read_unknown_or_skip_group(field_number, wire_type, is, self.mut_unknown_fields())?;
+                },
+            };
+        }
+        ::std::result::Result::Ok(())
+    }
+
+    // Compute sizes of nested messages
+    #[allow(unused_variables)]
+    fn compute_size(&self) -> u32 {
+        let mut my_size = 0;
+        if let Some(ref v) = self.ns.as_ref() {
+            my_size += ::protobuf::rt::string_size(1, &v);
+        }
+        if let Some(v) = self.limit {
+            my_size += ::protobuf::rt::value_size(2, v, ::protobuf::wire_format::WireTypeVarint);
+        }
+        if let Some(ref v) = self.cookie.as_ref() {
+            my_size += ::protobuf::rt::bytes_size(3, &v);
+        }
+        my_size += ::protobuf::rt::unknown_fields_size(self.get_unknown_fields());
+        self.cached_size.set(my_size);
+        my_size
+    }
+
+    fn write_to_with_cached_sizes(&self, os: &mut ::protobuf::CodedOutputStream<'_>) -> ::protobuf::ProtobufResult<()> {
+        if let Some(ref v) = self.ns.as_ref() {
+            os.write_string(1, &v)?;
+        }
+        if let Some(v) = self.limit {
+            os.write_uint64(2, v)?;
+        }
+        if let Some(ref v) = self.cookie.as_ref() {
+            os.write_bytes(3, &v)?;
+        }
+        os.write_unknown_fields(self.get_unknown_fields())?;
+        ::std::result::Result::Ok(())
+    }
+
+    fn get_cached_size(&self) -> u32 {
+        self.cached_size.get()
+    }
+
+    fn get_unknown_fields(&self) -> &::protobuf::UnknownFields {
+        &self.unknown_fields
+    }
+
+    fn mut_unknown_fields(&mut self) -> &mut ::protobuf::UnknownFields {
+        &mut self.unknown_fields
+    }
+
+    fn as_any(&self) -> &dyn (::std::any::Any) {
+        self as &dyn (::std::any::Any)
+    }
+    fn as_any_mut(&mut self) -> &mut dyn (::std::any::Any) {
+        self as &mut dyn (::std::any::Any)
+    }
+    fn into_any(self: Box<Self>) -> ::std::boxed::Box<dyn (::std::any::Any)> {
+        self
+    }
+
+    fn descriptor(&self) -> &'static ::protobuf::reflect::MessageDescriptor {
+        Self::descriptor_static()
+    }
+
+    fn new() -> Message_Discover {
+        Message_Discover::new()
+    }
+
+    fn descriptor_static() -> &'static ::protobuf::reflect::MessageDescriptor {
+        static mut descriptor: ::protobuf::lazy::Lazy<::protobuf::reflect::MessageDescriptor> = ::protobuf::lazy::Lazy {
+            lock: ::protobuf::lazy::ONCE_INIT,
+            ptr: 0 as *const ::protobuf::reflect::MessageDescriptor,
+        };
+        unsafe {
+            descriptor.get(|| {
+                let mut fields = ::std::vec::Vec::new();
+                fields.push(::protobuf::reflect::accessor::make_singular_field_accessor::<_, ::protobuf::types::ProtobufTypeString>(
+                    "ns",
+                    |m: &Message_Discover| { &m.ns },
+                    |m: &mut Message_Discover| { &mut m.ns },
+                ));
+                fields.push(::protobuf::reflect::accessor::make_option_accessor::<_, ::protobuf::types::ProtobufTypeUint64>(
+                    "limit",
+                    |m: &Message_Discover| { &m.limit },
+                    |m: &mut Message_Discover| { &mut m.limit },
+                ));
+                fields.push(::protobuf::reflect::accessor::make_singular_field_accessor::<_, ::protobuf::types::ProtobufTypeBytes>(
+                    "cookie",
+                    |m: &Message_Discover| { &m.cookie },
+                    |m: &mut Message_Discover| { &mut m.cookie },
+                ));
+                ::protobuf::reflect::MessageDescriptor::new::<Message_Discover>(
+                    "Message_Discover",
+                    fields,
+                    file_descriptor_proto()
+                )
+            })
+        }
+    }
+
+    fn default_instance() -> &'static Message_Discover {
+        static mut instance: ::protobuf::lazy::Lazy<Message_Discover> = ::protobuf::lazy::Lazy {
+            lock: ::protobuf::lazy::ONCE_INIT,
+            ptr: 0 as *const Message_Discover,
+        };
+        unsafe {
+            instance.get(Message_Discover::new)
+        }
+    }
+}
+
+impl ::protobuf::Clear for Message_Discover {
+    fn clear(&mut self) {
+        self.clear_ns();
+        self.clear_limit();
+        self.clear_cookie();
+        self.unknown_fields.clear();
+    }
+}
+
+impl ::std::fmt::Debug for Message_Discover {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+        ::protobuf::text_format::fmt(self, f)
+    }
+}
+
+impl ::protobuf::reflect::ProtobufValue for Message_Discover {
+    fn as_ref(&self) -> ::protobuf::reflect::ProtobufValueRef<'_> {
+        ::protobuf::reflect::ProtobufValueRef::Message(self)
+    }
+}
+
+#[derive(PartialEq,Clone,Default)]
+pub struct Message_DiscoverResponse {
+    // message fields
+    registrations: ::protobuf::RepeatedField<Message_Register>,
+    cookie: ::protobuf::SingularField<::std::vec::Vec<u8>>,
+    status: ::std::option::Option<Message_ResponseStatus>,
+    statusText: ::protobuf::SingularField<::std::string::String>,
+    // special fields
+    pub unknown_fields: ::protobuf::UnknownFields,
+    pub cached_size: ::protobuf::CachedSize,
+}
+
+impl Message_DiscoverResponse {
+    pub fn new() -> Message_DiscoverResponse {
+        ::std::default::Default::default()
+    }
+
+    // repeated .structs.Message.Register registrations = 1;
+
+    pub fn clear_registrations(&mut self) {
+        self.registrations.clear();
+    }
+
+    // Param is passed by value, moved
+    pub fn set_registrations(&mut self, v: ::protobuf::RepeatedField<Message_Register>) {
+        self.registrations = v;
+    }
+
+    // Mutable pointer to the field.
+    pub fn mut_registrations(&mut self) -> &mut ::protobuf::RepeatedField<Message_Register> {
+        &mut self.registrations
+    }
+
+    // Take field
+    pub fn take_registrations(&mut self) -> ::protobuf::RepeatedField<Message_Register> {
+        ::std::mem::replace(&mut self.registrations, ::protobuf::RepeatedField::new())
+    }
+
+    pub fn get_registrations(&self) -> &[Message_Register] {
+        &self.registrations
+    }
+
+    // optional bytes cookie = 2;
+
+    pub fn clear_cookie(&mut self) {
+        self.cookie.clear();
+    }
+
+    pub fn has_cookie(&self) -> bool {
+        self.cookie.is_some()
+    }
+
+    // Param is passed by value, moved
+    pub fn set_cookie(&mut self, v: ::std::vec::Vec<u8>) {
+        self.cookie = ::protobuf::SingularField::some(v);
+    }
+
+    // Mutable pointer to the field.
+    // If field is not initialized, it is initialized with default value first.
+    pub fn mut_cookie(&mut self) -> &mut ::std::vec::Vec<u8> {
+        if self.cookie.is_none() {
+            self.cookie.set_default();
+        }
+        self.cookie.as_mut().unwrap()
+    }
+
+    // Take field
+    pub fn take_cookie(&mut self) -> ::std::vec::Vec<u8> {
+        self.cookie.take().unwrap_or_else(|| ::std::vec::Vec::new())
+    }
+
+    pub fn get_cookie(&self) -> &[u8] {
+        match self.cookie.as_ref() {
+            Some(v) => &v,
+            None => &[],
+        }
+    }
+
+    // optional .structs.Message.ResponseStatus status = 3;
+
+    pub fn clear_status(&mut self) {
+        self.status = ::std::option::Option::None;
+    }
+
+    pub fn has_status(&self) -> bool {
+        self.status.is_some()
+    }
+
+    // Param is passed by value, moved
+    pub fn set_status(&mut self, v: Message_ResponseStatus) {
+        self.status = ::std::option::Option::Some(v);
+    }
+
+    pub fn get_status(&self) -> Message_ResponseStatus {
+        self.status.unwrap_or(Message_ResponseStatus::OK)
+    }
+
+    // optional string statusText = 4;
+
+    pub fn clear_statusText(&mut self) {
+        self.statusText.clear();
+    }
+
+    pub fn has_statusText(&self) -> bool {
+        self.statusText.is_some()
+    }
+
+    // Param is passed by value, moved
+    pub fn set_statusText(&mut self, v: ::std::string::String) {
+        self.statusText = ::protobuf::SingularField::some(v);
+    }
+
+    // Mutable pointer to the field.
+    // If field is not initialized, it is initialized with default value first.
+    pub fn mut_statusText(&mut self) -> &mut ::std::string::String {
+        if self.statusText.is_none() {
+            self.statusText.set_default();
+        }
+        self.statusText.as_mut().unwrap()
+    }
+
+    // Take field
+    pub fn take_statusText(&mut self) -> ::std::string::String {
+        self.statusText.take().unwrap_or_else(|| ::std::string::String::new())
+    }
+
+    pub fn get_statusText(&self) -> &str {
+        match self.statusText.as_ref() {
+            Some(v) => &v,
+            None => "",
+        }
+    }
+}
+
+impl ::protobuf::Message for Message_DiscoverResponse {
+    fn is_initialized(&self) -> bool {
+        for v in &self.registrations {
+            if !v.is_initialized() {
+                return false;
+            }
+        };
+        true
+    }
+
+    fn merge_from(&mut self, is: &mut ::protobuf::CodedInputStream<'_>) -> ::protobuf::ProtobufResult<()> {
+        while !is.eof()? {
+            let (field_number, wire_type) = is.read_tag_unpack()?;
+            match field_number {
+                1 => {
+                    ::protobuf::rt::read_repeated_message_into(wire_type, is, &mut self.registrations)?;
+                },
+                2 => {
+                    ::protobuf::rt::read_singular_bytes_into(wire_type, is, &mut self.cookie)?;
+                },
+                3 => {
+                    ::protobuf::rt::read_proto2_enum_with_unknown_fields_into(wire_type, is, &mut self.status, 3, &mut self.unknown_fields)?
+                },
+                4 => {
+                    ::protobuf::rt::read_singular_string_into(wire_type, is, &mut self.statusText)?;
+                },
+                _ => {
+                    ::protobuf::rt::read_unknown_or_skip_group(field_number, wire_type, is, self.mut_unknown_fields())?;
+                },
+            };
+        }
+        ::std::result::Result::Ok(())
+    }
+
+    // Compute sizes of nested messages
+    #[allow(unused_variables)]
+    fn compute_size(&self) -> u32 {
+        let mut my_size = 0;
+        for value in &self.registrations {
+            let len = value.compute_size();
+            my_size += 1 + ::protobuf::rt::compute_raw_varint32_size(len) + len;
+        };
+        if let Some(ref v) = self.cookie.as_ref() {
+            my_size += ::protobuf::rt::bytes_size(2, &v);
+        }
+        if let Some(v) = self.status {
+            my_size += ::protobuf::rt::enum_size(3, v);
+        }
+        if let Some(ref v) = self.statusText.as_ref() {
+            my_size += ::protobuf::rt::string_size(4, &v);
+        }
+        my_size += ::protobuf::rt::unknown_fields_size(self.get_unknown_fields());
+        self.cached_size.set(my_size);
+        my_size
+    }
+
+    fn write_to_with_cached_sizes(&self, os: &mut ::protobuf::CodedOutputStream<'_>) -> ::protobuf::ProtobufResult<()> {
+        for v in &self.registrations {
+            os.write_tag(1, ::protobuf::wire_format::WireTypeLengthDelimited)?;
+            os.write_raw_varint32(v.get_cached_size())?;
+            v.write_to_with_cached_sizes(os)?;
+        };
+        if let Some(ref v) = self.cookie.as_ref() {
+            os.write_bytes(2, &v)?;
+        }
+        if let Some(v) = self.status {
+            os.write_enum(3, v.value())?;
+        }
+        if let Some(ref v) = self.statusText.as_ref() {
+            os.write_string(4, &v)?;
+        }
+        os.write_unknown_fields(self.get_unknown_fields())?;
+        ::std::result::Result::Ok(())
+    }
+
+    fn get_cached_size(&self) -> u32 {
+        self.cached_size.get()
+    }
+
+    fn get_unknown_fields(&self) -> &::protobuf::UnknownFields {
+        &self.unknown_fields
+    }
+
+    fn mut_unknown_fields(&mut self) -> &mut ::protobuf::UnknownFields {
+        &mut self.unknown_fields
+    }
+
+    fn as_any(&self) -> &dyn (::std::any::Any) {
+        self as &dyn (::std::any::Any)
+    }
+    fn as_any_mut(&mut self) -> &mut dyn (::std::any::Any) {
+        self as &mut dyn (::std::any::Any)
+    }
+    fn into_any(self: Box<Self>) -> ::std::boxed::Box<dyn (::std::any::Any)> {
+        self
+    }
+
+    fn descriptor(&self) -> &'static ::protobuf::reflect::MessageDescriptor {
+        Self::descriptor_static()
+    }
+
+    fn new() -> Message_DiscoverResponse {
+        Message_DiscoverResponse::new()
+    }
+
+    fn descriptor_static() -> &'static ::protobuf::reflect::MessageDescriptor {
+        static mut descriptor: ::protobuf::lazy::Lazy<::protobuf::reflect::MessageDescriptor> = ::protobuf::lazy::Lazy {
+            lock: ::protobuf::lazy::ONCE_INIT,
+            ptr: 0 as *const ::protobuf::reflect::MessageDescriptor,
+        };
+        unsafe {
+            descriptor.get(|| {
+                let mut fields = ::std::vec::Vec::new();
+                fields.push(::protobuf::reflect::accessor::make_repeated_field_accessor::<_, ::protobuf::types::ProtobufTypeMessage<Message_Register>>(
+                    "registrations",
+                    |m: &Message_DiscoverResponse| { &m.registrations },
+                    |m: &mut Message_DiscoverResponse| { &mut m.registrations },
+                ));
+                fields.push(::protobuf::reflect::accessor::make_singular_field_accessor::<_, ::protobuf::types::ProtobufTypeBytes>(
+                    "cookie",
+                    |m: &Message_DiscoverResponse| { &m.cookie },
+                    |m: &mut Message_DiscoverResponse| { &mut m.cookie },
+                ));
+                fields.push(::protobuf::reflect::accessor::make_option_accessor::<_, ::protobuf::types::ProtobufTypeEnum<Message_ResponseStatus>>(
+                    "status",
+                    |m: &Message_DiscoverResponse| { &m.status },
+                    |m: &mut Message_DiscoverResponse| { &mut m.status },
+                ));
+                fields.push(::protobuf::reflect::accessor::make_singular_field_accessor::<_, ::protobuf::types::ProtobufTypeString>(
+                    "statusText",
+                    |m: &Message_DiscoverResponse| { &m.statusText },
+                    |m: &mut Message_DiscoverResponse| { &mut m.statusText },
+                ));
+                ::protobuf::reflect::MessageDescriptor::new::<Message_DiscoverResponse>(
+                    "Message_DiscoverResponse",
+                    fields,
+                    file_descriptor_proto()
+                )
+            })
+        }
+    }
+
+    fn default_instance() -> &'static Message_DiscoverResponse {
+        static mut instance: ::protobuf::lazy::Lazy<Message_DiscoverResponse> = ::protobuf::lazy::Lazy {
+            lock: ::protobuf::lazy::ONCE_INIT,
+            ptr: 0 as *const Message_DiscoverResponse,
+        };
+        unsafe {
+            instance.get(Message_DiscoverResponse::new)
+        }
+    }
+}
+
+impl ::protobuf::Clear for Message_DiscoverResponse {
+    fn clear(&mut self) {
+        self.clear_registrations();
+        self.clear_cookie();
+        self.clear_status();
+        self.clear_statusText();
+        self.unknown_fields.clear();
+    }
+}
+
+impl ::std::fmt::Debug for Message_DiscoverResponse {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+        ::protobuf::text_format::fmt(self, f)
+    }
+}
+
+impl ::protobuf::reflect::ProtobufValue for Message_DiscoverResponse {
+    fn as_ref(&self) -> ::protobuf::reflect::ProtobufValueRef<'_> {
+        ::protobuf::reflect::ProtobufValueRef::Message(self)
+    }
+}
+
+#[derive(Clone,PartialEq,Eq,Debug,Hash)]
+pub enum Message_MessageType {
+    REGISTER = 0,
+    REGISTER_RESPONSE = 1,
+    UNREGISTER = 2,
+    DISCOVER = 3,
+    DISCOVER_RESPONSE = 4,
+}
+
+impl ::protobuf::ProtobufEnum for Message_MessageType {
+    fn value(&self) -> i32 {
+        *self as i32
+    }
+
+    fn from_i32(value: i32) -> ::std::option::Option<Message_MessageType> {
+        match value {
+            0 => ::std::option::Option::Some(Message_MessageType::REGISTER),
+            1 => ::std::option::Option::Some(Message_MessageType::REGISTER_RESPONSE),
+            2 => ::std::option::Option::Some(Message_MessageType::UNREGISTER),
+            3 => ::std::option::Option::Some(Message_MessageType::DISCOVER),
+            4 => ::std::option::Option::Some(Message_MessageType::DISCOVER_RESPONSE),
+            _ => ::std::option::Option::None
+        }
+    }
+
+    fn values() -> &'static [Self] {
+        static values: &'static [Message_MessageType] = &[
+            Message_MessageType::REGISTER,
+            Message_MessageType::REGISTER_RESPONSE,
+            Message_MessageType::UNREGISTER,
+            Message_MessageType::DISCOVER,
+            Message_MessageType::DISCOVER_RESPONSE,
+        ];
+        values
+    }
+
+    fn enum_descriptor_static() -> &'static ::protobuf::reflect::EnumDescriptor {
+        static mut descriptor: ::protobuf::lazy::Lazy<::protobuf::reflect::EnumDescriptor> = ::protobuf::lazy::Lazy {
+            lock: ::protobuf::lazy::ONCE_INIT,
+            ptr: 0 as *const ::protobuf::reflect::EnumDescriptor,
+        };
+        unsafe {
+            descriptor.get(|| {
+                ::protobuf::reflect::EnumDescriptor::new("Message_MessageType", file_descriptor_proto())
+            })
+        }
+    }
+}
+
+impl ::std::marker::Copy for Message_MessageType {
+}
+
+impl ::std::default::Default for Message_MessageType {
+    fn default() -> Self {
+        Message_MessageType::REGISTER
+    }
+}
+
+impl ::protobuf::reflect::ProtobufValue for Message_MessageType {
+    fn as_ref(&self) -> ::protobuf::reflect::ProtobufValueRef<'_> {
+        ::protobuf::reflect::ProtobufValueRef::Enum(self.descriptor())
+    }
+}
+
+#[derive(Clone,PartialEq,Eq,Debug,Hash)]
+pub enum Message_ResponseStatus {
+    OK = 0,
+    E_INVALID_NAMESPACE = 100,
+    E_INVALID_SIGNED_PEER_RECORD = 101,
+    E_INVALID_TTL = 102,
+    E_INVALID_COOKIE = 103,
+    E_NOT_AUTHORIZED = 200,
+    E_INTERNAL_ERROR = 300,
+    E_UNAVAILABLE = 400,
+}
+
+impl ::protobuf::ProtobufEnum for Message_ResponseStatus {
+    fn value(&self) -> i32 {
+        *self as i32
+    }
+
+    fn from_i32(value: i32) -> ::std::option::Option<Message_ResponseStatus> {
+        match value {
+            0 => ::std::option::Option::Some(Message_ResponseStatus::OK),
+            100 => ::std::option::Option::Some(Message_ResponseStatus::E_INVALID_NAMESPACE),
+            101 => ::std::option::Option::Some(Message_ResponseStatus::E_INVALID_SIGNED_PEER_RECORD),
+            102 => ::std::option::Option::Some(Message_ResponseStatus::E_INVALID_TTL),
+            103 => ::std::option::Option::Some(Message_ResponseStatus::E_INVALID_COOKIE),
+            200 => ::std::option::Option::Some(Message_ResponseStatus::E_NOT_AUTHORIZED),
+            300 => ::std::option::Option::Some(Message_ResponseStatus::E_INTERNAL_ERROR),
+            400 => ::std::option::Option::Some(Message_ResponseStatus::E_UNAVAILABLE),
+            _ => ::std::option::Option::None
+        }
+    }
+
+    fn values() -> &'static [Self] {
+        static values: &'static [Message_ResponseStatus] = &[
+            Message_ResponseStatus::OK,
+            Message_ResponseStatus::E_INVALID_NAMESPACE,
+            Message_ResponseStatus::E_INVALID_SIGNED_PEER_RECORD,
+            Message_ResponseStatus::E_INVALID_TTL,
+            Message_ResponseStatus::E_INVALID_COOKIE,
+            Message_ResponseStatus::E_NOT_AUTHORIZED,
+            Message_ResponseStatus::E_INTERNAL_ERROR,
+            Message_ResponseStatus::E_UNAVAILABLE,
+        ];
+        values
+    }
+
+    fn enum_descriptor_static() -> &'static ::protobuf::reflect::EnumDescriptor {
+        static mut descriptor: ::protobuf::lazy::Lazy<::protobuf::reflect::EnumDescriptor> = ::protobuf::lazy::Lazy {
+            lock: ::protobuf::lazy::ONCE_INIT,
+            ptr: 0 as *const ::protobuf::reflect::EnumDescriptor,
+        };
+        unsafe {
+            descriptor.get(|| {
+                ::protobuf::reflect::EnumDescriptor::new("Message_ResponseStatus", file_descriptor_proto())
+            })
+        }
+    }
+}
+
+impl ::std::marker::Copy for Message_ResponseStatus {
+}
+
+impl ::std::default::Default for Message_ResponseStatus {
+    fn default() -> Self {
+        Message_ResponseStatus::OK
+    }
+}
+
+impl ::protobuf::reflect::ProtobufValue for Message_ResponseStatus {
+    fn as_ref(&self) -> ::protobuf::reflect::ProtobufValueRef<'_> {
+        ::protobuf::reflect::ProtobufValueRef::Enum(self.descriptor())
+    }
+}
+
+static file_descriptor_proto_data: &'static [u8] = b"\
+    \n\rstructs.proto\x12\x07structs\"\xc6\t\n\x07Message\x120\n\x04type\x18\
+    \x01\x20\x01(\x0e2\x1c.structs.Message.MessageTypeR\x04type\x125\n\x08re\
+    gister\x18\x02\x20\x01(\x0b2\x19.structs.Message.RegisterR\x08register\
+    \x12M\n\x10registerResponse\x18\x03\x20\x01(\x0b2!.structs.Message.Regis\
+    terResponseR\x10registerResponse\x12;\n\nunregister\x18\x04\x20\x01(\x0b\
+    2\x1b.structs.Message.UnregisterR\nunregister\x125\n\x08discover\x18\x05\
+    \x20\x01(\x0b2\x19.structs.Message.DiscoverR\x08discover\x12M\n\x10disco\
+    verResponse\x18\x06\x20\x01(\x0b2!.structs.Message.DiscoverResponseR\x10\
+    discoverResponse\x1aX\n\x08Register\x12\x0e\n\x02ns\x18\x01\x20\x01(\tR\
+    \x02ns\x12*\n\x10signedPeerRecord\x18\x02\x20\x01(\x0cR\x10signedPeerRec\
+    ord\x12\x10\n\x03ttl\x18\x03\x20\x01(\x04R\x03ttl\x1a}\n\x10RegisterResp\
+    onse\x127\n\x06status\x18\x01\x20\x01(\x0e2\x1f.structs.Message.Response\
+    StatusR\x06status\x12\x1e\n\nstatusText\x18\x02\x20\x01(\tR\nstatusText\
+    \x12\x10\n\x03ttl\x18\x03\x20\x01(\x04R\x03ttl\x1a,\n\nUnregister\x12\
+    \x0e\n\x02ns\x18\x01\x20\x01(\tR\x02ns\x12\x0e\n\x02id\x18\x02\x20\x01(\
+    \x0cR\x02id\x1aH\n\x08Discover\x12\x0e\n\x02ns\x18\x01\x20\x01(\tR\x02ns\
+    \x12\x14\n\x05limit\x18\x02\x20\x01(\x04R\x05limit\x12\x16\n\x06cookie\
+    \x18\x03\x20\x01(\x0cR\x06cookie\x1a\xc4\x01\n\x10DiscoverResponse\x12?\
+    \n\rregistrations\x18\x01\x20\x03(\x0b2\x19.structs.Message.RegisterR\rr\
+    egistrations\x12\x16\n\x06cookie\x18\x02\x20\x01(\x0cR\x06cookie\x127\n\
+    \x06status\x18\x03\x20\x01(\x0e2\x1f.structs.Message.ResponseStatusR\x06\
+    status\x12\x1e\n\nstatusText\x18\x04\x20\x01(\tR\nstatusText\"g\n\x0bMes\
+    sageType\x12\x0c\n\x08REGISTER\x10\0\x12\x15\n\x11REGISTER_RESPONSE\x10\
+    \x01\x12\x0e\n\nUNREGISTER\x10\x02\x12\x0c\n\x08DISCOVER\x10\x03\x12\x15\
+    \n\x11DISCOVER_RESPONSE\x10\x04\"\xbe\x01\n\x0eResponseStatus\x12\x06\n\
+    \x02OK\x10\0\x12\x17\n\x13E_INVALID_NAMESPACE\x10d\x12\x20\n\x1cE_INVALI\
+    D_SIGNED_PEER_RECORD\x10e\x12\x11\n\rE_INVALID_TTL\x10f\x12\x14\n\x10E_I\
+    NVALID_COOKIE\x10g\x12\x15\n\x10E_NOT_AUTHORIZED\x10\xc8\x01\x12\x15\n\
+    \x10E_INTERNAL_ERROR\x10\xac\x02\x12\x12\n\rE_UNAVAILABLE\x10\x90\x03\
+";
+
+static mut file_descriptor_proto_lazy: ::protobuf::lazy::Lazy<::protobuf::descriptor::FileDescriptorProto> = ::protobuf::lazy::Lazy {
+    lock: ::protobuf::lazy::ONCE_INIT,
+    ptr: 0 as *const ::protobuf::descriptor::FileDescriptorProto,
+};
+
+fn parse_descriptor_proto() -> ::protobuf::descriptor::FileDescriptorProto {
+    ::protobuf::parse_from_bytes(file_descriptor_proto_data).unwrap()
+}
+
+pub fn file_descriptor_proto() -> &'static ::protobuf::descriptor::FileDescriptorProto {
+    unsafe {
+        file_descriptor_proto_lazy.get(|| {
+            parse_descriptor_proto()
+        })
+    }
+}
@@ -0,0 +1,189 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Registers two clients with a rendezvous server over a memory transport, and lets a third
+//! client discover them.
+
+use futures::{future, prelude::*};
+
+use futures03::TryFutureExt as _;
+use libp2p_core::{
+    PeerId,
+    Transport,
+    identity,
+    multiaddr::{Multiaddr, Protocol},
+    muxing::StreamMuxerBox,
+    nodes::Substream,
+    transport::{MemoryTransport, boxed::Boxed},
+    upgrade,
+};
+use libp2p_rendezvous::{ErrorCode, Rendezvous, RendezvousConfig, RendezvousError, RendezvousEvent};
+use libp2p_secio::SecioConfig;
+use libp2p_swarm::{AddressSource, Swarm};
+use libp2p_yamux as yamux;
+use std::{io, time::Duration};
+use tokio::runtime::current_thread::Runtime;
+
+type TestTransport = Boxed<(PeerId, StreamMuxerBox), io::Error>;
+type TestSwarm = Swarm<TestTransport, Rendezvous<Substream<StreamMuxerBox>>>;
+
+fn build_swarm(config: RendezvousConfig) -> (PeerId, TestSwarm) {
+    let key = identity::Keypair::generate_ed25519();
+    let peer_id = key.public().into_peer_id();
+    let transport = MemoryTransport::default()
+        .with_upgrade(SecioConfig::new(key.clone()))
+        .and_then(move |out, endpoint| {
+            let peer_id = out.remote_key.into_peer_id();
+            let yamux = yamux::Config::default();
+            upgrade::apply(out.stream, yamux, endpoint)
+                .map_ok(|muxer| (peer_id, StreamMuxerBox::new(muxer)))
+        })
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))
+        .boxed();
+    let swarm = Swarm::new(transport, Rendezvous::new(key, config), peer_id.clone());
+    (peer_id, swarm)
+}
+
+fn random_memory_addr() -> Multiaddr {
+    Protocol::Memory(1 + rand::random::<u64>() % (u64::max_value() - 1)).into()
+}
+
+/// Builds a client listening on a memory address and knowing the address of the server.
+fn build_client(server_id: &PeerId, server_addr: &Multiaddr) -> (PeerId, Multiaddr, TestSwarm) {
+    let (peer_id, mut swarm) = build_swarm(RendezvousConfig::default());
+    let addr = random_memory_addr();
+    Swarm::listen_on(&mut swarm, addr.clone()).unwrap();
+    Swarm::peer_store_mut(&mut swarm).add_address(server_id, server_addr.clone(), AddressSource::Manual, None);
+    (peer_id, addr, swarm)
+}
+
+/// Polls the swarms until `f` returns `Some`, which is called with the index of the swarm and
+/// each event that it produces.
+fn run<T>(runtime: &mut Runtime, swarms: &mut [&mut TestSwarm], mut f: impl FnMut(usize, RendezvousEvent) -> Option<T>) -> T {
+    let future = future::poll_fn(|| -> Poll<T, io::Error> {
+        loop {
+            let mut progress = false;
+            for (n, swarm) in swarms.iter_mut().enumerate() {
+                while let Async::Ready(Some(event)) = swarm.poll()? {
+                    progress = true;
+                    if let Some(result) = f(n, event) {
+                        return Ok(Async::Ready(result));
+                    }
+                }
+            }
+            if !progress {
+                return Ok(Async::NotReady);
+            }
+        }
+    });
+    runtime.block_on(future).unwrap()
+}
+
+#[test]
+fn register_and_discover() {
+    let mut runtime = Runtime::new().unwrap();
+    let mut config = RendezvousConfig::default();
+    config.set_server(true);
+    let (server_id, mut server) = build_swarm(config);
+    let server_addr = random_memory_addr();
+    Swarm::listen_on(&mut server, server_addr.clone()).unwrap();
+
+    let (alice_id, alice_addr, mut alice) = build_client(&server_id, &server_addr);
+    let (bob_id, _, mut bob) = build_client(&server_id, &server_addr);
+    let (_, _, mut carol) = build_client(&server_id, &server_addr);
+
+    alice.register("room".to_owned(), server_id.clone(), None);
+    bob.register("room".to_owned(), server_id.clone(), Some(Duration::from_secs(300)));
+    let mut registered = 0;
+    run(&mut runtime, &mut [&mut server, &mut alice, &mut bob], |_, event| match event {
+        RendezvousEvent::PeerRegistered { .. } => None,
+        RendezvousEvent::Registered { namespace, .. } => {
+            assert_eq!(namespace, "room");
+            registered += 1;
+            if registered == 2 { Some(()) } else { None }
+        }
+        event => panic!("Unexpected event: {:?}", event),
+    });
+
+    // Discovers the registrations one at a time.
+    let mut cookie = None;
+    let mut discovered = Vec::new();
+    for _ in 0..3 {
+        carol.discover(Some("room".to_owned()), cookie.take(), Some(1), server_id.clone());
+        let (registrations, next) = run(&mut runtime, &mut [&mut server, &mut carol], |_, event| match event {
+            RendezvousEvent::Discovered { registrations, cookie, .. } => Some((registrations, cookie)),
+            event => panic!("Unexpected event: {:?}", event),
+        });
+        assert!(registrations.len() <= 1);
+        discovered.extend(registrations);
+        cookie = Some(next);
+    }
+
+    let mut peers = discovered.iter().map(|r| r.peer_id()).collect::<Vec<_>>();
+    peers.sort_by(|a, b| a.as_bytes().cmp(b.as_bytes()));
+    let mut expected = vec![alice_id.clone(), bob_id.clone()];
+    expected.sort_by(|a, b| a.as_bytes().cmp(b.as_bytes()));
+    assert_eq!(peers, expected);
+    let alice_registration = discovered.iter().find(|r| r.peer_id() == alice_id).unwrap();
+    assert_eq!(alice_registration.addresses(), &[alice_addr.clone()][..]);
+    assert!(Swarm::peer_store(&carol).addresses(&alice_id).any(|a| *a == alice_addr));
+
+    // Once unregistered, Alice isn't discovered anymore.
+    alice.unregister("room".to_owned(), server_id.clone());
+    let unregistered = run(&mut runtime, &mut [&mut server, &mut alice], |_, event| match event {
+        RendezvousEvent::PeerUnregistered { peer, .. } => Some(peer),
+        event => panic!("Unexpected event: {:?}", event),
+    });
+    assert_eq!(unregistered, alice_id);
+    carol.discover(Some("room".to_owned()), None, None, server_id.clone());
+    let registrations = run(&mut runtime, &mut [&mut server, &mut carol], |_, event| match event {
+        RendezvousEvent::Discovered { registrations, .. } => Some(registrations),
+        event => panic!("Unexpected event: {:?}", event),
+    });
+    assert_eq!(registrations.iter().map(|r| r.peer_id()).collect::<Vec<_>>(), vec![bob_id]);
+}
+
+#[test]
+fn invalid_registrations_are_refused() {
+    let mut runtime = Runtime::new().unwrap();
+    let mut config = RendezvousConfig::default();
+    config.set_server(true);
+    let (server_id, mut server) = build_swarm(config);
+    let server_addr = random_memory_addr();
+    Swarm::listen_on(&mut server, server_addr.clone()).unwrap();
+
+    let (_, _, mut client) = build_client(&server_id, &server_addr);
+    client.register("room".to_owned(), server_id.clone(), Some(Duration::from_secs(1)));
+    client.register(String::new(), server_id.clone(), None);
+
+    let mut errors = Vec::new();
+    run(&mut runtime, &mut [&mut server, &mut client], |_, event| match event {
+        RendezvousEvent::RegisterFailed { error, .. } => {
+            errors.push(error);
+            if errors.len() == 2 { Some(()) } else { None }
+        }
+        event => panic!("Unexpected event: {:?}", event),
+    });
+    errors.sort_by_key(|e| format!("{:?}", e));
+    assert_eq!(errors, vec![
+        RendezvousError::Rejected(ErrorCode::InvalidNamespace),
+        RendezvousError::Rejected(ErrorCode::InvalidTtl),
+    ]);
+}
@@ -0,0 +1,155 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use futures::{future, prelude::*};
+use libp2p_core::upgrade::{read_respond, Negotiated, ProtocolName, ReadOneError, ReadRespond};
+use std::io;
+use tokio_io::{io as tokio_io_util, AsyncRead, AsyncWrite};
+
+/// Future returned by the methods of `RequestResponseCodec`.
+pub type CodecFuture<T> = Box<dyn Future<Item = T, Error = io::Error> + Send>;
+
+/// A `RequestResponseCodec` defines the request and response types for a
+/// [`RequestResponse`](crate::RequestResponse) protocol and how they are encoded and decoded on
+/// a negotiated substream.
+///
+/// Reading methods yield the substream back together with the message, and writing methods
+/// yield the substream back once the message has been written and flushed. The helpers
+/// [`read_length_prefixed`] and [`write_length_prefixed`] can be used to implement the trait
+/// for protocols that send a single length-prefixed message in each direction.
+pub trait RequestResponseCodec {
+    /// The type of protocol(s) or protocol versions being negotiated.
+    type Protocol: ProtocolName + Clone + Send;
+    /// The type of inbound and outbound requests.
+    type Request: Send;
+    /// The type of inbound and outbound responses.
+    type Response: Send;
+
+    /// Reads a request from the given substream, using the given protocol.
+    fn read_request<T>(&mut self, protocol: &Self::Protocol, io: Negotiated<T>)
+        -> CodecFuture<(Negotiated<T>, Self::Request)>
+    where
+        T: AsyncRead + Send + 'static;
+
+    /// Reads a response from the given substream, using the given protocol.
+    fn read_response<T>(&mut self, protocol: &Self::Protocol, io: Negotiated<T>)
+        -> CodecFuture<(Negotiated<T>, Self::Response)>
+    where
+        T: AsyncRead + Send + 'static;
+
+    /// Writes a request to the given substream, using the given protocol.
+    fn write_request<T>(&mut self, protocol: &Self::Protocol, io: Negotiated<T>, request: Self::Request)
+        -> CodecFuture<Negotiated<T>>
+    where
+        T: AsyncWrite + Send + 'static;
+
+    /// Writes a response to the given substream, using the given protocol.
+    fn write_response<T>(&mut self, protocol: &Self::Protocol, io: Negotiated<T>, response: Self::Response)
+        -> CodecFuture<Negotiated<T>>
+    where
+        T: AsyncWrite + Send + 'static;
+}
+
+/// Future returned by `read_length_prefixed`.
+pub type ReadLengthPrefixed<TSocket> = future::MapErr<
+    ReadRespond<TSocket, (), fn(TSocket, Vec<u8>, ()) -> Result<(TSocket, Vec<u8>), ReadOneError>>,
+    fn(ReadOneError) -> io::Error,
+>;
+
+/// Reads a message prefixed with its length as an unsigned varint, and yields the socket back
+/// together with the message.
+///
+/// Messages larger than `max_size` bytes produce an error of kind `InvalidData`.
+pub fn read_length_prefixed<TSocket>(socket: TSocket, max_size: usize) -> ReadLengthPrefixed<TSocket>
+where
+    TSocket: AsyncRead,
+{
+    fn then<TSocket>(socket: TSocket, data: Vec<u8>, _: ()) -> Result<(TSocket, Vec<u8>), ReadOneError> {
+        Ok((socket, data))
+    }
+
+    fn map_err(err: ReadOneError) -> io::Error {
+        match err {
+            ReadOneError::Io(err) => err,
+            err @ ReadOneError::TooLarge { .. } => io::Error::new(io::ErrorKind::InvalidData, err),
+        }
+    }
+
+    read_respond(socket, max_size, (), then::<TSocket> as fn(_, _, _) -> _)
+        .map_err(map_err as fn(_) -> _)
+}
+
+/// Future returned by `write_length_prefixed`.
+pub type WriteLengthPrefixed<TSocket> = future::AndThen<
+    tokio_io_util::WriteAll<TSocket, Vec<u8>>,
+    tokio_io_util::Flush<TSocket>,
+    fn((TSocket, Vec<u8>)) -> tokio_io_util::Flush<TSocket>,
+>;
+
+/// Writes a message prefixed with its length as an unsigned varint, flushes the socket and
+/// yields it back.
+///
+/// > **Note**: Contrary to `write_one`, the writing side of the socket is not closed.
+pub fn write_length_prefixed<TSocket>(socket: TSocket, data: impl AsRef<[u8]>) -> WriteLengthPrefixed<TSocket>
+where
+    TSocket: AsyncWrite,
+{
+    fn flush<TSocket: AsyncWrite>((socket, _): (TSocket, Vec<u8>)) -> tokio_io_util::Flush<TSocket> {
+        tokio_io_util::flush(socket)
+    }
+
+    let data = data.as_ref();
+    let mut len_buf = unsigned_varint::encode::usize_buffer();
+    let len = unsigned_varint::encode::usize(data.len(), &mut len_buf);
+    let mut buf = Vec::with_capacity(len.len() + data.len());
+    buf.extend_from_slice(len);
+    buf.extend_from_slice(data);
+
+    tokio_io_util::write_all(socket, buf).and_then(flush::<TSocket> as fn(_) -> _)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn length_prefixed_roundtrip() {
+        let written = write_length_prefixed(Cursor::new(Vec::new()), b"hello world")
+            .wait()
+            .unwrap()
+            .into_inner();
+        assert_eq!(written[0], 11);
+
+        let (_, data) = read_length_prefixed(Cursor::new(written), 1024).wait().unwrap();
+        assert_eq!(data, b"hello world");
+    }
+
+    #[test]
+    fn length_prefixed_too_large() {
+        let written = write_length_prefixed(Cursor::new(Vec::new()), vec![0; 2048])
+            .wait()
+            .unwrap()
+            .into_inner();
+
+        let err = read_length_prefixed(Cursor::new(written), 1024).wait().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
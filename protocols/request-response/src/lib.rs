@@ -0,0 +1,665 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Generic request/response protocols.
+//!
+//! The [`RequestResponse`] network behaviour sends requests to remote peers and receives their
+//! responses, and reports the requests received from remote peers so that they can be answered.
+//! Each request is sent on a dedicated substream, which carries the request followed by the
+//! response.
+//!
+//! The protocol names and the encoding of requests and responses are defined by an
+//! implementation of the [`RequestResponseCodec`] trait.
+//!
+//! # Usage
+//!
+//! - Outbound requests are sent with [`RequestResponse::send_request`]. If we are not connected
+//!   to the peer, a connection is opened first using the addresses registered with
+//!   [`RequestResponse::add_address`]. Each request eventually produces either a
+//!   [`RequestResponseMessage::Response`] or a [`RequestResponseEvent::OutboundFailure`].
+//! - Inbound requests produce a [`RequestResponseMessage::Request`] containing a
+//!   [`ResponseChannel`]. The response must be sent with [`RequestResponse::send_response`]
+//!   before the request timeout, otherwise a [`RequestResponseEvent::InboundFailure`] is
+//!   produced.
+
+mod codec;
+mod protocol;
+
+pub use self::codec::{
+    CodecFuture,
+    ReadLengthPrefixed,
+    RequestResponseCodec,
+    WriteLengthPrefixed,
+    read_length_prefixed,
+    write_length_prefixed,
+};
+pub use self::protocol::{RequestProtocol, RequestResponseHandlerEvent, ResponseProtocol};
+
+use fnv::{FnvHashMap, FnvHashSet};
+use futures::{future, prelude::*};
+use libp2p_core::{ConnectedPoint, Multiaddr, PeerId, clock, nodes::ConnectionId, upgrade::Negotiated};
+use libp2p_swarm::{
+    NetworkBehaviourAction,
+    NotifyHandler,
+    OneShotHandler,
+    PollParameters,
+    ProtocolsHandler,
+    SubstreamProtocol,
+    compat::NetworkBehaviour01
+};
+use log::debug;
+use smallvec::SmallVec;
+use std::{collections::VecDeque, error, fmt, io, marker::PhantomData, mem, time::Duration};
+use tokio_io::{AsyncRead, AsyncWrite};
+use wasm_timer::Delay;
+
+/// Identifier of an inbound or outbound request.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct RequestId(u64);
+
+impl fmt::Display for RequestId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Configuration for the request-response behaviour.
+#[derive(Debug, Clone)]
+pub struct RequestResponseConfig {
+    request_timeout: Duration,
+    connection_keep_alive: Duration,
+}
+
+impl Default for RequestResponseConfig {
+    fn default() -> Self {
+        RequestResponseConfig {
+            request_timeout: Duration::from_secs(10),
+            connection_keep_alive: Duration::from_secs(10),
+        }
+    }
+}
+
+impl RequestResponseConfig {
+    /// Sets the timeout of inbound and outbound requests.
+    ///
+    /// For outbound requests, the timeout starts when calling `send_request` and includes
+    /// connecting to the peer. For inbound requests, it includes sending the response.
+    ///
+    /// The default is 10 seconds.
+    pub fn set_request_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.request_timeout = timeout;
+        self
+    }
+
+    /// Sets how long a connection without pending requests is kept alive.
+    ///
+    /// The default is 10 seconds.
+    pub fn set_connection_keep_alive(&mut self, keep_alive: Duration) -> &mut Self {
+        self.connection_keep_alive = keep_alive;
+        self
+    }
+}
+
+/// The channel through which the response to an inbound request is sent, using
+/// [`RequestResponse::send_response`].
+pub struct ResponseChannel<TResponse> {
+    peer: PeerId,
+    request_id: RequestId,
+    marker: PhantomData<TResponse>,
+}
+
+impl<TResponse> ResponseChannel<TResponse> {
+    /// Returns the peer that sent the request.
+    pub fn peer_id(&self) -> &PeerId {
+        &self.peer
+    }
+
+    /// Returns the identifier of the request.
+    pub fn request_id(&self) -> RequestId {
+        self.request_id
+    }
+}
+
+impl<TResponse> fmt::Debug for ResponseChannel<TResponse> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ResponseChannel")
+            .field("peer", &self.peer)
+            .field("request_id", &self.request_id)
+            .finish()
+    }
+}
+
+/// A request or response received from a remote.
+#[derive(Debug)]
+pub enum RequestResponseMessage<TRequest, TResponse> {
+    /// A request has been received.
+    Request {
+        /// The identifier of the request.
+        request_id: RequestId,
+        /// The request.
+        request: TRequest,
+        /// The channel on which to send the response.
+        channel: ResponseChannel<TResponse>,
+    },
+    /// A response to one of our requests has been received.
+    Response {
+        /// The identifier returned by `send_request`.
+        request_id: RequestId,
+        /// The response.
+        response: TResponse,
+    },
+}
+
+/// Event generated by the `RequestResponse` behaviour.
+#[derive(Debug)]
+pub enum RequestResponseEvent<TRequest, TResponse> {
+    /// A request or response has been received.
+    Message {
+        /// The peer that sent the message.
+        peer: PeerId,
+        /// The message.
+        message: RequestResponseMessage<TRequest, TResponse>,
+    },
+    /// An outbound request failed. No response will be received for it.
+    OutboundFailure {
+        /// The peer the request was sent to.
+        peer: PeerId,
+        /// The identifier returned by `send_request`.
+        request_id: RequestId,
+        /// The reason of the failure.
+        error: OutboundFailure,
+    },
+    /// An inbound request couldn't be answered.
+    InboundFailure {
+        /// The peer that sent the request.
+        peer: PeerId,
+        /// The identifier of the request.
+        request_id: RequestId,
+        /// The reason of the failure.
+        error: InboundFailure,
+    },
+}
+
+/// Reason of the failure of an outbound request.
+#[derive(Debug)]
+pub enum OutboundFailure {
+    /// We failed to connect to the peer.
+    DialFailure,
+    /// No response was received before the request timeout.
+    Timeout,
+    /// The connection closed before a response was received.
+    ConnectionClosed,
+    /// An error happened while sending the request or reading the response.
+    Io(io::Error),
+}
+
+impl fmt::Display for OutboundFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OutboundFailure::DialFailure => write!(f, "Failed to dial the peer"),
+            OutboundFailure::Timeout => write!(f, "Timeout while waiting for a response"),
+            OutboundFailure::ConnectionClosed =>
+                write!(f, "Connection closed before a response was received"),
+            OutboundFailure::Io(err) => write!(f, "I/O error: {}", err),
+        }
+    }
+}
+
+impl error::Error for OutboundFailure {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            OutboundFailure::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// Reason of the failure of an inbound request.
+#[derive(Debug)]
+pub enum InboundFailure {
+    /// The response wasn't sent before the request timeout.
+    Timeout,
+    /// The connection closed before the response was sent.
+    ConnectionClosed,
+    /// An error happened while sending the response.
+    Io(io::Error),
+}
+
+impl fmt::Display for InboundFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InboundFailure::Timeout => write!(f, "Timeout while sending the response"),
+            InboundFailure::ConnectionClosed =>
+                write!(f, "Connection closed before the response was sent"),
+            InboundFailure::Io(err) => write!(f, "I/O error: {}", err),
+        }
+    }
+}
+
+impl error::Error for InboundFailure {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            InboundFailure::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// Network behaviour that sends requests to remote peers and answers their requests, using the
+/// protocols and the encoding defined by `TCodec`.
+pub struct RequestResponse<TSubstream, TCodec>
+where
+    TCodec: RequestResponseCodec,
+{
+    /// Configuration of the behaviour.
+    config: RequestResponseConfig,
+
+    /// The codec, cloned into the upgrades.
+    codec: TCodec,
+
+    /// The supported protocols, by order of preference.
+    protocols: SmallVec<[TCodec::Protocol; 2]>,
+
+    /// Identifier of the next inbound or outbound request.
+    next_request_id: RequestId,
+
+    /// Peers we are connected to.
+    connected: FnvHashSet<PeerId>,
+
+    /// Addresses of peers registered with `add_address`.
+    addresses: FnvHashMap<PeerId, SmallVec<[Multiaddr; 6]>>,
+
+    /// Requests waiting for a connection to their peer.
+    pending_requests: FnvHashMap<PeerId, SmallVec<[RequestProtocol<TCodec>; 10]>>,
+
+    /// Outbound requests that didn't complete yet.
+    outbound: FnvHashMap<RequestId, PendingOutbound>,
+
+    /// Inbound requests that haven't been answered yet.
+    inbound: FnvHashMap<RequestId, PendingInbound<TSubstream, TCodec>>,
+
+    /// Events that need to be yielded to the outside when polling.
+    events: VecDeque<NetworkBehaviourAction<
+        RequestProtocol<TCodec>,
+        RequestResponseEvent<TCodec::Request, TCodec::Response>
+    >>,
+}
+
+/// An outbound request that didn't complete yet.
+struct PendingOutbound {
+    peer: PeerId,
+    deadline: Delay,
+}
+
+/// An inbound request that hasn't been answered yet.
+struct PendingInbound<TSubstream, TCodec>
+where
+    TCodec: RequestResponseCodec,
+{
+    peer: PeerId,
+    deadline: Delay,
+    state: InboundState<TSubstream, TCodec>,
+}
+
+enum InboundState<TSubstream, TCodec>
+where
+    TCodec: RequestResponseCodec,
+{
+    /// Waiting for `send_response` to be called.
+    AwaitingResponse {
+        protocol: TCodec::Protocol,
+        substream: Negotiated<TSubstream>,
+    },
+    /// Sending the response and closing the substream.
+    Responding(Box<dyn Future<Item = (), Error = io::Error> + Send>),
+}
+
+impl<TSubstream, TCodec> RequestResponse<TSubstream, TCodec>
+where
+    TCodec: RequestResponseCodec,
+{
+    /// Creates a `RequestResponse` supporting the given protocols, by order of preference.
+    pub fn new<I>(codec: TCodec, protocols: I, config: RequestResponseConfig) -> Self
+    where
+        I: IntoIterator<Item = TCodec::Protocol>,
+    {
+        RequestResponse {
+            config,
+            codec,
+            protocols: protocols.into_iter().collect(),
+            next_request_id: RequestId(0),
+            connected: FnvHashSet::default(),
+            addresses: FnvHashMap::default(),
+            pending_requests: FnvHashMap::default(),
+            outbound: FnvHashMap::default(),
+            inbound: FnvHashMap::default(),
+            events: VecDeque::new(),
+        }
+    }
+
+    /// Adds a known address for a peer, used when a request is sent to the peer and we are not
+    /// connected to it.
+    pub fn add_address(&mut self, peer: &PeerId, address: Multiaddr) {
+        let addresses = self.addresses.entry(peer.clone()).or_default();
+        if !addresses.contains(&address) {
+            addresses.push(address);
+        }
+    }
+
+    /// Removes an address of a peer previously added with `add_address`.
+    pub fn remove_address(&mut self, peer: &PeerId, address: &Multiaddr) {
+        let remove = match self.addresses.get_mut(peer) {
+            Some(addresses) => {
+                addresses.retain(|a| a != address);
+                addresses.is_empty()
+            }
+            None => false,
+        };
+        if remove {
+            self.addresses.remove(peer);
+        }
+    }
+
+    /// Returns true if we are connected to the given peer.
+    pub fn is_connected(&self, peer: &PeerId) -> bool {
+        self.connected.contains(peer)
+    }
+
+    /// Returns true if the given outbound request didn't complete yet.
+    pub fn is_pending_outbound(&self, request_id: &RequestId) -> bool {
+        self.outbound.contains_key(request_id)
+    }
+
+    fn next_request_id(&mut self) -> RequestId {
+        let request_id = self.next_request_id;
+        self.next_request_id = RequestId(request_id.0 + 1);
+        request_id
+    }
+
+    /// Fails all the outbound requests to the given peer.
+    fn fail_outbound(&mut self, peer: &PeerId, error: impl Fn() -> OutboundFailure) {
+        let failed = self.outbound
+            .iter()
+            .filter(|(_, outbound)| outbound.peer == *peer)
+            .map(|(request_id, _)| *request_id)
+            .collect::<Vec<_>>();
+        for request_id in failed {
+            self.outbound.remove(&request_id);
+            self.events.push_back(NetworkBehaviourAction::GenerateEvent(
+                RequestResponseEvent::OutboundFailure {
+                    peer: peer.clone(),
+                    request_id,
+                    error: error(),
+                }
+            ));
+        }
+    }
+
+    /// Fails all the inbound requests from the given peer.
+    fn fail_inbound(&mut self, peer: &PeerId, error: impl Fn() -> InboundFailure) {
+        let failed = self.inbound
+            .iter()
+            .filter(|(_, inbound)| inbound.peer == *peer)
+            .map(|(request_id, _)| *request_id)
+            .collect::<Vec<_>>();
+        for request_id in failed {
+            self.inbound.remove(&request_id);
+            self.events.push_back(NetworkBehaviourAction::GenerateEvent(
+                RequestResponseEvent::InboundFailure {
+                    peer: peer.clone(),
+                    request_id,
+                    error: error(),
+                }
+            ));
+        }
+    }
+}
+
+impl<TSubstream, TCodec> RequestResponse<TSubstream, TCodec>
+where
+    TSubstream: AsyncRead + AsyncWrite + Send + 'static,
+    TCodec: RequestResponseCodec + Clone,
+{
+    /// Sends a request to a peer, connecting to it first if necessary.
+    ///
+    /// Returns the identifier of the request, which is part of the event produced once the
+    /// request completes or fails.
+    pub fn send_request(&mut self, peer: &PeerId, request: TCodec::Request) -> RequestId {
+        let request_id = self.next_request_id();
+        let request = RequestProtocol {
+            codec: self.codec.clone(),
+            protocols: self.protocols.clone(),
+            request_id,
+            request,
+        };
+
+        self.outbound.insert(request_id, PendingOutbound {
+            peer: peer.clone(),
+            deadline: Delay::new(clock::now() + self.config.request_timeout),
+        });
+
+        if self.connected.contains(peer) {
+            self.events.push_back(NetworkBehaviourAction::NotifyHandler {
+                peer_id: peer.clone(),
+                handler: NotifyHandler::Any,
+                event: request,
+            });
+        } else {
+            let pending = self.pending_requests.entry(peer.clone()).or_default();
+            if pending.is_empty() {
+                self.events.push_back(NetworkBehaviourAction::DialPeer { peer_id: peer.clone() });
+            }
+            pending.push(request);
+        }
+
+        request_id
+    }
+
+    /// Sends the response to an inbound request.
+    ///
+    /// Returns the response back if the request is no longer pending, because it timed out or
+    /// because the connection closed. The corresponding `InboundFailure` event is produced in
+    /// that situation.
+    pub fn send_response(&mut self, channel: ResponseChannel<TCodec::Response>, response: TCodec::Response)
+        -> Result<(), TCodec::Response>
+    {
+        let inbound = match self.inbound.get_mut(&channel.request_id) {
+            Some(inbound) => inbound,
+            None => return Err(response),
+        };
+
+        if inbound.peer != channel.peer {
+            return Err(response);
+        }
+        if let InboundState::Responding(_) = inbound.state {
+            return Err(response);
+        }
+
+        let placeholder = InboundState::Responding(Box::new(future::ok(())));
+        if let InboundState::AwaitingResponse { protocol, substream } = mem::replace(&mut inbound.state, placeholder) {
+            let future = self.codec.write_response(&protocol, substream, response)
+                .and_then(tokio_io::io::shutdown)
+                .map(|_| ());
+            inbound.state = InboundState::Responding(Box::new(future));
+        }
+
+        Ok(())
+    }
+}
+
+impl<TSubstream, TCodec> NetworkBehaviour01 for RequestResponse<TSubstream, TCodec>
+where
+    TSubstream: AsyncRead + AsyncWrite + Send + 'static,
+    TCodec: RequestResponseCodec + Clone + Send + 'static,
+    TCodec::Protocol: 'static,
+    TCodec::Request: 'static,
+    TCodec::Response: 'static,
+{
+    type ProtocolsHandler = OneShotHandler<
+        TSubstream,
+        ResponseProtocol<TCodec>,
+        RequestProtocol<TCodec>,
+        RequestResponseHandlerEvent<TSubstream, TCodec>
+    >;
+    type OutEvent = RequestResponseEvent<TCodec::Request, TCodec::Response>;
+
+    fn new_handler(&mut self) -> Self::ProtocolsHandler {
+        let listen_protocol = ResponseProtocol {
+            codec: self.codec.clone(),
+            protocols: self.protocols.clone(),
+        };
+        OneShotHandler::new(
+            SubstreamProtocol::new(listen_protocol).with_timeout(self.config.request_timeout),
+            self.config.connection_keep_alive,
+        )
+    }
+
+    fn addresses_of_peer(&mut self, peer: &PeerId) -> Vec<Multiaddr> {
+        self.addresses.get(peer).map(|a| a.to_vec()).unwrap_or_default()
+    }
+
+    fn inject_connected(&mut self, peer: PeerId, _: ConnectedPoint) {
+        if let Some(pending) = self.pending_requests.remove(&peer) {
+            for request in pending {
+                self.events.push_back(NetworkBehaviourAction::NotifyHandler {
+                    peer_id: peer.clone(),
+                    handler: NotifyHandler::Any,
+                    event: request,
+                });
+            }
+        }
+        self.connected.insert(peer);
+    }
+
+    fn inject_disconnected(&mut self, peer: &PeerId, _: ConnectedPoint) {
+        self.connected.remove(peer);
+        self.fail_outbound(peer, || OutboundFailure::ConnectionClosed);
+        self.fail_inbound(peer, || InboundFailure::ConnectionClosed);
+    }
+
+    fn inject_dial_failure(&mut self, peer: &PeerId) {
+        if self.pending_requests.remove(peer).is_some() {
+            self.fail_outbound(peer, || OutboundFailure::DialFailure);
+        }
+    }
+
+    fn inject_node_event(&mut self, peer: PeerId, _: ConnectionId, event: RequestResponseHandlerEvent<TSubstream, TCodec>) {
+        match event {
+            RequestResponseHandlerEvent::Request { protocol, request, substream } => {
+                let request_id = self.next_request_id();
+                self.inbound.insert(request_id, PendingInbound {
+                    peer: peer.clone(),
+                    deadline: Delay::new(clock::now() + self.config.request_timeout),
+                    state: InboundState::AwaitingResponse { protocol, substream },
+                });
+                let channel = ResponseChannel {
+                    peer: peer.clone(),
+                    request_id,
+                    marker: PhantomData,
+                };
+                self.events.push_back(NetworkBehaviourAction::GenerateEvent(RequestResponseEvent::Message {
+                    peer,
+                    message: RequestResponseMessage::Request { request_id, request, channel },
+                }));
+            }
+            RequestResponseHandlerEvent::Response { request_id, result } => {
+                if self.outbound.remove(&request_id).is_none() {
+                    debug!("Ignoring the result of request {}, which timed out", request_id);
+                    return;
+                }
+                let event = match result {
+                    Ok(response) => RequestResponseEvent::Message {
+                        peer,
+                        message: RequestResponseMessage::Response { request_id, response },
+                    },
+                    Err(err) => RequestResponseEvent::OutboundFailure {
+                        peer,
+                        request_id,
+                        error: OutboundFailure::Io(err),
+                    },
+                };
+                self.events.push_back(NetworkBehaviourAction::GenerateEvent(event));
+            }
+        }
+    }
+
+    fn poll(
+        &mut self,
+        _: &mut impl PollParameters,
+    ) -> Async<
+        NetworkBehaviourAction<
+            <Self::ProtocolsHandler as ProtocolsHandler>::InEvent,
+            Self::OutEvent,
+        >,
+    > {
+        let events = &mut self.events;
+
+        self.inbound.retain(|request_id, inbound| {
+            let error = match inbound.deadline.poll() {
+                Ok(Async::NotReady) => match &mut inbound.state {
+                    InboundState::AwaitingResponse { .. } => return true,
+                    InboundState::Responding(future) => match future.poll() {
+                        Ok(Async::NotReady) => return true,
+                        Ok(Async::Ready(())) => return false,
+                        Err(err) => InboundFailure::Io(err),
+                    },
+                },
+                Ok(Async::Ready(())) | Err(_) => InboundFailure::Timeout,
+            };
+            events.push_back(NetworkBehaviourAction::GenerateEvent(RequestResponseEvent::InboundFailure {
+                peer: inbound.peer.clone(),
+                request_id: *request_id,
+                error,
+            }));
+            false
+        });
+
+        let pending_requests = &mut self.pending_requests;
+        self.outbound.retain(|request_id, outbound| {
+            match outbound.deadline.poll() {
+                Ok(Async::NotReady) => true,
+                Ok(Async::Ready(())) | Err(_) => {
+                    let remove = match pending_requests.get_mut(&outbound.peer) {
+                        Some(pending) => {
+                            pending.retain(|r| r.request_id != *request_id);
+                            pending.is_empty()
+                        }
+                        None => false,
+                    };
+                    if remove {
+                        pending_requests.remove(&outbound.peer);
+                    }
+                    events.push_back(NetworkBehaviourAction::GenerateEvent(RequestResponseEvent::OutboundFailure {
+                        peer: outbound.peer.clone(),
+                        request_id: *request_id,
+                        error: OutboundFailure::Timeout,
+                    }));
+                    false
+                }
+            }
+        });
+
+        if let Some(event) = self.events.pop_front() {
+            return Async::Ready(event);
+        }
+
+        Async::NotReady
+    }
+}
@@ -0,0 +1,183 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! The outbound and inbound upgrades of a request-response protocol.
+//!
+//! An outbound substream carries a single request, written by the dialer, followed by a single
+//! response, written by the listener.
+
+use crate::RequestId;
+use crate::codec::RequestResponseCodec;
+use futures::prelude::*;
+use libp2p_core::compat::Compat01As03;
+use libp2p_core::upgrade::{InboundUpgrade, Negotiated, OutboundUpgrade, ProtocolName, UpgradeInfo};
+use smallvec::SmallVec;
+use std::{fmt, io};
+use tokio_io::{AsyncRead, AsyncWrite};
+use void::Void;
+
+/// Event produced by the upgrades, and thus by the handler, of a request-response protocol.
+pub enum RequestResponseHandlerEvent<TSubstream, TCodec>
+where
+    TCodec: RequestResponseCodec,
+{
+    /// A request has been received on an inbound substream.
+    Request {
+        /// The protocol negotiated on the substream.
+        protocol: TCodec::Protocol,
+        /// The received request.
+        request: TCodec::Request,
+        /// The substream on which the response must be sent.
+        substream: Negotiated<TSubstream>,
+    },
+    /// An outbound request has finished, successfully or not.
+    Response {
+        /// The identifier of the request.
+        request_id: RequestId,
+        /// The response, or the error that happened when sending the request or reading the
+        /// response.
+        result: Result<TCodec::Response, io::Error>,
+    },
+}
+
+impl<TSubstream, TCodec> fmt::Debug for RequestResponseHandlerEvent<TSubstream, TCodec>
+where
+    TCodec: RequestResponseCodec,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RequestResponseHandlerEvent::Request { protocol, .. } => f
+                .debug_struct("Request")
+                .field("protocol", &String::from_utf8_lossy(protocol.protocol_name()))
+                .finish(),
+            RequestResponseHandlerEvent::Response { request_id, result } => f
+                .debug_struct("Response")
+                .field("request_id", request_id)
+                .field("success", &result.is_ok())
+                .finish(),
+        }
+    }
+}
+
+/// Upgrade for inbound substreams. Reads a request.
+#[derive(Clone)]
+pub struct ResponseProtocol<TCodec>
+where
+    TCodec: RequestResponseCodec,
+{
+    pub(crate) codec: TCodec,
+    pub(crate) protocols: SmallVec<[TCodec::Protocol; 2]>,
+}
+
+impl<TCodec> UpgradeInfo for ResponseProtocol<TCodec>
+where
+    TCodec: RequestResponseCodec,
+{
+    type Info = TCodec::Protocol;
+    type InfoIter = smallvec::IntoIter<[Self::Info; 2]>;
+
+    fn protocol_info(&self) -> Self::InfoIter {
+        self.protocols.clone().into_iter()
+    }
+}
+
+impl<TSubstream, TCodec> InboundUpgrade<TSubstream> for ResponseProtocol<TCodec>
+where
+    TSubstream: AsyncRead + AsyncWrite + Send + 'static,
+    TCodec: RequestResponseCodec + Send + 'static,
+    TCodec::Request: 'static,
+    TCodec::Protocol: 'static,
+{
+    type Output = RequestResponseHandlerEvent<TSubstream, TCodec>;
+    type Error = io::Error;
+    type Future = Compat01As03<Box<dyn Future<Item = Self::Output, Error = Self::Error> + Send>>;
+
+    fn upgrade_inbound(mut self, socket: Negotiated<TSubstream>, protocol: Self::Info) -> Self::Future {
+        let future = self.codec.read_request(&protocol, socket)
+            .map(move |(substream, request)| RequestResponseHandlerEvent::Request {
+                protocol,
+                request,
+                substream,
+            });
+        Compat01As03::new(Box::new(future))
+    }
+}
+
+/// Upgrade for outbound substreams. Writes a request and reads the response.
+///
+/// Failures to send the request or to read the response are reported in the output rather than
+/// as an error of the upgrade, so that they don't close the connection.
+pub struct RequestProtocol<TCodec>
+where
+    TCodec: RequestResponseCodec,
+{
+    pub(crate) codec: TCodec,
+    pub(crate) protocols: SmallVec<[TCodec::Protocol; 2]>,
+    pub(crate) request_id: RequestId,
+    pub(crate) request: TCodec::Request,
+}
+
+impl<TCodec> fmt::Debug for RequestProtocol<TCodec>
+where
+    TCodec: RequestResponseCodec,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RequestProtocol")
+            .field("request_id", &self.request_id)
+            .finish()
+    }
+}
+
+impl<TCodec> UpgradeInfo for RequestProtocol<TCodec>
+where
+    TCodec: RequestResponseCodec,
+{
+    type Info = TCodec::Protocol;
+    type InfoIter = smallvec::IntoIter<[Self::Info; 2]>;
+
+    fn protocol_info(&self) -> Self::InfoIter {
+        self.protocols.clone().into_iter()
+    }
+}
+
+impl<TSubstream, TCodec> OutboundUpgrade<TSubstream> for RequestProtocol<TCodec>
+where
+    TSubstream: AsyncRead + AsyncWrite + Send + 'static,
+    TCodec: RequestResponseCodec + Send + 'static,
+    TCodec::Response: 'static,
+    TCodec::Protocol: 'static,
+{
+    type Output = RequestResponseHandlerEvent<TSubstream, TCodec>;
+    type Error = Void;
+    type Future = Compat01As03<Box<dyn Future<Item = Self::Output, Error = Self::Error> + Send>>;
+
+    fn upgrade_outbound(self, socket: Negotiated<TSubstream>, protocol: Self::Info) -> Self::Future {
+        let RequestProtocol { mut codec, request_id, request, .. } = self;
+        let future = codec.write_request(&protocol, socket, request)
+            .and_then(move |socket| codec.read_response(&protocol, socket))
+            .then(move |result| -> Result<_, Void> {
+                Ok(RequestResponseHandlerEvent::Response {
+                    request_id,
+                    result: result.map(|(_, response)| response),
+                })
+            });
+        Compat01As03::new(Box::new(future))
+    }
+}
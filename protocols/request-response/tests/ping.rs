@@ -0,0 +1,255 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Exchanges requests and responses between two swarms over a memory transport.
+
+use futures::{future, prelude::*};
+
+use futures03::TryFutureExt as _;
+use libp2p_core::{
+    PeerId,
+    Transport,
+    identity,
+    multiaddr::{Multiaddr, Protocol},
+    muxing::StreamMuxerBox,
+    nodes::Substream,
+    transport::{MemoryTransport, boxed::Boxed},
+    upgrade::{self, Negotiated},
+};
+use libp2p_request_response::{
+    CodecFuture,
+    InboundFailure,
+    OutboundFailure,
+    RequestResponse,
+    RequestResponseCodec,
+    RequestResponseConfig,
+    RequestResponseEvent,
+    RequestResponseMessage,
+    read_length_prefixed,
+    write_length_prefixed,
+};
+use libp2p_secio::SecioConfig;
+use libp2p_swarm::Swarm;
+use libp2p_yamux as yamux;
+use std::{io, time::Duration};
+use tokio::runtime::current_thread::Runtime;
+use tokio_io::{AsyncRead, AsyncWrite};
+
+type TestTransport = Boxed<(PeerId, StreamMuxerBox), io::Error>;
+type TestSwarm = Swarm<TestTransport, RequestResponse<Substream<StreamMuxerBox>, PingCodec>>;
+
+/// Codec exchanging opaque byte strings.
+#[derive(Debug, Clone)]
+struct PingCodec;
+
+impl RequestResponseCodec for PingCodec {
+    type Protocol = &'static [u8];
+    type Request = Vec<u8>;
+    type Response = Vec<u8>;
+
+    fn read_request<T>(&mut self, _: &Self::Protocol, io: Negotiated<T>) -> CodecFuture<(Negotiated<T>, Vec<u8>)>
+    where
+        T: AsyncRead + Send + 'static,
+    {
+        Box::new(read_length_prefixed(io, 1024))
+    }
+
+    fn read_response<T>(&mut self, _: &Self::Protocol, io: Negotiated<T>) -> CodecFuture<(Negotiated<T>, Vec<u8>)>
+    where
+        T: AsyncRead + Send + 'static,
+    {
+        Box::new(read_length_prefixed(io, 1024))
+    }
+
+    fn write_request<T>(&mut self, _: &Self::Protocol, io: Negotiated<T>, request: Vec<u8>) -> CodecFuture<Negotiated<T>>
+    where
+        T: AsyncWrite + Send + 'static,
+    {
+        Box::new(write_length_prefixed(io, request))
+    }
+
+    fn write_response<T>(&mut self, _: &Self::Protocol, io: Negotiated<T>, response: Vec<u8>) -> CodecFuture<Negotiated<T>>
+    where
+        T: AsyncWrite + Send + 'static,
+    {
+        Box::new(write_length_prefixed(io, response))
+    }
+}
+
+fn build_swarm(config: RequestResponseConfig) -> (PeerId, TestSwarm) {
+    let key = identity::Keypair::generate_ed25519();
+    let peer_id = key.public().into_peer_id();
+    let transport = MemoryTransport::default()
+        .with_upgrade(SecioConfig::new(key))
+        .and_then(move |out, endpoint| {
+            let peer_id = out.remote_key.into_peer_id();
+            let yamux = yamux::Config::default();
+            upgrade::apply(out.stream, yamux, endpoint)
+                .map_ok(|muxer| (peer_id, StreamMuxerBox::new(muxer)))
+        })
+        .map_err(|e| panic!("Failed to create transport: {:?}", e))
+        .boxed();
+    let protocols = vec![&b"/ping/1.0.0"[..]];
+    let behaviour = RequestResponse::new(PingCodec, protocols, config);
+    (peer_id.clone(), Swarm::new(transport, behaviour, peer_id))
+}
+
+/// Builds two swarms, the first listening on a memory address known to the second one.
+fn build_pair(config: RequestResponseConfig) -> ((PeerId, TestSwarm), (PeerId, TestSwarm)) {
+    let (id1, mut swarm1) = build_swarm(config.clone());
+    let (id2, mut swarm2) = build_swarm(config);
+
+    let port = 1 + rand::random::<u64>() % (u64::max_value() - 1);
+    let addr: Multiaddr = Protocol::Memory(port).into();
+    Swarm::listen_on(&mut swarm1, addr.clone()).unwrap();
+    swarm2.add_address(&id1, addr);
+
+    ((id1, swarm1), (id2, swarm2))
+}
+
+#[test]
+fn ping_protocol() {
+    let ((id1, mut swarm1), (id2, mut swarm2)) = build_pair(RequestResponseConfig::default());
+
+    let request_id = swarm2.send_request(&id1, b"ping".to_vec());
+    let mut received = 0;
+
+    let future = future::poll_fn(move || -> Poll<(), io::Error> {
+        loop {
+            match swarm1.poll()? {
+                Async::Ready(Some(RequestResponseEvent::Message {
+                    peer,
+                    message: RequestResponseMessage::Request { request, channel, .. },
+                })) => {
+                    assert_eq!(peer, id2);
+                    assert_eq!(request, b"ping");
+                    received += 1;
+                    swarm1.send_response(channel, b"pong".to_vec()).unwrap();
+                }
+                Async::Ready(Some(event)) => panic!("Unexpected event: {:?}", event),
+                Async::Ready(None) | Async::NotReady => break,
+            }
+        }
+
+        loop {
+            match swarm2.poll()? {
+                Async::Ready(Some(RequestResponseEvent::Message {
+                    peer,
+                    message: RequestResponseMessage::Response { request_id: id, response },
+                })) => {
+                    assert_eq!(peer, id1);
+                    assert_eq!(id, request_id);
+                    assert_eq!(response, b"pong");
+                    assert_eq!(received, 1);
+                    return Ok(Async::Ready(()));
+                }
+                Async::Ready(Some(event)) => panic!("Unexpected event: {:?}", event),
+                Async::Ready(None) | Async::NotReady => break,
+            }
+        }
+
+        Ok(Async::NotReady)
+    });
+
+    Runtime::new().unwrap().block_on(future).unwrap();
+}
+
+#[test]
+fn unanswered_request_times_out() {
+    let mut config = RequestResponseConfig::default();
+    config.set_request_timeout(Duration::from_millis(500));
+    let ((id1, mut swarm1), (id2, mut swarm2)) = build_pair(config);
+
+    let request_id = swarm2.send_request(&id1, b"ping".to_vec());
+    let mut inbound_failed = false;
+    let mut outbound_failed = false;
+    // Keeps the channel alive without answering.
+    let mut channels = Vec::new();
+
+    let future = future::poll_fn(move || -> Poll<(), io::Error> {
+        loop {
+            match swarm1.poll()? {
+                Async::Ready(Some(RequestResponseEvent::Message {
+                    message: RequestResponseMessage::Request { channel, .. }, ..
+                })) => channels.push(channel),
+                Async::Ready(Some(RequestResponseEvent::InboundFailure { peer, error, .. })) => {
+                    assert_eq!(peer, id2);
+                    match error {
+                        InboundFailure::Timeout | InboundFailure::ConnectionClosed => {}
+                        err => panic!("Unexpected error: {:?}", err),
+                    }
+                    inbound_failed = true;
+                }
+                Async::Ready(Some(event)) => panic!("Unexpected event: {:?}", event),
+                Async::Ready(None) | Async::NotReady => break,
+            }
+        }
+
+        loop {
+            match swarm2.poll()? {
+                Async::Ready(Some(RequestResponseEvent::OutboundFailure { peer, request_id: id, error })) => {
+                    assert_eq!(peer, id1);
+                    assert_eq!(id, request_id);
+                    match error {
+                        OutboundFailure::Timeout => {}
+                        err => panic!("Unexpected error: {:?}", err),
+                    }
+                    outbound_failed = true;
+                }
+                Async::Ready(Some(event)) => panic!("Unexpected event: {:?}", event),
+                Async::Ready(None) | Async::NotReady => break,
+            }
+        }
+
+        if inbound_failed && outbound_failed {
+            Ok(Async::Ready(()))
+        } else {
+            Ok(Async::NotReady)
+        }
+    });
+
+    Runtime::new().unwrap().block_on(future).unwrap();
+}
+
+#[test]
+fn dial_failure_without_addresses() {
+    let (_, mut swarm) = build_swarm(RequestResponseConfig::default());
+    let unknown = PeerId::random();
+
+    let request_id = swarm.send_request(&unknown, b"ping".to_vec());
+
+    let future = future::poll_fn(move || -> Poll<(), io::Error> {
+        match swarm.poll()? {
+            Async::Ready(Some(RequestResponseEvent::OutboundFailure { peer, request_id: id, error })) => {
+                assert_eq!(peer, unknown);
+                assert_eq!(id, request_id);
+                match error {
+                    OutboundFailure::DialFailure => {}
+                    err => panic!("Unexpected error: {:?}", err),
+                }
+                Ok(Async::Ready(()))
+            }
+            Async::Ready(Some(event)) => panic!("Unexpected event: {:?}", event),
+            Async::Ready(None) | Async::NotReady => Ok(Async::NotReady),
+        }
+    });
+
+    Runtime::new().unwrap().block_on(future).unwrap();
+}
@@ -20,24 +20,45 @@
 
 use criterion::{Bencher, Criterion, criterion_main, criterion_group};
 use futures::prelude::*;
-use libp2p_core::Transport;
+use libp2p_core::{
+    Multiaddr,
+    Transport,
+    compat::{Compat03As01, Transport01},
+    identity,
+    transport::ListenerEvent
+};
 use tokio::{
     io,
     runtime::current_thread::Runtime
 };
 
+/// Starts listening on a random local port and returns the listener along with its address.
+fn listen<T>(transport: T) -> (impl Stream<Item = (T::ListenerUpgrade, Multiaddr), Error = T::Error>, Multiaddr)
+where
+    T: Transport01
+{
+    let mut listener = transport
+        .listen_on("/ip4/127.0.0.1/tcp/0".parse().unwrap())
+        .unwrap();
+    let addr = listener.by_ref()
+        .wait()
+        .next()
+        .expect("some event")
+        .expect("no error")
+        .into_new_address()
+        .expect("listen address");
+    (listener.filter_map(ListenerEvent::into_upgrade), addr)
+}
+
 fn secio_and_send_data(bench: &mut Bencher, data: &[u8]) {
-    let key = libp2p_secio::SecioKeyPair::ed25519_generated().unwrap();
-    let transport =
-        libp2p_tcp::TcpConfig::new().with_upgrade(libp2p_secio::SecioConfig::new(key));
+    let key = identity::Keypair::generate_ed25519();
+    let transport = Compat03As01::new(
+        libp2p_tcp::TcpConfig::new().with_upgrade(libp2p_secio::SecioConfig::new(key)));
 
     let data_vec = data.to_vec();
 
     bench.iter(move || {
-        let (listener, addr) = transport
-            .clone()
-            .listen_on("/ip4/127.0.0.1/tcp/0".parse().unwrap())
-            .unwrap();
+        let (listener, addr) = listen(transport.clone());
 
         let listener_side = listener
             .into_future()
@@ -50,9 +71,7 @@ fn secio_and_send_data(bench: &mut Bencher, data: &[u8]) {
                 Ok(())
             });
 
-        let dialer_side = transport
-            .clone()
-            .dial(addr)
+        let dialer_side = Transport01::dial(transport.clone(), addr)
             .unwrap()
             .map_err(|_| panic!())
             .and_then(|server| io::write_all(server.stream, data_vec.clone()))
@@ -71,10 +90,7 @@ fn raw_tcp_connect_and_send_data(bench: &mut Bencher, data: &[u8]) {
     let data_vec = data.to_vec();
 
     bench.iter(move || {
-        let (listener, addr) = transport
-            .clone()
-            .listen_on("/ip4/127.0.0.1/tcp/0".parse().unwrap())
-            .unwrap();
+        let (listener, addr) = listen(transport.clone());
 
         let listener_side = listener
             .into_future()
@@ -87,9 +103,7 @@ fn raw_tcp_connect_and_send_data(bench: &mut Bencher, data: &[u8]) {
                 Ok(())
             });
 
-        let dialer_side = transport
-            .clone()
-            .dial(addr)
+        let dialer_side = Transport01::dial(transport.clone(), addr)
             .unwrap()
             .map_err(|_| panic!())
             .and_then(|server| io::write_all(server, data_vec.clone()))
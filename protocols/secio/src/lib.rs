@@ -33,6 +33,7 @@
 //! use futures::Future;
 //! use libp2p_secio::{SecioConfig, SecioOutput};
 //! use libp2p_core::{Multiaddr, identity, upgrade::apply_inbound};
+//! use libp2p_core::compat::{Compat03As01, Transport01};
 //! use libp2p_core::transport::Transport;
 //! use libp2p_tcp::TcpConfig;
 //! use tokio_io::io::write_all;
@@ -47,7 +48,12 @@
 //!     })
 //!     .map(|out: SecioOutput<_>, _| out.stream);
 //!
-//! let future = dialer.dial("/ip4/127.0.0.1/tcp/12345".parse::<Multiaddr>().unwrap())
+//! // The futures of `Transport` are `std::future`s, which `Compat03As01` turns back into
+//! // futures 0.1 ones.
+//! let future = Transport01::dial(
+//!         Compat03As01::new(dialer),
+//!         "/ip4/127.0.0.1/tcp/12345".parse::<Multiaddr>().unwrap()
+//!     )
 //!     .unwrap()
 //!     .map_err(|e| panic!("error: {:?}", e))
 //!     .and_then(|connection| {
@@ -76,7 +82,7 @@ pub use self::error::SecioError;
 use bytes::BytesMut;
 use futures::stream::MapErr as StreamMapErr;
 use futures::{Future, Poll, Sink, StartSend, Stream};
-use libp2p_core::{PublicKey, identity, upgrade::{UpgradeInfo, InboundUpgrade, OutboundUpgrade, Negotiated}};
+use libp2p_core::{PublicKey, compat::Compat01As03, identity, upgrade::{UpgradeInfo, InboundUpgrade, OutboundUpgrade, Negotiated}};
 use log::debug;
 use rw_stream_sink::RwStreamSink;
 use std::io::{Error as IoError, ErrorKind as IoErrorKind};
@@ -190,10 +196,10 @@ where
 {
     type Output = SecioOutput<Negotiated<T>>;
     type Error = SecioError;
-    type Future = Box<dyn Future<Item = Self::Output, Error = Self::Error> + Send>;
+    type Future = Compat01As03<Box<dyn Future<Item = Self::Output, Error = Self::Error> + Send>>;
 
     fn upgrade_inbound(self, socket: Negotiated<T>, _: Self::Info) -> Self::Future {
-        Box::new(self.handshake(socket))
+        Compat01As03::new(Box::new(self.handshake(socket)))
     }
 }
 
@@ -203,10 +209,10 @@ where
 {
     type Output = SecioOutput<Negotiated<T>>;
     type Error = SecioError;
-    type Future = Box<dyn Future<Item = Self::Output, Error = Self::Error> + Send>;
+    type Future = Compat01As03<Box<dyn Future<Item = Self::Output, Error = Self::Error> + Send>>;
 
     fn upgrade_outbound(self, socket: Negotiated<T>, _: Self::Info) -> Self::Future {
-        Box::new(self.handshake(socket))
+        Compat01As03::new(Box::new(self.handshake(socket)))
     }
 }
 
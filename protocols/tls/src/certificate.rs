@@ -0,0 +1,286 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Generation and verification of the self-signed certificates used during the handshake.
+//!
+//! Every node generates a certificate for a fresh key pair, unrelated to its libp2p identity.
+//! The link between the two is made through a certificate extension that contains the libp2p
+//! public key and a signature, made with the libp2p key, of the public key of the certificate.
+//! Checking this signature, plus the TLS handshake proving the possession of the certificate's
+//! private key, authenticates the `PeerId` of the remote.
+//!
+//! The certificate must also be valid at the time it is received, according to its `notBefore`
+//! and `notAfter` fields.
+
+use crate::error::TlsError;
+use libp2p_core::{identity, PeerId};
+use rustls::{Certificate, PrivateKey, TLSError};
+use std::time::{SystemTime, UNIX_EPOCH};
+use yasna::{BERReader, models::ObjectIdentifier, Tag, tags::TAG_UTCTIME};
+
+/// Object identifier of the certificate extension containing the libp2p public key.
+const LIBP2P_OID: &[u64] = &[1, 3, 6, 1, 4, 1, 53594, 1, 1];
+
+/// Prefix of the message signed by the libp2p key in the certificate extension.
+const LIBP2P_SIGNING_PREFIX: &[u8] = b"libp2p-tls-handshake:";
+
+/// Algorithms accepted for the self-signature of the remote's certificate.
+static SIGNATURE_ALGORITHMS: &[&webpki::SignatureAlgorithm] = &[
+    &webpki::ECDSA_P256_SHA256,
+    &webpki::ECDSA_P256_SHA384,
+    &webpki::ECDSA_P384_SHA256,
+    &webpki::ECDSA_P384_SHA384,
+    &webpki::RSA_PSS_2048_8192_SHA256_LEGACY_KEY,
+    &webpki::RSA_PSS_2048_8192_SHA384_LEGACY_KEY,
+    &webpki::RSA_PSS_2048_8192_SHA512_LEGACY_KEY,
+    &webpki::RSA_PKCS1_2048_8192_SHA256,
+    &webpki::RSA_PKCS1_2048_8192_SHA384,
+    &webpki::RSA_PKCS1_2048_8192_SHA512,
+];
+
+/// Generates a self-signed certificate bound to the given identity, and its private key.
+pub fn generate(keypair: &identity::Keypair) -> Result<(Certificate, PrivateKey), TlsError> {
+    generate_with_params(keypair, rcgen::CertificateParams::new(Vec::new()))
+}
+
+/// Same as [`generate`], with the given parameters for the certificate.
+fn generate_with_params(keypair: &identity::Keypair, mut params: rcgen::CertificateParams)
+    -> Result<(Certificate, PrivateKey), TlsError>
+{
+    let cert_keypair = rcgen::KeyPair::generate(&rcgen::PKCS_ECDSA_P256_SHA256)?;
+
+    let mut msg = LIBP2P_SIGNING_PREFIX.to_vec();
+    msg.extend_from_slice(&cert_keypair.public_key_der());
+    let signature = keypair.sign(&msg)?;
+    let content = yasna::construct_der(|writer| {
+        writer.write_sequence(|writer| {
+            writer.next().write_bytes(&keypair.public().into_protobuf_encoding());
+            writer.next().write_bytes(&signature);
+        })
+    });
+
+    // The extension is not marked as critical, as webpki (which rustls relies upon to check the
+    // handshake signatures) refuses certificates containing unknown critical extensions.
+    params.custom_extensions.push(rcgen::CustomExtension::from_oid_content(LIBP2P_OID, content));
+    params.key_pair = Some(cert_keypair);
+    let certificate = rcgen::Certificate::from_params(params)
+        .and_then(|cert| Ok((cert.serialize_der()?, cert.serialize_private_key_der())))?;
+
+    Ok((Certificate(certificate.0), PrivateKey(certificate.1)))
+}
+
+/// Checks that the certificate chain presented by the remote consists of a single self-signed
+/// certificate bound to a libp2p identity, and returns the corresponding `PeerId`.
+pub fn verify(presented_certs: &[Certificate]) -> Result<PeerId, TLSError> {
+    let certificate = match presented_certs {
+        [certificate] => certificate,
+        _ => return Err(TLSError::General("Expected exactly one certificate".to_owned()))
+    };
+
+    let parsed = parse_certificate(&certificate.0)
+        .map_err(|_| TLSError::WebPKIError(webpki::Error::BadDER))?;
+
+    // A clock set before 1970 makes every certificate look not yet valid.
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(i64::min_value());
+    if now < parsed.not_before {
+        return Err(TLSError::WebPKIError(webpki::Error::CertNotValidYet))
+    }
+    if now > parsed.not_after {
+        return Err(TLSError::WebPKIError(webpki::Error::CertExpired))
+    }
+
+    let end_entity = webpki::EndEntityCert::from(untrusted::Input::from(&certificate.0))
+        .map_err(TLSError::WebPKIError)?;
+    let self_signed = SIGNATURE_ALGORITHMS.iter().any(|alg| {
+        end_entity.verify_signature(
+            alg,
+            untrusted::Input::from(&parsed.tbs_certificate),
+            untrusted::Input::from(&parsed.signature)
+        ).is_ok()
+    });
+    if !self_signed {
+        return Err(TLSError::WebPKIError(webpki::Error::InvalidSignatureForPublicKey))
+    }
+
+    let extension = parsed.libp2p_extension
+        .ok_or_else(|| TLSError::General("Missing libp2p certificate extension".to_owned()))?;
+    let (public_key, signature) = yasna::parse_der(&extension, |reader| {
+        reader.read_sequence(|reader| {
+            let public_key = reader.next().read_bytes()?;
+            let signature = reader.next().read_bytes()?;
+            Ok((public_key, signature))
+        })
+    }).map_err(|_| TLSError::General("Invalid libp2p certificate extension".to_owned()))?;
+
+    let public_key = identity::PublicKey::from_protobuf_encoding(&public_key)
+        .map_err(|_| TLSError::General("Invalid libp2p public key".to_owned()))?;
+    let mut msg = LIBP2P_SIGNING_PREFIX.to_vec();
+    msg.extend_from_slice(&parsed.subject_public_key_info);
+    if !public_key.verify(&msg, &signature) {
+        return Err(TLSError::General("Invalid libp2p certificate extension signature".to_owned()))
+    }
+
+    Ok(public_key.into_peer_id())
+}
+
+/// The parts of an X.509 certificate that we are interested in.
+struct ParsedCertificate {
+    /// DER encoding of the signed part of the certificate.
+    tbs_certificate: Vec<u8>,
+    /// Signature of `tbs_certificate`.
+    signature: Vec<u8>,
+    /// Start of the validity period, in seconds since the UNIX epoch.
+    not_before: i64,
+    /// End of the validity period, in seconds since the UNIX epoch.
+    not_after: i64,
+    /// DER encoding of the public key of the certificate.
+    subject_public_key_info: Vec<u8>,
+    /// Content of the libp2p extension, if any.
+    libp2p_extension: Option<Vec<u8>>,
+}
+
+/// Parses the DER encoding of an X.509 certificate, as described in RFC 5280.
+fn parse_certificate(der: &[u8]) -> yasna::ASN1Result<ParsedCertificate> {
+    let (tbs_certificate, signature) = yasna::parse_der(der, |reader| {
+        reader.read_sequence(|reader| {
+            let tbs_certificate = reader.next().read_der()?;
+            let _signature_algorithm = reader.next().read_der()?;
+            let (signature, _) = reader.next().read_bitvec_bytes()?;
+            Ok((tbs_certificate, signature))
+        })
+    })?;
+
+    let libp2p_oid = ObjectIdentifier::from_slice(LIBP2P_OID);
+    let mut libp2p_extension = None;
+    let (not_before, not_after, subject_public_key_info) = yasna::parse_der(&tbs_certificate, |reader| {
+        reader.read_sequence(|reader| {
+            let _version = reader.read_optional(|reader| {
+                reader.read_tagged(Tag::context(0), |reader| reader.read_u8())
+            })?;
+            let _serial_number = reader.next().read_der()?;
+            let _signature = reader.next().read_der()?;
+            let _issuer = reader.next().read_der()?;
+            let (not_before, not_after) = reader.next().read_sequence(|reader| {
+                Ok((read_time(reader.next())?, read_time(reader.next())?))
+            })?;
+            let _subject = reader.next().read_der()?;
+            let subject_public_key_info = reader.next().read_der()?;
+            reader.read_optional(|reader| {
+                reader.read_tagged(Tag::context(3), |reader| {
+                    reader.read_sequence_of(|reader| {
+                        reader.read_sequence(|reader| {
+                            let oid = reader.next().read_oid()?;
+                            let _critical = reader.read_default(false, |reader| reader.read_bool())?;
+                            let value = reader.next().read_bytes()?;
+                            if oid == libp2p_oid {
+                                if libp2p_extension.is_some() {
+                                    return Err(yasna::ASN1Error::new(yasna::ASN1ErrorKind::Invalid))
+                                }
+                                libp2p_extension = Some(value);
+                            }
+                            Ok(())
+                        })
+                    })
+                })
+            })?;
+            Ok((not_before, not_after, subject_public_key_info))
+        })
+    })?;
+
+    Ok(ParsedCertificate {
+        tbs_certificate,
+        signature,
+        not_before,
+        not_after,
+        subject_public_key_info,
+        libp2p_extension,
+    })
+}
+
+/// Reads a `Time`, which is either a `UTCTime` or a `GeneralizedTime`, and returns it in seconds
+/// since the UNIX epoch.
+fn read_time(reader: BERReader) -> yasna::ASN1Result<i64> {
+    if reader.lookahead_tag()? == TAG_UTCTIME {
+        Ok(reader.read_utctime()?.datetime().timestamp())
+    } else {
+        Ok(reader.read_generalized_time()?.datetime().timestamp())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn certificate_is_bound_to_identity() {
+        let keypair = identity::Keypair::generate_ed25519();
+        let (certificate, _) = generate(&keypair).unwrap();
+        let peer_id = verify(&[certificate]).unwrap();
+        assert_eq!(peer_id, keypair.public().into_peer_id());
+    }
+
+    #[test]
+    fn tampered_extension_is_refused() {
+        let keypair = identity::Keypair::generate_ed25519();
+        let (certificate, _) = generate(&keypair).unwrap();
+        let mut der = certificate.0;
+        // The libp2p signature is the very last element of the signed part of the certificate.
+        let signature_end = {
+            let parsed = parse_certificate(&der).unwrap();
+            let tbs_start = der.windows(parsed.tbs_certificate.len())
+                .position(|w| w == &parsed.tbs_certificate[..])
+                .unwrap();
+            tbs_start + parsed.tbs_certificate.len()
+        };
+        der[signature_end - 1] ^= 1;
+        assert!(verify(&[Certificate(der)]).is_err());
+    }
+
+    #[test]
+    fn certificate_outside_validity_is_refused() {
+        let keypair = identity::Keypair::generate_ed25519();
+
+        let mut params = rcgen::CertificateParams::new(Vec::new());
+        params.not_after = rcgen::date_time_ymd(2000, 1, 1);
+        let (certificate, _) = generate_with_params(&keypair, params).unwrap();
+        match verify(&[certificate]) {
+            Err(TLSError::WebPKIError(webpki::Error::CertExpired)) => {}
+            other => panic!("Unexpected result: {:?}", other),
+        }
+
+        // Dates after 2049 are encoded as a `GeneralizedTime`.
+        let mut params = rcgen::CertificateParams::new(Vec::new());
+        params.not_before = rcgen::date_time_ymd(4000, 1, 1);
+        let (certificate, _) = generate_with_params(&keypair, params).unwrap();
+        match verify(&[certificate]) {
+            Err(TLSError::WebPKIError(webpki::Error::CertNotValidYet)) => {}
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn missing_extension_is_refused() {
+        let certificate = rcgen::generate_simple_self_signed(Vec::new()).unwrap();
+        let der = certificate.serialize_der().unwrap();
+        assert!(verify(&[Certificate(der)]).is_err());
+    }
+}
@@ -0,0 +1,85 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use libp2p_core::identity;
+use std::{error::Error, fmt, io};
+
+/// libp2p_tls error type.
+#[derive(Debug)]
+pub enum TlsError {
+    /// An I/O error has been encountered.
+    Io(io::Error),
+    /// A TLS error has been encountered.
+    Tls(rustls::TLSError),
+    /// The self-signed certificate could not be generated.
+    Certificate(rcgen::RcgenError),
+    /// A signature was required and could not be created.
+    SigningError(identity::error::SigningError),
+    #[doc(hidden)]
+    __Nonexhaustive
+}
+
+impl fmt::Display for TlsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TlsError::Io(e) => write!(f, "{}", e),
+            TlsError::Tls(e) => write!(f, "{}", e),
+            TlsError::Certificate(e) => write!(f, "{}", e),
+            TlsError::SigningError(e) => write!(f, "{}", e),
+            TlsError::__Nonexhaustive => f.write_str("__Nonexhaustive")
+        }
+    }
+}
+
+impl Error for TlsError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TlsError::Io(e) => Some(e),
+            TlsError::Tls(e) => Some(e),
+            TlsError::Certificate(e) => Some(e),
+            TlsError::SigningError(e) => Some(e),
+            TlsError::__Nonexhaustive => None
+        }
+    }
+}
+
+impl From<io::Error> for TlsError {
+    fn from(e: io::Error) -> Self {
+        TlsError::Io(e)
+    }
+}
+
+impl From<rustls::TLSError> for TlsError {
+    fn from(e: rustls::TLSError) -> Self {
+        TlsError::Tls(e)
+    }
+}
+
+impl From<rcgen::RcgenError> for TlsError {
+    fn from(e: rcgen::RcgenError) -> Self {
+        TlsError::Certificate(e)
+    }
+}
+
+impl From<identity::error::SigningError> for TlsError {
+    fn from(e: identity::error::SigningError) -> Self {
+        TlsError::SigningError(e)
+    }
+}
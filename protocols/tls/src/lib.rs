@@ -0,0 +1,226 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! TLS 1.3 support for libp2p, as described in the [libp2p TLS handshake specification][spec].
+//!
+//! This crate provides `libp2p_core::InboundUpgrade` and `libp2p_core::OutboundUpgrade`
+//! implementations negotiated under the `/tls/1.0.0` protocol name.
+//!
+//! Both sides of the handshake present a self-signed certificate carrying their libp2p public
+//! key in an extension, signed by their `identity::Keypair`. Certificates that are not correctly
+//! bound to a libp2p identity are refused during the handshake.
+//!
+//! All upgrades produce as output a pair, consisting of the `PeerId` of the remote and a
+//! `TlsOutput` which represents the established cryptographic session with the remote,
+//! implementing `tokio_io::AsyncRead` and `tokio_io::AsyncWrite`.
+//!
+//! # Usage
+//!
+//! Example:
+//!
+//! ```
+//! use libp2p_core::{identity, Transport};
+//! use libp2p_tcp::TcpConfig;
+//! use libp2p_tls::TlsConfig;
+//!
+//! # fn main() {
+//! let id_keys = identity::Keypair::generate_ed25519();
+//! let tls = TlsConfig::new(&id_keys).unwrap();
+//! let transport = TcpConfig::new().with_upgrade(tls);
+//! // ...
+//! # }
+//! ```
+//!
+//! [spec]: https://github.com/libp2p/specs/blob/master/tls/tls.md
+
+pub mod certificate;
+mod error;
+mod verifier;
+
+pub use error::TlsError;
+
+use futures::{Future, Poll};
+use libp2p_core::{identity, PeerId, UpgradeInfo, InboundUpgrade, OutboundUpgrade, compat::Compat01As03, upgrade::Negotiated};
+use rustls::{ProtocolVersion, Session};
+use std::{fmt, io, iter, sync::Arc};
+use tokio_io::{AsyncRead, AsyncWrite};
+use tokio_rustls::{client, server, TlsAcceptor, TlsConnector};
+use verifier::Libp2pCertificateVerifier;
+
+/// ALPN protocol advertised during the handshake.
+const LIBP2P_ALPN: &[u8] = b"libp2p";
+
+/// Server name sent by the dialer. The listener has no use for it, and it isn't checked either.
+const SERVER_NAME: &str = "libp2p";
+
+/// The protocol upgrade configuration.
+#[derive(Clone)]
+pub struct TlsConfig {
+    client: Arc<rustls::ClientConfig>,
+    server: Arc<rustls::ServerConfig>,
+}
+
+impl TlsConfig {
+    /// Creates a new configuration, generating a certificate bound to the given identity.
+    pub fn new(keypair: &identity::Keypair) -> Result<TlsConfig, TlsError> {
+        let (certificate, key) = certificate::generate(keypair)?;
+        let verifier = Arc::new(Libp2pCertificateVerifier);
+
+        let mut client = rustls::ClientConfig::new();
+        client.versions = vec![ProtocolVersion::TLSv1_3];
+        client.set_protocols(&[LIBP2P_ALPN.to_vec()]);
+        client.set_single_client_cert(vec![certificate.clone()], key.clone());
+        client.dangerous().set_certificate_verifier(verifier.clone());
+
+        let mut server = rustls::ServerConfig::new(verifier);
+        server.versions = vec![ProtocolVersion::TLSv1_3];
+        server.set_protocols(&[LIBP2P_ALPN.to_vec()]);
+        server.set_single_cert(vec![certificate], key)?;
+
+        Ok(TlsConfig {
+            client: Arc::new(client),
+            server: Arc::new(server),
+        })
+    }
+}
+
+impl fmt::Debug for TlsConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("TlsConfig")
+    }
+}
+
+impl UpgradeInfo for TlsConfig {
+    type Info = &'static [u8];
+    type InfoIter = iter::Once<Self::Info>;
+
+    fn protocol_info(&self) -> Self::InfoIter {
+        iter::once(b"/tls/1.0.0")
+    }
+}
+
+impl<T> InboundUpgrade<T> for TlsConfig
+where
+    T: AsyncRead + AsyncWrite + Send + 'static
+{
+    type Output = (PeerId, TlsOutput<Negotiated<T>>);
+    type Error = TlsError;
+    type Future = Compat01As03<Box<dyn Future<Item = Self::Output, Error = Self::Error> + Send>>;
+
+    fn upgrade_inbound(self, socket: Negotiated<T>, _: Self::Info) -> Self::Future {
+        let future = TlsAcceptor::from(self.server)
+            .accept(socket)
+            .map_err(TlsError::from)
+            .and_then(|stream| {
+                let peer_id = remote_peer_id(stream.get_ref().1)?;
+                Ok((peer_id, TlsOutput { inner: TlsStream::Server(stream) }))
+            });
+        Compat01As03::new(Box::new(future))
+    }
+}
+
+impl<T> OutboundUpgrade<T> for TlsConfig
+where
+    T: AsyncRead + AsyncWrite + Send + 'static
+{
+    type Output = (PeerId, TlsOutput<Negotiated<T>>);
+    type Error = TlsError;
+    type Future = Compat01As03<Box<dyn Future<Item = Self::Output, Error = Self::Error> + Send>>;
+
+    fn upgrade_outbound(self, socket: Negotiated<T>, _: Self::Info) -> Self::Future {
+        let name = webpki::DNSNameRef::try_from_ascii_str(SERVER_NAME)
+            .expect("SERVER_NAME is a valid DNS name; QED");
+        let future = TlsConnector::from(self.client)
+            .connect(name, socket)
+            .map_err(TlsError::from)
+            .and_then(|stream| {
+                let peer_id = remote_peer_id(stream.get_ref().1)?;
+                Ok((peer_id, TlsOutput { inner: TlsStream::Client(stream) }))
+            });
+        Compat01As03::new(Box::new(future))
+    }
+}
+
+/// Returns the `PeerId` of the remote of a session whose handshake has completed.
+///
+/// The certificate of the remote has already been verified during the handshake; it is checked
+/// again here in order to extract the identity it is bound to.
+fn remote_peer_id(session: &dyn Session) -> Result<PeerId, TlsError> {
+    let certificates = session.get_peer_certificates()
+        .ok_or(rustls::TLSError::NoCertificatesPresented)?;
+    Ok(certificate::verify(&certificates)?)
+}
+
+/// Encrypted session with a remote, established by a `TlsConfig` upgrade.
+pub struct TlsOutput<T> {
+    inner: TlsStream<T>,
+}
+
+/// The TLS stream of either side of the handshake.
+enum TlsStream<T> {
+    Client(client::TlsStream<T>),
+    Server(server::TlsStream<T>),
+}
+
+impl<T> fmt::Debug for TlsOutput<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("TlsOutput")
+    }
+}
+
+impl<T: AsyncRead + AsyncWrite> io::Read for TlsOutput<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match &mut self.inner {
+            TlsStream::Client(stream) => stream.read(buf),
+            TlsStream::Server(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl<T: AsyncRead + AsyncWrite> io::Write for TlsOutput<T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match &mut self.inner {
+            TlsStream::Client(stream) => stream.write(buf),
+            TlsStream::Server(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.inner {
+            TlsStream::Client(stream) => stream.flush(),
+            TlsStream::Server(stream) => stream.flush(),
+        }
+    }
+}
+
+impl<T: AsyncRead + AsyncWrite> AsyncRead for TlsOutput<T> {
+    unsafe fn prepare_uninitialized_buffer(&self, _: &mut [u8]) -> bool {
+        false
+    }
+}
+
+impl<T: AsyncRead + AsyncWrite> AsyncWrite for TlsOutput<T> {
+    fn shutdown(&mut self) -> Poll<(), io::Error> {
+        match &mut self.inner {
+            TlsStream::Client(stream) => stream.shutdown(),
+            TlsStream::Server(stream) => stream.shutdown(),
+        }
+    }
+}
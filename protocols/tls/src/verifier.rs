@@ -0,0 +1,55 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use crate::certificate;
+use rustls::{
+    Certificate, ClientCertVerified, ClientCertVerifier, DistinguishedNames, RootCertStore,
+    ServerCertVerified, ServerCertVerifier, TLSError
+};
+
+/// Certificate verifier accepting any certificate that is correctly bound to a libp2p identity.
+///
+/// The usual checks against trust anchors and DNS names are meaningless for the self-signed
+/// certificates of libp2p nodes and are therefore skipped.
+pub(crate) struct Libp2pCertificateVerifier;
+
+impl ServerCertVerifier for Libp2pCertificateVerifier {
+    fn verify_server_cert(
+        &self,
+        _: &RootCertStore,
+        presented_certs: &[Certificate],
+        _: webpki::DNSNameRef<'_>,
+        _: &[u8]
+    ) -> Result<ServerCertVerified, TLSError> {
+        certificate::verify(presented_certs).map(|_| ServerCertVerified::assertion())
+    }
+}
+
+impl ClientCertVerifier for Libp2pCertificateVerifier {
+    fn client_auth_root_subjects(&self) -> DistinguishedNames {
+        DistinguishedNames::new()
+    }
+
+    fn verify_client_cert(&self, presented_certs: &[Certificate])
+        -> Result<ClientCertVerified, TLSError>
+    {
+        certificate::verify(presented_certs).map(|_| ClientCertVerified::assertion())
+    }
+}
@@ -0,0 +1,82 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use futures::prelude::*;
+use libp2p_core::compat::{Compat03As01, Transport01};
+use libp2p_core::identity;
+use libp2p_core::transport::{Transport, ListenerEvent};
+use libp2p_tcp::TcpConfig;
+use libp2p_tls::TlsConfig;
+use tokio::io;
+
+#[test]
+fn handshake_authenticates_both_sides() {
+    let server_id = identity::Keypair::generate_ed25519();
+    let client_id = identity::Keypair::generate_ed25519();
+    let server_peer_id = server_id.public().into_peer_id();
+    let client_peer_id = client_id.public().into_peer_id();
+
+    let server_transport =
+        Compat03As01::new(TcpConfig::new().with_upgrade(TlsConfig::new(&server_id).unwrap()));
+    let client_transport =
+        Compat03As01::new(TcpConfig::new().with_upgrade(TlsConfig::new(&client_id).unwrap()));
+
+    let mut server = Transport01::listen_on(server_transport, "/ip4/127.0.0.1/tcp/0".parse().unwrap())
+        .unwrap();
+
+    let server_address = server.by_ref().wait()
+        .next()
+        .expect("some event")
+        .expect("no error")
+        .into_new_address()
+        .expect("listen address");
+
+    let message = b"hello over TLS".to_vec();
+    let expected = message.clone();
+
+    let server = server.take(1)
+        .filter_map(ListenerEvent::into_upgrade)
+        .and_then(|client| client.0)
+        .map_err(|e| panic!("server error: {}", e))
+        .and_then(move |(peer_id, client)| {
+            assert_eq!(peer_id, client_peer_id);
+            io::read_to_end(client, Vec::new())
+        })
+        .for_each(move |(_, msg)| {
+            assert_eq!(msg, expected);
+            Ok(())
+        });
+
+    let client = Transport01::dial(client_transport, server_address).unwrap()
+        .map_err(|e| panic!("client error: {}", e))
+        .and_then(move |(peer_id, server)| {
+            assert_eq!(peer_id, server_peer_id);
+            io::write_all(server, message)
+                .and_then(|(server, _)| io::flush(server))
+                .and_then(io::shutdown)
+        })
+        .map(|_| ());
+
+    let future = client.join(server)
+        .map_err(|e| panic!("{:?}", e))
+        .map(|_| ());
+
+    tokio::run(future)
+}
@@ -18,7 +18,7 @@
 // FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // DEALINGS IN THE SOFTWARE.
 
-use crate::{Multiaddr, core::{Transport, transport::{ListenerEvent, TransportError}}};
+use crate::{Multiaddr, core::{Transport, compat::{Compat03As01, Transport01}, transport::{ListenerEvent, TransportError}}};
 use futures::{prelude::*, try_ready};
 use lazy_static::lazy_static;
 use parking_lot::Mutex;
@@ -56,27 +56,31 @@ impl<TInner> BandwidthLogging<TInner> {
     }
 }
 
-impl<TInner> Transport for BandwidthLogging<TInner>
+impl<TInner> Transport01 for BandwidthLogging<TInner>
 where
     TInner: Transport,
 {
     type Output = BandwidthConnecLogging<TInner::Output>;
     type Error = TInner::Error;
-    type Listener = BandwidthListener<TInner::Listener>;
-    type ListenerUpgrade = BandwidthFuture<TInner::ListenerUpgrade>;
-    type Dial = BandwidthFuture<TInner::Dial>;
+    type Listener = BandwidthListener<<Compat03As01<TInner> as Transport01>::Listener>;
+    type ListenerUpgrade = BandwidthFuture<<Compat03As01<TInner> as Transport01>::ListenerUpgrade>;
+    type Dial = BandwidthFuture<<Compat03As01<TInner> as Transport01>::Dial>;
 
     fn listen_on(self, addr: Multiaddr) -> Result<Self::Listener, TransportError<Self::Error>> {
         let sinks = self.sinks;
-        self.inner
-            .listen_on(addr)
+        Transport01::listen_on(Compat03As01::new(self.inner), addr)
             .map(move |inner| BandwidthListener { inner, sinks })
     }
 
     fn dial(self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
         let sinks = self.sinks;
-        self.inner
-            .dial(addr)
+        Transport01::dial(Compat03As01::new(self.inner), addr)
+            .map(move |fut| BandwidthFuture { inner: fut, sinks })
+    }
+
+    fn dial_as_listener(self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
+        let sinks = self.sinks;
+        Transport01::dial_as_listener(Compat03As01::new(self.inner), addr)
             .map(move |fut| BandwidthFuture { inner: fut, sinks })
     }
 }
@@ -161,8 +161,12 @@ pub use multihash;
 pub use tokio_io;
 pub use tokio_codec;
 
+#[doc(inline)]
+pub use libp2p_autonat as autonat;
 #[doc(inline)]
 pub use libp2p_core as core;
+#[doc(inline)]
+pub use libp2p_dcutr as dcutr;
 #[cfg(not(any(target_os = "emscripten", target_os = "unknown")))]
 #[doc(inline)]
 pub use libp2p_deflate as deflate;
@@ -176,6 +180,10 @@ pub use libp2p_kad as kad;
 #[doc(inline)]
 pub use libp2p_floodsub as floodsub;
 #[doc(inline)]
+pub use libp2p_gossipsub as gossipsub;
+#[doc(inline)]
+pub use libp2p_metrics as metrics;
+#[doc(inline)]
 pub use libp2p_mplex as mplex;
 #[cfg(not(any(target_os = "emscripten", target_os = "unknown")))]
 #[doc(inline)]
@@ -187,15 +195,30 @@ pub use libp2p_noise as noise;
 pub use libp2p_ping as ping;
 #[doc(inline)]
 pub use libp2p_plaintext as plaintext;
+#[cfg(not(any(target_os = "emscripten", target_os = "unknown")))]
+#[doc(inline)]
+pub use libp2p_portmap as portmap;
+#[cfg(not(any(target_os = "emscripten", target_os = "unknown")))]
+#[doc(inline)]
+pub use libp2p_quic as quic;
 #[doc(inline)]
 pub use libp2p_ratelimit as ratelimit;
 #[doc(inline)]
+pub use libp2p_relay as relay;
+#[doc(inline)]
+pub use libp2p_rendezvous as rendezvous;
+#[doc(inline)]
+pub use libp2p_request_response as request_response;
+#[doc(inline)]
 pub use libp2p_secio as secio;
 #[doc(inline)]
 pub use libp2p_swarm as swarm;
 #[cfg(not(any(target_os = "emscripten", target_os = "unknown")))]
 #[doc(inline)]
 pub use libp2p_tcp as tcp;
+#[cfg(not(any(target_os = "emscripten", target_os = "unknown")))]
+#[doc(inline)]
+pub use libp2p_tls as tls;
 #[doc(inline)]
 pub use libp2p_uds as uds;
 #[doc(inline)]
@@ -224,7 +247,7 @@ pub use self::simple::SimpleProtocol;
 pub use self::swarm::Swarm;
 pub use self::transport_ext::TransportExt;
 
-use futures::prelude::*;
+use futures03::TryFutureExt as _;
 use std::{error, io, time::Duration};
 
 /// Builds a `Transport` that supports the most commonly-used protocols that libp2p supports.
@@ -256,7 +279,7 @@ pub fn build_tcp_ws_secio_mplex_yamux(keypair: identity::Keypair)
                 .map_inbound(move |muxer| (peer_id, muxer))
                 .map_outbound(move |muxer| (peer_id2, muxer));
             core::upgrade::apply(output.stream, upgrade, endpoint)
-                .map(|(id, muxer)| (id, core::muxing::StreamMuxerBox::new(muxer)))
+                .map_ok(|(id, muxer)| (id, core::muxing::StreamMuxerBox::new(muxer)))
         })
         .with_timeout(Duration::from_secs(20))
 }
@@ -324,4 +347,8 @@ impl Transport for CommonTransport {
     fn dial(self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
         self.inner.inner.dial(addr)
     }
+
+    fn dial_as_listener(self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
+        self.inner.inner.dial_as_listener(addr)
+    }
 }
@@ -18,6 +18,7 @@
 // FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // DEALINGS IN THE SOFTWARE.
 
+use crate::core::compat::Compat01As03;
 use crate::core::upgrade::{InboundUpgrade, OutboundUpgrade, UpgradeInfo, Negotiated};
 use bytes::Bytes;
 use futures::{future::FromErr, prelude::*};
@@ -75,12 +76,12 @@ where
 {
     type Output = O::Item;
     type Error = IoError;
-    type Future = FromErr<O::Future, IoError>;
+    type Future = Compat01As03<FromErr<O::Future, IoError>>;
 
     #[inline]
     fn upgrade_inbound(self, socket: Negotiated<C>, _: Self::Info) -> Self::Future {
         let upgrade = &self.upgrade;
-        upgrade(socket).into_future().from_err()
+        Compat01As03::new(upgrade(socket).into_future().from_err())
     }
 }
 
@@ -92,11 +93,11 @@ where
 {
     type Output = O::Item;
     type Error = IoError;
-    type Future = FromErr<O::Future, IoError>;
+    type Future = Compat01As03<FromErr<O::Future, IoError>>;
 
     #[inline]
     fn upgrade_outbound(self, socket: Negotiated<C>, _: Self::Info) -> Self::Future {
         let upgrade = &self.upgrade;
-        upgrade(socket).into_future().from_err()
+        Compat01As03::new(upgrade(socket).into_future().from_err())
     }
 }
@@ -21,8 +21,8 @@
 //! Provides the `TransportExt` trait.
 
 use crate::{bandwidth::BandwidthLogging, bandwidth::BandwidthSinks, ratelimit::RateLimited, Transport};
+use libp2p_core::executor::TokioExecutor;
 use std::{io, sync::Arc, time::Duration};
-use tokio_executor::DefaultExecutor;
 
 /// Trait automatically implemented on all objects that implement `Transport`. Provides some
 /// additional utilities.
@@ -50,7 +50,7 @@ pub trait TransportExt: Transport {
         Self: Sized,
     {
         RateLimited::new(
-            &mut DefaultExecutor::current(),
+            &TokioExecutor,
             self,
             max_read_bytes_per_sec,
             max_write_bytes_per_sec,
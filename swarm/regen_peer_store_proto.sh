@@ -0,0 +1,13 @@
+#!/bin/sh
+
+# This script regenerates the `src/peer_store_proto.rs` file from `peer_store.proto`.
+
+sudo docker run --rm -v `pwd`:/usr/code:z -w /usr/code rust /bin/bash -c " \
+    apt-get update; \
+    apt-get install -y protobuf-compiler; \
+    cargo install --version 2.3.0 protobuf-codegen; \
+    protoc --rust_out . peer_store.proto"
+
+sudo chown $USER:$USER *.rs
+
+mv -f peer_store.rs ./src/peer_store_proto.rs
@@ -0,0 +1,416 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Accounting of the bandwidth used by each protocol with each peer.
+//!
+//! Contrary to the bandwidth logging of the transport, which only sees whole connections, a
+//! [`ProtocolBandwidth`] counts the bytes read from and written to each substream, and attributes
+//! them to the remote peer and to the protocol negotiated on the substream with
+//! multistream-select. The bytes exchanged during the negotiation itself are attributed to the
+//! negotiated protocol as well, while the traffic of substreams whose negotiation fails is not
+//! accounted for.
+//!
+//! The accounting is enabled with
+//! [`SwarmBuilder::protocol_bandwidth`](crate::SwarmBuilder::protocol_bandwidth), and the counters
+//! are then available through
+//! [`ExpandedSwarm::protocol_bandwidth`](crate::ExpandedSwarm::protocol_bandwidth).
+
+use crate::observer::{MeterState, SwarmObserver};
+use libp2p_core::{ConnectedPoint, Endpoint, PeerId, clock, muxing::SubstreamMeter};
+use parking_lot::Mutex;
+use smallvec::{SmallVec, smallvec};
+use std::{cmp, collections::HashMap, sync::Arc, time::Duration};
+use wasm_timer::Instant;
+
+/// The traffic with a peer and/or on a protocol.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct Traffic {
+    /// Total number of bytes received.
+    pub inbound: u64,
+    /// Total number of bytes sent.
+    pub outbound: u64,
+    /// Average number of bytes received per second over the rolling window.
+    pub inbound_per_sec: u64,
+    /// Average number of bytes sent per second over the rolling window.
+    pub outbound_per_sec: u64,
+}
+
+impl std::ops::Add for Traffic {
+    type Output = Traffic;
+
+    fn add(self, other: Traffic) -> Traffic {
+        Traffic {
+            inbound: self.inbound.saturating_add(other.inbound),
+            outbound: self.outbound.saturating_add(other.outbound),
+            inbound_per_sec: self.inbound_per_sec.saturating_add(other.inbound_per_sec),
+            outbound_per_sec: self.outbound_per_sec.saturating_add(other.outbound_per_sec),
+        }
+    }
+}
+
+/// Byte counters of the substreams, keyed by remote peer and negotiated protocol.
+///
+/// The counters of a peer are created on its first traffic, possibly before its connection is
+/// reported as established, and dropped once its last connection is closed. The counters of each
+/// protocol, aggregated over all the peers, are kept for the lifetime of the swarm.
+///
+/// Cloning a `ProtocolBandwidth` yields a handle to the same counters.
+#[derive(Debug, Clone)]
+pub struct ProtocolBandwidth {
+    inner: Arc<Mutex<Inner>>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    /// The instant the seconds of the rolling windows are counted from.
+    epoch: Instant,
+    /// Length of the rolling windows, in seconds.
+    window: u32,
+    /// Counters per remote peer and protocol.
+    peers: HashMap<(PeerId, Vec<u8>), Counters>,
+    /// Counters per protocol, aggregated over all peers.
+    protocols: HashMap<Vec<u8>, Counters>,
+    /// Number of open connections per peer.
+    connections: HashMap<PeerId, usize>,
+    /// Current session of each peer, which starts with its first connection or substream and
+    /// ends when its last connection is closed. Only the traffic of the substreams of the current
+    /// session is attributed to the peer.
+    sessions: HashMap<PeerId, u64>,
+    /// Identifier of the next session.
+    next_session: u64,
+}
+
+impl ProtocolBandwidth {
+    /// Creates new counters, whose rates are averaged over the given rolling window.
+    ///
+    /// The window is rounded up to the second, and capped to a day.
+    pub fn new(window: Duration) -> Self {
+        let mut seconds = cmp::min(window.as_secs(), 86400) as u32;
+        if window.subsec_nanos() > 0 {
+            seconds += 1;
+        }
+        ProtocolBandwidth {
+            inner: Arc::new(Mutex::new(Inner {
+                epoch: clock::now(),
+                window: cmp::max(seconds, 1),
+                peers: HashMap::new(),
+                protocols: HashMap::new(),
+                connections: HashMap::new(),
+                sessions: HashMap::new(),
+                next_session: 0,
+            }))
+        }
+    }
+
+    /// Returns the traffic with the given peer on the given protocol.
+    pub fn traffic(&self, peer_id: &PeerId, protocol: impl AsRef<[u8]>) -> Option<Traffic> {
+        let mut inner = self.inner.lock();
+        let now = inner.now();
+        let key = (peer_id.clone(), protocol.as_ref().to_vec());
+        inner.peers.get_mut(&key).map(|c| c.traffic(now))
+    }
+
+    /// Returns the traffic with the given peer, over all protocols.
+    pub fn peer_traffic(&self, peer_id: &PeerId) -> Traffic {
+        let mut inner = self.inner.lock();
+        let now = inner.now();
+        inner.peers.iter_mut()
+            .filter(|((p, _), _)| p == peer_id)
+            .fold(Traffic::default(), |t, (_, c)| t + c.traffic(now))
+    }
+
+    /// Returns the traffic on the given protocol, over all peers.
+    pub fn protocol_traffic(&self, protocol: impl AsRef<[u8]>) -> Traffic {
+        let mut inner = self.inner.lock();
+        let now = inner.now();
+        inner.protocols.get_mut(protocol.as_ref()).map(|c| c.traffic(now)).unwrap_or_default()
+    }
+
+    /// Returns the traffic of each connected peer on each protocol.
+    pub fn entries(&self) -> Vec<(PeerId, Vec<u8>, Traffic)> {
+        let mut inner = self.inner.lock();
+        let now = inner.now();
+        inner.peers.iter_mut()
+            .map(|((peer, protocol), c)| (peer.clone(), protocol.clone(), c.traffic(now)))
+            .collect()
+    }
+
+    /// Returns the traffic on each protocol, over all peers.
+    pub fn protocols(&self) -> Vec<(Vec<u8>, Traffic)> {
+        let mut inner = self.inner.lock();
+        let now = inner.now();
+        inner.protocols.iter_mut()
+            .map(|(protocol, c)| (protocol.clone(), c.traffic(now)))
+            .collect()
+    }
+}
+
+impl Inner {
+    fn now(&self) -> u64 {
+        clock::now().duration_since(self.epoch).as_secs()
+    }
+
+    /// Returns the current session of a peer, starting a new one if needed.
+    fn session(&mut self, peer_id: &PeerId) -> u64 {
+        let next_session = &mut self.next_session;
+        *self.sessions.entry(peer_id.clone()).or_insert_with(|| {
+            let session = *next_session;
+            *next_session += 1;
+            session
+        })
+    }
+
+    fn record(&mut self, key: &(PeerId, Vec<u8>), session: u64, read: u64, written: u64) {
+        let now = self.now();
+        let window = self.window;
+        // The counters of a peer whose connections have all been closed would never be removed,
+        // so the remaining traffic of its substreams is only attributed to the protocol.
+        if self.sessions.get(&key.0) == Some(&session) {
+            let c = self.peers.entry(key.clone()).or_insert_with(|| Counters::new(window, now));
+            c.inbound.add(now, read);
+            c.outbound.add(now, written);
+        }
+        let c = self.protocols.entry(key.1.clone()).or_insert_with(|| Counters::new(window, now));
+        c.inbound.add(now, read);
+        c.outbound.add(now, written);
+    }
+}
+
+impl SwarmObserver for ProtocolBandwidth {
+    fn connection_established(&self, peer_id: &PeerId, _: &ConnectedPoint) {
+        let mut inner = self.inner.lock();
+        inner.session(peer_id);
+        *inner.connections.entry(peer_id.clone()).or_insert(0) += 1;
+    }
+
+    fn connection_closed(&self, peer_id: &PeerId, _: &ConnectedPoint) {
+        let mut inner = self.inner.lock();
+        let remaining = match inner.connections.get_mut(peer_id) {
+            Some(n) => { *n = n.saturating_sub(1); *n }
+            None => 0
+        };
+        if remaining == 0 {
+            inner.connections.remove(peer_id);
+            inner.sessions.remove(peer_id);
+            inner.peers.retain(|(p, _), _| p != peer_id);
+        }
+    }
+
+    fn substream_meter(&self, peer_id: &PeerId, _: Endpoint) -> Option<Arc<dyn SubstreamMeter>> {
+        // The substreams of a connection may be opened before the connection is reported as
+        // established, in which case they start the session of the peer.
+        let session = self.inner.lock().session(peer_id);
+        Some(Arc::new(BandwidthMeter {
+            inner: self.inner.clone(),
+            peer_id: peer_id.clone(),
+            session,
+            state: Mutex::new(MeterState::new()),
+        }))
+    }
+}
+
+/// Meter of a single substream.
+struct BandwidthMeter {
+    inner: Arc<Mutex<Inner>>,
+    peer_id: PeerId,
+    /// The session of the peer the substream belongs to.
+    session: u64,
+    state: Mutex<MeterState<(PeerId, Vec<u8>)>>,
+}
+
+impl SubstreamMeter for BandwidthMeter {
+    fn on_negotiated(&self, protocol: &[u8]) {
+        let key = (self.peer_id.clone(), protocol.to_vec());
+        let (read, written) = self.state.lock().negotiated(key.clone());
+        self.inner.lock().record(&key, self.session, read, written);
+    }
+
+    fn on_read(&self, bytes: usize) {
+        if let Some(key) = self.state.lock().on_read(bytes) {
+            self.inner.lock().record(key, self.session, bytes as u64, 0);
+        }
+    }
+
+    fn on_write(&self, bytes: usize) {
+        if let Some(key) = self.state.lock().on_write(bytes) {
+            self.inner.lock().record(key, self.session, 0, bytes as u64);
+        }
+    }
+}
+
+/// The inbound and outbound counters of a peer and/or protocol.
+#[derive(Debug)]
+struct Counters {
+    inbound: Rolling,
+    outbound: Rolling,
+}
+
+impl Counters {
+    fn new(window: u32, now: u64) -> Self {
+        Counters { inbound: Rolling::new(window, now), outbound: Rolling::new(window, now) }
+    }
+
+    fn traffic(&mut self, now: u64) -> Traffic {
+        Traffic {
+            inbound: self.inbound.total,
+            outbound: self.outbound.total,
+            inbound_per_sec: self.inbound.rate(now),
+            outbound_per_sec: self.outbound.rate(now),
+        }
+    }
+}
+
+/// Counts bytes, in total and per second over a rolling window.
+#[derive(Debug)]
+struct Rolling {
+    /// Total number of bytes.
+    total: u64,
+    /// Number of bytes for each second of the window, indexed by the second modulo the length of
+    /// the window.
+    seconds: SmallVec<[u64; 8]>,
+    /// The last second at which `seconds` has been updated.
+    latest: u64,
+}
+
+impl Rolling {
+    fn new(window: u32, now: u64) -> Self {
+        Rolling { total: 0, seconds: smallvec![0; window as usize], latest: now }
+    }
+
+    fn add(&mut self, now: u64, bytes: u64) {
+        self.advance(now);
+        let len = self.seconds.len() as u64;
+        self.seconds[(now % len) as usize] += bytes;
+        self.total = self.total.saturating_add(bytes);
+    }
+
+    fn rate(&mut self, now: u64) -> u64 {
+        self.advance(now);
+        self.seconds.iter().fold(0u64, |a, &b| a.saturating_add(b)) / self.seconds.len() as u64
+    }
+
+    /// Clears the seconds that have elapsed since the last update.
+    fn advance(&mut self, now: u64) {
+        let len = self.seconds.len() as u64;
+        if now.saturating_sub(self.latest) >= len {
+            for s in self.seconds.iter_mut() {
+                *s = 0;
+            }
+        } else {
+            for second in self.latest + 1 ..= now {
+                self.seconds[(second % len) as usize] = 0;
+            }
+        }
+        self.latest = cmp::max(self.latest, now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libp2p_core::Multiaddr;
+
+    #[test]
+    fn rolling_window() {
+        let mut r = Rolling::new(4, 10);
+        r.add(10, 400);
+        r.add(11, 400);
+        assert_eq!(r.rate(11), 200);
+        assert_eq!(r.rate(13), 200);
+        // The bytes of second 10 leave the window.
+        assert_eq!(r.rate(14), 100);
+        assert_eq!(r.rate(100), 0);
+        assert_eq!(r.total, 800);
+    }
+
+    #[test]
+    fn traffic_per_peer_and_protocol() {
+        let bandwidth = ProtocolBandwidth::new(Duration::from_secs(10));
+        let peer = PeerId::random();
+        let endpoint = ConnectedPoint::Dialer { address: Multiaddr::empty(), role_override: Endpoint::Dialer };
+        bandwidth.connection_established(&peer, &endpoint);
+
+        let meter = bandwidth.substream_meter(&peer, Endpoint::Dialer).unwrap();
+        meter.on_write(20);
+        meter.on_read(10);
+        assert_eq!(bandwidth.traffic(&peer, "/ping"), None);
+        meter.on_negotiated(b"/ping");
+        meter.on_write(32);
+        meter.on_read(32);
+
+        let other = bandwidth.substream_meter(&peer, Endpoint::Listener).unwrap();
+        other.on_negotiated(b"/kad");
+        other.on_read(100);
+
+        // A substream whose negotiation fails is not accounted for.
+        let failed = bandwidth.substream_meter(&peer, Endpoint::Listener).unwrap();
+        failed.on_read(7);
+
+        let ping = bandwidth.traffic(&peer, "/ping").unwrap();
+        assert_eq!((ping.inbound, ping.outbound), (42, 52));
+        let kad = bandwidth.traffic(&peer, "/kad").unwrap();
+        assert_eq!((kad.inbound, kad.outbound), (100, 0));
+        let total = bandwidth.peer_traffic(&peer);
+        assert_eq!((total.inbound, total.outbound), (142, 52));
+        assert_eq!(bandwidth.entries().len(), 2);
+
+        // The counters of the peer are dropped on disconnection, not those of the protocols.
+        bandwidth.connection_closed(&peer, &endpoint);
+        assert_eq!(bandwidth.traffic(&peer, "/ping"), None);
+        assert_eq!(bandwidth.protocol_traffic("/ping").inbound, 42);
+        assert_eq!(bandwidth.protocols().len(), 2);
+
+        // The traffic of the substreams that outlive the connection is no longer attributed to
+        // the peer.
+        meter.on_read(8);
+        assert_eq!(bandwidth.traffic(&peer, "/ping"), None);
+        assert!(bandwidth.entries().is_empty());
+        assert_eq!(bandwidth.protocol_traffic("/ping").inbound, 50);
+    }
+
+    #[test]
+    fn traffic_before_connection_established() {
+        let bandwidth = ProtocolBandwidth::new(Duration::from_secs(10));
+        let peer = PeerId::random();
+        let endpoint = ConnectedPoint::Dialer { address: Multiaddr::empty(), role_override: Endpoint::Dialer };
+
+        // The substreams of a connection may be metered before it is reported as established.
+        let meter = bandwidth.substream_meter(&peer, Endpoint::Listener).unwrap();
+        meter.on_negotiated(b"/ipfs/id/1.0.0");
+        meter.on_write(64);
+        assert_eq!(bandwidth.traffic(&peer, "/ipfs/id/1.0.0").unwrap().outbound, 64);
+
+        bandwidth.connection_established(&peer, &endpoint);
+        meter.on_write(16);
+        assert_eq!(bandwidth.traffic(&peer, "/ipfs/id/1.0.0").unwrap().outbound, 80);
+
+        // A new connection after a disconnection starts from scratch, and the substreams of the
+        // previous one are no longer attributed to the peer.
+        bandwidth.connection_closed(&peer, &endpoint);
+        bandwidth.connection_established(&peer, &endpoint);
+        meter.on_write(8);
+        assert_eq!(bandwidth.traffic(&peer, "/ipfs/id/1.0.0"), None);
+        let meter = bandwidth.substream_meter(&peer, Endpoint::Dialer).unwrap();
+        meter.on_negotiated(b"/ipfs/id/1.0.0");
+        meter.on_write(4);
+        assert_eq!(bandwidth.traffic(&peer, "/ipfs/id/1.0.0").unwrap().outbound, 4);
+        assert_eq!(bandwidth.protocol_traffic("/ipfs/id/1.0.0").outbound, 92);
+    }
+}
@@ -18,15 +18,21 @@
 // FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // DEALINGS IN THE SOFTWARE.
 
+use crate::peer_score::Misbehaviour;
+use crate::peer_store::PeerStore;
 use crate::protocols_handler::{IntoProtocolsHandler, ProtocolsHandler};
-use libp2p_core::{ConnectedPoint, Multiaddr, PeerId};
-use futures::prelude::*;
+use libp2p_core::{ConnectedPoint, Multiaddr, PeerId, nodes::ConnectionId, nodes::network::ConnectionLimit};
+use futures03::task::{Context, Poll};
 use std::error;
 
 /// A behaviour for the network. Allows customizing the swarm.
 ///
 /// This trait has been designed to be composable. Multiple implementations can be combined into
 /// one that handles all the behaviours at once.
+///
+/// Behaviours whose `poll` is written against futures 0.1 can implement
+/// [`NetworkBehaviour01`](crate::compat::NetworkBehaviour01) instead, which provides an
+/// implementation of this trait.
 pub trait NetworkBehaviour {
     /// Handler for all the protocols the network behaviour supports.
     type ProtocolsHandler: IntoProtocolsHandler;
@@ -45,7 +51,7 @@ pub trait NetworkBehaviour {
     /// The network behaviour (ie. the implementation of this trait) and the handlers it has
     /// spawned (ie. the objects returned by `new_handler`) can communicate by passing messages.
     /// Messages sent from the handler to the behaviour are injected with `inject_node_event`, and
-    /// the behaviour can send a message to the handler by making `poll` return `SendEvent`.
+    /// the behaviour can send a message to the handler by making `poll` return `NotifyHandler`.
     fn new_handler(&mut self) -> Self::ProtocolsHandler;
 
     /// Addresses that this behaviour is aware of for this specific peer, and that may allow
@@ -59,37 +65,44 @@ pub trait NetworkBehaviour {
     /// Indicates the behaviour that we connected to the node with the given peer id through the
     /// given endpoint.
     ///
+    /// This is only called for the first connection to the node. Additional connections to the
+    /// same node are reported with `inject_connection_established` only.
+    ///
     /// This node now has a handler (as spawned by `new_handler`) running in the background.
     fn inject_connected(&mut self, peer_id: PeerId, endpoint: ConnectedPoint);
 
     /// Indicates the behaviour that we disconnected from the node with the given peer id. The
-    /// endpoint is the one we used to be connected to.
+    /// endpoint is the one of the last connection that has been closed.
     ///
-    /// There is no handler running anymore for this node. Any event that has been sent to it may
-    /// or may not have been processed by the handler.
+    /// This is only called once all the connections to the node have been closed. There is no
+    /// handler running anymore for this node. Any event that has been sent to it may or may not
+    /// have been processed by the handler.
     fn inject_disconnected(&mut self, peer_id: &PeerId, endpoint: ConnectedPoint);
 
-    /// Indicates the behaviour that we replace the connection from the node with another.
+    /// Informs the behaviour about a newly established connection to a peer.
     ///
-    /// The handler that used to be dedicated to this node has been destroyed and replaced with a
-    /// new one. Any event that has been sent to it may or may not have been processed.
+    /// This is called for every connection, including the first one, in which case it is called
+    /// before `inject_connected`.
+    fn inject_connection_established(&mut self, _peer_id: &PeerId, _connection: &ConnectionId, _endpoint: &ConnectedPoint) {
+    }
+
+    /// Informs the behaviour about a closed connection to a peer.
     ///
-    /// The default implementation of this method calls `inject_disconnected` followed with
-    /// `inject_connected`. This is a logically safe way to implement this behaviour. However, you
-    /// may want to overwrite this method in the situations where this isn't appropriate.
-    fn inject_replaced(&mut self, peer_id: PeerId, closed_endpoint: ConnectedPoint, new_endpoint: ConnectedPoint) {
-        self.inject_disconnected(&peer_id, closed_endpoint);
-        self.inject_connected(peer_id, new_endpoint);
+    /// This is called for every connection, including the last one, in which case it is called
+    /// before `inject_disconnected`. The handler of this connection is no longer running.
+    fn inject_connection_closed(&mut self, _peer_id: &PeerId, _connection: &ConnectionId, _endpoint: &ConnectedPoint) {
     }
 
-    /// Informs the behaviour about an event generated by the handler dedicated to the peer identified by `peer_id`.
-    /// for the behaviour.
+    /// Informs the behaviour about an event generated by the handler dedicated to the connection
+    /// identified by `connection`, with the peer identified by `peer_id`.
     ///
     /// The `peer_id` is guaranteed to be in a connected state. In other words, `inject_connected`
-    /// has previously been called with this `PeerId`.
+    /// has previously been called with this `PeerId`, and `inject_connection_established` with
+    /// this connection.
     fn inject_node_event(
         &mut self,
         peer_id: PeerId,
+        connection: ConnectionId,
         event: <<Self::ProtocolsHandler as IntoProtocolsHandler>::Handler as ProtocolsHandler>::OutEvent
     );
 
@@ -108,6 +121,16 @@ pub trait NetworkBehaviour {
     fn inject_dial_failure(&mut self, _peer_id: &PeerId) {
     }
 
+    /// Indicates to the behaviour that a connection has been refused or closed, or that inbound
+    /// substreams of a connection have been refused, because one of the connection limits of the
+    /// swarm was reached.
+    ///
+    /// If a connection we were dialing was refused or closed, `inject_addr_reach_failure` and, if
+    /// applicable, `inject_dial_failure` are called afterwards. This is not the case for refused
+    /// substreams, as their connection stays open.
+    fn inject_connection_limit_reached(&mut self, _peer_id: Option<&PeerId>, _endpoint: &ConnectedPoint, _limit: &ConnectionLimit) {
+    }
+
     /// Indicates to the behaviour that we have started listening on a new multiaddr.
     fn inject_new_listen_addr(&mut self, _addr: &Multiaddr) {
     }
@@ -121,12 +144,16 @@ pub trait NetworkBehaviour {
     fn inject_new_external_addr(&mut self, _addr: &Multiaddr) {
     }
 
+    /// Indicates to the behaviour that an external address of ours is no longer reachable.
+    fn inject_expired_external_addr(&mut self, _addr: &Multiaddr) {
+    }
+
     /// Polls for things that swarm should do.
     ///
-    /// This API mimics the API of the `Stream` trait. The method may register the current task in
-    /// order to wake it up at a later point in time.
-    fn poll(&mut self, params: &mut impl PollParameters)
-        -> Async<NetworkBehaviourAction<<<Self::ProtocolsHandler as IntoProtocolsHandler>::Handler as ProtocolsHandler>::InEvent, Self::OutEvent>>;
+    /// This API mimics the API of the `Stream` trait. The given `Context` is woken up when the
+    /// behaviour is ready to be polled again.
+    fn poll(&mut self, cx: &mut Context<'_>, params: &mut impl PollParameters)
+        -> Poll<NetworkBehaviourAction<<<Self::ProtocolsHandler as IntoProtocolsHandler>::Handler as ProtocolsHandler>::InEvent, Self::OutEvent>>;
 }
 
 /// Parameters passed to `poll()`, that the `NetworkBehaviour` has access to.
@@ -154,6 +181,12 @@ pub trait PollParameters {
 
     /// Returns the peer id of the local node.
     fn local_peer_id(&self) -> &PeerId;
+
+    /// Returns the address book shared by all the behaviours of the swarm.
+    fn peer_store(&self) -> &PeerStore;
+
+    /// Returns the address book shared by all the behaviours of the swarm, for modification.
+    fn peer_store_mut(&mut self) -> &mut PeerStore;
 }
 
 /// Used when deriving `NetworkBehaviour`. When deriving `NetworkBehaviour`, must be implemented
@@ -179,9 +212,20 @@ pub enum NetworkBehaviourAction<TInEvent, TOutEvent> {
         address: Multiaddr,
     },
 
+    /// Same as `DialAddress`, but the local node takes the role of the listener in the upgrades
+    /// of the connection.
+    ///
+    /// This is used for hole punching, where the remote dials us at the same time, see
+    /// [`Transport::dial_as_listener`](libp2p_core::Transport::dial_as_listener).
+    DialAddressAsListener {
+        /// The address to dial.
+        address: Multiaddr,
+    },
+
     /// Instructs the swarm to dial a known `PeerId`.
     ///
-    /// The `addresses_of_peer` method is called to determine which addresses to attempt to reach.
+    /// The `addresses_of_peer` method is called to determine which addresses to attempt to reach,
+    /// followed by the addresses of the peer in the `PeerStore`.
     ///
     /// If we were already trying to dial this node, the addresses that are not yet in the queue of
     /// addresses to try are added back to this queue.
@@ -193,17 +237,20 @@ pub enum NetworkBehaviourAction<TInEvent, TOutEvent> {
         peer_id: PeerId,
     },
 
-    /// Instructs the `Swarm` to send a message to the handler dedicated to the connection with the peer.
+    /// Instructs the `Swarm` to send a message to the handler dedicated to a connection with the
+    /// peer.
     ///
-    /// If the `Swarm` is connected to the peer, the message is delivered to the remote's
-    /// protocol handler. If there is no connection to the peer, the message is ignored.
-    /// To ensure delivery, the `NetworkBehaviour` must keep track of connected peers.
+    /// If the `Swarm` is connected to the peer, the message is delivered to the protocol handler
+    /// of the connection designated by `handler`. If there is no such connection, the message is
+    /// ignored. To ensure delivery, the `NetworkBehaviour` must keep track of connected peers.
     ///
     /// Note that even if the peer is currently connected, connections can get closed
     /// at any time and thus the message may not reach its destination.
-    SendEvent {
+    NotifyHandler {
         /// The peer to which to send the message.
         peer_id: PeerId,
+        /// The connection, or connections, whose handler is to receive the message.
+        handler: NotifyHandler,
         /// The message to send.
         event: TInEvent,
     },
@@ -218,4 +265,42 @@ pub enum NetworkBehaviourAction<TInEvent, TOutEvent> {
         /// The observed address of the local node.
         address: Multiaddr,
     },
+
+    /// Informs the `Swarm` about an external address of the local node that has been confirmed
+    /// to be reachable, for example by a remote successfully dialing it.
+    ///
+    /// Contrary to `ReportObservedAddr`, the address is added as is to the external addresses,
+    /// without being translated by the transport.
+    ReportExternalAddr {
+        /// The confirmed address of the local node.
+        address: Multiaddr,
+    },
+
+    /// Informs the `Swarm` that an external address of the local node is no longer reachable,
+    /// for example because the port mapping that forwarded it has expired.
+    ///
+    /// The address is removed from the external addresses, whatever the number of times it has
+    /// been reported.
+    ReportExpiredExternalAddr {
+        /// The address of the local node that is no longer reachable.
+        address: Multiaddr,
+    },
+
+    /// Informs the `Swarm` that a remote peer misbehaved, which lowers its score in the
+    /// [`PeerScore`](crate::PeerScore) and can get it banned.
+    ReportMisbehaviour {
+        /// The peer that misbehaved.
+        peer_id: PeerId,
+        /// What the peer did.
+        misbehaviour: Misbehaviour,
+    },
+}
+
+/// The options w.r.t. which connection handler to notify of an event.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum NotifyHandler {
+    /// Notify the handler of a particular connection.
+    One(ConnectionId),
+    /// Notify an arbitrary connection handler of the peer.
+    Any,
 }
@@ -0,0 +1,746 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+//! Compatibility layer for the network behaviours and protocols handlers written against
+//! futures 0.1.
+//!
+//! [`NetworkBehaviour`] and [`ProtocolsHandler`] are built on `std::future::Future`. This module
+//! provides their futures 0.1 flavour, [`NetworkBehaviour01`] and [`ProtocolsHandler01`], whose
+//! `poll` methods notify the current futures 0.1 task. Every type that implements one of them
+//! automatically implements its futures 0.3 counterpart, and
+//! [`Compat03As01`](libp2p_core::compat::Compat03As01) turns a futures 0.3 behaviour or handler
+//! back into the futures 0.1 flavour.
+//!
+//! The upgrades of both flavours are the same; see the `compat` module of `libp2p-core` for
+//! upgrades written against futures 0.1.
+
+use crate::behaviour::{NetworkBehaviour, NetworkBehaviourAction, PollParameters};
+use crate::protocols_handler::{
+    IntoProtocolsHandler,
+    KeepAlive,
+    ProtocolsHandler,
+    ProtocolsHandlerEvent,
+    ProtocolsHandlerUpgrErr,
+    SubstreamProtocol
+};
+use futures::{Async, Poll as Poll01};
+use futures03::task::{Context, Poll};
+use libp2p_core::{
+    ConnectedPoint,
+    Multiaddr,
+    PeerId,
+    compat::{Compat03As01, with_context, with_task},
+    nodes::{ConnectionId, network::ConnectionLimit},
+    upgrade::{InboundUpgrade, OutboundUpgrade}
+};
+use std::error;
+use tokio_io::{AsyncRead, AsyncWrite};
+
+/// Protocols handler built on futures 0.1.
+///
+/// This is the interface of [`ProtocolsHandler`] before its migration to
+/// `std::future::Future`. The methods have the same semantics, except that `poll` notifies the
+/// current futures 0.1 task. Every `ProtocolsHandler01` is also a [`ProtocolsHandler`].
+pub trait ProtocolsHandler01 {
+    /// Custom event that can be received from the outside.
+    type InEvent;
+    /// Custom event that can be produced by the handler and that will be returned to the outside.
+    type OutEvent;
+    /// The type of errors returned by [`ProtocolsHandler01::poll`].
+    type Error: error::Error;
+    /// The type of substreams on which the protocol(s) are negotiated.
+    type Substream: AsyncRead + AsyncWrite;
+    /// The inbound upgrade for the protocol(s) used by the handler.
+    type InboundProtocol: InboundUpgrade<Self::Substream>;
+    /// The outbound upgrade for the protocol(s) used by the handler.
+    type OutboundProtocol: OutboundUpgrade<Self::Substream>;
+    /// The type of additional information passed to an `OutboundSubstreamRequest`.
+    type OutboundOpenInfo;
+
+    /// The [`InboundUpgrade`] to apply on inbound substreams to negotiate the
+    /// desired protocols.
+    fn listen_protocol(&self) -> SubstreamProtocol<Self::InboundProtocol>;
+
+    /// Injects the output of a successful upgrade on a new inbound substream.
+    fn inject_fully_negotiated_inbound(
+        &mut self,
+        protocol: <Self::InboundProtocol as InboundUpgrade<Self::Substream>>::Output
+    );
+
+    /// Injects the output of a successful upgrade on a new outbound substream.
+    fn inject_fully_negotiated_outbound(
+        &mut self,
+        protocol: <Self::OutboundProtocol as OutboundUpgrade<Self::Substream>>::Output,
+        info: Self::OutboundOpenInfo
+    );
+
+    /// Injects an event coming from the outside in the handler.
+    fn inject_event(&mut self, event: Self::InEvent);
+
+    /// Indicates to the handler that upgrading a substream to the given protocol has failed.
+    fn inject_dial_upgrade_error(
+        &mut self,
+        info: Self::OutboundOpenInfo,
+        error: ProtocolsHandlerUpgrErr<
+            <Self::OutboundProtocol as OutboundUpgrade<Self::Substream>>::Error
+        >
+    );
+
+    /// Returns until when the connection should be kept alive.
+    fn connection_keep_alive(&self) -> KeepAlive;
+
+    /// Should behave like `Stream::poll()`.
+    ///
+    /// Returning an error will close the connection to the remote.
+    fn poll(&mut self) -> Poll01<
+        ProtocolsHandlerEvent<Self::OutboundProtocol, Self::OutboundOpenInfo, Self::OutEvent>,
+        Self::Error
+    >;
+}
+
+impl<T> ProtocolsHandler for T
+where
+    T: ProtocolsHandler01,
+{
+    type InEvent = T::InEvent;
+    type OutEvent = T::OutEvent;
+    type Error = T::Error;
+    type Substream = T::Substream;
+    type InboundProtocol = T::InboundProtocol;
+    type OutboundProtocol = T::OutboundProtocol;
+    type OutboundOpenInfo = T::OutboundOpenInfo;
+
+    fn listen_protocol(&self) -> SubstreamProtocol<Self::InboundProtocol> {
+        ProtocolsHandler01::listen_protocol(self)
+    }
+
+    fn inject_fully_negotiated_inbound(
+        &mut self,
+        protocol: <Self::InboundProtocol as InboundUpgrade<Self::Substream>>::Output
+    ) {
+        ProtocolsHandler01::inject_fully_negotiated_inbound(self, protocol)
+    }
+
+    fn inject_fully_negotiated_outbound(
+        &mut self,
+        protocol: <Self::OutboundProtocol as OutboundUpgrade<Self::Substream>>::Output,
+        info: Self::OutboundOpenInfo
+    ) {
+        ProtocolsHandler01::inject_fully_negotiated_outbound(self, protocol, info)
+    }
+
+    fn inject_event(&mut self, event: Self::InEvent) {
+        ProtocolsHandler01::inject_event(self, event)
+    }
+
+    fn inject_dial_upgrade_error(
+        &mut self,
+        info: Self::OutboundOpenInfo,
+        error: ProtocolsHandlerUpgrErr<
+            <Self::OutboundProtocol as OutboundUpgrade<Self::Substream>>::Error
+        >
+    ) {
+        ProtocolsHandler01::inject_dial_upgrade_error(self, info, error)
+    }
+
+    fn connection_keep_alive(&self) -> KeepAlive {
+        ProtocolsHandler01::connection_keep_alive(self)
+    }
+
+    fn poll(&mut self, cx: &mut Context<'_>) -> Poll<
+        Result<
+            ProtocolsHandlerEvent<Self::OutboundProtocol, Self::OutboundOpenInfo, Self::OutEvent>,
+            Self::Error
+        >
+    > {
+        match with_task(cx, || ProtocolsHandler01::poll(self)) {
+            Ok(Async::Ready(event)) => Poll::Ready(Ok(event)),
+            Ok(Async::NotReady) => Poll::Pending,
+            Err(err) => Poll::Ready(Err(err)),
+        }
+    }
+}
+
+impl<THandler> ProtocolsHandler01 for Compat03As01<THandler>
+where
+    THandler: ProtocolsHandler,
+{
+    type InEvent = THandler::InEvent;
+    type OutEvent = THandler::OutEvent;
+    type Error = THandler::Error;
+    type Substream = THandler::Substream;
+    type InboundProtocol = THandler::InboundProtocol;
+    type OutboundProtocol = THandler::OutboundProtocol;
+    type OutboundOpenInfo = THandler::OutboundOpenInfo;
+
+    fn listen_protocol(&self) -> SubstreamProtocol<Self::InboundProtocol> {
+        self.get_ref().listen_protocol()
+    }
+
+    fn inject_fully_negotiated_inbound(
+        &mut self,
+        protocol: <Self::InboundProtocol as InboundUpgrade<Self::Substream>>::Output
+    ) {
+        self.get_mut().inject_fully_negotiated_inbound(protocol)
+    }
+
+    fn inject_fully_negotiated_outbound(
+        &mut self,
+        protocol: <Self::OutboundProtocol as OutboundUpgrade<Self::Substream>>::Output,
+        info: Self::OutboundOpenInfo
+    ) {
+        self.get_mut().inject_fully_negotiated_outbound(protocol, info)
+    }
+
+    fn inject_event(&mut self, event: Self::InEvent) {
+        self.get_mut().inject_event(event)
+    }
+
+    fn inject_dial_upgrade_error(
+        &mut self,
+        info: Self::OutboundOpenInfo,
+        error: ProtocolsHandlerUpgrErr<
+            <Self::OutboundProtocol as OutboundUpgrade<Self::Substream>>::Error
+        >
+    ) {
+        self.get_mut().inject_dial_upgrade_error(info, error)
+    }
+
+    fn connection_keep_alive(&self) -> KeepAlive {
+        self.get_ref().connection_keep_alive()
+    }
+
+    fn poll(&mut self) -> Poll01<
+        ProtocolsHandlerEvent<Self::OutboundProtocol, Self::OutboundOpenInfo, Self::OutEvent>,
+        Self::Error
+    > {
+        match with_context(|cx| self.get_mut().poll(cx)) {
+            Poll::Ready(Ok(event)) => Ok(Async::Ready(event)),
+            Poll::Ready(Err(err)) => Err(err),
+            Poll::Pending => Ok(Async::NotReady),
+        }
+    }
+}
+
+/// Shortcut for the event that a behaviour sends to its handlers.
+type HandlerInEvent<TBehaviour> =
+    <<<TBehaviour as NetworkBehaviour>::ProtocolsHandler as IntoProtocolsHandler>::Handler as ProtocolsHandler>::InEvent;
+
+/// Network behaviour built on futures 0.1.
+///
+/// This is the interface of [`NetworkBehaviour`] before its migration to
+/// `std::future::Future`. The methods have the same semantics, except that `poll` notifies the
+/// current futures 0.1 task. Every `NetworkBehaviour01` is also a [`NetworkBehaviour`].
+pub trait NetworkBehaviour01 {
+    /// Handler for all the protocols the network behaviour supports.
+    type ProtocolsHandler: IntoProtocolsHandler;
+
+    /// Event generated by the `NetworkBehaviour01` and that the swarm will report back.
+    type OutEvent;
+
+    /// Creates a new `ProtocolsHandler` for a connection with a peer.
+    fn new_handler(&mut self) -> Self::ProtocolsHandler;
+
+    /// Addresses that this behaviour is aware of for this specific peer, and that may allow
+    /// reaching the peer.
+    fn addresses_of_peer(&mut self, peer_id: &PeerId) -> Vec<Multiaddr>;
+
+    /// Indicates the behaviour that we connected to the node with the given peer id through the
+    /// given endpoint.
+    fn inject_connected(&mut self, peer_id: PeerId, endpoint: ConnectedPoint);
+
+    /// Indicates the behaviour that we disconnected from the node with the given peer id.
+    fn inject_disconnected(&mut self, peer_id: &PeerId, endpoint: ConnectedPoint);
+
+    /// Informs the behaviour about a newly established connection to a peer.
+    fn inject_connection_established(&mut self, _peer_id: &PeerId, _connection: &ConnectionId, _endpoint: &ConnectedPoint) {
+    }
+
+    /// Informs the behaviour about a closed connection to a peer.
+    fn inject_connection_closed(&mut self, _peer_id: &PeerId, _connection: &ConnectionId, _endpoint: &ConnectedPoint) {
+    }
+
+    /// Informs the behaviour about an event generated by the handler dedicated to the connection
+    /// identified by `connection`, with the peer identified by `peer_id`.
+    fn inject_node_event(
+        &mut self,
+        peer_id: PeerId,
+        connection: ConnectionId,
+        event: <<Self::ProtocolsHandler as IntoProtocolsHandler>::Handler as ProtocolsHandler>::OutEvent
+    );
+
+    /// Indicates to the behaviour that we tried to reach an address, but failed.
+    fn inject_addr_reach_failure(&mut self, _peer_id: Option<&PeerId>, _addr: &Multiaddr, _error: &dyn error::Error) {
+    }
+
+    /// Indicates to the behaviour that we tried to dial all the addresses known for a node, but
+    /// failed.
+    fn inject_dial_failure(&mut self, _peer_id: &PeerId) {
+    }
+
+    /// Indicates to the behaviour that a connection has been refused or closed, or that inbound
+    /// substreams of a connection have been refused, because one of the connection limits of the
+    /// swarm was reached.
+    fn inject_connection_limit_reached(&mut self, _peer_id: Option<&PeerId>, _endpoint: &ConnectedPoint, _limit: &ConnectionLimit) {
+    }
+
+    /// Indicates to the behaviour that we have started listening on a new multiaddr.
+    fn inject_new_listen_addr(&mut self, _addr: &Multiaddr) {
+    }
+
+    /// Indicates to the behaviour that a new multiaddr we were listening on has expired.
+    fn inject_expired_listen_addr(&mut self, _addr: &Multiaddr) {
+    }
+
+    /// Indicates to the behaviour that we have discovered a new external address for us.
+    fn inject_new_external_addr(&mut self, _addr: &Multiaddr) {
+    }
+
+    /// Indicates to the behaviour that an external address of ours is no longer reachable.
+    fn inject_expired_external_addr(&mut self, _addr: &Multiaddr) {
+    }
+
+    /// Polls for things that swarm should do.
+    ///
+    /// This API mimics the API of the `Stream` trait. The method may register the current task in
+    /// order to wake it up at a later point in time.
+    fn poll(&mut self, params: &mut impl PollParameters)
+        -> Async<NetworkBehaviourAction<<<Self::ProtocolsHandler as IntoProtocolsHandler>::Handler as ProtocolsHandler>::InEvent, Self::OutEvent>>;
+}
+
+impl<T> NetworkBehaviour for T
+where
+    T: NetworkBehaviour01,
+{
+    type ProtocolsHandler = T::ProtocolsHandler;
+    type OutEvent = T::OutEvent;
+
+    fn new_handler(&mut self) -> Self::ProtocolsHandler {
+        NetworkBehaviour01::new_handler(self)
+    }
+
+    fn addresses_of_peer(&mut self, peer_id: &PeerId) -> Vec<Multiaddr> {
+        NetworkBehaviour01::addresses_of_peer(self, peer_id)
+    }
+
+    fn inject_connected(&mut self, peer_id: PeerId, endpoint: ConnectedPoint) {
+        NetworkBehaviour01::inject_connected(self, peer_id, endpoint)
+    }
+
+    fn inject_disconnected(&mut self, peer_id: &PeerId, endpoint: ConnectedPoint) {
+        NetworkBehaviour01::inject_disconnected(self, peer_id, endpoint)
+    }
+
+    fn inject_connection_established(&mut self, peer_id: &PeerId, connection: &ConnectionId, endpoint: &ConnectedPoint) {
+        NetworkBehaviour01::inject_connection_established(self, peer_id, connection, endpoint)
+    }
+
+    fn inject_connection_closed(&mut self, peer_id: &PeerId, connection: &ConnectionId, endpoint: &ConnectedPoint) {
+        NetworkBehaviour01::inject_connection_closed(self, peer_id, connection, endpoint)
+    }
+
+    fn inject_node_event(
+        &mut self,
+        peer_id: PeerId,
+        connection: ConnectionId,
+        event: <<Self::ProtocolsHandler as IntoProtocolsHandler>::Handler as ProtocolsHandler>::OutEvent
+    ) {
+        NetworkBehaviour01::inject_node_event(self, peer_id, connection, event)
+    }
+
+    fn inject_addr_reach_failure(&mut self, peer_id: Option<&PeerId>, addr: &Multiaddr, error: &dyn error::Error) {
+        NetworkBehaviour01::inject_addr_reach_failure(self, peer_id, addr, error)
+    }
+
+    fn inject_dial_failure(&mut self, peer_id: &PeerId) {
+        NetworkBehaviour01::inject_dial_failure(self, peer_id)
+    }
+
+    fn inject_connection_limit_reached(&mut self, peer_id: Option<&PeerId>, endpoint: &ConnectedPoint, limit: &ConnectionLimit) {
+        NetworkBehaviour01::inject_connection_limit_reached(self, peer_id, endpoint, limit)
+    }
+
+    fn inject_new_listen_addr(&mut self, addr: &Multiaddr) {
+        NetworkBehaviour01::inject_new_listen_addr(self, addr)
+    }
+
+    fn inject_expired_listen_addr(&mut self, addr: &Multiaddr) {
+        NetworkBehaviour01::inject_expired_listen_addr(self, addr)
+    }
+
+    fn inject_new_external_addr(&mut self, addr: &Multiaddr) {
+        NetworkBehaviour01::inject_new_external_addr(self, addr)
+    }
+
+    fn inject_expired_external_addr(&mut self, addr: &Multiaddr) {
+        NetworkBehaviour01::inject_expired_external_addr(self, addr)
+    }
+
+    fn poll(&mut self, cx: &mut Context<'_>, params: &mut impl PollParameters)
+        -> Poll<NetworkBehaviourAction<HandlerInEvent<Self>, Self::OutEvent>>
+    {
+        match with_task(cx, || NetworkBehaviour01::poll(self, params)) {
+            Async::Ready(action) => Poll::Ready(action),
+            Async::NotReady => Poll::Pending,
+        }
+    }
+}
+
+impl<TBehaviour> NetworkBehaviour01 for Compat03As01<TBehaviour>
+where
+    TBehaviour: NetworkBehaviour,
+{
+    type ProtocolsHandler = TBehaviour::ProtocolsHandler;
+    type OutEvent = TBehaviour::OutEvent;
+
+    fn new_handler(&mut self) -> Self::ProtocolsHandler {
+        self.get_mut().new_handler()
+    }
+
+    fn addresses_of_peer(&mut self, peer_id: &PeerId) -> Vec<Multiaddr> {
+        self.get_mut().addresses_of_peer(peer_id)
+    }
+
+    fn inject_connected(&mut self, peer_id: PeerId, endpoint: ConnectedPoint) {
+        self.get_mut().inject_connected(peer_id, endpoint)
+    }
+
+    fn inject_disconnected(&mut self, peer_id: &PeerId, endpoint: ConnectedPoint) {
+        self.get_mut().inject_disconnected(peer_id, endpoint)
+    }
+
+    fn inject_connection_established(&mut self, peer_id: &PeerId, connection: &ConnectionId, endpoint: &ConnectedPoint) {
+        self.get_mut().inject_connection_established(peer_id, connection, endpoint)
+    }
+
+    fn inject_connection_closed(&mut self, peer_id: &PeerId, connection: &ConnectionId, endpoint: &ConnectedPoint) {
+        self.get_mut().inject_connection_closed(peer_id, connection, endpoint)
+    }
+
+    fn inject_node_event(
+        &mut self,
+        peer_id: PeerId,
+        connection: ConnectionId,
+        event: <<Self::ProtocolsHandler as IntoProtocolsHandler>::Handler as ProtocolsHandler>::OutEvent
+    ) {
+        self.get_mut().inject_node_event(peer_id, connection, event)
+    }
+
+    fn inject_addr_reach_failure(&mut self, peer_id: Option<&PeerId>, addr: &Multiaddr, error: &dyn error::Error) {
+        self.get_mut().inject_addr_reach_failure(peer_id, addr, error)
+    }
+
+    fn inject_dial_failure(&mut self, peer_id: &PeerId) {
+        self.get_mut().inject_dial_failure(peer_id)
+    }
+
+    fn inject_connection_limit_reached(&mut self, peer_id: Option<&PeerId>, endpoint: &ConnectedPoint, limit: &ConnectionLimit) {
+        self.get_mut().inject_connection_limit_reached(peer_id, endpoint, limit)
+    }
+
+    fn inject_new_listen_addr(&mut self, addr: &Multiaddr) {
+        self.get_mut().inject_new_listen_addr(addr)
+    }
+
+    fn inject_expired_listen_addr(&mut self, addr: &Multiaddr) {
+        self.get_mut().inject_expired_listen_addr(addr)
+    }
+
+    fn inject_new_external_addr(&mut self, addr: &Multiaddr) {
+        self.get_mut().inject_new_external_addr(addr)
+    }
+
+    fn inject_expired_external_addr(&mut self, addr: &Multiaddr) {
+        self.get_mut().inject_expired_external_addr(addr)
+    }
+
+    fn poll(&mut self, params: &mut impl PollParameters)
+        -> Async<NetworkBehaviourAction<HandlerInEvent<TBehaviour>, Self::OutEvent>>
+    {
+        match with_context(|cx| self.get_mut().poll(cx, params)) {
+            Poll::Ready(action) => Async::Ready(action),
+            Poll::Pending => Async::NotReady,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        KeepAlive,
+        NetworkBehaviour,
+        NetworkBehaviourAction,
+        PollParameters,
+        ProtocolsHandler,
+        ProtocolsHandlerEvent,
+        ProtocolsHandlerUpgrErr,
+        SubstreamProtocol,
+        Swarm
+    };
+    use futures::{future, prelude::*};
+    use futures03::{
+        future::{BoxFuture, FutureExt, TryFutureExt},
+        io::{AsyncRead as AsyncRead03, AsyncReadExt, AsyncWrite as AsyncWrite03, AsyncWriteExt},
+        task::{Context, Poll as Poll03},
+    };
+    use libp2p_core::{
+        ConnectedPoint,
+        Multiaddr,
+        PeerId,
+        Transport,
+        compat::Compat03As01,
+        identity,
+        muxing::StreamMuxerBox,
+        nodes::ConnectionId,
+        transport::{MemoryTransport, boxed::Boxed},
+        upgrade::{self, InboundUpgrade, Negotiated, OutboundUpgrade, UpgradeInfo}
+    };
+    use libp2p_mplex::MplexConfig;
+    use std::{collections::VecDeque, io, iter, marker::PhantomData};
+    use tokio::runtime::current_thread::Runtime;
+    use tokio_io::{AsyncRead, AsyncWrite};
+    use void::Void;
+
+    /// Upgrade in which the dialer sends `hello` and the listener answers with `world`.
+    #[derive(Debug, Clone)]
+    struct HelloWorld;
+
+    impl UpgradeInfo for HelloWorld {
+        type Info = &'static [u8];
+        type InfoIter = iter::Once<Self::Info>;
+
+        fn protocol_info(&self) -> Self::InfoIter {
+            iter::once(b"/hello-world/1.0.0")
+        }
+    }
+
+    impl<C> InboundUpgrade<C> for HelloWorld
+    where
+        C: AsyncRead03 + AsyncWrite03 + Unpin + Send + 'static,
+    {
+        type Output = Vec<u8>;
+        type Error = io::Error;
+        type Future = BoxFuture<'static, Result<Self::Output, Self::Error>>;
+
+        fn upgrade_inbound(self, mut socket: Negotiated<C>, _: Self::Info) -> Self::Future {
+            async move {
+                let mut received = vec![0; 5];
+                socket.read_exact(&mut received).await?;
+                socket.write_all(b"world").await?;
+                socket.close().await?;
+                Ok(received)
+            }.boxed()
+        }
+    }
+
+    impl<C> OutboundUpgrade<C> for HelloWorld
+    where
+        C: AsyncRead03 + AsyncWrite03 + Unpin + Send + 'static,
+    {
+        type Output = Vec<u8>;
+        type Error = io::Error;
+        type Future = BoxFuture<'static, Result<Self::Output, Self::Error>>;
+
+        fn upgrade_outbound(self, mut socket: Negotiated<C>, _: Self::Info) -> Self::Future {
+            async move {
+                socket.write_all(b"hello").await?;
+                socket.close().await?;
+                let mut received = Vec::new();
+                socket.read_to_end(&mut received).await?;
+                Ok(received)
+            }.boxed()
+        }
+    }
+
+    /// Handler that performs the `HelloWorld` exchange once as a dialer.
+    struct HelloWorldHandler<TSubstream> {
+        requested: bool,
+        received: VecDeque<Vec<u8>>,
+        marker: PhantomData<TSubstream>,
+    }
+
+    impl<TSubstream> ProtocolsHandler for HelloWorldHandler<TSubstream>
+    where
+        TSubstream: AsyncRead + AsyncWrite + AsyncRead03 + AsyncWrite03 + Unpin + Send + 'static,
+    {
+        type InEvent = Void;
+        type OutEvent = Vec<u8>;
+        type Error = Void;
+        type Substream = TSubstream;
+        type InboundProtocol = HelloWorld;
+        type OutboundProtocol = HelloWorld;
+        type OutboundOpenInfo = ();
+
+        fn listen_protocol(&self) -> SubstreamProtocol<Self::InboundProtocol> {
+            SubstreamProtocol::new(HelloWorld)
+        }
+
+        fn inject_fully_negotiated_inbound(&mut self, received: Vec<u8>) {
+            self.received.push_back(received)
+        }
+
+        fn inject_fully_negotiated_outbound(&mut self, received: Vec<u8>, _: ()) {
+            self.received.push_back(received)
+        }
+
+        fn inject_event(&mut self, event: Void) {
+            void::unreachable(event)
+        }
+
+        fn inject_dial_upgrade_error(&mut self, _: (), error: ProtocolsHandlerUpgrErr<io::Error>) {
+            panic!("upgrade failed: {}", error)
+        }
+
+        fn connection_keep_alive(&self) -> KeepAlive {
+            KeepAlive::Yes
+        }
+
+        fn poll(&mut self, _: &mut Context<'_>)
+            -> Poll03<Result<ProtocolsHandlerEvent<HelloWorld, (), Vec<u8>>, Void>>
+        {
+            if !self.requested {
+                self.requested = true;
+                return Poll03::Ready(Ok(ProtocolsHandlerEvent::OutboundSubstreamRequest {
+                    protocol: SubstreamProtocol::new(HelloWorld),
+                    info: (),
+                }))
+            }
+            match self.received.pop_front() {
+                Some(received) => Poll03::Ready(Ok(ProtocolsHandlerEvent::Custom(received))),
+                None => Poll03::Pending,
+            }
+        }
+    }
+
+    /// Behaviour that reports what its handlers receive.
+    struct HelloWorldBehaviour<TSubstream> {
+        received: VecDeque<Vec<u8>>,
+        marker: PhantomData<TSubstream>,
+    }
+
+    impl<TSubstream> NetworkBehaviour for HelloWorldBehaviour<TSubstream>
+    where
+        TSubstream: AsyncRead + AsyncWrite + AsyncRead03 + AsyncWrite03 + Unpin + Send + 'static,
+    {
+        type ProtocolsHandler = HelloWorldHandler<TSubstream>;
+        type OutEvent = Vec<u8>;
+
+        fn new_handler(&mut self) -> Self::ProtocolsHandler {
+            HelloWorldHandler { requested: false, received: VecDeque::new(), marker: PhantomData }
+        }
+
+        fn addresses_of_peer(&mut self, _: &PeerId) -> Vec<Multiaddr> {
+            Vec::new()
+        }
+
+        fn inject_connected(&mut self, _: PeerId, _: ConnectedPoint) {}
+
+        fn inject_disconnected(&mut self, _: &PeerId, _: ConnectedPoint) {}
+
+        fn inject_node_event(&mut self, _: PeerId, _: ConnectionId, received: Vec<u8>) {
+            self.received.push_back(received)
+        }
+
+        fn poll(&mut self, _: &mut Context<'_>, _: &mut impl PollParameters)
+            -> Poll03<NetworkBehaviourAction<Void, Vec<u8>>>
+        {
+            match self.received.pop_front() {
+                Some(received) => Poll03::Ready(NetworkBehaviourAction::GenerateEvent(received)),
+                None => Poll03::Pending,
+            }
+        }
+    }
+
+    type TestTransport = Boxed<(PeerId, StreamMuxerBox), io::Error>;
+
+    /// Builds a transport that attributes all its connections to `remote`.
+    fn transport(remote: PeerId) -> TestTransport {
+        MemoryTransport
+            .and_then(move |out, endpoint| {
+                upgrade::apply(out, MplexConfig::new(), endpoint)
+                    .map_ok(move |muxer| (remote, StreamMuxerBox::new(muxer)))
+            })
+            .map_err(|err| io::Error::new(io::ErrorKind::Other, err.to_string()))
+            .boxed()
+    }
+
+    fn new_behaviour<TSubstream>() -> HelloWorldBehaviour<TSubstream> {
+        HelloWorldBehaviour { received: VecDeque::new(), marker: PhantomData }
+    }
+
+    /// Connects two swarms whose behaviours are built by `$behaviour`, and checks that each of
+    /// them receives `hello` as a listener and `world` as a dialer.
+    macro_rules! hello_world {
+        ($behaviour:expr) => {{
+            let id1 = PeerId::from(identity::Keypair::generate_ed25519().public());
+            let id2 = PeerId::from(identity::Keypair::generate_ed25519().public());
+
+            let mut swarm1 = Swarm::new(transport(id2.clone()), $behaviour, id1.clone());
+            let mut swarm2 = Swarm::new(transport(id1.clone()), $behaviour, id2);
+            Swarm::listen_on(&mut swarm1, "/memory/0".parse().unwrap()).unwrap();
+
+            let mut dialed = false;
+            let mut received1 = Vec::new();
+            let mut received2 = Vec::new();
+            let test = future::poll_fn(move || -> Poll<(), ()> {
+                loop {
+                    if let Async::Ready(Some(received)) = swarm1.poll().unwrap() {
+                        received1.push(received);
+                        continue
+                    }
+                    if let Async::Ready(Some(received)) = swarm2.poll().unwrap() {
+                        received2.push(received);
+                        continue
+                    }
+                    if !dialed {
+                        if let Some(addr) = Swarm::listeners(&swarm1).next().cloned() {
+                            Swarm::dial_addr(&mut swarm2, addr).unwrap();
+                            dialed = true;
+                            continue
+                        }
+                    }
+                    if received1.len() < 2 || received2.len() < 2 {
+                        return Ok(Async::NotReady)
+                    }
+                    break
+                }
+
+                received1.sort();
+                received2.sort();
+                assert_eq!(received1, vec![b"hello".to_vec(), b"world".to_vec()]);
+                assert_eq!(received2, vec![b"hello".to_vec(), b"world".to_vec()]);
+                Ok(Async::Ready(()))
+            });
+
+            let mut rt = Runtime::new().unwrap();
+            rt.block_on(test).unwrap();
+        }};
+    }
+
+    #[test]
+    fn async_behaviour_in_swarm() {
+        hello_world!(new_behaviour())
+    }
+
+    #[test]
+    fn behaviour_round_trip() {
+        // The behaviour and its handlers go through the futures 0.1 interface.
+        hello_world!(Compat03As01::new(new_behaviour()))
+    }
+}
@@ -0,0 +1,463 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Filtering of the connections and substreams of the swarm.
+//!
+//! A [`ConnectionGater`] can be passed to
+//! [`SwarmBuilder::connection_gater`](crate::SwarmBuilder::connection_gater) in order to reject
+//! connections based on their addresses or on the identity of the remote, and to reject
+//! protocols on a per-substream basis. The gater is consulted at the following points:
+//!
+//! - Before dialing an address, with [`ConnectionGater::allow_dial`].
+//! - When a connection arrives on a listener, before any handshake, with
+//!   [`ConnectionGater::allow_incoming`].
+//! - Once the security handshake has yielded the `PeerId` of the remote, with
+//!   [`ConnectionGater::allow_connection`].
+//! - Before a protocol is accepted on a substream, with [`ConnectionGater::allow_protocol`].
+//!
+//! The [`CidrGater`] is an implementation that allows or denies connections based on the IP
+//! address of the remote.
+
+use libp2p_core::{
+    ConnectedPoint, Endpoint, Multiaddr, Negotiated, PeerId,
+    multiaddr::Protocol,
+    upgrade::{InboundUpgrade, OutboundUpgrade, ProtocolName, UpgradeInfo}
+};
+use std::{error, fmt, net::{IpAddr, Ipv4Addr, Ipv6Addr}, str::FromStr, sync::Arc};
+
+/// Decides which connections and substreams a `Swarm` accepts.
+///
+/// All the methods have a default implementation that allows everything.
+///
+/// The gater is shared with the background tasks that drive the connections, and must therefore
+/// be thread-safe.
+pub trait ConnectionGater: Send + Sync {
+    /// Returns whether we may dial the given address, in order to reach the given peer if known.
+    fn allow_dial(&self, _peer_id: Option<&PeerId>, _address: &Multiaddr) -> bool {
+        true
+    }
+
+    /// Returns whether a connection that arrived on `listen_addr` from `send_back_addr` may be
+    /// upgraded. Called before any handshake happens.
+    fn allow_incoming(&self, _listen_addr: &Multiaddr, _send_back_addr: &Multiaddr) -> bool {
+        true
+    }
+
+    /// Returns whether a connection with the given peer may be kept, now that its identity is
+    /// known. A denied connection is closed before the behaviour is informed of it. If we were
+    /// dialing, the behaviour is informed of the failure to reach the address instead, with a
+    /// [`ConnectionDenied`] error.
+    fn allow_connection(&self, _peer_id: &PeerId, _endpoint: &ConnectedPoint) -> bool {
+        true
+    }
+
+    /// Returns whether the given protocol may be negotiated on a substream with the given peer.
+    ///
+    /// `endpoint` is `Dialer` for the substreams that we open, and `Listener` for the substreams
+    /// opened by the remote. A denied protocol is not offered to the remote, and is refused if
+    /// the remote proposes it.
+    fn allow_protocol(&self, _peer_id: &PeerId, _protocol: &[u8], _endpoint: Endpoint) -> bool {
+        true
+    }
+}
+
+/// A range of IP addresses, in CIDR notation.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Cidr {
+    addr: IpAddr,
+    prefix_len: u8,
+}
+
+impl Cidr {
+    /// Builds the range of the addresses whose first `prefix_len` bits are those of `addr`.
+    ///
+    /// The bits of `addr` beyond the prefix are ignored. Returns an error if `prefix_len` is
+    /// larger than the number of bits of the address.
+    pub fn new(addr: IpAddr, prefix_len: u8) -> Result<Self, CidrError> {
+        let addr = match addr {
+            IpAddr::V4(a) if prefix_len <= 32 =>
+                IpAddr::V4(Ipv4Addr::from(u32::from(a) & mask_v4(prefix_len))),
+            IpAddr::V6(a) if prefix_len <= 128 =>
+                IpAddr::V6(Ipv6Addr::from(u128::from(a) & mask_v6(prefix_len))),
+            _ => return Err(CidrError::InvalidPrefixLength),
+        };
+        Ok(Cidr { addr, prefix_len })
+    }
+
+    /// Returns the first address of the range.
+    pub fn addr(&self) -> IpAddr {
+        self.addr
+    }
+
+    /// Returns the number of leading bits shared by the addresses of the range.
+    pub fn prefix_len(&self) -> u8 {
+        self.prefix_len
+    }
+
+    /// Returns true if the given address belongs to the range.
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        match (self.addr, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) =>
+                u32::from(*ip) & mask_v4(self.prefix_len) == u32::from(net),
+            (IpAddr::V6(net), IpAddr::V6(ip)) =>
+                u128::from(*ip) & mask_v6(self.prefix_len) == u128::from(net),
+            _ => false,
+        }
+    }
+}
+
+fn mask_v4(prefix_len: u8) -> u32 {
+    u32::max_value().checked_shl(32 - u32::from(prefix_len)).unwrap_or(0)
+}
+
+fn mask_v6(prefix_len: u8) -> u128 {
+    u128::max_value().checked_shl(128 - u32::from(prefix_len)).unwrap_or(0)
+}
+
+impl From<IpAddr> for Cidr {
+    fn from(addr: IpAddr) -> Self {
+        let prefix_len = if addr.is_ipv4() { 32 } else { 128 };
+        Cidr { addr, prefix_len }
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = CidrError;
+
+    /// Parses a range such as `10.0.0.0/8` or `fc00::/7`. An address without a prefix length
+    /// designates that single address.
+    fn from_str(s: &str) -> Result<Self, CidrError> {
+        let mut parts = s.splitn(2, '/');
+        let addr = parts.next().unwrap_or("").parse::<IpAddr>()
+            .map_err(|_| CidrError::InvalidAddress)?;
+        match parts.next() {
+            Some(len) => {
+                let len = len.parse().map_err(|_| CidrError::InvalidPrefixLength)?;
+                Cidr::new(addr, len)
+            }
+            None => Ok(Cidr::from(addr)),
+        }
+    }
+}
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix_len)
+    }
+}
+
+/// Error while building or parsing a [`Cidr`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CidrError {
+    /// The address part is not a valid IPv4 or IPv6 address.
+    InvalidAddress,
+    /// The prefix length is not a number, or is too large for the address.
+    InvalidPrefixLength,
+}
+
+impl fmt::Display for CidrError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CidrError::InvalidAddress => write!(f, "Invalid IP address"),
+            CidrError::InvalidPrefixLength => write!(f, "Invalid prefix length"),
+        }
+    }
+}
+
+impl error::Error for CidrError {}
+
+/// Error reported to the behaviour when a connection is refused once established, after the
+/// security handshake has yielded the `PeerId` of the remote.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ConnectionDenied {
+    /// The connection was denied by [`ConnectionGater::allow_connection`].
+    Gated,
+    /// The remote is banned.
+    Banned,
+}
+
+impl fmt::Display for ConnectionDenied {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConnectionDenied::Gated => write!(f, "Connection denied by the connection gater"),
+            ConnectionDenied::Banned => write!(f, "Connection denied because the remote is banned"),
+        }
+    }
+}
+
+impl error::Error for ConnectionDenied {}
+
+/// A [`ConnectionGater`] that allows or denies connections based on the IP address of the remote.
+///
+/// An address is denied if it belongs to one of the denied ranges, or if it is not a public
+/// address and non-public addresses are denied. Otherwise, if at least one range is allowed, the
+/// address is only allowed if it belongs to one of the allowed ranges.
+///
+/// The addresses that don't start with an IP address, such as `/dns4` addresses, are allowed
+/// unless `deny_non_ip` is set. Protocols on substreams are not filtered.
+#[derive(Debug, Clone, Default)]
+pub struct CidrGater {
+    allowed: Vec<Cidr>,
+    denied: Vec<Cidr>,
+    deny_non_public: bool,
+    deny_non_ip: bool,
+}
+
+impl CidrGater {
+    /// Builds a `CidrGater` that allows everything.
+    pub fn new() -> Self {
+        CidrGater::default()
+    }
+
+    /// Adds a range of allowed addresses.
+    pub fn allow(mut self, range: Cidr) -> Self {
+        self.allowed.push(range);
+        self
+    }
+
+    /// Adds a range of denied addresses.
+    pub fn deny(mut self, range: Cidr) -> Self {
+        self.denied.push(range);
+        self
+    }
+
+    /// Sets whether loopback, private, link-local and other non-globally-routable addresses are
+    /// denied.
+    pub fn deny_non_public(mut self, deny: bool) -> Self {
+        self.deny_non_public = deny;
+        self
+    }
+
+    /// Sets whether the addresses that don't start with an IP address are denied.
+    pub fn deny_non_ip(mut self, deny: bool) -> Self {
+        self.deny_non_ip = deny;
+        self
+    }
+
+    /// Returns true if the given IP address is allowed.
+    pub fn allows_ip(&self, ip: &IpAddr) -> bool {
+        if self.denied.iter().any(|r| r.contains(ip)) {
+            return false
+        }
+        if self.deny_non_public && !is_public(ip) {
+            return false
+        }
+        self.allowed.is_empty() || self.allowed.iter().any(|r| r.contains(ip))
+    }
+
+    /// Returns true if the given multiaddress is allowed.
+    pub fn allows_addr(&self, addr: &Multiaddr) -> bool {
+        match addr.iter().next() {
+            Some(Protocol::Ip4(ip)) => self.allows_ip(&IpAddr::V4(ip)),
+            Some(Protocol::Ip6(ip)) => self.allows_ip(&IpAddr::V6(ip)),
+            _ => !self.deny_non_ip,
+        }
+    }
+}
+
+impl ConnectionGater for CidrGater {
+    fn allow_dial(&self, _: Option<&PeerId>, address: &Multiaddr) -> bool {
+        self.allows_addr(address)
+    }
+
+    fn allow_incoming(&self, _: &Multiaddr, send_back_addr: &Multiaddr) -> bool {
+        self.allows_addr(send_back_addr)
+    }
+
+    fn allow_connection(&self, _: &PeerId, endpoint: &ConnectedPoint) -> bool {
+        match endpoint {
+            ConnectedPoint::Dialer { address, .. } => self.allows_addr(address),
+            ConnectedPoint::Listener { send_back_addr, .. } => self.allows_addr(send_back_addr),
+        }
+    }
+}
+
+/// Returns true if the address is globally routable.
+fn is_public(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => {
+            if let Some(ip) = ip.to_ipv4() {
+                // IPv4-mapped and IPv4-compatible addresses; `::` and `::1` are handled below.
+                if ip.octets()[..3] != [0, 0, 0] {
+                    return is_public_v4(&ip)
+                }
+            }
+            let segments = ip.segments();
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || (segments[0] & 0xfe00) == 0xfc00 // unique local, fc00::/7
+                || (segments[0] & 0xffc0) == 0xfe80 // link-local, fe80::/10
+                || (segments[0] & 0xff00) == 0xff00 // multicast, ff00::/8
+                || (segments[0] == 0x2001 && segments[1] == 0x0db8)) // documentation
+        }
+    }
+}
+
+fn is_public_v4(ip: &Ipv4Addr) -> bool {
+    let octets = ip.octets();
+    !(ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_multicast()
+        || octets[0] == 0
+        || (octets[0] == 100 && (octets[1] & 0xc0) == 64)) // shared address space, 100.64.0.0/10
+}
+
+/// Upgrade that only negotiates the protocols of the inner upgrade that the gater allows.
+pub(crate) struct GatedUpgrade<TUpgrade> {
+    inner: TUpgrade,
+    gate: Option<(Arc<dyn ConnectionGater>, PeerId, Endpoint)>,
+}
+
+impl<TUpgrade> GatedUpgrade<TUpgrade> {
+    /// Wraps around `inner`. If `gate` is `None`, all the protocols are negotiated.
+    pub(crate) fn new(inner: TUpgrade, gate: Option<(Arc<dyn ConnectionGater>, PeerId, Endpoint)>) -> Self {
+        GatedUpgrade { inner, gate }
+    }
+}
+
+impl<TUpgrade> UpgradeInfo for GatedUpgrade<TUpgrade>
+where
+    TUpgrade: UpgradeInfo
+{
+    type Info = TUpgrade::Info;
+    type InfoIter = Vec<TUpgrade::Info>;
+
+    fn protocol_info(&self) -> Self::InfoIter {
+        let protocols = self.inner.protocol_info().into_iter();
+        match &self.gate {
+            Some((gater, peer_id, endpoint)) => protocols
+                .filter(|p| gater.allow_protocol(peer_id, p.protocol_name(), *endpoint))
+                .collect(),
+            None => protocols.collect(),
+        }
+    }
+}
+
+impl<C, TUpgrade> InboundUpgrade<C> for GatedUpgrade<TUpgrade>
+where
+    TUpgrade: InboundUpgrade<C>
+{
+    type Output = TUpgrade::Output;
+    type Error = TUpgrade::Error;
+    type Future = TUpgrade::Future;
+
+    fn upgrade_inbound(self, socket: Negotiated<C>, info: Self::Info) -> Self::Future {
+        self.inner.upgrade_inbound(socket, info)
+    }
+}
+
+impl<C, TUpgrade> OutboundUpgrade<C> for GatedUpgrade<TUpgrade>
+where
+    TUpgrade: OutboundUpgrade<C>
+{
+    type Output = TUpgrade::Output;
+    type Error = TUpgrade::Error;
+    type Future = TUpgrade::Future;
+
+    fn upgrade_outbound(self, socket: Negotiated<C>, info: Self::Info) -> Self::Future {
+        self.inner.upgrade_outbound(socket, info)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cidr(s: &str) -> Cidr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn cidr_parse_and_contains() {
+        let range = cidr("10.1.2.3/16");
+        assert_eq!(range.to_string(), "10.1.0.0/16");
+        assert!(range.contains(&"10.1.255.1".parse().unwrap()));
+        assert!(!range.contains(&"10.2.0.1".parse().unwrap()));
+        assert!(!range.contains(&"::1".parse().unwrap()));
+
+        assert!(cidr("0.0.0.0/0").contains(&"1.2.3.4".parse().unwrap()));
+        assert!(cidr("fc00::/7").contains(&"fd12::1".parse().unwrap()));
+        assert_eq!(cidr("1.2.3.4").prefix_len(), 32);
+
+        assert_eq!("1.2.3.4/33".parse::<Cidr>(), Err(CidrError::InvalidPrefixLength));
+        assert_eq!("1.2.3/8".parse::<Cidr>(), Err(CidrError::InvalidAddress));
+    }
+
+    #[test]
+    fn allow_and_deny_lists() {
+        let gater = CidrGater::new()
+            .allow(cidr("192.168.0.0/16"))
+            .deny(cidr("192.168.1.0/24"));
+
+        assert!(gater.allows_addr(&"/ip4/192.168.2.1/tcp/1".parse().unwrap()));
+        assert!(!gater.allows_addr(&"/ip4/192.168.1.1/tcp/1".parse().unwrap()));
+        assert!(!gater.allows_addr(&"/ip4/8.8.8.8/tcp/1".parse().unwrap()));
+        assert!(gater.allows_addr(&"/dns4/example.com/tcp/1".parse().unwrap()));
+        assert!(!gater.clone().deny_non_ip(true).allows_addr(&"/dns4/example.com/tcp/1".parse().unwrap()));
+    }
+
+    #[test]
+    fn non_public_addresses() {
+        let gater = CidrGater::new().deny_non_public(true);
+
+        for addr in &["127.0.0.1", "10.0.0.1", "100.64.0.1", "169.254.0.1", "::1", "fe80::1", "fd00::1", "::ffff:192.168.0.1"] {
+            assert!(!gater.allows_ip(&addr.parse().unwrap()), "{} should be denied", addr);
+        }
+        for addr in &["8.8.8.8", "2001:4860::8888", "::ffff:8.8.8.8"] {
+            assert!(gater.allows_ip(&addr.parse().unwrap()), "{} should be allowed", addr);
+        }
+    }
+
+    #[test]
+    fn gated_upgrade_filters_protocols() {
+        struct Protocols;
+
+        impl UpgradeInfo for Protocols {
+            type Info = &'static [u8];
+            type InfoIter = Vec<&'static [u8]>;
+
+            fn protocol_info(&self) -> Self::InfoIter {
+                vec![b"/allowed/1.0.0", b"/denied/1.0.0"]
+            }
+        }
+
+        struct DenyProtocol;
+
+        impl ConnectionGater for DenyProtocol {
+            fn allow_protocol(&self, _: &PeerId, protocol: &[u8], endpoint: Endpoint) -> bool {
+                endpoint == Endpoint::Dialer || protocol != b"/denied/1.0.0"
+            }
+        }
+
+        let peer_id = PeerId::random();
+        let gate = |endpoint| Some((Arc::new(DenyProtocol) as Arc<dyn ConnectionGater>, peer_id.clone(), endpoint));
+
+        let listener = GatedUpgrade::new(Protocols, gate(Endpoint::Listener));
+        assert_eq!(listener.protocol_info(), vec![&b"/allowed/1.0.0"[..]]);
+        let dialer = GatedUpgrade::new(Protocols, gate(Endpoint::Dialer));
+        assert_eq!(dialer.protocol_info().len(), 2);
+        assert_eq!(GatedUpgrade::new(Protocols, None).protocol_info().len(), 2);
+    }
+}
@@ -52,10 +52,24 @@
 //! remote should behave: how to handle incoming substreams, which protocols
 //! are supported, when to open a new outbound substream, etc.
 //!
+//! # Futures 0.1
+//!
+//! The poll methods of both traits are built on `std::future`. Behaviours and handlers written
+//! against futures 0.1 can implement the traits of the [`compat`] module instead. The substreams
+//! handed to a handler are still `AsyncRead`/`AsyncWrite` objects of tokio-io, and the [`Swarm`]
+//! itself is still a futures 0.1 `Stream`.
+//!
 
 mod behaviour;
+mod peer_store_proto;
 mod registry;
 
+pub mod bandwidth;
+pub mod compat;
+pub mod gater;
+pub mod observer;
+pub mod peer_score;
+pub mod peer_store;
 pub mod protocols_handler;
 pub mod toggle;
 
@@ -63,6 +77,7 @@ pub use behaviour::{
     NetworkBehaviour,
     NetworkBehaviourAction,
     NetworkBehaviourEventProcess,
+    NotifyHandler,
     PollParameters
 };
 pub use protocols_handler::{
@@ -76,11 +91,21 @@ pub use protocols_handler::{
     OneShotHandler,
     SubstreamProtocol
 };
+pub use libp2p_core::nodes::network::{ConnectionLimit, ConnectionLimitKind, ConnectionLimits};
+pub use bandwidth::{ProtocolBandwidth, Traffic};
+pub use gater::{Cidr, CidrError, CidrGater, ConnectionDenied, ConnectionGater};
+pub use observer::{ConnectionErrorKind, MeterState, SwarmObserver};
+pub use peer_score::{Misbehaviour, PeerScore, PeerScoreConfig};
+pub use peer_store::{AddressSource, PeerStore};
 
 use protocols_handler::{NodeHandlerWrapperBuilder, NodeHandlerWrapper, NodeHandlerWrapperError};
 use futures::prelude::*;
+use futures03::task::Poll as Poll03;
 use libp2p_core::{
-    Transport, Multiaddr, PeerId, InboundUpgrade, OutboundUpgrade, UpgradeInfo, ProtocolName,
+    ConnectedPoint, Endpoint, Transport, Multiaddr, PeerId, InboundUpgrade, OutboundUpgrade, UpgradeInfo, ProtocolName,
+    clock,
+    compat::with_context,
+    executor::{Executor, TokioExecutor},
     muxing::StreamMuxer,
     nodes::{
         collection::ConnectionInfo,
@@ -90,10 +115,18 @@ use libp2p_core::{
     },
     transport::TransportError
 };
+use observer::ObserverSet;
 use registry::{Addresses, AddressIntoIter};
 use smallvec::SmallVec;
-use std::{error, fmt, io, ops::{Deref, DerefMut}};
+use std::{error, fmt, io, ops::{Deref, DerefMut}, sync::Arc, time::Duration};
 use std::collections::HashSet;
+use wasm_timer::Delay;
+
+/// Interval between two removals of the expired addresses from the peer store.
+const PEER_STORE_EXPIRATION_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Interval between two removals of the expired bans and decayed scores from the peer scores.
+const PEER_SCORE_EXPIRATION_INTERVAL: Duration = Duration::from_secs(60);
 
 /// Contains the state of the network, plus the way it should behave.
 pub type Swarm<TTransport, TBehaviour, TConnInfo = PeerId> = ExpandedSwarm<
@@ -138,15 +171,36 @@ where
     /// List of nodes for which we deny any incoming connection.
     banned_peers: HashSet<PeerId>,
 
+    /// Address book shared by the behaviours.
+    peer_store: PeerStore,
+
+    /// Fires when the expired addresses should next be removed from `peer_store`.
+    peer_store_expiration: Delay,
+
+    /// Scores of the peers, and peers banned because of their score.
+    peer_score: PeerScore,
+
+    /// Fires when the expired bans and decayed scores should next be removed from `peer_score`.
+    peer_score_expiration: Delay,
+
+    /// Observer of the activity of the swarm, if any.
+    observer: Option<Arc<dyn SwarmObserver>>,
+
+    /// Filter of the connections and substreams, if any.
+    gater: Option<Arc<dyn ConnectionGater>>,
+
+    /// Byte counters per peer and protocol, if enabled.
+    protocol_bandwidth: Option<ProtocolBandwidth>,
+
     /// Pending event message to be delivered.
     ///
-    /// If the pair's second element is `AsyncSink::NotReady`, the event
-    /// message has yet to be sent using `PeerMut::start_send_event`.
+    /// If the triple's third element is `AsyncSink::NotReady`, the event
+    /// message has yet to be sent using `PeerConnected::start_send_event_to`.
     ///
-    /// If the pair's second element is `AsyncSink::Ready`, the event
+    /// If the triple's third element is `AsyncSink::Ready`, the event
     /// message has been sent and needs to be flushed using
-    /// `PeerMut::complete_send_event`.
-    send_event_to_complete: Option<(PeerId, AsyncSink<TInEvent>)>
+    /// `PeerConnected::complete_send_event_to`.
+    send_event_to_complete: Option<(PeerId, NotifyHandler, AsyncSink<TInEvent>)>
 }
 
 impl<TTransport, TBehaviour, TInEvent, TOutEvent, THandler, THandlerErr, TConnInfo> Deref for
@@ -224,21 +278,61 @@ where TBehaviour: NetworkBehaviour<ProtocolsHandler = THandler>,
 
     /// Tries to dial the given address.
     ///
-    /// Returns an error if the address is not supported.
+    /// Returns an error if the address is not supported, or if the connection gater doesn't
+    /// allow dialing it.
     pub fn dial_addr(me: &mut Self, addr: Multiaddr) -> Result<(), TransportError<TTransport::Error>> {
-        let handler = me.behaviour.new_handler();
-        me.network.dial(addr, handler.into_node_handler_builder())
+        if !ExpandedSwarm::allow_dial(me, None, &addr) {
+            return Err(TransportError::MultiaddrNotSupported(addr))
+        }
+        ExpandedSwarm::dial_addr_ungated(me, addr, Endpoint::Dialer)
+    }
+
+    /// Same as `dial_addr`, but without consulting the connection gater.
+    fn dial_addr_ungated(me: &mut Self, addr: Multiaddr, role_override: Endpoint) -> Result<(), TransportError<TTransport::Error>> {
+        let handler = me.behaviour.new_handler()
+            .into_node_handler_builder()
+            .with_observer(me.observer.clone())
+            .with_gater(me.gater.clone());
+        match role_override {
+            Endpoint::Dialer => me.network.dial(addr, handler),
+            Endpoint::Listener => me.network.dial_as_listener(addr, handler),
+        }
+    }
+
+    /// Dials an address on behalf of the behaviour, and reports the failures to it.
+    fn dial_addr_of_behaviour(me: &mut Self, address: Multiaddr, role_override: Endpoint) {
+        if !ExpandedSwarm::allow_dial(me, None, &address) {
+            let error = TransportError::<TTransport::Error>::MultiaddrNotSupported(address.clone());
+            me.behaviour.inject_addr_reach_failure(None, &address, &error);
+        } else if let Err(error) = ExpandedSwarm::dial_addr_ungated(me, address.clone(), role_override) {
+            if let Some(observer) = &me.observer {
+                observer.dial_failure(None, &address, (&error).into());
+            }
+            me.behaviour.inject_addr_reach_failure(None, &address, &error);
+        }
     }
 
     /// Tries to reach the given peer using the elements in the topology.
     ///
+    /// The addresses returned by the behaviour are attempted first, followed by the other
+    /// addresses of the peer store.
+    ///
     /// Has no effect if we are already connected to that peer, or if no address is known for the
     /// peer.
     pub fn dial(me: &mut Self, peer_id: PeerId) {
-        let addrs = me.behaviour.addresses_of_peer(&peer_id);
+        let mut addrs = me.behaviour.addresses_of_peer(&peer_id);
+        for addr in me.peer_store.addresses(&peer_id) {
+            if !addrs.contains(addr) {
+                addrs.push(addr.clone());
+            }
+        }
+        addrs.retain(|addr| ExpandedSwarm::allow_dial(me, Some(&peer_id), addr));
         match me.network.peer(peer_id.clone()) {
             network::Peer::NotConnected(peer) => {
-                let handler = me.behaviour.new_handler().into_node_handler_builder();
+                let handler = me.behaviour.new_handler()
+                    .into_node_handler_builder()
+                    .with_observer(me.observer.clone())
+                    .with_gater(me.gater.clone());
                 if peer.connect_iter(addrs, handler).is_err() {
                     me.behaviour.inject_dial_failure(&peer_id);
                 }
@@ -261,6 +355,35 @@ where TBehaviour: NetworkBehaviour<ProtocolsHandler = THandler>,
         me.external_addrs.iter()
     }
 
+    /// Returns the address book of the swarm.
+    pub fn peer_store(me: &Self) -> &PeerStore {
+        &me.peer_store
+    }
+
+    /// Returns the address book of the swarm, for modification.
+    pub fn peer_store_mut(me: &mut Self) -> &mut PeerStore {
+        &mut me.peer_store
+    }
+
+    /// Returns the scores of the peers.
+    pub fn peer_score(me: &Self) -> &PeerScore {
+        &me.peer_score
+    }
+
+    /// Returns the scores of the peers, for modification.
+    ///
+    /// The peers banned as a result of a modification are disconnected the next time the swarm
+    /// is polled.
+    pub fn peer_score_mut(me: &mut Self) -> &mut PeerScore {
+        &mut me.peer_score
+    }
+
+    /// Returns the byte counters per peer and protocol, if enabled with
+    /// [`SwarmBuilder::protocol_bandwidth`].
+    pub fn protocol_bandwidth(me: &Self) -> Option<&ProtocolBandwidth> {
+        me.protocol_bandwidth.as_ref()
+    }
+
     /// Returns the peer ID of the swarm passed as parameter.
     pub fn local_peer_id(me: &Self) -> &PeerId {
         &me.network.local_peer_id()
@@ -288,17 +411,56 @@ where TBehaviour: NetworkBehaviour<ProtocolsHandler = THandler>,
     ///
     /// Any incoming connection and any dialing attempt will immediately be rejected.
     /// This function has no effect is the peer is already banned.
+    ///
+    /// Contrary to the bans resulting from the [`PeerScore`], this ban is permanent.
     pub fn ban_peer_id(me: &mut Self, peer_id: PeerId) {
         me.banned_peers.insert(peer_id.clone());
-        if let Some(c) = me.network.peer(peer_id).into_connected() {
-            c.close();
-        }
+        ExpandedSwarm::disconnect(me, &peer_id);
     }
 
     /// Unbans a peer.
     pub fn unban_peer_id(me: &mut Self, peer_id: PeerId) {
         me.banned_peers.remove(&peer_id);
     }
+
+    /// Closes all the connections to a peer, and informs the observer and the behaviour of each
+    /// of them, as no `NodeClosed` event is produced for them.
+    fn disconnect(me: &mut Self, peer_id: &PeerId) {
+        let peer = match me.network.peer(peer_id.clone()).into_connected() {
+            Some(peer) => peer,
+            None => return,
+        };
+        let connections = peer.connections()
+            .filter_map(|id| Some((id, peer.connection_endpoint(id)?.clone())))
+            .collect::<Vec<_>>();
+        peer.close();
+        for (id, endpoint) in &connections {
+            if let Some(observer) = &me.observer {
+                observer.connection_closed(peer_id, endpoint);
+            }
+            me.behaviour.inject_connection_closed(peer_id, id, endpoint);
+        }
+        if let Some((_, endpoint)) = connections.into_iter().last() {
+            me.behaviour.inject_disconnected(peer_id, endpoint);
+        }
+    }
+
+    /// Returns true if the peer is banned, either permanently or because of its score.
+    fn is_banned(me: &Self, peer_id: &PeerId) -> bool {
+        me.banned_peers.contains(peer_id) || me.peer_score.is_banned(peer_id)
+    }
+
+    /// Returns true if the connection gater, if any, allows dialing the given address. Informs
+    /// the observer otherwise.
+    fn allow_dial(me: &Self, peer_id: Option<&PeerId>, addr: &Multiaddr) -> bool {
+        let allowed = me.gater.as_ref().map_or(true, |g| g.allow_dial(peer_id, addr));
+        if !allowed {
+            if let Some(observer) = &me.observer {
+                observer.dial_failure(peer_id, addr, ConnectionErrorKind::Gated);
+            }
+        }
+        allowed
+    }
 }
 
 impl<TTransport, TBehaviour, TMuxer, TInEvent, TOutEvent, THandler, THandlerErr, TConnInfo> Stream for
@@ -338,33 +500,97 @@ where TBehaviour: NetworkBehaviour<ProtocolsHandler = THandler>,
     type Error = io::Error;
 
     fn poll(&mut self) -> Poll<Option<Self::Item>, io::Error> {
+        // If the timer fails, the expired addresses stay in the store, where they are ignored.
+        while let Ok(Async::Ready(())) = self.peer_store_expiration.poll() {
+            self.peer_store.remove_expired();
+            self.peer_store_expiration.reset(clock::now() + PEER_STORE_EXPIRATION_INTERVAL);
+        }
+
+        // The expired bans are ignored as well until they are removed.
+        while let Ok(Async::Ready(())) = self.peer_score_expiration.poll() {
+            self.peer_score.expire();
+            self.peer_score_expiration.reset(clock::now() + PEER_SCORE_EXPIRATION_INTERVAL);
+        }
+
         loop {
+            for peer_id in self.peer_score.take_new_bans() {
+                ExpandedSwarm::disconnect(self, &peer_id);
+            }
+
             let mut network_not_ready = false;
 
             match self.network.poll() {
                 Async::NotReady => network_not_ready = true,
-                Async::Ready(NetworkEvent::NodeEvent { conn_info, event }) => {
-                    self.behaviour.inject_node_event(conn_info.peer_id().clone(), event);
+                Async::Ready(NetworkEvent::NodeEvent { conn_info, connection_id, event }) => {
+                    self.behaviour.inject_node_event(conn_info.peer_id().clone(), connection_id, event);
                 },
-                Async::Ready(NetworkEvent::Connected { conn_info, endpoint }) => {
-                    if self.banned_peers.contains(conn_info.peer_id()) {
-                        self.network.peer(conn_info.peer_id().clone())
+                Async::Ready(NetworkEvent::Connected { conn_info, connection_id, endpoint, num_established }) => {
+                    let denied = if !self.gater.as_ref().map_or(true, |g| g.allow_connection(conn_info.peer_id(), &endpoint)) {
+                        Some(ConnectionDenied::Gated)
+                    } else if ExpandedSwarm::is_banned(self, conn_info.peer_id()) {
+                        Some(ConnectionDenied::Banned)
+                    } else {
+                        None
+                    };
+                    if let Some(denied) = denied {
+                        // The connection is closed before anyone learns about it, so it is
+                        // reported as a failure to connect rather than as a closed connection.
+                        let peer_id = conn_info.peer_id();
+                        if let Some(observer) = &self.observer {
+                            match &endpoint {
+                                ConnectedPoint::Dialer { address, .. } =>
+                                    observer.dial_failure(Some(peer_id), address, denied.into()),
+                                ConnectedPoint::Listener { send_back_addr, .. } =>
+                                    observer.incoming_connection_error(send_back_addr, denied.into()),
+                            }
+                        }
+                        self.network.peer(peer_id.clone())
                             .into_connected()
                             .expect("the Network just notified us that we were connected; QED")
-                            .close();
+                            .close_connection(connection_id);
+                        if let ConnectedPoint::Dialer { address, .. } = endpoint {
+                            self.behaviour.inject_addr_reach_failure(Some(peer_id), &address, &denied);
+                            if let network::Peer::NotConnected(_) = self.network.peer(peer_id.clone()) {
+                                self.behaviour.inject_dial_failure(peer_id);
+                            }
+                        }
                     } else {
-                        self.behaviour.inject_connected(conn_info.peer_id().clone(), endpoint);
+                        if let Some(observer) = &self.observer {
+                            observer.connection_established(conn_info.peer_id(), &endpoint);
+                        }
+                        let peer_id = conn_info.peer_id();
+                        self.behaviour.inject_connection_established(peer_id, &connection_id, &endpoint);
+                        if num_established.get() == 1 {
+                            self.behaviour.inject_connected(peer_id.clone(), endpoint);
+                        }
                     }
                 },
-                Async::Ready(NetworkEvent::NodeClosed { conn_info, endpoint, .. }) => {
-                    self.behaviour.inject_disconnected(conn_info.peer_id(), endpoint);
-                },
-                Async::Ready(NetworkEvent::Replaced { new_info, closed_endpoint, endpoint, .. }) => {
-                    self.behaviour.inject_replaced(new_info.peer_id().clone(), closed_endpoint, endpoint);
+                Async::Ready(NetworkEvent::NodeClosed { conn_info, connection_id, endpoint, num_established, .. }) => {
+                    let peer_id = conn_info.peer_id();
+                    if let Some(observer) = &self.observer {
+                        observer.connection_closed(peer_id, &endpoint);
+                    }
+                    self.behaviour.inject_connection_closed(peer_id, &connection_id, &endpoint);
+                    if num_established == 0 {
+                        self.behaviour.inject_disconnected(peer_id, endpoint);
+                    }
                 },
                 Async::Ready(NetworkEvent::IncomingConnection(incoming)) => {
-                    let handler = self.behaviour.new_handler();
-                    incoming.accept(handler.into_node_handler_builder());
+                    let allowed = self.gater.as_ref()
+                        .map_or(true, |g| g.allow_incoming(incoming.listen_addr(), incoming.send_back_addr()));
+                    if allowed {
+                        let handler = self.behaviour.new_handler()
+                            .into_node_handler_builder()
+                            .with_observer(self.observer.clone())
+                            .with_gater(self.gater.clone());
+                        incoming.accept(handler);
+                    } else {
+                        if let Some(observer) = &self.observer {
+                            observer.incoming_connection_error(incoming.send_back_addr(), ConnectionErrorKind::Gated);
+                        }
+                        // Dropping the event drops the connection before any handshake.
+                        drop(incoming);
+                    }
                 },
                 Async::Ready(NetworkEvent::NewListenerAddress { listen_addr }) => {
                     if !self.listened_addrs.contains(&listen_addr) {
@@ -377,29 +603,70 @@ where TBehaviour: NetworkBehaviour<ProtocolsHandler = THandler>,
                     self.behaviour.inject_expired_listen_addr(&listen_addr);
                 }
                 Async::Ready(NetworkEvent::ListenerClosed { .. }) => {},
-                Async::Ready(NetworkEvent::IncomingConnectionError { .. }) => {},
+                Async::Ready(NetworkEvent::IncomingConnectionError { send_back_addr, error, .. }) => {
+                    if let Some(observer) = &self.observer {
+                        observer.incoming_connection_error(&send_back_addr, (&error).into());
+                    }
+                },
                 Async::Ready(NetworkEvent::DialError { peer_id, multiaddr, error, new_state }) => {
+                    if let Some(observer) = &self.observer {
+                        observer.dial_failure(Some(&peer_id), &multiaddr, (&error).into());
+                    }
                     self.behaviour.inject_addr_reach_failure(Some(&peer_id), &multiaddr, &error);
                     if let network::PeerState::NotConnected = new_state {
                         self.behaviour.inject_dial_failure(&peer_id);
                     }
                 },
                 Async::Ready(NetworkEvent::UnknownPeerDialError { multiaddr, error, .. }) => {
+                    if let Some(observer) = &self.observer {
+                        observer.dial_failure(None, &multiaddr, (&error).into());
+                    }
                     self.behaviour.inject_addr_reach_failure(None, &multiaddr, &error);
                 },
+                Async::Ready(NetworkEvent::ConnectionLimitReached { limit, peer_id, endpoint }) => {
+                    self.behaviour.inject_connection_limit_reached(peer_id.as_ref(), &endpoint, &limit);
+                    // A refused substream concerns a connection that is still open, and says
+                    // nothing about the reachability of its address.
+                    if limit.kind != ConnectionLimitKind::Substreams {
+                        if let Some(observer) = &self.observer {
+                            match &endpoint {
+                                ConnectedPoint::Dialer { address, .. } =>
+                                    observer.dial_failure(peer_id.as_ref(), address, ConnectionErrorKind::ConnectionLimit),
+                                ConnectedPoint::Listener { send_back_addr, .. } =>
+                                    observer.incoming_connection_error(send_back_addr, ConnectionErrorKind::ConnectionLimit),
+                            }
+                        }
+                        if let ConnectedPoint::Dialer { address, .. } = endpoint {
+                            self.behaviour.inject_addr_reach_failure(peer_id.as_ref(), &address, &limit);
+                            if let Some(peer_id) = peer_id {
+                                if let network::Peer::NotConnected(_) = self.network.peer(peer_id.clone()) {
+                                    self.behaviour.inject_dial_failure(&peer_id);
+                                }
+                            }
+                        }
+                    }
+                },
             }
 
             // Try to deliver pending event.
-            if let Some((id, pending)) = self.send_event_to_complete.take() {
+            if let Some((id, handler, pending)) = self.send_event_to_complete.take() {
                 if let Some(mut peer) = self.network.peer(id.clone()).into_connected() {
+                    // An event for any connection is pinned to the oldest one, so that sending
+                    // and flushing happen on the same connection.
+                    let conn = match handler {
+                        NotifyHandler::One(conn) => conn,
+                        NotifyHandler::Any => peer.connections().next()
+                            .expect("a connected peer has at least one connection; QED"),
+                    };
+                    let handler = NotifyHandler::One(conn);
                     if let AsyncSink::NotReady(e) = pending {
-                        if let Ok(a@AsyncSink::NotReady(_)) = peer.start_send_event(e) {
-                            self.send_event_to_complete = Some((id, a))
-                        } else if let Ok(Async::NotReady) = peer.complete_send_event() {
-                            self.send_event_to_complete = Some((id, AsyncSink::Ready))
+                        if let Ok(a@AsyncSink::NotReady(_)) = peer.start_send_event_to(conn, e) {
+                            self.send_event_to_complete = Some((id, handler, a))
+                        } else if let Ok(Async::NotReady) = peer.complete_send_event_to(conn) {
+                            self.send_event_to_complete = Some((id, handler, AsyncSink::Ready))
                         }
-                    } else if let Ok(Async::NotReady) = peer.complete_send_event() {
-                        self.send_event_to_complete = Some((id, AsyncSink::Ready))
+                    } else if let Ok(Async::NotReady) = peer.complete_send_event_to(conn) {
+                        self.send_event_to_complete = Some((id, handler, AsyncSink::Ready))
                     }
                 }
             }
@@ -412,9 +679,14 @@ where TBehaviour: NetworkBehaviour<ProtocolsHandler = THandler>,
                     local_peer_id: &mut self.network.local_peer_id(),
                     supported_protocols: &self.supported_protocols,
                     listened_addrs: &self.listened_addrs,
-                    external_addrs: &self.external_addrs
+                    external_addrs: &self.external_addrs,
+                    peer_store: &mut self.peer_store,
                 };
-                self.behaviour.poll(&mut parameters)
+                let behaviour = &mut self.behaviour;
+                match with_context(|cx| behaviour.poll(cx, &mut parameters)) {
+                    Poll03::Ready(action) => Async::Ready(action),
+                    Poll03::Pending => Async::NotReady,
+                }
             };
 
             match behaviour_poll {
@@ -424,23 +696,21 @@ where TBehaviour: NetworkBehaviour<ProtocolsHandler = THandler>,
                     return Ok(Async::Ready(Some(event)))
                 },
                 Async::Ready(NetworkBehaviourAction::DialAddress { address }) => {
-                    let _ = ExpandedSwarm::dial_addr(self, address);
+                    ExpandedSwarm::dial_addr_of_behaviour(self, address, Endpoint::Dialer);
+                },
+                Async::Ready(NetworkBehaviourAction::DialAddressAsListener { address }) => {
+                    ExpandedSwarm::dial_addr_of_behaviour(self, address, Endpoint::Listener);
                 },
                 Async::Ready(NetworkBehaviourAction::DialPeer { peer_id }) => {
-                    if self.banned_peers.contains(&peer_id) {
+                    if ExpandedSwarm::is_banned(self, &peer_id) {
                         self.behaviour.inject_dial_failure(&peer_id);
                     } else {
                         ExpandedSwarm::dial(self, peer_id);
                     }
                 },
-                Async::Ready(NetworkBehaviourAction::SendEvent { peer_id, event }) => {
-                    if let Some(mut peer) = self.network.peer(peer_id.clone()).into_connected() {
-                        if let Ok(a@AsyncSink::NotReady(_)) = peer.start_send_event(event) {
-                            self.send_event_to_complete = Some((peer_id, a))
-                        } else if let Ok(Async::NotReady) = peer.complete_send_event() {
-                            self.send_event_to_complete = Some((peer_id, AsyncSink::Ready))
-                        }
-                    }
+                Async::Ready(NetworkBehaviourAction::NotifyHandler { peer_id, handler, event }) => {
+                    // The event is delivered at the next iteration of the loop.
+                    self.send_event_to_complete = Some((peer_id, handler, AsyncSink::NotReady(event)));
                 },
                 Async::Ready(NetworkBehaviourAction::ReportObservedAddr { address }) => {
                     for addr in self.network.nat_traversal(&address) {
@@ -450,6 +720,21 @@ where TBehaviour: NetworkBehaviour<ProtocolsHandler = THandler>,
                         self.external_addrs.add(addr)
                     }
                 },
+                Async::Ready(NetworkBehaviourAction::ReportExternalAddr { address }) => {
+                    if self.external_addrs.iter().all(|a| *a != address) {
+                        self.behaviour.inject_new_external_addr(&address);
+                    }
+                    self.external_addrs.add(address)
+                },
+                Async::Ready(NetworkBehaviourAction::ReportExpiredExternalAddr { address }) => {
+                    if self.external_addrs.remove(&address) {
+                        self.behaviour.inject_expired_external_addr(&address);
+                    }
+                },
+                Async::Ready(NetworkBehaviourAction::ReportMisbehaviour { peer_id, misbehaviour }) => {
+                    // A resulting ban is applied at the next iteration of the loop.
+                    self.peer_score.report(&peer_id, misbehaviour);
+                },
             }
         }
     }
@@ -462,6 +747,7 @@ pub struct SwarmPollParameters<'a> {
     supported_protocols: &'a [Vec<u8>],
     listened_addrs: &'a [Multiaddr],
     external_addrs: &'a Addresses,
+    peer_store: &'a mut PeerStore,
 }
 
 impl<'a> PollParameters for SwarmPollParameters<'a> {
@@ -484,10 +770,24 @@ impl<'a> PollParameters for SwarmPollParameters<'a> {
     fn local_peer_id(&self) -> &PeerId {
         self.local_peer_id
     }
+
+    fn peer_store(&self) -> &PeerStore {
+        self.peer_store
+    }
+
+    fn peer_store_mut(&mut self) -> &mut PeerStore {
+        self.peer_store
+    }
 }
 
 pub struct SwarmBuilder<TTransport, TBehaviour> {
-    incoming_limit: Option<u32>,
+    limits: ConnectionLimits,
+    peer_store: PeerStore,
+    peer_score: PeerScoreConfig,
+    observers: Vec<Arc<dyn SwarmObserver>>,
+    gater: Option<Arc<dyn ConnectionGater>>,
+    protocol_bandwidth: Option<ProtocolBandwidth>,
+    executor: Option<Box<dyn Executor + Send>>,
     local_peer_id: PeerId,
     transport: TTransport,
     behaviour: TBehaviour,
@@ -526,7 +826,13 @@ where TBehaviour: NetworkBehaviour,
 {
     pub fn new(transport: TTransport, behaviour: TBehaviour, local_peer_id: PeerId) -> Self {
         SwarmBuilder {
-            incoming_limit: None,
+            limits: ConnectionLimits::default(),
+            peer_store: PeerStore::default(),
+            peer_score: PeerScoreConfig::default(),
+            observers: Vec::new(),
+            gater: None,
+            protocol_bandwidth: None,
+            executor: None,
             local_peer_id,
             transport,
             behaviour,
@@ -534,7 +840,60 @@ where TBehaviour: NetworkBehaviour,
     }
 
     pub fn incoming_limit(mut self, incoming_limit: Option<u32>) -> Self {
-        self.incoming_limit = incoming_limit;
+        self.limits = self.limits.with_max_pending_incoming(incoming_limit);
+        self
+    }
+
+    /// Configures the limits on pending and established connections, and on open substreams.
+    ///
+    /// This overrides any limit previously set with `incoming_limit`.
+    pub fn connection_limits(mut self, limits: ConnectionLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Sets the initial content of the address book, for example one restored from a snapshot.
+    pub fn peer_store(mut self, peer_store: PeerStore) -> Self {
+        self.peer_store = peer_store;
+        self
+    }
+
+    /// Configures the scoring of the peers, and when peers get banned because of their score.
+    pub fn peer_score(mut self, config: PeerScoreConfig) -> Self {
+        self.peer_score = config;
+        self
+    }
+
+    /// Adds an observer that is informed of the activity of the swarm, such as a metrics
+    /// collector.
+    ///
+    /// Can be called multiple times, in which case all the observers are informed.
+    pub fn observer(mut self, observer: Arc<dyn SwarmObserver>) -> Self {
+        self.observers.push(observer);
+        self
+    }
+
+    /// Sets the gater that decides which connections and substreams are accepted.
+    pub fn connection_gater(mut self, gater: Arc<dyn ConnectionGater>) -> Self {
+        self.gater = Some(gater);
+        self
+    }
+
+    /// Enables the counting of the bytes exchanged with each peer on each protocol, whose rates
+    /// are averaged over the given rolling window.
+    ///
+    /// The counters can then be queried with [`ExpandedSwarm::protocol_bandwidth`].
+    pub fn protocol_bandwidth(mut self, window: Duration) -> Self {
+        self.protocol_bandwidth = Some(ProtocolBandwidth::new(window));
+        self
+    }
+
+    /// Sets the executor that runs the background tasks of the connections.
+    ///
+    /// Defaults to [`TokioExecutor`], which processes the connections on the current task when
+    /// used outside of a tokio runtime.
+    pub fn executor(mut self, executor: Box<dyn Executor + Send>) -> Self {
+        self.executor = Some(executor);
         self
     }
 
@@ -547,7 +906,13 @@ where TBehaviour: NetworkBehaviour,
             .map(|info| info.protocol_name().to_vec())
             .collect();
 
-        let network = Network::new_with_incoming_limit(self.transport, self.local_peer_id, self.incoming_limit);
+        let executor = self.executor.unwrap_or_else(|| Box::new(TokioExecutor));
+        let network = Network::new_with_executor(self.transport, self.local_peer_id, self.limits, executor);
+
+        let mut observers = self.observers;
+        if let Some(bandwidth) = &self.protocol_bandwidth {
+            observers.push(Arc::new(bandwidth.clone()));
+        }
 
         ExpandedSwarm {
             network,
@@ -556,6 +921,13 @@ where TBehaviour: NetworkBehaviour,
             listened_addrs: SmallVec::new(),
             external_addrs: Addresses::default(),
             banned_peers: HashSet::new(),
+            peer_store: self.peer_store,
+            peer_store_expiration: Delay::new(clock::now() + PEER_STORE_EXPIRATION_INTERVAL),
+            peer_score: PeerScore::new(self.peer_score),
+            peer_score_expiration: Delay::new(clock::now() + PEER_SCORE_EXPIRATION_INTERVAL),
+            observer: ObserverSet(observers).into_observer(),
+            gater: self.gater,
+            protocol_bandwidth: self.protocol_bandwidth,
             send_event_to_complete: None
         }
     }
@@ -564,18 +936,25 @@ where TBehaviour: NetworkBehaviour,
 #[cfg(test)]
 mod tests {
     use crate::protocols_handler::{DummyProtocolsHandler, ProtocolsHandler};
-    use crate::{NetworkBehaviour, NetworkBehaviourAction, PollParameters, SwarmBuilder};
+    use crate::{ConnectionLimits, Misbehaviour, NetworkBehaviour, NetworkBehaviourAction, PeerScoreConfig};
+    use crate::{ConnectionErrorKind, ConnectionGater, PollParameters, Swarm, SwarmBuilder, SwarmObserver};
     use libp2p_core::{
         ConnectedPoint,
+        compat::with_context,
         identity,
         Multiaddr,
+        multiaddr::Protocol,
         PeerId,
+        nodes::ConnectionId,
         PublicKey,
-        transport::dummy::{DummyStream, DummyTransport}
+        Transport,
+        transport::{MemoryTransport, dummy::{DummyStream, DummyTransport}},
+        upgrade
     };
-    use libp2p_mplex::Multiplex;
-    use futures::prelude::*;
-    use std::marker::PhantomData;
+    use libp2p_mplex::{Multiplex, MplexConfig};
+    use futures::{future, prelude::*};
+    use futures03::{TryFutureExt as _, task::{Context, Poll as Poll03}};
+    use std::{collections::VecDeque, error, marker::PhantomData, sync::{Arc, Mutex}};
     use tokio_io::{AsyncRead, AsyncWrite};
     use void::Void;
 
@@ -605,18 +984,346 @@ mod tests {
 
         fn inject_disconnected(&mut self, _: &PeerId, _: ConnectedPoint) {}
 
-        fn inject_node_event(&mut self, _: PeerId,
+        fn inject_node_event(&mut self, _: PeerId, _: ConnectionId,
             _: <Self::ProtocolsHandler as ProtocolsHandler>::OutEvent) {}
 
-        fn poll(&mut self, _: &mut impl PollParameters) ->
-            Async<NetworkBehaviourAction<<Self::ProtocolsHandler as
+        fn poll(&mut self, _: &mut Context<'_>, _: &mut impl PollParameters) ->
+            Poll03<NetworkBehaviourAction<<Self::ProtocolsHandler as
             ProtocolsHandler>::InEvent, Self::OutEvent>>
         {
-            Async::NotReady
+            Poll03::Pending
         }
 
     }
 
+    /// Behaviour that reports every peer it connects to as misbehaving, if enabled.
+    struct ReportingBehaviour<TSubstream> {
+        report: bool,
+        connected: Vec<PeerId>,
+        disconnected: Vec<PeerId>,
+        addr_reach_failures: Vec<Multiaddr>,
+        dial_failures: Vec<PeerId>,
+        reports: VecDeque<PeerId>,
+        marker: PhantomData<TSubstream>,
+    }
+
+    impl<TSubstream> ReportingBehaviour<TSubstream> {
+        fn new(report: bool) -> Self {
+            ReportingBehaviour {
+                report,
+                connected: Vec::new(),
+                disconnected: Vec::new(),
+                addr_reach_failures: Vec::new(),
+                dial_failures: Vec::new(),
+                reports: VecDeque::new(),
+                marker: PhantomData
+            }
+        }
+    }
+
+    impl<TSubstream> NetworkBehaviour for ReportingBehaviour<TSubstream>
+        where TSubstream: AsyncRead + AsyncWrite
+    {
+        type ProtocolsHandler = DummyProtocolsHandler<TSubstream>;
+        type OutEvent = Void;
+
+        fn new_handler(&mut self) -> Self::ProtocolsHandler {
+            DummyProtocolsHandler::default()
+        }
+
+        fn addresses_of_peer(&mut self, _: &PeerId) -> Vec<Multiaddr> {
+            Vec::new()
+        }
+
+        fn inject_connected(&mut self, peer_id: PeerId, _: ConnectedPoint) {
+            if self.report {
+                self.reports.push_back(peer_id.clone());
+            }
+            self.connected.push(peer_id);
+        }
+
+        fn inject_disconnected(&mut self, peer_id: &PeerId, _: ConnectedPoint) {
+            self.disconnected.push(peer_id.clone());
+        }
+
+        fn inject_addr_reach_failure(&mut self, _: Option<&PeerId>, addr: &Multiaddr, _: &dyn error::Error) {
+            self.addr_reach_failures.push(addr.clone());
+        }
+
+        fn inject_dial_failure(&mut self, peer_id: &PeerId) {
+            self.dial_failures.push(peer_id.clone());
+        }
+
+        fn inject_node_event(&mut self, _: PeerId, _: ConnectionId,
+            _: <Self::ProtocolsHandler as ProtocolsHandler>::OutEvent) {}
+
+        fn poll(&mut self, _: &mut Context<'_>, _: &mut impl PollParameters) ->
+            Poll03<NetworkBehaviourAction<<Self::ProtocolsHandler as
+            ProtocolsHandler>::InEvent, Self::OutEvent>>
+        {
+            match self.reports.pop_front() {
+                Some(peer_id) => Poll03::Ready(NetworkBehaviourAction::ReportMisbehaviour {
+                    peer_id,
+                    misbehaviour: Misbehaviour::ProtocolViolation,
+                }),
+                None => Poll03::Pending
+            }
+        }
+    }
+
+    /// Observer that records the connections reported to it.
+    #[derive(Default)]
+    struct RecordingObserver {
+        established: Mutex<usize>,
+        closed: Mutex<usize>,
+        errors: Mutex<Vec<ConnectionErrorKind>>,
+    }
+
+    impl SwarmObserver for RecordingObserver {
+        fn connection_established(&self, _: &PeerId, _: &ConnectedPoint) {
+            *self.established.lock().unwrap() += 1;
+        }
+
+        fn connection_closed(&self, _: &PeerId, _: &ConnectedPoint) {
+            *self.closed.lock().unwrap() += 1;
+        }
+
+        fn incoming_connection_error(&self, _: &Multiaddr, kind: ConnectionErrorKind) {
+            self.errors.lock().unwrap().push(kind);
+        }
+    }
+
+    #[test]
+    fn misbehaving_peers_are_banned() {
+        let id1 = PeerId::from(get_random_id());
+        let id2 = PeerId::from(get_random_id());
+
+        // Each transport attributes all its connections to the other peer.
+        let transport = |remote: PeerId| MemoryTransport
+            .and_then(move |out, endpoint| {
+                upgrade::apply(out, MplexConfig::new(), endpoint).map_ok(move |muxer| (remote, muxer))
+            });
+
+        let config = PeerScoreConfig::default().with_ban_threshold(-1.0);
+        let observer = Arc::new(RecordingObserver::default());
+        let mut swarm1 = SwarmBuilder::new(transport(id2.clone()), ReportingBehaviour::new(true), id1.clone())
+            .peer_score(config)
+            .observer(observer.clone())
+            .build();
+        let mut swarm2 = SwarmBuilder::new(transport(id1.clone()), ReportingBehaviour::new(false), id2.clone())
+            .build();
+
+        Swarm::listen_on(&mut swarm1, "/memory/0".parse().unwrap()).unwrap();
+
+        // Connects twice in a row. The first connection gets the remote banned, and the second
+        // one is rejected.
+        let mut dials = 0;
+        future::poll_fn(move || -> Poll<(), ()> {
+            loop {
+                if swarm1.poll().unwrap().is_ready() || swarm2.poll().unwrap().is_ready() {
+                    continue
+                }
+                let addr = match Swarm::listeners(&swarm1).next() {
+                    Some(addr) => addr.clone(),
+                    None => return Ok(Async::NotReady)
+                };
+                if swarm2.disconnected.len() == dials {
+                    if dials == 2 {
+                        break
+                    }
+                    Swarm::dial_addr(&mut swarm2, addr).unwrap();
+                    dials += 1;
+                    continue
+                }
+                return Ok(Async::NotReady)
+            }
+
+            assert!(Swarm::peer_score(&swarm1).is_banned(&id2));
+            assert_eq!(swarm1.connected, vec![id2.clone()]);
+            assert_eq!(swarm1.disconnected, vec![id2.clone()]);
+            assert_eq!(swarm2.connected, vec![id1.clone(), id1.clone()]);
+
+            // The connection closed because of the ban is reported as closed, and the rejected
+            // one is never reported as established.
+            assert_eq!(*observer.established.lock().unwrap(), 1);
+            assert_eq!(*observer.closed.lock().unwrap(), 1);
+            assert_eq!(*observer.errors.lock().unwrap(), vec![ConnectionErrorKind::Banned]);
+            Ok(Async::Ready(()))
+        }).wait().unwrap();
+    }
+
+    /// Gater that denies the connections to and from a peer, and the dials to `/memory/1`.
+    struct DenyPeer(PeerId);
+
+    impl ConnectionGater for DenyPeer {
+        fn allow_dial(&self, _: Option<&PeerId>, addr: &Multiaddr) -> bool {
+            addr.iter().all(|p| p != Protocol::Memory(1))
+        }
+
+        fn allow_connection(&self, peer_id: &PeerId, _: &ConnectedPoint) -> bool {
+            *peer_id != self.0
+        }
+    }
+
+    #[test]
+    fn gated_connections_are_rejected() {
+        let id1 = PeerId::from(get_random_id());
+        let id2 = PeerId::from(get_random_id());
+
+        // Each transport attributes all its connections to the other peer.
+        let transport = |remote: PeerId| MemoryTransport
+            .and_then(move |out, endpoint| {
+                upgrade::apply(out, MplexConfig::new(), endpoint).map_ok(move |muxer| (remote, muxer))
+            });
+
+        let mut swarm1 = SwarmBuilder::new(transport(id2.clone()), ReportingBehaviour::new(false), id1.clone())
+            .connection_gater(Arc::new(DenyPeer(id2.clone())))
+            .build();
+        let mut swarm2 = SwarmBuilder::new(transport(id1.clone()), ReportingBehaviour::new(false), id2.clone())
+            .connection_gater(Arc::new(DenyPeer(PeerId::random())))
+            .build();
+
+        assert!(Swarm::dial_addr(&mut swarm2, "/memory/1".parse().unwrap()).is_err());
+        Swarm::listen_on(&mut swarm1, "/memory/0".parse().unwrap()).unwrap();
+
+        // The connection is accepted by the dialer, and closed by the listener.
+        let mut dialed = false;
+        future::poll_fn(move || -> Poll<(), ()> {
+            loop {
+                if swarm1.poll().unwrap().is_ready() || swarm2.poll().unwrap().is_ready() {
+                    continue
+                }
+                let addr = match Swarm::listeners(&swarm1).next() {
+                    Some(addr) => addr.clone(),
+                    None => return Ok(Async::NotReady)
+                };
+                if !dialed {
+                    Swarm::dial_addr(&mut swarm2, addr).unwrap();
+                    dialed = true;
+                    continue
+                }
+                if swarm2.disconnected.is_empty() {
+                    return Ok(Async::NotReady)
+                }
+                break
+            }
+
+            assert!(swarm1.connected.is_empty());
+            assert_eq!(swarm2.connected, vec![id1.clone()]);
+            Ok(Async::Ready(()))
+        }).wait().unwrap();
+    }
+
+    #[test]
+    fn connections_denied_by_the_dialer_are_dial_failures() {
+        let id1 = PeerId::from(get_random_id());
+        let id2 = PeerId::from(get_random_id());
+
+        // Each transport attributes all its connections to the other peer.
+        let transport = |remote: PeerId| MemoryTransport
+            .and_then(move |out, endpoint| {
+                upgrade::apply(out, MplexConfig::new(), endpoint).map_ok(move |muxer| (remote, muxer))
+            });
+
+        let mut swarm1 = SwarmBuilder::new(transport(id2.clone()), ReportingBehaviour::new(false), id1.clone())
+            .build();
+        let mut swarm2 = SwarmBuilder::new(transport(id1.clone()), ReportingBehaviour::new(false), id2.clone())
+            .connection_gater(Arc::new(DenyPeer(id1.clone())))
+            .build();
+
+        Swarm::listen_on(&mut swarm1, "/memory/0".parse().unwrap()).unwrap();
+
+        // The connection is accepted by the listener, and closed by the dialer.
+        let mut dialed = None;
+        future::poll_fn(move || -> Poll<(), ()> {
+            loop {
+                if swarm1.poll().unwrap().is_ready() || swarm2.poll().unwrap().is_ready() {
+                    continue
+                }
+                let addr = match Swarm::listeners(&swarm1).next() {
+                    Some(addr) => addr.clone(),
+                    None => return Ok(Async::NotReady)
+                };
+                if dialed.is_none() {
+                    Swarm::dial_addr(&mut swarm2, addr.clone()).unwrap();
+                    dialed = Some(addr);
+                    continue
+                }
+                if swarm2.dial_failures.is_empty() {
+                    return Ok(Async::NotReady)
+                }
+                break
+            }
+
+            assert!(swarm2.connected.is_empty());
+            assert_eq!(swarm2.addr_reach_failures, vec![dialed.clone().unwrap()]);
+            assert_eq!(swarm2.dial_failures, vec![id1.clone()]);
+            assert!(swarm1.addr_reach_failures.is_empty());
+            Ok(Async::Ready(()))
+        }).wait().unwrap();
+    }
+
+    #[test]
+    fn custom_executor_runs_connections() {
+        let id1 = PeerId::from(get_random_id());
+        let id2 = PeerId::from(get_random_id());
+
+        let transport = |remote: PeerId| MemoryTransport
+            .and_then(move |out, endpoint| {
+                upgrade::apply(out, MplexConfig::new(), endpoint).map_ok(move |muxer| (remote, muxer))
+            });
+
+        // The executor queues the tasks, which are polled below along with the swarms.
+        let spawned = Arc::new(Mutex::new(Vec::new()));
+        let executor = {
+            let spawned = spawned.clone();
+            move |task| spawned.lock().unwrap().push(task)
+        };
+
+        let mut swarm1 = SwarmBuilder::new(transport(id2.clone()), ReportingBehaviour::new(false), id1.clone())
+            .executor(Box::new(executor.clone()))
+            .build();
+        let mut swarm2 = SwarmBuilder::new(transport(id1.clone()), ReportingBehaviour::new(false), id2.clone())
+            .executor(Box::new(executor))
+            .build();
+
+        Swarm::listen_on(&mut swarm1, "/memory/0".parse().unwrap()).unwrap();
+
+        let mut tasks = Vec::new();
+        let mut dialed = false;
+        future::poll_fn(move || -> Poll<(), ()> {
+            loop {
+                if swarm1.poll().unwrap().is_ready() || swarm2.poll().unwrap().is_ready() {
+                    continue
+                }
+                tasks.extend(spawned.lock().unwrap().drain(..));
+                for n in (0 .. tasks.len()).rev() {
+                    let mut task: futures03::future::BoxFuture<'static, ()> = tasks.swap_remove(n);
+                    if with_context(|cx| task.as_mut().poll(cx)).is_pending() {
+                        tasks.push(task)
+                    }
+                }
+                let addr = match Swarm::listeners(&swarm1).next() {
+                    Some(addr) => addr.clone(),
+                    None => return Ok(Async::NotReady)
+                };
+                if !dialed {
+                    Swarm::dial_addr(&mut swarm2, addr).unwrap();
+                    dialed = true;
+                    continue
+                }
+                if swarm1.connected.is_empty() || swarm2.connected.is_empty() {
+                    return Ok(Async::NotReady)
+                }
+                break
+            }
+
+            assert_eq!(swarm1.connected, vec![id2.clone()]);
+            assert_eq!(swarm2.connected, vec![id1.clone()]);
+            Ok(Async::Ready(()))
+        }).wait().unwrap();
+    }
+
     fn get_random_id() -> PublicKey {
         identity::Keypair::generate_ed25519().public()
     }
@@ -639,4 +1346,18 @@ mod tests {
         let swarm = SwarmBuilder::new(transport, behaviour, id.into()).build();
         assert!(swarm.network.incoming_limit().is_none())
     }
+
+    #[test]
+    fn test_build_swarm_with_connection_limits() {
+        let id = get_random_id();
+        let transport = DummyTransport::<(PeerId, Multiplex<DummyStream>)>::new();
+        let behaviour = DummyBehaviour{marker: PhantomData};
+        let limits = ConnectionLimits::default()
+            .with_max_pending_incoming(Some(4))
+            .with_max_established_per_peer(Some(1));
+        let swarm = SwarmBuilder::new(transport, behaviour, id.into())
+            .connection_limits(limits.clone()).build();
+        assert_eq!(swarm.network.incoming_limit(), Some(4));
+        assert_eq!(swarm.network.limits(), &limits);
+    }
 }
@@ -0,0 +1,309 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Observation of the swarm's activity.
+//!
+//! A [`SwarmObserver`] can be passed to [`SwarmBuilder::observer`](crate::SwarmBuilder::observer)
+//! in order to be informed of connections being opened and closed, of connection attempts that
+//! fail, and of the traffic on the substreams of each connection. This is what metrics collectors
+//! hook into, without requiring any change to the network behaviours.
+
+use crate::gater::ConnectionDenied;
+use libp2p_core::{
+    ConnectedPoint, Endpoint, Multiaddr, PeerId,
+    muxing::SubstreamMeter,
+    nodes::network::{IncomingError, NetworkReachError, UnknownPeerDialErr},
+    transport::TransportError
+};
+use futures::Async;
+use std::{cmp, fmt, sync::Arc};
+
+/// Receives notifications about the activity of a `Swarm`.
+///
+/// All the methods have a default implementation that does nothing.
+///
+/// The observer is shared with the background tasks that drive the connections, and must
+/// therefore be thread-safe.
+pub trait SwarmObserver: Send + Sync {
+    /// A connection to the given peer has been established.
+    fn connection_established(&self, _peer_id: &PeerId, _endpoint: &ConnectedPoint) {}
+
+    /// A connection to the given peer has been closed.
+    fn connection_closed(&self, _peer_id: &PeerId, _endpoint: &ConnectedPoint) {}
+
+    /// An attempt to reach the given address has failed.
+    fn dial_failure(&self, _peer_id: Option<&PeerId>, _address: &Multiaddr, _kind: ConnectionErrorKind) {}
+
+    /// An incoming connection could not be established.
+    fn incoming_connection_error(&self, _send_back_addr: &Multiaddr, _kind: ConnectionErrorKind) {}
+
+    /// A new substream has been opened with the given peer, and its protocol is about to be
+    /// negotiated.
+    ///
+    /// The returned meter, if any, is attached to the substream for the rest of its lifetime.
+    fn substream_meter(&self, _peer_id: &PeerId, _endpoint: Endpoint) -> Option<Arc<dyn SubstreamMeter>> {
+        None
+    }
+}
+
+/// The reason why a connection could not be established.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ConnectionErrorKind {
+    /// The address is not supported by the transport.
+    MultiaddrNotSupported,
+    /// The transport failed to establish or upgrade the connection.
+    Transport,
+    /// The remote turned out to have a different `PeerId` than the one expected.
+    PeerIdMismatch,
+    /// The remote turned out to have the same `PeerId` as the local node.
+    LocalPeerId,
+    /// The connection was denied because of the configured `ConnectionLimits`.
+    ConnectionLimit,
+    /// The connection was denied by the `ConnectionGater`.
+    Gated,
+    /// The remote is banned.
+    Banned,
+}
+
+impl ConnectionErrorKind {
+    /// Returns a short, stable name for the kind of error, suitable as a metric label.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConnectionErrorKind::MultiaddrNotSupported => "multiaddr_not_supported",
+            ConnectionErrorKind::Transport => "transport",
+            ConnectionErrorKind::PeerIdMismatch => "peer_id_mismatch",
+            ConnectionErrorKind::LocalPeerId => "local_peer_id",
+            ConnectionErrorKind::ConnectionLimit => "connection_limit",
+            ConnectionErrorKind::Gated => "gated",
+            ConnectionErrorKind::Banned => "banned",
+        }
+    }
+}
+
+impl fmt::Display for ConnectionErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl From<ConnectionDenied> for ConnectionErrorKind {
+    fn from(denied: ConnectionDenied) -> Self {
+        match denied {
+            ConnectionDenied::Gated => ConnectionErrorKind::Gated,
+            ConnectionDenied::Banned => ConnectionErrorKind::Banned,
+        }
+    }
+}
+
+impl<TErr> From<&TransportError<TErr>> for ConnectionErrorKind {
+    fn from(err: &TransportError<TErr>) -> Self {
+        match err {
+            TransportError::MultiaddrNotSupported(_) => ConnectionErrorKind::MultiaddrNotSupported,
+            TransportError::Other(_) => ConnectionErrorKind::Transport,
+        }
+    }
+}
+
+impl<TErr, TConnInfo> From<&NetworkReachError<TErr, TConnInfo>> for ConnectionErrorKind {
+    fn from(err: &NetworkReachError<TErr, TConnInfo>) -> Self {
+        match err {
+            NetworkReachError::Transport(err) => err.into(),
+            NetworkReachError::PeerIdMismatch { .. } => ConnectionErrorKind::PeerIdMismatch,
+        }
+    }
+}
+
+impl<TErr> From<&UnknownPeerDialErr<TErr>> for ConnectionErrorKind {
+    fn from(err: &UnknownPeerDialErr<TErr>) -> Self {
+        match err {
+            UnknownPeerDialErr::Transport(err) => err.into(),
+            UnknownPeerDialErr::FoundLocalPeerId => ConnectionErrorKind::LocalPeerId,
+        }
+    }
+}
+
+impl<TErr> From<&IncomingError<TErr>> for ConnectionErrorKind {
+    fn from(err: &IncomingError<TErr>) -> Self {
+        match err {
+            IncomingError::Transport(err) => err.into(),
+            IncomingError::FoundLocalPeerId => ConnectionErrorKind::LocalPeerId,
+        }
+    }
+}
+
+/// State of a `SubstreamMeter` that attributes the traffic of a substream to its protocol.
+///
+/// The protocol is only known once it has been negotiated, and the traffic of the negotiation
+/// itself is accumulated until then. The traffic of a substream whose negotiation fails is
+/// therefore never attributed to anything.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MeterState<T> {
+    /// The protocol is being negotiated.
+    Negotiating {
+        /// Number of bytes received so far.
+        read: u64,
+        /// Number of bytes sent so far.
+        written: u64,
+    },
+    /// The protocol has been negotiated, and the traffic is attributed to the given value.
+    Negotiated(T),
+}
+
+impl<T> MeterState<T> {
+    /// Creates the state of a substream whose protocol is being negotiated.
+    pub fn new() -> Self {
+        MeterState::Negotiating { read: 0, written: 0 }
+    }
+
+    /// Marks the protocol as negotiated, and attributes the rest of the traffic to `value`.
+    ///
+    /// Returns the number of bytes received and sent during the negotiation, which are still to
+    /// be attributed to `value`.
+    pub fn negotiated(&mut self, value: T) -> (u64, u64) {
+        match std::mem::replace(self, MeterState::Negotiated(value)) {
+            MeterState::Negotiating { read, written } => (read, written),
+            MeterState::Negotiated(_) => (0, 0),
+        }
+    }
+
+    /// Records bytes received on the substream.
+    ///
+    /// Returns the value to attribute them to, or `None` if they have been accumulated because
+    /// the protocol is still being negotiated.
+    pub fn on_read(&mut self, bytes: usize) -> Option<&mut T> {
+        match self {
+            MeterState::Negotiating { read, .. } => { *read += bytes as u64; None }
+            MeterState::Negotiated(value) => Some(value),
+        }
+    }
+
+    /// Records bytes sent on the substream.
+    ///
+    /// Returns the value to attribute them to, or `None` if they have been accumulated because
+    /// the protocol is still being negotiated.
+    pub fn on_write(&mut self, bytes: usize) -> Option<&mut T> {
+        match self {
+            MeterState::Negotiating { written, .. } => { *written += bytes as u64; None }
+            MeterState::Negotiated(value) => Some(value),
+        }
+    }
+}
+
+impl<T> Default for MeterState<T> {
+    fn default() -> Self {
+        MeterState::new()
+    }
+}
+
+/// Dispatches the notifications to multiple observers.
+pub(crate) struct ObserverSet(pub(crate) Vec<Arc<dyn SwarmObserver>>);
+
+impl ObserverSet {
+    /// Turns the list of observers into a single one, if there is any.
+    pub(crate) fn into_observer(mut self) -> Option<Arc<dyn SwarmObserver>> {
+        match self.0.len() {
+            0 => None,
+            1 => self.0.pop(),
+            _ => Some(Arc::new(self))
+        }
+    }
+}
+
+impl SwarmObserver for ObserverSet {
+    fn connection_established(&self, peer_id: &PeerId, endpoint: &ConnectedPoint) {
+        for observer in &self.0 {
+            observer.connection_established(peer_id, endpoint);
+        }
+    }
+
+    fn connection_closed(&self, peer_id: &PeerId, endpoint: &ConnectedPoint) {
+        for observer in &self.0 {
+            observer.connection_closed(peer_id, endpoint);
+        }
+    }
+
+    fn dial_failure(&self, peer_id: Option<&PeerId>, address: &Multiaddr, kind: ConnectionErrorKind) {
+        for observer in &self.0 {
+            observer.dial_failure(peer_id, address, kind);
+        }
+    }
+
+    fn incoming_connection_error(&self, send_back_addr: &Multiaddr, kind: ConnectionErrorKind) {
+        for observer in &self.0 {
+            observer.incoming_connection_error(send_back_addr, kind);
+        }
+    }
+
+    fn substream_meter(&self, peer_id: &PeerId, endpoint: Endpoint) -> Option<Arc<dyn SubstreamMeter>> {
+        let mut meters = self.0.iter()
+            .filter_map(|o| o.substream_meter(peer_id, endpoint))
+            .collect::<Vec<_>>();
+        match meters.len() {
+            0 => None,
+            1 => meters.pop(),
+            _ => Some(Arc::new(MeterSet(meters)))
+        }
+    }
+}
+
+/// Dispatches the traffic of a substream to multiple meters.
+struct MeterSet(Vec<Arc<dyn SubstreamMeter>>);
+
+impl SubstreamMeter for MeterSet {
+    fn on_negotiated(&self, protocol: &[u8]) {
+        for meter in &self.0 {
+            meter.on_negotiated(protocol);
+        }
+    }
+
+    fn poll_read_quota(&self, len: usize) -> Async<usize> {
+        let mut quota = len;
+        for meter in &self.0 {
+            match meter.poll_read_quota(quota) {
+                Async::Ready(q) => quota = cmp::min(quota, q),
+                Async::NotReady => return Async::NotReady
+            }
+        }
+        Async::Ready(quota)
+    }
+
+    fn poll_write_quota(&self, len: usize) -> Async<usize> {
+        let mut quota = len;
+        for meter in &self.0 {
+            match meter.poll_write_quota(quota) {
+                Async::Ready(q) => quota = cmp::min(quota, q),
+                Async::NotReady => return Async::NotReady
+            }
+        }
+        Async::Ready(quota)
+    }
+
+    fn on_read(&self, bytes: usize) {
+        for meter in &self.0 {
+            meter.on_read(bytes);
+        }
+    }
+
+    fn on_write(&self, bytes: usize) {
+        for meter in &self.0 {
+            meter.on_write(bytes);
+        }
+    }
+}
@@ -0,0 +1,314 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Scoring of the peers according to their behaviour.
+//!
+//! Behaviours report the misbehaviour of remote peers, such as protocol violations, timeouts or
+//! invalid records, with [`NetworkBehaviourAction::ReportMisbehaviour`]. Each report lowers the
+//! score of the peer by a configurable penalty, and the scores decay back towards zero over time.
+//!
+//! When the score of a peer falls below the ban threshold, the swarm disconnects from the peer
+//! and rejects any connection to or from it until the ban expires. The score of the peer is
+//! then reset.
+//!
+//! [`NetworkBehaviourAction::ReportMisbehaviour`]: crate::NetworkBehaviourAction::ReportMisbehaviour
+
+use crate::protocols_handler::ProtocolsHandlerUpgrErr;
+use libp2p_core::{PeerId, clock, upgrade::UpgradeError};
+use std::{collections::HashMap, fmt, io, time::Duration};
+use wasm_timer::Instant;
+
+/// A misbehaviour of a remote peer.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Misbehaviour {
+    /// The peer sent data that doesn't respect the protocol.
+    ProtocolViolation,
+    /// The peer didn't answer a request in time.
+    Timeout,
+    /// The peer sent a record that isn't valid.
+    InvalidRecord,
+}
+
+impl Misbehaviour {
+    /// Returns the misbehaviour that an error while upgrading a substream denotes, if any.
+    ///
+    /// Timeouts are reported as such, and data that can't be decoded, denoted by an
+    /// `io::ErrorKind::InvalidData` error, as a protocol violation. Other errors, such as the
+    /// remote not supporting the protocol or the connection being closed, are not considered
+    /// misbehaviours.
+    pub fn from_upgrade_error(error: &ProtocolsHandlerUpgrErr<io::Error>) -> Option<Misbehaviour> {
+        match error {
+            ProtocolsHandlerUpgrErr::Timeout => Some(Misbehaviour::Timeout),
+            ProtocolsHandlerUpgrErr::Upgrade(UpgradeError::Apply(err))
+                if err.kind() == io::ErrorKind::InvalidData => Some(Misbehaviour::ProtocolViolation),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Misbehaviour {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Misbehaviour::ProtocolViolation => write!(f, "protocol violation"),
+            Misbehaviour::Timeout => write!(f, "timeout"),
+            Misbehaviour::InvalidRecord => write!(f, "invalid record"),
+        }
+    }
+}
+
+/// Configuration of a [`PeerScore`].
+#[derive(Debug, Clone)]
+pub struct PeerScoreConfig {
+    half_life: Duration,
+    ban_threshold: f64,
+    ban_duration: Duration,
+    protocol_violation: f64,
+    timeout: f64,
+    invalid_record: f64,
+}
+
+impl Default for PeerScoreConfig {
+    fn default() -> Self {
+        PeerScoreConfig {
+            half_life: Duration::from_secs(10 * 60),
+            ban_threshold: -100.0,
+            ban_duration: Duration::from_secs(60 * 60),
+            protocol_violation: 20.0,
+            timeout: 5.0,
+            invalid_record: 10.0,
+        }
+    }
+}
+
+impl PeerScoreConfig {
+    /// Sets the time after which a score has decayed to half its value. Defaults to 10 minutes.
+    pub fn with_half_life(mut self, half_life: Duration) -> Self {
+        self.half_life = half_life;
+        self
+    }
+
+    /// Sets the score below which a peer is banned. Defaults to -100.
+    pub fn with_ban_threshold(mut self, threshold: f64) -> Self {
+        self.ban_threshold = threshold;
+        self
+    }
+
+    /// Sets for how long a peer remains banned. Defaults to an hour.
+    pub fn with_ban_duration(mut self, duration: Duration) -> Self {
+        self.ban_duration = duration;
+        self
+    }
+
+    /// Sets the amount by which a misbehaviour lowers the score of a peer.
+    ///
+    /// Defaults to 20 for a protocol violation, 5 for a timeout and 10 for an invalid record.
+    pub fn with_penalty(mut self, misbehaviour: Misbehaviour, penalty: f64) -> Self {
+        *self.penalty_mut(misbehaviour) = penalty.abs();
+        self
+    }
+
+    /// Returns the amount by which a misbehaviour lowers the score of a peer.
+    pub fn penalty(&self, misbehaviour: Misbehaviour) -> f64 {
+        match misbehaviour {
+            Misbehaviour::ProtocolViolation => self.protocol_violation,
+            Misbehaviour::Timeout => self.timeout,
+            Misbehaviour::InvalidRecord => self.invalid_record,
+        }
+    }
+
+    fn penalty_mut(&mut self, misbehaviour: Misbehaviour) -> &mut f64 {
+        match misbehaviour {
+            Misbehaviour::ProtocolViolation => &mut self.protocol_violation,
+            Misbehaviour::Timeout => &mut self.timeout,
+            Misbehaviour::InvalidRecord => &mut self.invalid_record,
+        }
+    }
+}
+
+/// The scores of the remote peers, and the peers that are banned because of their score.
+#[derive(Debug, Clone, Default)]
+pub struct PeerScore {
+    config: PeerScoreConfig,
+    /// The score of each peer that has one, along with when it was last updated.
+    scores: HashMap<PeerId, (f64, Instant)>,
+    /// The banned peers, along with when their ban expires.
+    bans: HashMap<PeerId, Instant>,
+    /// The peers banned since the last call to `take_new_bans`.
+    new_bans: Vec<PeerId>,
+}
+
+impl PeerScore {
+    /// Creates scores with the given configuration.
+    pub fn new(config: PeerScoreConfig) -> Self {
+        PeerScore {
+            config,
+            scores: HashMap::new(),
+            bans: HashMap::new(),
+            new_bans: Vec::new(),
+        }
+    }
+
+    /// Returns the configuration.
+    pub fn config(&self) -> &PeerScoreConfig {
+        &self.config
+    }
+
+    /// Returns the current score of a peer, which is zero for a peer that never misbehaved.
+    pub fn score(&self, peer_id: &PeerId) -> f64 {
+        self.score_at(peer_id, clock::now())
+    }
+
+    /// Lowers the score of a peer because of a misbehaviour, banning the peer if the score falls
+    /// below the threshold.
+    ///
+    /// Returns `true` if the peer has been banned as a result.
+    pub fn report(&mut self, peer_id: &PeerId, misbehaviour: Misbehaviour) -> bool {
+        let penalty = self.config.penalty(misbehaviour);
+        self.adjust_at(peer_id, -penalty, clock::now())
+    }
+
+    /// Adds `delta` to the score of a peer, which can be positive in order to reward good
+    /// behaviour, banning the peer if the score falls below the threshold.
+    ///
+    /// Returns `true` if the peer has been banned as a result.
+    pub fn adjust(&mut self, peer_id: &PeerId, delta: f64) -> bool {
+        self.adjust_at(peer_id, delta, clock::now())
+    }
+
+    /// Returns true if the peer is currently banned.
+    pub fn is_banned(&self, peer_id: &PeerId) -> bool {
+        self.bans.get(peer_id).map_or(false, |expires| *expires > clock::now())
+    }
+
+    /// Returns the banned peers, along with when their ban expires.
+    pub fn banned_peers(&self) -> impl Iterator<Item = (&PeerId, Instant)> {
+        let now = clock::now();
+        self.bans.iter().filter(move |(_, e)| **e > now).map(|(p, e)| (p, *e))
+    }
+
+    /// Lifts the ban of a peer, if any, and resets its score.
+    pub fn unban(&mut self, peer_id: &PeerId) {
+        self.bans.remove(peer_id);
+        self.scores.remove(peer_id);
+    }
+
+    /// Returns the peers banned since the last call.
+    pub(crate) fn take_new_bans(&mut self) -> Vec<PeerId> {
+        std::mem::replace(&mut self.new_bans, Vec::new())
+    }
+
+    /// Forgets the expired bans and the scores that have decayed to nothing.
+    pub(crate) fn expire(&mut self) {
+        let now = clock::now();
+        self.bans.retain(|_, expires| *expires > now);
+        let half_life = self.config.half_life;
+        self.scores.retain(|_, (score, updated)| decay(*score, *updated, now, half_life).abs() >= 0.01);
+    }
+
+    fn score_at(&self, peer_id: &PeerId, now: Instant) -> f64 {
+        self.scores.get(peer_id)
+            .map_or(0.0, |(score, updated)| decay(*score, *updated, now, self.config.half_life))
+    }
+
+    fn adjust_at(&mut self, peer_id: &PeerId, delta: f64, now: Instant) -> bool {
+        if self.bans.get(peer_id).map_or(false, |expires| *expires > now) {
+            return false
+        }
+        let score = self.score_at(peer_id, now) + delta;
+        if score < self.config.ban_threshold {
+            self.scores.remove(peer_id);
+            self.bans.insert(peer_id.clone(), now + self.config.ban_duration);
+            self.new_bans.push(peer_id.clone());
+            true
+        } else {
+            self.scores.insert(peer_id.clone(), (score, now));
+            false
+        }
+    }
+}
+
+/// Returns the value of a score after it decayed from `updated` until `now`.
+fn decay(score: f64, updated: Instant, now: Instant, half_life: Duration) -> f64 {
+    if half_life == Duration::from_secs(0) {
+        return 0.0
+    }
+    if now <= updated {
+        return score
+    }
+    let elapsed = now - updated;
+    let elapsed = elapsed.as_secs() as f64 + f64::from(elapsed.subsec_nanos()) / 1e9;
+    let half_life = half_life.as_secs() as f64 + f64::from(half_life.subsec_nanos()) / 1e9;
+    score * 0.5f64.powf(elapsed / half_life)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scores_decay() {
+        let mut scores = PeerScore::new(PeerScoreConfig::default().with_half_life(Duration::from_secs(10)));
+        let peer = PeerId::random();
+        let now = Instant::now();
+        assert!(!scores.adjust_at(&peer, -40.0, now));
+        assert_eq!(scores.score_at(&peer, now), -40.0);
+        assert_eq!(scores.score_at(&peer, now + Duration::from_secs(10)), -20.0);
+        assert_eq!(scores.score_at(&peer, now + Duration::from_secs(20)), -10.0);
+        assert_eq!(scores.score_at(&PeerId::random(), now), 0.0);
+    }
+
+    #[test]
+    fn peers_are_banned_below_threshold() {
+        let config = PeerScoreConfig::default()
+            .with_ban_threshold(-50.0)
+            .with_penalty(Misbehaviour::InvalidRecord, 30.0);
+        let mut scores = PeerScore::new(config);
+        let peer = PeerId::random();
+        assert!(!scores.report(&peer, Misbehaviour::InvalidRecord));
+        assert!(!scores.is_banned(&peer));
+        assert!(scores.report(&peer, Misbehaviour::InvalidRecord));
+        assert!(scores.is_banned(&peer));
+        assert_eq!(scores.take_new_bans(), vec![peer.clone()]);
+        assert!(scores.take_new_bans().is_empty());
+        // The score is reset, and reports are ignored during the ban.
+        assert_eq!(scores.score(&peer), 0.0);
+        assert!(!scores.report(&peer, Misbehaviour::InvalidRecord));
+        assert_eq!(scores.score(&peer), 0.0);
+
+        scores.unban(&peer);
+        assert!(!scores.is_banned(&peer));
+    }
+
+    #[test]
+    fn bans_expire() {
+        let config = PeerScoreConfig::default()
+            .with_ban_threshold(-1.0)
+            .with_ban_duration(Duration::from_millis(10));
+        let mut scores = PeerScore::new(config);
+        let peer = PeerId::random();
+        assert!(scores.report(&peer, Misbehaviour::ProtocolViolation));
+        assert!(scores.is_banned(&peer));
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(!scores.is_banned(&peer));
+        scores.expire();
+        assert_eq!(scores.banned_peers().count(), 0);
+        assert!(scores.bans.is_empty());
+    }
+}
@@ -0,0 +1,462 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Address book of the swarm.
+//!
+//! The [`PeerStore`] gathers what is known about remote peers: the addresses they can be reached
+//! at, along with where each address comes from and for how long it is valid, their public key,
+//! the protocols they support and their agent version.
+//!
+//! The store is owned by the `Swarm`. Behaviours access it through
+//! [`PollParameters::peer_store`](crate::PollParameters::peer_store) and
+//! [`PollParameters::peer_store_mut`](crate::PollParameters::peer_store_mut), and the addresses
+//! it contains are attempted when dialing a peer, after the ones returned by
+//! `NetworkBehaviour::addresses_of_peer`.
+//!
+//! The `Swarm` periodically removes the addresses that have expired, along with the peers that
+//! have neither an address nor any other information left.
+//!
+//! The content of the store can be persisted with [`PeerStore::snapshot`] and loaded back with
+//! [`PeerStore::restore`].
+
+use crate::peer_store_proto as proto;
+use libp2p_core::{Multiaddr, PeerId, PublicKey, clock, identity::error::DecodingError, multiaddr};
+use protobuf::{Message, ProtobufError};
+use std::{collections::HashMap, convert::TryFrom, error, fmt, time::Duration};
+use wasm_timer::Instant;
+
+/// Where an address of a peer has been learned from.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum AddressSource {
+    /// The address has been added by the user.
+    Manual,
+    /// The peer reported the address through the identify protocol.
+    Identify,
+    /// The address has been found through the Kademlia DHT.
+    Kademlia,
+    /// The address has been discovered on the local network through mDNS.
+    Mdns,
+    /// Any other source.
+    Other,
+}
+
+/// An address of a peer, with its origin and expiration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AddressRecord {
+    /// The address.
+    pub address: Multiaddr,
+    /// Where the address has been learned from, most recently.
+    pub source: AddressSource,
+    /// When the address expires. `None` if it never does.
+    pub expires: Option<Instant>,
+}
+
+impl AddressRecord {
+    fn is_expired(&self, now: Instant) -> bool {
+        self.expires.map_or(false, |e| e <= now)
+    }
+}
+
+/// Information known about a peer.
+#[derive(Debug, Clone, Default)]
+struct PeerInfo {
+    addresses: Vec<AddressRecord>,
+    public_key: Option<PublicKey>,
+    protocols: Vec<String>,
+    agent_version: Option<String>,
+}
+
+impl PeerInfo {
+    /// Returns true if nothing is known about the peer.
+    fn is_empty(&self) -> bool {
+        self.addresses.is_empty()
+            && self.public_key.is_none()
+            && self.protocols.is_empty()
+            && self.agent_version.is_none()
+    }
+}
+
+/// Address book of the peers known to the local node.
+#[derive(Debug, Clone, Default)]
+pub struct PeerStore {
+    peers: HashMap<PeerId, PeerInfo>,
+}
+
+impl PeerStore {
+    /// Creates an empty `PeerStore`.
+    pub fn new() -> Self {
+        PeerStore::default()
+    }
+
+    /// Returns the peers present in the store.
+    pub fn peers(&self) -> impl Iterator<Item = &PeerId> {
+        self.peers.keys()
+    }
+
+    /// Removes everything that is known about a peer.
+    pub fn remove_peer(&mut self, peer_id: &PeerId) {
+        self.peers.remove(peer_id);
+    }
+
+    /// Adds an address for a peer, valid for `ttl` or forever if `None` or too large to be
+    /// represented.
+    ///
+    /// If the address is already known, its source is updated and it is kept for the longest of
+    /// the two durations.
+    pub fn add_address(&mut self, peer_id: &PeerId, address: Multiaddr, source: AddressSource, ttl: Option<Duration>) {
+        let now = clock::now();
+        let expires = ttl.and_then(|ttl| now.checked_add(ttl));
+        let addresses = &mut self.peers.entry(peer_id.clone()).or_default().addresses;
+        addresses.retain(|r| !r.is_expired(now));
+
+        if let Some(record) = addresses.iter_mut().find(|r| r.address == address) {
+            record.source = source;
+            record.expires = match (record.expires, expires) {
+                (Some(a), Some(b)) => Some(a.max(b)),
+                _ => None,
+            };
+        } else {
+            addresses.push(AddressRecord { address, source, expires });
+        }
+    }
+
+    /// Removes an address of a peer. Returns `true` if the address was known.
+    pub fn remove_address(&mut self, peer_id: &PeerId, address: &Multiaddr) -> bool {
+        if let Some(info) = self.peers.get_mut(peer_id) {
+            let len = info.addresses.len();
+            info.addresses.retain(|r| r.address != *address);
+            len != info.addresses.len()
+        } else {
+            false
+        }
+    }
+
+    /// Returns the addresses of a peer that haven't expired.
+    pub fn addresses<'a>(&'a self, peer_id: &PeerId) -> impl Iterator<Item = &'a Multiaddr> + 'a {
+        self.address_records(peer_id).map(|r| &r.address)
+    }
+
+    /// Returns the records of the addresses of a peer that haven't expired.
+    pub fn address_records<'a>(&'a self, peer_id: &PeerId) -> impl Iterator<Item = &'a AddressRecord> + 'a {
+        let now = clock::now();
+        self.peers.get(peer_id)
+            .into_iter()
+            .flat_map(|info| info.addresses.iter())
+            .filter(move |r| !r.is_expired(now))
+    }
+
+    /// Removes all the addresses that have expired, then the peers about which nothing is left.
+    ///
+    /// The public key, protocols and agent version of a peer do not expire, thus a peer for
+    /// which one of them is known is kept until it is removed with [`PeerStore::remove_peer`],
+    /// even without addresses.
+    pub fn remove_expired(&mut self) {
+        let now = clock::now();
+        self.peers.retain(|_, info| {
+            info.addresses.retain(|r| !r.is_expired(now));
+            !info.is_empty()
+        });
+    }
+
+    /// Records the public key of a peer.
+    pub fn set_public_key(&mut self, peer_id: &PeerId, public_key: PublicKey) {
+        self.peers.entry(peer_id.clone()).or_default().public_key = Some(public_key);
+    }
+
+    /// Returns the public key of a peer, if known.
+    pub fn public_key(&self, peer_id: &PeerId) -> Option<&PublicKey> {
+        self.peers.get(peer_id).and_then(|info| info.public_key.as_ref())
+    }
+
+    /// Records the protocols supported by a peer, replacing the ones previously known.
+    pub fn set_protocols(&mut self, peer_id: &PeerId, protocols: impl IntoIterator<Item = String>) {
+        self.peers.entry(peer_id.clone()).or_default().protocols = protocols.into_iter().collect();
+    }
+
+    /// Returns the protocols supported by a peer.
+    pub fn protocols<'a>(&'a self, peer_id: &PeerId) -> impl Iterator<Item = &'a str> + 'a {
+        self.peers.get(peer_id)
+            .into_iter()
+            .flat_map(|info| info.protocols.iter())
+            .map(|p| p.as_str())
+    }
+
+    /// Records the agent version of a peer.
+    pub fn set_agent_version(&mut self, peer_id: &PeerId, agent_version: String) {
+        self.peers.entry(peer_id.clone()).or_default().agent_version = Some(agent_version);
+    }
+
+    /// Returns the agent version of a peer, if known.
+    pub fn agent_version(&self, peer_id: &PeerId) -> Option<&str> {
+        self.peers.get(peer_id).and_then(|info| info.agent_version.as_ref().map(|a| a.as_str()))
+    }
+
+    /// Serializes the content of the store.
+    ///
+    /// Expired addresses are skipped, and the expiration of the other ones is stored as the
+    /// remaining time to live, so that it is relative to the moment the snapshot is restored.
+    pub fn snapshot(&self) -> Vec<u8> {
+        let now = clock::now();
+        let mut msg = proto::PeerStore::new();
+        for (peer_id, info) in &self.peers {
+            let mut peer = proto::Peer::new();
+            peer.set_peer_id(peer_id.as_bytes().to_vec());
+            for record in info.addresses.iter().filter(|r| !r.is_expired(now)) {
+                let mut address = proto::Address::new();
+                address.set_multiaddr(record.address.to_vec());
+                address.set_source(match record.source {
+                    AddressSource::Manual => proto::Source::MANUAL,
+                    AddressSource::Identify => proto::Source::IDENTIFY,
+                    AddressSource::Kademlia => proto::Source::KADEMLIA,
+                    AddressSource::Mdns => proto::Source::MDNS,
+                    AddressSource::Other => proto::Source::OTHER,
+                });
+                if let Some(expires) = record.expires {
+                    address.set_ttl_secs((expires - now).as_secs());
+                }
+                peer.mut_addresses().push(address);
+            }
+            if let Some(public_key) = &info.public_key {
+                peer.set_public_key(public_key.clone().into_protobuf_encoding());
+            }
+            peer.set_protocols(info.protocols.clone().into());
+            if let Some(agent_version) = &info.agent_version {
+                peer.set_agent_version(agent_version.clone());
+            }
+            msg.mut_peers().push(peer);
+        }
+
+        msg.write_to_bytes().expect("there is no situation in which the protobuf message can be invalid")
+    }
+
+    /// Builds a store from the output of [`PeerStore::snapshot`].
+    pub fn restore(bytes: &[u8]) -> Result<PeerStore, SnapshotError> {
+        let now = clock::now();
+        let mut msg = protobuf::parse_from_bytes::<proto::PeerStore>(bytes)?;
+        let mut peers = HashMap::new();
+        for mut peer in msg.take_peers().into_iter() {
+            let peer_id = PeerId::from_bytes(peer.take_peer_id())
+                .map_err(|_| SnapshotError::InvalidPeerId)?;
+
+            let mut addresses = Vec::with_capacity(peer.get_addresses().len());
+            for mut address in peer.take_addresses().into_iter() {
+                let source = match address.get_source() {
+                    proto::Source::MANUAL => AddressSource::Manual,
+                    proto::Source::IDENTIFY => AddressSource::Identify,
+                    proto::Source::KADEMLIA => AddressSource::Kademlia,
+                    proto::Source::MDNS => AddressSource::Mdns,
+                    proto::Source::OTHER => AddressSource::Other,
+                };
+                let expires = if address.has_ttl_secs() {
+                    let ttl = Duration::from_secs(address.get_ttl_secs());
+                    Some(now.checked_add(ttl).ok_or(SnapshotError::InvalidTtl)?)
+                } else {
+                    None
+                };
+                let address = Multiaddr::try_from(address.take_multiaddr())?;
+                addresses.push(AddressRecord { address, source, expires });
+            }
+
+            let public_key = if peer.has_public_key() {
+                Some(PublicKey::from_protobuf_encoding(peer.get_public_key())?)
+            } else {
+                None
+            };
+
+            let agent_version = if peer.has_agent_version() {
+                Some(peer.take_agent_version())
+            } else {
+                None
+            };
+
+            peers.insert(peer_id, PeerInfo {
+                addresses,
+                public_key,
+                protocols: peer.take_protocols().into_vec(),
+                agent_version,
+            });
+        }
+
+        Ok(PeerStore { peers })
+    }
+}
+
+/// Error while restoring a [`PeerStore`] from a snapshot.
+#[derive(Debug)]
+pub enum SnapshotError {
+    /// The snapshot is not a valid protobuf message.
+    Decode(ProtobufError),
+    /// The snapshot contains an invalid peer ID.
+    InvalidPeerId,
+    /// The snapshot contains an invalid address.
+    InvalidAddress(multiaddr::Error),
+    /// The snapshot contains an invalid public key.
+    InvalidPublicKey(DecodingError),
+    /// The snapshot contains a time to live that is too large to be represented.
+    InvalidTtl,
+    #[doc(hidden)]
+    __Nonexhaustive
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::Decode(e) => write!(f, "Failed to decode snapshot: {}", e),
+            SnapshotError::InvalidPeerId => f.write_str("Invalid peer ID in snapshot"),
+            SnapshotError::InvalidAddress(e) => write!(f, "Invalid address in snapshot: {}", e),
+            SnapshotError::InvalidPublicKey(e) => write!(f, "Invalid public key in snapshot: {}", e),
+            SnapshotError::InvalidTtl => f.write_str("Invalid time to live in snapshot"),
+            SnapshotError::__Nonexhaustive => f.write_str("__Nonexhaustive")
+        }
+    }
+}
+
+impl error::Error for SnapshotError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            SnapshotError::Decode(e) => Some(e),
+            SnapshotError::InvalidPeerId => None,
+            SnapshotError::InvalidAddress(e) => Some(e),
+            SnapshotError::InvalidPublicKey(e) => Some(e),
+            SnapshotError::InvalidTtl => None,
+            SnapshotError::__Nonexhaustive => None
+        }
+    }
+}
+
+impl From<ProtobufError> for SnapshotError {
+    fn from(e: ProtobufError) -> Self {
+        SnapshotError::Decode(e)
+    }
+}
+
+impl From<multiaddr::Error> for SnapshotError {
+    fn from(e: multiaddr::Error) -> Self {
+        SnapshotError::InvalidAddress(e)
+    }
+}
+
+impl From<DecodingError> for SnapshotError {
+    fn from(e: DecodingError) -> Self {
+        SnapshotError::InvalidPublicKey(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libp2p_core::identity;
+    use std::collections::HashSet;
+
+    #[test]
+    fn expired_addresses_are_ignored() {
+        let peer_id = PeerId::random();
+        let mut store = PeerStore::new();
+        let a: Multiaddr = "/ip4/127.0.0.1/tcp/1".parse().unwrap();
+        let b: Multiaddr = "/ip4/127.0.0.1/tcp/2".parse().unwrap();
+        store.add_address(&peer_id, a.clone(), AddressSource::Manual, None);
+        store.add_address(&peer_id, b, AddressSource::Mdns, Some(Duration::from_secs(0)));
+        assert_eq!(store.addresses(&peer_id).collect::<Vec<_>>(), vec![&a]);
+    }
+
+    #[test]
+    fn re_adding_an_address_keeps_longest_ttl() {
+        let peer_id = PeerId::random();
+        let mut store = PeerStore::new();
+        let a: Multiaddr = "/ip4/127.0.0.1/tcp/1".parse().unwrap();
+        store.add_address(&peer_id, a.clone(), AddressSource::Kademlia, None);
+        store.add_address(&peer_id, a.clone(), AddressSource::Identify, Some(Duration::from_secs(10)));
+        let records = store.address_records(&peer_id).collect::<Vec<_>>();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].source, AddressSource::Identify);
+        assert_eq!(records[0].expires, None);
+        assert!(store.remove_address(&peer_id, &a));
+        assert_eq!(store.addresses(&peer_id).count(), 0);
+    }
+
+    #[test]
+    fn snapshot_round_trip() {
+        let key = identity::Keypair::generate_ed25519().public();
+        let peer_id = key.clone().into_peer_id();
+        let a: Multiaddr = "/ip4/127.0.0.1/tcp/1".parse().unwrap();
+        let b: Multiaddr = "/dns4/example.com/tcp/2".parse().unwrap();
+
+        let mut store = PeerStore::new();
+        store.add_address(&peer_id, a.clone(), AddressSource::Identify, Some(Duration::from_secs(3600)));
+        store.add_address(&peer_id, b.clone(), AddressSource::Manual, None);
+        store.set_public_key(&peer_id, key.clone());
+        store.set_protocols(&peer_id, vec!["/ipfs/id/1.0.0".to_owned()]);
+        store.set_agent_version(&peer_id, "rust-libp2p".to_owned());
+
+        let restored = PeerStore::restore(&store.snapshot()).unwrap();
+        let records = restored.address_records(&peer_id).collect::<Vec<_>>();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].address, a);
+        assert_eq!(records[0].source, AddressSource::Identify);
+        assert!(records[0].expires.is_some());
+        assert_eq!(records[1].address, b);
+        assert_eq!(records[1].expires, None);
+        assert_eq!(restored.public_key(&peer_id), Some(&key));
+        assert_eq!(restored.protocols(&peer_id).collect::<Vec<_>>(), vec!["/ipfs/id/1.0.0"]);
+        assert_eq!(restored.agent_version(&peer_id), Some("rust-libp2p"));
+
+        assert!(PeerStore::restore(&[0xff]).is_err());
+    }
+
+    #[test]
+    fn restore_refuses_overflowing_ttl() {
+        let mut address = proto::Address::new();
+        address.set_multiaddr("/ip4/127.0.0.1/tcp/1".parse::<Multiaddr>().unwrap().to_vec());
+        address.set_source(proto::Source::MANUAL);
+        address.set_ttl_secs(u64::max_value());
+        let mut peer = proto::Peer::new();
+        peer.set_peer_id(PeerId::random().into_bytes());
+        peer.mut_addresses().push(address);
+        let mut msg = proto::PeerStore::new();
+        msg.mut_peers().push(peer);
+
+        match PeerStore::restore(&msg.write_to_bytes().unwrap()) {
+            Err(SnapshotError::InvalidTtl) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn remove_expired_drops_peers_without_information() {
+        let (a, b, c) = (PeerId::random(), PeerId::random(), PeerId::random());
+        let mut store = PeerStore::new();
+        let addr: Multiaddr = "/ip4/127.0.0.1/tcp/1".parse().unwrap();
+        store.add_address(&a, addr.clone(), AddressSource::Manual, Some(Duration::from_secs(u64::max_value())));
+        store.add_address(&b, addr.clone(), AddressSource::Mdns, Some(Duration::from_secs(0)));
+        store.add_address(&c, addr, AddressSource::Mdns, Some(Duration::from_secs(0)));
+        store.set_agent_version(&c, "rust-libp2p".to_owned());
+        store.remove_expired();
+
+        let peers = store.peers().collect::<HashSet<_>>();
+        assert_eq!(peers, vec![&a, &c].into_iter().collect());
+        assert_eq!(store.address_records(&a).next().unwrap().expires, None);
+
+        // The identify metadata of a peer outlives its addresses.
+        assert_eq!(store.addresses(&c).count(), 0);
+        assert_eq!(store.agent_version(&c), Some("rust-libp2p"));
+        store.remove_peer(&c);
+        assert_eq!(store.agent_version(&c), None);
+    }
+}
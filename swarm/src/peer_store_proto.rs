@@ -0,0 +1,911 @@
+// This file is generated by rust-protobuf 2.3.0. Do not edit
+// @generated
+
+// https://github.com/Manishearth/rust-clippy/issues/702
+#![allow(unknown_lints)]
+#![allow(clippy::all)]
+
+#![cfg_attr(rustfmt, rustfmt_skip)]
+
+#![allow(box_pointers)]
+#![allow(dead_code)]
+#![allow(missing_docs)]
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+#![allow(non_upper_case_globals)]
+#![allow(trivial_casts)]
+#![allow(unsafe_code)]
+#![allow(unused_imports)]
+#![allow(unused_results)]
+
+use protobuf::Message as Message_imported_for_functions;
+use protobuf::ProtobufEnum as ProtobufEnum_imported_for_functions;
+
+#[derive(PartialEq,Clone,Default)]
+pub struct PeerStore {
+    // message fields
+    peers: ::protobuf::RepeatedField<Peer>,
+    // special fields
+    pub unknown_fields: ::protobuf::UnknownFields,
+    pub cached_size: ::protobuf::CachedSize,
+}
+
+impl PeerStore {
+    pub fn new() -> PeerStore {
+        ::std::default::Default::default()
+    }
+
+    // repeated .peer_store.Peer peers = 1;
+
+    pub fn clear_peers(&mut self) {
+        self.peers.clear();
+    }
+
+    // Param is passed by value, moved
+    pub fn set_peers(&mut self, v: ::protobuf::RepeatedField<Peer>) {
+        self.peers = v;
+    }
+
+    // Mutable pointer to the field.
+    pub fn mut_peers(&mut self) -> &mut ::protobuf::RepeatedField<Peer> {
+        &mut self.peers
+    }
+
+    // Take field
+    pub fn take_peers(&mut self) -> ::protobuf::RepeatedField<Peer> {
+        ::std::mem::replace(&mut self.peers, ::protobuf::RepeatedField::new())
+    }
+
+    pub fn get_peers(&self) -> &[Peer] {
+        &self.peers
+    }
+}
+
+impl ::protobuf::Message for PeerStore {
+    fn is_initialized(&self) -> bool {
+        for v in &self.peers {
+            if !v.is_initialized() {
+                return false;
+            }
+        };
+        true
+    }
+
+    fn merge_from(&mut self, is: &mut ::protobuf::CodedInputStream<'_>) -> ::protobuf::ProtobufResult<()> {
+        while !is.eof()? {
+            let (field_number, wire_type) = is.read_tag_unpack()?;
+            match field_number {
+                1 => {
+                    ::protobuf::rt::read_repeated_message_into(wire_type, is, &mut self.peers)?;
+                },
+                _ => {
+                    ::protobuf::rt::read_unknown_or_skip_group(field_number, wire_type, is, self.mut_unknown_fields())?;
+                },
+            };
+        }
+        ::std::result::Result::Ok(())
+    }
+
+    // Compute sizes of nested messages
+    #[allow(unused_variables)]
+    fn compute_size(&self) -> u32 {
+        let mut my_size = 0;
+        for value in &self.peers {
+            let len = value.compute_size();
+            my_size += 1 + ::protobuf::rt::compute_raw_varint32_size(len) + len;
+        };
+        my_size += ::protobuf::rt::unknown_fields_size(self.get_unknown_fields());
+        self.cached_size.set(my_size);
+        my_size
+    }
+
+    fn write_to_with_cached_sizes(&self, os: &mut ::protobuf::CodedOutputStream<'_>) -> ::protobuf::ProtobufResult<()> {
+        for v in &self.peers {
+            os.write_tag(1, ::protobuf::wire_format::WireTypeLengthDelimited)?;
+            os.write_raw_varint32(v.get_cached_size())?;
+            v.write_to_with_cached_sizes(os)?;
+        };
+        os.write_unknown_fields(self.get_unknown_fields())?;
+        ::std::result::Result::Ok(())
+    }
+
+    fn get_cached_size(&self) -> u32 {
+        self.cached_size.get()
+    }
+
+    fn get_unknown_fields(&self) -> &::protobuf::UnknownFields {
+        &self.unknown_fields
+    }
+
+    fn mut_unknown_fields(&mut self) -> &mut ::protobuf::UnknownFields {
+        &mut self.unknown_fields
+    }
+
+    fn as_any(&self) -> &dyn (::std::any::Any) {
+        self as &dyn (::std::any::Any)
+    }
+    fn as_any_mut(&mut self) -> &mut dyn (::std::any::Any) {
+        self as &mut dyn (::std::any::Any)
+    }
+    fn into_any(self: Box<Self>) -> ::std::boxed::Box<dyn (::std::any::Any)> {
+        self
+    }
+
+    fn descriptor(&self) -> &'static ::protobuf::reflect::MessageDescriptor {
+        Self::descriptor_static()
+    }
+
+    fn new() -> PeerStore {
+        PeerStore::new()
+    }
+
+    fn descriptor_static() -> &'static ::protobuf::reflect::MessageDescriptor {
+        static mut descriptor: ::protobuf::lazy::Lazy<::protobuf::reflect::MessageDescriptor> = ::protobuf::lazy::Lazy {
+            lock: ::protobuf::lazy::ONCE_INIT,
+            ptr: 0 as *const ::protobuf::reflect::MessageDescriptor,
+        };
+        unsafe {
+            descriptor.get(|| {
+                let mut fields = ::std::vec::Vec::new();
+                fields.push(::protobuf::reflect::accessor::make_repeated_field_accessor::<_, ::protobuf::types::ProtobufTypeMessage<Peer>>(
+                    "peers",
+                    |m: &PeerStore| { &m.peers },
+                    |m: &mut PeerStore| { &mut m.peers },
+                ));
+                ::protobuf::reflect::MessageDescriptor::new::<PeerStore>(
+                    "PeerStore",
+                    fields,
+                    file_descriptor_proto()
+                )
+            })
+        }
+    }
+
+    fn default_instance() -> &'static PeerStore {
+        static mut instance: ::protobuf::lazy::Lazy<PeerStore> = ::protobuf::lazy::Lazy {
+            lock: ::protobuf::lazy::ONCE_INIT,
+            ptr: 0 as *const PeerStore,
+        };
+        unsafe {
+            instance.get(PeerStore::new)
+        }
+    }
+}
+
+impl ::protobuf::Clear for PeerStore {
+    fn clear(&mut self) {
+        self.clear_peers();
+        self.unknown_fields.clear();
+    }
+}
+
+impl ::std::fmt::Debug for PeerStore {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+        ::protobuf::text_format::fmt(self, f)
+    }
+}
+
+impl ::protobuf::reflect::ProtobufValue for PeerStore {
+    fn as_ref(&self) -> ::protobuf::reflect::ProtobufValueRef<'_> {
+        ::protobuf::reflect::ProtobufValueRef::Message(self)
+    }
+}
+
+#[derive(PartialEq,Clone,Default)]
+pub struct Peer {
+    // message fields
+    peer_id: ::protobuf::SingularField<::std::vec::Vec<u8>>,
+    addresses: ::protobuf::RepeatedField<Address>,
+    public_key: ::protobuf::SingularField<::std::vec::Vec<u8>>,
+    protocols: ::protobuf::RepeatedField<::std::string::String>,
+    agent_version: ::protobuf::SingularField<::std::string::String>,
+    // special fields
+    pub unknown_fields: ::protobuf::UnknownFields,
+    pub cached_size: ::protobuf::CachedSize,
+}
+
+impl Peer {
+    pub fn new() -> Peer {
+        ::std::default::Default::default()
+    }
+
+    // required bytes peer_id = 1;
+
+    pub fn clear_peer_id(&mut self) {
+        self.peer_id.clear();
+    }
+
+    pub fn has_peer_id(&self) -> bool {
+        self.peer_id.is_some()
+    }
+
+    // Param is passed by value, moved
+    pub fn set_peer_id(&mut self, v: ::std::vec::Vec<u8>) {
+        self.peer_id = ::protobuf::SingularField::some(v);
+    }
+
+    // Mutable pointer to the field.
+    // If field is not initialized, it is initialized with default value first.
+    pub fn mut_peer_id(&mut self) -> &mut ::std::vec::Vec<u8> {
+        if self.peer_id.is_none() {
+            self.peer_id.set_default();
+        }
+        self.peer_id.as_mut().unwrap()
+    }
+
+    // Take field
+    pub fn take_peer_id(&mut self) -> ::std::vec::Vec<u8> {
+        self.peer_id.take().unwrap_or_else(|| ::std::vec::Vec::new())
+    }
+
+    pub fn get_peer_id(&self) -> &[u8] {
+        match self.peer_id.as_ref() {
+            Some(v) => &v,
+            None => &[],
+        }
+    }
+
+    // repeated .peer_store.Address addresses = 2;
+
+    pub fn clear_addresses(&mut self) {
+        self.addresses.clear();
+    }
+
+    // Param is passed by value, moved
+    pub fn set_addresses(&mut self, v: ::protobuf::RepeatedField<Address>) {
+        self.addresses = v;
+    }
+
+    // Mutable pointer to the field.
+    pub fn mut_addresses(&mut self) -> &mut ::protobuf::RepeatedField<Address> {
+        &mut self.addresses
+    }
+
+    // Take field
+    pub fn take_addresses(&mut self) -> ::protobuf::RepeatedField<Address> {
+        ::std::mem::replace(&mut self.addresses, ::protobuf::RepeatedField::new())
+    }
+
+    pub fn get_addresses(&self) -> &[Address] {
+        &self.addresses
+    }
+
+    // optional bytes public_key = 3;
+
+    pub fn clear_public_key(&mut self) {
+        self.public_key.clear();
+    }
+
+    pub fn has_public_key(&self) -> bool {
+        self.public_key.is_some()
+    }
+
+    // Param is passed by value, moved
+    pub fn set_public_key(&mut self, v: ::std::vec::Vec<u8>) {
+        self.public_key = ::protobuf::SingularField::some(v);
+    }
+
+    // Mutable pointer to the field.
+    // If field is not initialized, it is initialized with default value first.
+    pub fn mut_public_key(&mut self) -> &mut ::std::vec::Vec<u8> {
+        if self.public_key.is_none() {
+            self.public_key.set_default();
+        }
+        self.public_key.as_mut().unwrap()
+    }
+
+    // Take field
+    pub fn take_public_key(&mut self) -> ::std::vec::Vec<u8> {
+        self.public_key.take().unwrap_or_else(|| ::std::vec::Vec::new())
+    }
+
+    pub fn get_public_key(&self) -> &[u8] {
+        match self.public_key.as_ref() {
+            Some(v) => &v,
+            None => &[],
+        }
+    }
+
+    // repeated string protocols = 4;
+
+    pub fn clear_protocols(&mut self) {
+        self.protocols.clear();
+    }
+
+    // Param is passed by value, moved
+    pub fn set_protocols(&mut self, v: ::protobuf::RepeatedField<::std::string::String>) {
+        self.protocols = v;
+    }
+
+    // Mutable pointer to the field.
+    pub fn mut_protocols(&mut self) -> &mut ::protobuf::RepeatedField<::std::string::String> {
+        &mut self.protocols
+    }
+
+    // Take field
+    pub fn take_protocols(&mut self) -> ::protobuf::RepeatedField<::std::string::String> {
+        ::std::mem::replace(&mut self.protocols, ::protobuf::RepeatedField::new())
+    }
+
+    pub fn get_protocols(&self) -> &[::std::string::String] {
+        &self.protocols
+    }
+
+    // optional string agent_version = 5;
+
+    pub fn clear_agent_version(&mut self) {
+        self.agent_version.clear();
+    }
+
+    pub fn has_agent_version(&self) -> bool {
+        self.agent_version.is_some()
+    }
+
+    // Param is passed by value, moved
+    pub fn set_agent_version(&mut self, v: ::std::string::String) {
+        self.agent_version = ::protobuf::SingularField::some(v);
+    }
+
+    // Mutable pointer to the field.
+    // If field is not initialized, it is initialized with default value first.
+    pub fn mut_agent_version(&mut self) -> &mut ::std::string::String {
+        if self.agent_version.is_none() {
+            self.agent_version.set_default();
+        }
+        self.agent_version.as_mut().unwrap()
+    }
+
+    // Take field
+    pub fn take_agent_version(&mut self) -> ::std::string::String {
+        self.agent_version.take().unwrap_or_else(|| ::std::string::String::new())
+    }
+
+    pub fn get_agent_version(&self) -> &str {
+        match self.agent_version.as_ref() {
+            Some(v) => &v,
+            None => "",
+        }
+    }
+}
+
+impl ::protobuf::Message for Peer {
+    fn is_initialized(&self) -> bool {
+        if self.peer_id.is_none() {
+            return false;
+        }
+        for v in &self.addresses {
+            if !v.is_initialized() {
+                return false;
+            }
+        };
+        true
+    }
+
+    fn merge_from(&mut self, is: &mut ::protobuf::CodedInputStream<'_>) -> ::protobuf::ProtobufResult<()> {
+        while !is.eof()? {
+            let (field_number, wire_type) = is.read_tag_unpack()?;
+            match field_number {
+                1 => {
+                    ::protobuf::rt::read_singular_bytes_into(wire_type, is, &mut self.peer_id)?;
+                },
+                2 => {
+                    ::protobuf::rt::read_repeated_message_into(wire_type, is, &mut self.addresses)?;
+                },
+                3 => {
+                    ::protobuf::rt::read_singular_bytes_into(wire_type, is, &mut self.public_key)?;
+                },
+                4 => {
+                    ::protobuf::rt::read_repeated_string_into(wire_type, is, &mut self.protocols)?;
+                },
+                5 => {
+                    ::protobuf::rt::read_singular_string_into(wire_type, is, &mut self.agent_version)?;
+                },
+                _ => {
+                    ::protobuf::rt::read_unknown_or_skip_group(field_number, wire_type, is, self.mut_unknown_fields())?;
+                },
+            };
+        }
+        ::std::result::Result::Ok(())
+    }
+
+    // Compute sizes of nested messages
+    #[allow(unused_variables)]
+    fn compute_size(&self) -> u32 {
+        let mut my_size = 0;
+        if let Some(ref v) = self.peer_id.as_ref() {
+            my_size += ::protobuf::rt::bytes_size(1, &v);
+        }
+        for value in &self.addresses {
+            let len = value.compute_size();
+            my_size += 1 + ::protobuf::rt::compute_raw_varint32_size(len) + len;
+        };
+        if let Some(ref v) = self.public_key.as_ref() {
+            my_size += ::protobuf::rt::bytes_size(3, &v);
+        }
+        for value in &self.protocols {
+            my_size += ::protobuf::rt::string_size(4, &value);
+        };
+        if let Some(ref v) = self.agent_version.as_ref() {
+            my_size += ::protobuf::rt::string_size(5, &v);
+        }
+        my_size += ::protobuf::rt::unknown_fields_size(self.get_unknown_fields());
+        self.cached_size.set(my_size);
+        my_size
+    }
+
+    fn write_to_with_cached_sizes(&self, os: &mut ::protobuf::CodedOutputStream<'_>) -> ::protobuf::ProtobufResult<()> {
+        if let Some(ref v) = self.peer_id.as_ref() {
+            os.write_bytes(1, &v)?;
+        }
+        for v in &self.addresses {
+            os.write_tag(2, ::protobuf::wire_format::WireTypeLengthDelimited)?;
+            os.write_raw_varint32(v.get_cached_size())?;
+            v.write_to_with_cached_sizes(os)?;
+        };
+        if let Some(ref v) = self.public_key.as_ref() {
+            os.write_bytes(3, &v)?;
+        }
+        for v in &self.protocols {
+            os.write_string(4, &v)?;
+        };
+        if let Some(ref v) = self.agent_version.as_ref() {
+            os.write_string(5, &v)?;
+        }
+        os.write_unknown_fields(self.get_unknown_fields())?;
+        ::std::result::Result::Ok(())
+    }
+
+    fn get_cached_size(&self) -> u32 {
+        self.cached_size.get()
+    }
+
+    fn get_unknown_fields(&self) -> &::protobuf::UnknownFields {
+        &self.unknown_fields
+    }
+
+    fn mut_unknown_fields(&mut self) -> &mut ::protobuf::UnknownFields {
+        &mut self.unknown_fields
+    }
+
+    fn as_any(&self) -> &dyn (::std::any::Any) {
+        self as &dyn (::std::any::Any)
+    }
+    fn as_any_mut(&mut self) -> &mut dyn (::std::any::Any) {
+        self as &mut dyn (::std::any::Any)
+    }
+    fn into_any(self: Box<Self>) -> ::std::boxed::Box<dyn (::std::any::Any)> {
+        self
+    }
+
+    fn descriptor(&self) -> &'static ::protobuf::reflect::MessageDescriptor {
+        Self::descriptor_static()
+    }
+
+    fn new() -> Peer {
+        Peer::new()
+    }
+
+    fn descriptor_static() -> &'static ::protobuf::reflect::MessageDescriptor {
+        static mut descriptor: ::protobuf::lazy::Lazy<::protobuf::reflect::MessageDescriptor> = ::protobuf::lazy::Lazy {
+            lock: ::protobuf::lazy::ONCE_INIT,
+            ptr: 0 as *const ::protobuf::reflect::MessageDescriptor,
+        };
+        unsafe {
+            descriptor.get(|| {
+                let mut fields = ::std::vec::Vec::new();
+                fields.push(::protobuf::reflect::accessor::make_singular_field_accessor::<_, ::protobuf::types::ProtobufTypeBytes>(
+                    "peer_id",
+                    |m: &Peer| { &m.peer_id },
+                    |m: &mut Peer| { &mut m.peer_id },
+                ));
+                fields.push(::protobuf::reflect::accessor::make_repeated_field_accessor::<_, ::protobuf::types::ProtobufTypeMessage<Address>>(
+                    "addresses",
+                    |m: &Peer| { &m.addresses },
+                    |m: &mut Peer| { &mut m.addresses },
+                ));
+                fields.push(::protobuf::reflect::accessor::make_singular_field_accessor::<_, ::protobuf::types::ProtobufTypeBytes>(
+                    "public_key",
+                    |m: &Peer| { &m.public_key },
+                    |m: &mut Peer| { &mut m.public_key },
+                ));
+                fields.push(::protobuf::reflect::accessor::make_repeated_field_accessor::<_, ::protobuf::types::ProtobufTypeString>(
+                    "protocols",
+                    |m: &Peer| { &m.protocols },
+                    |m: &mut Peer| { &mut m.protocols },
+                ));
+                fields.push(::protobuf::reflect::accessor::make_singular_field_accessor::<_, ::protobuf::types::ProtobufTypeString>(
+                    "agent_version",
+                    |m: &Peer| { &m.agent_version },
+                    |m: &mut Peer| { &mut m.agent_version },
+                ));
+                ::protobuf::reflect::MessageDescriptor::new::<Peer>(
+                    "Peer",
+                    fields,
+                    file_descriptor_proto()
+                )
+            })
+        }
+    }
+
+    fn default_instance() -> &'static Peer {
+        static mut instance: ::protobuf::lazy::Lazy<Peer> = ::protobuf::lazy::Lazy {
+            lock: ::protobuf::lazy::ONCE_INIT,
+            ptr: 0 as *const Peer,
+        };
+        unsafe {
+            instance.get(Peer::new)
+        }
+    }
+}
+
+impl ::protobuf::Clear for Peer {
+    fn clear(&mut self) {
+        self.clear_peer_id();
+        self.clear_addresses();
+        self.clear_public_key();
+        self.clear_protocols();
+        self.clear_agent_version();
+        self.unknown_fields.clear();
+    }
+}
+
+impl ::std::fmt::Debug for Peer {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+        ::protobuf::text_format::fmt(self, f)
+    }
+}
+
+impl ::protobuf::reflect::ProtobufValue for Peer {
+    fn as_ref(&self) -> ::protobuf::reflect::ProtobufValueRef<'_> {
+        ::protobuf::reflect::ProtobufValueRef::Message(self)
+    }
+}
+
+#[derive(PartialEq,Clone,Default)]
+pub struct Address {
+    // message fields
+    multiaddr: ::protobuf::SingularField<::std::vec::Vec<u8>>,
+    source: ::std::option::Option<Source>,
+    ttl_secs: ::std::option::Option<u64>,
+    // special fields
+    pub unknown_fields: ::protobuf::UnknownFields,
+    pub cached_size: ::protobuf::CachedSize,
+}
+
+impl Address {
+    pub fn new() -> Address {
+        ::std::default::Default::default()
+    }
+
+    // required bytes multiaddr = 1;
+
+    pub fn clear_multiaddr(&mut self) {
+        self.multiaddr.clear();
+    }
+
+    pub fn has_multiaddr(&self) -> bool {
+        self.multiaddr.is_some()
+    }
+
+    // Param is passed by value, moved
+    pub fn set_multiaddr(&mut self, v: ::std::vec::Vec<u8>) {
+        self.multiaddr = ::protobuf::SingularField::some(v);
+    }
+
+    // Mutable pointer to the field.
+    // If field is not initialized, it is initialized with default value first.
+    pub fn mut_multiaddr(&mut self) -> &mut ::std::vec::Vec<u8> {
+        if self.multiaddr.is_none() {
+            self.multiaddr.set_default();
+        }
+        self.multiaddr.as_mut().unwrap()
+    }
+
+    // Take field
+    pub fn take_multiaddr(&mut self) -> ::std::vec::Vec<u8> {
+        self.multiaddr.take().unwrap_or_else(|| ::std::vec::Vec::new())
+    }
+
+    pub fn get_multiaddr(&self) -> &[u8] {
+        match self.multiaddr.as_ref() {
+            Some(v) => &v,
+            None => &[],
+        }
+    }
+
+    // required .peer_store.Source source = 2;
+
+    pub fn clear_source(&mut self) {
+        self.source = ::std::option::Option::None;
+    }
+
+    pub fn has_source(&self) -> bool {
+        self.source.is_some()
+    }
+
+    // Param is passed by value, moved
+    pub fn set_source(&mut self, v: Source) {
+        self.source = ::std::option::Option::Some(v);
+    }
+
+    pub fn get_source(&self) -> Source {
+        self.source.unwrap_or(Source::MANUAL)
+    }
+
+    // optional uint64 ttl_secs = 3;
+
+    pub fn clear_ttl_secs(&mut self) {
+        self.ttl_secs = ::std::option::Option::None;
+    }
+
+    pub fn has_ttl_secs(&self) -> bool {
+        self.ttl_secs.is_some()
+    }
+
+    // Param is passed by value, moved
+    pub fn set_ttl_secs(&mut self, v: u64) {
+        self.ttl_secs = ::std::option::Option::Some(v);
+    }
+
+    pub fn get_ttl_secs(&self) -> u64 {
+        self.ttl_secs.unwrap_or(0)
+    }
+}
+
+impl ::protobuf::Message for Address {
+    fn is_initialized(&self) -> bool {
+        if self.multiaddr.is_none() {
+            return false;
+        }
+        if self.source.is_none() {
+            return false;
+        }
+        true
+    }
+
+    fn merge_from(&mut self, is: &mut ::protobuf::CodedInputStream<'_>) -> ::protobuf::ProtobufResult<()> {
+        while !is.eof()? {
+            let (field_number, wire_type) = is.read_tag_unpack()?;
+            match field_number {
+                1 => {
+                    ::protobuf::rt::read_singular_bytes_into(wire_type, is, &mut self.multiaddr)?;
+                },
+                2 => {
+                    ::protobuf::rt::read_proto2_enum_with_unknown_fields_into(wire_type, is, &mut self.source, 2, &mut self.unknown_fields)?
+                },
+                3 => {
+                    if wire_type != ::protobuf::wire_format::WireTypeVarint {
+                        return ::std::result::Result::Err(::protobuf::rt::unexpected_wire_type(wire_type));
+                    }
+                    let tmp = is.read_uint64()?;
+                    self.ttl_secs = ::std::option::Option::Some(tmp);
+                },
+                _ => {
+                    ::protobuf::rt::read_unknown_or_skip_group(field_number, wire_type, is, self.mut_unknown_fields())?;
+                },
+            };
+        }
+        ::std::result::Result::Ok(())
+    }
+
+    // Compute sizes of nested messages
+    #[allow(unused_variables)]
+    fn compute_size(&self) -> u32 {
+        let mut my_size = 0;
+        if let Some(ref v) = self.multiaddr.as_ref() {
+            my_size += ::protobuf::rt::bytes_size(1, &v);
+        }
+        if let Some(v) = self.source {
+            my_size += ::protobuf::rt::enum_size(2, v);
+        }
+        if let Some(v) = self.ttl_secs {
+            my_size += ::protobuf::rt::value_size(3, v, ::protobuf::wire_format::WireTypeVarint);
+        }
+        my_size += ::protobuf::rt::unknown_fields_size(self.get_unknown_fields());
+        self.cached_size.set(my_size);
+        my_size
+    }
+
+    fn write_to_with_cached_sizes(&self, os: &mut ::protobuf::CodedOutputStream<'_>) -> ::protobuf::ProtobufResult<()> {
+        if let Some(ref v) = self.multiaddr.as_ref() {
+            os.write_bytes(1, &v)?;
+        }
+        if let Some(v) = self.source {
+            os.write_enum(2, v.value())?;
+        }
+        if let Some(v) = self.ttl_secs {
+            os.write_uint64(3, v)?;
+        }
+        os.write_unknown_fields(self.get_unknown_fields())?;
+        ::std::result::Result::Ok(())
+    }
+
+    fn get_cached_size(&self) -> u32 {
+        self.cached_size.get()
+    }
+
+    fn get_unknown_fields(&self) -> &::protobuf::UnknownFields {
+        &self.unknown_fields
+    }
+
+    fn mut_unknown_fields(&mut self) -> &mut ::protobuf::UnknownFields {
+        &mut self.unknown_fields
+    }
+
+    fn as_any(&self) -> &dyn (::std::any::Any) {
+        self as &dyn (::std::any::Any)
+    }
+    fn as_any_mut(&mut self) -> &mut dyn (::std::any::Any) {
+        self as &mut dyn (::std::any::Any)
+    }
+    fn into_any(self: Box<Self>) -> ::std::boxed::Box<dyn (::std::any::Any)> {
+        self
+    }
+
+    fn descriptor(&self) -> &'static ::protobuf::reflect::MessageDescriptor {
+        Self::descriptor_static()
+    }
+
+    fn new() -> Address {
+        Address::new()
+    }
+
+    fn descriptor_static() -> &'static ::protobuf::reflect::MessageDescriptor {
+        static mut descriptor: ::protobuf::lazy::Lazy<::protobuf::reflect::MessageDescriptor> = ::protobuf::lazy::Lazy {
+            lock: ::protobuf::lazy::ONCE_INIT,
+            ptr: 0 as *const ::protobuf::reflect::MessageDescriptor,
+        };
+        unsafe {
+            descriptor.get(|| {
+                let mut fields = ::std::vec::Vec::new();
+                fields.push(::protobuf::reflect::accessor::make_singular_field_accessor::<_, ::protobuf::types::ProtobufTypeBytes>(
+                    "multiaddr",
+                    |m: &Address| { &m.multiaddr },
+                    |m: &mut Address| { &mut m.multiaddr },
+                ));
+                fields.push(::protobuf::reflect::accessor::make_option_accessor::<_, ::protobuf::types::ProtobufTypeEnum<Source>>(
+                    "source",
+                    |m: &Address| { &m.source },
+                    |m: &mut Address| { &mut m.source },
+                ));
+                fields.push(::protobuf::reflect::accessor::make_option_accessor::<_, ::protobuf::types::ProtobufTypeUint64>(
+                    "ttl_secs",
+                    |m: &Address| { &m.ttl_secs },
+                    |m: &mut Address| { &mut m.ttl_secs },
+                ));
+                ::protobuf::reflect::MessageDescriptor::new::<Address>(
+                    "Address",
+                    fields,
+                    file_descriptor_proto()
+                )
+            })
+        }
+    }
+
+    fn default_instance() -> &'static Address {
+        static mut instance: ::protobuf::lazy::Lazy<Address> = ::protobuf::lazy::Lazy {
+            lock: ::protobuf::lazy::ONCE_INIT,
+            ptr: 0 as *const Address,
+        };
+        unsafe {
+            instance.get(Address::new)
+        }
+    }
+}
+
+impl ::protobuf::Clear for Address {
+    fn clear(&mut self) {
+        self.clear_multiaddr();
+        self.clear_source();
+        self.clear_ttl_secs();
+        self.unknown_fields.clear();
+    }
+}
+
+impl ::std::fmt::Debug for Address {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+        ::protobuf::text_format::fmt(self, f)
+    }
+}
+
+impl ::protobuf::reflect::ProtobufValue for Address {
+    fn as_ref(&self) -> ::protobuf::reflect::ProtobufValueRef<'_> {
+        ::protobuf::reflect::ProtobufValueRef::Message(self)
+    }
+}
+
+#[derive(Clone,PartialEq,Eq,Debug,Hash)]
+pub enum Source {
+    MANUAL = 0,
+    IDENTIFY = 1,
+    KADEMLIA = 2,
+    MDNS = 3,
+    OTHER = 4,
+}
+
+impl ::protobuf::ProtobufEnum for Source {
+    fn value(&self) -> i32 {
+        *self as i32
+    }
+
+    fn from_i32(value: i32) -> ::std::option::Option<Source> {
+        match value {
+            0 => ::std::option::Option::Some(Source::MANUAL),
+            1 => ::std::option::Option::Some(Source::IDENTIFY),
+            2 => ::std::option::Option::Some(Source::KADEMLIA),
+            3 => ::std::option::Option::Some(Source::MDNS),
+            4 => ::std::option::Option::Some(Source::OTHER),
+            _ => ::std::option::Option::None
+        }
+    }
+
+    fn values() -> &'static [Self] {
+        static values: &'static [Source] = &[
+            Source::MANUAL,
+            Source::IDENTIFY,
+            Source::KADEMLIA,
+            Source::MDNS,
+            Source::OTHER,
+        ];
+        values
+    }
+
+    fn enum_descriptor_static() -> &'static ::protobuf::reflect::EnumDescriptor {
+        static mut descriptor: ::protobuf::lazy::Lazy<::protobuf::reflect::EnumDescriptor> = ::protobuf::lazy::Lazy {
+            lock: ::protobuf::lazy::ONCE_INIT,
+            ptr: 0 as *const ::protobuf::reflect::EnumDescriptor,
+        };
+        unsafe {
+            descriptor.get(|| {
+                ::protobuf::reflect::EnumDescriptor::new("Source", file_descriptor_proto())
+            })
+        }
+    }
+}
+
+impl ::std::marker::Copy for Source {
+}
+
+impl ::std::default::Default for Source {
+    fn default() -> Self {
+        Source::MANUAL
+    }
+}
+
+impl ::protobuf::reflect::ProtobufValue for Source {
+    fn as_ref(&self) -> ::protobuf::reflect::ProtobufValueRef<'_> {
+        ::protobuf::reflect::ProtobufValueRef::Enum(self.descriptor())
+    }
+}
+
+static file_descriptor_proto_data: &'static [u8] = b"\
+    \n\x10peer_store.proto\x12\npeer_store\"3\n\tPeerStore\x12&\n\x05peers\
+    \x18\x01\x20\x03(\x0b2\x10.peer_store.PeerR\x05peers\"\xb4\x01\n\x04Peer\
+    \x12\x17\n\x07peer_id\x18\x01\x20\x02(\x0cR\x06peerId\x121\n\taddresses\
+    \x18\x02\x20\x03(\x0b2\x13.peer_store.AddressR\taddresses\x12\x1d\n\npub\
+    lic_key\x18\x03\x20\x01(\x0cR\tpublicKey\x12\x1c\n\tprotocols\x18\x04\
+    \x20\x03(\tR\tprotocols\x12#\n\ragent_version\x18\x05\x20\x01(\tR\x0cage\
+    ntVersion\"n\n\x07Address\x12\x1c\n\tmultiaddr\x18\x01\x20\x02(\x0cR\tmu\
+    ltiaddr\x12*\n\x06source\x18\x02\x20\x02(\x0e2\x12.peer_store.SourceR\
+    \x06source\x12\x19\n\x08ttl_secs\x18\x03\x20\x01(\x04R\x07ttlSecs*E\n\
+    \x06Source\x12\n\n\x06MANUAL\x10\0\x12\x0c\n\x08IDENTIFY\x10\x01\x12\x0c\
+    \n\x08KADEMLIA\x10\x02\x12\x08\n\x04MDNS\x10\x03\x12\t\n\x05OTHER\x10\
+    \x04\
+";
+
+static mut file_descriptor_proto_lazy: ::protobuf::lazy::Lazy<::protobuf::descriptor::FileDescriptorProto> = ::protobuf::lazy::Lazy {
+    lock: ::protobuf::lazy::ONCE_INIT,
+    ptr: 0 as *const ::protobuf::descriptor::FileDescriptorProto,
+};
+
+fn parse_descriptor_proto() -> ::protobuf::descriptor::FileDescriptorProto {
+    ::protobuf::parse_from_bytes(file_descriptor_proto_data).unwrap()
+}
+
+pub fn file_descriptor_proto() -> &'static ::protobuf::descriptor::FileDescriptorProto {
+    unsafe {
+        file_descriptor_proto_lazy.get(|| {
+            parse_descriptor_proto()
+        })
+    }
+}
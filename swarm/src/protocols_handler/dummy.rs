@@ -25,7 +25,7 @@ use crate::protocols_handler::{
     ProtocolsHandlerEvent,
     ProtocolsHandlerUpgrErr
 };
-use futures::prelude::*;
+use futures03::task::{Context, Poll};
 use libp2p_core::upgrade::{InboundUpgrade, OutboundUpgrade, DeniedUpgrade};
 use std::marker::PhantomData;
 use tokio_io::{AsyncRead, AsyncWrite};
@@ -89,10 +89,10 @@ where
     #[inline]
     fn poll(
         &mut self,
+        _: &mut Context<'_>,
     ) -> Poll<
-        ProtocolsHandlerEvent<Self::OutboundProtocol, Self::OutboundOpenInfo, Self::OutEvent>,
-        Void,
+        Result<ProtocolsHandlerEvent<Self::OutboundProtocol, Self::OutboundOpenInfo, Self::OutEvent>, Void>,
     > {
-        Ok(Async::NotReady)
+        Poll::Pending
     }
 }
@@ -25,7 +25,7 @@ use crate::protocols_handler::{
     ProtocolsHandlerEvent,
     ProtocolsHandlerUpgrErr
 };
-use futures::prelude::*;
+use futures03::task::{Context, Poll};
 use libp2p_core::upgrade::{InboundUpgrade, OutboundUpgrade};
 use std::marker::PhantomData;
 
@@ -103,10 +103,10 @@ where
     #[inline]
     fn poll(
         &mut self,
+        cx: &mut Context<'_>,
     ) -> Poll<
-        ProtocolsHandlerEvent<Self::OutboundProtocol, Self::OutboundOpenInfo, Self::OutEvent>,
-        Self::Error,
+        Result<ProtocolsHandlerEvent<Self::OutboundProtocol, Self::OutboundOpenInfo, Self::OutEvent>, Self::Error>,
     > {
-        self.inner.poll()
+        self.inner.poll(cx)
     }
 }
@@ -25,7 +25,7 @@ use crate::protocols_handler::{
     ProtocolsHandlerEvent,
     ProtocolsHandlerUpgrErr
 };
-use futures::prelude::*;
+use futures03::task::{Context, Poll};
 use libp2p_core::upgrade::{InboundUpgrade, OutboundUpgrade};
 
 /// Wrapper around a protocol handler that turns the output event into something else.
@@ -98,17 +98,17 @@ where
     #[inline]
     fn poll(
         &mut self,
+        cx: &mut Context<'_>,
     ) -> Poll<
-        ProtocolsHandlerEvent<Self::OutboundProtocol, Self::OutboundOpenInfo, Self::OutEvent>,
-        Self::Error,
+        Result<ProtocolsHandlerEvent<Self::OutboundProtocol, Self::OutboundOpenInfo, Self::OutEvent>, Self::Error>,
     > {
-        Ok(self.inner.poll()?.map(|ev| {
+        self.inner.poll(cx).map_ok(|ev| {
             match ev {
                 ProtocolsHandlerEvent::Custom(ev) => ProtocolsHandlerEvent::Custom((self.map)(ev)),
                 ProtocolsHandlerEvent::OutboundSubstreamRequest { protocol, info } => {
                     ProtocolsHandlerEvent::OutboundSubstreamRequest { protocol, info }
                 }
             }
-        }))
+        })
     }
 }
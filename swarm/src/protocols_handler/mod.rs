@@ -44,7 +44,7 @@ mod node_handler;
 mod one_shot;
 mod select;
 
-use futures::prelude::*;
+use futures03::task::{Context, Poll};
 use libp2p_core::{
     ConnectedPoint,
     PeerId,
@@ -93,6 +93,12 @@ pub use select::{IntoProtocolsHandlerSelect, ProtocolsHandlerSelect};
 /// Implementors of this trait should keep in mind that the connection can be closed at any time.
 /// When a connection is closed gracefully, the substreams used by the handler may still
 /// continue reading data until the remote closes its side of the connection.
+///
+/// # Futures 0.1
+///
+/// Handlers whose `poll` is written against futures 0.1 can implement
+/// [`ProtocolsHandler01`](crate::compat::ProtocolsHandler01) instead, which provides an
+/// implementation of this trait.
 pub trait ProtocolsHandler {
     /// Custom event that can be received from the outside.
     type InEvent;
@@ -168,12 +174,14 @@ pub trait ProtocolsHandler {
     /// > of the handler.
     fn connection_keep_alive(&self) -> KeepAlive;
 
-    /// Should behave like `Stream::poll()`.
+    /// Should behave like `Stream::poll_next()`.
     ///
     /// Returning an error will close the connection to the remote.
-    fn poll(&mut self) -> Poll<
-        ProtocolsHandlerEvent<Self::OutboundProtocol, Self::OutboundOpenInfo, Self::OutEvent>,
-        Self::Error
+    fn poll(&mut self, cx: &mut Context<'_>) -> Poll<
+        Result<
+            ProtocolsHandlerEvent<Self::OutboundProtocol, Self::OutboundOpenInfo, Self::OutEvent>,
+            Self::Error
+        >
     >;
 
     /// Adds a closure that turns the input event into something else.
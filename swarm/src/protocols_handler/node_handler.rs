@@ -18,6 +18,8 @@
 // FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // DEALINGS IN THE SOFTWARE.
 
+use crate::gater::{ConnectionGater, GatedUpgrade};
+use crate::observer::SwarmObserver;
 use crate::protocols_handler::{
     KeepAlive,
     ProtocolsHandler,
@@ -26,20 +28,28 @@ use crate::protocols_handler::{
     ProtocolsHandlerUpgrErr
 };
 use futures::prelude::*;
+use futures03::task::Poll as Poll03;
 use libp2p_core::{
     ConnectedPoint,
+    Endpoint,
     PeerId,
+    compat::{Compat03As01, with_context},
+    muxing::{MeteredSubstream, SubstreamMeter},
     nodes::collection::ConnectionInfo,
     nodes::handled_node::{IntoNodeHandler, NodeHandler, NodeHandlerEndpoint, NodeHandlerEvent},
     upgrade::{self, InboundUpgradeApply, OutboundUpgradeApply}
 };
-use std::{error, fmt, time::Duration};
+use std::{error, fmt, sync::Arc, time::Duration};
 use wasm_timer::{Delay, Timeout};
 
 /// Prototype for a `NodeHandlerWrapper`.
 pub struct NodeHandlerWrapperBuilder<TIntoProtoHandler> {
     /// The underlying handler.
     handler: TIntoProtoHandler,
+    /// The observer of the swarm, if any.
+    observer: Option<Arc<dyn SwarmObserver>>,
+    /// The connection gater of the swarm, if any.
+    gater: Option<Arc<dyn ConnectionGater>>,
 }
 
 impl<TIntoProtoHandler> NodeHandlerWrapperBuilder<TIntoProtoHandler>
@@ -51,9 +61,25 @@ where
     pub(crate) fn new(handler: TIntoProtoHandler) -> Self {
         NodeHandlerWrapperBuilder {
             handler,
+            observer: None,
+            gater: None,
         }
     }
 
+    /// Sets the observer that provides the meters of the substreams.
+    #[inline]
+    pub(crate) fn with_observer(mut self, observer: Option<Arc<dyn SwarmObserver>>) -> Self {
+        self.observer = observer;
+        self
+    }
+
+    /// Sets the gater that filters the protocols negotiated on the substreams.
+    #[inline]
+    pub(crate) fn with_gater(mut self, gater: Option<Arc<dyn ConnectionGater>>) -> Self {
+        self.gater = gater;
+        self
+    }
+
     /// Builds the `NodeHandlerWrapper`.
     #[deprecated(note = "Pass the NodeHandlerWrapperBuilder directly")]
     #[inline]
@@ -62,6 +88,8 @@ where
     {
         NodeHandlerWrapper {
             handler: self.handler,
+            metering: None,
+            gating: None,
             negotiating_in: Vec::new(),
             negotiating_out: Vec::new(),
             queued_dial_upgrades: Vec::new(),
@@ -76,6 +104,7 @@ impl<TIntoProtoHandler, TProtoHandler, TConnInfo> IntoNodeHandler<(TConnInfo, Co
 where
     TIntoProtoHandler: IntoProtocolsHandler<Handler = TProtoHandler>,
     TProtoHandler: ProtocolsHandler,
+    TProtoHandler::Substream: MeteredSubstream,
     TConnInfo: ConnectionInfo<PeerId = PeerId>,
 {
     type Handler = NodeHandlerWrapper<TIntoProtoHandler::Handler>;
@@ -83,6 +112,8 @@ where
     fn into_handler(self, remote_info: &(TConnInfo, ConnectedPoint)) -> Self::Handler {
         NodeHandlerWrapper {
             handler: self.handler.into_handler(&remote_info.0.peer_id(), &remote_info.1),
+            metering: self.observer.map(|o| (remote_info.0.peer_id().clone(), o)),
+            gating: self.gater.map(|g| (remote_info.0.peer_id().clone(), g)),
             negotiating_in: Vec::new(),
             negotiating_out: Vec::new(),
             queued_dial_upgrades: Vec::new(),
@@ -100,14 +131,20 @@ where
 {
     /// The underlying handler.
     handler: TProtoHandler,
+    /// The remote peer and the observer of the swarm, if substreams are to be metered.
+    metering: Option<(PeerId, Arc<dyn SwarmObserver>)>,
+    /// The remote peer and the gater of the swarm, if protocols are to be filtered.
+    gating: Option<(PeerId, Arc<dyn ConnectionGater>)>,
     /// Futures that upgrade incoming substreams.
-    negotiating_in:
-        Vec<Timeout<InboundUpgradeApply<TProtoHandler::Substream, TProtoHandler::InboundProtocol>>>,
+    negotiating_in: Vec<Timeout<Compat03As01<InboundUpgradeApply<
+        TProtoHandler::Substream,
+        GatedUpgrade<TProtoHandler::InboundProtocol>
+    >>>>,
     /// Futures that upgrade outgoing substreams. The first element of the tuple is the userdata
     /// to pass back once successfully opened.
     negotiating_out: Vec<(
         TProtoHandler::OutboundOpenInfo,
-        Timeout<OutboundUpgradeApply<TProtoHandler::Substream, TProtoHandler::OutboundProtocol>>,
+        Timeout<Compat03As01<OutboundUpgradeApply<TProtoHandler::Substream, GatedUpgrade<TProtoHandler::OutboundProtocol>>>>,
     )>,
     /// For each outbound substream request, how to upgrade it. The first element of the tuple
     /// is the unique identifier (see `unique_dial_upgrade_id`).
@@ -176,9 +213,34 @@ where
     }
 }
 
+impl<TProtoHandler> NodeHandlerWrapper<TProtoHandler>
+where
+    TProtoHandler: ProtocolsHandler,
+    TProtoHandler::Substream: MeteredSubstream,
+{
+    /// Attaches a meter to a new substream, if the observer of the swarm provides one.
+    fn meter_substream(&self, substream: &mut TProtoHandler::Substream, endpoint: Endpoint)
+        -> Option<Arc<dyn SubstreamMeter>>
+    {
+        let (peer_id, observer) = self.metering.as_ref()?;
+        let meter = observer.substream_meter(peer_id, endpoint)?;
+        substream.set_meter(meter.clone());
+        Some(meter)
+    }
+
+    /// Wraps an upgrade so that only the protocols allowed by the gater of the swarm are
+    /// negotiated.
+    fn gate_upgrade<TUpgrade>(&self, upgrade: TUpgrade, endpoint: Endpoint) -> GatedUpgrade<TUpgrade> {
+        let gate = self.gating.as_ref()
+            .map(|(peer_id, gater)| (gater.clone(), peer_id.clone(), endpoint));
+        GatedUpgrade::new(upgrade, gate)
+    }
+}
+
 impl<TProtoHandler> NodeHandler for NodeHandlerWrapper<TProtoHandler>
 where
     TProtoHandler: ProtocolsHandler,
+    TProtoHandler::Substream: MeteredSubstream,
 {
     type InEvent = TProtoHandler::InEvent;
     type OutEvent = TProtoHandler::OutEvent;
@@ -190,15 +252,20 @@ where
 
     fn inject_substream(
         &mut self,
-        substream: Self::Substream,
+        mut substream: Self::Substream,
         endpoint: NodeHandlerEndpoint<Self::OutboundOpenInfo>,
     ) {
         match endpoint {
             NodeHandlerEndpoint::Listener => {
+                let meter = self.meter_substream(&mut substream, Endpoint::Listener);
                 let protocol = self.handler.listen_protocol();
                 let timeout = protocol.timeout().clone();
-                let upgrade = upgrade::apply_inbound(substream, protocol.into_upgrade());
-                let with_timeout = Timeout::new(upgrade, timeout);
+                let protocol = self.gate_upgrade(protocol.into_upgrade(), Endpoint::Listener);
+                let mut upgrade = upgrade::apply_inbound(substream, protocol);
+                if let Some(meter) = meter {
+                    upgrade = upgrade.with_meter(meter);
+                }
+                let with_timeout = Timeout::new(Compat03As01::new(upgrade), timeout);
                 self.negotiating_in.push(with_timeout);
             }
             NodeHandlerEndpoint::Dialer((upgrade_id, user_data, timeout)) => {
@@ -215,8 +282,13 @@ where
                 };
 
                 let (_, proto_upgrade) = self.queued_dial_upgrades.remove(pos);
-                let upgrade = upgrade::apply_outbound(substream, proto_upgrade);
-                let with_timeout = Timeout::new(upgrade, timeout);
+                let meter = self.meter_substream(&mut substream, Endpoint::Dialer);
+                let proto_upgrade = self.gate_upgrade(proto_upgrade, Endpoint::Dialer);
+                let mut upgrade = upgrade::apply_outbound(substream, proto_upgrade);
+                if let Some(meter) = meter {
+                    upgrade = upgrade.with_meter(meter);
+                }
+                let with_timeout = Timeout::new(Compat03As01::new(upgrade), timeout);
                 self.negotiating_out.push((user_data, with_timeout));
             }
         }
@@ -272,7 +344,11 @@ where
 
         // Poll the handler at the end so that we see the consequences of the method
         // calls on `self.handler`.
-        let poll_result = self.handler.poll()?;
+        let poll_result = match with_context(|cx| self.handler.poll(cx)) {
+            Poll03::Ready(Ok(event)) => Async::Ready(event),
+            Poll03::Ready(Err(err)) => return Err(err.into()),
+            Poll03::Pending => Async::NotReady,
+        };
 
         // Ask the handler whether it wants the connection (and the handler itself)
         // to be kept alive, which determines the planned shutdown, if any.
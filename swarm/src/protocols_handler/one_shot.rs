@@ -25,12 +25,12 @@ use crate::protocols_handler::{
     ProtocolsHandlerUpgrErr,
     SubstreamProtocol
 };
-use futures::prelude::*;
+use futures03::task::{Context, Poll};
+use libp2p_core::clock;
 use libp2p_core::upgrade::{InboundUpgrade, OutboundUpgrade};
 use smallvec::SmallVec;
 use std::{error, marker::PhantomData, time::Duration};
 use tokio_io::{AsyncRead, AsyncWrite};
-use wasm_timer::Instant;
 
 /// Implementation of `ProtocolsHandler` that opens a new substream for each individual message.
 ///
@@ -110,6 +110,8 @@ where
     }
 
     /// Opens an outbound substream with `upgrade`.
+    ///
+    /// The upgrade is applied with the same timeout as the listen protocol.
     #[inline]
     pub fn send_request(&mut self, upgrade: TOutProto) {
         self.keep_alive = KeepAlive::Yes;
@@ -162,7 +164,7 @@ where
     ) {
         // If we're shutting down the connection for inactivity, reset the timeout.
         if !self.keep_alive.is_yes() {
-            self.keep_alive = KeepAlive::Until(Instant::now() + self.inactive_timeout);
+            self.keep_alive = KeepAlive::Until(clock::now() + self.inactive_timeout);
         }
 
         self.events_out.push(out.into());
@@ -177,7 +179,7 @@ where
         self.dial_negotiated -= 1;
 
         if self.dial_negotiated == 0 && self.dial_queue.is_empty() {
-            self.keep_alive = KeepAlive::Until(Instant::now() + self.inactive_timeout);
+            self.keep_alive = KeepAlive::Until(clock::now() + self.inactive_timeout);
         }
 
         self.events_out.push(out.into());
@@ -208,16 +210,16 @@ where
 
     fn poll(
         &mut self,
+        _: &mut Context<'_>,
     ) -> Poll<
-        ProtocolsHandlerEvent<Self::OutboundProtocol, Self::OutboundOpenInfo, Self::OutEvent>,
-        Self::Error,
+        Result<ProtocolsHandlerEvent<Self::OutboundProtocol, Self::OutboundOpenInfo, Self::OutEvent>, Self::Error>,
     > {
         if let Some(err) = self.pending_error.take() {
-            return Err(err);
+            return Poll::Ready(Err(err));
         }
 
         if !self.events_out.is_empty() {
-            return Ok(Async::Ready(ProtocolsHandlerEvent::Custom(
+            return Poll::Ready(Ok(ProtocolsHandlerEvent::Custom(
                 self.events_out.remove(0),
             )));
         } else {
@@ -227,9 +229,10 @@ where
         if !self.dial_queue.is_empty() {
             if self.dial_negotiated < self.max_dial_negotiated {
                 self.dial_negotiated += 1;
-                return Ok(Async::Ready(
+                return Poll::Ready(Ok(
                     ProtocolsHandlerEvent::OutboundSubstreamRequest {
-                        protocol: SubstreamProtocol::new(self.dial_queue.remove(0)),
+                        protocol: SubstreamProtocol::new(self.dial_queue.remove(0))
+                            .with_timeout(*self.listen_protocol.timeout()),
                         info: (),
                     },
                 ));
@@ -238,6 +241,6 @@ where
             self.dial_queue.shrink_to_fit();
         }
 
-        Ok(Async::NotReady)
+        Poll::Pending
     }
 }
@@ -26,7 +26,7 @@ use crate::protocols_handler::{
     ProtocolsHandlerEvent,
     ProtocolsHandlerUpgrErr,
 };
-use futures::prelude::*;
+use futures03::task::{Context, Poll};
 use libp2p_core::{
     ConnectedPoint,
     PeerId,
@@ -201,40 +201,42 @@ where
         cmp::max(self.proto1.connection_keep_alive(), self.proto2.connection_keep_alive())
     }
 
-    fn poll(&mut self) -> Poll<ProtocolsHandlerEvent<Self::OutboundProtocol, Self::OutboundOpenInfo, Self::OutEvent>, Self::Error> {
+    fn poll(&mut self, cx: &mut Context<'_>) -> Poll<Result<ProtocolsHandlerEvent<Self::OutboundProtocol, Self::OutboundOpenInfo, Self::OutEvent>, Self::Error>> {
 
-        match self.proto1.poll().map_err(EitherError::A)? {
-            Async::Ready(ProtocolsHandlerEvent::Custom(event)) => {
-                return Ok(Async::Ready(ProtocolsHandlerEvent::Custom(EitherOutput::First(event))));
+        match self.proto1.poll(cx) {
+            Poll::Ready(Ok(ProtocolsHandlerEvent::Custom(event))) => {
+                return Poll::Ready(Ok(ProtocolsHandlerEvent::Custom(EitherOutput::First(event))));
             },
-            Async::Ready(ProtocolsHandlerEvent::OutboundSubstreamRequest {
+            Poll::Ready(Ok(ProtocolsHandlerEvent::OutboundSubstreamRequest {
                 protocol,
                 info,
-            }) => {
-                return Ok(Async::Ready(ProtocolsHandlerEvent::OutboundSubstreamRequest {
+            })) => {
+                return Poll::Ready(Ok(ProtocolsHandlerEvent::OutboundSubstreamRequest {
                     protocol: protocol.map_upgrade(EitherUpgrade::A),
                     info: EitherOutput::First(info),
                 }));
             },
-            Async::NotReady => ()
+            Poll::Ready(Err(err)) => return Poll::Ready(Err(EitherError::A(err))),
+            Poll::Pending => ()
         };
 
-        match self.proto2.poll().map_err(EitherError::B)? {
-            Async::Ready(ProtocolsHandlerEvent::Custom(event)) => {
-                return Ok(Async::Ready(ProtocolsHandlerEvent::Custom(EitherOutput::Second(event))));
+        match self.proto2.poll(cx) {
+            Poll::Ready(Ok(ProtocolsHandlerEvent::Custom(event))) => {
+                return Poll::Ready(Ok(ProtocolsHandlerEvent::Custom(EitherOutput::Second(event))));
             },
-            Async::Ready(ProtocolsHandlerEvent::OutboundSubstreamRequest {
+            Poll::Ready(Ok(ProtocolsHandlerEvent::OutboundSubstreamRequest {
                 protocol,
                 info,
-            }) => {
-                return Ok(Async::Ready(ProtocolsHandlerEvent::OutboundSubstreamRequest {
+            })) => {
+                return Poll::Ready(Ok(ProtocolsHandlerEvent::OutboundSubstreamRequest {
                     protocol: protocol.map_upgrade(EitherUpgrade::B),
                     info: EitherOutput::Second(info),
                 }));
             },
-            Async::NotReady => ()
+            Poll::Ready(Err(err)) => return Poll::Ready(Err(EitherError::B(err))),
+            Poll::Pending => ()
         };
 
-        Ok(Async::NotReady)
+        Poll::Pending
     }
 }
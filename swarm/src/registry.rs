@@ -99,6 +99,20 @@ impl Addresses {
         self.registry.push(r)
     }
 
+    /// Remove a [`Multiaddr`] from the collection, regardless of its score.
+    ///
+    /// Returns `true` if the address was in the collection.
+    pub fn remove(&mut self, a: &Multiaddr) -> bool {
+        self.reports.retain(|r| r != a);
+        match self.registry.iter().position(|r| r.addr == *a) {
+            Some(pos) => {
+                self.registry.remove(pos);
+                true
+            }
+            None => false
+        }
+    }
+
     /// Return an iterator over all [`Multiaddr`] values.
     ///
     /// The iteration is ordered by descending score.
@@ -223,6 +237,24 @@ mod tests {
         assert!(addresses.iter().find(|a| **a == single).is_none());
     }
 
+    #[test]
+    fn removed_addresses_are_forgotten() {
+        let mut addresses = Addresses::default();
+        let removed: Multiaddr = "/tcp/2108".parse().unwrap();
+        let other: Multiaddr = "/tcp/120".parse().unwrap();
+        addresses.add(removed.clone());
+        addresses.add(removed.clone());
+        addresses.add(other.clone());
+
+        assert!(addresses.remove(&removed));
+        assert!(!addresses.remove(&removed));
+        assert_eq!(addresses.iter().collect::<Vec<_>>(), vec![&other]);
+
+        // The reports made before the removal don't count anymore.
+        addresses.add(removed.clone());
+        assert_eq!(addresses.registry.iter().find(|r| r.addr == removed).unwrap().score, 1);
+    }
+
     #[test]
     fn record_score_equals_last_n_reports() {
         #[derive(PartialEq, Eq, Clone, Hash, Debug)]
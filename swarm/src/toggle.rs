@@ -32,9 +32,11 @@ use libp2p_core::{
     PeerId,
     Multiaddr,
     either::EitherOutput,
+    nodes::ConnectionId,
+    nodes::network::ConnectionLimit,
     upgrade::{InboundUpgrade, OutboundUpgrade, DeniedUpgrade, EitherUpgrade}
 };
-use futures::prelude::*;
+use futures03::task::{Context, Poll};
 use std::error;
 
 /// Implementation of `NetworkBehaviour` that can be either in the disabled or enabled state.
@@ -79,19 +81,26 @@ where
         }
     }
 
-    fn inject_replaced(&mut self, peer_id: PeerId, closed_endpoint: ConnectedPoint, new_endpoint: ConnectedPoint) {
+    fn inject_connection_established(&mut self, peer_id: &PeerId, connection: &ConnectionId, endpoint: &ConnectedPoint) {
         if let Some(inner) = self.inner.as_mut() {
-            inner.inject_replaced(peer_id, closed_endpoint, new_endpoint)
+            inner.inject_connection_established(peer_id, connection, endpoint)
+        }
+    }
+
+    fn inject_connection_closed(&mut self, peer_id: &PeerId, connection: &ConnectionId, endpoint: &ConnectedPoint) {
+        if let Some(inner) = self.inner.as_mut() {
+            inner.inject_connection_closed(peer_id, connection, endpoint)
         }
     }
 
     fn inject_node_event(
         &mut self,
         peer_id: PeerId,
+        connection: ConnectionId,
         event: <<Self::ProtocolsHandler as IntoProtocolsHandler>::Handler as ProtocolsHandler>::OutEvent
     ) {
         if let Some(inner) = self.inner.as_mut() {
-            inner.inject_node_event(peer_id, event);
+            inner.inject_node_event(peer_id, connection, event);
         }
     }
 
@@ -107,6 +116,12 @@ where
         }
     }
 
+    fn inject_connection_limit_reached(&mut self, peer_id: Option<&PeerId>, endpoint: &ConnectedPoint, limit: &ConnectionLimit) {
+        if let Some(inner) = self.inner.as_mut() {
+            inner.inject_connection_limit_reached(peer_id, endpoint, limit)
+        }
+    }
+
     fn inject_new_listen_addr(&mut self, addr: &Multiaddr) {
         if let Some(inner) = self.inner.as_mut() {
             inner.inject_new_listen_addr(addr)
@@ -125,13 +140,19 @@ where
         }
     }
 
-    fn poll(&mut self, params: &mut impl PollParameters)
-        -> Async<NetworkBehaviourAction<<<Self::ProtocolsHandler as IntoProtocolsHandler>::Handler as ProtocolsHandler>::InEvent, Self::OutEvent>>
+    fn inject_expired_external_addr(&mut self, addr: &Multiaddr) {
+        if let Some(inner) = self.inner.as_mut() {
+            inner.inject_expired_external_addr(addr)
+        }
+    }
+
+    fn poll(&mut self, cx: &mut Context<'_>, params: &mut impl PollParameters)
+        -> Poll<NetworkBehaviourAction<<<Self::ProtocolsHandler as IntoProtocolsHandler>::Handler as ProtocolsHandler>::InEvent, Self::OutEvent>>
     {
         if let Some(inner) = self.inner.as_mut() {
-            inner.poll(params)
+            inner.poll(cx, params)
         } else {
-            Async::NotReady
+            Poll::Pending
         }
     }
 }
@@ -237,14 +258,14 @@ where
 
     fn poll(
         &mut self,
+        cx: &mut Context<'_>,
     ) -> Poll<
-        ProtocolsHandlerEvent<Self::OutboundProtocol, Self::OutboundOpenInfo, Self::OutEvent>,
-        Self::Error,
+        Result<ProtocolsHandlerEvent<Self::OutboundProtocol, Self::OutboundOpenInfo, Self::OutEvent>, Self::Error>,
     > {
         if let Some(inner) = self.inner.as_mut() {
-            inner.poll()
+            inner.poll(cx)
         } else {
-            Ok(Async::NotReady)
+            Poll::Pending
         }
     }
 }
@@ -35,7 +35,9 @@
 
 use futures::{future::{self, Either, FutureResult, JoinAll}, prelude::*, stream, try_ready};
 use libp2p_core::{
+    Endpoint,
     Transport,
+    compat::{Compat03As01, Transport01},
     multiaddr::{Protocol, Multiaddr},
     transport::{TransportError, ListenerEvent}
 };
@@ -82,7 +84,10 @@ where
     }
 }
 
-impl<T> Transport for DnsConfig<T>
+/// The underlying transport, as seen by the futures 0.1 implementation of the DNS layer.
+type Inner<T> = Compat03As01<T>;
+
+impl<T> Transport01 for DnsConfig<T>
 where
     T: Transport,
     T::Error: 'static,
@@ -90,19 +95,20 @@ where
     type Output = T::Output;
     type Error = DnsErr<T::Error>;
     type Listener = stream::MapErr<
-        stream::Map<T::Listener,
-            fn(ListenerEvent<T::ListenerUpgrade>) -> ListenerEvent<Self::ListenerUpgrade>>,
+        stream::Map<<Inner<T> as Transport01>::Listener,
+            fn(ListenerEvent<<Inner<T> as Transport01>::ListenerUpgrade>) -> ListenerEvent<Self::ListenerUpgrade>>,
         fn(T::Error) -> Self::Error>;
-    type ListenerUpgrade = future::MapErr<T::ListenerUpgrade, fn(T::Error) -> Self::Error>;
-    type Dial = Either<future::MapErr<T::Dial, fn(T::Error) -> Self::Error>,
-        DialFuture<T, JoinFuture<JoinAll<std::vec::IntoIter<Either<
+    type ListenerUpgrade = future::MapErr<<Inner<T> as Transport01>::ListenerUpgrade, fn(T::Error) -> Self::Error>;
+    type Dial = Either<future::MapErr<<Inner<T> as Transport01>::Dial, fn(T::Error) -> Self::Error>,
+        DialFuture<Inner<T>, JoinFuture<JoinAll<std::vec::IntoIter<Either<
             ResolveFuture<tokio_dns::IoFuture<Vec<IpAddr>>, T::Error>,
             FutureResult<Protocol<'static>, Self::Error>>>>
         >>
     >;
 
     fn listen_on(self, addr: Multiaddr) -> Result<Self::Listener, TransportError<Self::Error>> {
-        let listener = self.inner.listen_on(addr).map_err(|err| err.map(DnsErr::Underlying))?;
+        let listener = Transport01::listen_on(Compat03As01::new(self.inner), addr)
+            .map_err(|err| err.map(DnsErr::Underlying))?;
         let listener = listener
             .map::<_, fn(_) -> _>(|event| event.map(|upgr| {
                 upgr.map_err::<fn(_) -> _, _>(DnsErr::Underlying)
@@ -112,6 +118,24 @@ where
     }
 
     fn dial(self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
+        self.dial_with_role(addr, Endpoint::Dialer)
+    }
+
+    fn dial_as_listener(self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
+        self.dial_with_role(addr, Endpoint::Listener)
+    }
+}
+
+impl<T> DnsConfig<T>
+where
+    T: Transport,
+    T::Error: 'static,
+{
+    fn dial_with_role(self, addr: Multiaddr, role_override: Endpoint)
+        -> Result<<Self as Transport01>::Dial, TransportError<<Self as Transport01>::Error>>
+    {
+        let inner = Compat03As01::new(self.inner);
+
         let contains_dns = addr.iter().any(|cmp| match cmp {
             Protocol::Dns4(_) => true,
             Protocol::Dns6(_) => true,
@@ -120,7 +144,10 @@ where
 
         if !contains_dns {
             trace!("Pass-through address without DNS: {}", addr);
-            let inner_dial = self.inner.dial(addr).map_err(|err| err.map(DnsErr::Underlying))?;
+            let inner_dial = match role_override {
+                Endpoint::Dialer => Transport01::dial(inner, addr),
+                Endpoint::Listener => Transport01::dial_as_listener(inner, addr),
+            }.map_err(|err| err.map(DnsErr::Underlying))?;
             return Ok(Either::A(inner_dial.map_err(DnsErr::Underlying)));
         }
 
@@ -157,7 +184,7 @@ where
             .into_iter();
 
         let new_addr = JoinFuture { addr, future: future::join_all(resolve_iters) };
-        Ok(Either::B(DialFuture { trans: Some(self.inner), future: Either::A(new_addr) }))
+        Ok(Either::B(DialFuture { trans: Some(inner), role_override, future: Either::A(new_addr) }))
     }
 }
 
@@ -274,14 +301,15 @@ where
 
 /// Future, dialing the resolved multi-address.
 #[derive(Debug)]
-pub struct DialFuture<T: Transport, F> {
+pub struct DialFuture<T: Transport01, F> {
     trans: Option<T>,
+    role_override: Endpoint,
     future: Either<F, T::Dial>,
 }
 
 impl<T, F, TErr> Future for DialFuture<T, F>
 where
-    T: Transport<Error = TErr>,
+    T: Transport01<Error = TErr>,
     F: Future<Item = Multiaddr, Error = DnsErr<TErr>>,
     TErr: error::Error,
 {
@@ -293,7 +321,12 @@ where
             let next = match self.future {
                 Either::A(ref mut f) => {
                     let addr = try_ready!(f.poll());
-                    match self.trans.take().unwrap().dial(addr) {
+                    let trans = self.trans.take().unwrap();
+                    let dial = match self.role_override {
+                        Endpoint::Dialer => Transport01::dial(trans, addr),
+                        Endpoint::Listener => Transport01::dial_as_listener(trans, addr),
+                    };
+                    match dial {
                         Ok(dial) => Either::B(dial),
                         Err(_) => return Err(DnsErr::MultiaddrNotSupported)
                     }
@@ -310,7 +343,7 @@ mod tests {
     use libp2p_tcp::TcpConfig;
     use futures::future;
     use libp2p_core::{
-        Transport,
+        compat::Transport01,
         multiaddr::{Protocol, Multiaddr},
         transport::TransportError
     };
@@ -321,11 +354,11 @@ mod tests {
         #[derive(Clone)]
         struct CustomTransport;
 
-        impl Transport for CustomTransport {
-            type Output = <TcpConfig as Transport>::Output;
-            type Error = <TcpConfig as Transport>::Error;
-            type Listener = <TcpConfig as Transport>::Listener;
-            type ListenerUpgrade = <TcpConfig as Transport>::ListenerUpgrade;
+        impl Transport01 for CustomTransport {
+            type Output = <TcpConfig as Transport01>::Output;
+            type Error = <TcpConfig as Transport01>::Error;
+            type Listener = <TcpConfig as Transport01>::Listener;
+            type ListenerUpgrade = <TcpConfig as Transport01>::ListenerUpgrade;
             type Dial = future::Empty<Self::Output, Self::Error>;
 
             fn listen_on(self, _: Multiaddr) -> Result<Self::Listener, TransportError<Self::Error>> {
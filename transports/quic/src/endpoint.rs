@@ -0,0 +1,515 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! QUIC endpoint bound to a UDP socket, shared between the listener, the dialer and the muxers
+//! of the connections it carries.
+//!
+//! `quinn_proto` doesn't perform any I/O by itself. The state machines of the endpoint and of its
+//! connections are put behind a mutex, and a background task, the `EndpointDriver`, sends and
+//! receives the packets and handles the timers. The other objects only act upon the state
+//! machines and wake up the driver afterwards.
+
+use crate::tls::Libp2pVerifier;
+use bytes::BytesMut;
+use futures::{prelude::*, task::{self, Task}};
+use libp2p_core::PeerId;
+use log::debug;
+use parking_lot::{Mutex, MutexGuard};
+use quinn_proto::{
+    ClientConfig, ConnectionEvent, ConnectionHandle, DatagramEvent, EndpointConfig,
+    EndpointEvent, Event, ServerConfig, Side, Timer, TimerSetting, TimerUpdate, Transmit
+};
+use std::{collections::{HashMap, VecDeque}, io, net::SocketAddr, sync::Arc};
+use tokio_executor::Executor;
+use tokio_udp::UdpSocket;
+use wasm_timer::{Delay, Instant};
+
+/// Maximum size of a UDP datagram.
+const MAX_DATAGRAM_SIZE: usize = 65527;
+
+/// A QUIC endpoint.
+pub struct Endpoint {
+    inner: Mutex<EndpointInner>,
+}
+
+/// Handle to an `Endpoint`, held by the objects that use the endpoint. The driver of the endpoint
+/// stops once all the handles have been dropped and all the connections have been drained.
+pub struct EndpointHandle {
+    endpoint: Arc<Endpoint>,
+}
+
+/// State of an endpoint.
+pub struct EndpointInner {
+    /// The socket the endpoint is bound to.
+    socket: UdpSocket,
+    /// The state machine of the endpoint.
+    endpoint: quinn_proto::Endpoint,
+    /// Verifier of the certificates presented by the remotes, shared with the TLS configuration
+    /// of the endpoint.
+    verifier: Arc<Libp2pVerifier>,
+    /// The connections of the endpoint.
+    connections: HashMap<ConnectionHandle, Connection>,
+    /// Identifier to assign to the next connection.
+    next_connection_id: u64,
+    /// Incoming connections whose handshake has succeeded, and that haven't been returned by the
+    /// listener yet.
+    incoming: VecDeque<ConnectionHandle>,
+    /// Whether there is a listener accepting the incoming connections.
+    listening: bool,
+    /// Task of the listener, to notify when a new incoming connection is available.
+    listener_task: Option<Task>,
+    /// Number of `EndpointHandle`s alive.
+    handles: usize,
+    /// Whether the driver has been spawned.
+    driver_spawned: bool,
+    /// Task of the driver, to notify when there is something to do.
+    driver_task: Option<Task>,
+    /// Packet that couldn't be sent because the socket wasn't ready.
+    pending_transmit: Option<Transmit>,
+    /// Timer firing when the earliest timer of the connections expires.
+    timer: Option<(Instant, Delay)>,
+}
+
+/// State of a connection of an endpoint.
+pub struct Connection {
+    /// Identifier of the connection, unique within the endpoint. Contrary to the
+    /// `ConnectionHandle`s, identifiers are never reused.
+    pub id: u64,
+    /// The state machine of the connection.
+    pub connection: quinn_proto::Connection,
+    /// Identity of the remote, known once its certificate has been verified.
+    pub peer_id: Option<PeerId>,
+    /// Whether the handshake of the connection has succeeded.
+    pub established: bool,
+    /// Why the connection has been lost, if it has.
+    pub error: Option<quinn_proto::ConnectionError>,
+    /// Whether the connection still holds a slot in the accept buffer of the endpoint.
+    holds_handshake_slot: bool,
+    /// Deadlines of the timers of the connection.
+    timers: [Option<Instant>; Timer::COUNT],
+    /// Tasks to notify when something happens on the connection.
+    tasks: Vec<Task>,
+}
+
+impl EndpointHandle {
+    /// Creates a new endpoint using the given socket. The endpoint accepts incoming connections
+    /// if and only if `server_config` is `Some`.
+    pub fn new(
+        socket: UdpSocket,
+        server_config: Option<ServerConfig>,
+        verifier: Arc<Libp2pVerifier>
+    ) -> Result<EndpointHandle, io::Error> {
+        let log = slog::Logger::root(slog::Discard, slog::o!());
+        let listening = server_config.is_some();
+        let endpoint = quinn_proto::Endpoint::new(
+            log,
+            Arc::new(EndpointConfig::default()),
+            server_config.map(Arc::new)
+        ).map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+
+        let endpoint = Arc::new(Endpoint {
+            inner: Mutex::new(EndpointInner {
+                socket,
+                endpoint,
+                verifier,
+                connections: HashMap::new(),
+                next_connection_id: 0,
+                incoming: VecDeque::new(),
+                listening,
+                listener_task: None,
+                handles: 1,
+                driver_spawned: false,
+                driver_task: None,
+                pending_transmit: None,
+                timer: None,
+            })
+        });
+
+        Ok(EndpointHandle { endpoint })
+    }
+
+    /// Locks the state of the endpoint.
+    pub fn lock(&self) -> MutexGuard<'_, EndpointInner> {
+        self.endpoint.inner.lock()
+    }
+
+    /// Locks the state of the endpoint, after having spawned its driver if that hasn't been done
+    /// yet. Must therefore be called from within a task running on an executor.
+    pub fn drive_and_lock(&self) -> Result<MutexGuard<'_, EndpointInner>, io::Error> {
+        let mut inner = self.endpoint.inner.lock();
+        if !inner.driver_spawned {
+            let driver = EndpointDriver { endpoint: self.endpoint.clone() };
+            tokio_executor::DefaultExecutor::current().spawn(Box::new(driver))
+                .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+            inner.driver_spawned = true;
+        }
+        Ok(inner)
+    }
+}
+
+impl Clone for EndpointHandle {
+    fn clone(&self) -> Self {
+        self.endpoint.inner.lock().handles += 1;
+        EndpointHandle { endpoint: self.endpoint.clone() }
+    }
+}
+
+impl Drop for EndpointHandle {
+    fn drop(&mut self) {
+        let mut inner = self.endpoint.inner.lock();
+        inner.handles -= 1;
+        inner.wake_driver();
+    }
+}
+
+impl EndpointInner {
+    /// Starts connecting to the given address. Returns the handle and the identifier of the new
+    /// connection.
+    pub fn connect(&mut self, config: ClientConfig, remote: SocketAddr)
+        -> Result<(ConnectionHandle, u64), io::Error>
+    {
+        // The name is irrelevant, as the certificates are self-signed and verified against the
+        // libp2p identity they contain.
+        let (handle, connection) = self.endpoint.connect(config, remote, "libp2p")
+            .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+        let id = self.insert_connection(handle, connection, false);
+        self.wake_driver();
+        Ok((handle, id))
+    }
+
+    /// Returns the connection with the given handle and identifier, or an error if it doesn't
+    /// exist anymore.
+    pub fn connection(&mut self, handle: ConnectionHandle, id: u64)
+        -> Result<&mut Connection, io::Error>
+    {
+        match self.connections.get_mut(&handle) {
+            Some(connection) if connection.id == id => Ok(connection),
+            _ => Err(io::ErrorKind::ConnectionAborted.into())
+        }
+    }
+
+    /// Returns the next incoming connection whose handshake has succeeded, as well as the
+    /// identity of the remote. Registers the current task to be notified otherwise.
+    pub fn poll_incoming(&mut self) -> Option<(ConnectionHandle, u64, PeerId, SocketAddr)> {
+        while let Some(handle) = self.incoming.pop_front() {
+            if let Some(connection) = self.connections.get(&handle) {
+                if let Some(ref peer_id) = connection.peer_id {
+                    let remote = connection.connection.remote();
+                    return Some((handle, connection.id, peer_id.clone(), remote))
+                }
+            }
+        }
+
+        self.listener_task = Some(task::current());
+        None
+    }
+
+    /// Stops accepting incoming connections, and closes the ones that have been established but
+    /// not returned by `poll_incoming` yet.
+    pub fn stop_listening(&mut self) {
+        self.listening = false;
+        self.listener_task = None;
+        self.endpoint.reject_new_connections();
+        let now = Instant::now();
+        for handle in self.incoming.drain(..) {
+            if let Some(connection) = self.connections.get_mut(&handle) {
+                connection.connection.close(now, 0, Default::default());
+            }
+        }
+        self.wake_driver();
+    }
+
+    /// Wakes up the driver, so that it processes what has been done on the state machines.
+    pub fn wake_driver(&mut self) {
+        if let Some(task) = self.driver_task.take() {
+            task.notify();
+        }
+    }
+
+    fn insert_connection(
+        &mut self,
+        handle: ConnectionHandle,
+        connection: quinn_proto::Connection,
+        holds_handshake_slot: bool
+    ) -> u64 {
+        let id = self.next_connection_id;
+        self.next_connection_id += 1;
+        self.connections.insert(handle, Connection {
+            id,
+            connection,
+            peer_id: None,
+            established: false,
+            error: None,
+            holds_handshake_slot,
+            timers: [None; Timer::COUNT],
+            tasks: Vec::new(),
+        });
+        id
+    }
+
+    /// Processes an incoming datagram.
+    fn handle_datagram(&mut self, now: Instant, from: SocketAddr, data: BytesMut) {
+        let (handle, event) = match self.endpoint.handle(now, from, None, data) {
+            Some(event) => event,
+            None => return
+        };
+
+        match event {
+            DatagramEvent::NewConnection(connection) => {
+                self.insert_connection(handle, connection, true);
+            }
+            DatagramEvent::ConnectionEvent(event) => {
+                if let Some(connection) = self.connections.get_mut(&handle) {
+                    connection.connection.handle_event(event);
+                }
+            }
+        }
+
+        // Verifying the certificate of the remote happens synchronously while handling a packet
+        // of the handshake, hence the verified identity belongs to this connection.
+        if let Some(peer_id) = self.verifier.take_verified() {
+            if let Some(connection) = self.connections.get_mut(&handle) {
+                connection.peer_id = Some(peer_id);
+            }
+        }
+    }
+
+    /// Fires the timers of the connections that have expired.
+    fn handle_timers(&mut self, now: Instant) {
+        for connection in self.connections.values_mut() {
+            for timer in Timer::iter() {
+                match connection.timers[timer as usize] {
+                    Some(deadline) if deadline <= now => {}
+                    _ => continue
+                }
+                connection.timers[timer as usize] = None;
+                connection.connection.handle_event(ConnectionEvent::Timer(now, timer));
+            }
+        }
+    }
+
+    /// Processes the events produced by the connections, and removes the ones that have been
+    /// drained.
+    fn process_connections(&mut self) {
+        let mut drained = Vec::new();
+
+        for (handle, connection) in self.connections.iter_mut() {
+            while let Some(event) = connection.connection.poll_endpoint_events() {
+                if let EndpointEvent::Drained = event {
+                    drained.push(*handle);
+                }
+                if let Some(event) = self.endpoint.handle_event(*handle, event) {
+                    connection.connection.handle_event(event);
+                }
+            }
+
+            while let Some(event) = connection.connection.poll() {
+                match event {
+                    Event::Connected => {
+                        connection.established = true;
+                        if connection.holds_handshake_slot {
+                            connection.holds_handshake_slot = false;
+                            self.endpoint.accept();
+                        }
+                        if connection.connection.side() == Side::Server {
+                            if self.listening && connection.peer_id.is_some() {
+                                self.incoming.push_back(*handle);
+                                if let Some(task) = self.listener_task.take() {
+                                    task.notify();
+                                }
+                            } else {
+                                connection.connection.close(Instant::now(), 0, Default::default());
+                            }
+                        }
+                    }
+                    Event::ConnectionLost { reason } => {
+                        debug!("Lost QUIC connection with {}: {}",
+                            connection.connection.remote(), reason);
+                        connection.error = Some(reason);
+                    }
+                    _ => {}
+                }
+                connection.notify_tasks();
+            }
+
+            while let Some(TimerUpdate { timer, update }) = connection.connection.poll_timers() {
+                connection.timers[timer as usize] = match update {
+                    TimerSetting::Start(deadline) => Some(deadline),
+                    TimerSetting::Stop => None,
+                };
+            }
+        }
+
+        for handle in drained {
+            if let Some(mut connection) = self.connections.remove(&handle) {
+                if connection.holds_handshake_slot {
+                    self.endpoint.accept();
+                }
+                connection.notify_tasks();
+            }
+        }
+    }
+
+    /// Sends the packets produced by the endpoint and its connections. Returns `false` if the
+    /// socket isn't ready to send more.
+    fn send_transmits(&mut self, now: Instant) -> bool {
+        if let Some(transmit) = self.pending_transmit.take() {
+            if !self.send(transmit) {
+                return false
+            }
+        }
+
+        while let Some(transmit) = self.endpoint.poll_transmit() {
+            if !self.send(transmit) {
+                return false
+            }
+        }
+
+        let handles = self.connections.keys().cloned().collect::<Vec<_>>();
+        for handle in handles {
+            loop {
+                let transmit = match self.connections.get_mut(&handle) {
+                    Some(connection) => connection.connection.poll_transmit(now),
+                    None => None
+                };
+                match transmit {
+                    Some(transmit) => if !self.send(transmit) { return false },
+                    None => break
+                }
+            }
+        }
+
+        true
+    }
+
+    /// Sends a packet. Returns `false` and keeps the packet for later if the socket isn't ready.
+    fn send(&mut self, transmit: Transmit) -> bool {
+        match self.socket.poll_send_to(&transmit.packet, &transmit.destination) {
+            Ok(Async::Ready(_)) => true,
+            Ok(Async::NotReady) => {
+                self.pending_transmit = Some(transmit);
+                false
+            }
+            Err(err) => {
+                debug!("Failed to send QUIC packet to {}: {}", transmit.destination, err);
+                true
+            }
+        }
+    }
+
+    /// Returns the earliest deadline of the timers of the connections.
+    fn next_deadline(&self) -> Option<Instant> {
+        self.connections.values()
+            .flat_map(|connection| connection.timers.iter().filter_map(|t| *t))
+            .min()
+    }
+}
+
+impl Connection {
+    /// Registers the current task to be notified when something happens on the connection.
+    pub fn register_task(&mut self) {
+        if !self.tasks.iter().any(|task| task.will_notify_current()) {
+            self.tasks.push(task::current());
+        }
+    }
+
+    /// Returns the error to report to the users of a connection that has been closed.
+    pub fn closed_error(&self) -> Option<io::Error> {
+        if let Some(ref error) = self.error {
+            Some(error.clone().into())
+        } else if self.connection.is_closed() {
+            Some(io::ErrorKind::ConnectionAborted.into())
+        } else {
+            None
+        }
+    }
+
+    fn notify_tasks(&mut self) {
+        for task in self.tasks.drain(..) {
+            task.notify();
+        }
+    }
+}
+
+/// Background task performing the I/O of an endpoint.
+struct EndpointDriver {
+    endpoint: Arc<Endpoint>,
+}
+
+impl Future for EndpointDriver {
+    type Item = ();
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<(), ()> {
+        let mut inner = self.endpoint.inner.lock();
+        inner.driver_task = Some(task::current());
+        let mut buf = vec![0; MAX_DATAGRAM_SIZE];
+
+        loop {
+            let now = Instant::now();
+
+            loop {
+                match inner.socket.poll_recv_from(&mut buf) {
+                    Ok(Async::Ready((len, from))) => {
+                        inner.handle_datagram(now, from, BytesMut::from(&buf[..len]))
+                    }
+                    Ok(Async::NotReady) => break,
+                    Err(err) => {
+                        // Errors can be caused by ICMP messages, and don't prevent receiving
+                        // further packets.
+                        debug!("Error while receiving QUIC packet: {}", err);
+                        break
+                    }
+                }
+            }
+
+            inner.handle_timers(now);
+            inner.process_connections();
+            inner.send_transmits(now);
+            // Sending packets updates the timers of the connections.
+            inner.process_connections();
+
+            if inner.handles == 0 && inner.connections.is_empty() {
+                return Ok(Async::Ready(()))
+            }
+
+            let deadline = match inner.next_deadline() {
+                Some(deadline) => deadline,
+                None => {
+                    inner.timer = None;
+                    return Ok(Async::NotReady)
+                }
+            };
+            if inner.timer.as_ref().map(|(current, _)| *current) != Some(deadline) {
+                inner.timer = Some((deadline, Delay::new(deadline)));
+            }
+            if let Some((_, ref mut delay)) = inner.timer {
+                match delay.poll() {
+                    Ok(Async::Ready(())) => {}
+                    Ok(Async::NotReady) => return Ok(Async::NotReady),
+                    Err(err) => {
+                        debug!("Error with the timer of a QUIC endpoint: {}", err);
+                        return Ok(Async::NotReady)
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,527 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Implementation of the libp2p `Transport` trait for QUIC.
+//!
+//! Uses [the *quinn-proto* library](https://github.com/djc/quinn) for the QUIC state machines
+//! and [the *tokio* library](https://tokio.rs) for the UDP sockets.
+//!
+//! Connections are encrypted with TLS 1.3, using certificates bound to the libp2p identities of
+//! the endpoints, and multiplexed with the native streams of QUIC. The output of the transport is
+//! therefore the `PeerId` of the remote and a `StreamMuxer`, and no further upgrade is necessary.
+//!
+//! # Usage
+//!
+//! Example:
+//!
+//! ```
+//! extern crate libp2p_quic;
+//! use libp2p_core::identity;
+//! use libp2p_quic::QuicConfig;
+//!
+//! # fn main() {
+//! let keypair = identity::Keypair::generate_ed25519();
+//! let quic = QuicConfig::new(&keypair);
+//! # }
+//! ```
+//!
+//! The `QuicConfig` structs implements the `Transport` trait of the `swarm` library. See the
+//! documentation of `swarm` and of libp2p in general to learn how to use the `Transport` trait.
+//!
+//! Background tasks performing the I/O of the sockets are spawned on the default executor, which
+//! means that the futures and streams of this transport must be run by a tokio runtime.
+
+mod endpoint;
+mod muxer;
+mod tls;
+
+pub use crate::muxer::{QuicMuxer, QuicOutboundSubstream, QuicSubstream};
+
+use crate::{endpoint::EndpointHandle, tls::Libp2pVerifier};
+use futures::{future::{self, FutureResult}, prelude::*};
+use get_if_addrs::{IfAddr, get_if_addrs};
+use ipnet::{IpNet, Ipv4Net, Ipv6Net};
+use libp2p_core::{
+    PeerId,
+    compat::Transport01,
+    identity,
+    multiaddr::{Protocol, Multiaddr},
+    transport::{ListenerEvent, TransportError}
+};
+use log::debug;
+use quinn_proto::{ClientConfig, ConnectionHandle, ServerConfig, TransportConfig};
+use std::{
+    collections::VecDeque,
+    fmt,
+    io,
+    iter::{self, FromIterator},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    sync::Arc
+};
+use tokio_udp::UdpSocket;
+
+/// Represents the configuration for a QUIC transport capability for libp2p.
+#[derive(Clone)]
+pub struct QuicConfig {
+    /// Identity of the local node, which the certificates of the endpoints are bound to.
+    keypair: identity::Keypair,
+    /// Interval at which to send keep-alive packets, in milliseconds.
+    keep_alive_interval: u32,
+    /// Duration of inactivity after which a connection is closed, in milliseconds.
+    idle_timeout: u64,
+}
+
+impl QuicConfig {
+    /// Creates a new configuration object for QUIC, using the given identity for the
+    /// handshakes.
+    pub fn new(keypair: &identity::Keypair) -> QuicConfig {
+        QuicConfig {
+            keypair: keypair.clone(),
+            keep_alive_interval: 5_000,
+            idle_timeout: 30_000,
+        }
+    }
+
+    /// Sets the interval at which keep-alive packets are sent, in milliseconds, or `0` to not
+    /// send any. Connections are kept alive as long as this is lower than the idle timeout.
+    pub fn keep_alive_interval(mut self, value: u32) -> Self {
+        self.keep_alive_interval = value;
+        self
+    }
+
+    /// Sets the duration of inactivity after which a connection is closed, in milliseconds, or
+    /// `0` to never close idle connections.
+    pub fn idle_timeout(mut self, value: u64) -> Self {
+        self.idle_timeout = value;
+        self
+    }
+
+    /// Builds the configuration of the QUIC connections.
+    fn transport_config(&self) -> Arc<TransportConfig> {
+        Arc::new(TransportConfig {
+            // We only ever use bidirectional streams.
+            stream_window_uni: 0,
+            keep_alive_interval: self.keep_alive_interval,
+            idle_timeout: self.idle_timeout,
+            ..TransportConfig::default()
+        })
+    }
+}
+
+impl fmt::Debug for QuicConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("QuicConfig")
+            .field("keep_alive_interval", &self.keep_alive_interval)
+            .field("idle_timeout", &self.idle_timeout)
+            .finish()
+    }
+}
+
+impl Transport01 for QuicConfig {
+    type Output = (PeerId, QuicMuxer);
+    type Error = io::Error;
+    type Listener = QuicListenStream;
+    type ListenerUpgrade = FutureResult<Self::Output, Self::Error>;
+    type Dial = QuicDialFut;
+
+    fn listen_on(self, addr: Multiaddr) -> Result<Self::Listener, TransportError<Self::Error>> {
+        let socket_addr =
+            if let Ok(sa) = multiaddr_to_socketaddr(&addr) {
+                sa
+            } else {
+                return Err(TransportError::MultiaddrNotSupported(addr))
+            };
+
+        let socket = UdpSocket::bind(&socket_addr).map_err(TransportError::Other)?;
+        let local_addr = socket.local_addr().map_err(TransportError::Other)?;
+        let port = local_addr.port();
+
+        let (certificate, key) = tls::make_certificate(&self.keypair)
+            .map_err(TransportError::Other)?;
+        let verifier = Arc::new(Libp2pVerifier::default());
+        let crypto = tls::make_server_config(certificate, key, verifier.clone())
+            .map_err(|err| TransportError::Other(io::Error::new(io::ErrorKind::Other, err)))?;
+        let server_config = ServerConfig {
+            transport: self.transport_config(),
+            crypto: Arc::new(crypto),
+            ..ServerConfig::default()
+        };
+        let endpoint = EndpointHandle::new(socket, Some(server_config), verifier)
+            .map_err(TransportError::Other)?;
+
+        // Determine all our listen addresses which is either a single local IP address
+        // or (if a wildcard IP address was used) the addresses of all our interfaces,
+        // as reported by `get_if_addrs`.
+        let addrs =
+            if socket_addr.ip().is_unspecified() {
+                let addrs = host_addresses(port).map_err(TransportError::Other)?;
+                debug!("Listening on {:?}", addrs.iter().map(|(_, _, ma)| ma).collect::<Vec<_>>());
+                Addresses::Many(addrs)
+            } else {
+                let ma = ip_to_multiaddr(local_addr.ip(), port);
+                debug!("Listening on {:?}", ma);
+                Addresses::One(ma)
+            };
+
+        let events = match addrs {
+            Addresses::One(ref ma) => iter::once(ListenerEvent::NewAddress(ma.clone())).collect(),
+            Addresses::Many(ref aa) => aa.iter()
+                .map(|(_, _, ma)| ListenerEvent::NewAddress(ma.clone()))
+                .collect()
+        };
+
+        Ok(QuicListenStream { endpoint, addrs, pending: events })
+    }
+
+    fn dial(self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
+        let socket_addr =
+            if let Ok(socket_addr) = multiaddr_to_socketaddr(&addr) {
+                if socket_addr.port() == 0 || socket_addr.ip().is_unspecified() {
+                    debug!("Instantly refusing dialing {}, as it is invalid", addr);
+                    return Err(TransportError::Other(io::ErrorKind::ConnectionRefused.into()))
+                }
+                socket_addr
+            } else {
+                return Err(TransportError::MultiaddrNotSupported(addr))
+            };
+
+        debug!("Dialing {}", addr);
+
+        // Every dial uses its own endpoint, bound to an ephemeral port.
+        let bind_addr = match socket_addr {
+            SocketAddr::V4(_) => SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), 0),
+            SocketAddr::V6(_) => SocketAddr::new(Ipv6Addr::UNSPECIFIED.into(), 0),
+        };
+        let socket = UdpSocket::bind(&bind_addr).map_err(TransportError::Other)?;
+
+        let (certificate, key) = tls::make_certificate(&self.keypair)
+            .map_err(TransportError::Other)?;
+        let verifier = Arc::new(Libp2pVerifier::default());
+        let client_config = ClientConfig {
+            transport: self.transport_config(),
+            crypto: Arc::new(tls::make_client_config(certificate, key, verifier.clone())),
+            log: None,
+        };
+        let endpoint = EndpointHandle::new(socket, None, verifier).map_err(TransportError::Other)?;
+        let (handle, id) = endpoint.lock().connect(client_config, socket_addr)
+            .map_err(TransportError::Other)?;
+
+        Ok(QuicDialFut { endpoint: Some(endpoint), handle, id })
+    }
+
+    /// The QUIC handshake always has the dialer act as the client, so dialing as the listener
+    /// isn't supported.
+    fn dial_as_listener(self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
+        debug!("Refusing to dial {} as the listener over QUIC", addr);
+        Err(TransportError::MultiaddrNotSupported(addr))
+    }
+}
+
+/// Future that dials a QUIC address.
+#[must_use = "futures do nothing unless polled"]
+pub struct QuicDialFut {
+    /// The endpoint of the connection, or `None` if the future has finished.
+    endpoint: Option<EndpointHandle>,
+    handle: ConnectionHandle,
+    id: u64,
+}
+
+impl Future for QuicDialFut {
+    type Item = (PeerId, QuicMuxer);
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        let peer_id = {
+            let endpoint = self.endpoint.as_ref().expect("future polled after completion");
+            let mut inner = endpoint.drive_and_lock()?;
+            let connection = inner.connection(self.handle, self.id)?;
+            if let Some(err) = connection.closed_error() {
+                debug!("Error while dialing => {:?}", err);
+                return Err(err)
+            }
+            if !connection.established {
+                connection.register_task();
+                return Ok(Async::NotReady)
+            }
+            connection.peer_id.clone().ok_or_else(|| {
+                io::Error::new(io::ErrorKind::Other, "remote didn't present a certificate")
+            })?
+        };
+
+        let endpoint = self.endpoint.take().expect("future polled after completion");
+        Ok(Async::Ready((peer_id, QuicMuxer::new(endpoint, self.handle, self.id))))
+    }
+}
+
+impl Drop for QuicDialFut {
+    fn drop(&mut self) {
+        if let Some(ref endpoint) = self.endpoint {
+            let mut inner = endpoint.lock();
+            if let Ok(connection) = inner.connection(self.handle, self.id) {
+                connection.connection.close(wasm_timer::Instant::now(), 0, Default::default());
+            }
+        }
+    }
+}
+
+impl fmt::Debug for QuicDialFut {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("QuicDialFut").field("id", &self.id).finish()
+    }
+}
+
+/// Stream of `ListenerEvent`s.
+pub struct QuicListenStream {
+    /// The endpoint of the listener.
+    endpoint: EndpointHandle,
+    /// The addresses we are listening on.
+    addrs: Addresses,
+    /// Events to report before accepting connections.
+    pending: VecDeque<ListenerEvent<FutureResult<(PeerId, QuicMuxer), io::Error>>>,
+}
+
+impl Stream for QuicListenStream {
+    type Item = ListenerEvent<FutureResult<(PeerId, QuicMuxer), io::Error>>;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        if let Some(event) = self.pending.pop_front() {
+            return Ok(Async::Ready(Some(event)))
+        }
+
+        let (handle, id, peer_id, remote) = match self.endpoint.drive_and_lock()?.poll_incoming() {
+            Some(incoming) => incoming,
+            None => return Ok(Async::NotReady)
+        };
+
+        let listen_addr = match self.addrs {
+            Addresses::One(ref ma) => ma.clone(),
+            Addresses::Many(ref addrs) => {
+                // Pick the address of the interface whose network contains the remote, or any
+                // address of the same family otherwise.
+                addrs.iter()
+                    .find(|(_, net, _)| net.contains(&remote.ip()))
+                    .or_else(|| addrs.iter().find(|(ip, _, _)| ip.is_ipv4() == remote.is_ipv4()))
+                    .map(|(_, _, ma)| ma.clone())
+                    .unwrap_or_else(|| ip_to_multiaddr(remote.ip(), 0))
+            }
+        };
+
+        debug!("Incoming QUIC connection from {} at {}", remote, listen_addr);
+        let muxer = QuicMuxer::new(self.endpoint.clone(), handle, id);
+        Ok(Async::Ready(Some(ListenerEvent::Upgrade {
+            upgrade: future::ok((peer_id, muxer)),
+            listen_addr,
+            remote_addr: ip_to_multiaddr(remote.ip(), remote.port()),
+        })))
+    }
+}
+
+impl Drop for QuicListenStream {
+    fn drop(&mut self) {
+        self.endpoint.lock().stop_listening();
+    }
+}
+
+impl fmt::Debug for QuicListenStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("QuicListenStream").field("addrs", &self.addrs).finish()
+    }
+}
+
+/// Listen address information.
+#[derive(Debug)]
+enum Addresses {
+    /// A specific address is used to listen.
+    One(Multiaddr),
+    /// A set of addresses is used to listen.
+    Many(Vec<(IpAddr, IpNet, Multiaddr)>)
+}
+
+// This type of logic should probably be moved into the multiaddr package
+fn multiaddr_to_socketaddr(addr: &Multiaddr) -> Result<SocketAddr, ()> {
+    let mut iter = addr.iter();
+    let proto1 = iter.next().ok_or(())?;
+    let proto2 = iter.next().ok_or(())?;
+    let proto3 = iter.next().ok_or(())?;
+
+    if iter.next().is_some() {
+        return Err(());
+    }
+
+    match (proto1, proto2, proto3) {
+        (Protocol::Ip4(ip), Protocol::Udp(port), Protocol::Quic) => Ok(SocketAddr::new(ip.into(), port)),
+        (Protocol::Ip6(ip), Protocol::Udp(port), Protocol::Quic) => Ok(SocketAddr::new(ip.into(), port)),
+        _ => Err(()),
+    }
+}
+
+// Create a [`Multiaddr`] from the given IP address and port number.
+fn ip_to_multiaddr(ip: IpAddr, port: u16) -> Multiaddr {
+    let proto = match ip {
+        IpAddr::V4(ip) => Protocol::Ip4(ip),
+        IpAddr::V6(ip) => Protocol::Ip6(ip)
+    };
+    let it = iter::once(proto)
+        .chain(iter::once(Protocol::Udp(port)))
+        .chain(iter::once(Protocol::Quic));
+    Multiaddr::from_iter(it)
+}
+
+// Collect all local host addresses and use the provided port number as listen port.
+fn host_addresses(port: u16) -> io::Result<Vec<(IpAddr, IpNet, Multiaddr)>> {
+    let mut addrs = Vec::new();
+    for iface in get_if_addrs()? {
+        let ip = iface.ip();
+        let ma = ip_to_multiaddr(ip, port);
+        let ipn = match iface.addr {
+            IfAddr::V4(ip4) => {
+                let prefix_len = (!u32::from_be_bytes(ip4.netmask.octets())).leading_zeros();
+                let ipnet = Ipv4Net::new(ip4.ip, prefix_len as u8)
+                    .expect("prefix_len is the number of bits in a u32, so can not exceed 32");
+                IpNet::V4(ipnet)
+            }
+            IfAddr::V6(ip6) => {
+                let prefix_len = (!u128::from_be_bytes(ip6.netmask.octets())).leading_zeros();
+                let ipnet = Ipv6Net::new(ip6.ip, prefix_len as u8)
+                    .expect("prefix_len is the number of bits in a u128, so can not exceed 128");
+                IpNet::V6(ipnet)
+            }
+        };
+        addrs.push((ip, ipn, ma))
+    }
+    Ok(addrs)
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::prelude::*;
+    use libp2p_core::{compat::{Compat03As01, Transport01}, identity, muxing, multiaddr::{Multiaddr, Protocol}};
+    use std::{net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr}, sync::Arc};
+    use super::{multiaddr_to_socketaddr, QuicConfig};
+    use tokio::runtime::Runtime;
+    use tokio_io::io::{read_exact, shutdown, write_all};
+
+    #[test]
+    fn multiaddr_to_quic_conversion() {
+        assert!(
+            multiaddr_to_socketaddr(&"/ip4/127.0.0.1/udp/1234".parse::<Multiaddr>().unwrap())
+                .is_err()
+        );
+        assert!(
+            multiaddr_to_socketaddr(&"/ip4/127.0.0.1/tcp/1234/quic".parse::<Multiaddr>().unwrap())
+                .is_err()
+        );
+
+        assert_eq!(
+            multiaddr_to_socketaddr(&"/ip4/127.0.0.1/udp/12345/quic".parse::<Multiaddr>().unwrap()),
+            Ok(SocketAddr::new(
+                IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
+                12345,
+            ))
+        );
+        assert_eq!(
+            multiaddr_to_socketaddr(&"/ip6/::1/udp/12345/quic".parse::<Multiaddr>().unwrap()),
+            Ok(SocketAddr::new(
+                IpAddr::V6(Ipv6Addr::new(0, 0, 0, 0, 0, 0, 0, 1)),
+                12345,
+            ))
+        );
+    }
+
+    #[test]
+    fn communicating_between_dialer_and_listener() {
+        let listener_keys = identity::Keypair::generate_ed25519();
+        let listener_id = listener_keys.public().into_peer_id();
+        let dialer_keys = identity::Keypair::generate_ed25519();
+        let dialer_id = dialer_keys.public().into_peer_id();
+
+        // Large enough to require the flow control windows to be extended.
+        let request = (0 .. 4 * 1024 * 1024).map(|_| rand::random::<u8>()).collect::<Vec<_>>();
+        let expected_request = request.clone();
+
+        let mut listener = QuicConfig::new(&listener_keys)
+            .listen_on("/ip4/127.0.0.1/udp/0/quic".parse().unwrap())
+            .unwrap();
+        let addr = listener.by_ref()
+            .wait()
+            .next()
+            .expect("some event")
+            .expect("no error")
+            .into_new_address()
+            .expect("listen address");
+        match addr.iter().nth(1) {
+            Some(Protocol::Udp(port)) => assert_ne!(port, 0),
+            _ => panic!("No UDP port in address: {}", addr)
+        }
+
+        let server = listener
+            .filter_map(|event| event.into_upgrade())
+            .into_future()
+            .map_err(|(err, _)| err)
+            .and_then(|(upgrade, _)| upgrade.expect("listener stopped").0)
+            .and_then(move |(peer_id, muxer)| {
+                assert_eq!(peer_id, dialer_id);
+                let muxer = Arc::new(muxer);
+                Compat03As01::new(muxing::inbound_from_ref_and_wrap(muxer))
+            })
+            .and_then(move |substream| {
+                read_exact(substream, vec![0; expected_request.len()])
+                    .map(move |(substream, request)| {
+                        assert_eq!(request, expected_request);
+                        substream
+                    })
+            })
+            .and_then(|substream| write_all(substream, b"pong"))
+            .and_then(|(substream, _)| shutdown(substream))
+            // Wait for the dialer to close the substream.
+            .and_then(|substream| read_exact(substream, [0; 1]).then(|_| Ok(())));
+
+        let client = QuicConfig::new(&dialer_keys)
+            .dial(addr)
+            .unwrap()
+            .and_then(move |(peer_id, muxer)| {
+                assert_eq!(peer_id, listener_id);
+                let muxer = Arc::new(muxer);
+                Compat03As01::new(muxing::outbound_from_ref_and_wrap(muxer))
+            })
+            .and_then(|substream| write_all(substream, request))
+            .and_then(|(substream, _)| read_exact(substream, [0; 4]))
+            .map(|(_, response)| assert_eq!(&response, b"pong"));
+
+        let mut runtime = Runtime::new().unwrap();
+        runtime.block_on(server.join(client)).unwrap();
+    }
+
+    #[test]
+    fn dialing_closed_port_fails() {
+        let keys = identity::Keypair::generate_ed25519();
+        let (_, port) = {
+            let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+            let addr = socket.local_addr().unwrap();
+            (socket, addr.port())
+        };
+        let addr = format!("/ip4/127.0.0.1/udp/{}/quic", port).parse().unwrap();
+        let dial = QuicConfig::new(&keys).idle_timeout(1_000).dial(addr).unwrap();
+
+        let mut runtime = Runtime::new().unwrap();
+        assert!(runtime.block_on(dial).is_err());
+    }
+}
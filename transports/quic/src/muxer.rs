@@ -0,0 +1,244 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use crate::endpoint::EndpointHandle;
+use futures::prelude::*;
+use libp2p_core::compat::StreamMuxer01;
+use quinn_proto::{ConnectionHandle, Directionality, ReadError, StreamId, WriteError};
+use std::{fmt, io};
+use wasm_timer::Instant;
+
+/// Multiplexes substreams over a QUIC connection, using the native streams of QUIC.
+pub struct QuicMuxer {
+    endpoint: EndpointHandle,
+    handle: ConnectionHandle,
+    id: u64,
+}
+
+impl QuicMuxer {
+    pub(crate) fn new(endpoint: EndpointHandle, handle: ConnectionHandle, id: u64) -> Self {
+        QuicMuxer { endpoint, handle, id }
+    }
+}
+
+impl fmt::Debug for QuicMuxer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("QuicMuxer").field("id", &self.id).finish()
+    }
+}
+
+/// A bidirectional QUIC stream.
+#[derive(Debug)]
+pub struct QuicSubstream {
+    id: StreamId,
+    /// Whether the remote has finished or reset its side of the stream.
+    read_closed: bool,
+    /// Whether we have finished our side of the stream, or the remote asked us to stop sending.
+    write_closed: bool,
+}
+
+impl QuicSubstream {
+    fn new(id: StreamId) -> Self {
+        QuicSubstream { id, read_closed: false, write_closed: false }
+    }
+}
+
+/// Substream being opened.
+#[derive(Debug)]
+pub struct QuicOutboundSubstream(());
+
+impl StreamMuxer01 for QuicMuxer {
+    type Substream = QuicSubstream;
+    type OutboundSubstream = QuicOutboundSubstream;
+    type Error = io::Error;
+
+    fn poll_inbound(&self) -> Poll<Self::Substream, Self::Error> {
+        let mut inner = self.endpoint.lock();
+        let connection = inner.connection(self.handle, self.id)?;
+        // We don't allow the remote to open unidirectional streams.
+        if let Some(id) = connection.connection.accept() {
+            inner.wake_driver();
+            return Ok(Async::Ready(QuicSubstream::new(id)))
+        }
+        if let Some(err) = connection.closed_error() {
+            return Err(err)
+        }
+        connection.register_task();
+        Ok(Async::NotReady)
+    }
+
+    fn open_outbound(&self) -> Self::OutboundSubstream {
+        QuicOutboundSubstream(())
+    }
+
+    fn poll_outbound(&self, _: &mut Self::OutboundSubstream) -> Poll<Self::Substream, Self::Error> {
+        let mut inner = self.endpoint.lock();
+        let connection = inner.connection(self.handle, self.id)?;
+        if let Some(err) = connection.closed_error() {
+            return Err(err)
+        }
+        if let Some(id) = connection.connection.open(Directionality::Bi) {
+            return Ok(Async::Ready(QuicSubstream::new(id)))
+        }
+        connection.register_task();
+        Ok(Async::NotReady)
+    }
+
+    fn destroy_outbound(&self, _: Self::OutboundSubstream) {
+    }
+
+    fn read_substream(&self, substream: &mut Self::Substream, buf: &mut [u8])
+        -> Poll<usize, Self::Error>
+    {
+        if substream.read_closed {
+            return Ok(Async::Ready(0))
+        }
+
+        let mut inner = self.endpoint.lock();
+        let connection = inner.connection(self.handle, self.id)?;
+        match connection.connection.read(substream.id, buf) {
+            Ok(len) => {
+                // Reading gives credit to the remote, which we have to send.
+                inner.wake_driver();
+                Ok(Async::Ready(len))
+            }
+            Err(ReadError::Blocked) => {
+                if let Some(err) = connection.closed_error() {
+                    return Err(err)
+                }
+                connection.register_task();
+                Ok(Async::NotReady)
+            }
+            Err(ReadError::Finished) => {
+                substream.read_closed = true;
+                Ok(Async::Ready(0))
+            }
+            Err(ReadError::Reset { .. }) => {
+                substream.read_closed = true;
+                Err(io::ErrorKind::ConnectionReset.into())
+            }
+            Err(err @ ReadError::UnknownStream) => Err(io::Error::new(io::ErrorKind::Other, err))
+        }
+    }
+
+    fn write_substream(&self, substream: &mut Self::Substream, buf: &[u8])
+        -> Poll<usize, Self::Error>
+    {
+        if substream.write_closed {
+            return Err(io::ErrorKind::BrokenPipe.into())
+        }
+
+        let mut inner = self.endpoint.lock();
+        let connection = inner.connection(self.handle, self.id)?;
+        match connection.connection.write(substream.id, buf) {
+            Ok(len) => {
+                inner.wake_driver();
+                Ok(Async::Ready(len))
+            }
+            Err(WriteError::Blocked) => {
+                if let Some(err) = connection.closed_error() {
+                    return Err(err)
+                }
+                connection.register_task();
+                Ok(Async::NotReady)
+            }
+            Err(WriteError::Stopped { .. }) => {
+                substream.write_closed = true;
+                Err(io::ErrorKind::BrokenPipe.into())
+            }
+            Err(err @ WriteError::UnknownStream) => Err(io::Error::new(io::ErrorKind::Other, err))
+        }
+    }
+
+    fn flush_substream(&self, _: &mut Self::Substream) -> Poll<(), Self::Error> {
+        Ok(Async::Ready(()))
+    }
+
+    fn shutdown_substream(&self, substream: &mut Self::Substream) -> Poll<(), Self::Error> {
+        if substream.write_closed {
+            return Ok(Async::Ready(()))
+        }
+
+        let mut inner = self.endpoint.lock();
+        let connection = inner.connection(self.handle, self.id)?;
+        // Finishing a stream that the remote asked us to stop sending on panics, and an empty
+        // write is the only way to find out whether that is the case.
+        match connection.connection.write(substream.id, &[]) {
+            Ok(_) => {
+                connection.connection.finish(substream.id);
+                substream.write_closed = true;
+                inner.wake_driver();
+                Ok(Async::Ready(()))
+            }
+            Err(WriteError::Blocked) => {
+                if let Some(err) = connection.closed_error() {
+                    return Err(err)
+                }
+                connection.register_task();
+                Ok(Async::NotReady)
+            }
+            Err(WriteError::Stopped { .. }) => {
+                substream.write_closed = true;
+                Ok(Async::Ready(()))
+            }
+            Err(err @ WriteError::UnknownStream) => Err(io::Error::new(io::ErrorKind::Other, err))
+        }
+    }
+
+    fn destroy_substream(&self, substream: Self::Substream) {
+        let mut inner = self.endpoint.lock();
+        if let Ok(connection) = inner.connection(self.handle, self.id) {
+            if !substream.write_closed {
+                connection.connection.reset(substream.id, 0);
+            }
+            if !substream.read_closed {
+                connection.connection.stop_sending(substream.id, 0);
+            }
+            inner.wake_driver();
+        }
+    }
+
+    fn is_remote_acknowledged(&self) -> bool {
+        // The muxer is only created once the handshake has succeeded.
+        true
+    }
+
+    fn close(&self) -> Poll<(), Self::Error> {
+        let mut inner = self.endpoint.lock();
+        if let Ok(connection) = inner.connection(self.handle, self.id) {
+            connection.connection.close(Instant::now(), 0, Default::default());
+            inner.wake_driver();
+        }
+        Ok(Async::Ready(()))
+    }
+
+    fn flush_all(&self) -> Poll<(), Self::Error> {
+        Ok(Async::Ready(()))
+    }
+}
+
+impl Drop for QuicMuxer {
+    fn drop(&mut self) {
+        let mut inner = self.endpoint.lock();
+        if let Ok(connection) = inner.connection(self.handle, self.id) {
+            connection.connection.close(Instant::now(), 0, Default::default());
+        }
+    }
+}
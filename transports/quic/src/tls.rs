@@ -0,0 +1,117 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! TLS 1.3 configuration binding the QUIC handshake to libp2p identities.
+//!
+//! The certificates are the ones of the `/tls/1.0.0` upgrade, generated and verified by
+//! `libp2p_tls::certificate`.
+
+use libp2p_core::{identity, PeerId};
+use parking_lot::Mutex;
+use rustls::{
+    Certificate, ClientCertVerified, ClientCertVerifier, DistinguishedNames, PrivateKey,
+    ProtocolVersion, RootCertStore, ServerCertVerified, ServerCertVerifier, TLSError
+};
+use std::{io, sync::Arc};
+
+/// ALPN protocol negotiated on every connection.
+const LIBP2P_ALPN: &[u8] = b"libp2p";
+
+/// Generates a self-signed certificate bound to the given identity, and its private key.
+pub fn make_certificate(keypair: &identity::Keypair) -> Result<(Certificate, PrivateKey), io::Error> {
+    libp2p_tls::certificate::generate(keypair)
+        .map_err(|err| io::Error::new(io::ErrorKind::Other, err))
+}
+
+/// Builds the TLS configuration of a dialing endpoint.
+pub fn make_client_config(
+    certificate: Certificate,
+    key: PrivateKey,
+    verifier: Arc<Libp2pVerifier>
+) -> rustls::ClientConfig {
+    let mut config = rustls::ClientConfig::new();
+    config.versions = vec![ProtocolVersion::TLSv1_3];
+    config.set_protocols(&[LIBP2P_ALPN.to_vec()]);
+    config.set_single_client_cert(vec![certificate], key);
+    config.dangerous().set_certificate_verifier(verifier);
+    config
+}
+
+/// Builds the TLS configuration of a listening endpoint.
+pub fn make_server_config(
+    certificate: Certificate,
+    key: PrivateKey,
+    verifier: Arc<Libp2pVerifier>
+) -> Result<rustls::ServerConfig, TLSError> {
+    let mut config = rustls::ServerConfig::new(verifier);
+    config.versions = vec![ProtocolVersion::TLSv1_3];
+    config.set_protocols(&[LIBP2P_ALPN.to_vec()]);
+    config.set_single_cert(vec![certificate], key)?;
+    Ok(config)
+}
+
+/// Certificate verifier accepting any certificate that is correctly bound to a libp2p identity.
+///
+/// Verifiers have no way to know which connection they are verifying a certificate for. Since
+/// the TLS handshakes of an endpoint all progress while processing incoming packets, the
+/// identity of the remote is instead stored in the verifier, and must be retrieved with
+/// `take_verified` right after the packet that triggered the verification has been handled.
+#[derive(Default)]
+pub struct Libp2pVerifier {
+    verified: Mutex<Option<PeerId>>,
+}
+
+impl Libp2pVerifier {
+    /// Returns the identity of the last remote whose certificate has been verified, if it hasn't
+    /// been retrieved yet.
+    pub fn take_verified(&self) -> Option<PeerId> {
+        self.verified.lock().take()
+    }
+
+    fn verify(&self, presented_certs: &[Certificate]) -> Result<(), TLSError> {
+        let peer_id = libp2p_tls::certificate::verify(presented_certs)?;
+        *self.verified.lock() = Some(peer_id);
+        Ok(())
+    }
+}
+
+impl ServerCertVerifier for Libp2pVerifier {
+    fn verify_server_cert(
+        &self,
+        _: &RootCertStore,
+        presented_certs: &[Certificate],
+        _: webpki::DNSNameRef<'_>,
+        _: &[u8]
+    ) -> Result<ServerCertVerified, TLSError> {
+        self.verify(presented_certs).map(|()| ServerCertVerified::assertion())
+    }
+}
+
+impl ClientCertVerifier for Libp2pVerifier {
+    fn client_auth_root_subjects(&self) -> DistinguishedNames {
+        DistinguishedNames::new()
+    }
+
+    fn verify_client_cert(&self, presented_certs: &[Certificate])
+        -> Result<ClientCertVerified, TLSError>
+    {
+        self.verify(presented_certs).map(|()| ClientCertVerified::assertion())
+    }
+}
@@ -18,13 +18,24 @@
 // FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // DEALINGS IN THE SOFTWARE.
 
+pub mod limiter;
+
+pub use limiter::{Limit, Rate, RateLimiter};
+
 use aio_limited::{Limited, Limiter};
 use futures::prelude::*;
 use futures::try_ready;
-use libp2p_core::{Multiaddr, Transport, transport::{ListenerEvent, TransportError}};
+use futures03::FutureExt as _;
+use libp2p_core::{
+    Executor,
+    Multiaddr,
+    Transport,
+    compat::{Compat01As03, Compat03As01, Transport01},
+    transport::{ListenerEvent, TransportError}
+};
 use log::error;
 use std::{error, fmt, io};
-use tokio_executor::Executor;
+use tokio_executor::SpawnError;
 use tokio_io::{AsyncRead, AsyncWrite, io::{ReadHalf, WriteHalf}};
 
 #[derive(Clone)]
@@ -35,19 +46,24 @@ pub struct RateLimited<T> {
 }
 
 impl<T> RateLimited<T> {
-    pub fn new<E: Executor>(
-        e: &mut E,
+    /// Wraps `value` and limits its transfer rates, in bytes per second.
+    ///
+    /// The executor runs the timers that periodically replenish the limits. It must accept the
+    /// tasks, which means that an `InlineExecutor` results in an error.
+    pub fn new<E: Executor + ?Sized>(
+        e: &E,
         value: T,
         max_read: usize,
         max_write: usize,
     ) -> io::Result<RateLimited<T>> {
+        let mut e = TokioCompat(e);
         Ok(RateLimited {
             value,
-            rlimiter: Limiter::new(e, max_read).map_err(|e| {
+            rlimiter: Limiter::new(&mut e, max_read).map_err(|e| {
                 error!("failed to create read limiter: {}", e);
                 io::Error::new(io::ErrorKind::Other, e)
             })?,
-            wlimiter: Limiter::new(e, max_write).map_err(|e| {
+            wlimiter: Limiter::new(&mut e, max_write).map_err(|e| {
                 error!("failed to create write limiter: {}", e);
                 io::Error::new(io::ErrorKind::Other, e)
             })?,
@@ -63,6 +79,16 @@ impl<T> RateLimited<T> {
     }
 }
 
+/// Adapts an `Executor` to the executor trait of tokio, used by the limiters.
+struct TokioCompat<'a, E: ?Sized>(&'a E);
+
+impl<E: Executor + ?Sized> tokio_executor::Executor for TokioCompat<'_, E> {
+    fn spawn(&mut self, future: Box<dyn Future<Item = (), Error = ()> + Send>) -> Result<(), SpawnError> {
+        let future = Compat01As03::new(future).map(|_| ()).boxed();
+        self.0.exec(future).map_err(|_| SpawnError::shutdown())
+    }
+}
+
 /// Error that can be generated by the rate limiter.
 #[derive(Debug)]
 pub enum RateLimitedErr<TErr> {
@@ -148,9 +174,9 @@ impl<C: AsyncRead + AsyncWrite> AsyncWrite for Connection<C> {
     }
 }
 
-pub struct Listener<T: Transport>(RateLimited<T::Listener>);
+pub struct Listener<T: Transport01>(RateLimited<T::Listener>);
 
-impl<T: Transport> Stream for Listener<T> {
+impl<T: Transport01> Stream for Listener<T> {
     type Item = ListenerEvent<ListenerUpgrade<T>>;
     type Error = RateLimitedErr<T::Error>;
 
@@ -170,11 +196,11 @@ impl<T: Transport> Stream for Listener<T> {
 }
 
 #[must_use = "futures do nothing unless polled"]
-pub struct ListenerUpgrade<T: Transport>(RateLimited<T::ListenerUpgrade>);
+pub struct ListenerUpgrade<T: Transport01>(RateLimited<T::ListenerUpgrade>);
 
 impl<T> Future for ListenerUpgrade<T>
 where
-    T: Transport,
+    T: Transport01,
     T::Output: AsyncRead + AsyncWrite
 {
     type Item = Connection<T::Output>;
@@ -188,7 +214,7 @@ where
     }
 }
 
-impl<T> Transport for RateLimited<T>
+impl<T> Transport01 for RateLimited<T>
 where
     T: Transport,
     T::Output: AsyncRead + AsyncWrite,
@@ -196,15 +222,14 @@ where
 {
     type Output = Connection<T::Output>;
     type Error = RateLimitedErr<T::Error>;
-    type Listener = Listener<T>;
-    type ListenerUpgrade = ListenerUpgrade<T>;
-    type Dial = DialFuture<T::Dial>;
+    type Listener = Listener<Compat03As01<T>>;
+    type ListenerUpgrade = ListenerUpgrade<Compat03As01<T>>;
+    type Dial = DialFuture<<Compat03As01<T> as Transport01>::Dial>;
 
     fn listen_on(self, addr: Multiaddr) -> Result<Self::Listener, TransportError<Self::Error>> {
         let r = self.rlimiter;
         let w = self.wlimiter;
-        self.value
-            .listen_on(addr)
+        Transport01::listen_on(Compat03As01::new(self.value), addr)
             .map_err(|err| err.map(RateLimitedErr::Underlying))
             .map(|listener| {
                 Listener(RateLimited::from_parts(listener, r.clone(), w.clone()))
@@ -214,7 +239,16 @@ where
     fn dial(self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
         let r = self.rlimiter;
         let w = self.wlimiter;
-        let dial = self.value.dial(addr).map_err(|err| err.map(RateLimitedErr::Underlying))?;
+        let dial = Transport01::dial(Compat03As01::new(self.value), addr)
+            .map_err(|err| err.map(RateLimitedErr::Underlying))?;
+        Ok(DialFuture { r, w, f: dial })
+    }
+
+    fn dial_as_listener(self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
+        let r = self.rlimiter;
+        let w = self.wlimiter;
+        let dial = Transport01::dial_as_listener(Compat03As01::new(self.value), addr)
+            .map_err(|err| err.map(RateLimitedErr::Underlying))?;
         Ok(DialFuture { r, w, f: dial })
     }
 }
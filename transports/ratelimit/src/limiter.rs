@@ -0,0 +1,612 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Rate limiting of the substreams per peer, per protocol, and globally.
+//!
+//! Contrary to [`RateLimited`](crate::RateLimited), which limits the traffic of whole
+//! connections, a [`RateLimiter`] is attached to a swarm with
+//! [`SwarmBuilder::observer`](libp2p_swarm::SwarmBuilder::observer), and limits the traffic of each
+//! substream according to the remote peer and to the protocol negotiated on the substream.
+//!
+//! The limits form a hierarchy of token buckets. A substream can only transfer data if the global
+//! bucket, the bucket of its peer and the bucket of its protocol with that peer all allow it. A
+//! throttled substream is not read from or written to until enough tokens are available, which
+//! pushes back on the remote through the flow control of the multiplexer or of the transport,
+//! and no data is ever dropped.
+//!
+//! In addition to bytes, the number of inbound substreams can be limited, which caps the rate of
+//! requests of protocols that open a substream per request, such as Kademlia. The data of an
+//! inbound substream beyond that rate is not read until the limit allows it.
+//!
+//! The limits can be changed at any time, through any clone of the `RateLimiter`.
+
+use futures::prelude::*;
+use libp2p_core::{ConnectedPoint, Endpoint, PeerId, clock, muxing::SubstreamMeter};
+use libp2p_swarm::SwarmObserver;
+use log::warn;
+use parking_lot::Mutex;
+use std::{cmp, collections::HashMap, sync::Arc, time::Duration};
+use wasm_timer::{Delay, Instant};
+
+/// A number of units per second, with the number of units that can be used at once.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Rate {
+    per_second: u32,
+    burst: u32,
+}
+
+impl Rate {
+    /// Creates a rate of the given number of units per second, which is also the burst.
+    ///
+    /// A rate of zero is treated as a rate of one.
+    pub fn new(per_second: u32) -> Self {
+        let per_second = cmp::max(per_second, 1);
+        Rate { per_second, burst: per_second }
+    }
+
+    /// Sets the number of units that can be used at once after a period of inactivity.
+    pub fn with_burst(mut self, burst: u32) -> Self {
+        self.burst = cmp::max(burst, 1);
+        self
+    }
+
+    /// Returns the number of units per second.
+    pub fn per_second(&self) -> u32 {
+        self.per_second
+    }
+
+    /// Returns the number of units that can be used at once.
+    pub fn burst(&self) -> u32 {
+        self.burst
+    }
+}
+
+/// The limits of a level of the hierarchy. Everything is unlimited by default.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct Limit {
+    inbound: Option<Rate>,
+    outbound: Option<Rate>,
+    inbound_substreams: Option<Rate>,
+}
+
+impl Limit {
+    /// Creates limits that don't limit anything.
+    pub fn unlimited() -> Self {
+        Limit::default()
+    }
+
+    /// Limits the number of bytes read per second.
+    pub fn with_inbound_bytes(mut self, rate: Rate) -> Self {
+        self.inbound = Some(rate);
+        self
+    }
+
+    /// Limits the number of bytes written per second.
+    pub fn with_outbound_bytes(mut self, rate: Rate) -> Self {
+        self.outbound = Some(rate);
+        self
+    }
+
+    /// Limits the number of substreams opened by the remote per second.
+    pub fn with_inbound_substreams(mut self, rate: Rate) -> Self {
+        self.inbound_substreams = Some(rate);
+        self
+    }
+
+    /// Returns the limit on the number of bytes read per second, if any.
+    pub fn inbound_bytes(&self) -> Option<Rate> {
+        self.inbound
+    }
+
+    /// Returns the limit on the number of bytes written per second, if any.
+    pub fn outbound_bytes(&self) -> Option<Rate> {
+        self.outbound
+    }
+
+    /// Returns the limit on the number of substreams opened by the remote per second, if any.
+    pub fn inbound_substreams(&self) -> Option<Rate> {
+        self.inbound_substreams
+    }
+}
+
+/// Limits the traffic of the substreams of a swarm. See the [module-level
+/// documentation](self).
+///
+/// Cloning a `RateLimiter` yields a handle to the same limits.
+#[derive(Debug, Clone, Default)]
+pub struct RateLimiter {
+    inner: Arc<Mutex<Inner>>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    /// The buckets shared by all the substreams.
+    global: Buckets,
+    /// The limits of the peers that don't have their own.
+    default_peer_limit: Limit,
+    /// The limits of specific peers.
+    peer_limits: HashMap<PeerId, Limit>,
+    /// The limits of each protocol, applied separately to each peer.
+    protocol_limits: HashMap<Vec<u8>, Limit>,
+    /// The buckets of the connected peers, and of the peers with substreams still open.
+    peers: HashMap<PeerId, PeerBuckets>,
+}
+
+#[derive(Debug, Default)]
+struct PeerBuckets {
+    /// Number of open connections to the peer.
+    connections: usize,
+    /// Number of substreams with the peer, which may outlive their connection.
+    substreams: usize,
+    /// The buckets shared by all the substreams with the peer.
+    buckets: Buckets,
+    /// The buckets of each limited protocol.
+    protocols: HashMap<Vec<u8>, Buckets>,
+}
+
+impl RateLimiter {
+    /// Creates a rate limiter that doesn't limit anything until limits are set.
+    pub fn new() -> Self {
+        RateLimiter::default()
+    }
+
+    /// Sets the limits shared by all the peers and protocols.
+    pub fn set_global_limit(&self, limit: Limit) {
+        let mut inner = self.inner.lock();
+        inner.global.configure(&limit, clock::now());
+    }
+
+    /// Sets the limits of each peer that doesn't have limits of its own.
+    pub fn set_default_peer_limit(&self, limit: Limit) {
+        let mut inner = self.inner.lock();
+        let inner = &mut *inner;
+        let now = clock::now();
+        inner.default_peer_limit = limit;
+        for (peer_id, peer) in inner.peers.iter_mut() {
+            if !inner.peer_limits.contains_key(peer_id) {
+                peer.buckets.configure(&limit, now);
+            }
+        }
+    }
+
+    /// Sets the limits of the given peer, or makes it use the default limits again if `None`.
+    pub fn set_peer_limit(&self, peer_id: PeerId, limit: Option<Limit>) {
+        let mut inner = self.inner.lock();
+        let effective = limit.unwrap_or(inner.default_peer_limit);
+        if let Some(peer) = inner.peers.get_mut(&peer_id) {
+            peer.buckets.configure(&effective, clock::now());
+        }
+        match limit {
+            Some(limit) => inner.peer_limits.insert(peer_id, limit),
+            None => inner.peer_limits.remove(&peer_id),
+        };
+    }
+
+    /// Sets the limits of the given protocol, applied separately to each peer, or removes them
+    /// if `None`.
+    pub fn set_protocol_limit(&self, protocol: impl Into<Vec<u8>>, limit: Option<Limit>) {
+        let protocol = protocol.into();
+        let mut inner = self.inner.lock();
+        let now = clock::now();
+        for peer in inner.peers.values_mut() {
+            match limit {
+                Some(limit) => if let Some(buckets) = peer.protocols.get_mut(&protocol) {
+                    buckets.configure(&limit, now)
+                },
+                None => { peer.protocols.remove(&protocol); }
+            }
+        }
+        match limit {
+            Some(limit) => inner.protocol_limits.insert(protocol, limit),
+            None => inner.protocol_limits.remove(&protocol),
+        };
+    }
+}
+
+impl Inner {
+    /// Returns the buckets of the given peer, and of the given protocol with that peer if it is
+    /// limited, creating them if necessary.
+    fn buckets_mut(&mut self, peer_id: &PeerId, protocol: Option<&[u8]>, now: Instant)
+        -> (&mut Buckets, &mut Buckets, Option<&mut Buckets>)
+    {
+        let limit = self.peer_limits.get(peer_id).unwrap_or(&self.default_peer_limit);
+        let peer = self.peers.entry(peer_id.clone()).or_insert_with(|| PeerBuckets {
+            connections: 0,
+            substreams: 0,
+            buckets: Buckets::new(limit, now),
+            protocols: HashMap::new(),
+        });
+        let protocol_limits = &self.protocol_limits;
+        let protocol = match protocol.and_then(|p| Some((p, protocol_limits.get(p)?))) {
+            Some((p, limit)) => Some(peer.protocols.entry(p.to_vec()).or_insert_with(|| Buckets::new(limit, now))),
+            None => None
+        };
+        (&mut self.global, &mut peer.buckets, protocol)
+    }
+
+    /// Forgets the buckets of the given peer once it has neither connections nor substreams.
+    fn release(&mut self, peer_id: &PeerId) {
+        if self.peers.get(peer_id).map_or(false, |p| p.connections == 0 && p.substreams == 0) {
+            self.peers.remove(peer_id);
+        }
+    }
+
+    /// Computes the number of bytes that a substream may transfer, or how long to wait until it
+    /// can transfer anything.
+    fn quota(&mut self, substream: &SubstreamState, peer_id: &PeerId, kind: Kind, len: usize, now: Instant)
+        -> Result<usize, Duration>
+    {
+        let (global, peer, protocol) = self.buckets_mut(peer_id, substream.protocol.as_ref().map(|p| &p[..]), now);
+        let mut levels = [Some(global), Some(peer), protocol];
+        let mut quota = len as f64;
+        let mut wait = Duration::from_secs(0);
+        for buckets in levels.iter_mut().filter_map(|b| b.as_mut()) {
+            if !substream.admitted {
+                if let Some(bucket) = &mut buckets.inbound_substreams {
+                    if let Err(w) = bucket.available(0.0, now) {
+                        wait = cmp::max(wait, w);
+                    }
+                }
+            }
+            if let Some(bucket) = buckets.bucket_mut(kind) {
+                match bucket.available(1.0, now) {
+                    Ok(tokens) => quota = quota.min(tokens),
+                    Err(w) => wait = cmp::max(wait, w),
+                }
+            }
+        }
+        if wait > Duration::from_secs(0) {
+            Err(wait)
+        } else {
+            Ok(cmp::max(quota as usize, 1))
+        }
+    }
+
+    /// Takes `amount` tokens from the buckets of the given substream.
+    fn take(&mut self, peer_id: &PeerId, protocol: Option<&[u8]>, kind: Kind, amount: usize, now: Instant) {
+        let (global, peer, protocol) = self.buckets_mut(peer_id, protocol, now);
+        for buckets in [Some(global), Some(peer), protocol].iter_mut().filter_map(|b| b.as_mut()) {
+            if let Some(bucket) = buckets.bucket_mut(kind) {
+                bucket.take(amount as f64, now);
+            }
+        }
+    }
+}
+
+impl SwarmObserver for RateLimiter {
+    fn connection_established(&self, peer_id: &PeerId, _: &ConnectedPoint) {
+        let mut inner = self.inner.lock();
+        inner.buckets_mut(peer_id, None, clock::now());
+        if let Some(peer) = inner.peers.get_mut(peer_id) {
+            peer.connections += 1;
+        }
+    }
+
+    fn connection_closed(&self, peer_id: &PeerId, _: &ConnectedPoint) {
+        let mut inner = self.inner.lock();
+        if let Some(peer) = inner.peers.get_mut(peer_id) {
+            peer.connections = peer.connections.saturating_sub(1);
+        }
+        inner.release(peer_id);
+    }
+
+    fn substream_meter(&self, peer_id: &PeerId, endpoint: Endpoint) -> Option<Arc<dyn SubstreamMeter>> {
+        let mut inner = self.inner.lock();
+        inner.buckets_mut(peer_id, None, clock::now());
+        if let Some(peer) = inner.peers.get_mut(peer_id) {
+            peer.substreams += 1;
+        }
+        Some(Arc::new(Throttle {
+            inner: self.inner.clone(),
+            peer_id: peer_id.clone(),
+            endpoint,
+            state: Mutex::new(SubstreamState { protocol: None, admitted: true, delay: None }),
+        }))
+    }
+}
+
+/// The kind of tokens of a bucket.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Kind {
+    Inbound,
+    Outbound,
+    InboundSubstreams,
+}
+
+/// Meter that throttles a substream.
+struct Throttle {
+    inner: Arc<Mutex<Inner>>,
+    peer_id: PeerId,
+    endpoint: Endpoint,
+    state: Mutex<SubstreamState>,
+}
+
+struct SubstreamState {
+    /// The negotiated protocol, once known.
+    protocol: Option<Vec<u8>>,
+    /// Whether the substream has been allowed by the limits on the number of inbound substreams.
+    admitted: bool,
+    /// Notifies the task once the substream can transfer data again.
+    delay: Option<Delay>,
+}
+
+impl Throttle {
+    fn poll_quota(&self, kind: Kind, len: usize) -> Async<usize> {
+        let mut state = self.state.lock();
+        loop {
+            let now = clock::now();
+            let wait = match self.inner.lock().quota(&state, &self.peer_id, kind, len, now) {
+                Ok(quota) => {
+                    state.admitted = true;
+                    state.delay = None;
+                    return Async::Ready(quota)
+                }
+                Err(wait) => wait
+            };
+            let mut delay = Delay::new(now + wait);
+            match delay.poll() {
+                Ok(Async::NotReady) => {
+                    state.delay = Some(delay);
+                    return Async::NotReady
+                }
+                Ok(Async::Ready(())) => continue,
+                Err(err) => {
+                    // Without a timer, we couldn't be woken up again. Better not to limit.
+                    warn!("Rate limiter timer error: {}", err);
+                    return Async::Ready(len)
+                }
+            }
+        }
+    }
+}
+
+impl Drop for Throttle {
+    fn drop(&mut self) {
+        let mut inner = self.inner.lock();
+        if let Some(peer) = inner.peers.get_mut(&self.peer_id) {
+            peer.substreams = peer.substreams.saturating_sub(1);
+        }
+        inner.release(&self.peer_id);
+    }
+}
+
+impl SubstreamMeter for Throttle {
+    fn on_negotiated(&self, protocol: &[u8]) {
+        let mut state = self.state.lock();
+        state.protocol = Some(protocol.to_vec());
+        if self.endpoint == Endpoint::Listener {
+            self.inner.lock().take(&self.peer_id, Some(protocol), Kind::InboundSubstreams, 1, clock::now());
+            state.admitted = false;
+        }
+    }
+
+    fn poll_read_quota(&self, len: usize) -> Async<usize> {
+        self.poll_quota(Kind::Inbound, len)
+    }
+
+    fn poll_write_quota(&self, len: usize) -> Async<usize> {
+        self.poll_quota(Kind::Outbound, len)
+    }
+
+    fn on_read(&self, bytes: usize) {
+        let state = self.state.lock();
+        self.inner.lock().take(&self.peer_id, state.protocol.as_ref().map(|p| &p[..]), Kind::Inbound, bytes, clock::now());
+    }
+
+    fn on_write(&self, bytes: usize) {
+        let state = self.state.lock();
+        self.inner.lock().take(&self.peer_id, state.protocol.as_ref().map(|p| &p[..]), Kind::Outbound, bytes, clock::now());
+    }
+}
+
+/// The buckets of a level of the hierarchy.
+#[derive(Debug, Default)]
+struct Buckets {
+    inbound: Option<Bucket>,
+    outbound: Option<Bucket>,
+    inbound_substreams: Option<Bucket>,
+}
+
+impl Buckets {
+    fn new(limit: &Limit, now: Instant) -> Self {
+        let mut buckets = Buckets::default();
+        buckets.configure(limit, now);
+        buckets
+    }
+
+    /// Applies new limits, keeping the tokens of the buckets that remain limited.
+    fn configure(&mut self, limit: &Limit, now: Instant) {
+        fn apply(bucket: &mut Option<Bucket>, rate: Option<Rate>, now: Instant) {
+            *bucket = match (bucket.take(), rate) {
+                (Some(mut bucket), Some(rate)) => { bucket.set_rate(rate, now); Some(bucket) }
+                (None, Some(rate)) => Some(Bucket::new(rate, now)),
+                (_, None) => None,
+            };
+        }
+        apply(&mut self.inbound, limit.inbound, now);
+        apply(&mut self.outbound, limit.outbound, now);
+        apply(&mut self.inbound_substreams, limit.inbound_substreams, now);
+    }
+
+    fn bucket_mut(&mut self, kind: Kind) -> Option<&mut Bucket> {
+        match kind {
+            Kind::Inbound => self.inbound.as_mut(),
+            Kind::Outbound => self.outbound.as_mut(),
+            Kind::InboundSubstreams => self.inbound_substreams.as_mut(),
+        }
+    }
+}
+
+/// A token bucket.
+///
+/// The tokens can go below zero, in which case the debt has to be paid back before the bucket
+/// allows anything again. This lets transfers proceed without reserving tokens beforehand, while
+/// still enforcing the rate over time.
+#[derive(Debug)]
+struct Bucket {
+    rate: Rate,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn new(rate: Rate, now: Instant) -> Self {
+        Bucket { rate, tokens: f64::from(rate.burst), updated: now }
+    }
+
+    fn set_rate(&mut self, rate: Rate, now: Instant) {
+        self.refill(now);
+        self.rate = rate;
+        self.tokens = self.tokens.min(f64::from(rate.burst));
+    }
+
+    fn refill(&mut self, now: Instant) {
+        if now > self.updated {
+            let elapsed = now - self.updated;
+            let elapsed = elapsed.as_secs() as f64 + f64::from(elapsed.subsec_nanos()) / 1e9;
+            self.tokens = (self.tokens + elapsed * f64::from(self.rate.per_second))
+                .min(f64::from(self.rate.burst));
+            self.updated = now;
+        }
+    }
+
+    /// Returns the available tokens if there are at least `min`, or how long to wait until there
+    /// are.
+    fn available(&mut self, min: f64, now: Instant) -> Result<f64, Duration> {
+        self.refill(now);
+        if self.tokens >= min {
+            Ok(self.tokens)
+        } else {
+            let secs = (min - self.tokens) / f64::from(self.rate.per_second);
+            Err(cmp::max(Duration::from_nanos((secs * 1e9).ceil() as u64), Duration::from_millis(1)))
+        }
+    }
+
+    fn take(&mut self, amount: f64, now: Instant) {
+        self.refill(now);
+        self.tokens -= amount;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bucket_refills_and_tracks_debt() {
+        let start = Instant::now();
+        let mut bucket = Bucket::new(Rate::new(100).with_burst(50), start);
+        assert_eq!(bucket.available(1.0, start), Ok(50.0));
+        bucket.take(149.0, start);
+        assert_eq!(bucket.available(1.0, start), Err(Duration::from_secs(1)));
+        let later = start + Duration::from_secs(1);
+        assert!(bucket.available(1.0, later).is_ok());
+        // Never more than the burst.
+        assert_eq!(bucket.available(1.0, later + Duration::from_secs(10)), Ok(50.0));
+    }
+
+    #[test]
+    fn hierarchy() {
+        let now = Instant::now();
+        let limiter = RateLimiter::new();
+        limiter.set_global_limit(Limit::unlimited().with_inbound_bytes(Rate::new(1000)));
+        limiter.set_default_peer_limit(Limit::unlimited().with_inbound_bytes(Rate::new(500)));
+        limiter.set_protocol_limit("/kad", Some(Limit::unlimited().with_inbound_bytes(Rate::new(100))));
+        let mut inner = limiter.inner.lock();
+        let peer = PeerId::random();
+        let kad = SubstreamState { protocol: Some(b"/kad".to_vec()), admitted: true, delay: None };
+        let ping = SubstreamState { protocol: Some(b"/ping".to_vec()), admitted: true, delay: None };
+
+        assert_eq!(inner.quota(&kad, &peer, Kind::Inbound, 4096, now), Ok(100));
+        assert_eq!(inner.quota(&ping, &peer, Kind::Inbound, 4096, now), Ok(500));
+        assert_eq!(inner.quota(&ping, &peer, Kind::Outbound, 4096, now), Ok(4096));
+
+        inner.take(&peer, Some(b"/kad"), Kind::Inbound, 100, now);
+        assert!(inner.quota(&kad, &peer, Kind::Inbound, 4096, now).is_err());
+        assert_eq!(inner.quota(&ping, &peer, Kind::Inbound, 4096, now), Ok(400));
+
+        // The global limit is shared with the other peers.
+        let other = PeerId::random();
+        assert_eq!(inner.quota(&ping, &other, Kind::Inbound, 4096, now), Ok(500));
+        inner.take(&other, Some(b"/ping"), Kind::Inbound, 500, now);
+        assert_eq!(inner.quota(&ping, &peer, Kind::Inbound, 4096, now), Ok(400));
+        inner.take(&peer, Some(b"/ping"), Kind::Inbound, 400, now);
+        assert!(inner.quota(&ping, &PeerId::random(), Kind::Inbound, 4096, now).is_err());
+    }
+
+    #[test]
+    fn inbound_substreams() {
+        let now = Instant::now();
+        let limiter = RateLimiter::new();
+        limiter.set_protocol_limit("/kad", Some(Limit::unlimited().with_inbound_substreams(Rate::new(2))));
+        let mut inner = limiter.inner.lock();
+        let peer = PeerId::random();
+        let request = SubstreamState { protocol: Some(b"/kad".to_vec()), admitted: false, delay: None };
+
+        for _ in 0..2 {
+            inner.take(&peer, Some(b"/kad"), Kind::InboundSubstreams, 1, now);
+            assert_eq!(inner.quota(&request, &peer, Kind::Inbound, 10, now), Ok(10));
+        }
+        inner.take(&peer, Some(b"/kad"), Kind::InboundSubstreams, 1, now);
+        assert_eq!(inner.quota(&request, &peer, Kind::Inbound, 10, now), Err(Duration::from_millis(500)));
+        assert_eq!(inner.quota(&request, &peer, Kind::Inbound, 10, now + Duration::from_millis(500)), Ok(10));
+
+        // Other peers aren't affected.
+        inner.take(&PeerId::random(), Some(b"/kad"), Kind::InboundSubstreams, 1, now);
+        assert!(inner.quota(&request, &PeerId::random(), Kind::Inbound, 10, now).is_ok());
+    }
+
+    #[test]
+    fn limits_can_be_changed() {
+        let limiter = RateLimiter::new();
+        let peer = PeerId::random();
+        let state = SubstreamState { protocol: None, admitted: true, delay: None };
+        limiter.set_peer_limit(peer.clone(), Some(Limit::unlimited().with_outbound_bytes(Rate::new(10))));
+        let now = Instant::now();
+        assert_eq!(limiter.inner.lock().quota(&state, &peer, Kind::Outbound, 100, now), Ok(10));
+        limiter.set_peer_limit(peer.clone(), Some(Limit::unlimited().with_outbound_bytes(Rate::new(5))));
+        assert_eq!(limiter.inner.lock().quota(&state, &peer, Kind::Outbound, 100, now), Ok(5));
+        limiter.set_peer_limit(peer.clone(), None);
+        assert_eq!(limiter.inner.lock().quota(&state, &peer, Kind::Outbound, 100, now), Ok(100));
+    }
+
+    #[test]
+    fn buckets_are_forgotten_with_the_last_connection_and_substream() {
+        let limiter = RateLimiter::new();
+        let peer = PeerId::random();
+        let endpoint = ConnectedPoint::Dialer { address: libp2p_core::Multiaddr::empty(), role_override: libp2p_core::Endpoint::Dialer };
+
+        limiter.connection_established(&peer, &endpoint);
+        let throttle = limiter.substream_meter(&peer, Endpoint::Dialer).unwrap();
+        limiter.connection_closed(&peer, &endpoint);
+
+        // The substream outlives its connection, and is still limited as before.
+        throttle.on_write(10);
+        assert!(limiter.inner.lock().peers.contains_key(&peer));
+        drop(throttle);
+        assert!(limiter.inner.lock().peers.is_empty());
+
+        // The same goes for the substreams of a connection that is never reported.
+        let throttle = limiter.substream_meter(&peer, Endpoint::Listener).unwrap();
+        throttle.on_read(10);
+        drop(throttle);
+        assert!(limiter.inner.lock().peers.is_empty());
+    }
+}
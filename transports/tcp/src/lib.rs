@@ -46,22 +46,25 @@ use futures::{
 use get_if_addrs::{IfAddr, get_if_addrs};
 use ipnet::{IpNet, Ipv4Net, Ipv6Net};
 use libp2p_core::{
-    Transport,
+    compat::Transport01,
     multiaddr::{Protocol, Multiaddr},
     transport::{ListenerEvent, TransportError}
 };
 use log::{debug, trace};
+use net2::TcpBuilder;
 use std::{
-    collections::VecDeque,
+    collections::{HashSet, VecDeque},
     fmt,
     io::{self, Read, Write},
     iter::{self, FromIterator},
     net::{IpAddr, SocketAddr},
+    sync::{Arc, Mutex},
     time::Duration,
     vec::IntoIter
 };
 use tk_listen::{ListenExt, SleepOnError};
 use tokio_io::{AsyncRead, AsyncWrite};
+use tokio_reactor::Handle;
 use tokio_tcp::{ConnectFuture, Incoming, TcpStream};
 
 /// Represents the configuration for a TCP/IP transport capability for libp2p.
@@ -82,6 +85,26 @@ pub struct TcpConfig {
     keepalive: Option<Option<Duration>>,
     /// `TCP_NODELAY` to set for opened sockets, or `None` to keep default.
     nodelay: Option<bool>,
+    /// The addresses of the listeners, if dialing from the listen port is enabled.
+    port_reuse: Option<PortReuse>,
+}
+
+/// The addresses of the listeners of a `TcpConfig` and of its clones, to dial from.
+#[derive(Debug, Clone, Default)]
+struct PortReuse {
+    listen_addrs: Arc<Mutex<HashSet<SocketAddr>>>,
+}
+
+impl PortReuse {
+    /// Returns the local address to bind to in order to dial `remote`, if we listen on an
+    /// address of the same family.
+    fn local_dial_addr(&self, remote: &IpAddr) -> Option<SocketAddr> {
+        let listen_addrs = self.listen_addrs.lock().expect("the lock is never poisoned");
+        listen_addrs.iter()
+            .find(|addr| addr.is_ipv4() == remote.is_ipv4()
+                && (addr.ip().is_unspecified() || addr.ip().is_loopback() == remote.is_loopback()))
+            .cloned()
+    }
 }
 
 impl TcpConfig {
@@ -94,9 +117,21 @@ impl TcpConfig {
             ttl: None,
             keepalive: None,
             nodelay: None,
+            port_reuse: None,
         }
     }
 
+    /// Sets whether outgoing connections are dialed from the port we listen on.
+    ///
+    /// When enabled, the sockets are created with `SO_REUSEADDR` and `SO_REUSEPORT`, and the
+    /// dialing sockets are bound to the address of a listener of the same IP family. The remote
+    /// then observes our listen port as the source port of our connections, which is necessary
+    /// for NAT traversal by hole punching. If no listener matches, the OS picks the port.
+    pub fn port_reuse(mut self, value: bool) -> Self {
+        self.port_reuse = if value { Some(PortReuse::default()) } else { None };
+        self
+    }
+
     /// Sets the size of the recv buffer size to set for opened sockets.
     pub fn recv_buffer_size(mut self, value: usize) -> Self {
         self.recv_buffer_size = Some(value);
@@ -128,7 +163,7 @@ impl TcpConfig {
     }
 }
 
-impl Transport for TcpConfig {
+impl Transport01 for TcpConfig {
     type Output = TcpTransStream;
     type Error = io::Error;
     type Listener = TcpListener;
@@ -143,10 +178,19 @@ impl Transport for TcpConfig {
                 return Err(TransportError::MultiaddrNotSupported(addr))
             };
 
-        let listener = tokio_tcp::TcpListener::bind(&socket_addr).map_err(TransportError::Other)?;
+        let listener = match self.port_reuse {
+            Some(_) => reuse_socket(&socket_addr)
+                .and_then(|builder| builder.bind(socket_addr)?.listen(1024))
+                .and_then(|listener| tokio_tcp::TcpListener::from_std(listener, &Handle::default())),
+            None => tokio_tcp::TcpListener::bind(&socket_addr),
+        }.map_err(TransportError::Other)?;
         let local_addr = listener.local_addr().map_err(TransportError::Other)?;
         let port = local_addr.port();
 
+        if let Some(port_reuse) = &self.port_reuse {
+            port_reuse.listen_addrs.lock().expect("the lock is never poisoned").insert(local_addr);
+        }
+
         // Determine all our listen addresses which is either a single local IP address
         // or (if a wildcard IP address was used) the addresses of all our interfaces,
         // as reported by `get_if_addrs`.
@@ -179,6 +223,7 @@ impl Transport for TcpConfig {
 
         let stream = TcpListenStream {
             inner: Ok(listener.incoming().sleep_on_error(self.sleep_on_error)),
+            local_addr,
             port,
             addrs,
             pending: VecDeque::new(),
@@ -207,8 +252,24 @@ impl Transport for TcpConfig {
 
         debug!("Dialing {}", addr);
 
+        let local_addr = self.port_reuse.as_ref().and_then(|p| p.local_dial_addr(&socket_addr.ip()));
+        let inner = match local_addr {
+            Some(local_addr) => {
+                let stream = reuse_socket(&socket_addr)
+                    .and_then(|builder| builder.bind(local_addr)?.to_tcp_stream());
+                match stream {
+                    Ok(stream) => TcpStream::connect_std(stream, &socket_addr, &Handle::default()),
+                    Err(err) => {
+                        debug!("Failed to bind to {} for dialing, using any port: {:?}", local_addr, err);
+                        TcpStream::connect(&socket_addr)
+                    }
+                }
+            }
+            None => TcpStream::connect(&socket_addr),
+        };
+
         let future = TcpDialFut {
-            inner: TcpStream::connect(&socket_addr),
+            inner,
             config: self
         };
 
@@ -216,6 +277,15 @@ impl Transport for TcpConfig {
     }
 }
 
+/// Creates a socket of the family of `addr` that can share its port with other sockets.
+fn reuse_socket(addr: &SocketAddr) -> io::Result<TcpBuilder> {
+    let builder = if addr.is_ipv4() { TcpBuilder::new_v4()? } else { TcpBuilder::new_v6()? };
+    builder.reuse_address(true)?;
+    #[cfg(unix)]
+    net2::unix::UnixTcpBuilderExt::reuse_port(&builder, true)?;
+    Ok(builder)
+}
+
 // This type of logic should probably be moved into the multiaddr package
 fn multiaddr_to_socketaddr(addr: &Multiaddr) -> Result<SocketAddr, ()> {
     let mut iter = addr.iter();
@@ -357,6 +427,8 @@ type Buffer = VecDeque<ListenerEvent<FutureResult<TcpTransStream, io::Error>>>;
 pub struct TcpListenStream {
     /// Stream of incoming sockets.
     inner: Result<SleepOnError<Incoming>, Option<io::Error>>,
+    /// The address the socket is bound to.
+    local_addr: SocketAddr,
     /// The port which we use as our listen port in listener event addresses.
     port: u16,
     /// The set of known addresses.
@@ -485,6 +557,14 @@ impl Stream for TcpListenStream {
     }
 }
 
+impl Drop for TcpListenStream {
+    fn drop(&mut self) {
+        if let Some(port_reuse) = &self.config.port_reuse {
+            port_reuse.listen_addrs.lock().expect("the lock is never poisoned").remove(&self.local_addr);
+        }
+    }
+}
+
 impl fmt::Debug for TcpListenStream {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self.inner {
@@ -546,7 +626,7 @@ impl Drop for TcpTransStream {
 #[cfg(test)]
 mod tests {
     use futures::prelude::*;
-    use libp2p_core::{Transport, multiaddr::{Multiaddr, Protocol}, transport::ListenerEvent};
+    use libp2p_core::{compat::Transport01, multiaddr::{Multiaddr, Protocol}, transport::ListenerEvent};
     use std::net::{IpAddr, Ipv4Addr, SocketAddr};
     use super::{multiaddr_to_socketaddr, TcpConfig};
     use tokio::runtime::current_thread::Runtime;
@@ -686,6 +766,31 @@ mod tests {
         let _ = rt.block_on(action).unwrap();
     }
 
+    #[test]
+    fn port_reuse_dials_from_listen_port() {
+        let mut rt = Runtime::new().unwrap();
+        let tcp = TcpConfig::new().port_reuse(true);
+
+        let listener = tcp.clone().listen_on("/ip4/127.0.0.1/tcp/0".parse().unwrap()).unwrap();
+        let (event, _listener) = rt.block_on(listener.into_future()).map_err(|(err, _)| err).unwrap();
+        let listen_addr = event.unwrap().into_new_address().unwrap();
+
+        let remote = TcpConfig::new().listen_on("/ip4/127.0.0.1/tcp/0".parse().unwrap()).unwrap();
+        let (event, remote) = rt.block_on(remote.into_future()).map_err(|(err, _)| err).unwrap();
+        let remote_listen_addr = event.unwrap().into_new_address().unwrap();
+
+        let incoming = remote
+            .filter_map(|event| match event {
+                ListenerEvent::Upgrade { remote_addr, .. } => Some(remote_addr),
+                _ => None
+            })
+            .into_future()
+            .map_err(|(err, _)| err);
+        let dial = tcp.dial(remote_listen_addr).unwrap();
+        let ((observed, _), _stream) = rt.block_on(incoming.join(dial)).unwrap();
+        assert_eq!(observed, Some(listen_addr));
+    }
+
     #[test]
     fn replace_port_0_in_returned_multiaddr_ipv4() {
         let tcp = TcpConfig::new();
@@ -51,7 +51,7 @@ use futures::stream::Stream;
 use log::debug;
 use std::{io, path::PathBuf};
 use libp2p_core::{
-    Transport,
+    compat::Transport01,
     multiaddr::{Protocol, Multiaddr},
     transport::{ListenerEvent, TransportError}
 };
@@ -73,7 +73,7 @@ impl UdsConfig {
     }
 }
 
-impl Transport for UdsConfig {
+impl Transport01 for UdsConfig {
     type Output = UnixStream;
     type Error = io::Error;
     type Listener = ListenerStream<tokio_uds::Incoming>;
@@ -176,7 +176,7 @@ mod tests {
     use futures::prelude::*;
     use std::{self, borrow::Cow, path::Path};
     use libp2p_core::{
-        Transport,
+        compat::Transport01,
         multiaddr::{Protocol, Multiaddr},
         transport::ListenerEvent
     };
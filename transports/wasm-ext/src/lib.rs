@@ -33,7 +33,7 @@
 //!
 
 use futures::{future::FutureResult, prelude::*, stream::Stream, try_ready};
-use libp2p_core::{transport::ListenerEvent, transport::TransportError, Multiaddr, Transport};
+use libp2p_core::{compat::Transport01, transport::ListenerEvent, transport::TransportError, Multiaddr};
 use parity_send_wrapper::SendWrapper;
 use std::{collections::VecDeque, error, fmt, io, mem};
 use wasm_bindgen::{JsCast, prelude::*};
@@ -152,7 +152,7 @@ impl Clone for ExtTransport {
     }
 }
 
-impl Transport for ExtTransport {
+impl Transport01 for ExtTransport {
     type Output = Connection;
     type Error = JsErr;
     type Listener = Listen;
@@ -22,7 +22,9 @@ use bytes::BytesMut;
 use crate::{error::Error, tls};
 use futures::{future::{self, Either, Loop}, prelude::*, try_ready};
 use libp2p_core::{
+    Endpoint,
     Transport,
+    compat::{Compat03As01, Transport01},
     either::EitherOutput,
     multiaddr::{Protocol, Multiaddr},
     transport::{ListenerEvent, TransportError}
@@ -103,7 +105,7 @@ impl<T> WsConfig<T> {
     }
 }
 
-impl<T> Transport for WsConfig<T>
+impl<T> Transport01 for WsConfig<T>
 where
     T: Transport + Send + Clone + 'static,
     T::Error: Send + 'static,
@@ -139,7 +141,7 @@ where
         let tls_config = self.tls_config;
         let max_size = self.max_data_size;
         let use_deflate = self.use_deflate;
-        let listen = self.transport.listen_on(inner_addr)
+        let listen = Transport01::listen_on(Compat03As01::new(self.transport), inner_addr)
             .map_err(|e| e.map(Error::Transport))?
             .map_err(Error::Transport)
             .map(move |event| match event {
@@ -215,6 +217,28 @@ where
     }
 
     fn dial(self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
+        self.dial_with_role(addr, Endpoint::Dialer)
+    }
+
+    fn dial_as_listener(self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
+        self.dial_with_role(addr, Endpoint::Listener)
+    }
+}
+
+impl<T> WsConfig<T>
+where
+    T: Transport + Send + Clone + 'static,
+    T::Error: Send + 'static,
+    T::Dial: Send + 'static,
+    T::Listener: Send + 'static,
+    T::ListenerUpgrade: Send + 'static,
+    T::Output: AsyncRead + AsyncWrite + Send + 'static
+{
+    /// Dials `addr`, with the underlying transport taking the given role. The websocket
+    /// handshake itself is always performed as the client.
+    fn dial_with_role(self, addr: Multiaddr, role_override: Endpoint)
+        -> Result<<Self as Transport01>::Dial, TransportError<<Self as Transport01>::Error>>
+    {
         // Quick sanity check of the provided Multiaddr.
         if let Some(Protocol::Ws(_)) | Some(Protocol::Wss(_)) = addr.iter().last() {
             // ok
@@ -224,8 +248,8 @@ where
         }
         // We are looping here in order to follow redirects (if any):
         let max_redirects = self.max_redirects;
-        let future = future::loop_fn((addr, self, max_redirects), |(addr, cfg, remaining)| {
-            dial(addr, cfg.clone()).and_then(move |result| match result {
+        let future = future::loop_fn((addr, self, max_redirects), move |(addr, cfg, remaining)| {
+            dial(addr, cfg.clone(), role_override).and_then(move |result| match result {
                 Either::A(redirect) => {
                     if remaining == 0 {
                         debug!("too many redirects");
@@ -242,7 +266,7 @@ where
 }
 
 /// Attempty to dial the given address and perform a websocket handshake.
-fn dial<T>(address: Multiaddr, config: WsConfig<T>)
+fn dial<T>(address: Multiaddr, config: WsConfig<T>, role_override: Endpoint)
     -> impl Future<Item = Either<Redirect, BytesConnection<T::Output>>, Error = Error<T::Error>>
 where
     T: Transport,
@@ -274,7 +298,12 @@ where
         }
     };
 
-    let dial = match transport.dial(inner_addr) {
+    let transport = Compat03As01::new(transport);
+    let dial = match role_override {
+        Endpoint::Dialer => Transport01::dial(transport, inner_addr),
+        Endpoint::Listener => Transport01::dial_as_listener(transport, inner_addr),
+    };
+    let dial = match dial {
         Ok(dial) => dial,
         Err(TransportError::MultiaddrNotSupported(a)) =>
             return Either::A(future::err(Error::InvalidMultiaddr(a))),
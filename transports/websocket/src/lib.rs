@@ -102,9 +102,9 @@ where
 {
     type Output = RwStreamSink<BytesConnection<T::Output>>;
     type Error = Error<T::Error>;
-    type Listener = MapStream<InnerStream<T::Output, T::Error>, WrapperFn<T::Output>>;
-    type ListenerUpgrade = MapFuture<InnerFuture<T::Output, T::Error>, WrapperFn<T::Output>>;
-    type Dial = MapFuture<InnerFuture<T::Output, T::Error>, WrapperFn<T::Output>>;
+    type Listener = MapStream<<framed::WsConfig<T> as Transport>::Listener, WrapperFn<T::Output>>;
+    type ListenerUpgrade = MapFuture<<framed::WsConfig<T> as Transport>::ListenerUpgrade, WrapperFn<T::Output>>;
+    type Dial = MapFuture<<framed::WsConfig<T> as Transport>::Dial, WrapperFn<T::Output>>;
 
     fn listen_on(self, addr: Multiaddr) -> Result<Self::Listener, TransportError<Self::Error>> {
         self.transport.map(wrap_connection as WrapperFn<T::Output>).listen_on(addr)
@@ -113,6 +113,10 @@ where
     fn dial(self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
         self.transport.map(wrap_connection as WrapperFn<T::Output>).dial(addr)
     }
+
+    fn dial_as_listener(self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
+        self.transport.map(wrap_connection as WrapperFn<T::Output>).dial_as_listener(addr)
+    }
 }
 
 /// Type alias corresponding to `framed::WsConfig::Listener`.
@@ -144,15 +148,39 @@ mod tests {
     use tokio::runtime::current_thread::Runtime;
     use futures::{Future, Stream};
     use libp2p_core::{
-        Transport,
-        multiaddr::Protocol,
-        transport::ListenerEvent
+        compat::{Compat03As01, Transport01},
+        multiaddr::{Multiaddr, Protocol},
+        transport::{ListenerEvent, TransportError}
     };
     use super::WsConfig;
 
+    /// TCP transport that can only dial in the role of the listener.
+    #[derive(Debug, Clone)]
+    struct ListenerRoleOnly(tcp::TcpConfig);
+
+    impl Transport01 for ListenerRoleOnly {
+        type Output = <tcp::TcpConfig as Transport01>::Output;
+        type Error = <tcp::TcpConfig as Transport01>::Error;
+        type Listener = <tcp::TcpConfig as Transport01>::Listener;
+        type ListenerUpgrade = <tcp::TcpConfig as Transport01>::ListenerUpgrade;
+        type Dial = <tcp::TcpConfig as Transport01>::Dial;
+
+        fn listen_on(self, addr: Multiaddr) -> Result<Self::Listener, TransportError<Self::Error>> {
+            self.0.listen_on(addr)
+        }
+
+        fn dial(self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
+            Err(TransportError::MultiaddrNotSupported(addr))
+        }
+
+        fn dial_as_listener(self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
+            self.0.dial(addr)
+        }
+    }
+
     #[test]
     fn dialer_connects_to_listener_ipv4() {
-        let ws_config = WsConfig::new(tcp::TcpConfig::new());
+        let ws_config = Compat03As01::new(WsConfig::new(tcp::TcpConfig::new()));
 
         let mut listener = ws_config.clone()
             .listen_on("/ip4/127.0.0.1/tcp/0/ws".parse().unwrap())
@@ -186,7 +214,7 @@ mod tests {
 
     #[test]
     fn dialer_connects_to_listener_ipv6() {
-        let ws_config = WsConfig::new(tcp::TcpConfig::new());
+        let ws_config = Compat03As01::new(WsConfig::new(tcp::TcpConfig::new()));
 
         let mut listener = ws_config.clone()
             .listen_on("/ip6/::1/tcp/0/ws".parse().unwrap())
@@ -218,4 +246,33 @@ mod tests {
         let mut rt = Runtime::new().unwrap();
         let _ = rt.block_on(future).unwrap();
     }
+
+    #[test]
+    fn dial_as_listener_is_forwarded() {
+        let ws_config = Compat03As01::new(WsConfig::new(ListenerRoleOnly(tcp::TcpConfig::new())));
+
+        let mut listener = ws_config.clone()
+            .listen_on("/ip4/127.0.0.1/tcp/0/ws".parse().unwrap())
+            .unwrap();
+
+        let addr = listener.by_ref().wait()
+            .next()
+            .expect("some event")
+            .expect("no error")
+            .into_new_address()
+            .expect("listen address");
+
+        let listener = listener
+            .filter_map(ListenerEvent::into_upgrade)
+            .into_future()
+            .map_err(|(e, _)| e)
+            .and_then(|(c, _)| c.unwrap().0);
+
+        // The connection is only established if the role reaches the TCP transport.
+        let dialer = ws_config.clone().dial_as_listener(addr.clone()).unwrap();
+
+        let future = listener.join(dialer);
+        let mut rt = Runtime::new().unwrap();
+        let _ = rt.block_on(future).unwrap();
+    }
 }